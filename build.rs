@@ -1,15 +1,217 @@
 use gl_generator::{Api, Fallbacks, GlobalGenerator, Profile, Registry};
 use std::env;
 use std::fs::File;
+use std::io::{self, Write};
 use std::path::Path;
 
+/// Desktop GL levels selectable with the `glXY` features, which each imply the ones
+/// below; the highest enabled one is generated.
+const GL_LEVELS: &[(&str, (u8, u8))] = &[
+    ("GL46", (4, 6)),
+    ("GL45", (4, 5)),
+    ("GL43", (4, 3)),
+    ("GL41", (4, 1)),
+    ("GL33", (3, 3)),
+];
+
+/// Like [`GL_LEVELS`] for the `gles` bindings. `gles` alone generates 3.2.
+const GLES_LEVELS: &[(&str, (u8, u8))] =
+    &[("GLES32", (3, 2)), ("GLES31", (3, 1)), ("GLES30", (3, 0))];
+
+/// Extensions generated at every level. The wrappers look these up at runtime and fall
+/// back to older entry points without them, so their bindings have to exist even where
+/// the level doesn't include them.
+const PROBED_EXTENSIONS: &[&str] = &[
+    "GL_NVX_gpu_memory_info",
+    "GL_ATI_meminfo",
+    "GL_ARB_pipeline_statistics_query",
+    "GL_ARB_texture_filter_anisotropic",
+    "GL_ARB_base_instance",
+    "GL_ARB_clear_buffer_object",
+    "GL_ARB_clip_control",
+    "GL_ARB_direct_state_access",
+    "GL_ARB_draw_buffers_blend",
+    "GL_ARB_ES2_compatibility",
+    "GL_ARB_ES3_compatibility",
+    "GL_ARB_invalidate_subdata",
+    "GL_ARB_program_interface_query",
+    "GL_ARB_query_buffer_object",
+    "GL_ARB_robustness",
+    "GL_ARB_tessellation_shader",
+    "GL_ARB_texture_cube_map_array",
+    "GL_ARB_transform_feedback2",
+    "GL_KHR_debug",
+    "GL_KHR_robustness",
+];
+
+/// A group of wrapper APIs compiled only when the bindings have what they call.
+struct Capability {
+    /// Set with `cargo:rustc-cfg` when the capability is in.
+    cfg: &'static str,
+    /// The level that made it core.
+    core: (u8, u8),
+    /// The `gl-ext-*` feature, as in `CARGO_FEATURE_*`, that adds it below `core`.
+    feature: &'static str,
+    extensions: &'static [&'static str],
+}
+
+const CAPABILITIES: &[Capability] = &[
+    Capability {
+        cfg: "gl_compute",
+        core: (4, 3),
+        feature: "GL_EXT_COMPUTE",
+        extensions: &[
+            "GL_ARB_compute_shader",
+            "GL_ARB_shader_atomic_counters",
+            "GL_ARB_shader_image_load_store",
+            "GL_ARB_shader_storage_buffer_object",
+        ],
+    },
+    Capability {
+        cfg: "gl_indirect",
+        core: (4, 3),
+        feature: "GL_EXT_INDIRECT",
+        extensions: &["GL_ARB_draw_indirect", "GL_ARB_multi_draw_indirect"],
+    },
+];
+
+/// Cfgs set when the generated level has these entry points under their core names. Below
+/// it only the extensions' suffixed names from [`PROBED_EXTENSIONS`] exist.
+const CORE_NAMES: &[(&str, (u8, u8))] = &[("gl_blend_indexed", (4, 0))];
+
+fn feature(name: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", name)).is_some()
+}
+
+/// The highest of `levels` whose feature is enabled.
+fn level(levels: &[(&str, (u8, u8))]) -> Option<(u8, u8)> {
+    levels
+        .iter()
+        .find(|(name, _)| feature(name))
+        .map(|&(_, level)| level)
+}
+
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 
     let dest = env::var("OUT_DIR").unwrap();
-    let mut file = File::create(&Path::new(&dest).join("bindings.rs")).unwrap();
+    let mut file = File::create(Path::new(&dest).join("bindings.rs")).unwrap();
+
+    // Without any level, e.g. with `default-features = false`, keep the long-standing 4.5.
+    let (major, minor) = level(GL_LEVELS).unwrap_or((4, 5));
+    let mut extensions = PROBED_EXTENSIONS.to_vec();
+    for capability in CAPABILITIES {
+        println!("cargo:rustc-check-cfg=cfg({})", capability.cfg);
+        if (major, minor) >= capability.core {
+            println!("cargo:rustc-cfg={}", capability.cfg);
+        } else if feature(capability.feature) {
+            println!("cargo:rustc-cfg={}", capability.cfg);
+            extensions.extend_from_slice(capability.extensions);
+        }
+    }
+
+    for &(cfg, core) in CORE_NAMES {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+        if (major, minor) >= core {
+            println!("cargo:rustc-cfg={}", cfg);
+        }
+    }
 
-    Registry::new(Api::Gl, (4, 5), Profile::Core, Fallbacks::All, [])
+    let registry = Registry::new(
+        Api::Gl,
+        (major, minor),
+        Profile::Core,
+        Fallbacks::All,
+        extensions,
+    );
+    if env::var_os("CARGO_FEATURE_GL_TRACE").is_some() {
+        writeln!(file, "mod raw {{").unwrap();
+        registry.write_bindings(GlobalGenerator, &mut file).unwrap();
+        writeln!(file, "}}").unwrap();
+        write_trace_shims(&registry, &mut file).unwrap();
+    } else {
+        registry.write_bindings(GlobalGenerator, &mut file).unwrap();
+    }
+
+    if env::var_os("CARGO_FEATURE_GLES").is_some() {
+        let mut file = File::create(Path::new(&dest).join("gles_bindings.rs")).unwrap();
+        Registry::new(
+            Api::Gles2,
+            level(GLES_LEVELS).unwrap_or((3, 2)),
+            Profile::Core,
+            Fallbacks::All,
+            ["GL_EXT_color_buffer_float", "GL_OES_texture_float_linear"],
+        )
         .write_bindings(GlobalGenerator, &mut file)
         .unwrap();
+    }
+}
+
+/// Writes a function for every command in `registry` that forwards to the real binding
+/// in `raw` and logs the call, its arguments and its result through `tracing` at trace
+/// level with target `gl`, and records it for `crash::recent_calls`. Everything else
+/// (types, enums, `load_with` and the per-function `is_loaded`) is re-exported from
+/// `raw` unchanged.
+fn write_trace_shims(registry: &Registry, dest: &mut impl Write) -> io::Result<()> {
+    writeln!(dest, "pub use self::raw::*;")?;
+    // Parameter types name `std::os::raw` through the generator's private module.
+    writeln!(dest, "mod __gl_imports {{ pub use std::os::raw; }}")?;
+    for cmd in &registry.cmds {
+        let name = &cmd.proto.ident;
+        let params: Vec<String> = cmd
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.ident, param.ty))
+            .collect();
+        let args: Vec<&str> = cmd
+            .params
+            .iter()
+            .map(|param| param.ident.as_str())
+            .collect();
+        let mut formats = Vec::new();
+        let mut values = Vec::new();
+        for param in &cmd.params {
+            if param.ty.contains("GLDEBUGPROC") {
+                formats.push("<callback>");
+            } else if param.ty == "types::GLenum" || param.ty == "types::GLbitfield" {
+                formats.push("{:#x}");
+                values.push(param.ident.as_str());
+            } else {
+                formats.push("{:?}");
+                values.push(param.ident.as_str());
+            }
+        }
+        let returns = cmd.proto.ty != "()";
+        if returns {
+            values.push("result");
+        }
+
+        writeln!(dest, "#[inline]")?;
+        writeln!(dest, "#[allow(non_snake_case)]")?;
+        writeln!(
+            dest,
+            "pub unsafe fn {}({}) -> {} {{",
+            name,
+            params.join(", "),
+            cmd.proto.ty
+        )?;
+        let message = format!(
+            "\"gl{}({}){}\"{}{}",
+            name,
+            formats.join(", "),
+            if returns { " -> {:?}" } else { "" },
+            if values.is_empty() { "" } else { ", " },
+            values.join(", ")
+        );
+        writeln!(dest, "    let result = raw::{}({});", name, args.join(", "))?;
+        writeln!(dest, "    tracing::trace!(target: \"gl\", {});", message)?;
+        writeln!(
+            dest,
+            "    crate::crash::record_call(format_args!({}));",
+            message
+        )?;
+        writeln!(dest, "    result")?;
+        writeln!(dest, "}}")?;
+    }
+    Ok(())
 }
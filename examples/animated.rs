@@ -0,0 +1,70 @@
+use hello_gl::animated_texture::{AnimatedTexture, Animator};
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec2};
+use hello_gl::sprite::{Sprite, SpriteBatch};
+
+/// Plays an animated GIF or APNG centered in the window, scaled to fit.
+struct Player {
+    batch: SpriteBatch,
+    texture: AnimatedTexture,
+    animator: Animator,
+    size: Vec2,
+}
+
+impl App for Player {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.size = Vec2::new(width as f32, height as f32);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.animator.update(dt);
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.15, 0.15, 0.15, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        let image = Vec2::new(self.texture.width() as f32, self.texture.height() as f32);
+        let scale = (self.size / image).min_element().min(4.0);
+        let sprite = Sprite {
+            origin: Vec2::splat(0.5),
+            ..Sprite::new(self.size * 0.5, image * scale)
+        };
+        self.batch.begin(Mat4::orthographic_rh_gl(
+            0.0,
+            self.size.x,
+            self.size.y,
+            0.0,
+            -1.0,
+            1.0,
+        ));
+        self.batch
+            .draw_animated(&self.texture, &self.animator, &sprite);
+        self.batch.end();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: animated <image.gif|image.png>");
+    app::run("Animated texture", move |_| {
+        let texture = AnimatedTexture::load(&path)?;
+        Ok(Player {
+            batch: SpriteBatch::new(1)?,
+            animator: texture.animator(),
+            texture,
+            size: Vec2::ONE,
+        })
+    });
+}
@@ -0,0 +1,144 @@
+use std::f32::consts::TAU;
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::image::Image;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::primitives;
+use hello_gl::texture::Texture;
+use hello_gl::viewport::OrbitCamera;
+
+const NORMAL_MAP_SIZE: u32 = 512;
+
+/// Rows of round bumps, repeating across the texture so the sphere's seam is invisible.
+fn bumps(u: f32, v: f32) -> f32 {
+    let (x, y) = ((u * 24.0 * TAU).sin(), (v * 12.0 * TAU).sin());
+    (x * y).max(0.0).sqrt()
+}
+
+/// Converts the slopes of [`bumps`] into a tangent-space normal map.
+fn normal_map(strength: f32) -> Image {
+    let size = NORMAL_MAP_SIZE;
+    let texel = 1.0 / size as f32;
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let (u, v) = ((x as f32 + 0.5) * texel, (y as f32 + 0.5) * texel);
+            let du = (bumps(u + texel, v) - bumps(u - texel, v)) / (2.0 * texel);
+            let dv = (bumps(u, v + texel) - bumps(u, v - texel)) / (2.0 * texel);
+            let normal = Vec3::new(-du * strength * texel, -dv * strength * texel, 1.0);
+            let encoded = normal.normalize() * 0.5 + 0.5;
+            pixels.extend(encoded.to_array().map(|c| (c * 255.0).round() as u8));
+            pixels.push(255);
+        }
+    }
+    Image {
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+/// A sphere with a procedural normal map under a circling light. Press N to toggle the
+/// normal map and +/- to change its strength. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    sphere: Mesh,
+    material: Material,
+    normal_map: Rc<Texture>,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let normal_map = Texture::from_image(&normal_map(4.0))?;
+        normal_map.label("bumps");
+        let normal_map = Rc::new(normal_map);
+        let material = Material {
+            normal_texture: Some(normal_map.clone()),
+            ..Material::pbr(Vec4::new(0.8, 0.3, 0.2, 1.0), 0.0, 0.35)
+        };
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            sphere: primitives::uv_sphere(1.5, 64, 32).mesh()?,
+            material,
+            normal_map,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 5.0),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state != ElementState::Pressed {
+                return;
+            }
+            match input.key {
+                Some(KeyCode::KeyN) => {
+                    self.material.normal_texture = match self.material.normal_texture {
+                        Some(_) => None,
+                        None => Some(self.normal_map.clone()),
+                    }
+                }
+                Some(KeyCode::Equal) => self.material.normal_scale *= 1.25,
+                Some(KeyCode::Minus) => self.material.normal_scale /= 1.25,
+                _ => {}
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Point {
+            position: Vec3::new(self.time.cos() * 4.0, 1.5, self.time.sin() * 4.0),
+            color: Vec3::ONE,
+            intensity: 40.0,
+            range: 20.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.02)).unwrap();
+        let camera = self.orbit.camera();
+        self.shaders
+            .set_camera(camera.projection(self.aspect) * camera.view(), camera.eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.07, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let program = self.shaders.bind(&self.material);
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.sphere.draw();
+    }
+}
+
+fn main() {
+    app::run("Bumpy sphere", |_| Demo::new());
+}
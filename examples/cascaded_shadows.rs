@@ -0,0 +1,151 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::shadow::{CascadedShadowMap, CASCADED_SHADOW_GLSL};
+use hello_gl::viewport::OrbitCamera;
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform mat4 u_model;
+out vec3 v_normal;
+out vec3 v_world;
+out float v_view_depth;
+void main() {
+    vec4 world = u_model * vec4(pos, 1.0);
+    vec4 view = u_view * world;
+    v_normal = mat3(u_model) * normal;
+    v_world = world.xyz;
+    v_view_depth = -view.z;
+    gl_Position = u_projection * view;
+}
+"#;
+
+const FRAG_SHADER: &str = r#"
+in vec3 v_normal;
+in vec3 v_world;
+in float v_view_depth;
+out vec4 final_color;
+uniform vec3 u_light_dir;
+uniform vec3 u_color;
+void main() {
+    vec3 n = normalize(v_normal);
+    float diffuse = max(dot(n, -normalize(u_light_dir)), 0.0);
+    float lit = cascaded_shadow_factor(v_world, v_view_depth, n, u_light_dir);
+    vec3 color = u_color * (0.15 + 0.85 * diffuse * lit);
+    final_color = vec4(color * cascade_debug_color(v_view_depth), 1.0);
+}
+"#;
+
+/// A long field of pillars lit by a low sun, shadowed by four cascades. Press C to tint
+/// the cascades. Drag to orbit and scroll to zoom.
+struct Demo {
+    program: Program,
+    shadow: CascadedShadowMap,
+    ground: Mesh,
+    pillar: Mesh,
+    light_dir: Vec3,
+    input: Input,
+    orbit: OrbitCamera,
+    width: i32,
+    height: i32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let fragment = format!(
+            "#version 330 core\n{}\n{}",
+            CASCADED_SHADOW_GLSL, FRAG_SHADER
+        );
+        let mut orbit = OrbitCamera::new(Vec3::ZERO, 12.0);
+        orbit.pitch = 0.15;
+        Ok(Demo {
+            program: Program::from_sources(VERT_SHADER, &fragment)?,
+            shadow: CascadedShadowMap::new(2048, 4)?,
+            ground: Mesh::plane(200.0)?,
+            pillar: Mesh::cube(1.0)?,
+            light_dir: Vec3::new(-0.6, -0.5, -0.4).normalize(),
+            input: Input::new(),
+            orbit,
+            width: 1,
+            height: 1,
+        })
+    }
+
+    fn draw_scene(&self, program: &Program) {
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        program.set_vec3("u_color", [0.7, 0.7, 0.7]);
+        self.ground.draw();
+
+        program.set_vec3("u_color", [0.9, 0.5, 0.2]);
+        for x in -12..=12 {
+            for z in -12..=12 {
+                let height = 1.0 + ((x * 7 + z * 13) as f32).sin().abs() * 3.0;
+                let model =
+                    Mat4::from_translation(Vec3::new(x as f32 * 6.0, height * 0.5, z as f32 * 6.0))
+                        * Mat4::from_scale(Vec3::new(0.8, height, 0.8));
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.pillar.draw();
+            }
+        }
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyC) {
+                self.shadow.cascades.debug = !self.shadow.cascades.debug;
+            }
+        }
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let camera = self.orbit.camera();
+        let aspect = self.width as f32 / self.height as f32;
+        self.shadow.update(&camera, aspect, self.light_dir);
+        for cascade in 0..self.shadow.cascade_count() {
+            self.shadow.begin(cascade);
+            self.draw_scene(self.shadow.depth_program());
+            self.shadow.end();
+        }
+
+        unsafe {
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.5, 0.6, 0.75, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view", &camera.view().to_cols_array());
+        self.program
+            .set_mat4("u_projection", &camera.projection(aspect).to_cols_array());
+        self.program
+            .set_vec3("u_light_dir", self.light_dir.to_array());
+        self.shadow.apply(&self.program, 0);
+        self.draw_scene(&self.program);
+    }
+}
+
+fn main() {
+    app::run("Cascaded shadow maps", |_| Demo::new());
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Result};
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::load_store::{self, Barriers, ImageAccess, ImageFormat};
+use hello_gl::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use hello_gl::shader::Program;
+use hello_gl::texture::Texture;
+
+const SIZE: i32 = 512;
+
+/// Writes an animated interference pattern straight into the texture.
+const COMPUTE_SHADER: &str = r#"#version 430 core
+layout (local_size_x = 8, local_size_y = 8) in;
+layout (rgba8, binding = 0) uniform writeonly image2D u_image;
+uniform float u_time;
+void main() {
+    ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+    vec2 p = (vec2(texel) / vec2(imageSize(u_image)) - 0.5) * 16.0;
+    float wave = sin(length(p - vec2(3.0 * sin(u_time), 0.0)) * 2.0 - u_time * 3.0)
+               + sin(length(p + vec2(0.0, 3.0 * cos(u_time))) * 2.0 - u_time * 2.0);
+    vec3 color = 0.5 + 0.5 * cos(wave * 1.5 + vec3(0.0, 2.1, 4.2));
+    imageStore(u_image, texel, vec4(color, 1.0));
+}
+"#;
+
+const SHOW_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_texture;
+void main() {
+    frag_color = texture(u_texture, uv);
+}
+"#;
+
+/// A compute shader writes a texture every frame through an image unit, which is then
+/// sampled to fill the window.
+struct Demo {
+    texture: Texture,
+    compute: Program,
+    show: Program,
+    triangle: FullscreenTriangle,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        if !load_store::is_supported() || !gl::DispatchCompute::is_loaded() {
+            return Err(anyhow!("This example needs GL 4.3 compute shaders"));
+        }
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.image_2d(0, gl::RGBA8, SIZE, SIZE, gl::RGBA, gl::UNSIGNED_BYTE, None);
+        texture.unbind();
+        texture.label("compute output");
+
+        Ok(Demo {
+            texture,
+            compute: Program::from_compute(COMPUTE_SHADER)?,
+            show: Program::from_sources(FULLSCREEN_VERTEX_SHADER, SHOW_SHADER)?,
+            triangle: FullscreenTriangle::new()?,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        self.texture
+            .bind_image(0, 0, None, ImageAccess::WriteOnly, ImageFormat::Rgba8)
+            .unwrap();
+        self.compute.use_program();
+        self.compute.set_float("u_time", self.time);
+        unsafe {
+            gl::DispatchCompute(SIZE as u32 / 8, SIZE as u32 / 8, 1);
+        }
+        // The fragment shader samples what the compute shader stored.
+        load_store::memory_barrier(Barriers::TEXTURE_FETCH);
+
+        self.show.use_program();
+        self.show.set_int("u_texture", 0);
+        self.texture.bind_unit(0);
+        self.triangle.draw();
+    }
+}
+
+fn main() {
+    app::run("Compute to texture", |_| Demo::new());
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::blit::Blitter;
+use hello_gl::graph::{PassContext, ResourceId};
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Quat, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::pipeline::{Frame, PassBuilder, Pipeline, RenderPass, COLOR_FORMAT};
+use hello_gl::postprocess::FullscreenTriangle;
+use hello_gl::shader::Program;
+use hello_gl::viewport::Camera;
+
+const FOG_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_color;
+uniform sampler2D u_depth;
+uniform mat4 u_inverse_projection;
+uniform vec3 u_fog_color;
+uniform float u_density;
+
+void main() {
+    vec4 clip = vec4(uv * 2.0 - 1.0, texture(u_depth, uv).r * 2.0 - 1.0, 1.0);
+    vec4 view = u_inverse_projection * clip;
+    float distance = length(view.xyz / view.w);
+    float fog = 1.0 - exp(-u_density * distance);
+    frag_color = vec4(mix(texture(u_color, uv).rgb, u_fog_color, fog), 1.0);
+}
+"#;
+
+/// Distance fog, as a downstream crate might add it: reads the scene's `"color"` and
+/// `"depth"` and replaces `"color"` for the passes after it.
+struct FogPass {
+    program: Program,
+    triangle: FullscreenTriangle,
+    inputs: Option<(ResourceId, ResourceId)>,
+}
+
+impl RenderPass for FogPass {
+    fn name(&self) -> &str {
+        "fog"
+    }
+
+    fn setup(&mut self, builder: &mut PassBuilder) -> Result<()> {
+        let color = builder.read("color")?;
+        let depth = builder.read("depth")?;
+        builder.create_frame_sized("color", COLOR_FORMAT);
+        self.inputs = Some((color, depth));
+        Ok(())
+    }
+
+    fn execute(&mut self, context: &PassContext, frame: &Frame) {
+        let Some((color, depth)) = self.inputs else {
+            return;
+        };
+        self.program.use_program();
+        context.texture(color).bind_unit(0);
+        context.texture(depth).bind_unit(1);
+        self.program.set_int("u_color", 0);
+        self.program.set_int("u_depth", 1);
+        let inverse = frame.projection.inverse();
+        self.program
+            .set_mat4("u_inverse_projection", &inverse.to_cols_array());
+        self.program.set_vec3("u_fog_color", [0.5, 0.55, 0.6]);
+        self.program.set_float("u_density", 0.15);
+        self.triangle.draw();
+    }
+}
+
+/// A row of spinning cubes fading into fog, with the fog pass inserted between the
+/// built-in scene and tonemap passes of a standard pipeline.
+struct Demo {
+    pipeline: Pipeline,
+    camera: Camera,
+    width: i32,
+    height: i32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let shaders = MaterialShaders::new()?;
+        let lights = LightBuffer::new()?;
+        let cube = Mesh::cube(1.0)?;
+        let material = Material::pbr(Vec4::new(0.9, 0.5, 0.2, 1.0), 0.0, 0.4);
+        let mut pipeline = Pipeline::standard(move |frame: &Frame| {
+            let light = Light::Directional {
+                direction: Vec3::new(-0.3, -1.0, -0.5),
+                color: Vec3::ONE,
+                intensity: 6.0,
+            };
+            lights.upload(&[light], Vec3::splat(0.1)).unwrap();
+            shaders.set_camera(frame.view_projection(), frame.eye);
+            let program = shaders.bind(&material);
+            for i in 0..12 {
+                let model = Mat4::from_rotation_translation(
+                    Quat::from_rotation_y(frame.time + i as f32),
+                    Vec3::new(0.0, 0.0, -2.5 * i as f32),
+                );
+                program.set_mat4("u_model", &model.to_cols_array());
+                cube.draw();
+            }
+        })?;
+        pipeline.insert_after(
+            "scene",
+            FogPass {
+                program: Blitter::program(FOG_SHADER)?,
+                triangle: FullscreenTriangle::new()?,
+                inputs: None,
+            },
+        )?;
+        println!("Passes: {}", pipeline.pass_names().join(" -> "));
+        Ok(Demo {
+            pipeline,
+            camera: Camera::look_at(Vec3::new(2.0, 1.5, 4.0), Vec3::new(0.0, 0.0, -6.0)),
+            width: 1,
+            height: 1,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as i32;
+        self.height = height as i32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let frame = Frame::new(self.width, self.height, &self.camera, self.time);
+        self.pipeline.render(&frame).unwrap();
+    }
+}
+
+fn main() {
+    app::run("Custom render pass", |_| Demo::new());
+}
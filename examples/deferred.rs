@@ -0,0 +1,179 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::deferred::{Deferred, PointLight, GBUFFER_OUTPUTS_GLSL};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::ssao::{Ssao, SsaoConfig, SsaoQuality};
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+uniform mat4 u_view_projection;
+uniform mat4 u_model;
+out vec3 v_normal;
+void main() {
+    v_normal = mat3(u_model) * normal;
+    gl_Position = u_view_projection * u_model * vec4(pos, 1.0);
+}
+"#;
+
+const GEOMETRY_SHADER: &str = r#"
+in vec3 v_normal;
+uniform vec3 u_color;
+void main() {
+    g_albedo = vec4(u_color, 1.0);
+    g_normal = vec4(normalize(v_normal), 0.0);
+}
+"#;
+
+const TRANSPARENT_SHADER: &str = r#"#version 330 core
+in vec3 v_normal;
+out vec4 final_color;
+uniform vec4 u_color;
+void main() {
+    final_color = u_color;
+}
+"#;
+
+const LIGHT_COUNT: usize = 128;
+
+struct Demo {
+    deferred: Deferred,
+    geometry: Program,
+    transparent: Program,
+    ground: Mesh,
+    cube: Mesh,
+    width: i32,
+    height: i32,
+    aspect: f32,
+    time: f32,
+}
+
+impl Demo {
+    fn new(width: i32, height: i32) -> Result<Demo> {
+        let geometry = format!(
+            "#version 330 core\n{}\n{}",
+            GBUFFER_OUTPUTS_GLSL, GEOMETRY_SHADER
+        );
+        let mut deferred = Deferred::new(width, height)?;
+        deferred.ambient = Vec3::splat(0.3);
+        deferred.set_ssao(Some(Ssao::new(width, height, SsaoConfig::default())?));
+        Ok(Demo {
+            deferred,
+            geometry: Program::from_sources(VERT_SHADER, &geometry)?,
+            transparent: Program::from_sources(VERT_SHADER, TRANSPARENT_SHADER)?,
+            ground: Mesh::plane(20.0)?,
+            cube: Mesh::cube(0.8)?,
+            width,
+            height,
+            aspect: width as f32 / height as f32,
+            time: 0.0,
+        })
+    }
+
+    fn lights(&self) -> Vec<PointLight> {
+        (0..LIGHT_COUNT)
+            .map(|i| {
+                let f = i as f32;
+                let angle = f * 0.618 * std::f32::consts::TAU + self.time * 0.3;
+                let distance = 1.0 + (f * 0.37) % 8.0;
+                PointLight {
+                    position: Vec3::new(angle.cos() * distance, 0.5, angle.sin() * distance),
+                    color: Vec3::new(
+                        0.5 + 0.5 * (f * 1.3).sin(),
+                        0.5 + 0.5 * (f * 2.1).sin(),
+                        0.5 + 0.5 * (f * 3.7).sin(),
+                    ),
+                    radius: 2.0,
+                }
+            })
+            .collect()
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.deferred.resize(width as i32, height as i32).unwrap();
+        self.width = width as i32;
+        self.height = height as i32;
+        self.aspect = width as f32 / height as f32;
+    }
+
+    /// 0 turns ambient occlusion off; 1 to 4 select the quality presets.
+    fn window_event(&mut self, event: &WindowEvent) {
+        let WindowEvent::KeyboardInput { input, .. } = event else {
+            return;
+        };
+        if input.state != ElementState::Pressed {
+            return;
+        }
+        let quality = match input.key {
+            Some(KeyCode::Digit0) => None,
+            Some(KeyCode::Digit1) => Some(SsaoQuality::Low),
+            Some(KeyCode::Digit2) => Some(SsaoQuality::Medium),
+            Some(KeyCode::Digit3) => Some(SsaoQuality::High),
+            Some(KeyCode::Digit4) => Some(SsaoQuality::Ultra),
+            _ => return,
+        };
+        println!("SSAO: {:?}", quality);
+        let ssao = quality
+            .map(|quality| Ssao::new(self.width, self.height, SsaoConfig::preset(quality)))
+            .transpose()
+            .unwrap();
+        self.deferred.set_ssao(ssao);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 10.0, 14.0), Vec3::ZERO, Vec3::Y);
+        let view_projection = projection * view;
+        if let Some(ssao) = self.deferred.ssao_mut() {
+            ssao.set_camera(view, projection);
+        }
+
+        self.deferred.begin_geometry();
+        self.geometry.use_program();
+        self.geometry
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+        self.geometry
+            .set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.geometry.set_vec3("u_color", [0.8, 0.8, 0.8]);
+        self.ground.draw();
+        for x in -4..=4 {
+            for z in -4..=4 {
+                let model = Mat4::from_translation(Vec3::new(x as f32 * 2.0, 0.4, z as f32 * 2.0));
+                self.geometry.set_mat4("u_model", &model.to_cols_array());
+                self.cube.draw();
+            }
+        }
+
+        self.deferred
+            .resolve(view_projection, &self.lights())
+            .unwrap();
+
+        self.deferred.begin_forward();
+        self.transparent.use_program();
+        self.transparent
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+        let model = Mat4::from_translation(Vec3::new(0.0, 2.0, 0.0))
+            * Mat4::from_rotation_y(self.time)
+            * Mat4::from_scale(Vec3::splat(3.0));
+        self.transparent.set_mat4("u_model", &model.to_cols_array());
+        self.transparent.set_vec4("u_color", [0.2, 0.6, 1.0, 0.35]);
+        self.cube.draw();
+        self.deferred.end_forward();
+    }
+}
+
+fn main() {
+    app::run("Deferred shading", |window| {
+        let size = window.inner_size();
+        Demo::new(size.width as i32, size.height as i32)
+    });
+}
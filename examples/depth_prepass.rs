@@ -0,0 +1,144 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::draw::DrawList;
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders, MAX_LIGHTS};
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::profiler::GpuTimer;
+use hello_gl::state::StateCache;
+use hello_gl::stats;
+use hello_gl::viewport::OrbitCamera;
+
+/// Rows of cubes hiding one another, shaded with PBR under a full light buffer so
+/// fragments are expensive. Press P to toggle the depth pre-pass; the GPU time and draw
+/// calls of the scene are logged once a second. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cache: StateCache,
+    timer: Option<GpuTimer>,
+    cube: Mesh,
+    materials: Vec<Material>,
+    depth_prepass: bool,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+    report: f32,
+    gpu_seconds: f64,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let materials = (0..8)
+            .map(|i| {
+                let hue = i as f32 / 8.0 * std::f32::consts::TAU;
+                let color = Vec3::new(hue.cos(), (hue + 2.1).cos(), (hue + 4.2).cos());
+                Material::pbr((color * 0.35 + 0.5).extend(1.0), 0.1, 0.3)
+            })
+            .collect();
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cache: StateCache::new(),
+            timer: GpuTimer::new().ok(),
+            cube: Mesh::cube(1.0)?,
+            materials,
+            depth_prepass: true,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 30.0),
+            aspect: 1.0,
+            report: 0.0,
+            gpu_seconds: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyP) {
+                self.depth_prepass = !self.depth_prepass;
+                tracing::info!("Depth pre-pass: {}", self.depth_prepass);
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+
+        if let Some(timer) = &mut self.timer {
+            if let Some(time) = timer.poll().last() {
+                self.gpu_seconds = time.seconds;
+            }
+        }
+        self.report += dt;
+        if self.report >= 1.0 {
+            self.report = 0.0;
+            let counters = stats::take_counters();
+            tracing::info!(
+                "Pre-pass {}: {:.2} ms on the GPU, {} draw calls",
+                if self.depth_prepass { "on" } else { "off" },
+                self.gpu_seconds * 1e3,
+                counters.draw_calls
+            );
+        }
+    }
+
+    fn render(&mut self) {
+        let lights: Vec<_> = (0..MAX_LIGHTS)
+            .map(|i| {
+                let angle = i as f32 / MAX_LIGHTS as f32 * std::f32::consts::TAU;
+                Light::Point {
+                    position: Vec3::new(angle.cos() * 12.0, 3.0, angle.sin() * 12.0),
+                    color: Vec3::new(0.6 + 0.4 * angle.cos(), 0.7, 0.6 + 0.4 * angle.sin()),
+                    intensity: 20.0,
+                    range: 20.0,
+                }
+            })
+            .collect();
+        self.lights.upload(&lights, Vec3::splat(0.03)).unwrap();
+
+        let camera = self.orbit.camera();
+        let view_projection = camera.projection_for_context(self.aspect) * camera.view();
+        self.shaders.set_camera(view_projection, camera.eye);
+        self.cache.invalidate();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.02, 0.02, 0.03, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let mut list = DrawList::new(view_projection);
+        list.depth_prepass = self.depth_prepass;
+        for x in -12i32..=12 {
+            for y in 0i32..6 {
+                for z in -12i32..=12 {
+                    let material = &self.materials[(x + y + z).rem_euclid(8) as usize];
+                    let model = Mat4::from_translation(Vec3::new(x as f32, y as f32, z as f32))
+                        * Mat4::from_scale(Vec3::splat(0.9));
+                    list.push(&self.shaders, &self.cube, 0, material, model);
+                }
+            }
+        }
+        if let Some(timer) = &mut self.timer {
+            timer.begin().unwrap();
+        }
+        list.submit(&self.shaders, &mut self.cache);
+        if let Some(timer) = &mut self.timer {
+            timer.end();
+        }
+    }
+}
+
+fn main() {
+    app::run("Depth pre-pass", |_| Demo::new());
+}
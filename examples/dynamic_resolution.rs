@@ -0,0 +1,137 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::dynamic_resolution::{DynamicResolution, DynamicResolutionConfig, Upscale};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::viewport::OrbitCamera;
+
+/// A field of spinning cubes lit by many lights, rendered at whatever resolution keeps
+/// the GPU within budget. U switches between bilinear and sharpened upscaling, +/-
+/// halve and double the budget. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    ground: Mesh,
+    cube: Mesh,
+    resolution: DynamicResolution,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+    time: f32,
+    report: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            ground: Mesh::plane(40.0)?,
+            cube: Mesh::cube(1.0)?,
+            resolution: DynamicResolution::new(1, 1, DynamicResolutionConfig::default())?,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 20.0),
+            aspect: 1.0,
+            time: 0.0,
+            report: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.resolution.resize(width, height).unwrap();
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state != ElementState::Pressed {
+                return;
+            }
+            let config = &mut self.resolution.config;
+            match input.key {
+                Some(KeyCode::KeyU) => {
+                    config.upscale = match config.upscale {
+                        Upscale::Bilinear => Upscale::Sharpened { sharpening: 0.5 },
+                        Upscale::Sharpened { .. } => Upscale::Bilinear,
+                    };
+                    tracing::info!("Upscaling: {:?}", config.upscale);
+                }
+                Some(KeyCode::Equal) => config.frame_budget *= 2.0,
+                Some(KeyCode::Minus) => config.frame_budget /= 2.0,
+                _ => {}
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+
+        self.report += dt;
+        if self.report >= 1.0 {
+            self.report = 0.0;
+            let (width, height) = self.resolution.render_size();
+            tracing::info!(
+                "{}x{} ({:.0}%), {:.2} ms of {:.2} ms",
+                width,
+                height,
+                self.resolution.scale() * 100.0,
+                self.resolution.gpu_time().unwrap_or(0.0) * 1e3,
+                self.resolution.config.frame_budget * 1e3
+            );
+        }
+    }
+
+    fn render(&mut self) {
+        let lights: Vec<_> = (0..8)
+            .map(|i| {
+                let angle = i as f32 / 8.0 * std::f32::consts::TAU + self.time * 0.5;
+                Light::Point {
+                    position: Vec3::new(angle.cos() * 10.0, 2.0, angle.sin() * 10.0),
+                    color: Vec3::new(0.5 + 0.5 * angle.cos(), 0.6, 0.5 + 0.5 * angle.sin()),
+                    intensity: 30.0,
+                    range: 15.0,
+                }
+            })
+            .collect();
+        self.lights.upload(&lights, Vec3::splat(0.05)).unwrap();
+
+        self.resolution.begin().unwrap();
+        let camera = self.orbit.camera();
+        let view_projection = camera.projection_for_context(self.aspect) * camera.view();
+        self.shaders.set_camera(view_projection, camera.eye);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.07, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let program = self.shaders.bind(&Material::default());
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.ground.draw();
+        for x in -10..=10 {
+            for z in -10..=10 {
+                let color = Vec4::new(0.5 + x as f32 / 20.0, 0.5, 0.5 + z as f32 / 20.0, 1.0);
+                let program = self.shaders.bind(&Material::pbr(color, 0.2, 0.4));
+                let model = Mat4::from_translation(Vec3::new(x as f32, 0.5, z as f32) * 1.8)
+                    * Mat4::from_rotation_y(self.time + (x * z) as f32 * 0.1)
+                    * Mat4::from_scale(Vec3::splat(0.8));
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.cube.draw();
+            }
+        }
+        self.resolution.end().unwrap();
+    }
+}
+
+fn main() {
+    app::run("Dynamic resolution", |_| Demo::new());
+}
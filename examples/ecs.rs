@@ -0,0 +1,92 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::ecs::{Camera, Light, Renderer, Transform};
+use hello_gl::gl;
+use hello_gl::material::Material;
+use hello_gl::math::{Quat, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::primitives;
+
+/// Spins every entity with a `Spin` component.
+struct Spin(f32);
+
+/// A grid of spinning shapes and a floor, kept in a `hecs` world and drawn by the ECS
+/// renderer.
+struct Demo {
+    world: hecs::World,
+    renderer: Renderer,
+    aspect: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let mut renderer = Renderer::new()?;
+        let shapes = [
+            primitives::cube(0.6).mesh()?,
+            primitives::uv_sphere(0.35, 24, 16).mesh()?,
+            primitives::torus(0.3, 0.1, 32, 12).mesh()?,
+            primitives::cylinder(0.3, 0.6, 24).mesh()?,
+            primitives::capsule(0.2, 0.3, 24, 8).mesh()?,
+        ]
+        .map(|mesh| renderer.add_mesh(mesh));
+        let floor = renderer.add_mesh(Mesh::plane(12.0)?);
+
+        let mut world = hecs::World::new();
+        world.spawn((Camera::look_at(Vec3::new(0.0, 5.0, 9.0), Vec3::ZERO),));
+        world.spawn((Light::Directional {
+            direction: Vec3::new(-0.3, -1.0, -0.5),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        },));
+        world.spawn((
+            Transform::from_translation(Vec3::new(0.0, -0.5, 0.0)),
+            floor,
+        ));
+        for x in -2..=2 {
+            for z in -2..=2 {
+                let color = Vec4::new(0.5 + x as f32 * 0.1, 0.4, 0.5 + z as f32 * 0.1, 1.0);
+                let material = renderer.add_material(Material::pbr(color, 0.0, 0.4));
+                world.spawn((
+                    Transform::from_translation(Vec3::new(x as f32 * 1.5, 0.0, z as f32 * 1.5)),
+                    shapes[(x + z + 4) as usize % shapes.len()],
+                    material,
+                    Spin(0.5 + (x + z + 4) as f32 * 0.2),
+                ));
+            }
+        }
+
+        Ok(Demo {
+            world,
+            renderer,
+            aspect: 1.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        for (_, (transform, spin)) in self.world.query_mut::<(&mut Transform, &Spin)>() {
+            transform.rotation *= Quat::from_rotation_y(spin.0 * dt);
+        }
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.renderer.render(&self.world, self.aspect).unwrap();
+    }
+}
+
+fn main() {
+    app::run("ECS", |_| Demo::new());
+}
@@ -0,0 +1,103 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::egui::Egui;
+use hello_gl::event::WindowEvent;
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Quat, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+
+struct Demo {
+    egui: Egui,
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cube: Mesh,
+    material: Material,
+    clear_color: [f32; 3],
+    speed: f32,
+    angle: f32,
+    dt: f32,
+    aspect: f32,
+}
+
+impl Demo {
+    fn new(scale_factor: f32) -> Result<Demo> {
+        Ok(Demo {
+            egui: Egui::new(scale_factor)?,
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cube: Mesh::cube(1.0)?,
+            material: Material::pbr(Vec4::new(0.8, 0.3, 0.2, 1.0), 0.0, 0.5),
+            clear_color: [0.2, 0.3, 0.3],
+            speed: 1.0,
+            angle: 0.0,
+            dt: 0.0,
+            aspect: 1.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.egui.resize(width, height);
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.egui.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.dt = dt;
+        self.angle += dt * self.speed;
+    }
+
+    fn render(&mut self) {
+        let [r, g, b] = self.clear_color;
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(r, g, b, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.3, -1.0, -0.5),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+        let eye = Vec3::new(0.0, 1.5, 4.0);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        self.shaders
+            .set_camera(projection * Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y), eye);
+        let program = self.shaders.bind(&self.material);
+        program.set_mat4(
+            "u_model",
+            &Mat4::from_quat(Quat::from_rotation_y(self.angle)).to_cols_array(),
+        );
+        self.cube.draw();
+
+        let Demo {
+            egui,
+            material,
+            clear_color,
+            speed,
+            ..
+        } = self;
+        egui.frame(self.dt, |ctx| {
+            ::egui::Window::new("Settings").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Background");
+                    ui.color_edit_button_rgb(clear_color);
+                });
+                ui.add(::egui::Slider::new(speed, 0.0..=5.0).text("Speed"));
+                ui.add(::egui::Slider::new(&mut material.metallic, 0.0..=1.0).text("Metallic"));
+                ui.add(::egui::Slider::new(&mut material.roughness, 0.05..=1.0).text("Roughness"));
+            });
+        })
+        .unwrap();
+    }
+}
+
+fn main() {
+    app::run("egui", |window| Demo::new(window.scale_factor() as f32));
+}
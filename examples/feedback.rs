@@ -0,0 +1,113 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::feedback::FeedbackPingPong;
+use hello_gl::gl;
+use hello_gl::shader::Program;
+
+const POINTS: usize = 100_000;
+
+/// Moves each point towards an orbiting attractor; captured by transform feedback.
+const UPDATE_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_velocity;
+uniform float u_dt;
+uniform vec2 u_attractor;
+out vec2 o_position;
+out vec2 o_velocity;
+void main() {
+    vec2 to_attractor = u_attractor - a_position;
+    float d = max(length(to_attractor), 0.05);
+    vec2 velocity = (a_velocity + to_attractor / (d * d * d) * 0.02 * u_dt) * 0.995;
+    o_velocity = velocity;
+    o_position = a_position + velocity * u_dt;
+}
+"#;
+
+const DRAW_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_velocity;
+out float v_speed;
+void main() {
+    v_speed = length(a_velocity);
+    gl_Position = vec4(a_position, 0.0, 1.0);
+}
+"#;
+
+const DRAW_FRAGMENT_SHADER: &str = r#"#version 330 core
+in float v_speed;
+out vec4 frag_color;
+void main() {
+    frag_color = vec4(mix(vec3(0.2, 0.4, 1.0), vec3(1.0, 0.9, 0.6), clamp(v_speed, 0.0, 1.0)), 0.3);
+}
+"#;
+
+struct Demo {
+    points: FeedbackPingPong,
+    update: Program,
+    draw: Program,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let initial: Vec<f32> = (0..POINTS)
+            .flat_map(|i| {
+                let angle = i as f32 * 2.399;
+                let radius = (i as f32 / POINTS as f32).sqrt() * 0.9;
+                [radius * angle.cos(), radius * angle.sin(), 0.0, 0.0]
+            })
+            .collect();
+        let points = FeedbackPingPong::new(bytemuck::cast_slice(&initial), POINTS, || unsafe {
+            let stride = 4 * std::mem::size_of::<f32>() as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, 8 as *const _);
+            gl::EnableVertexAttribArray(1);
+        })?;
+        Ok(Demo {
+            points,
+            update: Program::from_feedback_vertex(UPDATE_SHADER, &["o_position", "o_velocity"])?,
+            draw: Program::from_sources(DRAW_VERTEX_SHADER, DRAW_FRAGMENT_SHADER)?,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.update.use_program();
+        self.update.set_float("u_dt", dt.min(0.05));
+        self.update.set_vec2(
+            "u_attractor",
+            [self.time.cos() * 0.5, (self.time * 1.3).sin() * 0.5],
+        );
+        self.points.step(&self.update);
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+        }
+        self.draw.use_program();
+        self.points.vertex_array().bind();
+        unsafe {
+            gl::DrawArrays(gl::POINTS, 0, self.points.vertex_count());
+            gl::Disable(gl::BLEND);
+        }
+        self.points.vertex_array().unbind();
+    }
+}
+
+fn main() {
+    app::run("Transform feedback", |_| Demo::new());
+}
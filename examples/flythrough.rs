@@ -0,0 +1,122 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::tracks::{CameraTrack, LightTrack};
+use hello_gl::viewport::Camera;
+
+/// A camera flying a keyframed loop through pillars while three colored lights circle
+/// them. Space pauses.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    ground: Mesh,
+    pillar: Mesh,
+    flight: CameraTrack,
+    light_tracks: Vec<(Light, LightTrack)>,
+    camera: Camera,
+    paused: bool,
+    aspect: f32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let at = Vec3::new;
+        let flight = CameraTrack::through(&[
+            (0.0, at(-12.0, 3.0, 12.0), at(0.0, 1.0, 0.0)),
+            (4.0, at(0.0, 1.5, 6.0), at(6.0, 1.0, 0.0)),
+            (8.0, at(10.0, 6.0, 0.0), at(0.0, 0.0, -4.0)),
+            (12.0, at(0.0, 2.0, -10.0), at(-6.0, 1.0, 4.0)),
+            (16.0, at(-12.0, 3.0, 12.0), at(0.0, 1.0, 0.0)),
+        ])?;
+        let light_tracks = [
+            (Vec3::new(1.0, 0.3, 0.2), 6.0, 7.0),
+            (Vec3::new(0.2, 1.0, 0.4), 9.0, 11.0),
+            (Vec3::new(0.3, 0.4, 1.0), 4.0, 5.0),
+        ]
+        .into_iter()
+        .map(|(color, radius, period)| {
+            let light = Light::Point {
+                position: Vec3::ZERO,
+                color,
+                intensity: 25.0,
+                range: 14.0,
+            };
+            let center = Vec3::new(0.0, 2.0, 0.0);
+            (light, LightTrack::orbit(center, radius, period))
+        })
+        .collect();
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            ground: Mesh::plane(40.0)?,
+            pillar: Mesh::cube(1.0)?,
+            flight,
+            light_tracks,
+            camera: Camera::look_at(Vec3::ZERO, Vec3::Z),
+            paused: false,
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width.max(1) as f32 / height.max(1) as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::Space) {
+                self.paused = !self.paused;
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        if !self.paused {
+            self.time += dt;
+        }
+        self.flight.apply(self.time, &mut self.camera);
+        for (light, track) in &mut self.light_tracks {
+            track.apply(self.time, light);
+        }
+    }
+
+    fn render(&mut self) {
+        let lights: Vec<_> = self.light_tracks.iter().map(|(light, _)| *light).collect();
+        self.lights.upload(&lights, Vec3::splat(0.04)).unwrap();
+        let view_projection = self.camera.projection_for_context(self.aspect) * self.camera.view();
+        self.shaders.set_camera(view_projection, self.camera.eye);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.02, 0.02, 0.03, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let program = self.shaders.bind(&Material::default());
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.ground.draw();
+        let program = self
+            .shaders
+            .bind(&Material::pbr(Vec4::new(0.8, 0.8, 0.8, 1.0), 0.0, 0.5));
+        for x in -2..=2 {
+            for z in -2..=2 {
+                let position = Vec3::new(x as f32 * 4.0, 1.5, z as f32 * 4.0);
+                let model =
+                    Mat4::from_translation(position) * Mat4::from_scale(Vec3::new(0.6, 3.0, 0.6));
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.pillar.draw();
+            }
+        }
+    }
+}
+
+fn main() {
+    app::run("Fly-through", |_| Demo::new());
+}
@@ -0,0 +1,96 @@
+use hello_gl::app::{self, App};
+use hello_gl::culling::{CullStats, Frustum};
+use hello_gl::gl;
+use hello_gl::gltf::{self, GltfCamera};
+use hello_gl::material::{Light, LightBuffer, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::scene::{NodeId, Scene};
+
+struct Viewer {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    scene: Scene,
+    /// The first camera found in the hierarchy.
+    camera: Option<(NodeId, GltfCamera)>,
+    aspect: f32,
+    stats: CullStats,
+}
+
+impl Viewer {
+    fn camera(&self) -> (Mat4, Vec3) {
+        match self.camera {
+            Some((node, camera)) => {
+                let world = self.scene.node(node).world();
+                (
+                    camera.projection(self.aspect) * world.inverse(),
+                    world.transform_point3(Vec3::ZERO),
+                )
+            }
+            None => {
+                let eye = Vec3::new(0.0, 1.0, 4.0);
+                let projection =
+                    Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+                (projection * Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y), eye)
+            }
+        }
+    }
+}
+
+impl App for Viewer {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.6),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+        self.scene.update();
+        let (view_projection, eye) = self.camera();
+        self.shaders.set_camera(view_projection, eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let stats = self.scene.draw_culled(
+            &self.shaders,
+            &Frustum::from_view_projection(view_projection),
+        );
+        if stats != self.stats {
+            println!("{} visible, {} culled", stats.visible, stats.culled);
+            self.stats = stats;
+        }
+    }
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: gltf_viewer <scene.gltf|scene.glb>");
+    app::run("glTF viewer", move |_| {
+        let gltf = gltf::load(&path)?;
+        let cameras = gltf.cameras.clone();
+        let camera_nodes: Vec<_> = gltf.nodes.iter().map(|node| node.camera).collect();
+        let (scene, ids) = gltf.into_scene();
+        let camera = ids
+            .iter()
+            .zip(camera_nodes)
+            .find_map(|(id, camera)| Some(((*id)?, cameras[camera?])));
+        Ok(Viewer {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            scene,
+            camera,
+            aspect: 1.0,
+            stats: CullStats::default(),
+        })
+    });
+}
@@ -0,0 +1,148 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::buffer::{Buffer, VertexArray};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::exposure::{self, AutoExposure, ExposureConfig};
+use hello_gl::gl;
+use hello_gl::postprocess::{Pass, PostProcess, Tonemap};
+use hello_gl::shader::{Program, Uniform};
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 pos;
+layout (location = 1) in vec3 color;
+out vec3 v_color;
+void main() {
+    v_color = color;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+"#;
+
+const FRAG_SHADER: &str = r#"#version 330 core
+in vec3 v_color;
+out vec4 final_color;
+uniform float u_time;
+void main() {
+    // Swings between 1/16 and 16 times as bright for auto exposure to follow.
+    final_color = vec4(v_color * exp2(sin(u_time * 0.5) * 4.0), 1.0);
+}
+"#;
+
+struct Demo {
+    _vb: Buffer,
+    va: VertexArray,
+    program: Program,
+    post: PostProcess,
+    operator: Tonemap,
+    exposure: f32,
+}
+
+impl Demo {
+    fn new(width: i32, height: i32) -> Result<Demo> {
+        // Vertex colors well above 1.0 that would clip without tonemapping.
+        #[rustfmt::skip]
+        const VERTICES: [f32; 15] = [
+            -0.5, -0.5, 8.0, 0.5, 0.2,
+             0.5, -0.5, 0.2, 4.0, 0.5,
+             0.0,  0.5, 0.5, 0.2, 16.0,
+        ];
+
+        let va = VertexArray::new()?;
+        va.bind();
+        let vb = Buffer::new()?;
+        vb.bind(gl::ARRAY_BUFFER);
+        vb.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&VERTICES),
+            gl::STATIC_DRAW,
+        );
+        unsafe {
+            let stride = 5 * std::mem::size_of::<f32>() as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (2 * 4) as *const _);
+            gl::EnableVertexAttribArray(1);
+        }
+        va.unbind();
+
+        let operator = Tonemap::Aces;
+        let exposure = 1.0;
+        let mut post = PostProcess::hdr(width, height)?;
+        post.push(Pass::tonemap(operator, exposure)?);
+
+        Ok(Demo {
+            _vb: vb,
+            va,
+            program: Program::from_sources(VERT_SHADER, FRAG_SHADER)?,
+            post,
+            operator,
+            exposure,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.post.resize(width as i32, height as i32).unwrap();
+    }
+
+    // T switches the operator, +/- adjust exposure, A toggles auto exposure.
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state != ElementState::Pressed {
+                return;
+            }
+            match input.key {
+                Some(KeyCode::KeyT) => {
+                    self.operator = match self.operator {
+                        Tonemap::Reinhard => Tonemap::Aces,
+                        Tonemap::Aces => Tonemap::Reinhard,
+                    }
+                }
+                Some(KeyCode::Equal) => self.exposure *= 1.25,
+                Some(KeyCode::Minus) => self.exposure /= 1.25,
+                Some(KeyCode::KeyA) if exposure::is_supported() => {
+                    let auto_exposure = match self.post.auto_exposure_mut() {
+                        Some(_) => None,
+                        None => Some(AutoExposure::new(ExposureConfig::default()).unwrap()),
+                    };
+                    self.post.set_auto_exposure(auto_exposure);
+                }
+                _ => return,
+            }
+            println!(
+                "{:?}, exposure {:.2}{}",
+                self.operator,
+                self.exposure,
+                if self.post.auto_exposure_mut().is_some() {
+                    " (compensation for auto exposure)"
+                } else {
+                    ""
+                }
+            );
+            let pass = self.post.pass_mut("tonemap").unwrap();
+            pass.set_uniform("u_operator", Uniform::Int(self.operator as i32));
+            pass.set_uniform("u_exposure", Uniform::Float(self.exposure));
+        }
+    }
+
+    fn render(&mut self) {
+        self.post.begin();
+        unsafe {
+            gl::ClearColor(0.05, 0.05, 0.08, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.va.bind();
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.post.end();
+    }
+}
+
+fn main() {
+    app::run("HDR tonemapping", |window| {
+        let size = window.inner_size();
+        Demo::new(size.width as i32, size.height as i32)
+    });
+}
@@ -0,0 +1,166 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::ibl::{Environment, EnvironmentConfig};
+use hello_gl::image::HdrImage;
+use hello_gl::input::Input;
+use hello_gl::material::{LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::primitives;
+use hello_gl::probe::CubeCapture;
+use hello_gl::viewport::{Camera, OrbitCamera};
+
+const GRID: usize = 7;
+
+/// A sky gradient over a dark ground with a small, very bright sun, for running without
+/// an HDR file.
+fn procedural_sky() -> HdrImage {
+    let (width, height) = (512, 256);
+    let sun = Vec3::new(0.4, 0.5, -0.75).normalize();
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let azimuth = (x as f32 + 0.5) / width as f32 * std::f32::consts::TAU;
+            let elevation = (0.5 - (y as f32 + 0.5) / height as f32) * std::f32::consts::PI;
+            let direction = Vec3::new(
+                elevation.cos() * -azimuth.cos(),
+                elevation.sin(),
+                elevation.cos() * -azimuth.sin(),
+            );
+            let color = if direction.y > 0.0 {
+                Vec3::new(0.9, 0.95, 1.0).lerp(Vec3::new(0.2, 0.4, 0.9), direction.y.sqrt())
+            } else {
+                Vec3::new(0.25, 0.2, 0.15)
+            };
+            let glow = direction.dot(sun).max(0.0).powf(2000.0) * 200.0;
+            pixels.push((color + Vec3::splat(glow)).to_array());
+        }
+    }
+    HdrImage {
+        width: width as u32,
+        height: height as u32,
+        pixels,
+    }
+}
+
+/// Spheres of increasing roughness (left to right) and metalness (bottom to top), lit
+/// only by an environment. Pass a Radiance `.hdr` panorama to use it instead of the
+/// procedural sky. Press C to capture the scene in front of the grid into `probe.hdr`
+/// and `probe.ktx`; the `.hdr` can be passed back in. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    sphere: Mesh,
+    materials: Vec<Material>,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+    size: (u32, u32),
+}
+
+impl Demo {
+    fn new(path: Option<String>) -> Result<Demo> {
+        let environment = match path {
+            Some(path) => Environment::load(path)?,
+            None => Environment::from_hdr(&procedural_sky(), EnvironmentConfig::default())?,
+        };
+        let mut shaders = MaterialShaders::new()?;
+        shaders.set_environment(Some(Rc::new(environment)));
+
+        let mut materials = Vec::new();
+        for row in 0..GRID {
+            for column in 0..GRID {
+                let metallic = row as f32 / (GRID - 1) as f32;
+                let roughness = (column as f32 / (GRID - 1) as f32).max(0.05);
+                let color = Vec4::new(0.9, 0.6, 0.3, 1.0);
+                materials.push(Material::pbr(color, metallic, roughness));
+            }
+        }
+
+        Ok(Demo {
+            shaders,
+            lights: LightBuffer::new()?,
+            sphere: primitives::uv_sphere(0.4, 48, 24).mesh()?,
+            materials,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 10.0),
+            aspect: 1.0,
+            size: (1, 1),
+        })
+    }
+
+    fn draw_scene(&self, camera: &Camera, aspect: f32) {
+        let (view, projection) = (camera.view(), camera.projection_for_context(aspect));
+        self.shaders.set_camera(projection * view, camera.eye);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        for (i, material) in self.materials.iter().enumerate() {
+            let (row, column) = (i / GRID, i % GRID);
+            let offset = (GRID - 1) as f32 / 2.0;
+            let position = Vec3::new(column as f32 - offset, row as f32 - offset, 0.0);
+            let program = self.shaders.bind(material);
+            program.set_mat4("u_model", &Mat4::from_translation(position).to_cols_array());
+            self.sphere.draw();
+        }
+        if let Some(environment) = self.shaders.environment() {
+            environment.draw_skybox(view, projection);
+        }
+    }
+
+    fn capture(&self) -> Result<()> {
+        let capture = CubeCapture::new(256)?;
+        capture.capture(Vec3::new(0.0, 0.0, 4.0), 0.1, 100.0, |camera| {
+            self.draw_scene(camera, 1.0)
+        })?;
+        capture.save_hdr("probe.hdr")?;
+        capture.save_ktx("probe.ktx")?;
+        let (width, height) = self.size;
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        println!("Saved probe.hdr and probe.ktx");
+        Ok(())
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+        self.size = (width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyC) {
+                if let Err(e) = self.capture() {
+                    eprintln!("Capture failed: {:#}", e);
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        self.lights.upload(&[], Vec3::ZERO).unwrap();
+        self.draw_scene(&self.orbit.camera(), self.aspect);
+    }
+}
+
+fn main() {
+    let path = std::env::args().nth(1);
+    app::run("Image-based lighting", move |_| Demo::new(path));
+}
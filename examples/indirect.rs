@@ -0,0 +1,163 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::buffer::Buffer;
+use hello_gl::gl;
+use hello_gl::indirect::{DrawElementsIndirectCommand, DrawIndirectBuffer};
+use hello_gl::math::{Mat4, Quat, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::stats::{self, Counters};
+
+const GRID: usize = 64;
+
+/// Per-instance model matrices at locations 6..=9, past the mesh tangents, selected by
+/// each command's `base_instance`.
+const VERTEX_SHADER: &str = r#"#version 430 core
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 6) in mat4 a_model;
+uniform mat4 u_view_projection;
+out vec3 v_normal;
+out vec3 v_color;
+void main() {
+    v_normal = mat3(a_model) * a_normal;
+    v_color = 0.5 + 0.5 * sin(vec3(0.0, 2.0, 4.0) + float(gl_BaseInstance) * 0.01);
+    gl_Position = u_view_projection * a_model * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 430 core
+in vec3 v_normal;
+in vec3 v_color;
+out vec4 frag_color;
+void main() {
+    float light = max(dot(normalize(v_normal), normalize(vec3(0.4, 1.0, 0.3))), 0.0) * 0.8 + 0.2;
+    frag_color = vec4(v_color * light, 1.0);
+}
+"#;
+
+struct Demo {
+    cube: Mesh,
+    _instances: Buffer,
+    commands: DrawIndirectBuffer,
+    program: Program,
+    aspect: f32,
+    time: f32,
+    frame_time: f32,
+    last: Counters,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let cube = Mesh::cube(0.6)?;
+        let models: Vec<Mat4> = (0..GRID * GRID)
+            .map(|i| {
+                let (x, z) = ((i % GRID) as f32, (i / GRID) as f32);
+                let offset = (GRID as f32 - 1.0) * 0.5;
+                Mat4::from_rotation_translation(
+                    Quat::from_rotation_y(i as f32 * 0.37),
+                    Vec3::new(
+                        x - offset,
+                        ((x * 0.3).sin() + (z * 0.2).cos()) * 1.5,
+                        z - offset,
+                    ),
+                )
+            })
+            .collect();
+
+        let instances = Buffer::new()?;
+        instances.bind(gl::ARRAY_BUFFER);
+        instances.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&models),
+            gl::STATIC_DRAW,
+        );
+        instances.unbind(gl::ARRAY_BUFFER);
+        let stride = std::mem::size_of::<Mat4>() as i32;
+        for column in 0..4 {
+            let location = 6 + column;
+            let vertex_array = cube.vertex_array();
+            vertex_array.attribute(
+                location,
+                &instances,
+                4,
+                gl::FLOAT,
+                false,
+                stride,
+                column as usize * 16,
+            );
+            vertex_array.divisor(location, 1);
+        }
+
+        // One command per cube; a real scene would point each at a different submesh
+        // of a `Mesh::packed` vertex/index buffer.
+        let submesh = cube.submeshes()[0];
+        let commands: Vec<DrawElementsIndirectCommand> = (0..models.len() as u32)
+            .map(|i| DrawElementsIndirectCommand {
+                count: submesh.index_count as u32,
+                instance_count: 1,
+                first_index: submesh.first_index,
+                base_vertex: submesh.base_vertex,
+                base_instance: i,
+            })
+            .collect();
+
+        Ok(Demo {
+            cube,
+            _instances: instances,
+            commands: DrawIndirectBuffer::new(&commands)?,
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            aspect: 1.0,
+            time: 0.0,
+            frame_time: 0.0,
+            last: Counters::default(),
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height.max(1) as f32;
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.frame_time += dt;
+        if self.frame_time >= 1.0 {
+            let counters = self.last;
+            println!(
+                "{} commands in {} draw call(s), {} triangles",
+                self.commands.len(),
+                counters.draw_calls,
+                counters.triangles
+            );
+            self.frame_time = 0.0;
+        }
+    }
+
+    fn render(&mut self) {
+        let eye = Vec3::new(self.time.cos() * 50.0, 25.0, self.time.sin() * 50.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 200.0);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.08, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        stats::take_counters();
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &(projection * view).to_cols_array());
+        self.cube.vertex_array().bind();
+        self.commands.draw();
+        self.cube.vertex_array().unbind();
+        self.last = stats::take_counters();
+    }
+}
+
+fn main() {
+    app::run("Indirect multi-draw", |_| Demo::new());
+}
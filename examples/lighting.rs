@@ -0,0 +1,113 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    materials: Vec<Material>,
+    ground: Mesh,
+    cube: Mesh,
+    aspect: f32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let mut materials = Vec::new();
+        for i in 0..5 {
+            let t = i as f32 / 4.0;
+            materials.push(Material::blinn_phong(
+                Vec4::new(0.9, 0.4, 0.2, 1.0),
+                8.0 + t * 120.0,
+            ));
+            materials.push(Material::pbr(
+                Vec4::new(0.9, 0.7, 0.3, 1.0),
+                t,
+                0.2 + t * 0.6,
+            ));
+        }
+
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            materials,
+            ground: Mesh::plane(20.0)?,
+            cube: Mesh::cube(1.0)?,
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let lights = [
+            Light::Directional {
+                direction: Vec3::new(-0.3, -1.0, -0.5),
+                color: Vec3::ONE,
+                intensity: 0.6,
+            },
+            Light::Point {
+                position: Vec3::new(self.time.cos() * 4.0, 2.0, self.time.sin() * 4.0),
+                color: Vec3::new(1.0, 0.6, 0.3),
+                intensity: 20.0,
+                range: 10.0,
+            },
+            Light::Spot {
+                position: Vec3::new(0.0, 6.0, 0.0),
+                direction: Vec3::NEG_Y,
+                color: Vec3::new(0.3, 0.5, 1.0),
+                intensity: 40.0,
+                range: 12.0,
+                inner_angle: 0.3,
+                outer_angle: 0.5,
+            },
+        ];
+        self.lights.upload(&lights, Vec3::splat(0.03)).unwrap();
+
+        let eye = Vec3::new(0.0, 6.0, 12.0);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        let view = Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y);
+        self.shaders.set_camera(projection * view, eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let ground = Material::default();
+        let program = self.shaders.bind(&ground);
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.ground.draw();
+
+        for (i, material) in self.materials.iter().enumerate() {
+            let x = (i / 2) as f32 * 2.0 - 4.0;
+            let z = if i % 2 == 0 { -1.5 } else { 1.5 };
+            let model = Mat4::from_translation(Vec3::new(x, 0.5, z))
+                * Mat4::from_rotation_y(self.time * 0.5);
+            let program = self.shaders.bind(material);
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.cube.draw();
+        }
+    }
+}
+
+fn main() {
+    app::run("Materials and lights", |_| Demo::new());
+}
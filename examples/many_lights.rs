@@ -0,0 +1,145 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::clustered::{ClusterBackend, ClusteredLights};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::primitives;
+use hello_gl::viewport::OrbitCamera;
+
+const LIGHT_COUNT: usize = 512;
+
+/// A field of spheres lit by hundreds of wandering point lights, shaded through clustered
+/// forward lighting. Press B to switch between the compute and CPU clustering backends.
+/// Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    floor: Mesh,
+    sphere: Mesh,
+    ground: Material,
+    spheres: [Material; 3],
+    input: Input,
+    orbit: OrbitCamera,
+    size: (u32, u32),
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let mut shaders = MaterialShaders::new()?;
+        let clustered = ClusteredLights::new()?;
+        println!("Clustering backend: {:?}", clustered.backend());
+        shaders.set_clustered_lights(Some(clustered));
+        Ok(Demo {
+            shaders,
+            lights: LightBuffer::new()?,
+            floor: Mesh::plane(40.0)?,
+            sphere: primitives::uv_sphere(0.5, 24, 16).mesh()?,
+            ground: Material::pbr(Vec4::new(0.6, 0.6, 0.6, 1.0), 0.0, 0.6),
+            spheres: [
+                Material::pbr(Vec4::new(0.9, 0.9, 0.9, 1.0), 0.0, 0.3),
+                Material::pbr(Vec4::new(0.9, 0.7, 0.4, 1.0), 1.0, 0.3),
+                Material::blinn_phong(Vec4::new(0.4, 0.6, 0.9, 1.0), 64.0),
+            ],
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 25.0),
+            size: (1, 1),
+            time: 0.0,
+        })
+    }
+
+    fn point_lights(&self) -> Vec<Light> {
+        (0..LIGHT_COUNT)
+            .map(|i| {
+                let f = i as f32;
+                let angle = f * 0.618 * std::f32::consts::TAU + self.time * (0.1 + f % 7.0 * 0.03);
+                let distance = 1.0 + (f * 0.37) % 18.0;
+                Light::Point {
+                    position: Vec3::new(
+                        angle.cos() * distance,
+                        0.3 + (f * 0.7 + self.time).sin().abs() * 1.5,
+                        angle.sin() * distance,
+                    ),
+                    color: Vec3::new(
+                        0.5 + 0.5 * (f * 1.3).sin(),
+                        0.5 + 0.5 * (f * 2.1).sin(),
+                        0.5 + 0.5 * (f * 3.7).sin(),
+                    ),
+                    intensity: 2.0,
+                    range: 2.5,
+                }
+            })
+            .collect()
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.size = (width.max(1), height.max(1));
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyB) {
+                let backend = match self.shaders.clustered_lights().map(|c| c.backend()) {
+                    Some(ClusterBackend::Cpu) => ClusterBackend::best_available(),
+                    _ => ClusterBackend::Cpu,
+                };
+                let clustered = ClusteredLights::with_backend(backend).unwrap();
+                println!("Clustering backend: {:?}", clustered.backend());
+                self.shaders.set_clustered_lights(Some(clustered));
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let camera = self.orbit.camera();
+        let (width, height) = self.size;
+        let aspect = width as f32 / height as f32;
+        self.lights.upload(&[], Vec3::splat(0.02)).unwrap();
+        let point_lights = self.point_lights();
+        if let Some(clustered) = self.shaders.clustered_lights_mut() {
+            clustered
+                .update(&point_lights, &camera, width, height)
+                .unwrap();
+        }
+        self.shaders
+            .set_camera(camera.projection(aspect) * camera.view(), camera.eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.01, 0.01, 0.02, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let program = self.shaders.bind(&self.ground);
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.floor.draw();
+        for x in -8..=8 {
+            for z in -8..=8 {
+                let material = &self.spheres[(x + z + 16) as usize % self.spheres.len()];
+                let program = self.shaders.bind(material);
+                let model = Mat4::from_translation(Vec3::new(x as f32 * 2.0, 0.5, z as f32 * 2.0));
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.sphere.draw();
+            }
+        }
+    }
+}
+
+fn main() {
+    app::run("Clustered forward lighting", |_| Demo::new());
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App, ContextConfig};
+use hello_gl::buffer::{Buffer, VertexArray};
+use hello_gl::gl;
+use hello_gl::shader::Program;
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 pos;
+uniform float u_angle;
+void main() {
+    mat2 rotation = mat2(cos(u_angle), sin(u_angle), -sin(u_angle), cos(u_angle));
+    gl_Position = vec4(rotation * pos, 0.0, 1.0);
+}
+"#;
+
+const FRAG_SHADER: &str = r#"#version 330 core
+uniform vec3 u_color;
+out vec4 final_color;
+void main() {
+    final_color = vec4(u_color, 1.0);
+}
+"#;
+
+/// One window's view. The buffer and program are shared between the windows; vertex
+/// arrays are not shareable, so each window builds its own.
+struct View {
+    program: Rc<Program>,
+    _vb: Rc<Buffer>,
+    va: VertexArray,
+    color: [f32; 3],
+    angle: f32,
+    speed: f32,
+}
+
+impl View {
+    fn new(program: Rc<Program>, vb: Rc<Buffer>, color: [f32; 3], speed: f32) -> Result<View> {
+        let va = VertexArray::new()?;
+        va.bind();
+        vb.bind(gl::ARRAY_BUFFER);
+        unsafe {
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 8, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+        va.unbind();
+        Ok(View {
+            program,
+            _vb: vb,
+            va,
+            color,
+            angle: 0.0,
+            speed,
+        })
+    }
+}
+
+impl App for View {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.angle += dt * self.speed;
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.program.set_float("u_angle", self.angle);
+        self.program.set_vec3("u_color", self.color);
+        self.va.bind();
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.va.unbind();
+    }
+}
+
+fn main() {
+    const VERTICES: [[f32; 2]; 3] = [[-0.5, -0.5], [0.5, -0.5], [0.0, 0.5]];
+
+    let mut shared: Option<(Rc<Program>, Rc<Buffer>)> = None;
+    app::run_multi(
+        &["Left", "Right"],
+        &ContextConfig::default(),
+        true,
+        move |index, _| {
+            if shared.is_none() {
+                let vb = Buffer::new()?;
+                vb.bind(gl::ARRAY_BUFFER);
+                vb.data(
+                    gl::ARRAY_BUFFER,
+                    bytemuck::cast_slice(&VERTICES),
+                    gl::STATIC_DRAW,
+                );
+                vb.unbind(gl::ARRAY_BUFFER);
+                let program = Program::from_sources(VERT_SHADER, FRAG_SHADER)?;
+                shared = Some((Rc::new(program), Rc::new(vb)));
+            }
+            let (program, vb) = shared.clone().unwrap();
+            match index {
+                0 => View::new(program, vb, [1.0, 0.5, 0.2], 1.0),
+                _ => View::new(program, vb, [0.2, 0.6, 1.0], -1.5),
+            }
+        },
+    );
+}
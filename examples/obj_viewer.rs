@@ -0,0 +1,81 @@
+use anyhow::anyhow;
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+
+struct Viewer {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    mesh: Mesh,
+    materials: Vec<Material>,
+    fallback: Material,
+    aspect: f32,
+    time: f32,
+}
+
+impl App for Viewer {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.6),
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+
+        let eye = Vec3::new(0.0, 1.0, 4.0);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        self.shaders
+            .set_camera(projection * Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y), eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let model = Mat4::from_rotation_y(self.time * 0.5);
+        for (i, submesh) in self.mesh.submeshes().iter().enumerate() {
+            let material = submesh
+                .material
+                .and_then(|m| self.materials.get(m))
+                .unwrap_or(&self.fallback);
+            let program = self.shaders.bind(material);
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.mesh.draw_submesh(i);
+        }
+    }
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: obj_viewer <model.obj>");
+    app::run("OBJ viewer", move |_| {
+        let (mesh, materials) = Mesh::from_obj(&path)?;
+        if mesh.index_count() == 0 {
+            return Err(anyhow!("{} contains no triangles", path));
+        }
+        Ok(Viewer {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            mesh,
+            materials,
+            fallback: Material::default(),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    });
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use hello_gl::animation::{Interpolation, Track};
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::particles::{EmitterConfig, ParticleBackend, ParticleSystem};
+
+/// A fountain of particles. Uses compute shaders when available; pass `--cpu` to force
+/// the CPU backend.
+struct Demo {
+    fountain: ParticleSystem,
+    aspect: f32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let config = EmitterConfig {
+            rate: 5000.0,
+            lifetime: 3.0,
+            velocity: Vec3::new(0.0, 4.0, 0.0),
+            velocity_randomness: 1.0,
+            gravity: Vec3::new(0.0, -3.0, 0.0),
+            size_over_life: Track::new(vec![0.0, 1.0], vec![0.03, 0.08], Interpolation::Linear)?,
+            color_over_life: Track::new(
+                vec![0.0, 0.3, 1.0],
+                vec![
+                    Vec4::new(1.0, 1.0, 0.6, 1.0),
+                    Vec4::new(1.0, 0.5, 0.1, 0.8),
+                    Vec4::new(0.5, 0.1, 0.1, 0.0),
+                ],
+                Interpolation::Linear,
+            )?,
+            additive: true,
+            ..EmitterConfig::default()
+        };
+        let backend = if std::env::args().any(|arg| arg == "--cpu") {
+            ParticleBackend::Cpu
+        } else {
+            ParticleBackend::best_available()
+        };
+        let fountain = ParticleSystem::with_backend(config, 16384, backend)?;
+        println!("backend: {:?}", fountain.backend());
+        Ok(Demo {
+            fountain,
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.fountain.config.origin = Vec3::new(self.time.cos(), 0.0, self.time.sin()) * 0.5;
+        self.fountain.update(dt);
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.02, 0.02, 0.05, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 2.0, 6.0), Vec3::new(0.0, 1.0, 0.0), Vec3::Y);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        self.fountain.draw(view, projection);
+    }
+}
+
+fn main() {
+    app::run("Particles", |_| Demo::new());
+}
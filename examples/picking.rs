@@ -0,0 +1,128 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, MouseButton, WindowEvent};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::picking::PickBuffer;
+use hello_gl::viewport::{Camera, Viewport};
+
+const GRID: i32 = 5;
+
+/// A grid of cubes; clicking one highlights it.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cube: Mesh,
+    normal: Material,
+    highlight: Material,
+    picks: Option<PickBuffer>,
+    selected: Option<u32>,
+    cursor: (f64, f64),
+    clicked: bool,
+    viewport: Viewport,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cube: Mesh::cube(1.0)?,
+            normal: Material::pbr(Vec4::new(0.6, 0.6, 0.65, 1.0), 0.0, 0.5),
+            highlight: Material::pbr(Vec4::new(1.0, 0.6, 0.1, 1.0), 0.0, 0.3),
+            picks: None,
+            selected: None,
+            cursor: (0.0, 0.0),
+            clicked: false,
+            viewport: Viewport::full(1, 1),
+            time: 0.0,
+        })
+    }
+
+    fn model(&self, id: u32) -> Mat4 {
+        let (x, z) = (id as i32 % GRID, id as i32 / GRID);
+        let offset = (GRID - 1) as f32;
+        Mat4::from_translation(Vec3::new(
+            x as f32 * 2.0 - offset,
+            0.5,
+            z as f32 * 2.0 - offset,
+        )) * Mat4::from_rotation_y(self.time * 0.3 + id as f32)
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.viewport = Viewport::full(width, height);
+        self.picks = PickBuffer::new(width as i32, height as i32).ok();
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor = (position.x, position.y),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.clicked = true,
+            _ => (),
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let camera = Camera::look_at(Vec3::new(0.0, 10.0, 12.0), Vec3::ZERO);
+        let view_projection = self.viewport.view_projection(&camera);
+        let models: Vec<Mat4> = (0..(GRID * GRID) as u32).map(|id| self.model(id)).collect();
+
+        if let Some(picks) = &mut self.picks {
+            if let Some(pick) = picks.poll() {
+                self.selected = pick.id;
+            }
+            picks.begin(view_projection);
+            for (id, model) in models.iter().enumerate() {
+                picks.draw(&self.cube, *model, id as u32);
+            }
+            picks.end();
+            if std::mem::take(&mut self.clicked) {
+                picks
+                    .request(self.cursor.0 as u32, self.cursor.1 as u32)
+                    .unwrap();
+            }
+        }
+
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.3, -1.0, -0.5),
+            color: Vec3::ONE,
+            intensity: 2.5,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+        self.shaders.set_camera(view_projection, camera.eye);
+
+        self.viewport.apply();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        for (id, model) in models.iter().enumerate() {
+            let material = if self.selected == Some(id as u32) {
+                &self.highlight
+            } else {
+                &self.normal
+            };
+            let program = self.shaders.bind(material);
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.cube.draw();
+        }
+    }
+}
+
+fn main() {
+    app::run("Picking", |_| Demo::new());
+}
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use hello_gl::app::{self, App, Window};
+use hello_gl::atlas::AtlasBuilder;
+use hello_gl::camera2d::Camera2D;
+use hello_gl::cursor::SoftwareCursor;
+use hello_gl::event::WindowEvent;
+use hello_gl::gl;
+use hello_gl::image::Image;
+use hello_gl::input::Input;
+use hello_gl::math::{Vec2, Vec4};
+use hello_gl::sprite::{Sprite, SpriteBatch, TextureAtlas};
+
+const TILE: u32 = 8;
+const MAP_WIDTH: u32 = 96;
+const MAP_HEIGHT: u32 = 64;
+const TILES: [&str; 3] = ["water", "grass", "stone"];
+
+/// A tile map of 8x8 pixel tiles seen through a pixel-perfect [`Camera2D`]: drag with the
+/// right mouse button to pan, scroll to zoom by whole steps. The tile under the cursor is
+/// highlighted, and the cursor itself is a software one scaled with the tiles.
+struct Demo {
+    batch: SpriteBatch,
+    atlas: TextureAtlas,
+    camera: Camera2D,
+    cursor: SoftwareCursor,
+    input: Input,
+}
+
+/// An 8x11 arrow pointing up and left, black outlined.
+fn arrow() -> Image {
+    const ROWS: [&str; 11] = [
+        "#.......", "##......", "#o#.....", "#oo#....", "#ooo#...", "#oooo#..", "#ooooo#.",
+        "#oooooo#", "#ooo####", "#o##....", "##......",
+    ];
+    let pixels = ROWS
+        .iter()
+        .flat_map(|row| row.bytes())
+        .flat_map(|pixel| match pixel {
+            b'#' => [20, 20, 20, 255],
+            b'o' => [250, 250, 240, 255],
+            _ => [0, 0, 0, 0],
+        })
+        .collect();
+    Image {
+        width: 8,
+        height: ROWS.len() as u32,
+        pixels,
+    }
+}
+
+/// A tile of `base` color with a sparse dither of `detail`.
+fn tile(base: [u8; 3], detail: [u8; 3], seed: u32) -> Image {
+    let mut pixels = Vec::with_capacity((TILE * TILE * 4) as usize);
+    for y in 0..TILE {
+        for x in 0..TILE {
+            let hash = (x * 7 + y * 13 + seed).wrapping_mul(2654435761) >> 28;
+            let [r, g, b] = if hash < 3 { detail } else { base };
+            pixels.extend([r, g, b, 255]);
+        }
+    }
+    Image {
+        width: TILE,
+        height: TILE,
+        pixels,
+    }
+}
+
+/// Which of [`TILES`] covers map cell `(x, y)`.
+fn terrain(x: u32, y: u32) -> usize {
+    let (fx, fy) = (x as f32 * 0.15, y as f32 * 0.2);
+    let height = fx.sin() + fy.cos() + (fx * 0.5 + fy * 0.7).sin();
+    match height {
+        h if h < -0.4 => 0,
+        h if h < 1.2 => 1,
+        _ => 2,
+    }
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let camera = Camera2D::pixel_perfect(1, 1, 3);
+        let mut builder = AtlasBuilder::new(64).filter(camera.texture_filter());
+        builder.add("water", tile([40, 80, 170], [90, 140, 220], 1));
+        builder.add("grass", tile([60, 140, 60], [100, 180, 80], 2));
+        builder.add("stone", tile([110, 110, 120], [70, 70, 80], 3));
+        Ok(Demo {
+            batch: SpriteBatch::new(4096)?,
+            atlas: builder.build()?,
+            camera,
+            cursor: SoftwareCursor::new(&arrow(), (0, 0))?,
+            input: Input::new(),
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.camera.set_viewport(width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.camera.update(&self.input);
+        self.cursor.scale = self.camera.effective_zoom();
+        self.input.end_frame();
+    }
+
+    fn update_window(&mut self, window: &mut dyn Window) {
+        self.cursor.update_window(window);
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.05, 0.05, 0.08, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        let hovered = self
+            .input
+            .cursor()
+            .map(|cursor| (self.camera.screen_to_world(cursor) / TILE as f32).floor());
+        // Only the cells in view are drawn.
+        let visible = self.camera.visible();
+        let size = TILE as f32;
+        let cells = |start: f32, extent: f32, count: u32| {
+            let first = (start / size).floor().max(0.0) as u32;
+            let end = ((start + extent) / size).ceil().clamp(0.0, count as f32) as u32;
+            first..end.max(first)
+        };
+        let x_range = cells(visible.x, visible.width, MAP_WIDTH);
+        let y_range = cells(visible.y, visible.height, MAP_HEIGHT);
+
+        self.batch.begin(self.camera.projection());
+        for y in y_range {
+            for x in x_range.clone() {
+                let cell = Vec2::new(x as f32, y as f32);
+                let sprite = Sprite {
+                    color: if hovered == Some(cell) {
+                        Vec4::new(1.6, 1.6, 1.6, 1.0)
+                    } else {
+                        Vec4::ONE
+                    },
+                    ..Sprite::new(cell * size, Vec2::splat(size))
+                };
+                self.batch
+                    .draw_region(&self.atlas, TILES[terrain(x, y)], &sprite);
+            }
+        }
+        self.batch.end();
+
+        let viewport = self.camera.viewport();
+        self.cursor
+            .draw(self.input.cursor(), viewport.x as u32, viewport.y as u32);
+    }
+}
+
+fn main() {
+    app::run("Pixel art", |_| Demo::new());
+}
@@ -0,0 +1,215 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::WindowEvent;
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::Light;
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::shadow::{PointShadowMap, ShadowMap, POINT_SHADOW_GLSL, SHADOW_GLSL};
+use hello_gl::viewport::OrbitCamera;
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+uniform mat4 u_view_projection;
+uniform mat4 u_model;
+uniform mat4 u_light_space;
+out vec3 v_normal;
+out vec3 v_world;
+out vec4 v_light_space_pos;
+void main() {
+    vec4 world = u_model * vec4(pos, 1.0);
+    v_normal = mat3(u_model) * normal;
+    v_world = world.xyz;
+    v_light_space_pos = u_light_space * world;
+    gl_Position = u_view_projection * world;
+}
+"#;
+
+const FRAG_SHADER: &str = r#"
+in vec3 v_normal;
+in vec3 v_world;
+in vec4 v_light_space_pos;
+out vec4 final_color;
+uniform vec3 u_color;
+uniform vec3 u_point_position;
+uniform float u_point_range;
+uniform vec3 u_spot_position;
+uniform vec3 u_spot_direction;
+uniform float u_spot_cos_outer;
+void main() {
+    vec3 n = normalize(v_normal);
+
+    vec3 to_point = u_point_position - v_world;
+    float point_falloff = max(1.0 - length(to_point) / u_point_range, 0.0);
+    float point = max(dot(n, normalize(to_point)), 0.0) * point_falloff
+        * point_shadow_factor(v_world, n);
+
+    vec3 spot_dir = normalize(v_world - u_spot_position);
+    float cone = smoothstep(u_spot_cos_outer, u_spot_cos_outer + 0.05,
+        dot(spot_dir, normalize(u_spot_direction)));
+    float spot = max(dot(n, -spot_dir), 0.0) * cone
+        * shadow_factor(v_light_space_pos, n, spot_dir);
+
+    vec3 light = vec3(1.0, 0.8, 0.6) * point + vec3(0.5, 0.7, 1.0) * spot;
+    final_color = vec4(u_color * (0.05 + light), 1.0);
+}
+"#;
+
+/// A ring of blocks around an orbiting point light, with a spot light shining in from
+/// above. The point light's shadows fall outwards in every direction. Drag to orbit and
+/// scroll to zoom.
+struct Demo {
+    program: Program,
+    point_shadow: PointShadowMap,
+    spot_shadow: ShadowMap,
+    ground: Mesh,
+    cube: Mesh,
+    input: Input,
+    orbit: OrbitCamera,
+    width: i32,
+    height: i32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let fragment = format!(
+            "#version 330 core\n{}\n{}\n{}",
+            SHADOW_GLSL, POINT_SHADOW_GLSL, FRAG_SHADER
+        );
+        let point_shadow = PointShadowMap::new(1024)?;
+        let passes = if point_shadow.is_layered() {
+            "single pass"
+        } else {
+            "one pass per face"
+        };
+        println!("Point shadows: {}", passes);
+        Ok(Demo {
+            program: Program::from_sources(VERT_SHADER, &fragment)?,
+            point_shadow,
+            spot_shadow: ShadowMap::new(1024)?,
+            ground: Mesh::plane(20.0)?,
+            cube: Mesh::cube(1.0)?,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 14.0),
+            width: 1,
+            height: 1,
+            time: 0.0,
+        })
+    }
+
+    fn lights(&self) -> [Light; 2] {
+        let t = self.time * 0.5;
+        [
+            Light::Point {
+                position: Vec3::new(t.cos() * 1.5, 1.2, t.sin() * 1.5),
+                color: Vec3::new(1.0, 0.8, 0.6),
+                intensity: 1.0,
+                range: 12.0,
+            },
+            Light::Spot {
+                position: Vec3::new(6.0, 8.0, 6.0),
+                direction: Vec3::new(-1.0, -1.5, -1.0),
+                color: Vec3::new(0.5, 0.7, 1.0),
+                intensity: 1.0,
+                range: 25.0,
+                inner_angle: 0.3,
+                outer_angle: 0.4,
+            },
+        ]
+    }
+
+    fn draw_scene(&self, program: &Program) {
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        program.set_vec3("u_color", [0.7, 0.7, 0.7]);
+        self.ground.draw();
+
+        program.set_vec3("u_color", [0.9, 0.5, 0.2]);
+        for i in 0..8 {
+            let angle = i as f32 / 8.0 * std::f32::consts::TAU;
+            let model = Mat4::from_translation(Vec3::new(angle.cos(), 0.5, angle.sin()) * 4.0)
+                * Mat4::from_rotation_y(-angle)
+                * Mat4::from_scale(Vec3::new(0.6, 1.0 + (i % 3) as f32, 0.6));
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.cube.draw();
+        }
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width.max(1) as i32;
+        self.height = height.max(1) as i32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let [point, spot] = self.lights();
+        self.point_shadow.set_light(&point).unwrap();
+        self.spot_shadow.set_light(&spot, Vec3::ZERO, 0.0).unwrap();
+
+        for pass in 0..self.point_shadow.passes() {
+            self.point_shadow.begin(pass);
+            self.draw_scene(self.point_shadow.depth_program());
+            self.point_shadow.end();
+        }
+        self.spot_shadow.begin();
+        self.draw_scene(self.spot_shadow.depth_program());
+        self.spot_shadow.end();
+
+        let camera = self.orbit.camera();
+        let aspect = self.width as f32 / self.height as f32;
+        unsafe {
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.08, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.program.set_mat4(
+            "u_view_projection",
+            &(camera.projection(aspect) * camera.view()).to_cols_array(),
+        );
+        if let Light::Point {
+            position, range, ..
+        } = point
+        {
+            self.program
+                .set_vec3("u_point_position", position.to_array());
+            self.program.set_float("u_point_range", range);
+        }
+        if let Light::Spot {
+            position,
+            direction,
+            outer_angle,
+            ..
+        } = spot
+        {
+            self.program
+                .set_vec3("u_spot_position", position.to_array());
+            self.program
+                .set_vec3("u_spot_direction", direction.to_array());
+            self.program
+                .set_float("u_spot_cos_outer", outer_angle.cos());
+        }
+        self.point_shadow.apply(&self.program, 0);
+        self.spot_shadow.apply(&self.program, 1);
+        self.draw_scene(&self.program);
+    }
+}
+
+fn main() {
+    app::run("Point and spot light shadows", |_| Demo::new());
+}
@@ -0,0 +1,210 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::WindowEvent;
+use hello_gl::framebuffer::RenderTarget;
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::portal::{self, Mirror, Portal};
+use hello_gl::viewport::OrbitCamera;
+
+/// Something to look through: a mirror or one side of a portal pair.
+enum Opening {
+    Mirror(Mirror),
+    Portal(Portal),
+}
+
+impl Opening {
+    fn faces(&self, eye: Vec3) -> bool {
+        match self {
+            Opening::Mirror(mirror) => mirror.faces(eye),
+            Opening::Portal(portal) => portal.faces(eye),
+        }
+    }
+
+    /// The view projection and eye of the virtual camera.
+    fn camera(&self, view: Mat4, projection: Mat4, eye: Vec3) -> (Mat4, Vec3) {
+        match self {
+            Opening::Mirror(mirror) => (
+                mirror.projection(view, projection) * mirror.view(view),
+                mirror.eye(eye),
+            ),
+            Opening::Portal(portal) => (
+                portal.projection(view, projection) * portal.view(view),
+                portal.eye(eye),
+            ),
+        }
+    }
+}
+
+/// A courtyard of colored blocks with a mirror at the back and a pair of portals on the
+/// sides, each showing the courtyard as seen from the other. The openings are masked
+/// in the stencil buffer and clipped with oblique projections. Drag to orbit and scroll
+/// to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    ground: Mesh,
+    cube: Mesh,
+    /// A unit square facing +Z, scaled into each opening's shape.
+    square: Mesh,
+    openings: Vec<(Opening, Mat4)>,
+    target: RenderTarget,
+    input: Input,
+    orbit: OrbitCamera,
+    size: (u32, u32),
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let facing = Mat4::from_rotation_x(FRAC_PI_2);
+        let mirror = Mat4::from_translation(Vec3::new(0.0, 1.5, -6.0));
+        let entrance =
+            Mat4::from_translation(Vec3::new(-6.0, 1.5, 0.0)) * Mat4::from_rotation_y(FRAC_PI_2);
+        let exit =
+            Mat4::from_translation(Vec3::new(6.0, 1.5, 2.0)) * Mat4::from_rotation_y(-FRAC_PI_2);
+        let portal = Portal::new(entrance, exit);
+        let shape =
+            |frame: Mat4, width: f32| frame * facing * Mat4::from_scale(Vec3::new(width, 1.0, 3.0));
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            ground: Mesh::plane(16.0)?,
+            cube: Mesh::cube(1.0)?,
+            square: Mesh::plane(1.0)?,
+            openings: vec![
+                (
+                    Opening::Mirror(Mirror::new(Vec3::new(0.0, 1.5, -6.0), Vec3::Z)),
+                    shape(mirror, 5.0),
+                ),
+                (Opening::Portal(portal), shape(entrance, 2.0)),
+                (Opening::Portal(portal.reversed()), shape(exit, 2.0)),
+            ],
+            target: RenderTarget::with_stencil(1, 1, 0, gl::RGBA8)?,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::new(0.0, 1.0, 0.0), 9.0),
+            size: (1, 1),
+            time: 0.0,
+        })
+    }
+
+    fn draw_scene(&self, view_projection: Mat4, eye: Vec3) {
+        self.shaders.set_camera(view_projection, eye);
+        let program = self.shaders.bind(&Material::default());
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.ground.draw();
+        for i in 0..6 {
+            let angle = i as f32 / 6.0 * 2.0 * PI + self.time * 0.3;
+            let hue = i as f32 / 6.0;
+            let color = Vec4::new(
+                0.5 + 0.5 * (hue * 2.0 * PI).cos(),
+                0.5 + 0.5 * (hue * 2.0 * PI + 2.0).cos(),
+                0.5 + 0.5 * (hue * 2.0 * PI + 4.0).cos(),
+                1.0,
+            );
+            let program = self.shaders.bind(&Material::pbr(color, 0.0, 0.5));
+            let model = Mat4::from_translation(Vec3::new(
+                angle.cos() * 3.0,
+                0.5 + (i % 2) as f32,
+                angle.sin() * 3.0,
+            )) * Mat4::from_rotation_y(angle);
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.cube.draw();
+        }
+    }
+
+    fn draw_shape(&self, shape: Mat4) {
+        let program = self.shaders.bind(&Material::default());
+        program.set_mat4("u_model", &shape.to_cols_array());
+        self.square.draw();
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        let (w, h) = (width.max(1) as i32, height.max(1) as i32);
+        self.target = RenderTarget::with_stencil(w, h, 0, gl::RGBA8).unwrap();
+        self.size = (width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.3),
+            color: Vec3::ONE,
+            intensity: 2.5,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.15)).unwrap();
+
+        let (width, height) = self.size;
+        let camera = self.orbit.camera();
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        let (view, projection) = (camera.view(), camera.projection_for_context(aspect));
+        let view_projection = projection * view;
+
+        self.target.bind();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::CULL_FACE);
+            gl::ClearColor(0.55, 0.7, 0.9, 1.0);
+            gl::ClearStencil(0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+
+        // What each opening shows, inside its own stencil value.
+        let visible: Vec<_> = self
+            .openings
+            .iter()
+            .filter(|(opening, _)| opening.faces(camera.eye))
+            .collect();
+        for (i, (opening, shape)) in visible.iter().enumerate() {
+            let reference = i as u8 + 1;
+            self.shaders.set_camera(view_projection, camera.eye);
+            portal::write_mask(reference);
+            self.draw_shape(*shape);
+
+            portal::test_mask(reference);
+            let (virtual_view_projection, eye) = opening.camera(view, projection, camera.eye);
+            let mirrored = matches!(opening, Opening::Mirror(_));
+            portal::set_reflected(mirrored);
+            self.draw_scene(virtual_view_projection, eye);
+            portal::set_reflected(false);
+            unsafe {
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+            }
+        }
+
+        // Seal the openings so the scene behind them stays hidden, then draw the rest.
+        self.shaders.set_camera(view_projection, camera.eye);
+        portal::write_depth_only();
+        for (_, shape) in &visible {
+            self.draw_shape(*shape);
+        }
+        portal::end_mask();
+        self.draw_scene(view_projection, camera.eye);
+
+        unsafe {
+            gl::Disable(gl::CULL_FACE);
+        }
+        self.target.framebuffer.unbind(gl::FRAMEBUFFER);
+        self.target.present(width, height);
+    }
+}
+
+fn main() {
+    app::run("Mirrors and portals", |_| Demo::new());
+}
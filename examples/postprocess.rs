@@ -0,0 +1,119 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::buffer::{Buffer, VertexArray};
+use hello_gl::gl;
+use hello_gl::postprocess::{Pass, PostProcess};
+use hello_gl::shader::Program;
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 pos;
+layout (location = 1) in vec3 color;
+out vec3 v_color;
+void main() {
+    v_color = color;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+"#;
+
+const FRAG_SHADER: &str = r#"#version 330 core
+in vec3 v_color;
+out vec4 final_color;
+void main() {
+    final_color = vec4(v_color, 1.0);
+}
+"#;
+
+struct Demo {
+    _vb: Buffer,
+    va: VertexArray,
+    program: Program,
+    post: PostProcess,
+}
+
+impl Demo {
+    fn new(width: i32, height: i32) -> Result<Demo> {
+        #[rustfmt::skip]
+        const VERTICES: [f32; 15] = [
+            -0.5, -0.5, 1.0, 0.0, 0.0,
+             0.5, -0.5, 0.0, 1.0, 0.0,
+             0.0,  0.5, 0.0, 0.0, 1.0,
+        ];
+
+        let va = VertexArray::new()?;
+        va.bind();
+        let vb = Buffer::new()?;
+        vb.bind(gl::ARRAY_BUFFER);
+        vb.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&VERTICES),
+            gl::STATIC_DRAW,
+        );
+        unsafe {
+            let stride = 5 * std::mem::size_of::<f32>() as i32;
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, (2 * 4) as *const _);
+            gl::EnableVertexAttribArray(1);
+        }
+        va.unbind();
+
+        let mut post = PostProcess::new(width, height)?;
+        post.push(Pass::grayscale()?);
+        post.push(Pass::vignette()?);
+        post.push(Pass::fxaa()?);
+
+        Ok(Demo {
+            _vb: vb,
+            va,
+            program: Program::from_sources(VERT_SHADER, FRAG_SHADER)?,
+            post,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.post.resize(width as i32, height as i32).unwrap();
+    }
+
+    fn window_event(&mut self, event: &hello_gl::event::WindowEvent) {
+        use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+
+        // Keys 1-3 toggle the individual passes.
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state != ElementState::Pressed {
+                return;
+            }
+            let index = match input.key {
+                Some(KeyCode::Digit1) => 0,
+                Some(KeyCode::Digit2) => 1,
+                Some(KeyCode::Digit3) => 2,
+                _ => return,
+            };
+            let pass = &mut self.post.passes_mut()[index];
+            pass.enabled = !pass.enabled;
+            println!("{}: {}", pass.name(), pass.enabled);
+        }
+    }
+
+    fn render(&mut self) {
+        self.post.begin();
+        unsafe {
+            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.va.bind();
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.post.end();
+    }
+}
+
+fn main() {
+    app::run("Post-processing", |window| {
+        let size = window.inner_size();
+        Demo::new(size.width as i32, size.height as i32)
+    });
+}
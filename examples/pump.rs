@@ -0,0 +1,58 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use hello_gl::app::{App, ContextConfig, PumpStatus, Runner};
+use hello_gl::gl;
+use winit::event_loop::EventLoop;
+
+/// A clear color cycling through hues, driven by a host loop that owns the event loop
+/// and does its own work between frames, as an editor or game engine embedding the
+/// renderer would.
+struct Demo {
+    time: f32,
+}
+
+impl App for Demo {
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let hue = self.time * 0.5;
+        unsafe {
+            gl::ClearColor(
+                0.5 + 0.5 * hue.sin(),
+                0.5 + 0.5 * (hue + 2.1).sin(),
+                0.5 + 0.5 * (hue + 4.2).sin(),
+                1.0,
+            );
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let mut event_loop = EventLoop::new()?;
+    let config = ContextConfig::from_settings();
+    let mut runner = Runner::new(&event_loop, "Pumped event loop", &config, |_| {
+        Ok(Demo { time: 0.0 })
+    })?;
+
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut frames = 0;
+    loop {
+        match runner.pump_events(&mut event_loop) {
+            PumpStatus::Continue => {}
+            PumpStatus::Exit => break,
+            PumpStatus::Error(e) => return Err(e),
+        }
+        // The host's own work for this iteration.
+        frames += 1;
+        if last_report.elapsed() >= Duration::from_secs(1) {
+            println!("{} frames in {:.1?}", frames, start.elapsed());
+            last_report = Instant::now();
+        }
+    }
+    Ok(())
+}
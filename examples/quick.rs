@@ -0,0 +1,38 @@
+use hello_gl::math::Vec2;
+use hello_gl::quick::{self, rgb, rgba};
+
+/// The whole program is one closure: a sun, an orbiting planet, a line from the sun to
+/// the cursor and a caption.
+fn main() {
+    quick::run_titled("Quick", |frame| {
+        frame.clear(rgb(0.05, 0.05, 0.1));
+        let center = frame.size() * 0.5;
+        frame.circle(center, 60.0, rgb(1.0, 0.8, 0.3));
+
+        let angle = frame.time() * 0.8;
+        let planet = center + Vec2::new(angle.cos(), angle.sin()) * 200.0;
+        frame.circle(planet, 20.0, rgb(0.3, 0.6, 1.0));
+        frame.triangle(
+            planet + Vec2::new(0.0, -40.0),
+            planet + Vec2::new(-8.0, -24.0),
+            planet + Vec2::new(8.0, -24.0),
+            rgb(0.9, 0.9, 0.9),
+        );
+
+        if let Some(mouse) = frame.mouse() {
+            frame.line(center, mouse, 3.0, rgba(1.0, 1.0, 1.0, 0.5));
+        }
+        frame.rect(
+            Vec2::new(10.0, 10.0),
+            Vec2::new(300.0, 44.0),
+            rgba(0.0, 0.0, 0.0, 0.6),
+        );
+        let fps = 1.0 / frame.dt().max(1e-6);
+        frame.text(
+            &format!("Frame {} at {:.0} fps", frame.frame(), fps),
+            Vec2::new(20.0, 18.0),
+            24.0,
+            rgb(1.0, 1.0, 1.0),
+        );
+    });
+}
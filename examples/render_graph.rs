@@ -0,0 +1,120 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::graph::{RenderGraph, TextureDesc, TransientPool};
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Quat, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::postprocess::{FullscreenTriangle, Pass, Tonemap};
+
+/// Scene into an HDR target, tonemapped to the window. An unused "debug" pass is culled;
+/// run with `--dot` to print the graph once.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cube: Mesh,
+    material: Material,
+    tonemap: Pass,
+    triangle: FullscreenTriangle,
+    pool: TransientPool,
+    width: i32,
+    height: i32,
+    time: f32,
+    dump_dot: bool,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cube: Mesh::cube(1.0)?,
+            material: Material::pbr(Vec4::new(0.9, 0.5, 0.2, 1.0), 0.0, 0.4),
+            tonemap: Pass::tonemap(Tonemap::Aces, 1.0)?,
+            triangle: FullscreenTriangle::new()?,
+            pool: TransientPool::new(),
+            width: 1,
+            height: 1,
+            time: 0.0,
+            dump_dot: std::env::args().any(|arg| arg == "--dot"),
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as i32;
+        self.height = height as i32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let mut graph = RenderGraph::new();
+        let color = graph.create_texture("hdr", TextureDesc::new(width, height, gl::RGBA16F));
+        let depth = graph.create_texture(
+            "depth",
+            TextureDesc::new(width, height, gl::DEPTH_COMPONENT24),
+        );
+        let debug = graph.create_texture("debug", TextureDesc::new(width, height, gl::RGBA8));
+        let backbuffer = graph.backbuffer(width, height);
+
+        // The passes borrow the demo while the graph runs, so the pool is moved out.
+        let mut pool = std::mem::take(&mut self.pool);
+        let this = &*self;
+        graph.add_pass("tonemap", &[color], &[backbuffer], move |ctx| {
+            let program = this.tonemap.program();
+            program.use_program();
+            ctx.texture(color).bind_unit(0);
+            program.set_int("u_input", 0);
+            program.set_int("u_operator", 1);
+            program.set_float("u_exposure", 1.0);
+            this.triangle.draw();
+        });
+        graph.add_pass("scene", &[], &[color, depth], move |ctx| {
+            let (width, height) = ctx.size();
+            let lights = [Light::Directional {
+                direction: Vec3::new(-0.3, -1.0, -0.5),
+                color: Vec3::ONE,
+                intensity: 6.0,
+            }];
+            this.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+            let eye = Vec3::new(0.0, 1.5, 4.0);
+            let projection = Mat4::perspective_rh_gl(
+                45f32.to_radians(),
+                width as f32 / height as f32,
+                0.1,
+                100.0,
+            );
+            this.shaders
+                .set_camera(projection * Mat4::look_at_rh(eye, Vec3::ZERO, Vec3::Y), eye);
+            unsafe {
+                gl::Enable(gl::DEPTH_TEST);
+                gl::ClearColor(0.1, 0.1, 0.15, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            let model = Mat4::from_quat(Quat::from_rotation_y(this.time));
+            let program = this.shaders.bind(&this.material);
+            program.set_mat4("u_model", &model.to_cols_array());
+            this.cube.draw();
+            unsafe {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+        });
+        graph.add_pass("debug", &[depth], &[debug], |_| {});
+
+        if this.dump_dot {
+            print!("{}", graph.to_dot());
+        }
+        graph.execute(&mut pool).unwrap();
+        self.pool = pool;
+        self.dump_dot = false;
+    }
+}
+
+fn main() {
+    app::run("Render graph", |_| Demo::new());
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use hello_gl::app::{self, App, ContextConfig};
+use hello_gl::context::ContextOptions;
+use hello_gl::depth;
+use hello_gl::framebuffer::RenderTarget;
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::viewport::Camera;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+uniform mat4 u_mvp;
+void main() {
+    gl_Position = u_mvp * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform vec4 u_color;
+out vec4 frag_color;
+void main() {
+    frag_color = u_color;
+}
+"#;
+
+/// Pairs of squares receding from 1 to over 50000 units, each blue square a ten-thousandth
+/// of its distance in front of a red one, seen through a 0.01 near plane. Reversed-Z
+/// with a float depth buffer keeps every blue square in front; run with `--standard`
+/// to watch the distant pairs z-fight instead.
+struct Demo {
+    plane: Mesh,
+    program: Program,
+    target: RenderTarget,
+    size: (u32, u32),
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        println!(
+            "Depth: {}",
+            if depth::reversed_z() {
+                "reversed-Z, 32-bit float"
+            } else {
+                "standard, 24-bit"
+            }
+        );
+        Ok(Demo {
+            plane: Mesh::plane(1.0)?,
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            target: RenderTarget::new(1, 1, gl::RGBA8, true)?,
+            size: (1, 1),
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        let (w, h) = (width.max(1) as i32, height.max(1) as i32);
+        self.target = RenderTarget::new(w, h, gl::RGBA8, true).unwrap();
+        self.size = (width, height);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let (width, height) = self.size;
+        let camera = Camera {
+            near: 0.01,
+            far: 100_000.0,
+            ..Camera::look_at(
+                Vec3::new(self.time.sin() * 0.2, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            )
+        };
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        let view_projection = camera.projection_for_context(aspect) * camera.view();
+
+        self.target.bind();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.08, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        let facing = Mat4::from_rotation_x(std::f32::consts::FRAC_PI_2);
+        for i in 0..16 {
+            let distance = 2f32.powf(i as f32 * 1.05);
+            // Side by side, each pair a few degrees apart.
+            let x = (i as f32 - 7.5) * distance * 0.06;
+            for (offset, color) in [(0.0, [0.9, 0.2, 0.2, 1.0]), (1e-4, [0.2, 0.4, 1.0, 1.0])] {
+                let model = Mat4::from_translation(Vec3::new(x, 0.0, -distance * (1.0 - offset)))
+                    * Mat4::from_scale(Vec3::splat(distance * 0.05))
+                    * facing;
+                self.program
+                    .set_mat4("u_mvp", &(view_projection * model).to_cols_array());
+                self.program.set_vec4("u_color", color);
+                self.plane.draw();
+            }
+        }
+        self.target.framebuffer.unbind(gl::FRAMEBUFFER);
+        self.target.present(width, height);
+    }
+}
+
+fn main() {
+    let standard = std::env::args().any(|arg| arg == "--standard");
+    let config = ContextConfig {
+        options: ContextOptions {
+            reversed_z: !standard,
+            ..ContextOptions::default()
+        },
+        ..ContextConfig::from_settings()
+    };
+    app::run_with("Reversed-Z depth", &config, |_| Demo::new());
+}
@@ -0,0 +1,209 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::arena::FrameArena;
+use hello_gl::debug_draw::DebugDraw;
+use hello_gl::draw::DrawList;
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Quat, Transform, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::scene::{Drawable, NodeId, Scene};
+use hello_gl::scene_file::{Resources, SceneFile};
+use hello_gl::state::StateCache;
+use hello_gl::viewport::Camera;
+
+/// A sun with an orbiting planet, which in turn has an orbiting moon. Only the pivots'
+/// local rotations are animated; the scene graph composes the rest.
+///
+/// Given a path, the scene is loaded from it if it exists, and S saves the current
+/// arrangement there as RON, or JSON for a `.json` path.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    resources: Resources,
+    file: SceneFile,
+    path: Option<PathBuf>,
+    scene: Scene,
+    cache: StateCache,
+    arena: FrameArena,
+    debug: DebugDraw,
+    planet_pivot: Option<NodeId>,
+    moon_pivot: Option<NodeId>,
+    aspect: f32,
+    time: f32,
+}
+
+/// The scene when no file is loaded.
+fn solar_system(cube: &Rc<Mesh>) -> Scene {
+    let body = |color: Vec4| Drawable::new(cube.clone(), Rc::new(Material::pbr(color, 0.0, 0.5)));
+
+    let mut scene = Scene::new();
+    let sun = scene.add_drawable(
+        None,
+        Some("sun"),
+        Transform {
+            scale: Vec3::splat(1.5),
+            ..Transform::IDENTITY
+        },
+        body(Vec4::new(1.0, 0.8, 0.2, 1.0)),
+    );
+    let planet_pivot = scene.add(Some(sun), Some("planet pivot"), Transform::IDENTITY);
+    let planet = scene.add_drawable(
+        Some(planet_pivot),
+        Some("planet"),
+        Transform {
+            translation: Vec3::new(3.0, 0.0, 0.0),
+            scale: Vec3::splat(0.4),
+            ..Transform::IDENTITY
+        },
+        body(Vec4::new(0.2, 0.4, 1.0, 1.0)),
+    );
+    let moon_pivot = scene.add(Some(planet), Some("moon pivot"), Transform::IDENTITY);
+    scene.add_drawable(
+        Some(moon_pivot),
+        Some("moon"),
+        Transform {
+            translation: Vec3::new(2.5, 0.0, 0.0),
+            scale: Vec3::splat(0.4),
+            ..Transform::IDENTITY
+        },
+        body(Vec4::new(0.7, 0.7, 0.7, 1.0)),
+    );
+    scene
+}
+
+impl Demo {
+    fn new(path: Option<PathBuf>) -> Result<Demo> {
+        let cube = Rc::new(Mesh::cube(1.0)?);
+        let mut resources = Resources::new();
+        resources.add_mesh("cube", cube.clone());
+
+        let (file, scene) = match &path {
+            Some(path) if path.exists() => {
+                let file = SceneFile::load(path)?;
+                let scene = file.to_scene(&resources)?;
+                (file, scene)
+            }
+            _ => {
+                let scene = solar_system(&cube);
+                let file = SceneFile {
+                    camera: Some(Camera::look_at(Vec3::new(0.0, 6.0, 10.0), Vec3::ZERO)),
+                    lights: vec![Light::Directional {
+                        direction: Vec3::new(-0.3, -1.0, -0.5),
+                        color: Vec3::ONE,
+                        intensity: 3.0,
+                    }],
+                    ..SceneFile::from_scene(&scene, &resources)?
+                };
+                (file, scene)
+            }
+        };
+
+        let mut debug = DebugDraw::new()?;
+        debug.line_width = 2.0;
+        debug.smooth = true;
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            resources,
+            path,
+            planet_pivot: scene.find("planet pivot"),
+            moon_pivot: scene.find("moon pivot"),
+            file,
+            scene,
+            cache: StateCache::new(),
+            arena: FrameArena::default(),
+            debug,
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            println!("Pass a path to save the scene to");
+            return Ok(());
+        };
+        let file = SceneFile {
+            nodes: SceneFile::from_scene(&self.scene, &self.resources)?.nodes,
+            ..self.file.clone()
+        };
+        file.save(path)?;
+        println!("Saved {}", path.display());
+        Ok(())
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyS) {
+                if let Err(e) = self.save() {
+                    eprintln!("{:#}", e);
+                }
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        if let Some(pivot) = self.planet_pivot {
+            self.scene.local_mut(pivot).rotation = Quat::from_rotation_y(self.time * 0.5);
+        }
+        if let Some(pivot) = self.moon_pivot {
+            self.scene.local_mut(pivot).rotation = Quat::from_rotation_y(self.time * 2.0);
+        }
+        self.scene.update();
+    }
+
+    fn render(&mut self) {
+        self.lights
+            .upload(&self.file.lights, self.file.ambient)
+            .unwrap();
+        let camera = self
+            .file
+            .camera
+            .unwrap_or_else(|| Camera::look_at(Vec3::new(0.0, 6.0, 10.0), Vec3::ZERO));
+        let view_projection = camera.projection(self.aspect) * camera.view();
+        self.shaders.set_camera(view_projection, camera.eye);
+        // set_camera binds programs behind the cache's back.
+        self.cache.invalidate();
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.02, 0.02, 0.05, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.arena.reset();
+        let mut list = DrawList::new_in(view_projection, &self.arena);
+        self.scene.record(&mut list, &self.shaders, None);
+        list.submit(&self.shaders, &mut self.cache);
+
+        self.debug.grid(20.0, 20, Vec3::splat(0.3));
+        for (id, node) in self.scene.iter() {
+            if let Some(bounds) = self.scene.world_bounds(id) {
+                self.debug.aabb(&bounds, Vec3::new(1.0, 1.0, 0.0));
+                self.debug.axes(node.world(), 1.0);
+                self.debug
+                    .point(node.world().transform_point3(Vec3::ZERO), Vec3::ONE);
+            }
+        }
+        self.debug.flush(view_projection);
+    }
+}
+
+fn main() {
+    let path = std::env::args().nth(1).map(PathBuf::from);
+    app::run("Scene graph", move |_| Demo::new(path));
+}
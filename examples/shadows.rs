@@ -0,0 +1,121 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::shadow::{ShadowMap, SHADOW_GLSL};
+
+const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec3 normal;
+uniform mat4 u_view_projection;
+uniform mat4 u_model;
+uniform mat4 u_light_space;
+out vec3 v_normal;
+out vec4 v_light_space_pos;
+void main() {
+    vec4 world = u_model * vec4(pos, 1.0);
+    v_normal = mat3(u_model) * normal;
+    v_light_space_pos = u_light_space * world;
+    gl_Position = u_view_projection * world;
+}
+"#;
+
+const FRAG_SHADER: &str = r#"
+in vec3 v_normal;
+in vec4 v_light_space_pos;
+out vec4 final_color;
+uniform vec3 u_light_dir;
+uniform vec3 u_color;
+void main() {
+    vec3 n = normalize(v_normal);
+    float diffuse = max(dot(n, -normalize(u_light_dir)), 0.0);
+    float lit = shadow_factor(v_light_space_pos, n, u_light_dir);
+    final_color = vec4(u_color * (0.15 + 0.85 * diffuse * lit), 1.0);
+}
+"#;
+
+struct Demo {
+    program: Program,
+    shadow: ShadowMap,
+    ground: Mesh,
+    cube: Mesh,
+    light_dir: Vec3,
+    aspect: f32,
+    width: i32,
+    height: i32,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let fragment = format!("#version 330 core\n{}\n{}", SHADOW_GLSL, FRAG_SHADER);
+        let light_dir = Vec3::new(-0.5, -1.0, -0.3).normalize();
+        let mut shadow = ShadowMap::new(2048)?;
+        shadow.set_directional(light_dir, Vec3::ZERO, 6.0);
+
+        Ok(Demo {
+            program: Program::from_sources(VERT_SHADER, &fragment)?,
+            shadow,
+            ground: Mesh::plane(10.0)?,
+            cube: Mesh::cube(1.0)?,
+            light_dir,
+            aspect: 1.0,
+            width: 1,
+            height: 1,
+            time: 0.0,
+        })
+    }
+
+    fn draw_scene(&self, program: &Program) {
+        let cube = Mat4::from_translation(Vec3::new(0.0, 1.0, 0.0))
+            * Mat4::from_rotation_y(self.time)
+            * Mat4::from_rotation_x(self.time * 0.7);
+        program.set_mat4("u_model", &cube.to_cols_array());
+        program.set_vec3("u_color", [0.9, 0.5, 0.2]);
+        self.cube.draw();
+
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        program.set_vec3("u_color", [0.7, 0.7, 0.7]);
+        self.ground.draw();
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width as i32;
+        self.height = height as i32;
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        self.shadow.begin();
+        self.draw_scene(self.shadow.depth_program());
+        self.shadow.end();
+
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 100.0);
+        let view = Mat4::look_at_rh(Vec3::new(4.0, 4.0, 6.0), Vec3::ZERO, Vec3::Y);
+        unsafe {
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &(projection * view).to_cols_array());
+        self.program
+            .set_vec3("u_light_dir", self.light_dir.to_array());
+        self.shadow.apply(&self.program, 0);
+        self.draw_scene(&self.program);
+    }
+}
+
+fn main() {
+    app::run("Shadow mapping", |_| Demo::new());
+}
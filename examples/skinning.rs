@@ -0,0 +1,98 @@
+use hello_gl::animation::JointBuffer;
+use hello_gl::app::{self, App};
+use hello_gl::gl;
+use hello_gl::gltf::{self, GltfScene};
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3};
+
+/// Plays the first animation of a skinned glTF character, e.g. Khronos' CesiumMan or Fox.
+struct Character {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    joints: JointBuffer,
+    scene: GltfScene,
+    fallback: Material,
+    aspect: f32,
+    time: f32,
+}
+
+impl App for Character {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.6),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+
+        let eye = Vec3::new(0.0, 1.5, 4.0);
+        let projection = Mat4::perspective_rh_gl(45f32.to_radians(), self.aspect, 0.1, 1000.0);
+        let view = Mat4::look_at_rh(eye, Vec3::new(0.0, 0.8, 0.0), Vec3::Y);
+        self.shaders.set_camera(projection * view, eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let mut pose = self.scene.rest_pose();
+        if let Some(clip) = self.scene.animations.first() {
+            clip.sample_looped(self.time, &mut pose);
+        }
+        let world = self.scene.world_matrices(&pose);
+
+        for (index, node) in self.scene.nodes.iter().enumerate() {
+            let Some(mesh) = node.mesh.map(|m| &self.scene.meshes[m]) else {
+                continue;
+            };
+            // Skinned vertices are placed by their joints, so the node's own transform
+            // is ignored, as the glTF spec requires.
+            let skinned = mesh.is_skinned() && node.skin.is_some();
+            let model = if skinned {
+                let skin = &self.scene.skins[node.skin.unwrap()];
+                self.joints.upload(&skin.joint_matrices(&world)).unwrap();
+                Mat4::IDENTITY
+            } else {
+                world[index]
+            };
+            for (i, submesh) in mesh.submeshes().iter().enumerate() {
+                let material = submesh
+                    .material
+                    .and_then(|m| self.scene.materials.get(m))
+                    .unwrap_or(&self.fallback);
+                let program = self.shaders.bind_variant(material, skinned);
+                program.set_mat4("u_model", &model.to_cols_array());
+                mesh.draw_submesh(i);
+            }
+        }
+    }
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: skinning <character.gltf|character.glb>");
+    app::run("Skeletal animation", move |_| {
+        Ok(Character {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            joints: JointBuffer::new()?,
+            scene: gltf::load(&path)?,
+            fallback: Material::default(),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    });
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, KeyboardInput, WindowEvent};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::viewport::{self, Camera};
+
+/// A player orbiting the arena, steered with its own set of keys.
+struct Player {
+    left: KeyCode,
+    right: KeyCode,
+    closer: KeyCode,
+    farther: KeyCode,
+    angle: f32,
+    distance: f32,
+    color: Vec4,
+}
+
+impl Player {
+    fn camera(&self) -> Camera {
+        let eye = Vec3::new(
+            self.angle.sin() * self.distance,
+            self.distance * 0.5,
+            self.angle.cos() * self.distance,
+        );
+        Camera::look_at(eye, Vec3::ZERO)
+    }
+
+    fn position(&self) -> Vec3 {
+        let eye = self.camera().eye;
+        Vec3::new(eye.x, 0.5, eye.z) * 0.5
+    }
+}
+
+/// Two players, WASD and the arrow keys, each with their own half of the window.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    ground: Mesh,
+    cube: Mesh,
+    players: [Player; 2],
+    pressed: HashSet<KeyCode>,
+    size: (u32, u32),
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let player = |keys: [KeyCode; 4], angle: f32, color: Vec4| Player {
+            left: keys[0],
+            right: keys[1],
+            closer: keys[2],
+            farther: keys[3],
+            angle,
+            distance: 12.0,
+            color,
+        };
+        use KeyCode::*;
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            ground: Mesh::plane(20.0)?,
+            cube: Mesh::cube(1.0)?,
+            players: [
+                player([KeyA, KeyD, KeyW, KeyS], 0.0, Vec4::new(0.9, 0.3, 0.2, 1.0)),
+                player(
+                    [ArrowLeft, ArrowRight, ArrowUp, ArrowDown],
+                    3.0,
+                    Vec4::new(0.2, 0.5, 0.9, 1.0),
+                ),
+            ],
+            pressed: HashSet::new(),
+            size: (1, 1),
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state,
+                    key: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match state {
+                ElementState::Pressed => self.pressed.insert(*key),
+                ElementState::Released => self.pressed.remove(key),
+            };
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        for player in &mut self.players {
+            let held = |key| self.pressed.contains(&key) as i32 as f32;
+            player.angle += (held(player.right) - held(player.left)) * dt * 1.5;
+            player.distance += (held(player.farther) - held(player.closer)) * dt * 5.0;
+            player.distance = player.distance.clamp(4.0, 30.0);
+        }
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.3, -1.0, -0.5),
+            color: Vec3::ONE,
+            intensity: 2.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.1)).unwrap();
+
+        let (width, height) = self.size;
+        let viewports = viewport::split_screen(width, height, self.players.len());
+        for (player, viewport) in self.players.iter().zip(&viewports) {
+            viewport.apply();
+            let camera = player.camera();
+            self.shaders
+                .set_camera(viewport.view_projection(&camera), camera.eye);
+            unsafe {
+                gl::Enable(gl::DEPTH_TEST);
+                gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+
+            let program = self.shaders.bind(&Material::default());
+            program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+            self.ground.draw();
+
+            // Each player sees both avatars.
+            for avatar in &self.players {
+                let program = self.shaders.bind(&Material::pbr(avatar.color, 0.0, 0.4));
+                let model = Mat4::from_translation(avatar.position());
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.cube.draw();
+            }
+        }
+        viewport::reset(width, height);
+    }
+}
+
+fn main() {
+    app::run("Split screen", |_| Demo::new());
+}
@@ -0,0 +1,159 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::atlas::AtlasBuilder;
+use hello_gl::gl;
+use hello_gl::image::Image;
+use hello_gl::math::{Mat4, Vec2, Vec4};
+use hello_gl::sprite::{Sprite, SpriteBatch, TextureAtlas};
+use hello_gl::texture::Texture;
+
+const SPRITES: usize = 2000;
+
+/// Thousands of rotating sprites from 16 generated images packed into one atlas, in a
+/// single draw call, over a tiled background and behind a nine-patch frame.
+struct Demo {
+    batch: SpriteBatch,
+    atlas: TextureAtlas,
+    background: Rc<Texture>,
+    size: Vec2,
+    time: f32,
+}
+
+/// A disc in one of 16 hues, growing with `index` so the packer sees mixed sizes.
+fn disc(index: u32) -> Image {
+    let size = 16 + index * 2;
+    let radius = size as f32 / 2.0;
+    let hue = index as f32 / 16.0 * std::f32::consts::TAU;
+    let channel = |offset: f32| (((hue + offset).cos() * 0.5 + 0.5) * 255.0) as u8;
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let (cx, cy) = (x as f32 + 0.5 - radius, y as f32 + 0.5 - radius);
+            let inside = cx * cx + cy * cy < radius * radius;
+            pixels.extend([
+                channel(0.0),
+                channel(2.1),
+                channel(4.2),
+                if inside { 255 } else { 0 },
+            ]);
+        }
+    }
+    Image {
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+/// A 16x16 checkerboard of two dark grays.
+fn checker() -> Image {
+    let pixels = (0..16 * 16)
+        .flat_map(|i| {
+            let shade = if (i % 16 < 8) == (i / 16 < 8) { 30 } else { 38 };
+            [shade, shade, shade, 255]
+        })
+        .collect();
+    Image {
+        width: 16,
+        height: 16,
+        pixels,
+    }
+}
+
+/// A 24x24 frame with an 8 pixel border and a translucent inside.
+fn frame() -> Image {
+    let pixels = (0..24 * 24)
+        .flat_map(|i| {
+            let (x, y) = (i % 24, i / 24);
+            let edge = x.min(y).min(23 - x).min(23 - y);
+            match edge {
+                0..=1 => [230, 200, 120, 255],
+                2..=7 => [120, 90, 50, 255],
+                _ => [0, 0, 0, 0],
+            }
+        })
+        .collect();
+    Image {
+        width: 24,
+        height: 24,
+        pixels,
+    }
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let mut builder = AtlasBuilder::new(1024);
+        for i in 0..16 {
+            builder.add(&i.to_string(), disc(i));
+        }
+        builder.add("frame", frame());
+        Ok(Demo {
+            batch: SpriteBatch::new(SPRITES)?,
+            atlas: builder.build()?,
+            background: Rc::new(Texture::from_image(&checker())?),
+            size: Vec2::ONE,
+            time: 0.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.size = Vec2::new(width as f32, height as f32);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.1, 0.1, 0.1, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.batch.begin(Mat4::orthographic_rh_gl(
+            0.0,
+            self.size.x,
+            self.size.y,
+            0.0,
+            -1.0,
+            1.0,
+        ));
+        let screen = Sprite::new(Vec2::ZERO, self.size);
+        self.batch
+            .draw_tiled(&self.background, &screen, Vec2::splat(64.0));
+        for i in 0..SPRITES {
+            let t = i as f32 * 0.618;
+            let position = Vec2::new(
+                (t * 97.0).rem_euclid(self.size.x),
+                (t * 61.0 + self.time * 40.0).rem_euclid(self.size.y),
+            );
+            let sprite = Sprite {
+                origin: Vec2::splat(0.5),
+                rotation: self.time + t,
+                color: Vec4::new(1.0, 1.0, 1.0, 0.8),
+                ..Sprite::new(position, Vec2::splat(24.0))
+            };
+            self.batch
+                .draw_region(&self.atlas, &(i % 16).to_string(), &sprite);
+        }
+        let frame = Sprite::new(Vec2::splat(16.0), self.size - 32.0);
+        self.batch
+            .draw_region_nine_patch(&self.atlas, "frame", [8; 4], &frame);
+        self.batch.end();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+fn main() {
+    app::run("Sprites", |_| Demo::new());
+}
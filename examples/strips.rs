@@ -0,0 +1,131 @@
+use anyhow::Result;
+use hello_gl::app::{self, App, ContextConfig};
+use hello_gl::context::ContextOptions;
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::{Mesh, PrimitiveMode, Vertex};
+use hello_gl::viewport::OrbitCamera;
+
+const CELLS: u32 = 48;
+
+/// A rippled grid stored the way strip-based exporters write it: one triangle strip per
+/// row, separated by the restart index.
+fn grid() -> (Vec<Vertex>, Vec<u32>) {
+    let size = 6.0;
+    let mut vertices = Vec::new();
+    for z in 0..=CELLS {
+        for x in 0..=CELLS {
+            let (u, v) = (x as f32 / CELLS as f32, z as f32 / CELLS as f32);
+            let (px, pz) = ((u - 0.5) * size, (v - 0.5) * size);
+            let r = (px * px + pz * pz).sqrt().max(1e-4);
+            let slope = 0.4 * (2.0 * r).cos() / r;
+            vertices.push(Vertex {
+                position: [px, (2.0 * r).sin() * 0.2, pz],
+                normal: Vec3::new(-slope * px, 1.0, -slope * pz)
+                    .normalize()
+                    .to_array(),
+                uv: [u, v],
+            });
+        }
+    }
+    let row = CELLS + 1;
+    let mut indices = Vec::new();
+    for z in 0..CELLS {
+        if z > 0 {
+            indices.push(u32::MAX);
+        }
+        for x in 0..row {
+            indices.extend([(z + 1) * row + x, z * row + x]);
+        }
+    }
+    (vertices, indices)
+}
+
+/// A grid drawn straight from restart-separated triangle strips. Press L to see the
+/// same indices as line strips. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    grid: Mesh,
+    material: Material,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let (vertices, indices) = grid();
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            grid: Mesh::with_mode(&vertices, &indices, PrimitiveMode::TriangleStrip)?,
+            material: Material::pbr(Vec4::new(0.3, 0.6, 0.9, 1.0), 0.0, 0.4),
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 8.0),
+            aspect: 1.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyL) {
+                let mode = match self.grid.mode() {
+                    PrimitiveMode::TriangleStrip => PrimitiveMode::LineStrip,
+                    _ => PrimitiveMode::TriangleStrip,
+                };
+                self.grid.set_mode(mode).unwrap();
+            }
+        }
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.3),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.05)).unwrap();
+        let camera = self.orbit.camera();
+        self.shaders
+            .set_camera(camera.projection(self.aspect) * camera.view(), camera.eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.07, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let program = self.shaders.bind(&self.material);
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.grid.draw();
+    }
+}
+
+fn main() {
+    let config = ContextConfig {
+        options: ContextOptions {
+            primitive_restart: Some(u32::MAX),
+            ..ContextOptions::default()
+        },
+        ..ContextConfig::from_settings()
+    };
+    app::run_with("Triangle strips", &config, |_| Demo::new());
+}
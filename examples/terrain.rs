@@ -0,0 +1,167 @@
+use std::rc::Rc;
+
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::culling::Frustum;
+use hello_gl::event::WindowEvent;
+use hello_gl::gl;
+use hello_gl::image::Image;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer};
+use hello_gl::math::Vec3;
+use hello_gl::noise::{Noise, NoiseConfig};
+use hello_gl::terrain::{Heightmap, SplatMaterial, Terrain, TerrainConfig, TerrainShader};
+use hello_gl::texture::Texture;
+use hello_gl::viewport::OrbitCamera;
+
+/// Sand, grass, rock and snow.
+const LAYER_COLORS: [[u8; 3]; 4] = [
+    [194, 178, 128],
+    [70, 120, 50],
+    [110, 100, 95],
+    [240, 240, 245],
+];
+
+/// Rolling hills from a few octaves of Perlin noise.
+const HILLS: NoiseConfig = NoiseConfig {
+    noise: Noise::Perlin,
+    frequency: 3,
+    octaves: 5,
+    gain: 0.45,
+    seed: 7,
+};
+
+/// Weighs the layers by height and slope: sand low down, snow on top, rock where steep.
+fn splat_map(terrain: &Terrain) -> Image {
+    let heightmap = terrain.heightmap();
+    let (width, depth) = heightmap.size();
+    let mut pixels = Vec::with_capacity((width * depth * 4) as usize);
+    for z in 0..depth as i64 {
+        for x in 0..width as i64 {
+            let height = heightmap.height(x, z);
+            let slope = ((heightmap.height(x + 1, z) - heightmap.height(x - 1, z)).abs()
+                + (heightmap.height(x, z + 1) - heightmap.height(x, z - 1)).abs())
+                * terrain.config().height;
+            let rock = (slope * 2.0).clamp(0.0, 1.0);
+            let snow = ((height - 0.75) * 8.0).clamp(0.0, 1.0) * (1.0 - rock);
+            let sand = ((0.3 - height) * 8.0).clamp(0.0, 1.0) * (1.0 - rock);
+            let grass = (1.0 - rock - snow - sand).max(0.0);
+            pixels.extend([sand, grass, rock, snow].map(|w| (w * 255.0) as u8));
+        }
+    }
+    Image {
+        width,
+        height: depth,
+        pixels,
+    }
+}
+
+/// A small noisy texture around `color`.
+fn layer(color: [u8; 3], seed: u32) -> Result<Texture> {
+    let size = 64;
+    let mut state = seed.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    let mut pixels = Vec::with_capacity(size * size * 4);
+    for _ in 0..size * size {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        let shade = 0.8 + (state % 1000) as f32 / 1000.0 * 0.4;
+        pixels.extend(color.map(|c| (c as f32 * shade).min(255.0) as u8));
+        pixels.push(255);
+    }
+    Texture::from_image(&Image {
+        width: size as u32,
+        height: size as u32,
+        pixels,
+    })
+}
+
+/// A heightmap terrain with texture splatting. Pass a grayscale PNG to use it as the
+/// heightmap. Drag to orbit and scroll to zoom.
+struct Demo {
+    terrain: Terrain,
+    shader: TerrainShader,
+    material: SplatMaterial,
+    lights: LightBuffer,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+}
+
+impl Demo {
+    fn new(path: Option<String>) -> Result<Demo> {
+        let heightmap = match path {
+            Some(path) => Heightmap::from_image(&Image::load(path)?)?,
+            None => Heightmap::from_noise(257, 257, &HILLS)?,
+        };
+        let terrain = Terrain::new(heightmap, TerrainConfig::default())?;
+        let splat_map = Texture::from_image(&splat_map(&terrain))?;
+        let material = SplatMaterial {
+            splat_map: Rc::new(splat_map),
+            layers: [
+                layer(LAYER_COLORS[0], 1)?,
+                layer(LAYER_COLORS[1], 2)?,
+                layer(LAYER_COLORS[2], 3)?,
+                layer(LAYER_COLORS[3], 4)?,
+            ]
+            .map(Rc::new),
+            tiling: 32.0,
+        };
+
+        let mut orbit = OrbitCamera::new(Vec3::ZERO, 90.0);
+        orbit.target.y = terrain.height_at(0.0, 0.0);
+        Ok(Demo {
+            terrain,
+            shader: TerrainShader::new()?,
+            material,
+            lights: LightBuffer::new()?,
+            input: Input::new(),
+            orbit,
+            aspect: 1.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.5, -0.8, -0.3),
+            color: Vec3::new(1.0, 0.95, 0.85),
+            intensity: 2.5,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.15)).unwrap();
+        let camera = self.orbit.camera();
+        let view_projection = camera.projection(self.aspect) * camera.view();
+        self.shader.set_camera(view_projection);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.55, 0.7, 0.9, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let program = self.shader.bind(&self.material);
+        let frustum = Frustum::from_view_projection(view_projection);
+        self.terrain.draw(program, camera.eye, Some(&frustum));
+    }
+}
+
+fn main() {
+    let path = std::env::args().nth(1);
+    app::run("Terrain", move |_| Demo::new(path));
+}
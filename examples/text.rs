@@ -0,0 +1,100 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::event::WindowEvent;
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec2, Vec4};
+use hello_gl::profiler::PipelineStatistics;
+use hello_gl::sprite::SpriteBatch;
+use hello_gl::stats::StatsOverlay;
+use hello_gl::text::Font;
+
+/// Centered text with the stats overlay in the corner, including the pipeline statistics
+/// of drawing the text where the context has them; F3 toggles the overlay.
+struct Demo {
+    batch: SpriteBatch,
+    font: Font,
+    overlay: StatsOverlay,
+    pipeline: Option<PipelineStatistics>,
+    size: Vec2,
+    scale: f32,
+}
+
+impl Demo {
+    fn new(path: &str, scale: f32) -> Result<Demo> {
+        let mut overlay = StatsOverlay::new()?;
+        overlay.set_scale_factor(scale);
+        Ok(Demo {
+            batch: SpriteBatch::new(4096)?,
+            font: Font::load(path, 1024)?,
+            overlay,
+            pipeline: PipelineStatistics::new().ok(),
+            size: Vec2::ONE,
+            scale,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.size = Vec2::new(width as f32, height as f32);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+            self.scale = *scale_factor as f32;
+        }
+        self.overlay.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.overlay.end_frame(dt);
+        if let Some(pipeline) = &mut self.pipeline {
+            self.overlay.set_pass_statistics(pipeline.poll());
+        }
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::ClearColor(0.1, 0.1, 0.15, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        if let Some(pipeline) = &mut self.pipeline {
+            pipeline.begin_pass("text").unwrap();
+        }
+        let projection = Mat4::orthographic_rh_gl(0.0, self.size.x, self.size.y, 0.0, -1.0, 1.0);
+        self.batch.begin(projection);
+        let title = "Hello, GL!\nGlyphs are rasterized on demand.";
+        let size = 48.0 * self.scale;
+        let extent = self.font.measure(title, size).unwrap();
+        self.font
+            .draw(
+                &mut self.batch,
+                title,
+                (self.size - extent) * 0.5,
+                size,
+                Vec4::new(1.0, 0.8, 0.3, 1.0),
+            )
+            .unwrap();
+        self.batch.end();
+        if let Some(pipeline) = &mut self.pipeline {
+            pipeline.end_pass();
+            pipeline.end_frame();
+        }
+
+        self.overlay
+            .draw(&mut self.font, self.size.x as u32, self.size.y as u32)
+            .unwrap();
+    }
+}
+
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: text <font.ttf>");
+    app::run("Text", move |window| {
+        Demo::new(&path, window.scale_factor() as f32)
+    });
+}
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use hello_gl::app::{self, App};
+use hello_gl::depth;
+use hello_gl::draw::{DrawList, Transparency};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::framebuffer::RenderTarget;
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::oit::OitTargets;
+use hello_gl::primitives;
+use hello_gl::state::StateCache;
+use hello_gl::viewport::OrbitCamera;
+
+/// Three interpenetrating translucent panels over a floor, a case no draw order gets
+/// right. Press T to switch between sorted and weighted blended transparency. Drag to
+/// orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cache: StateCache,
+    target: RenderTarget,
+    oit: OitTargets,
+    weighted_blended: bool,
+    panel: Mesh,
+    floor: Mesh,
+    panels: [Material; 3],
+    ground: Material,
+    input: Input,
+    orbit: OrbitCamera,
+    size: (u32, u32),
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let panel = |r, g, b| Material {
+            emissive: Vec3::new(r, g, b) * 0.2,
+            ..Material::blinn_phong(Vec4::new(r, g, b, 0.5), 16.0)
+        };
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cache: StateCache::new(),
+            target: RenderTarget::new(1, 1, gl::RGBA8, true)?,
+            oit: OitTargets::new(1, 1, depth::format())?,
+            weighted_blended: true,
+            panel: primitives::cube(1.0).mesh()?,
+            floor: Mesh::plane(8.0)?,
+            panels: [
+                panel(1.0, 0.2, 0.2),
+                panel(0.2, 1.0, 0.2),
+                panel(0.2, 0.4, 1.0),
+            ],
+            ground: Material::blinn_phong(Vec4::new(0.6, 0.6, 0.6, 1.0), 8.0),
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 6.0),
+            size: (1, 1),
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        let (w, h) = (width.max(1) as i32, height.max(1) as i32);
+        self.target = RenderTarget::new(w, h, gl::RGBA8, true).unwrap();
+        self.oit = OitTargets::new(w, h, depth::format()).unwrap();
+        self.size = (width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::KeyT) {
+                self.weighted_blended = !self.weighted_blended;
+                let mode = if self.weighted_blended {
+                    "weighted blended"
+                } else {
+                    "sorted"
+                };
+                println!("Transparency: {}", mode);
+            }
+        }
+    }
+
+    fn update(&mut self, _dt: f32) {
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.3, -1.0, -0.5),
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.2)).unwrap();
+        let camera = self.orbit.camera();
+        let aspect = self.target.width() as f32 / self.target.height() as f32;
+        let view_projection = camera.projection(aspect) * camera.view();
+        self.shaders.set_camera(view_projection, camera.eye);
+        self.cache.invalidate();
+
+        self.target.bind();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.1, 0.1, 0.12, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        // Thin boxes crossing at the origin, each turned a different way.
+        let panel = Mat4::from_scale(Vec3::new(3.0, 2.0, 0.02));
+        let models = [0.0, 1.0, -1.0].map(|angle| Mat4::from_rotation_y(angle) * panel);
+        let mut list = DrawList::new(view_projection);
+        if self.weighted_blended {
+            list.transparency = Transparency::WeightedBlended(&self.oit);
+        }
+        let floor = Mat4::from_translation(Vec3::new(0.0, -1.0, 0.0));
+        list.push(&self.shaders, &self.floor, 0, &self.ground, floor);
+        for (material, model) in self.panels.iter().zip(models) {
+            list.push(&self.shaders, &self.panel, 0, material, model);
+        }
+        list.submit(&self.shaders, &mut self.cache);
+
+        self.target.present(self.size.0, self.size.1);
+    }
+}
+
+fn main() {
+    app::run("Order-independent transparency", |_| Demo::new());
+}
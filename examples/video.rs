@@ -0,0 +1,103 @@
+use anyhow::{anyhow, Result};
+use hello_gl::app::{self, App};
+use hello_gl::event::{ElementState, KeyCode, WindowEvent};
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::primitives;
+use hello_gl::video::VideoTexture;
+use hello_gl::viewport::OrbitCamera;
+
+/// Plays the video file given on the command line, looping, on every face of a cube.
+/// Press space to pause. Drag to orbit and scroll to zoom.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cube: Mesh,
+    material: Material,
+    video: VideoTexture,
+    input: Input,
+    orbit: OrbitCamera,
+    aspect: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        let path = std::env::args()
+            .nth(1)
+            .ok_or_else(|| anyhow!("Usage: video <file>"))?;
+        let video = VideoTexture::open(&path, true)?;
+        let info = video.info();
+        println!(
+            "{}: {}x{}, {:.2} fps, {:.1} s",
+            path, info.width, info.height, info.frame_rate, info.duration
+        );
+        let material = Material {
+            base_color_texture: Some(video.texture().clone()),
+            ..Material::pbr(Vec4::ONE, 0.0, 0.6)
+        };
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cube: primitives::cube(2.0).mesh()?,
+            material,
+            video,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 5.0),
+            aspect: 1.0,
+        })
+    }
+}
+
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+        if let WindowEvent::KeyboardInput { input, .. } = event {
+            if input.state == ElementState::Pressed && input.key == Some(KeyCode::Space) {
+                self.video.set_paused(!self.video.is_paused());
+            }
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        if let Err(e) = self.video.update(dt) {
+            eprintln!("{:#}", e);
+        }
+        self.orbit.update(&self.input);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.6),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        }];
+        self.lights.upload(&lights, Vec3::splat(0.3)).unwrap();
+        let camera = self.orbit.camera();
+        self.shaders
+            .set_camera(camera.projection(self.aspect) * camera.view(), camera.eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.05, 0.05, 0.07, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        let program = self.shaders.bind(&self.material);
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.cube.draw();
+    }
+}
+
+fn main() {
+    app::run("Video texture", |_| Demo::new());
+}
@@ -0,0 +1,89 @@
+//! The hello triangle on WebGL2. Build with
+//! `cargo build --target wasm32-unknown-unknown --example web_triangle --features web`
+//! and serve it with a page containing `<canvas id="hello-gl">`.
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+    use anyhow::Result;
+    use hello_gl::app::App;
+    use hello_gl::buffer::{Buffer, VertexArray};
+    use hello_gl::gl;
+    use hello_gl::shader::Program;
+    use hello_gl::web;
+
+    const VERT_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+void main() {
+    gl_Position = vec4(pos, 1.0);
+}
+"#;
+
+    const FRAG_SHADER: &str = r#"#version 330 core
+out vec4 final_color;
+void main() {
+    final_color = vec4(1.0, 0.5, 0.2, 1.0);
+}
+"#;
+
+    struct Triangle {
+        program: Program,
+        va: VertexArray,
+        _vb: Buffer,
+    }
+
+    impl Triangle {
+        fn new() -> Result<Triangle> {
+            const VERTICES: [[f32; 3]; 3] = [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
+
+            let program = Program::from_sources(VERT_SHADER, FRAG_SHADER)?;
+            let va = VertexArray::new()?;
+            let vb = Buffer::new()?;
+            vb.bind(gl::ARRAY_BUFFER);
+            vb.data(
+                gl::ARRAY_BUFFER,
+                bytemuck::cast_slice(&VERTICES),
+                gl::STATIC_DRAW,
+            );
+            va.attribute(0, &vb, 3, gl::FLOAT, false, 12, 0);
+            Ok(Triangle {
+                program,
+                va,
+                _vb: vb,
+            })
+        }
+    }
+
+    impl App for Triangle {
+        fn resize(&mut self, width: u32, height: u32) {
+            unsafe {
+                gl::Viewport(0, 0, width as i32, height as i32);
+            }
+        }
+
+        fn render(&mut self) {
+            unsafe {
+                gl::ClearColor(0.2, 0.3, 0.3, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+            self.program.use_program();
+            self.va.bind();
+            unsafe {
+                gl::DrawArrays(gl::TRIANGLES, 0, 3);
+            }
+        }
+    }
+
+    pub fn main() {
+        web::run("hello-gl", |_| Triangle::new())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::main()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("web_triangle only runs in a browser; build it for wasm32-unknown-unknown");
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use hello_gl::app::{xr, App};
+use hello_gl::gl;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Quat, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::viewport::{Camera, EyeView};
+
+/// A ring of pillars around the stage, seen on a head-mounted display through the
+/// OpenXR runner. The window mirrors the left eye.
+struct Demo {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    ground: Mesh,
+    pillar: Mesh,
+    /// Places the stage in the world: its origin on the ground, facing -Z.
+    origin: Camera,
+    time: f32,
+}
+
+impl Demo {
+    fn new() -> Result<Demo> {
+        Ok(Demo {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            ground: Mesh::plane(40.0)?,
+            pillar: Mesh::cube(1.0)?,
+            origin: Camera::look_at(Vec3::ZERO, Vec3::NEG_Z),
+            time: 0.0,
+        })
+    }
+
+    fn draw(&mut self, view_projection: Mat4, eye: Vec3) {
+        self.shaders.set_camera(view_projection, eye);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearColor(0.4, 0.55, 0.7, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        let program = self.shaders.bind(&Material::default());
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        self.ground.draw();
+        let program = self
+            .shaders
+            .bind(&Material::pbr(Vec4::new(0.8, 0.3, 0.2, 1.0), 0.0, 0.4));
+        for i in 0..8 {
+            let angle = i as f32 / 8.0 * std::f32::consts::TAU + self.time * 0.1;
+            let position = Vec3::new(angle.cos() * 3.0, 1.0, angle.sin() * 3.0);
+            let model = Mat4::from_scale_rotation_translation(
+                Vec3::new(0.3, 2.0, 0.3),
+                Quat::from_rotation_y(angle),
+                position,
+            );
+            program.set_mat4("u_model", &model.to_cols_array());
+            self.pillar.draw();
+        }
+    }
+}
+
+impl App for Demo {
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        // Once per frame, shared by both eyes.
+        let light = Light::Directional {
+            direction: Vec3::new(-0.4, -1.0, -0.3).normalize(),
+            color: Vec3::ONE,
+            intensity: 3.0,
+        };
+        self.lights.upload(&[light], Vec3::splat(0.1)).unwrap();
+    }
+
+    /// Only reached without a headset view, e.g. from another runner.
+    fn render(&mut self) {
+        let camera = Camera::look_at(Vec3::new(0.0, 1.6, 0.0), Vec3::new(0.0, 1.6, -1.0));
+        let view_projection = camera.projection_for_context(1.0) * camera.view();
+        self.draw(view_projection, camera.eye);
+    }
+
+    fn render_eye(&mut self, eye: &EyeView) {
+        let view = self.origin.eye_view(eye);
+        let view_projection = self.origin.eye_projection(eye) * view;
+        let position = view.inverse().w_axis.truncate();
+        self.draw(view_projection, position);
+    }
+}
+
+fn main() {
+    xr::run("OpenXR", |_| Demo::new());
+}
@@ -0,0 +1,407 @@
+//! Animated GIF and APNG images as array textures.
+//!
+//! [`AnimatedImage`] decodes every frame and composites it onto the canvas the way
+//! viewers do: frames may cover only part of it, blend over what is there and clear or
+//! restore their area afterwards. [`AnimatedTexture`] uploads the finished frames as the
+//! layers of one `GL_TEXTURE_2D_ARRAY`, so switching frames is a different layer index
+//! rather than another texture, and an [`Animator`] picks that index from the frame
+//! delays. [`crate::sprite::SpriteBatch::draw_animated`] draws it.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::context;
+use crate::gl;
+use crate::image::{self, Image};
+use crate::texture::Texture;
+
+/// Frames with a delay under 20 ms show for 100 ms instead, as in browsers; many GIFs
+/// store a delay of 0 and rely on it.
+const MIN_DELAY: f32 = 0.02;
+const DEFAULT_DELAY: f32 = 0.1;
+
+/// A decoded animation with tightly packed 8-bit RGBA frames, all of the full size.
+pub struct AnimatedImage {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<Image>,
+    /// How long each frame shows, in seconds.
+    pub delays: Vec<f32>,
+    /// How often the animation plays, `None` for forever.
+    pub loops: Option<u32>,
+}
+
+/// What happens to a frame's area once its delay is over.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dispose {
+    Keep,
+    Clear,
+    Restore,
+}
+
+/// Composites partial frames onto a full-size RGBA canvas.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u32, height: u32) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+        }
+    }
+
+    /// Draws `frame` at `(x, y)`, alpha blended over the canvas if `blend`, and returns
+    /// the finished image. `dispose` is then applied to the frame's area.
+    fn draw(&mut self, frame: &Image, x: u32, y: u32, blend: bool, dispose: Dispose) -> Image {
+        let previous = (dispose == Dispose::Restore).then(|| self.pixels.clone());
+        let width = frame.width.min(self.width.saturating_sub(x)) as usize;
+        let height = frame.height.min(self.height.saturating_sub(y)) as usize;
+        for row in 0..height {
+            let source = row * frame.width as usize * 4;
+            let target = ((y as usize + row) * self.width as usize + x as usize) * 4;
+            let source = &frame.pixels[source..source + width * 4];
+            let target = &mut self.pixels[target..target + width * 4];
+            if !blend {
+                target.copy_from_slice(source);
+                continue;
+            }
+            for (target, source) in target.chunks_exact_mut(4).zip(source.chunks_exact(4)) {
+                let alpha = source[3] as u32;
+                if alpha == 255 {
+                    target.copy_from_slice(source);
+                } else if alpha > 0 {
+                    let target_alpha = target[3] as u32 * (255 - alpha) / 255;
+                    let out_alpha = alpha + target_alpha;
+                    for (target, &source) in target[..3].iter_mut().zip(&source[..3]) {
+                        let mixed = source as u32 * alpha + *target as u32 * target_alpha;
+                        *target = (mixed / out_alpha) as u8;
+                    }
+                    target[3] = out_alpha as u8;
+                }
+            }
+        }
+        let image = Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.clone(),
+        };
+        match dispose {
+            Dispose::Keep => {}
+            Dispose::Clear => {
+                for row in 0..height {
+                    let start = ((y as usize + row) * self.width as usize + x as usize) * 4;
+                    self.pixels[start..start + width * 4].fill(0);
+                }
+            }
+            Dispose::Restore => self.pixels = previous.unwrap_or_default(),
+        }
+        image
+    }
+}
+
+impl AnimatedImage {
+    /// Loads a GIF or PNG, animated or not, telling them apart by their signature.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<AnimatedImage> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| anyhow!("Failed to open {}: {}", path.as_ref().display(), e))?;
+        let mut reader = BufReader::new(file);
+        if reader.fill_buf()?.starts_with(b"GIF8") {
+            AnimatedImage::from_gif(reader)
+        } else {
+            AnimatedImage::from_png(reader)
+        }
+    }
+
+    pub fn from_gif<R: Read>(reader: R) -> Result<AnimatedImage> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(reader)?;
+        let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+        let loops = match decoder.repeat() {
+            gif::Repeat::Infinite => None,
+            // The count is of repeats after the first play.
+            gif::Repeat::Finite(repeats) => Some(repeats as u32 + 1),
+        };
+        let mut canvas = Canvas::new(width, height);
+        let (mut frames, mut delays) = (Vec::new(), Vec::new());
+        while let Some(frame) = decoder.read_next_frame()? {
+            let image = Image {
+                width: frame.width as u32,
+                height: frame.height as u32,
+                pixels: frame.buffer.to_vec(),
+            };
+            let dispose = match frame.dispose {
+                gif::DisposalMethod::Background => Dispose::Clear,
+                gif::DisposalMethod::Previous => Dispose::Restore,
+                gif::DisposalMethod::Any | gif::DisposalMethod::Keep => Dispose::Keep,
+            };
+            let (x, y) = (frame.left as u32, frame.top as u32);
+            frames.push(canvas.draw(&image, x, y, true, dispose));
+            delays.push(frame.delay as f32 / 100.0);
+        }
+        AnimatedImage::new(width, height, frames, delays, loops)
+    }
+
+    /// Decodes an APNG, or a plain PNG as a single frame. A default image that isn't part
+    /// of the animation is skipped.
+    pub fn from_png<R: Read>(reader: R) -> Result<AnimatedImage> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let info = reader.info();
+        let (width, height) = (info.width, info.height);
+        let default_is_frame = info.frame_control.is_some();
+        let Some(control) = info.animation_control else {
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let output = reader.next_frame(&mut buf)?;
+            buf.truncate(output.buffer_size());
+            let image = Image {
+                width,
+                height,
+                pixels: image::png_to_rgba(buf, output.color_type)?,
+            };
+            return AnimatedImage::new(width, height, vec![image], vec![DEFAULT_DELAY], None);
+        };
+        let loops = (control.num_plays > 0).then_some(control.num_plays);
+
+        let mut buf = vec![0; reader.output_buffer_size()];
+        if !default_is_frame {
+            reader.next_frame(&mut buf)?;
+        }
+        let mut canvas = Canvas::new(width, height);
+        let (mut frames, mut delays) = (Vec::new(), Vec::new());
+        for _ in 0..control.num_frames {
+            let output = reader.next_frame(&mut buf)?;
+            let frame = reader
+                .info()
+                .frame_control
+                .ok_or_else(|| anyhow!("APNG frame without frame control"))?;
+            let image = Image {
+                width: output.width,
+                height: output.height,
+                pixels: image::png_to_rgba(
+                    buf[..output.buffer_size()].to_vec(),
+                    output.color_type,
+                )?,
+            };
+            let dispose = match frame.dispose_op {
+                png::DisposeOp::None => Dispose::Keep,
+                png::DisposeOp::Background => Dispose::Clear,
+                png::DisposeOp::Previous => Dispose::Restore,
+            };
+            // The first frame has nothing to restore to; the spec clears it instead.
+            let dispose = if frames.is_empty() && dispose == Dispose::Restore {
+                Dispose::Clear
+            } else {
+                dispose
+            };
+            let blend = frame.blend_op == png::BlendOp::Over;
+            let (x, y) = (frame.x_offset, frame.y_offset);
+            frames.push(canvas.draw(&image, x, y, blend, dispose));
+            let denominator = if frame.delay_den == 0 {
+                100
+            } else {
+                frame.delay_den
+            };
+            delays.push(frame.delay_num as f32 / denominator as f32);
+        }
+        AnimatedImage::new(width, height, frames, delays, loops)
+    }
+
+    fn new(
+        width: u32,
+        height: u32,
+        frames: Vec<Image>,
+        delays: Vec<f32>,
+        loops: Option<u32>,
+    ) -> Result<AnimatedImage> {
+        if frames.is_empty() {
+            return Err(anyhow!("Animation has no frames"));
+        }
+        let delays = delays
+            .into_iter()
+            .map(|delay| {
+                if delay < MIN_DELAY {
+                    DEFAULT_DELAY
+                } else {
+                    delay
+                }
+            })
+            .collect();
+        Ok(AnimatedImage {
+            width,
+            height,
+            frames,
+            delays,
+            loops,
+        })
+    }
+
+    /// The length of one play, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.delays.iter().sum()
+    }
+}
+
+/// The frames of an [`AnimatedImage`] as layers of a `GL_TEXTURE_2D_ARRAY`, with their
+/// timing.
+pub struct AnimatedTexture {
+    texture: Rc<Texture>,
+    width: u32,
+    height: u32,
+    delays: Vec<f32>,
+    loops: Option<u32>,
+}
+
+impl AnimatedTexture {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<AnimatedTexture> {
+        AnimatedTexture::from_image(&AnimatedImage::load(path)?)
+    }
+
+    /// Uploads the frames as linearly filtered, clamped `RGBA8` layers.
+    pub fn from_image(image: &AnimatedImage) -> Result<AnimatedTexture> {
+        let limits = context::limits();
+        limits.check_texture_size(image.width as i32, image.height as i32)?;
+        if image.frames.len() > limits.max_array_texture_layers as usize {
+            return Err(anyhow!(
+                "{} frames exceed the maximum of {} array layers",
+                image.frames.len(),
+                limits.max_array_texture_layers
+            ));
+        }
+        let pixels: Vec<u8> = image
+            .frames
+            .iter()
+            .flat_map(|frame| frame.pixels.iter().copied())
+            .collect();
+        let texture = Texture::new(gl::TEXTURE_2D_ARRAY)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        texture.image_3d(
+            0,
+            gl::RGBA8,
+            image.width as i32,
+            image.height as i32,
+            image.frames.len() as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&pixels),
+        );
+        texture.unbind();
+        Ok(AnimatedTexture {
+            texture: Rc::new(texture),
+            width: image.width,
+            height: image.height,
+            delays: image.delays.clone(),
+            loops: image.loops,
+        })
+    }
+
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.delays.len()
+    }
+
+    /// How long each frame shows, in seconds.
+    pub fn delays(&self) -> &[f32] {
+        &self.delays
+    }
+
+    /// An animator playing the texture's frames as often as the file asks.
+    pub fn animator(&self) -> Animator {
+        Animator::new(&self.delays, self.loops)
+    }
+}
+
+/// Playback state of an animation: which frame shows after how much time.
+#[derive(Clone, Debug)]
+pub struct Animator {
+    delays: Vec<f32>,
+    loops: Option<u32>,
+    /// Playback rate; 2 plays twice as fast.
+    pub speed: f32,
+    pub paused: bool,
+    frame: usize,
+    /// Time spent on the current frame.
+    elapsed: f32,
+    plays: u32,
+}
+
+impl Animator {
+    /// Plays frames of the given `delays` in seconds, `loops` times or forever.
+    pub fn new(delays: &[f32], loops: Option<u32>) -> Animator {
+        Animator {
+            delays: delays.to_vec(),
+            loops,
+            speed: 1.0,
+            paused: false,
+            frame: 0,
+            elapsed: 0.0,
+            plays: 0,
+        }
+    }
+
+    /// Advances by `dt` seconds, skipping as many frames as that covers.
+    pub fn update(&mut self, dt: f32) {
+        if self.paused || self.is_finished() || self.delays.is_empty() {
+            return;
+        }
+        self.elapsed += dt * self.speed;
+        loop {
+            let delay = self.delays[self.frame].max(1e-3);
+            if self.elapsed < delay {
+                return;
+            }
+            self.elapsed -= delay;
+            if self.frame + 1 < self.delays.len() {
+                self.frame += 1;
+                continue;
+            }
+            self.plays += 1;
+            if self.is_finished() {
+                self.elapsed = 0.0;
+                return;
+            }
+            self.frame = 0;
+        }
+    }
+
+    /// The frame to show, which is the texture layer to sample.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    /// Whether a limited number of loops has played; the last frame stays.
+    pub fn is_finished(&self) -> bool {
+        matches!(self.loops, Some(loops) if self.plays >= loops)
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.elapsed = 0.0;
+        self.plays = 0;
+    }
+}
@@ -0,0 +1,374 @@
+//! Keyframe animation and skeletal skinning.
+//!
+//! An [`AnimationClip`] samples translation/rotation/scale channels into a pose (one
+//! [`Transform`] per node). A [`Skin`] turns the resulting world matrices into joint
+//! matrices, which [`JointBuffer`] uploads for the vertex shader snippet in
+//! [`SKINNING_GLSL`]. The same [`Track`]s drive cameras and lights in
+//! [`crate::tracks`].
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::Buffer;
+use crate::gl;
+use crate::math::{Mat4, Quat, Transform, Vec3, Vec4};
+
+/// Uniform buffer binding point of the `Joints` block.
+pub const JOINTS_BINDING: u32 = 2;
+
+/// Maximum number of joints in one skin.
+pub const MAX_JOINTS: usize = 128;
+
+/// Vertex shader declarations for skinning. Expects joint indices at location 3 and
+/// weights at location 4, matching [`crate::mesh::SkinnedVertex`].
+pub const SKINNING_GLSL: &str = r#"
+#define MAX_JOINTS 128
+layout (location = 3) in uvec4 a_joints;
+layout (location = 4) in vec4 a_weights;
+
+layout (std140) uniform Joints {
+    mat4 joints[MAX_JOINTS];
+};
+
+mat4 skin_matrix() {
+    return a_weights.x * joints[a_joints.x] + a_weights.y * joints[a_joints.y] +
+           a_weights.z * joints[a_joints.z] + a_weights.w * joints[a_joints.w];
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    /// Cubic Hermite spline. Each keyframe stores an in-tangent, a value and an
+    /// out-tangent, in that order.
+    CubicSpline,
+    /// Cubic through the values, one per keyframe as for `Linear`, with Catmull-Rom
+    /// tangents from the neighboring keyframes: smooth motion without authored tangents.
+    CatmullRom,
+}
+
+/// Values that can be interpolated between keyframes.
+pub trait Keyframe: Copy {
+    fn linear(a: Self, b: Self, t: f32) -> Self;
+
+    /// Hermite interpolation between `p0` and `p1` with tangents `m0` and `m1`, already
+    /// scaled by the keyframe interval.
+    fn cubic(p0: Self, m0: Self, p1: Self, m1: Self, t: f32) -> Self;
+
+    fn scale(self, factor: f32) -> Self;
+
+    /// `self - other`, for tangents estimated from neighboring values.
+    fn difference(self, other: Self) -> Self;
+}
+
+fn hermite_weights(t: f32) -> [f32; 4] {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    [
+        2.0 * t3 - 3.0 * t2 + 1.0,
+        t3 - 2.0 * t2 + t,
+        -2.0 * t3 + 3.0 * t2,
+        t3 - t2,
+    ]
+}
+
+impl Keyframe for f32 {
+    fn linear(a: Self, b: Self, t: f32) -> Self {
+        a + (b - a) * t
+    }
+
+    fn cubic(p0: Self, m0: Self, p1: Self, m1: Self, t: f32) -> Self {
+        let [a, b, c, d] = hermite_weights(t);
+        p0 * a + m0 * b + p1 * c + m1 * d
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        self * factor
+    }
+
+    fn difference(self, other: Self) -> Self {
+        self - other
+    }
+}
+
+impl Keyframe for Vec3 {
+    fn linear(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+
+    fn cubic(p0: Self, m0: Self, p1: Self, m1: Self, t: f32) -> Self {
+        let [a, b, c, d] = hermite_weights(t);
+        p0 * a + m0 * b + p1 * c + m1 * d
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        self * factor
+    }
+
+    fn difference(self, other: Self) -> Self {
+        self - other
+    }
+}
+
+impl Keyframe for Vec4 {
+    fn linear(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+
+    fn cubic(p0: Self, m0: Self, p1: Self, m1: Self, t: f32) -> Self {
+        let [a, b, c, d] = hermite_weights(t);
+        p0 * a + m0 * b + p1 * c + m1 * d
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        self * factor
+    }
+
+    fn difference(self, other: Self) -> Self {
+        self - other
+    }
+}
+
+impl Keyframe for Quat {
+    fn linear(a: Self, b: Self, t: f32) -> Self {
+        a.slerp(b, t)
+    }
+
+    fn cubic(p0: Self, m0: Self, p1: Self, m1: Self, t: f32) -> Self {
+        let [a, b, c, d] = hermite_weights(t);
+        let v = Vec4::from(p0) * a + Vec4::from(m0) * b + Vec4::from(p1) * c + Vec4::from(m1) * d;
+        Quat::from_vec4(v).normalize()
+    }
+
+    fn scale(self, factor: f32) -> Self {
+        Quat::from_vec4(Vec4::from(self) * factor)
+    }
+
+    fn difference(self, other: Self) -> Self {
+        Quat::from_vec4(Vec4::from(self) - Vec4::from(other))
+    }
+}
+
+/// A keyframed value. `times` must be ascending.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track<T> {
+    pub times: Vec<f32>,
+    pub values: Vec<T>,
+    pub interpolation: Interpolation,
+}
+
+impl<T: Keyframe> Track<T> {
+    pub fn new(times: Vec<f32>, values: Vec<T>, interpolation: Interpolation) -> Result<Track<T>> {
+        let expected = match interpolation {
+            Interpolation::CubicSpline => times.len() * 3,
+            _ => times.len(),
+        };
+        if times.is_empty() || values.len() != expected {
+            return Err(anyhow!(
+                "Track has {} keyframes but {} values",
+                times.len(),
+                values.len()
+            ));
+        }
+        Ok(Track {
+            times,
+            values,
+            interpolation,
+        })
+    }
+
+    /// A track holding `value` at all times.
+    pub fn constant(value: T) -> Track<T> {
+        Track {
+            times: vec![0.0],
+            values: vec![value],
+            interpolation: Interpolation::Step,
+        }
+    }
+
+    /// A track through `(time, value)` keyframes, which must be in ascending time order.
+    pub fn from_keys(keys: &[(f32, T)], interpolation: Interpolation) -> Result<Track<T>> {
+        if interpolation == Interpolation::CubicSpline {
+            return Err(anyhow!("Cubic spline keyframes need tangents"));
+        }
+        let (times, values) = keys.iter().copied().unzip();
+        Track::new(times, values, interpolation)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    fn value(&self, key: usize) -> T {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values[key * 3 + 1],
+            _ => self.values[key],
+        }
+    }
+
+    /// Samples the track at `time`, holding the first/last value outside its range.
+    pub fn sample(&self, time: f32) -> T {
+        let last = self.times.len() - 1;
+        if time <= self.times[0] {
+            return self.value(0);
+        }
+        if time >= self.times[last] {
+            return self.value(last);
+        }
+        let next = self.times.partition_point(|&t| t <= time);
+        let prev = next - 1;
+        let dt = self.times[next] - self.times[prev];
+        let t = (time - self.times[prev]) / dt;
+        match self.interpolation {
+            Interpolation::Step => self.values[prev],
+            Interpolation::Linear => T::linear(self.values[prev], self.values[next], t),
+            Interpolation::CubicSpline => T::cubic(
+                self.values[prev * 3 + 1],
+                self.values[prev * 3 + 2].scale(dt),
+                self.values[next * 3 + 1],
+                self.values[next * 3].scale(dt),
+                t,
+            ),
+            Interpolation::CatmullRom => {
+                // One-sided differences at the ends.
+                let tangent = |key: usize| {
+                    let (before, after) = (key.saturating_sub(1), (key + 1).min(last));
+                    let span = self.times[after] - self.times[before];
+                    self.values[after]
+                        .difference(self.values[before])
+                        .scale(dt / span.max(f32::EPSILON))
+                };
+                T::cubic(
+                    self.values[prev],
+                    tangent(prev),
+                    self.values[next],
+                    tangent(next),
+                    t,
+                )
+            }
+        }
+    }
+
+    /// Like [`Track::sample`], repeating the track from its first keyframe. A track
+    /// whose last value equals its first loops without a jump.
+    pub fn sample_looped(&self, time: f32) -> T {
+        let start = self.times[0];
+        let length = self.duration() - start;
+        if length <= 0.0 {
+            return self.value(0);
+        }
+        self.sample(start + (time - start).rem_euclid(length))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChannelTarget {
+    Translation(Track<Vec3>),
+    Rotation(Track<Quat>),
+    Scale(Track<Vec3>),
+}
+
+/// Animates one property of one node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Channel {
+    pub node: usize,
+    pub target: ChannelTarget,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    pub channels: Vec<Channel>,
+}
+
+impl AnimationClip {
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .map(|channel| match &channel.target {
+                ChannelTarget::Translation(track) | ChannelTarget::Scale(track) => track.duration(),
+                ChannelTarget::Rotation(track) => track.duration(),
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// Writes the animated properties at `time` into `pose`, indexed by node. Properties
+    /// without a channel are left untouched.
+    pub fn sample(&self, time: f32, pose: &mut [Transform]) {
+        for channel in &self.channels {
+            let Some(transform) = pose.get_mut(channel.node) else {
+                continue;
+            };
+            match &channel.target {
+                ChannelTarget::Translation(track) => transform.translation = track.sample(time),
+                ChannelTarget::Rotation(track) => transform.rotation = track.sample(time),
+                ChannelTarget::Scale(track) => transform.scale = track.sample(time),
+            }
+        }
+    }
+
+    /// Like [`AnimationClip::sample`], wrapping `time` into the clip's duration.
+    pub fn sample_looped(&self, time: f32, pose: &mut [Transform]) {
+        let duration = self.duration();
+        let time = if duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            0.0
+        };
+        self.sample(time, pose);
+    }
+}
+
+/// Joints of a skinned mesh, as node indices, with their inverse bind matrices.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Skin {
+    pub joints: Vec<usize>,
+    pub inverse_bind_matrices: Vec<Mat4>,
+}
+
+impl Skin {
+    /// Computes the joint matrices from world matrices indexed by node.
+    pub fn joint_matrices(&self, world: &[Mat4]) -> Vec<Mat4> {
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind)| world[joint] * *inverse_bind)
+            .collect()
+    }
+}
+
+/// The `Joints` uniform block.
+pub struct JointBuffer {
+    buffer: Buffer,
+}
+
+impl JointBuffer {
+    pub fn new() -> Result<JointBuffer> {
+        let buffer = Buffer::new()?;
+        buffer.bind(gl::UNIFORM_BUFFER);
+        buffer.allocate(
+            gl::UNIFORM_BUFFER,
+            MAX_JOINTS * std::mem::size_of::<Mat4>(),
+            gl::DYNAMIC_DRAW,
+        );
+        buffer.unbind(gl::UNIFORM_BUFFER);
+        Ok(JointBuffer { buffer })
+    }
+
+    /// Uploads `matrices` and binds the block at [`JOINTS_BINDING`].
+    pub fn upload(&self, matrices: &[Mat4]) -> Result<()> {
+        if matrices.len() > MAX_JOINTS {
+            return Err(anyhow!(
+                "{} joints exceed the limit of {}",
+                matrices.len(),
+                MAX_JOINTS
+            ));
+        }
+        self.buffer.bind(gl::UNIFORM_BUFFER);
+        self.buffer
+            .sub_data(gl::UNIFORM_BUFFER, 0, bytemuck::cast_slice(matrices));
+        self.buffer.unbind(gl::UNIFORM_BUFFER);
+        self.buffer.bind_base(gl::UNIFORM_BUFFER, JOINTS_BINDING);
+        Ok(())
+    }
+}
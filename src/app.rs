@@ -0,0 +1,228 @@
+//! A minimal window + event loop runner used by the examples.
+//!
+//! The runner is desktop-only; on `wasm32` an [`App`] is driven by `crate::web::run`
+//! instead. With the `sdl2` feature, [`sdl::run`] is an alternative runner on SDL2; it
+//! translates SDL events into the same [`WindowEvent`]s, so an [`App`] works with
+//! either backend. [`run_multi`] drives several windows, [`EmbeddedContext`] renders
+//! into a window owned by another toolkit, and [`HeadlessContext`] renders without
+//! showing a window. With the `openxr` feature, [`xr::run`] renders to a head-mounted
+//! display. A [`Runner`] is the [`run`] loop as a value, for hosts that keep their own
+//! loop and pump its events once per iteration. Every runner keeps the
+//! [`crate::builtins`] uniforms current, and the event-loop runners hold the
+//! [`crate::pacing`] frame rate target.
+//!
+//! Window and context settings are read from `hello-gl.toml`; see [`crate::settings`].
+//! `--gl-info` prints the [`crate::context::ContextInfo`] and `--gles` requests an
+//! OpenGL ES context. F3 toggles [`crate::state::set_wireframe`], F4 cycles through the
+//! [`crate::debug_view`] modes and F10 writes a [`crate::crash`] dump of the GL state to
+//! the working directory. With the `renderdoc`
+//! feature, F12 captures a frame when running under RenderDoc.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::context::ResetStatus;
+use crate::crash;
+use crate::debug_view;
+use crate::event::{KeyCode, WindowEvent};
+use crate::image::Image;
+use crate::state;
+use crate::viewport::EyeView;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod embedded;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+#[cfg(not(target_arch = "wasm32"))]
+mod multi;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+#[cfg(feature = "sdl2")]
+pub mod sdl;
+#[cfg(feature = "openxr")]
+pub mod xr;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use embedded::EmbeddedContext;
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::HeadlessContext;
+#[cfg(not(target_arch = "wasm32"))]
+pub use multi::run_multi;
+#[cfg(not(target_arch = "wasm32"))]
+pub use native::{run, run_with, Api, ContextConfig, GlProfile, PumpStatus, Robustness, Runner};
+
+pub trait App {
+    /// Called with the new framebuffer size in physical pixels, including when the window
+    /// moves to a display with a different scale factor. Recreate size-dependent
+    /// framebuffers here.
+    fn resize(&mut self, _width: u32, _height: u32) {}
+
+    fn window_event(&mut self, _event: &WindowEvent) {}
+
+    /// Called once per frame with the elapsed time in seconds.
+    fn update(&mut self, _dt: f32) {}
+
+    fn render(&mut self);
+
+    /// Called by the OpenXR runner (feature `openxr`) once per eye each frame, with the
+    /// eye's framebuffer bound and the viewport set. Draw with [`Camera::eye_view`] and
+    /// [`Camera::eye_projection`]. The default calls [`App::render`], which shows both
+    /// eyes the app's own camera.
+    ///
+    /// [`Camera::eye_view`]: crate::viewport::Camera::eye_view
+    /// [`Camera::eye_projection`]: crate::viewport::Camera::eye_projection
+    fn render_eye(&mut self, _eye: &EyeView) {
+        self.render();
+    }
+
+    /// Called after every [`App::update`] with the window, to change its title, icon or
+    /// cursor. [`EmbeddedContext`] doesn't call it; the host owns that window.
+    fn update_window(&mut self, _window: &mut dyn Window) {}
+
+    /// Checked after every frame; returning `true` closes the window as if the user had.
+    fn exit_requested(&self) -> bool {
+        false
+    }
+
+    /// Called when the GL context was lost to a GPU reset, with who the driver blames for
+    /// it (see [`crate::context::reset_status`]), while the dead context is still
+    /// current. GL objects can be dropped here or after the replacement context is
+    /// current; objects of the lost context then skip deleting their names, which may
+    /// belong to new objects.
+    fn context_lost(&mut self, _status: ResetStatus) {}
+
+    /// Called once a new context is current after [`App::context_lost`], to recreate GL
+    /// objects, e.g. with [`crate::assets::Assets::recreate_all`]. The default gives up,
+    /// which ends the run.
+    fn context_restored(&mut self) -> Result<()> {
+        Err(anyhow!("The app can't recreate its GL objects"))
+    }
+}
+
+/// Handles the runner's own hotkeys before the event reaches the [`App`].
+pub(crate) fn debug_keys(event: &WindowEvent) {
+    if event.is_key_pressed(KeyCode::F3) {
+        state::set_wireframe(!state::wireframe());
+    } else if event.is_key_pressed(KeyCode::F4) {
+        let view = debug_view::get().next();
+        debug_view::set(view);
+        tracing::info!("Debug view: {:?}", view);
+    } else if event.is_key_pressed(KeyCode::F10) {
+        match crash::write(Path::new("."), None) {
+            Ok(path) => tracing::info!("GL state written to {}", path.display()),
+            Err(e) => tracing::error!("{:#}", e),
+        }
+    }
+}
+
+/// A mouse cursor shape.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Cursor {
+    #[default]
+    Default,
+    /// A pointing hand, as over links.
+    Pointer,
+    Text,
+    Crosshair,
+    Move,
+    Grab,
+    Grabbing,
+    NotAllowed,
+    Wait,
+    ResizeHorizontal,
+    ResizeVertical,
+}
+
+/// What an app may ask of its window, whichever backend created it.
+pub trait Window {
+    /// Framebuffer size in physical pixels.
+    fn size(&self) -> (u32, u32);
+
+    /// Physical pixels per logical pixel; `2.0` on typical HiDPI displays.
+    fn scale_factor(&self) -> f64;
+
+    /// Size in logical pixels, the unit of window sizes in [`crate::settings`].
+    fn logical_size(&self) -> (f64, f64) {
+        let (width, height) = self.size();
+        let scale = self.scale_factor();
+        (width as f64 / scale, height as f64 / scale)
+    }
+
+    fn set_title(&mut self, title: &str);
+
+    /// Sets the icon shown in the title bar and task switcher. `None` restores the
+    /// default where the backend can; SDL keeps the current icon.
+    fn set_icon(&mut self, icon: Option<&Image>) -> Result<()>;
+
+    fn set_cursor(&mut self, cursor: Cursor);
+
+    fn set_cursor_visible(&mut self, visible: bool);
+
+    /// Locks the cursor in place (or at least inside the window) for mouse-look, e.g.
+    /// with a fly camera. `CursorMoved` events may stop while grabbed, and with them
+    /// [`crate::input::Input::mouse_motion`]. Fails if the platform supports neither.
+    fn set_cursor_grab(&mut self, grab: bool) -> Result<()>;
+}
+
+impl Window for winit::window::Window {
+    fn size(&self) -> (u32, u32) {
+        let size = self.inner_size();
+        (size.width, size.height)
+    }
+
+    fn scale_factor(&self) -> f64 {
+        winit::window::Window::scale_factor(self)
+    }
+
+    fn set_title(&mut self, title: &str) {
+        winit::window::Window::set_title(self, title);
+    }
+
+    fn set_icon(&mut self, icon: Option<&Image>) -> Result<()> {
+        let icon = icon
+            .map(|image| {
+                winit::window::Icon::from_rgba(image.pixels.clone(), image.width, image.height)
+            })
+            .transpose()
+            .map_err(|e| anyhow!("Invalid window icon: {}", e))?;
+        self.set_window_icon(icon);
+        Ok(())
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        use winit::window::CursorIcon;
+        winit::window::Window::set_cursor(
+            self,
+            match cursor {
+                Cursor::Default => CursorIcon::Default,
+                Cursor::Pointer => CursorIcon::Pointer,
+                Cursor::Text => CursorIcon::Text,
+                Cursor::Crosshair => CursorIcon::Crosshair,
+                Cursor::Move => CursorIcon::Move,
+                Cursor::Grab => CursorIcon::Grab,
+                Cursor::Grabbing => CursorIcon::Grabbing,
+                Cursor::NotAllowed => CursorIcon::NotAllowed,
+                Cursor::Wait => CursorIcon::Wait,
+                Cursor::ResizeHorizontal => CursorIcon::EwResize,
+                Cursor::ResizeVertical => CursorIcon::NsResize,
+            },
+        );
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        winit::window::Window::set_cursor_visible(self, visible);
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) -> Result<()> {
+        use winit::window::CursorGrabMode;
+        let result = if grab {
+            // Windows can only confine, macOS can only lock.
+            winit::window::Window::set_cursor_grab(self, CursorGrabMode::Locked)
+                .or_else(|_| winit::window::Window::set_cursor_grab(self, CursorGrabMode::Confined))
+        } else {
+            winit::window::Window::set_cursor_grab(self, CursorGrabMode::None)
+        };
+        result.map_err(|e| anyhow!("Failed to grab the cursor: {}", e))
+    }
+}
@@ -0,0 +1,118 @@
+use std::ffi::CString;
+use std::num::NonZeroU32;
+
+use anyhow::{anyhow, Result};
+use glutin::config::ConfigTemplateBuilder;
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::{Display, DisplayApiPreference};
+use glutin::prelude::*;
+use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
+
+use super::native::{choose_config, create_context};
+use super::{App, ContextConfig};
+use crate::builtins;
+use crate::context::{self, GlContext};
+use crate::gl;
+use crate::per_frame;
+use crate::upload;
+
+/// A GL context rendering into a window owned by the host application, for embedding
+/// the renderer in Qt, GTK or editor windows.
+///
+/// The host keeps its event loop and drives the renderer itself: it forwards size
+/// changes to [`EmbeddedContext::resize`] and calls [`EmbeddedContext::frame`] when the
+/// window needs repainting.
+pub struct EmbeddedContext {
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+}
+
+impl EmbeddedContext {
+    /// Creates a context and window surface on the given native handles, makes it
+    /// current on this thread and loads the GL functions.
+    ///
+    /// # Safety
+    ///
+    /// The handles must be valid and outlive the returned context.
+    pub unsafe fn new(
+        raw_display_handle: RawDisplayHandle,
+        raw_window_handle: RawWindowHandle,
+        width: u32,
+        height: u32,
+        config: &ContextConfig,
+    ) -> Result<EmbeddedContext> {
+        #[cfg(target_os = "windows")]
+        let preference = DisplayApiPreference::WglThenEgl(Some(raw_window_handle));
+        #[cfg(target_os = "macos")]
+        let preference = DisplayApiPreference::Cgl;
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        let preference = DisplayApiPreference::Egl;
+
+        let display = Display::new(raw_display_handle, preference)?;
+        let template = ConfigTemplateBuilder::new()
+            .compatible_with_native_window(raw_window_handle)
+            .build();
+        let gl_config = choose_config(display.find_configs(template)?, None);
+        let context = create_context(&gl_config, Some(raw_window_handle), config, None)?;
+
+        let attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+            raw_window_handle,
+            non_zero(width)?,
+            non_zero(height)?,
+        );
+        let surface = display.create_window_surface(&gl_config, &attributes)?;
+        let context = context.make_current(&surface)?;
+
+        gl::load_with(|symbol| {
+            let symbol = CString::new(symbol).unwrap();
+            display.get_proc_address(&symbol).cast()
+        });
+        #[cfg(feature = "gles")]
+        crate::gles::load_with(|symbol| {
+            let symbol = CString::new(symbol).unwrap();
+            display.get_proc_address(&symbol).cast()
+        });
+        unsafe { GlContext::assume_current() };
+        context::warn_if_software();
+        upload::register(&display, &gl_config, &context);
+        config.options.apply();
+
+        Ok(EmbeddedContext { surface, context })
+    }
+
+    /// Makes the context current on this thread, e.g. after the host toolkit has used
+    /// its own.
+    pub fn make_current(&self) -> Result<()> {
+        Ok(self.context.make_current(&self.surface)?)
+    }
+
+    /// Resizes the surface to the host window's new size in physical pixels and
+    /// forwards it to `app`. The host sets the other [`crate::builtins`] itself.
+    pub fn resize(&self, app: &mut impl App, width: u32, height: u32) {
+        if let (Some(w), Some(h)) = (NonZeroU32::new(width), NonZeroU32::new(height)) {
+            self.surface.resize(&self.context, w, h);
+            builtins::resize(width, height);
+            app.resize(width, height);
+        }
+    }
+
+    /// Updates and renders `app`, then presents the frame.
+    pub fn frame(&self, app: &mut impl App, dt: f32) -> Result<()> {
+        let _span = tracing::debug_span!("frame").entered();
+        tracing::debug_span!("update").in_scope(|| app.update(dt));
+        tracing::debug_span!("render").in_scope(|| app.render());
+        self.swap_buffers()
+    }
+
+    /// Presents the frame and advances [`crate::per_frame::frame_index`].
+    pub fn swap_buffers(&self) -> Result<()> {
+        self.surface.swap_buffers(&self.context)?;
+        per_frame::advance_frame();
+        Ok(())
+    }
+}
+
+fn non_zero(value: u32) -> Result<NonZeroU32> {
+    NonZeroU32::new(value).ok_or_else(|| anyhow!("Window size must be non-zero"))
+}
@@ -0,0 +1,248 @@
+use std::ffi::CString;
+
+use anyhow::{anyhow, Result};
+use glutin::config::{Config, ConfigTemplateBuilder};
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::{Display, GetGlDisplay};
+use glutin::prelude::*;
+use glutin::surface::{Surface, WindowSurface};
+use glutin_winit::{DisplayBuilder, GlWindow};
+use raw_window_handle::HasWindowHandle;
+use winit::dpi::PhysicalSize;
+use winit::event_loop::EventLoop;
+use winit::window::Window;
+
+use super::native::{choose_config, create_context};
+use super::{App, ContextConfig};
+use crate::builtins;
+use crate::context::{self, GlContext};
+use crate::framebuffer;
+use crate::gl;
+use crate::image::Image;
+use crate::per_frame;
+use crate::settings;
+use crate::texture;
+use crate::upload;
+use crate::validate;
+use crate::viewport::Viewport;
+
+/// What a [`HeadlessContext`] renders into.
+enum Target {
+    Window {
+        surface: Surface<WindowSurface>,
+        /// Boxed, like the event loop, since it is far larger than a surface.
+        _window: Box<Window>,
+        _event_loop: Box<EventLoop<()>>,
+    },
+    /// A pbuffer on an EGL device display, which needs no display server.
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    Pbuffer(Surface<glutin::surface::PbufferSurface>),
+}
+
+/// A GL context on a hidden window, for rendering without a visible window: tests,
+/// screenshots and benchmarks on CI machines.
+///
+/// Apps render into the hidden window's back buffer as usual and
+/// [`HeadlessContext::read_pixels`] reads it back; nothing is ever presented. Where no
+/// window can be created, e.g. without a display server, EGL platforms fall back to a
+/// pbuffer on the first EGL device (`EGL_EXT_platform_device`), which on a machine
+/// without a GPU is Mesa's llvmpipe. A process can create only one on a window, since
+/// it owns winit's event loop, and on macOS only on the main thread.
+pub struct HeadlessContext {
+    target: Target,
+    context: PossiblyCurrentContext,
+    width: u32,
+    height: u32,
+}
+
+impl HeadlessContext {
+    /// Creates a hidden `width` × `height` window without multisampling, makes a
+    /// context following `config` current on it and loads the GL functions. The clear
+    /// color comes from [`crate::settings`] as with the windowed runners.
+    pub fn new(width: u32, height: u32, config: &ContextConfig) -> Result<HeadlessContext> {
+        if settings::get().software {
+            context::request_software();
+        }
+        let (target, context, display, gl_config) = match on_window(width, height, config) {
+            Ok(created) => created,
+            #[cfg(all(
+                unix,
+                not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+            ))]
+            Err(e) => {
+                tracing::warn!("{:#}; falling back to a surfaceless EGL device", e);
+                on_device(width, height, config)?
+            }
+            #[cfg(not(all(
+                unix,
+                not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+            )))]
+            Err(e) => return Err(e),
+        };
+
+        gl::load_with(|symbol| {
+            let symbol = CString::new(symbol).unwrap();
+            display.get_proc_address(&symbol).cast()
+        });
+        #[cfg(feature = "gles")]
+        crate::gles::load_with(|symbol| {
+            let symbol = CString::new(symbol).unwrap();
+            display.get_proc_address(&symbol).cast()
+        });
+        unsafe { GlContext::assume_current() };
+        context::warn_if_software();
+        upload::register(&display, &gl_config, &context);
+        config.options.apply();
+        validate::set_enabled(settings::get().validate);
+        texture::set_default_anisotropy(settings::get().anisotropy);
+        let [r, g, b, a] = settings::get().clear_color;
+        unsafe {
+            gl::ClearColor(r, g, b, a);
+        }
+
+        Ok(HeadlessContext {
+            target,
+            context,
+            width,
+            height,
+        })
+    }
+
+    /// Makes the context current on this thread again.
+    pub fn make_current(&self) -> Result<()> {
+        match &self.target {
+            Target::Window { surface, .. } => self.context.make_current(surface)?,
+            #[cfg(all(
+                unix,
+                not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+            ))]
+            Target::Pbuffer(surface) => self.context.make_current(surface)?,
+        }
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Tells `app` the size, then updates and renders it for `frames` frames of `dt`
+    /// seconds each, or until it requests exit. The [`crate::builtins`] time advances
+    /// by exactly `dt` per frame, so the result doesn't depend on how fast the machine
+    /// is.
+    pub fn run(&self, app: &mut impl App, frames: u64, dt: f32) {
+        builtins::resize(self.width, self.height);
+        app.resize(self.width, self.height);
+        for frame in 0..frames {
+            let _span = tracing::debug_span!("frame", frame).entered();
+            builtins::set_time(frame as f32 * dt);
+            tracing::debug_span!("update").in_scope(|| app.update(dt));
+            tracing::debug_span!("render").in_scope(|| app.render());
+            per_frame::advance_frame();
+            if app.exit_requested() {
+                break;
+            }
+        }
+    }
+
+    /// The last rendered frame, with top-down rows.
+    pub fn read_pixels(&self) -> Image {
+        framebuffer::Framebuffer::bind_default(gl::READ_FRAMEBUFFER);
+        if let Target::Window { .. } = self.target {
+            unsafe {
+                gl::ReadBuffer(gl::BACK);
+            }
+        }
+        framebuffer::read_pixels(Viewport::full(self.width, self.height))
+    }
+}
+
+/// Creates a hidden window without multisampling and makes a context current on it.
+fn on_window(
+    width: u32,
+    height: u32,
+    config: &ContextConfig,
+) -> Result<(Target, PossiblyCurrentContext, Display, Config)> {
+    // winit panics rather than failing without a display server.
+    #[cfg(all(
+        unix,
+        not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+    ))]
+    if std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return Err(anyhow!("No display server"));
+    }
+    let mut builder = EventLoop::builder();
+    // Test harnesses run tests off the main thread.
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+    winit::platform::x11::EventLoopBuilderExtX11::with_any_thread(&mut builder, true);
+    #[cfg(target_os = "windows")]
+    winit::platform::windows::EventLoopBuilderExtWindows::with_any_thread(&mut builder, true);
+    let event_loop = builder
+        .build()
+        .map_err(|e| anyhow!("Failed to create an event loop: {}", e))?;
+
+    let window_attributes = Window::default_attributes()
+        .with_title("hello-gl (headless)")
+        .with_inner_size(PhysicalSize::new(width, height))
+        .with_resizable(false)
+        .with_visible(false);
+    let (window, gl_config) = DisplayBuilder::new()
+        .with_window_attributes(Some(window_attributes))
+        .build(&event_loop, ConfigTemplateBuilder::new(), |configs| {
+            choose_config(configs, Some(0))
+        })
+        .map_err(|e| anyhow!("Failed to create a GL display: {}", e))?;
+    let window = window.ok_or_else(|| anyhow!("Failed to create a hidden window"))?;
+
+    let raw_window_handle = window.window_handle()?.as_raw();
+    let context = create_context(&gl_config, Some(raw_window_handle), config, None)?;
+    let display = gl_config.display();
+    let attributes = window.build_surface_attributes(Default::default())?;
+    let surface = unsafe { display.create_window_surface(&gl_config, &attributes)? };
+    let context = context.make_current(&surface)?;
+    let target = Target::Window {
+        surface,
+        _window: Box::new(window),
+        _event_loop: Box::new(event_loop),
+    };
+    Ok((target, context, display, gl_config))
+}
+
+/// Creates a pbuffer on the first EGL device and makes a context current on it.
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+fn on_device(
+    width: u32,
+    height: u32,
+    config: &ContextConfig,
+) -> Result<(Target, PossiblyCurrentContext, Display, Config)> {
+    use std::num::NonZeroU32;
+
+    use glutin::api::egl;
+    use glutin::config::ConfigSurfaceTypes;
+    use glutin::surface::{PbufferSurface, SurfaceAttributesBuilder};
+
+    let device = egl::device::Device::query_devices()
+        .map_err(|e| anyhow!("Failed to list EGL devices: {}", e))?
+        .next()
+        .ok_or_else(|| anyhow!("No EGL devices"))?;
+    let display = Display::Egl(unsafe { egl::display::Display::with_device(&device, None)? });
+    let template = ConfigTemplateBuilder::new()
+        .with_surface_type(ConfigSurfaceTypes::PBUFFER)
+        .build();
+    let gl_config = choose_config(unsafe { display.find_configs(template)? }, Some(0));
+
+    let context = create_context(&gl_config, None, config, None)?;
+    let size = |value: u32| {
+        NonZeroU32::new(value).ok_or_else(|| anyhow!("Headless size must be non-zero"))
+    };
+    let attributes =
+        SurfaceAttributesBuilder::<PbufferSurface>::new().build(size(width)?, size(height)?);
+    let surface = unsafe { display.create_pbuffer_surface(&gl_config, &attributes)? };
+    let context = context.make_current(&surface)?;
+    Ok((Target::Pbuffer(surface), context, display, gl_config))
+}
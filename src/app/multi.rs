@@ -0,0 +1,243 @@
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use glutin::config::ConfigTemplateBuilder;
+use glutin::context::PossiblyCurrentContext;
+use glutin::display::GetGlDisplay;
+use glutin::prelude::*;
+use glutin::surface::{Surface, SwapInterval, WindowSurface};
+use glutin_winit::{DisplayBuilder, GlWindow};
+use raw_window_handle::HasWindowHandle;
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Window, WindowAttributes, WindowId};
+
+use super::native::{choose_config, create_context};
+use super::{App, ContextConfig};
+use crate::builtins;
+use crate::context::{self, GlContext};
+use crate::event;
+use crate::gl;
+use crate::pacing::{self, FrameLimiter};
+use crate::per_frame;
+use crate::settings;
+use crate::texture;
+use crate::upload;
+use crate::validate;
+
+/// A window of [`run_multi`] with its own context and app. Fields drop in order, so
+/// the app is dropped before its context.
+struct Slot<A> {
+    app: A,
+    context: PossiblyCurrentContext,
+    surface: Surface<WindowSurface>,
+    window: Window,
+    last_frame: Instant,
+}
+
+/// Opens one window per title, each with its own context, and builds an app for each
+/// with `init(index, window)`. Events and redraws go to the app of the window they
+/// belong to, with its context made current first. The loop exits when the last
+/// window is closed.
+///
+/// With `shared`, every context shares objects with the first, so buffers, textures
+/// and programs created in one window can be drawn in the others; vertex arrays and
+/// framebuffers are never shared and must be created per window. Only the first window
+/// waits for vsync, so presenting several windows does not divide the frame rate.
+pub fn run_multi<A, F>(titles: &[&str], config: &ContextConfig, shared: bool, mut init: F) -> !
+where
+    A: App + 'static,
+    F: FnMut(usize, &Window) -> Result<A>,
+{
+    if settings::get().software {
+        context::request_software();
+    }
+    let event_loop = EventLoop::new().unwrap();
+    let window_attributes: Vec<WindowAttributes> = titles
+        .iter()
+        .map(|title| Window::default_attributes().with_title(*title))
+        .collect();
+    let first = window_attributes
+        .first()
+        .expect("run_multi needs at least one title");
+
+    let (mut first_window, gl_config) = DisplayBuilder::new()
+        .with_window_attributes(Some(first.clone()))
+        .build(&event_loop, ConfigTemplateBuilder::new(), |configs| {
+            choose_config(configs, None)
+        })
+        .map_err(|e| anyhow!("Failed to create a GL display: {}", e))
+        .unwrap();
+    let display = gl_config.display();
+
+    let mut slots: Vec<Slot<A>> = Vec::new();
+    for (index, attributes) in window_attributes.iter().enumerate() {
+        let window = match first_window.take() {
+            Some(window) => window,
+            None => {
+                glutin_winit::finalize_window(&event_loop, attributes.clone(), &gl_config).unwrap()
+            }
+        };
+        let share = if shared {
+            slots.first().map(|slot| &slot.context)
+        } else {
+            None
+        };
+        let raw_window_handle = window.window_handle().unwrap().as_raw();
+        let context = create_context(&gl_config, Some(raw_window_handle), config, share).unwrap();
+        let attributes = window.build_surface_attributes(Default::default()).unwrap();
+        let surface = unsafe { display.create_window_surface(&gl_config, &attributes) }.unwrap();
+        let context = context.make_current(&surface).unwrap();
+
+        if index == 0 {
+            gl::load_with(|symbol| {
+                let symbol = CString::new(symbol).unwrap();
+                display.get_proc_address(&symbol).cast()
+            });
+            #[cfg(feature = "gles")]
+            crate::gles::load_with(|symbol| {
+                let symbol = CString::new(symbol).unwrap();
+                display.get_proc_address(&symbol).cast()
+            });
+            unsafe { GlContext::assume_current() };
+            upload::register(&display, &gl_config, &context);
+            let info = context::info();
+            if std::env::args().any(|arg| arg == "--gl-info") {
+                println!("{}", info);
+            }
+            context::warn_if_software();
+        } else {
+            let _ = surface.set_swap_interval(&context, SwapInterval::DontWait);
+        }
+        config.options.apply();
+
+        let mut app = tracing::info_span!("init", window = index)
+            .in_scope(|| init(index, &window))
+            .unwrap();
+        let size = window.inner_size();
+        builtins::resize(size.width, size.height);
+        app.resize(size.width, size.height);
+        slots.push(Slot {
+            app,
+            context,
+            surface,
+            window,
+            last_frame: Instant::now(),
+        });
+    }
+
+    pacing::set_target_fps(settings::get().max_fps);
+    validate::set_enabled(settings::get().validate);
+    texture::set_default_anisotropy(settings::get().anisotropy);
+    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut runner = MultiRunner {
+        // The context of the last window created is current.
+        current: slots.last().map(|slot| slot.window.id()),
+        slots,
+        limiter: FrameLimiter::new(),
+        start: Instant::now(),
+    };
+    event_loop.run_app(&mut runner).unwrap();
+    std::process::exit(0);
+}
+
+/// The windows of a [`run_multi`] run, as a winit [`ApplicationHandler`].
+struct MultiRunner<A> {
+    slots: Vec<Slot<A>>,
+    /// The window whose context is current.
+    current: Option<WindowId>,
+    limiter: FrameLimiter,
+    start: Instant,
+}
+
+impl<A: App> MultiRunner<A> {
+    /// The index of the slot of `id`, with its context made current.
+    fn make_current(&mut self, id: WindowId) -> Option<usize> {
+        let index = self.slots.iter().position(|slot| slot.window.id() == id)?;
+        if self.current != Some(id) {
+            let slot = &self.slots[index];
+            slot.context.make_current(&slot.surface).unwrap();
+            self.current = Some(id);
+        }
+        Some(index)
+    }
+
+    /// Drops the app of the slot at `index`, whose context must be current so its
+    /// objects are deleted from the right context, and exits after the last window.
+    fn close(&mut self, event_loop: &ActiveEventLoop, index: usize) {
+        self.slots.remove(index);
+        if self.slots.is_empty() {
+            event_loop.exit();
+        }
+    }
+
+    fn redraw(&mut self, event_loop: &ActiveEventLoop, index: usize) {
+        let slot = &mut self.slots[index];
+        // The built-ins are shared by all windows, so set this one's size.
+        let size = slot.window.inner_size();
+        builtins::resize(size.width, size.height);
+        let _frame = tracing::debug_span!("frame", window = index).entered();
+        let now = Instant::now();
+        builtins::set_time((now - self.start).as_secs_f32());
+        tracing::debug_span!("update").in_scope(|| {
+            slot.app.update((now - slot.last_frame).as_secs_f32());
+            slot.app.update_window(&mut slot.window);
+        });
+        slot.last_frame = now;
+
+        tracing::debug_span!("render").in_scope(|| slot.app.render());
+        slot.surface.swap_buffers(&slot.context).unwrap();
+        if slot.app.exit_requested() {
+            self.close(event_loop, index);
+        }
+    }
+}
+
+impl<A: App> ApplicationHandler for MultiRunner<A> {
+    /// The windows are created up front.
+    fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(index) = self.make_current(window_id) else {
+            return;
+        };
+        match event {
+            WindowEvent::RedrawRequested => return self.redraw(event_loop, index),
+            WindowEvent::Resized(physical_size) => {
+                let slot = &mut self.slots[index];
+                if let (Some(width), Some(height)) = (
+                    NonZeroU32::new(physical_size.width),
+                    NonZeroU32::new(physical_size.height),
+                ) {
+                    slot.surface.resize(&slot.context, width, height);
+                    builtins::resize(physical_size.width, physical_size.height);
+                    slot.app.resize(physical_size.width, physical_size.height);
+                }
+            }
+            WindowEvent::CloseRequested => return self.close(event_loop, index),
+            _ => (),
+        }
+        let slot = &mut self.slots[index];
+        for event in event::WindowEvent::from_winit(&event) {
+            builtins::window_event(&event);
+            slot.app.window_event(&event);
+        }
+    }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        // Once per round of redraws, so each window's slots turn once per frame.
+        per_frame::advance_frame();
+        self.limiter.wait();
+        for slot in &self.slots {
+            slot.window.request_redraw();
+        }
+    }
+}
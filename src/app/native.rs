@@ -0,0 +1,677 @@
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use glutin::config::{Config, ConfigTemplateBuilder};
+use glutin::context::{
+    ContextApi, ContextAttributesBuilder, NotCurrentContext, PossiblyCurrentContext, Version,
+};
+use glutin::display::GetGlDisplay;
+use glutin::prelude::*;
+use glutin::surface::{Surface, SwapInterval, WindowSurface};
+use glutin_winit::{DisplayBuilder, GlWindow};
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
+
+pub use glutin::context::{GlProfile, Robustness};
+
+use super::{debug_keys, App};
+use crate::builtins;
+use crate::context::{self, ContextOptions, GlContext, ResetStatus};
+use crate::event::WindowEvent;
+use crate::gl;
+use crate::pacing::{self, FrameLimiter};
+use crate::per_frame;
+use crate::settings::{self, Settings};
+use crate::texture;
+use crate::upload;
+use crate::validate;
+
+/// The API family of the requested context.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Api {
+    OpenGl,
+    OpenGlEs,
+}
+
+/// How the runner requests its GL context.
+///
+/// `versions` are tried in order until one succeeds. Forward compatibility is not
+/// configurable with glutin 0.32; it is implied for core profiles on macOS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContextConfig {
+    pub api: Api,
+    pub versions: Vec<(u8, u8)>,
+    /// Ignored for ES contexts.
+    pub profile: GlProfile,
+    pub robustness: Robustness,
+    /// Requests a debug context, which makes `KHR_debug` output more verbose.
+    pub debug: bool,
+    /// Applied once the context is current.
+    pub options: ContextOptions,
+}
+
+impl Default for ContextConfig {
+    /// 4.6 core, falling back through 4.5, 4.3 and 4.1 to 3.3 core.
+    fn default() -> Self {
+        ContextConfig {
+            api: Api::OpenGl,
+            versions: vec![(4, 6), (4, 5), (4, 3), (4, 1), (3, 3)],
+            profile: GlProfile::Core,
+            robustness: Robustness::NotRobust,
+            debug: false,
+            options: ContextOptions::default(),
+        }
+    }
+}
+
+impl ContextConfig {
+    /// OpenGL ES 3.2, falling back to 3.1 and 3.0. Through EGL this also selects ANGLE
+    /// where it provides `libEGL`.
+    pub fn gles() -> ContextConfig {
+        ContextConfig {
+            api: Api::OpenGlEs,
+            versions: vec![(3, 2), (3, 1), (3, 0)],
+            ..ContextConfig::default()
+        }
+    }
+
+    /// The config [`run`] uses: `--gles` or `gles` in [`crate::settings`] selects
+    /// [`ContextConfig::gles`], a `gl_version` setting replaces the fallback chain and
+    /// `robust` enables recovery from context loss (see [`run_with`]).
+    pub fn from_settings() -> ContextConfig {
+        let settings = settings::get();
+        let mut config = if settings.gles || std::env::args().any(|arg| arg == "--gles") {
+            ContextConfig::gles()
+        } else {
+            ContextConfig::default()
+        };
+        if let Some(version) = settings.gl_version {
+            config.versions = vec![version];
+        }
+        if settings.robust {
+            config.robustness = Robustness::RobustLoseContextOnReset;
+        }
+        config
+    }
+}
+
+/// Creates a context following `config`'s fallback chain, sharing objects with `share`
+/// if given.
+pub(super) fn create_context(
+    gl_config: &Config,
+    raw_window_handle: Option<RawWindowHandle>,
+    config: &ContextConfig,
+    share: Option<&PossiblyCurrentContext>,
+) -> Result<NotCurrentContext> {
+    let profile = match (config.api, config.profile) {
+        (Api::OpenGl, GlProfile::Core) => "core",
+        (Api::OpenGl, GlProfile::Compatibility) => "compatibility",
+        _ => "ES",
+    };
+    let display = gl_config.display();
+    let mut attempts = Vec::new();
+    for &(major, minor) in &config.versions {
+        let version = Some(Version::new(major, minor));
+        let mut builder = ContextAttributesBuilder::new()
+            .with_robustness(config.robustness)
+            .with_debug(config.debug);
+        builder = match config.api {
+            Api::OpenGl => builder
+                .with_context_api(ContextApi::OpenGl(version))
+                .with_profile(config.profile),
+            Api::OpenGlEs => builder.with_context_api(ContextApi::Gles(version)),
+        };
+        if let Some(share) = share {
+            builder = builder.with_sharing(share);
+        }
+        let attributes = builder.build(raw_window_handle);
+        match unsafe { display.create_context(gl_config, &attributes) } {
+            Ok(context) => return Ok(context),
+            Err(e) => attempts.push(format!("{}.{} {}: {}", major, minor, profile, e)),
+        }
+    }
+    Err(anyhow!(
+        "Failed to create an OpenGL context; tried:\n  {}",
+        attempts.join("\n  ")
+    ))
+}
+
+/// Picks the config with the most MSAA samples, up to `max_samples` if given. Falls
+/// back to the config with the fewest samples if none is within the limit.
+pub(super) fn choose_config(
+    configs: Box<dyn Iterator<Item = Config> + '_>,
+    max_samples: Option<u8>,
+) -> Config {
+    let within = |config: &Config| max_samples.is_none_or(|max| config.num_samples() <= max);
+    configs
+        .reduce(|best, config| {
+            let better = match (within(&config), within(&best)) {
+                (true, false) => true,
+                (false, true) => false,
+                (true, true) => config.num_samples() > best.num_samples(),
+                (false, false) => config.num_samples() < best.num_samples(),
+            };
+            if better {
+                config
+            } else {
+                best
+            }
+        })
+        .expect("no GL configs available")
+}
+
+/// A window with its surface and the current context.
+struct Current {
+    window: Window,
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+}
+
+impl Current {
+    /// Creates the surface for `window` (creating the window itself first if needed) and
+    /// makes `context` current on it.
+    fn new(
+        window: Option<Window>,
+        event_loop: &ActiveEventLoop,
+        window_attributes: &WindowAttributes,
+        gl_config: &Config,
+        context: NotCurrentContext,
+    ) -> Result<Current> {
+        let window = match window {
+            Some(window) => window,
+            None => {
+                glutin_winit::finalize_window(event_loop, window_attributes.clone(), gl_config)?
+            }
+        };
+        let attributes = window.build_surface_attributes(Default::default())?;
+        let surface = unsafe {
+            gl_config
+                .display()
+                .create_window_surface(gl_config, &attributes)?
+        };
+        let context = context.make_current(&surface)?;
+        Ok(Current {
+            window,
+            surface,
+            context,
+        })
+    }
+}
+
+fn set_swap_interval(current: &Current, vsync: bool) {
+    let interval = if vsync {
+        SwapInterval::Wait(NonZeroU32::new(1).unwrap())
+    } else {
+        SwapInterval::DontWait
+    };
+    if let Err(e) = current
+        .surface
+        .set_swap_interval(&current.context, interval)
+    {
+        tracing::warn!("Failed to set the swap interval: {}", e);
+    }
+}
+
+/// Loads the function pointers for a context that just became current and applies the
+/// options and settings that live in context state.
+fn prepare_context(
+    gl_config: &Config,
+    context: &PossiblyCurrentContext,
+    config: &ContextConfig,
+    settings: &Settings,
+) {
+    let display = gl_config.display();
+    let load = |symbol: &str| {
+        let symbol = CString::new(symbol).unwrap();
+        display.get_proc_address(&symbol).cast()
+    };
+    gl::load_with(load);
+    #[cfg(feature = "gles")]
+    crate::gles::load_with(load);
+    unsafe { GlContext::assume_current() };
+    upload::register(&display, gl_config, context);
+    config.options.apply();
+
+    let [r, g, b, a] = settings.clear_color;
+    unsafe {
+        gl::ClearColor(r, g, b, a);
+    }
+}
+
+/// Replaces the context of `lost`, lost to a reset of `status`, with a new one on the
+/// same window and has `app` recreate its objects in it.
+#[allow(clippy::too_many_arguments)]
+fn recover<A: App>(
+    lost: Current,
+    status: ResetStatus,
+    app: &mut A,
+    event_loop: &ActiveEventLoop,
+    window_attributes: &WindowAttributes,
+    gl_config: &Config,
+    config: &ContextConfig,
+    settings: &Settings,
+) -> Result<Current> {
+    let _span = tracing::info_span!("recover").entered();
+    app.context_lost(status);
+    // Queued names belong to the lost context; deleting them later would hit the new one's.
+    context::flush_deletions();
+    let Current {
+        window,
+        surface,
+        context,
+    } = lost;
+    drop(surface);
+    drop(context);
+
+    let raw_window_handle = Some(window.window_handle()?.as_raw());
+    let context = create_context(gl_config, raw_window_handle, config, None)?;
+    let current = Current::new(
+        Some(window),
+        event_loop,
+        window_attributes,
+        gl_config,
+        context,
+    )?;
+    set_swap_interval(&current, settings.vsync);
+    // Forget the lost context first, so the options are checked against the new one.
+    context::forget();
+    prepare_context(gl_config, &current.context, config, settings);
+    app.context_restored()?;
+
+    let size = current.window.inner_size();
+    builtins::resize(size.width, size.height);
+    app.resize(size.width, size.height);
+    Ok(current)
+}
+
+/// Creates a window with a current GL context, builds the app with `init` and runs it
+/// until the window is closed. The context follows [`ContextConfig::from_settings`].
+pub fn run<A, F>(title: &str, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A> + 'static,
+{
+    run_with(title, &ContextConfig::from_settings(), init)
+}
+
+/// Like [`run`], requesting the context described by `config`. Window size, title,
+/// fullscreen, vsync, MSAA samples and clear color still come from [`crate::settings`].
+///
+/// The window and its surface are created on `Resumed` and the surface is dropped on
+/// `Suspended`, as Android requires. The context, and with it every GL object the app
+/// owns, survives the round trip.
+///
+/// With [`Robustness::RobustLoseContextOnReset`], the runner checks
+/// [`context::reset_status`] every frame. After a GPU reset it calls
+/// [`App::context_lost`], creates a new context on the same window and calls
+/// [`App::context_restored`]; if that fails the run ends.
+///
+/// Panics if the window, context or app can't be created, or if the run fails later.
+/// To keep control of the loop and get those errors back, e.g. inside a host
+/// application, create a [`Runner`] and call [`Runner::pump_events`] instead.
+pub fn run_with<A, F>(title: &str, config: &ContextConfig, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A> + 'static,
+{
+    let event_loop = EventLoop::new().unwrap();
+    let mut runner = Runner::new(&event_loop, title, config, init).unwrap();
+    event_loop.run_app(&mut runner).unwrap();
+    runner.take_error().map_or(Ok(()), Err).unwrap();
+    // `exit` skips destructors, and the app's GL objects have to be deleted.
+    drop(runner);
+    std::process::exit(0);
+}
+
+/// What [`Runner::pump_events`] left behind.
+#[derive(Debug)]
+pub enum PumpStatus {
+    /// The window is open; pump again.
+    Continue,
+    /// The window was closed or the app asked to exit.
+    Exit,
+    /// The run failed: the window, surface or app couldn't be created, a frame couldn't
+    /// be presented or the context couldn't be recovered. Later pumps return `Exit`.
+    Error(anyhow::Error),
+}
+
+/// The window, context and app of a [`run_with`] run, as a winit [`ApplicationHandler`].
+/// [`run_with`] hands it to [`EventLoop::run_app`] until exit; [`Runner::pump_events`]
+/// hands it to `pump_app_events` for one iteration and returns, so a host application
+/// can interleave its own work. A host with its own handler can forward winit's calls
+/// to it as well. Fields drop in order, so the app is dropped before its context.
+pub struct Runner<A, F> {
+    init: Option<F>,
+    app: Option<A>,
+    settings: &'static Settings,
+    config: ContextConfig,
+    check_reset: bool,
+    window_attributes: WindowAttributes,
+    gl_config: Config,
+    /// Created with the display where the platform allows it, and kept while suspended.
+    window: Option<Window>,
+    not_current: Option<NotCurrentContext>,
+    current: Option<Current>,
+    limiter: FrameLimiter,
+    start: Instant,
+    last_frame: Instant,
+    exit: bool,
+    /// Why the run ended, until [`Runner::take_error`] or [`Runner::pump_events`] takes it.
+    error: Option<anyhow::Error>,
+}
+
+impl<A, F> Runner<A, F>
+where
+    A: App,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    /// Creates the display and context on `event_loop` as [`run_with`] would, and sets
+    /// it polling. The window appears, and `init` builds the app, on the first `Resumed`.
+    pub fn new(
+        event_loop: &EventLoop<()>,
+        title: &str,
+        config: &ContextConfig,
+        init: F,
+    ) -> Result<Runner<A, F>> {
+        let settings = settings::get();
+        if settings.software {
+            context::request_software();
+        }
+        let window_attributes = Window::default_attributes()
+            .with_title(settings.title.as_deref().unwrap_or(title))
+            .with_inner_size(LogicalSize::new(settings.width, settings.height))
+            .with_fullscreen(settings.fullscreen.then_some(Fullscreen::Borderless(None)));
+
+        // Android only hands out a native window after the first `Resumed`.
+        let eager_window = (!cfg!(target_os = "android")).then(|| window_attributes.clone());
+        let (window, gl_config) = DisplayBuilder::new()
+            .with_window_attributes(eager_window)
+            .build(event_loop, ConfigTemplateBuilder::new(), |configs| {
+                choose_config(configs, settings.samples)
+            })
+            .map_err(|e| anyhow!("Failed to create a GL display: {}", e))?;
+        let raw_window_handle = window
+            .as_ref()
+            .map(|window| window.window_handle().map(|handle| handle.as_raw()))
+            .transpose()?;
+        let not_current = create_context(&gl_config, raw_window_handle, config, None)?;
+
+        event_loop.set_control_flow(ControlFlow::Poll);
+        pacing::set_target_fps(settings.max_fps);
+        validate::set_enabled(settings.validate);
+        texture::set_default_anisotropy(settings.anisotropy);
+        let start = Instant::now();
+        Ok(Runner {
+            init: Some(init),
+            app: None,
+            settings,
+            config: config.clone(),
+            check_reset: matches!(config.robustness, Robustness::RobustLoseContextOnReset),
+            window_attributes,
+            gl_config,
+            window,
+            not_current: Some(not_current),
+            current: None,
+            limiter: FrameLimiter::new(),
+            start,
+            last_frame: start,
+            exit: false,
+            error: None,
+        })
+    }
+
+    /// The app, once the first `Resumed` has built it.
+    pub fn app(&self) -> Option<&A> {
+        self.app.as_ref()
+    }
+
+    pub fn app_mut(&mut self) -> Option<&mut A> {
+        self.app.as_mut()
+    }
+
+    /// The window while resumed.
+    pub fn window(&self) -> Option<&Window> {
+        self.current.as_ref().map(|current| &current.window)
+    }
+
+    /// Whether the run is over: the window was closed, [`App::exit_requested`] returned
+    /// `true` or the run failed.
+    pub fn exiting(&self) -> bool {
+        self.exit
+    }
+
+    /// Why the run failed, for hosts driving the runner as their own handler; see
+    /// [`PumpStatus::Error`].
+    pub fn take_error(&mut self) -> Option<anyhow::Error> {
+        self.error.take()
+    }
+
+    /// Handles the events that have arrived since the last call, then returns. One
+    /// iteration of the event loop runs, up to and including a frame, so call this once
+    /// per iteration of the host's loop, until it returns [`PumpStatus::Exit`] or
+    /// [`PumpStatus::Error`].
+    #[cfg(not(target_os = "ios"))]
+    pub fn pump_events(&mut self, event_loop: &mut EventLoop<()>) -> PumpStatus {
+        use std::time::Duration;
+
+        use winit::platform::pump_events::{self, EventLoopExtPumpEvents};
+
+        if !self.exit {
+            let status = event_loop.pump_app_events(Some(Duration::ZERO), self);
+            if let pump_events::PumpStatus::Exit(_) = status {
+                self.exit = true;
+            }
+        }
+        if let Some(error) = self.error.take() {
+            PumpStatus::Error(error)
+        } else if self.exit {
+            PumpStatus::Exit
+        } else {
+            PumpStatus::Continue
+        }
+    }
+
+    /// Ends the run with `error`.
+    fn fail(&mut self, event_loop: &ActiveEventLoop, error: anyhow::Error) {
+        self.error = Some(error);
+        self.exit = true;
+        event_loop.exit();
+    }
+
+    /// Recovers a lost context if needed, then updates, renders and presents a frame.
+    fn redraw(&mut self, event_loop: &ActiveEventLoop) {
+        let checked = self.check_reset && self.current.is_some() && self.app.is_some();
+        if let Some(status) = checked.then(context::reset_status).flatten() {
+            tracing::warn!("GL context lost ({:?} reset); recreating it", status);
+            let recovered = recover(
+                self.current.take().unwrap(),
+                status,
+                self.app.as_mut().unwrap(),
+                event_loop,
+                &self.window_attributes,
+                &self.gl_config,
+                &self.config,
+                self.settings,
+            );
+            match recovered {
+                Ok(recovered) => self.current = Some(recovered),
+                Err(e) => {
+                    let error = anyhow!("Failed to recover from the context loss: {:#}", e);
+                    return self.fail(event_loop, error);
+                }
+            }
+        }
+        let Some(current) = &mut self.current else {
+            return;
+        };
+        let Some(app) = &mut self.app else {
+            return;
+        };
+        let _frame = tracing::debug_span!("frame").entered();
+        let now = Instant::now();
+        builtins::set_time((now - self.start).as_secs_f32());
+        tracing::debug_span!("update").in_scope(|| {
+            app.update((now - self.last_frame).as_secs_f32());
+            app.update_window(&mut current.window);
+        });
+        self.last_frame = now;
+
+        tracing::debug_span!("render").in_scope(|| app.render());
+        if let Err(e) = current.surface.swap_buffers(&current.context) {
+            return self.fail(event_loop, anyhow!("Failed to present a frame: {}", e));
+        }
+        per_frame::advance_frame();
+        if app.exit_requested() {
+            self.exit = true;
+        }
+    }
+}
+
+impl<A, F> ApplicationHandler for Runner<A, F>
+where
+    A: App,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    /// Creates the surface, and the window if needed, and makes the context current. The
+    /// first time, also loads GL and builds the app. Does nothing if the context is
+    /// already current.
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(context) = self.not_current.take() else {
+            return;
+        };
+        let resumed = match Current::new(
+            self.window.take(),
+            event_loop,
+            &self.window_attributes,
+            &self.gl_config,
+            context,
+        ) {
+            Ok(resumed) => resumed,
+            Err(e) => {
+                let error = anyhow!("Failed to make the context current on the window: {}", e);
+                return self.fail(event_loop, error);
+            }
+        };
+        set_swap_interval(&resumed, self.settings.vsync);
+
+        if let Some(init) = self.init.take() {
+            let _span = tracing::info_span!("init").entered();
+            prepare_context(
+                &self.gl_config,
+                &resumed.context,
+                &self.config,
+                self.settings,
+            );
+            let info = context::info();
+            if std::env::args().any(|arg| arg == "--gl-info") {
+                println!("{}", info);
+            }
+            context::warn_if_software();
+            if self.check_reset {
+                context::warn_if_no_reset_notification();
+            }
+
+            #[cfg(feature = "renderdoc")]
+            if let Ok(template) = std::env::var(crate::renderdoc::CAPTURE_PATH_ENV) {
+                crate::renderdoc::set_capture_path(template);
+            }
+
+            match init(&resumed.window) {
+                Ok(app) => self.app = Some(app),
+                Err(e) => {
+                    self.current = Some(resumed);
+                    return self.fail(event_loop, e);
+                }
+            }
+        }
+        let size = resumed.window.inner_size();
+        builtins::resize(size.width, size.height);
+        if let Some(app) = &mut self.app {
+            app.resize(size.width, size.height);
+        }
+        self.last_frame = Instant::now();
+        self.current = Some(resumed);
+    }
+
+    /// Drops the surface and releases the context, keeping the window except on
+    /// Android, where it is about to be destroyed.
+    fn suspended(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(suspended) = self.current.take() {
+            let Current {
+                window,
+                surface,
+                context,
+            } = suspended;
+            drop(surface);
+            if !cfg!(target_os = "android") {
+                self.window = Some(window);
+            }
+            match context.make_not_current() {
+                Ok(context) => self.not_current = Some(context),
+                Err(e) => {
+                    let error = anyhow!("Failed to release the context on suspend: {}", e);
+                    self.fail(event_loop, error);
+                }
+            }
+        }
+    }
+
+    /// Draws a frame on `RedrawRequested`. Otherwise resizes the surface, handles the
+    /// runner's hotkeys and passes the event on to the builtins and the app.
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        match event {
+            winit::event::WindowEvent::RedrawRequested => self.redraw(event_loop),
+            winit::event::WindowEvent::Resized(physical_size) => {
+                if let (Some(current), Some(width), Some(height)) = (
+                    &self.current,
+                    NonZeroU32::new(physical_size.width),
+                    NonZeroU32::new(physical_size.height),
+                ) {
+                    current.surface.resize(&current.context, width, height);
+                    builtins::resize(physical_size.width, physical_size.height);
+                    if let Some(app) = &mut self.app {
+                        app.resize(physical_size.width, physical_size.height);
+                    }
+                }
+            }
+            winit::event::WindowEvent::CloseRequested => self.exit = true,
+            _ => (),
+        }
+        for event in WindowEvent::from_winit(&event) {
+            #[cfg(feature = "renderdoc")]
+            if event.is_key_pressed(crate::event::KeyCode::F12) {
+                if crate::renderdoc::trigger_capture() {
+                    tracing::info!("RenderDoc: capturing next frame");
+                } else {
+                    tracing::warn!("RenderDoc is not attached");
+                }
+            }
+            debug_keys(&event);
+            builtins::window_event(&event);
+            if let Some(app) = &mut self.app {
+                app.window_event(&event);
+            }
+        }
+        if self.exit {
+            event_loop.exit();
+        }
+    }
+
+    /// Holds the frame rate target and requests the next frame.
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(current) = &self.current {
+            self.limiter.wait();
+            current.window.request_redraw();
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! SDL2 runner (feature `sdl2`).
+//!
+//! Creates the window and context through SDL instead of winit and glutin. SDL events
+//! are translated into the crate's [`WindowEvent`]s before they reach the [`App`], so
+//! apps, [`crate::egui`] and [`crate::stats`] need no changes. Events without an
+//! equivalent (gamepads, audio devices, ...) are dropped. [`crate::settings`] apply as
+//! they do to the winit runner, except for MSAA samples.
+
+use std::cell::RefCell;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use sdl2::event::{Event, WindowEvent as SdlWindowEvent};
+use sdl2::keyboard::{Mod, Scancode};
+use sdl2::mouse::{Cursor as SdlCursor, MouseButton as SdlMouseButton, SystemCursor};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use sdl2::video::{GLContext, GLProfile, SwapInterval, Window};
+use sdl2::VideoSubsystem;
+
+use super::{debug_keys, Api, App, ContextConfig, Cursor, GlProfile, Robustness};
+use crate::builtins;
+use crate::context::{self, GlContext};
+use crate::event::{
+    ElementState, KeyCode, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    PhysicalPosition, PhysicalSize, WindowEvent,
+};
+use crate::gl;
+use crate::image::Image;
+use crate::pacing::{self, FrameLimiter};
+use crate::per_frame;
+use crate::settings;
+use crate::texture;
+use crate::validate;
+
+impl super::Window for Window {
+    fn size(&self) -> (u32, u32) {
+        self.drawable_size()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        let (width, _) = Window::size(self);
+        let (drawable_width, _) = self.drawable_size();
+        drawable_width as f64 / width.max(1) as f64
+    }
+
+    fn set_title(&mut self, title: &str) {
+        // Only fails on interior NULs.
+        let _ = Window::set_title(self, title);
+    }
+
+    fn set_icon(&mut self, icon: Option<&Image>) -> Result<()> {
+        let Some(image) = icon else {
+            return Ok(());
+        };
+        let mut pixels = image.pixels.clone();
+        // RGBA bytes are ABGR8888 in SDL's native-endian naming.
+        let surface = Surface::from_data(
+            &mut pixels,
+            image.width,
+            image.height,
+            image.width * 4,
+            PixelFormatEnum::ABGR8888,
+        )
+        .map_err(|e| anyhow!("Invalid window icon: {}", e))?;
+        Window::set_icon(self, surface);
+        Ok(())
+    }
+
+    fn set_cursor(&mut self, cursor: Cursor) {
+        let system = match cursor {
+            Cursor::Default => SystemCursor::Arrow,
+            Cursor::Pointer | Cursor::Grab | Cursor::Grabbing => SystemCursor::Hand,
+            Cursor::Text => SystemCursor::IBeam,
+            Cursor::Crosshair => SystemCursor::Crosshair,
+            Cursor::Move => SystemCursor::SizeAll,
+            Cursor::NotAllowed => SystemCursor::No,
+            Cursor::Wait => SystemCursor::Wait,
+            Cursor::ResizeHorizontal => SystemCursor::SizeWE,
+            Cursor::ResizeVertical => SystemCursor::SizeNS,
+        };
+        // SDL stops showing a cursor once it is freed, so keep the current one alive.
+        thread_local! {
+            static CURRENT: RefCell<Option<SdlCursor>> = const { RefCell::new(None) };
+        }
+        match SdlCursor::from_system(system) {
+            Ok(cursor) => {
+                cursor.set();
+                CURRENT.with(|current| *current.borrow_mut() = Some(cursor));
+            }
+            Err(e) => tracing::warn!("Failed to create a cursor: {}", e),
+        }
+    }
+
+    fn set_cursor_visible(&mut self, visible: bool) {
+        self.subsystem().sdl().mouse().show_cursor(visible);
+    }
+
+    fn set_cursor_grab(&mut self, grab: bool) -> Result<()> {
+        self.set_grab(grab);
+        self.subsystem().sdl().mouse().set_relative_mouse_mode(grab);
+        Ok(())
+    }
+}
+
+fn create_window(
+    video: &VideoSubsystem,
+    title: &str,
+    config: &ContextConfig,
+) -> Result<(Window, GLContext)> {
+    let profile = match (config.api, config.profile) {
+        (Api::OpenGl, GlProfile::Core) => GLProfile::Core,
+        (Api::OpenGl, GlProfile::Compatibility) => GLProfile::Compatibility,
+        (Api::OpenGlEs, _) => GLProfile::GLES,
+    };
+    let gl_attr = video.gl_attr();
+    gl_attr.set_context_profile(profile);
+    let mut flags = gl_attr.set_context_flags();
+    if config.debug {
+        flags.debug();
+    }
+    if config.robustness != Robustness::NotRobust {
+        flags.robust_access();
+    }
+    flags.set();
+
+    let settings = settings::get();
+    let mut attempts = Vec::new();
+    for &(major, minor) in &config.versions {
+        gl_attr.set_context_version(major, minor);
+        let mut builder = video.window(title, settings.width, settings.height);
+        builder.opengl().resizable().allow_highdpi();
+        if settings.fullscreen {
+            builder.fullscreen_desktop();
+        }
+        let window = builder.build()?;
+        match window.gl_create_context() {
+            Ok(gl_context) => return Ok((window, gl_context)),
+            Err(e) => attempts.push(format!("{}.{} {:?}: {}", major, minor, profile, e)),
+        }
+    }
+    Err(anyhow!(
+        "Failed to create an OpenGL context; tried:\n  {}",
+        attempts.join("\n  ")
+    ))
+}
+
+/// Like [`super::run`], on SDL2.
+pub fn run<A, F>(title: &str, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    let settings = settings::get();
+    let mut config = if settings.gles || std::env::args().any(|arg| arg == "--gles") {
+        ContextConfig::gles()
+    } else {
+        ContextConfig::default()
+    };
+    if let Some(version) = settings.gl_version {
+        config.versions = vec![version];
+    }
+    run_with(title, &config, init)
+}
+
+/// Like [`super::run_with`], on SDL2.
+pub fn run_with<A, F>(title: &str, config: &ContextConfig, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    let settings = settings::get();
+    if settings.software {
+        context::request_software();
+    }
+    let sdl = sdl2::init().map_err(|e| anyhow!(e)).unwrap();
+    let video = sdl.video().map_err(|e| anyhow!(e)).unwrap();
+    let title = settings.title.as_deref().unwrap_or(title);
+    let (mut window, gl_context) = create_window(&video, title, config).unwrap();
+    let interval = if settings.vsync {
+        SwapInterval::VSync
+    } else {
+        SwapInterval::Immediate
+    };
+    if let Err(e) = video.gl_set_swap_interval(interval) {
+        tracing::warn!("Failed to set the swap interval: {}", e);
+    }
+
+    gl::load_with(|name| video.gl_get_proc_address(name).cast());
+    #[cfg(feature = "gles")]
+    crate::gles::load_with(|name| video.gl_get_proc_address(name).cast());
+    unsafe { GlContext::assume_current() };
+    config.options.apply();
+    let info = context::info();
+    if std::env::args().any(|arg| arg == "--gl-info") {
+        println!("{}", info);
+    }
+    context::warn_if_software();
+    let [r, g, b, a] = settings.clear_color;
+    unsafe {
+        gl::ClearColor(r, g, b, a);
+    }
+
+    let mut app = tracing::info_span!("init")
+        .in_scope(|| init(&window))
+        .unwrap();
+    let (width, height) = window.drawable_size();
+    builtins::resize(width, height);
+    app.resize(width, height);
+
+    let mut event_pump = sdl.event_pump().map_err(|e| anyhow!(e)).unwrap();
+    pacing::set_target_fps(settings.max_fps);
+    validate::set_enabled(settings.validate);
+    texture::set_default_anisotropy(settings.anisotropy);
+    let mut limiter = FrameLimiter::new();
+    let start = Instant::now();
+    let mut last_frame = start;
+    loop {
+        for event in event_pump.poll_iter() {
+            for event in translate(&event, &window) {
+                match event {
+                    WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                        builtins::resize(size.width, size.height);
+                        app.resize(size.width, size.height);
+                    }
+                    WindowEvent::CloseRequested => {
+                        // Objects must be deleted while the context still exists.
+                        drop(app);
+                        drop(gl_context);
+                        std::process::exit(0);
+                    }
+                    _ => (),
+                }
+                debug_keys(&event);
+                builtins::window_event(&event);
+                app.window_event(&event);
+            }
+        }
+
+        let frame = tracing::debug_span!("frame").entered();
+        let now = Instant::now();
+        builtins::set_time((now - start).as_secs_f32());
+        tracing::debug_span!("update").in_scope(|| {
+            app.update((now - last_frame).as_secs_f32());
+            app.update_window(&mut window);
+        });
+        last_frame = now;
+
+        tracing::debug_span!("render").in_scope(|| app.render());
+        window.gl_swap_window();
+        per_frame::advance_frame();
+        drop(frame);
+        if app.exit_requested() {
+            drop(app);
+            drop(gl_context);
+            std::process::exit(0);
+        }
+        limiter.wait();
+    }
+}
+
+/// The crate's equivalents of an SDL event.
+fn translate(event: &Event, window: &Window) -> Vec<WindowEvent> {
+    match *event {
+        Event::Quit { .. }
+        | Event::Window {
+            win_event: SdlWindowEvent::Close,
+            ..
+        } => vec![WindowEvent::CloseRequested],
+        Event::Window {
+            win_event: SdlWindowEvent::SizeChanged(..),
+            ..
+        } => {
+            let (width, height) = window.drawable_size();
+            vec![WindowEvent::Resized(PhysicalSize::new(width, height))]
+        }
+        Event::KeyDown {
+            scancode,
+            keymod,
+            repeat,
+            ..
+        }
+        | Event::KeyUp {
+            scancode,
+            keymod,
+            repeat,
+            ..
+        } => {
+            let state = if matches!(event, Event::KeyDown { .. }) {
+                ElementState::Pressed
+            } else {
+                ElementState::Released
+            };
+            let modifiers = modifiers(keymod);
+            vec![
+                WindowEvent::ModifiersChanged(modifiers),
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        key: scancode.and_then(key_code),
+                        state,
+                        repeat,
+                    },
+                },
+            ]
+        }
+        Event::TextInput { ref text, .. } => {
+            text.chars().map(WindowEvent::ReceivedCharacter).collect()
+        }
+        Event::MouseMotion { x, y, .. } => {
+            let scale = super::Window::scale_factor(window);
+            vec![WindowEvent::CursorMoved {
+                position: PhysicalPosition::new(x as f64 * scale, y as f64 * scale),
+            }]
+        }
+        Event::MouseButtonDown { mouse_btn, .. } | Event::MouseButtonUp { mouse_btn, .. } => {
+            let state = if matches!(event, Event::MouseButtonDown { .. }) {
+                ElementState::Pressed
+            } else {
+                ElementState::Released
+            };
+            let button = match mouse_btn {
+                SdlMouseButton::Left => MouseButton::Left,
+                SdlMouseButton::Right => MouseButton::Right,
+                SdlMouseButton::Middle => MouseButton::Middle,
+                SdlMouseButton::X1 => MouseButton::Back,
+                SdlMouseButton::X2 => MouseButton::Forward,
+                SdlMouseButton::Unknown => return Vec::new(),
+            };
+            vec![WindowEvent::MouseInput { state, button }]
+        }
+        Event::MouseWheel { x, y, .. } => vec![WindowEvent::MouseWheel {
+            delta: MouseScrollDelta::LineDelta(x as f32, y as f32),
+        }],
+        _ => Vec::new(),
+    }
+}
+
+fn modifiers(keymod: Mod) -> ModifiersState {
+    let mut modifiers = ModifiersState::empty();
+    modifiers.set(
+        ModifiersState::SHIFT,
+        keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+    );
+    modifiers.set(
+        ModifiersState::CONTROL,
+        keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+    );
+    modifiers.set(
+        ModifiersState::ALT,
+        keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+    );
+    modifiers.set(
+        ModifiersState::SUPER,
+        keymod.intersects(Mod::LGUIMOD | Mod::RGUIMOD),
+    );
+    modifiers
+}
+
+/// The key at the position of `scancode`; both name keys after the US layout.
+fn key_code(scancode: Scancode) -> Option<KeyCode> {
+    macro_rules! map {
+        ($($sdl:ident => $key:ident),* $(,)?) => {
+            match scancode {
+                $(Scancode::$sdl => Some(KeyCode::$key),)*
+                _ => None,
+            }
+        };
+    }
+    map! {
+        A => KeyA, B => KeyB, C => KeyC, D => KeyD, E => KeyE, F => KeyF, G => KeyG,
+        H => KeyH, I => KeyI, J => KeyJ, K => KeyK, L => KeyL, M => KeyM, N => KeyN,
+        O => KeyO, P => KeyP, Q => KeyQ, R => KeyR, S => KeyS, T => KeyT, U => KeyU,
+        V => KeyV, W => KeyW, X => KeyX, Y => KeyY, Z => KeyZ,
+        Num0 => Digit0, Num1 => Digit1, Num2 => Digit2, Num3 => Digit3, Num4 => Digit4,
+        Num5 => Digit5, Num6 => Digit6, Num7 => Digit7, Num8 => Digit8, Num9 => Digit9,
+        F1 => F1, F2 => F2, F3 => F3, F4 => F4, F5 => F5, F6 => F6,
+        F7 => F7, F8 => F8, F9 => F9, F10 => F10, F11 => F11, F12 => F12,
+        Escape => Escape, Return => Enter, Space => Space, Tab => Tab, Backspace => Backspace,
+        Insert => Insert, Delete => Delete, Home => Home, End => End,
+        PageUp => PageUp, PageDown => PageDown,
+        Left => ArrowLeft, Right => ArrowRight, Up => ArrowUp, Down => ArrowDown,
+        LShift => ShiftLeft, RShift => ShiftRight, LCtrl => ControlLeft, RCtrl => ControlRight,
+        LAlt => AltLeft, RAlt => AltRight,
+        Minus => Minus, Equals => Equal, Comma => Comma, Period => Period, Slash => Slash,
+    }
+}
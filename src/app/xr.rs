@@ -0,0 +1,502 @@
+//! OpenXR runner (feature `openxr`).
+//!
+//! Renders an [`App`] on a head-mounted display. The runner opens a window for the GL
+//! context, starts an OpenXR session on that context through `XR_KHR_opengl_enable`,
+//! and each frame renders the scene twice, into the two layers of one array swapchain
+//! from the runtime, calling [`App::render_eye`] with each eye's [`EyeView`]. Eye poses
+//! are located at the display time the runtime predicts for the frame, so the image
+//! matches where the head will be when it is shown. The window mirrors the left eye.
+//!
+//! The runtime binds to GLX contexts on Linux and WGL contexts on Windows, so glutin
+//! is asked for those rather than EGL; OpenGL ES contexts are not supported. The
+//! tracking space is the runtime's stage, or its local space where there is no stage.
+//! Frames are paced by the runtime: the mirror window doesn't wait for vsync and
+//! [`crate::pacing`] is ignored.
+
+use std::ffi::CString;
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use glutin::config::{AsRawConfig, Config, ConfigTemplateBuilder, RawConfig};
+use glutin::context::{AsRawContext, PossiblyCurrentContext, RawContext};
+use glutin::display::{AsRawDisplay, Display, GetGlDisplay, RawDisplay};
+use glutin::prelude::*;
+use glutin::surface::{AsRawSurface, RawSurface, Surface, SwapInterval, WindowSurface};
+use glutin_winit::{ApiPreference, DisplayBuilder, GlWindow};
+use openxr as xr;
+use raw_window_handle::HasWindowHandle;
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{Window, WindowId};
+
+use super::native::{choose_config, create_context};
+use super::{debug_keys, Api, App, ContextConfig};
+use crate::builtins;
+use crate::context::{self, GlContext};
+use crate::event;
+use crate::framebuffer::{Framebuffer, Renderbuffer};
+use crate::gl;
+use crate::math::{Quat, Vec3};
+use crate::per_frame;
+use crate::settings;
+use crate::texture;
+use crate::upload;
+use crate::validate;
+use crate::viewport::{self, EyeView, Fov, Viewport};
+
+const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+
+/// An OpenXR session on the current GL context, with the swapchain and framebuffer the
+/// eyes are rendered into.
+struct Session {
+    framebuffer: Framebuffer,
+    _depth: Renderbuffer,
+    swapchain: xr::Swapchain<xr::OpenGL>,
+    /// The texture names of the swapchain images, 2D arrays of one layer per eye.
+    images: Vec<gl::types::GLuint>,
+    width: u32,
+    height: u32,
+    space: xr::Space,
+    waiter: xr::FrameWaiter,
+    stream: xr::FrameStream<xr::OpenGL>,
+    session: xr::Session<xr::OpenGL>,
+    blend_mode: xr::EnvironmentBlendMode,
+    events: xr::EventDataBuffer,
+    /// Between the `READY` and `STOPPING` session states, when frames are submitted.
+    running: bool,
+    last_display_time: Option<xr::Time>,
+    instance: xr::Instance,
+}
+
+impl Session {
+    fn new(
+        title: &str,
+        display: &Display,
+        gl_config: &Config,
+        surface: &Surface<WindowSurface>,
+        context: &PossiblyCurrentContext,
+    ) -> Result<Session> {
+        let entry = unsafe { xr::Entry::load() }
+            .map_err(|e| anyhow!("Failed to load the OpenXR loader: {}", e))?;
+        if !entry.enumerate_extensions()?.khr_opengl_enable {
+            return Err(anyhow!("The OpenXR runtime doesn't support OpenGL"));
+        }
+        let mut extensions = xr::ExtensionSet::default();
+        extensions.khr_opengl_enable = true;
+        let instance = entry.create_instance(
+            &xr::ApplicationInfo {
+                application_name: title,
+                application_version: 0,
+                engine_name: "hello-gl",
+                engine_version: 0,
+            },
+            &extensions,
+            &[],
+        )?;
+        let system = instance.system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)?;
+
+        // Required before creating the session.
+        let requirements = instance.graphics_requirements::<xr::OpenGL>(system)?;
+        let minimum = requirements.min_api_version_supported;
+        let (major, minor) = context::info().version;
+        if (major, minor) < (minimum.major() as i32, minimum.minor() as i32) {
+            return Err(anyhow!(
+                "The OpenXR runtime needs OpenGL {}.{}, the context is {}.{}",
+                minimum.major(),
+                minimum.minor(),
+                major,
+                minor
+            ));
+        }
+        let binding = graphics_binding(display, gl_config, surface, context)?;
+        let (session, waiter, stream) =
+            unsafe { instance.create_session::<xr::OpenGL>(system, &binding)? };
+
+        let blend_mode = instance.enumerate_environment_blend_modes(system, VIEW_TYPE)?[0];
+        let spaces = session.enumerate_reference_spaces()?;
+        let space_type = if spaces.contains(&xr::ReferenceSpaceType::STAGE) {
+            xr::ReferenceSpaceType::STAGE
+        } else {
+            xr::ReferenceSpaceType::LOCAL
+        };
+        let space = session.create_reference_space(space_type, xr::Posef::IDENTITY)?;
+
+        let views = instance.enumerate_view_configuration_views(system, VIEW_TYPE)?;
+        let view = views
+            .first()
+            .ok_or_else(|| anyhow!("The OpenXR system has no stereo views"))?;
+        let (width, height) = (
+            view.recommended_image_rect_width,
+            view.recommended_image_rect_height,
+        );
+        // The app writes display-ready colors, which an sRGB swapchain passes on
+        // unchanged as long as `GL_FRAMEBUFFER_SRGB` stays off.
+        let formats = session.enumerate_swapchain_formats()?;
+        let format = [gl::SRGB8_ALPHA8, gl::RGBA8]
+            .into_iter()
+            .find(|format| formats.contains(format))
+            .ok_or_else(|| anyhow!("The OpenXR runtime offers no RGBA8 swapchain"))?;
+        let swapchain = session.create_swapchain(&xr::SwapchainCreateInfo {
+            create_flags: xr::SwapchainCreateFlags::EMPTY,
+            usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+                | xr::SwapchainUsageFlags::SAMPLED,
+            format,
+            sample_count: 1,
+            width,
+            height,
+            face_count: 1,
+            array_size: 2,
+            mip_count: 1,
+        })?;
+        let images = swapchain.enumerate_images()?;
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.label("OpenXR eyes");
+        let depth =
+            Renderbuffer::with_storage(gl::DEPTH24_STENCIL8, width as i32, height as i32, 0)?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_renderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, &depth);
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+
+        Ok(Session {
+            framebuffer,
+            _depth: depth,
+            swapchain,
+            images,
+            width,
+            height,
+            space,
+            waiter,
+            stream,
+            session,
+            blend_mode,
+            events: xr::EventDataBuffer::new(),
+            running: false,
+            last_display_time: None,
+            instance,
+        })
+    }
+
+    /// Handles the runtime's events. Returns `false` once the session is over.
+    fn poll_events(&mut self) -> Result<bool> {
+        while let Some(event) = self.instance.poll_event(&mut self.events)? {
+            match event {
+                xr::Event::SessionStateChanged(change) => match change.state() {
+                    xr::SessionState::READY => {
+                        self.session.begin(VIEW_TYPE)?;
+                        self.running = true;
+                    }
+                    xr::SessionState::STOPPING => {
+                        self.session.end()?;
+                        self.running = false;
+                    }
+                    xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => return Ok(false),
+                    _ => {}
+                },
+                xr::Event::InstanceLossPending(_) => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// Asks the runtime to end the session, which it does through the session states.
+    fn request_exit(&self) -> Result<()> {
+        if self.running {
+            self.session.request_exit()?;
+            Ok(())
+        } else {
+            Err(anyhow!("The OpenXR session is not running"))
+        }
+    }
+
+    /// Waits for the runtime's next frame, updates `app` and renders both eyes, then
+    /// copies the left eye to the default framebuffer of size `mirror`.
+    fn frame(&mut self, app: &mut impl App, start: Instant, mirror: Viewport) -> Result<()> {
+        let state = self.waiter.wait()?;
+        self.stream.begin()?;
+        let time = state.predicted_display_time;
+        if !state.should_render {
+            self.stream.end(time, self.blend_mode, &[])?;
+            return Ok(());
+        }
+
+        let dt = self
+            .last_display_time
+            .map_or(0.0, |last| (time.as_nanos() - last.as_nanos()) as f32 / 1e9);
+        self.last_display_time = Some(time);
+        builtins::set_time(start.elapsed().as_secs_f32());
+        tracing::debug_span!("update").in_scope(|| app.update(dt));
+
+        let (_, views) = self.session.locate_views(VIEW_TYPE, time, &self.space)?;
+        let image = self.swapchain.acquire_image()? as usize;
+        self.swapchain.wait_image(xr::Duration::INFINITE)?;
+        let eye_viewport = Viewport::full(self.width, self.height);
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        for (index, view) in views.iter().enumerate().take(2) {
+            attach_layer(self.images[image], index as i32);
+            eye_viewport.apply();
+            tracing::debug_span!("render", eye = index)
+                .in_scope(|| app.render_eye(&eye_view(index, view)));
+        }
+        attach_layer(self.images[image], 0);
+        viewport::reset(self.width, self.height);
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        self.framebuffer
+            .blit_to(None, eye_viewport, mirror, gl::COLOR_BUFFER_BIT, gl::LINEAR);
+        self.swapchain.release_image()?;
+
+        let rect = xr::Rect2Di {
+            offset: xr::Offset2Di { x: 0, y: 0 },
+            extent: xr::Extent2Di {
+                width: self.width as i32,
+                height: self.height as i32,
+            },
+        };
+        let projection_views: Vec<_> = views
+            .iter()
+            .take(2)
+            .enumerate()
+            .map(|(index, view)| {
+                xr::CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(
+                        xr::SwapchainSubImage::new()
+                            .swapchain(&self.swapchain)
+                            .image_array_index(index as u32)
+                            .image_rect(rect),
+                    )
+            })
+            .collect();
+        let layer = xr::CompositionLayerProjection::new()
+            .space(&self.space)
+            .views(&projection_views);
+        self.stream.end(time, self.blend_mode, &[&layer])?;
+        Ok(())
+    }
+}
+
+/// Attaches layer `layer` of the swapchain image `texture` as color attachment 0 of the
+/// bound framebuffer.
+fn attach_layer(texture: gl::types::GLuint, layer: i32) {
+    unsafe {
+        gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, texture, 0, layer);
+    }
+}
+
+fn eye_view(index: usize, view: &xr::View) -> EyeView {
+    let (position, orientation) = (view.pose.position, view.pose.orientation);
+    EyeView {
+        index,
+        position: Vec3::new(position.x, position.y, position.z),
+        orientation: Quat::from_xyzw(orientation.x, orientation.y, orientation.z, orientation.w),
+        fov: Fov {
+            left: view.fov.angle_left,
+            right: view.fov.angle_right,
+            up: view.fov.angle_up,
+            down: view.fov.angle_down,
+        },
+    }
+}
+
+/// The platform handles of the current context that the runtime renders with.
+fn graphics_binding(
+    display: &Display,
+    gl_config: &Config,
+    surface: &Surface<WindowSurface>,
+    context: &PossiblyCurrentContext,
+) -> Result<xr::opengl::SessionCreateInfo> {
+    match (display.raw_display(), context.raw_context()) {
+        #[cfg(all(
+            unix,
+            not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+        ))]
+        (RawDisplay::Glx(x_display), RawContext::Glx(glx_context)) => {
+            let (RawConfig::Glx(glx_fb_config), RawSurface::Glx(glx_drawable)) =
+                (gl_config.raw_config(), surface.raw_surface())
+            else {
+                return Err(anyhow!("Mixed GLX and non-GLX handles"));
+            };
+            Ok(xr::opengl::SessionCreateInfo::Xlib {
+                x_display: x_display as _,
+                // Runtimes find the visual through the FB config.
+                visualid: 0,
+                glx_fb_config: glx_fb_config as _,
+                glx_drawable: glx_drawable as _,
+                glx_context: glx_context as _,
+            })
+        }
+        #[cfg(target_os = "windows")]
+        (RawDisplay::Wgl, RawContext::Wgl(h_glrc)) => {
+            let _ = (gl_config, surface);
+            let symbol = CString::new("wglGetCurrentDC").unwrap();
+            let get_current_dc = display.get_proc_address(&symbol);
+            if get_current_dc.is_null() {
+                return Err(anyhow!("Failed to load wglGetCurrentDC"));
+            }
+            let get_current_dc: unsafe extern "system" fn() -> *const std::ffi::c_void =
+                unsafe { std::mem::transmute(get_current_dc) };
+            Ok(xr::opengl::SessionCreateInfo::Windows {
+                h_dc: unsafe { get_current_dc() } as _,
+                h_glrc: h_glrc as _,
+            })
+        }
+        _ => Err(anyhow!(
+            "The OpenXR runner needs a GLX or WGL context; glutin created another kind"
+        )),
+    }
+}
+
+/// Opens a window for the context, starts an OpenXR session on it and runs `app` on the
+/// head-mounted display until the runtime ends the session or the window is closed.
+/// The context follows [`ContextConfig::from_settings`] but must be desktop OpenGL.
+pub fn run<A, F>(title: &str, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A> + 'static,
+{
+    let settings = settings::get();
+    if settings.software {
+        context::request_software();
+    }
+    let config = ContextConfig::from_settings();
+    if config.api == Api::OpenGlEs {
+        panic!("The OpenXR runner needs a desktop OpenGL context");
+    }
+    let event_loop = EventLoop::new().unwrap();
+    let window_attributes = Window::default_attributes()
+        .with_title(settings.title.as_deref().unwrap_or(title))
+        .with_inner_size(LogicalSize::new(settings.width, settings.height));
+    let (window, gl_config) = DisplayBuilder::new()
+        .with_preference(ApiPreference::FallbackEgl)
+        .with_window_attributes(Some(window_attributes))
+        .build(&event_loop, ConfigTemplateBuilder::new(), |configs| {
+            choose_config(configs, Some(0))
+        })
+        .map_err(|e| anyhow!("Failed to create a GL display: {}", e))
+        .unwrap();
+    let window = window
+        .ok_or_else(|| anyhow!("Failed to create a window"))
+        .unwrap();
+    let raw_window_handle = window.window_handle().unwrap().as_raw();
+    let context = create_context(&gl_config, Some(raw_window_handle), &config, None).unwrap();
+    let display = gl_config.display();
+    let attributes = window.build_surface_attributes(Default::default()).unwrap();
+    let surface = unsafe { display.create_window_surface(&gl_config, &attributes) }.unwrap();
+    let context = context.make_current(&surface).unwrap();
+    // The runtime paces frames; waiting for the mirror's vsync as well would halve them.
+    if let Err(e) = surface.set_swap_interval(&context, SwapInterval::DontWait) {
+        tracing::warn!("Failed to set the swap interval: {}", e);
+    }
+
+    let load = |symbol: &str| {
+        let symbol = CString::new(symbol).unwrap();
+        display.get_proc_address(&symbol).cast()
+    };
+    gl::load_with(load);
+    #[cfg(feature = "gles")]
+    crate::gles::load_with(load);
+    unsafe { GlContext::assume_current() };
+    context::warn_if_software();
+    upload::register(&display, &gl_config, &context);
+    config.options.apply();
+    validate::set_enabled(settings.validate);
+    texture::set_default_anisotropy(settings.anisotropy);
+    let [r, g, b, a] = settings.clear_color;
+    unsafe {
+        gl::ClearColor(r, g, b, a);
+    }
+
+    let session = Session::new(title, &display, &gl_config, &surface, &context).unwrap();
+    let mut app = tracing::info_span!("init").in_scope(|| init(&window).unwrap());
+    builtins::resize(session.width, session.height);
+    app.resize(session.width, session.height);
+    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut runner = XrRunner {
+        app,
+        session,
+        surface,
+        context,
+        window,
+        start: Instant::now(),
+    };
+    event_loop.run_app(&mut runner).unwrap();
+    std::process::exit(0);
+}
+
+/// The app, session and mirror window of an OpenXR [`run`], as a winit
+/// [`ApplicationHandler`].
+struct XrRunner<A> {
+    app: A,
+    session: Session,
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+    window: Window,
+    start: Instant,
+}
+
+impl<A: App> ApplicationHandler for XrRunner<A> {
+    /// The window and session are created up front.
+    fn resumed(&mut self, _event_loop: &ActiveEventLoop) {}
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        match event {
+            WindowEvent::Resized(size) => {
+                if let (Some(width), Some(height)) =
+                    (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+                {
+                    self.surface.resize(&self.context, width, height);
+                }
+            }
+            WindowEvent::CloseRequested if self.session.request_exit().is_err() => {
+                event_loop.exit();
+            }
+            _ => (),
+        }
+        for event in event::WindowEvent::from_winit(&event) {
+            debug_keys(&event);
+            builtins::window_event(&event);
+            self.app.window_event(&event);
+        }
+    }
+
+    /// Polls the session and, while it runs, renders a frame for the runtime.
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        match self.session.poll_events() {
+            Ok(true) => {}
+            Ok(false) => return event_loop.exit(),
+            Err(e) => {
+                tracing::error!("OpenXR session failed: {:#}", e);
+                return event_loop.exit();
+            }
+        }
+        if !self.session.running {
+            // Nothing to render until the runtime says the session is ready.
+            std::thread::sleep(Duration::from_millis(10));
+            return;
+        }
+
+        let _frame = tracing::debug_span!("frame").entered();
+        let size = self.window.inner_size();
+        let mirror = Viewport::full(size.width, size.height);
+        if let Err(e) = self.session.frame(&mut self.app, self.start, mirror) {
+            tracing::error!("OpenXR frame failed: {:#}", e);
+            return event_loop.exit();
+        }
+        self.surface.swap_buffers(&self.context).unwrap();
+        per_frame::advance_frame();
+        self.app.update_window(&mut self.window);
+        if self.app.exit_requested() && self.session.request_exit().is_err() {
+            event_loop.exit();
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Per-frame CPU memory for render commands.
+//!
+//! A [`FrameArena`] hands out memory by bumping an offset through a few large chunks,
+//! and [`FrameArena::reset`] takes it all back at once, so recording a frame's commands
+//! costs no allocator calls once the chunks have grown to the frame's needs. Chunks
+//! outgrown during a frame are merged into one on reset.
+//!
+//! [`ArenaVec`] is a growable list in an arena, for `Copy` data only since nothing in an
+//! arena is ever dropped. Growing copies the list to a new block and abandons the old
+//! one until the reset. Lists borrow their arena, so it can't be reset while any are
+//! alive; [`crate::draw::DrawList::new_in`] records into one.
+
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// Bytes of the first chunk unless given otherwise.
+pub const DEFAULT_CAPACITY: usize = 64 << 10;
+
+/// Alignment of every chunk, and the largest alignment allocations may ask for.
+const CHUNK_ALIGN: usize = 16;
+
+/// Memory use of a [`FrameArena`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Bytes handed out since the last reset, including padding and abandoned blocks.
+    pub used: usize,
+    /// The most bytes handed out between two resets.
+    pub peak: usize,
+    /// Bytes in all chunks.
+    pub capacity: usize,
+    pub chunks: usize,
+    /// Allocations served since the last reset.
+    pub allocations: usize,
+    /// Chunks allocated from the heap over the arena's life.
+    pub chunk_allocations: usize,
+    pub resets: u64,
+}
+
+struct Chunk {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> Chunk {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN).unwrap();
+        let ptr = unsafe { alloc::alloc(layout) };
+        let ptr = NonNull::new(ptr).unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        Chunk { ptr, size }
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.size, CHUNK_ALIGN).unwrap();
+        unsafe {
+            alloc::dealloc(self.ptr.as_ptr(), layout);
+        }
+    }
+}
+
+/// A bump allocator reset once per frame.
+///
+/// Lists borrow the arena, and [`FrameArena::reset`] takes it mutably, so no list can
+/// outlive the frame its memory belongs to.
+pub struct FrameArena {
+    chunks: RefCell<Vec<Chunk>>,
+    /// The chunk being filled and the bytes used in it.
+    current: Cell<usize>,
+    offset: Cell<usize>,
+    stats: Cell<ArenaStats>,
+}
+
+impl FrameArena {
+    /// An arena starting with one chunk of `capacity` bytes.
+    pub fn new(capacity: usize) -> FrameArena {
+        let capacity = capacity.max(CHUNK_ALIGN).next_multiple_of(CHUNK_ALIGN);
+        FrameArena {
+            chunks: RefCell::new(vec![Chunk::new(capacity)]),
+            current: Cell::new(0),
+            offset: Cell::new(0),
+            stats: Cell::new(ArenaStats {
+                capacity,
+                chunks: 1,
+                chunk_allocations: 1,
+                ..ArenaStats::default()
+            }),
+        }
+    }
+
+    pub fn stats(&self) -> ArenaStats {
+        self.stats.get()
+    }
+
+    /// Takes back everything handed out, merging the chunks into one if there are
+    /// several.
+    pub fn reset(&mut self) {
+        let stats = self.stats.get_mut();
+        let chunks = self.chunks.get_mut();
+        if chunks.len() > 1 {
+            chunks.clear();
+            chunks.push(Chunk::new(stats.capacity));
+            stats.chunks = 1;
+            stats.chunk_allocations += 1;
+        }
+        self.current.set(0);
+        self.offset.set(0);
+        stats.used = 0;
+        stats.allocations = 0;
+        stats.resets += 1;
+    }
+
+    /// Uninitialized memory for `layout`, valid until the next reset.
+    fn alloc(&self, layout: Layout) -> NonNull<u8> {
+        assert!(
+            layout.align() <= CHUNK_ALIGN,
+            "FrameArena allocations are aligned to at most {} bytes",
+            CHUNK_ALIGN
+        );
+        let mut stats = self.stats.get();
+        let mut chunks = self.chunks.borrow_mut();
+        loop {
+            let (ptr, chunk_size) = {
+                let chunk = &chunks[self.current.get()];
+                (chunk.ptr, chunk.size)
+            };
+            let start = self.offset.get().next_multiple_of(layout.align());
+            if start + layout.size() <= chunk_size {
+                stats.used += start + layout.size() - self.offset.get();
+                stats.peak = stats.peak.max(stats.used);
+                stats.allocations += 1;
+                self.stats.set(stats);
+                self.offset.set(start + layout.size());
+                return unsafe { NonNull::new_unchecked(ptr.as_ptr().add(start)) };
+            }
+            // What is left of the chunk is wasted.
+            stats.used += chunk_size - self.offset.get();
+            if self.current.get() + 1 == chunks.len() {
+                let size = (chunk_size * 2).max(layout.size().next_multiple_of(CHUNK_ALIGN));
+                chunks.push(Chunk::new(size));
+                stats.capacity += size;
+                stats.chunks += 1;
+                stats.chunk_allocations += 1;
+            }
+            self.current.set(self.current.get() + 1);
+            self.offset.set(0);
+        }
+    }
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        FrameArena::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl fmt::Debug for FrameArena {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("FrameArena").field(&self.stats()).finish()
+    }
+}
+
+/// A growable list of `T` in a [`FrameArena`].
+pub struct ArenaVec<'a, T: Copy> {
+    arena: &'a FrameArena,
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+}
+
+impl<'a, T: Copy> ArenaVec<'a, T> {
+    /// An empty list; nothing is allocated until the first push.
+    pub fn new_in(arena: &'a FrameArena) -> ArenaVec<'a, T> {
+        ArenaVec {
+            arena,
+            ptr: NonNull::dangling(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    pub fn arena(&self) -> &'a FrameArena {
+        self.arena
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Makes room for `additional` more elements.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.len + additional;
+        if needed <= self.capacity || std::mem::size_of::<T>() == 0 {
+            return;
+        }
+        let capacity = needed.max(self.capacity * 2).max(4);
+        let layout = Layout::array::<T>(capacity).expect("ArenaVec capacity overflow");
+        let ptr = self.arena.alloc(layout).cast::<T>();
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len);
+        }
+        self.ptr = ptr;
+        self.capacity = capacity;
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.reserve(1);
+        unsafe {
+            self.ptr.as_ptr().add(self.len).write(value);
+        }
+        self.len += 1;
+    }
+
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        unsafe {
+            let end = self.ptr.as_ptr().add(self.len);
+            std::ptr::copy_nonoverlapping(values.as_ptr(), end, values.len());
+        }
+        self.len += values.len();
+    }
+
+    /// Empties the list, keeping its block for reuse.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<T: Copy> Deref for ArenaVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for ArenaVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy + fmt::Debug> fmt::Debug for ArenaVec<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_across_chunks() {
+        let arena = FrameArena::new(64);
+        let mut list = ArenaVec::new_in(&arena);
+        for i in 0..1000u32 {
+            list.push(i);
+        }
+        assert!(list.iter().copied().eq(0..1000));
+        let stats = arena.stats();
+        assert!(stats.chunks > 1);
+        assert_eq!(stats.chunks, stats.chunk_allocations);
+        assert!(stats.used >= 1000 * 4);
+        assert!(stats.capacity >= stats.used);
+    }
+
+    #[test]
+    fn lists_share_an_arena() {
+        let arena = FrameArena::new(64);
+        let mut a = ArenaVec::new_in(&arena);
+        let mut b = ArenaVec::new_in(&arena);
+        for i in 0..100u64 {
+            a.push(i);
+            b.extend_from_slice(&[i as u8, !(i as u8)]);
+        }
+        assert!(a.iter().copied().eq(0..100));
+        assert_eq!(b.len(), 200);
+        assert!(b.chunks(2).all(|pair| pair[1] == !pair[0]));
+    }
+
+    #[test]
+    fn reset_merges_chunks() {
+        let mut arena = FrameArena::new(64);
+        {
+            let mut list = ArenaVec::new_in(&arena);
+            list.extend_from_slice(&[0u8; 1000]);
+        }
+        let grown = arena.stats();
+        arena.reset();
+        let stats = arena.stats();
+        assert_eq!(stats.chunks, 1);
+        assert_eq!(stats.capacity, grown.capacity);
+        assert_eq!(stats.used, 0);
+        assert_eq!(stats.allocations, 0);
+        assert_eq!(stats.peak, grown.peak);
+        assert_eq!(stats.resets, 1);
+
+        // The merged chunk holds the whole frame, so the next one needs no more.
+        let mut list = ArenaVec::new_in(&arena);
+        list.extend_from_slice(&[1u8; 1000]);
+        assert_eq!(arena.stats().chunk_allocations, stats.chunk_allocations);
+        assert!(list.iter().all(|&byte| byte == 1));
+    }
+
+    #[test]
+    fn clear_keeps_the_block() {
+        let arena = FrameArena::new(1024);
+        let mut list = ArenaVec::new_in(&arena);
+        list.extend_from_slice(&[1u32, 2, 3]);
+        let allocations = arena.stats().allocations;
+        list.clear();
+        assert!(list.is_empty());
+        list.extend_from_slice(&[4, 5]);
+        assert_eq!(&*list, &[4, 5]);
+        assert_eq!(arena.stats().allocations, allocations);
+    }
+
+    #[test]
+    fn alignment() {
+        let arena = FrameArena::new(64);
+        let mut bytes = ArenaVec::new_in(&arena);
+        bytes.push(1u8);
+        let mut wide = ArenaVec::new_in(&arena);
+        wide.push(1u128);
+        assert_eq!(wide.as_ptr() as usize % std::mem::align_of::<u128>(), 0);
+    }
+}
@@ -0,0 +1,921 @@
+//! Path-keyed asset cache.
+//!
+//! [`Assets`] loads textures, models and programs from files and hands out [`Handle`]s
+//! instead of the GL wrappers themselves. Loading a path that is already loaded returns
+//! the existing handle and adds a reference; [`Assets::release`] drops one, and the asset
+//! is unloaded when none are left. Handles are small `Copy` values carrying a generation,
+//! so a handle outliving its asset resolves to `None` rather than to whatever reused the
+//! slot.
+//!
+//! Assets are stored as `Rc`s, so they can still be shared with APIs that take
+//! `Rc<Texture>` such as [`crate::material::Material`] and
+//! [`crate::sprite::SpriteBatch`]; an unloaded asset lives on until those clones drop.
+//!
+//! [`Assets::reload_changed`], called once per frame, reloads assets whose files were
+//! modified on disk. Textures are re-uploaded into their existing GL objects, so every
+//! `Rc` clone sees the new pixels; models, scenes and programs are rebuilt and replace
+//! the asset behind the handle, so look them up through [`Assets::get`] every frame
+//! rather than holding on to the `Rc`. A hook set with [`Assets::on_reload`] hears about
+//! every reload, successful or not.
+//!
+//! After the GL context is lost and replaced, [`Assets::recreate_all`] loads everything
+//! again into the new context behind the same handles.
+//!
+//! [`Assets::load_texture_async`] and [`Assets::load_model_async`] decode on a
+//! [`ThreadPool`] instead of blocking, so a big scene doesn't hold up the first frame.
+//! Their handle is usable at once and resolves to a placeholder (a pink texture, a unit
+//! cube) until [`Assets::update`], called once per frame, has uploaded the decoded data
+//! and a fence shows the upload has completed on the GPU. The returned [`AssetFuture`]
+//! can be polled or awaited for that moment.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Result};
+
+#[cfg(feature = "gltf")]
+use crate::gltf::{self, GltfScene};
+use crate::image::Image;
+use crate::material::Material;
+use crate::mesh::{Mesh, ObjModel};
+use crate::shader::Program;
+use crate::sync::Fence;
+use crate::texture::Texture;
+use crate::thread_pool::ThreadPool;
+
+/// A reference to an asset of type `T` in an [`Assets`].
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> Hash for Handle<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+/// A mesh loaded from a model file together with its materials, which its submeshes
+/// index into.
+pub struct Model {
+    pub mesh: Mesh,
+    pub materials: Vec<Material>,
+}
+
+/// The outcome of an asynchronous load, shared by the [`AssetFuture`]s waiting for it.
+#[derive(Default)]
+struct Status {
+    result: Option<Result<()>>,
+    waker: Option<Waker>,
+}
+
+/// Hands `outcome` to every waiter; all but the first get a copy of the error message.
+fn resolve(waiters: Vec<Rc<RefCell<Status>>>, outcome: Result<()>) {
+    let message = outcome.as_ref().err().map(|e| format!("{:#}", e));
+    let mut outcome = Some(outcome);
+    for waiter in waiters {
+        let result = outcome.take().unwrap_or_else(|| match &message {
+            Some(message) => Err(anyhow!("{}", message)),
+            None => Ok(()),
+        });
+        let mut status = waiter.borrow_mut();
+        status.result = Some(result);
+        if let Some(waker) = status.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A load started by [`Assets::load_texture_async`] or [`Assets::load_model_async`],
+/// ready once the asset is decoded and its upload has completed on the GPU. Poll it with
+/// [`AssetFuture::try_take`] or await it on an executor running on the render thread.
+pub struct AssetFuture<H> {
+    handle: H,
+    status: Rc<RefCell<Status>>,
+}
+
+impl<H: Copy> AssetFuture<H> {
+    /// The handle, usable right away: until the load finishes, [`Assets::get`] returns a
+    /// placeholder for it, and after a failed load nothing.
+    pub fn handle(&self) -> H {
+        self.handle
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status.borrow().result.is_some()
+    }
+
+    /// The outcome once the load has finished, without waiting. It is handed out once;
+    /// later calls return `None`.
+    pub fn try_take(&mut self) -> Option<Result<H>> {
+        let result = self.status.borrow_mut().result.take()?;
+        Some(result.map(|()| self.handle))
+    }
+}
+
+impl<H: Copy + Unpin> Future for AssetFuture<H> {
+    type Output = Result<H>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Result<H>> {
+        let this = self.get_mut();
+        if let Some(result) = this.try_take() {
+            return Poll::Ready(result);
+        }
+        this.status.borrow_mut().waker = Some(context.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// How often [`Assets::reload_changed`] looks at the files.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+struct Slot<K, T> {
+    generation: u32,
+    refs: usize,
+    entry: Option<(K, Rc<T>)>,
+    /// Modification times of the asset's files when it was last loaded.
+    stamps: Vec<Option<SystemTime>>,
+    /// Whether the entry is a placeholder for an asynchronous load still in progress.
+    loading: bool,
+    /// Futures to resolve once that load finishes.
+    waiters: Vec<Rc<RefCell<Status>>>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Storage for one type of asset.
+pub struct Pool<K, T> {
+    slots: Vec<Slot<K, T>>,
+    free: Vec<u32>,
+    by_key: HashMap<K, Handle<T>>,
+    /// Shared by every asset of the type that is still loading.
+    placeholder: Option<Rc<T>>,
+}
+
+impl<K, T> Default for Pool<K, T> {
+    fn default() -> Self {
+        Pool {
+            slots: Vec::new(),
+            free: Vec::new(),
+            by_key: HashMap::new(),
+            placeholder: None,
+        }
+    }
+}
+
+impl<T: Asset> Pool<T::Key, T> {
+    fn slot(&self, handle: Handle<T>) -> Option<&Slot<T::Key, T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.entry.is_some())
+    }
+
+    fn slot_mut(&mut self, handle: Handle<T>) -> Option<&mut Slot<T::Key, T>> {
+        self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.entry.is_some())
+    }
+
+    /// Adds a reference to the asset for `key`, loading it if needed.
+    fn acquire(&mut self, key: T::Key) -> Result<Handle<T>> {
+        if let Some(&handle) = self.by_key.get(&key) {
+            self.slot_mut(handle).unwrap().refs += 1;
+            return Ok(handle);
+        }
+        let _span = tracing::info_span!("load", path = ?T::files(&key)).entered();
+        let stamps = T::files(&key).into_iter().map(modified).collect();
+        let value = Rc::new(T::load(&key)?);
+        Ok(self.insert(key, value, stamps, false))
+    }
+
+    /// Stores `value` for `key` in a free slot with one reference.
+    fn insert(
+        &mut self,
+        key: T::Key,
+        value: Rc<T>,
+        stamps: Vec<Option<SystemTime>>,
+        loading: bool,
+    ) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    refs: 0,
+                    entry: None,
+                    stamps: Vec::new(),
+                    loading: false,
+                    waiters: Vec::new(),
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        let slot = &mut self.slots[index as usize];
+        slot.refs = 1;
+        slot.entry = Some((key.clone(), value));
+        slot.stamps = stamps;
+        slot.loading = loading;
+        let handle = Handle {
+            index,
+            generation: slot.generation,
+            _marker: PhantomData,
+        };
+        self.by_key.insert(key, handle);
+        handle
+    }
+
+    /// Replaces the placeholder of a loading asset with the loaded value, or unloads the
+    /// asset if loading failed, and resolves its futures.
+    fn finish(&mut self, handle: Handle<T>, result: Result<T>) {
+        let Some(slot) = self.slot_mut(handle).filter(|slot| slot.loading) else {
+            return;
+        };
+        slot.loading = false;
+        let waiters = std::mem::take(&mut slot.waiters);
+        let outcome = match result {
+            Ok(value) => {
+                slot.entry.as_mut().unwrap().1 = Rc::new(value);
+                Ok(())
+            }
+            Err(e) => {
+                self.remove(handle);
+                Err(e)
+            }
+        };
+        resolve(waiters, outcome);
+    }
+
+    /// Reloads every asset whose files changed since it was loaded, returning the
+    /// outcome for each.
+    fn reload_changed(&mut self) -> Vec<(Handle<T>, PathBuf, Result<()>)> {
+        let mut reloaded = Vec::new();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.loading {
+                continue;
+            }
+            let Some((key, value)) = &mut slot.entry else {
+                continue;
+            };
+            let files = T::files(key);
+            let stamps: Vec<_> = files.iter().map(|path| modified(path)).collect();
+            let Some(changed) = (0..files.len()).find(|&i| stamps[i] != slot.stamps[i]) else {
+                continue;
+            };
+            // Half-written files are retried once their time changes again.
+            slot.stamps = stamps;
+            let _span = tracing::info_span!("reload", path = ?files[changed]).entered();
+            let result = T::reload(key, value).map(|replacement| {
+                if let Some(replacement) = replacement {
+                    *value = Rc::new(replacement);
+                }
+            });
+            let handle = Handle {
+                index: index as u32,
+                generation: slot.generation,
+                _marker: PhantomData,
+            };
+            reloaded.push((handle, files[changed].to_path_buf(), result));
+        }
+        reloaded
+    }
+
+    /// Loads every asset again, for a new context. Failed assets keep their dead values,
+    /// and assets still loading their dead placeholder until they finish. The dead values
+    /// are dropped without deleting their names, which belong to the lost context.
+    fn recreate_all(&mut self) -> Vec<(Handle<T>, PathBuf, Result<()>)> {
+        self.placeholder = None;
+        let mut recreated = Vec::new();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let Some((key, value)) = &mut slot.entry else {
+                continue;
+            };
+            if slot.loading {
+                continue;
+            }
+            let files = T::files(key);
+            slot.stamps = files.iter().map(|path| modified(path)).collect();
+            let _span = tracing::info_span!("recreate", path = ?files).entered();
+            let result = T::load(key).map(|replacement| *value = Rc::new(replacement));
+            let handle = Handle {
+                index: index as u32,
+                generation: slot.generation,
+                _marker: PhantomData,
+            };
+            recreated.push((handle, files[0].to_path_buf(), result));
+        }
+        recreated
+    }
+
+    fn remove(&mut self, handle: Handle<T>) -> Option<Rc<T>> {
+        let slot = self.slot_mut(handle)?;
+        let (key, value) = slot.entry.take().unwrap();
+        slot.refs = 0;
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.loading = false;
+        let waiters = std::mem::take(&mut slot.waiters);
+        self.free.push(handle.index);
+        self.by_key.remove(&key);
+        resolve(waiters, Err(anyhow!("Unloaded before it finished loading")));
+        Some(value)
+    }
+
+    fn len(&self) -> usize {
+        self.by_key.len()
+    }
+}
+
+/// An asset type stored in [`Assets`].
+pub trait Asset: Sized {
+    /// What identifies the source of the asset.
+    type Key: Clone + Eq + Hash;
+
+    fn load(key: &Self::Key) -> Result<Self>;
+
+    /// The files the asset is loaded from, watched by [`Assets::reload_changed`].
+    fn files(key: &Self::Key) -> Vec<&Path>;
+
+    /// Reloads the asset after its files changed, either updating `current` in place
+    /// and returning `None`, or returning a replacement.
+    fn reload(key: &Self::Key, _current: &Rc<Self>) -> Result<Option<Self>> {
+        Self::load(key).map(Some)
+    }
+
+    fn reloaded(handle: Handle<Self>) -> Reloaded;
+
+    fn pool(assets: &Assets) -> &Pool<Self::Key, Self>;
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<Self::Key, Self>;
+}
+
+impl Asset for Texture {
+    type Key = PathBuf;
+
+    fn load(path: &PathBuf) -> Result<Texture> {
+        let texture = Texture::from_image(&Image::load(path)?)?;
+        texture.label(&path.display().to_string());
+        Ok(texture)
+    }
+
+    fn files(path: &PathBuf) -> Vec<&Path> {
+        vec![path.as_path()]
+    }
+
+    /// Re-specifies the texture's images, resizing its storage if the dimensions
+    /// changed.
+    fn reload(path: &PathBuf, current: &Rc<Texture>) -> Result<Option<Texture>> {
+        let image = Image::load(path)?;
+        current.bind();
+        current.image_level(0, &image);
+        current.generate_mipmaps();
+        current.unbind();
+        Ok(None)
+    }
+
+    fn reloaded(handle: Handle<Texture>) -> Reloaded {
+        Reloaded::Texture(handle)
+    }
+
+    fn pool(assets: &Assets) -> &Pool<PathBuf, Texture> {
+        &assets.textures
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<PathBuf, Texture> {
+        &mut assets.textures
+    }
+}
+
+impl Asset for Model {
+    type Key = PathBuf;
+
+    fn load(path: &PathBuf) -> Result<Model> {
+        let (mesh, materials) = Mesh::from_obj(path)?;
+        mesh.label(&path.display().to_string());
+        Ok(Model { mesh, materials })
+    }
+
+    /// Only the OBJ file; edits to its MTL files are picked up with the next OBJ change.
+    fn files(path: &PathBuf) -> Vec<&Path> {
+        vec![path.as_path()]
+    }
+
+    fn reloaded(handle: Handle<Model>) -> Reloaded {
+        Reloaded::Model(handle)
+    }
+
+    fn pool(assets: &Assets) -> &Pool<PathBuf, Model> {
+        &assets.models
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<PathBuf, Model> {
+        &mut assets.models
+    }
+}
+
+impl Asset for Program {
+    /// The vertex and fragment shader paths.
+    type Key = (PathBuf, PathBuf);
+
+    fn load((vertex, fragment): &(PathBuf, PathBuf)) -> Result<Program> {
+        let program = Program::from_files(vertex, fragment)?;
+        program.label(&format!("{} + {}", vertex.display(), fragment.display()));
+        Ok(program)
+    }
+
+    fn files((vertex, fragment): &(PathBuf, PathBuf)) -> Vec<&Path> {
+        vec![vertex.as_path(), fragment.as_path()]
+    }
+
+    fn reloaded(handle: Handle<Program>) -> Reloaded {
+        Reloaded::Program(handle)
+    }
+
+    fn pool(assets: &Assets) -> &Pool<(PathBuf, PathBuf), Program> {
+        &assets.programs
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<(PathBuf, PathBuf), Program> {
+        &mut assets.programs
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl Asset for GltfScene {
+    type Key = PathBuf;
+
+    fn load(path: &PathBuf) -> Result<GltfScene> {
+        gltf::load(path)
+    }
+
+    /// Only the glTF file itself, not external buffers or images.
+    fn files(path: &PathBuf) -> Vec<&Path> {
+        vec![path.as_path()]
+    }
+
+    fn reloaded(handle: Handle<GltfScene>) -> Reloaded {
+        Reloaded::Gltf(handle)
+    }
+
+    fn pool(assets: &Assets) -> &Pool<PathBuf, GltfScene> {
+        &assets.scenes
+    }
+
+    fn pool_mut(assets: &mut Assets) -> &mut Pool<PathBuf, GltfScene> {
+        &mut assets.scenes
+    }
+}
+
+/// An [`Asset`] that can be decoded on a worker thread and uploaded afterwards.
+pub trait AsyncAsset: Asset + 'static {
+    /// The CPU-side result of decoding the files.
+    type Decoded: Send + 'static;
+
+    /// Reads and decodes the files. Runs on a worker thread, without GL.
+    fn decode(key: &Self::Key) -> Result<Self::Decoded>;
+
+    /// Creates the GL objects from decoded data on the render thread.
+    fn upload(key: &Self::Key, decoded: Self::Decoded) -> Result<Self>;
+
+    /// What [`Assets::get`] returns while the asset loads.
+    fn placeholder() -> Result<Self>;
+}
+
+impl AsyncAsset for Texture {
+    type Decoded = Image;
+
+    fn decode(path: &PathBuf) -> Result<Image> {
+        Image::load(path)
+    }
+
+    fn upload(path: &PathBuf, image: Image) -> Result<Texture> {
+        let texture = Texture::from_image(&image)?;
+        texture.label(&path.display().to_string());
+        Ok(texture)
+    }
+
+    /// Opaque magenta, which no real texture is mistaken for.
+    fn placeholder() -> Result<Texture> {
+        let texture = Texture::solid([255, 0, 255, 255])?;
+        texture.label("loading placeholder");
+        Ok(texture)
+    }
+}
+
+impl AsyncAsset for Model {
+    type Decoded = ObjModel;
+
+    fn decode(path: &PathBuf) -> Result<ObjModel> {
+        ObjModel::read(path)
+    }
+
+    fn upload(_: &PathBuf, model: ObjModel) -> Result<Model> {
+        let (mesh, materials) = model.upload()?;
+        Ok(Model { mesh, materials })
+    }
+
+    /// A unit cube with no materials.
+    fn placeholder() -> Result<Model> {
+        let mesh = Mesh::cube(1.0)?;
+        mesh.label("loading placeholder");
+        Ok(Model {
+            mesh,
+            materials: Vec::new(),
+        })
+    }
+}
+
+type Decoded = Result<Box<dyn Any + Send>>;
+
+/// Uploads what a worker decoded, on the render thread.
+type Upload = Box<dyn FnOnce(&mut Assets, Decoded)>;
+
+/// Makes an uploaded asset current once its fence has signaled.
+type Finish = Box<dyn FnOnce(&mut Assets)>;
+
+/// Set with [`Assets::on_reload`].
+type ReloadHook = Box<dyn FnMut(&ReloadEvent)>;
+
+/// The worker threads of asynchronous loads and the channel they answer on.
+struct Decoder {
+    pool: ThreadPool,
+    sender: Sender<(u64, Decoded)>,
+    receiver: Receiver<(u64, Decoded)>,
+}
+
+/// The asset a [`ReloadEvent`] is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reloaded {
+    Texture(Handle<Texture>),
+    Model(Handle<Model>),
+    Program(Handle<Program>),
+    #[cfg(feature = "gltf")]
+    Gltf(Handle<GltfScene>),
+}
+
+/// A reload attempted by [`Assets::reload_changed`] or [`Assets::recreate_all`]. On
+/// failure the asset keeps its previous contents.
+#[derive(Debug)]
+pub struct ReloadEvent {
+    pub asset: Reloaded,
+    /// The modified file, or the first file of an asset recreated for a new context.
+    pub path: PathBuf,
+    pub result: Result<()>,
+}
+
+/// Loaded assets, keyed by path relative to a root directory.
+pub struct Assets {
+    root: PathBuf,
+    textures: Pool<PathBuf, Texture>,
+    models: Pool<PathBuf, Model>,
+    programs: Pool<(PathBuf, PathBuf), Program>,
+    #[cfg(feature = "gltf")]
+    scenes: Pool<PathBuf, GltfScene>,
+    next_check: Instant,
+    hook: Option<ReloadHook>,
+    /// Started on the first asynchronous load.
+    decoder: Option<Decoder>,
+    next_job: u64,
+    /// Asynchronous loads being decoded, by job id.
+    decoding: HashMap<u64, Upload>,
+    /// Uploads waiting for their fence, oldest first.
+    uploading: Vec<(Fence, Finish)>,
+}
+
+impl Assets {
+    /// An empty cache resolving relative paths against `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Assets {
+        Assets {
+            root: root.into(),
+            textures: Pool::default(),
+            models: Pool::default(),
+            programs: Pool::default(),
+            #[cfg(feature = "gltf")]
+            scenes: Pool::default(),
+            next_check: Instant::now(),
+            hook: None,
+            decoder: None,
+            next_job: 0,
+            decoding: HashMap::new(),
+            uploading: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// Loads a PNG as a mipmapped texture, or adds a reference if already loaded.
+    pub fn load_texture(&mut self, path: impl AsRef<Path>) -> Result<Handle<Texture>> {
+        let path = self.resolve(path.as_ref());
+        self.textures.acquire(path)
+    }
+
+    /// Loads a Wavefront OBJ model with [`Mesh::from_obj`], or adds a reference if
+    /// already loaded.
+    pub fn load_model(&mut self, path: impl AsRef<Path>) -> Result<Handle<Model>> {
+        let path = self.resolve(path.as_ref());
+        self.models.acquire(path)
+    }
+
+    /// Loads a glTF scene with [`gltf::load`], or adds a reference if already loaded.
+    #[cfg(feature = "gltf")]
+    pub fn load_gltf(&mut self, path: impl AsRef<Path>) -> Result<Handle<GltfScene>> {
+        let path = self.resolve(path.as_ref());
+        self.scenes.acquire(path)
+    }
+
+    /// Compiles a program from vertex and fragment shader files, or adds a reference if
+    /// the pair is already loaded.
+    pub fn load_program(
+        &mut self,
+        vertex: impl AsRef<Path>,
+        fragment: impl AsRef<Path>,
+    ) -> Result<Handle<Program>> {
+        let key = (
+            self.resolve(vertex.as_ref()),
+            self.resolve(fragment.as_ref()),
+        );
+        self.programs.acquire(key)
+    }
+
+    /// Starts loading a PNG as a mipmapped texture in the background, or adds a reference
+    /// if it is already loaded or loading. See [`AssetFuture`].
+    pub fn load_texture_async(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<AssetFuture<Handle<Texture>>> {
+        let path = self.resolve(path.as_ref());
+        self.load_async(path)
+    }
+
+    /// Starts loading a Wavefront OBJ model in the background, or adds a reference if it
+    /// is already loaded or loading. See [`AssetFuture`].
+    pub fn load_model_async(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> Result<AssetFuture<Handle<Model>>> {
+        let path = self.resolve(path.as_ref());
+        self.load_async(path)
+    }
+
+    /// Fails only if the placeholder can't be created or the worker threads started.
+    fn load_async<T: AsyncAsset>(&mut self, key: T::Key) -> Result<AssetFuture<Handle<T>>>
+    where
+        T::Key: Send,
+    {
+        let status = Rc::new(RefCell::new(Status::default()));
+        let pool = T::pool_mut(self);
+        if let Some(&handle) = pool.by_key.get(&key) {
+            let slot = pool.slot_mut(handle).unwrap();
+            slot.refs += 1;
+            if slot.loading {
+                slot.waiters.push(status.clone());
+            } else {
+                status.borrow_mut().result = Some(Ok(()));
+            }
+            return Ok(AssetFuture { handle, status });
+        }
+
+        if self.decoder.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            self.decoder = Some(Decoder {
+                pool: ThreadPool::new("asset decode", ThreadPool::default_threads())?,
+                sender,
+                receiver,
+            });
+        }
+        let pool = T::pool_mut(self);
+        let placeholder = match &pool.placeholder {
+            Some(placeholder) => placeholder.clone(),
+            None => pool.placeholder.insert(Rc::new(T::placeholder()?)).clone(),
+        };
+        // Files edited while decoding are reloaded at the next check.
+        let stamps = T::files(&key).into_iter().map(modified).collect();
+        let handle = pool.insert(key.clone(), placeholder, stamps, true);
+        pool.slot_mut(handle).unwrap().waiters.push(status.clone());
+
+        let id = self.next_job;
+        self.next_job += 1;
+        let decoder = self.decoder.as_ref().unwrap();
+        let sender = decoder.sender.clone();
+        decoder.pool.spawn(move || {
+            let _span = tracing::info_span!("decode", path = ?T::files(&key)).entered();
+            let decoded = T::decode(&key).map(|decoded| Box::new(decoded) as Box<dyn Any + Send>);
+            let _ = sender.send((id, decoded));
+        });
+        self.decoding.insert(
+            id,
+            Box::new(move |assets: &mut Assets, decoded: Decoded| {
+                // Released while decoding, nothing to upload.
+                let Some(key) = T::pool(assets)
+                    .slot(handle)
+                    .filter(|slot| slot.loading)
+                    .and_then(|slot| slot.entry.as_ref())
+                    .map(|(key, _)| key.clone())
+                else {
+                    return;
+                };
+                let _span = tracing::info_span!("upload", path = ?T::files(&key)).entered();
+                let decoded = decoded.map(|decoded| *decoded.downcast::<T::Decoded>().unwrap());
+                match decoded.and_then(|decoded| T::upload(&key, decoded)) {
+                    Ok(value) => {
+                        let finish = move |assets: &mut Assets| {
+                            T::pool_mut(assets).finish(handle, Ok(value));
+                        };
+                        assets.uploading.push((Fence::new(), Box::new(finish)));
+                    }
+                    Err(e) => T::pool_mut(assets).finish(handle, Err(e)),
+                }
+            }),
+        );
+        Ok(AssetFuture { handle, status })
+    }
+
+    /// Uploads assets whose decoding finished and swaps in those whose upload has
+    /// completed, resolving their futures. Call once per frame while loads are pending.
+    pub fn update(&mut self) {
+        let decoded: Vec<_> = match &self.decoder {
+            Some(decoder) => decoder.receiver.try_iter().collect(),
+            None => return,
+        };
+        for (id, decoded) in decoded {
+            if let Some(upload) = self.decoding.remove(&id) {
+                upload(self, decoded);
+            }
+        }
+        let ready = self
+            .uploading
+            .iter()
+            .take_while(|(fence, _)| fence.is_signaled())
+            .count();
+        let finished: Vec<_> = self.uploading.drain(..ready).collect();
+        for (_, finish) in finished {
+            finish(self);
+        }
+    }
+
+    /// Number of asynchronous loads not finished yet.
+    pub fn loading(&self) -> usize {
+        self.decoding.len() + self.uploading.len()
+    }
+
+    /// Calls `hook` with every reload made by [`Assets::reload_changed`], replacing any
+    /// previous hook.
+    pub fn on_reload(&mut self, hook: impl FnMut(&ReloadEvent) + 'static) {
+        self.hook = Some(Box::new(hook));
+    }
+
+    /// Reloads assets whose files changed on disk and returns the attempts. Checks the
+    /// files at most twice a second, so it can be called every frame.
+    pub fn reload_changed(&mut self) -> Vec<ReloadEvent> {
+        let now = Instant::now();
+        if now < self.next_check {
+            return Vec::new();
+        }
+        self.next_check = now + CHECK_INTERVAL;
+
+        let mut events = Vec::new();
+        collect(&mut events, self.textures.reload_changed());
+        collect(&mut events, self.models.reload_changed());
+        collect(&mut events, self.programs.reload_changed());
+        #[cfg(feature = "gltf")]
+        collect(&mut events, self.scenes.reload_changed());
+        self.notify(&events);
+        events
+    }
+
+    /// Loads every asset again after the GL context was replaced (see
+    /// [`crate::app::App::context_restored`]), keeping all handles valid, and returns the
+    /// attempts, which also go to the [`Assets::on_reload`] hook. `Rc` clones held
+    /// elsewhere still point at the previous values, whose names are not deleted when they
+    /// drop since they belong to the lost context (see [`crate::context::GlContext`]).
+    pub fn recreate_all(&mut self) -> Vec<ReloadEvent> {
+        // Uploads into the old context are finished with their dead objects, which are
+        // then loaded again with everything else.
+        for (fence, finish) in std::mem::take(&mut self.uploading) {
+            std::mem::forget(fence);
+            finish(self);
+        }
+        let mut events = Vec::new();
+        collect(&mut events, self.textures.recreate_all());
+        collect(&mut events, self.models.recreate_all());
+        collect(&mut events, self.programs.recreate_all());
+        #[cfg(feature = "gltf")]
+        collect(&mut events, self.scenes.recreate_all());
+        self.notify(&events);
+        events
+    }
+
+    fn notify(&mut self, events: &[ReloadEvent]) {
+        if let Some(hook) = &mut self.hook {
+            for event in events {
+                hook(event);
+            }
+        }
+    }
+
+    /// The asset behind `handle`, or `None` once it has been unloaded.
+    pub fn get<T: Asset>(&self, handle: Handle<T>) -> Option<&Rc<T>> {
+        T::pool(self)
+            .slot(handle)
+            .and_then(|slot| slot.entry.as_ref().map(|(_, value)| value))
+    }
+
+    /// What `handle` was loaded from.
+    pub fn key<'a, T: Asset + 'a>(&'a self, handle: Handle<T>) -> Option<&'a T::Key> {
+        T::pool(self)
+            .slot(handle)
+            .and_then(|slot| slot.entry.as_ref().map(|(key, _)| key))
+    }
+
+    /// Number of outstanding references to `handle`'s asset; zero once unloaded.
+    pub fn ref_count<T: Asset>(&self, handle: Handle<T>) -> usize {
+        T::pool(self).slot(handle).map_or(0, |slot| slot.refs)
+    }
+
+    /// Adds a reference, for a second owner of the same handle.
+    pub fn retain<T: Asset>(&mut self, handle: Handle<T>) {
+        if let Some(slot) = T::pool_mut(self).slot_mut(handle) {
+            slot.refs += 1;
+        }
+    }
+
+    /// Drops a reference, unloading the asset when it was the last. Returns whether the
+    /// asset was unloaded.
+    pub fn release<T: Asset>(&mut self, handle: Handle<T>) -> bool {
+        let pool = T::pool_mut(self);
+        let Some(slot) = pool.slot_mut(handle) else {
+            return false;
+        };
+        slot.refs -= 1;
+        if slot.refs > 0 {
+            return false;
+        }
+        pool.remove(handle);
+        true
+    }
+
+    /// Unloads the asset regardless of outstanding references, which become stale.
+    pub fn unload<T: Asset>(&mut self, handle: Handle<T>) -> Option<Rc<T>> {
+        T::pool_mut(self).remove(handle)
+    }
+
+    /// Number of loaded assets of type `T`.
+    pub fn count<T: Asset>(&self) -> usize {
+        T::pool(self).len()
+    }
+}
+
+fn collect<T: Asset>(
+    events: &mut Vec<ReloadEvent>,
+    reloaded: Vec<(Handle<T>, PathBuf, Result<()>)>,
+) {
+    events.extend(
+        reloaded
+            .into_iter()
+            .map(|(handle, path, result)| ReloadEvent {
+                asset: T::reloaded(handle),
+                path,
+                result,
+            }),
+    );
+}
@@ -0,0 +1,183 @@
+//! Rectangle packing into atlas textures.
+//!
+//! [`AtlasBuilder`] packs many small images into one texture up front and returns a
+//! [`TextureAtlas`] of their regions, so a [`crate::sprite::SpriteBatch`] can draw them
+//! all without switching textures. [`ShelfPacker`] is the underlying packer, also used
+//! on its own by [`crate::text`] to add glyphs to an atlas as they are first needed.
+
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::gl;
+use crate::image::Image;
+use crate::sprite::TextureAtlas;
+use crate::texture::Texture;
+
+/// A row-based ("shelf") rectangle packer. Rectangles are placed left to right in rows
+/// as tall as the tallest rectangle in them; packing in order of decreasing height
+/// wastes the least space.
+pub struct ShelfPacker {
+    width: u32,
+    height: u32,
+    padding: u32,
+    x: u32,
+    y: u32,
+    row_height: u32,
+}
+
+impl ShelfPacker {
+    /// An empty `width` x `height` area, leaving `padding` pixels between rectangles so
+    /// linear filtering doesn't bleed neighbours in.
+    pub fn new(width: u32, height: u32, padding: u32) -> ShelfPacker {
+        ShelfPacker {
+            width,
+            height,
+            padding,
+            x: 0,
+            y: 0,
+            row_height: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Reserves a `width` x `height` rectangle and returns its top-left corner, or
+    /// `None` when it doesn't fit in the remaining space.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.x + width > self.width {
+            self.x = 0;
+            self.y += self.row_height + self.padding;
+            self.row_height = 0;
+        }
+        if self.y + height > self.height || width > self.width {
+            return None;
+        }
+        let position = (self.x, self.y);
+        self.x += width + self.padding;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+/// Images packed on the CPU by [`AtlasBuilder::pack`].
+pub struct Packed {
+    pub image: Image,
+    /// The index of every image, in the order it was added, and its top-left corner.
+    pub placements: Vec<(usize, (u32, u32))>,
+}
+
+/// Collects named images and packs them into a single atlas texture.
+pub struct AtlasBuilder {
+    images: Vec<(String, Image)>,
+    max_size: u32,
+    padding: u32,
+    filter: gl::types::GLenum,
+}
+
+impl AtlasBuilder {
+    /// A builder for atlases of at most `max_size` x `max_size` pixels.
+    pub fn new(max_size: u32) -> AtlasBuilder {
+        AtlasBuilder {
+            images: Vec::new(),
+            max_size,
+            padding: 1,
+            filter: gl::LINEAR,
+        }
+    }
+
+    /// Pixels left between packed images; 1 by default.
+    pub fn padding(mut self, padding: u32) -> AtlasBuilder {
+        self.padding = padding;
+        self
+    }
+
+    /// Minification and magnification filter of the texture; `GL_LINEAR` by default.
+    /// Pixel art wants `GL_NEAREST`, e.g. from [`crate::camera2d::Camera2D::texture_filter`].
+    pub fn filter(mut self, filter: gl::types::GLenum) -> AtlasBuilder {
+        self.filter = filter;
+        self
+    }
+
+    /// Adds an image, replacing any earlier one with the same name.
+    pub fn add(&mut self, name: &str, image: Image) -> &mut AtlasBuilder {
+        self.images.retain(|(existing, _)| existing != name);
+        self.images.push((name.to_owned(), image));
+        self
+    }
+
+    /// Packs the images into the smallest power-of-two square that holds them all and
+    /// uploads it as a clamped `RGBA8` texture with the builder's filter.
+    pub fn build(&self) -> Result<TextureAtlas> {
+        let Packed { image, placements } = self.pack()?;
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, self.filter as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, self.filter as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        texture.image_level(0, &image);
+        texture.unbind();
+        texture.label("sprite atlas");
+
+        let mut atlas = TextureAtlas::new(Rc::new(texture), image.width, image.height);
+        for (index, (x, y)) in placements {
+            let (name, image) = &self.images[index];
+            atlas.add_region(name, x, y, image.width, image.height);
+        }
+        Ok(atlas)
+    }
+
+    /// Packs the images on the CPU into the atlas image and the position of every image.
+    pub fn pack(&self) -> Result<Packed> {
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(self.images[i].1.height));
+
+        let mut size = 64.min(self.max_size);
+        let placements = loop {
+            let mut packer = ShelfPacker::new(size, size, self.padding);
+            let placements: Option<Vec<_>> = order
+                .iter()
+                .map(|&i| {
+                    let image = &self.images[i].1;
+                    packer.pack(image.width, image.height).map(|at| (i, at))
+                })
+                .collect();
+            match placements {
+                Some(placements) => break placements,
+                None if size < self.max_size => size = (size * 2).min(self.max_size),
+                None => {
+                    return Err(anyhow!(
+                        "{} images don't fit in a {}x{} atlas",
+                        self.images.len(),
+                        self.max_size,
+                        self.max_size
+                    ))
+                }
+            }
+        };
+
+        let mut pixels = vec![0u8; (size * size * 4) as usize];
+        for &(index, (x, y)) in &placements {
+            let image = &self.images[index].1;
+            let row = (image.width * 4) as usize;
+            for line in 0..image.height as usize {
+                let src = line * row;
+                let dst = (((y as usize + line) * size as usize) + x as usize) * 4;
+                pixels[dst..dst + row].copy_from_slice(&image.pixels[src..src + row]);
+            }
+        }
+        let image = Image {
+            width: size,
+            height: size,
+            pixels,
+        };
+        Ok(Packed { image, placements })
+    }
+}
@@ -0,0 +1,105 @@
+//! Atomic counter buffers (GL 4.2 / `ARB_shader_atomic_counters`).
+//!
+//! [`AtomicCounters`] holds a small array of `uint` counters that shaders increment with
+//! `atomicCounterIncrement`, e.g. to count visible fragments or to hand out slots when
+//! appending to a buffer. Declare them in GLSL as
+//! `layout (binding = 0, offset = 0) uniform atomic_uint u_count;`, with the binding
+//! passed to [`AtomicCounters::bind`] and the offset being four times the counter index.
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::Buffer;
+use crate::context;
+use crate::gl;
+use crate::load_store::{self, Barriers};
+
+/// Whether the context supports atomic counters.
+pub fn is_supported() -> bool {
+    context::version() >= (4, 2) || context::has_extension("GL_ARB_shader_atomic_counters")
+}
+
+pub struct AtomicCounters {
+    buffer: Buffer,
+    count: usize,
+}
+
+impl AtomicCounters {
+    /// Creates `count` counters, all zero.
+    pub fn new(count: usize) -> Result<AtomicCounters> {
+        if !is_supported() {
+            return Err(anyhow!("Atomic counters require GL 4.2"));
+        }
+        let buffer = Buffer::new()?;
+        buffer.bind(gl::ATOMIC_COUNTER_BUFFER);
+        buffer.data(
+            gl::ATOMIC_COUNTER_BUFFER,
+            bytemuck::cast_slice(&vec![0u32; count]),
+            gl::DYNAMIC_READ,
+        );
+        buffer.unbind(gl::ATOMIC_COUNTER_BUFFER);
+        buffer.label("atomic counters");
+        Ok(AtomicCounters { buffer, count })
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Binds the counters to `GL_ATOMIC_COUNTER_BUFFER` binding `binding`.
+    pub fn bind(&self, binding: u32) {
+        self.buffer.bind_base(gl::ATOMIC_COUNTER_BUFFER, binding);
+    }
+
+    /// Overwrites the counters with `values`, starting at the first.
+    pub fn set(&self, values: &[u32]) {
+        assert!(values.len() <= self.count, "more values than counters");
+        self.buffer.bind(gl::ATOMIC_COUNTER_BUFFER);
+        self.buffer
+            .sub_data(gl::ATOMIC_COUNTER_BUFFER, 0, bytemuck::cast_slice(values));
+        self.buffer.unbind(gl::ATOMIC_COUNTER_BUFFER);
+    }
+
+    /// Sets every counter to zero, typically at the start of a frame.
+    pub fn reset(&self) {
+        self.set(&vec![0; self.count]);
+    }
+
+    /// Reads all counters back. Issues the barrier that makes earlier shader increments
+    /// visible, then stalls until the GPU has finished them; for per-frame statistics
+    /// prefer copying into a buffer read a few frames later.
+    pub fn read(&self) -> Vec<u32> {
+        load_store::memory_barrier(Barriers::BUFFER_UPDATE);
+        let mut values = vec![0u32; self.count];
+        self.buffer.bind(gl::ATOMIC_COUNTER_BUFFER);
+        self.buffer.get_sub_data(
+            gl::ATOMIC_COUNTER_BUFFER,
+            0,
+            bytemuck::cast_slice_mut(&mut values),
+        );
+        self.buffer.unbind(gl::ATOMIC_COUNTER_BUFFER);
+        values
+    }
+
+    /// Reads the counter at `index`. See [`AtomicCounters::read`].
+    pub fn get(&self, index: usize) -> u32 {
+        assert!(index < self.count, "counter index out of range");
+        load_store::memory_barrier(Barriers::BUFFER_UPDATE);
+        let mut value = 0u32;
+        self.buffer.bind(gl::ATOMIC_COUNTER_BUFFER);
+        self.buffer.get_sub_data(
+            gl::ATOMIC_COUNTER_BUFFER,
+            index * 4,
+            bytemuck::bytes_of_mut(&mut value),
+        );
+        self.buffer.unbind(gl::ATOMIC_COUNTER_BUFFER);
+        value
+    }
+}
@@ -0,0 +1,282 @@
+//! `hello-gl --bench 1000`: renders a fixed number of frames without vsync and writes
+//! per-frame timings to `--bench-output`, as CSV or, for a `.json` path, JSON.
+//!
+//! Each frame records the time since the previous frame, the CPU time spent in the
+//! app's `update` and `render` (submission, not presentation), and the GPU time of
+//! `render` from a [`GpuTimer`] where the context has timer queries. The first
+//! [`WARMUP`] frames, which include shader compilation and first-use uploads, are
+//! rendered but not recorded. The app advances by a fixed [`STEP`] per frame whatever
+//! the measured frame time, so animated workloads, e.g. driven by [`hello_gl::tracks`],
+//! render the same frames on every run.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use hello_gl::app::{self, App};
+use hello_gl::context;
+use hello_gl::event::WindowEvent;
+use hello_gl::profiler::GpuTimer;
+
+/// Frames rendered before recording starts.
+pub const WARMUP: u64 = 10;
+
+/// Seconds the app's `update` is told each frame took.
+pub const STEP: f32 = 1.0 / 60.0;
+
+struct Record {
+    frame_ms: f64,
+    cpu_ms: f64,
+    gpu_ms: Option<f64>,
+}
+
+/// Mean, 95th percentile and maximum of `values`.
+fn summary(values: &[f64]) -> (f64, f64, f64) {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mean = sorted.iter().sum::<f64>() / sorted.len().max(1) as f64;
+    let p95 = sorted
+        .get((sorted.len() * 95).div_ceil(100).saturating_sub(1))
+        .copied()
+        .unwrap_or(0.0);
+    (mean, p95, sorted.last().copied().unwrap_or(0.0))
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Wraps the app being measured.
+pub struct Bench {
+    app: Box<dyn App>,
+    name: String,
+    frames: u64,
+    output: PathBuf,
+    gpu: Option<GpuTimer>,
+    warmup: u64,
+    records: Vec<Record>,
+    update_start: Instant,
+    frame_ms: f64,
+    done: bool,
+}
+
+impl Bench {
+    /// Measures `frames` frames of `app`, described as `name` in the report.
+    pub fn new(app: Box<dyn App>, name: &str, frames: u64, output: PathBuf) -> Bench {
+        let gpu = match GpuTimer::new() {
+            Ok(gpu) => Some(gpu),
+            Err(e) => {
+                tracing::warn!("{:#}; GPU times are left out", e);
+                None
+            }
+        };
+        Bench {
+            app,
+            name: name.to_string(),
+            frames,
+            output,
+            gpu,
+            warmup: WARMUP,
+            records: Vec::with_capacity(frames as usize),
+            update_start: Instant::now(),
+            frame_ms: 0.0,
+            done: false,
+        }
+    }
+
+    fn collect_gpu_times(&mut self, finish: bool) {
+        let Some(gpu) = &mut self.gpu else {
+            return;
+        };
+        let times = if finish { gpu.finish() } else { gpu.poll() };
+        for time in times {
+            if let Some(record) = self.records.get_mut(time.frame as usize) {
+                record.gpu_ms = Some(time.seconds * 1000.0);
+            }
+        }
+    }
+
+    fn csv(&self) -> String {
+        let mut out = String::from("frame,frame_ms,cpu_ms,gpu_ms\n");
+        for (i, record) in self.records.iter().enumerate() {
+            let gpu = record
+                .gpu_ms
+                .map_or(String::new(), |ms| format!("{:.4}", ms));
+            writeln!(
+                out,
+                "{},{:.4},{:.4},{}",
+                i, record.frame_ms, record.cpu_ms, gpu
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    fn json(&self) -> String {
+        let info = context::info();
+        let metric = |values: Vec<f64>| {
+            let (mean, p95, max) = summary(&values);
+            format!(
+                "{{\"mean\": {:.4}, \"p95\": {:.4}, \"max\": {:.4}}}",
+                mean, p95, max
+            )
+        };
+        let mut out = String::from("{\n");
+        writeln!(out, "  \"scene\": {},", json_string(&self.name)).unwrap();
+        writeln!(out, "  \"renderer\": {},", json_string(&info.renderer)).unwrap();
+        writeln!(out, "  \"version\": {},", json_string(&info.version_string)).unwrap();
+        writeln!(out, "  \"warmup\": {},", WARMUP).unwrap();
+        out.push_str("  \"summary\": {\n");
+        writeln!(
+            out,
+            "    \"frame_ms\": {},",
+            metric(self.records.iter().map(|r| r.frame_ms).collect())
+        )
+        .unwrap();
+        write!(
+            out,
+            "    \"cpu_ms\": {}",
+            metric(self.records.iter().map(|r| r.cpu_ms).collect())
+        )
+        .unwrap();
+        if self.gpu.is_some() {
+            write!(
+                out,
+                ",\n    \"gpu_ms\": {}",
+                metric(self.records.iter().filter_map(|r| r.gpu_ms).collect())
+            )
+            .unwrap();
+        }
+        out.push_str("\n  },\n  \"frames\": [\n");
+        for (i, record) in self.records.iter().enumerate() {
+            let gpu = record
+                .gpu_ms
+                .map_or("null".to_string(), |ms| format!("{:.4}", ms));
+            let separator = if i + 1 == self.records.len() { "" } else { "," };
+            writeln!(
+                out,
+                "    {{\"frame\": {}, \"frame_ms\": {:.4}, \"cpu_ms\": {:.4}, \"gpu_ms\": {}}}{}",
+                i, record.frame_ms, record.cpu_ms, gpu, separator
+            )
+            .unwrap();
+        }
+        out.push_str("  ]\n}\n");
+        out
+    }
+
+    fn write_report(&self) -> Result<()> {
+        let json = self
+            .output
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let report = if json { self.json() } else { self.csv() };
+        std::fs::write(&self.output, report)
+            .map_err(|e| anyhow!("Failed to write {}: {}", self.output.display(), e))?;
+
+        let line = |label: &str, values: Vec<f64>| {
+            let (mean, p95, max) = summary(&values);
+            println!(
+                "  {:<5} mean {:.3} ms  p95 {:.3} ms  max {:.3} ms",
+                label, mean, p95, max
+            );
+        };
+        println!("{}: {} frames", self.name, self.records.len());
+        line("frame", self.records.iter().map(|r| r.frame_ms).collect());
+        line("cpu", self.records.iter().map(|r| r.cpu_ms).collect());
+        if self.gpu.is_some() {
+            line(
+                "gpu",
+                self.records.iter().filter_map(|r| r.gpu_ms).collect(),
+            );
+        }
+        println!("Wrote {}", self.output.display());
+        Ok(())
+    }
+}
+
+impl App for Bench {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.app.resize(width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.app.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.update_start = Instant::now();
+        self.frame_ms = dt as f64 * 1000.0;
+        self.app.update(STEP);
+    }
+
+    fn update_window(&mut self, window: &mut dyn app::Window) {
+        self.app.update_window(window);
+    }
+
+    fn render(&mut self) {
+        if self.done {
+            self.app.render();
+            return;
+        }
+        if self.warmup > 0 {
+            self.warmup -= 1;
+            self.app.render();
+            return;
+        }
+
+        let timed = match &mut self.gpu {
+            Some(gpu) => match gpu.begin() {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("{:#}; GPU times are left out", e);
+                    self.gpu = None;
+                    false
+                }
+            },
+            None => false,
+        };
+        self.app.render();
+        if timed {
+            self.gpu.as_mut().unwrap().end();
+        }
+        self.records.push(Record {
+            frame_ms: self.frame_ms,
+            cpu_ms: self.update_start.elapsed().as_secs_f64() * 1000.0,
+            gpu_ms: None,
+        });
+
+        let finished = self.records.len() as u64 >= self.frames;
+        self.collect_gpu_times(finished);
+        if finished {
+            if let Err(e) = self.write_report() {
+                tracing::error!("{:#}", e);
+            }
+            self.done = true;
+        }
+    }
+
+    fn exit_requested(&self) -> bool {
+        self.done
+    }
+
+    /// Drops the GPU timer along with the context's other objects; GPU times stop here.
+    fn context_lost(&mut self, status: context::ResetStatus) {
+        self.gpu = None;
+        self.app.context_lost(status);
+    }
+
+    fn context_restored(&mut self) -> Result<()> {
+        self.app.context_restored()
+    }
+}
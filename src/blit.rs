@@ -0,0 +1,75 @@
+//! Fullscreen passes and texture copies.
+//!
+//! A [`Blitter`] draws the vertexless [`FullscreenTriangle`], either with a fragment
+//! shader from [`Blitter::program`] or with its own copy shader. Unlike
+//! `glBlitFramebuffer` a copy converts between any color formats, e.g. a float target to
+//! the 8-bit default framebuffer on ES, where blits need matching formats; it filters
+//! with the texture's own sampling state and honors blending.
+
+use anyhow::Result;
+
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::texture::Texture;
+use crate::viewport::Viewport;
+
+/// Samples `u_texture` at the fragment's `uv`.
+pub const COPY_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_texture;
+void main() {
+    frag_color = texture(u_texture, uv);
+}
+"#;
+
+pub struct Blitter {
+    triangle: FullscreenTriangle,
+    copy: Program,
+}
+
+impl Blitter {
+    pub fn new() -> Result<Blitter> {
+        Ok(Blitter {
+            triangle: FullscreenTriangle::new()?,
+            copy: Blitter::program(COPY_SHADER)?,
+        })
+    }
+
+    /// Compiles `fragment` against [`FULLSCREEN_VERTEX_SHADER`]. It receives `in vec2 uv`,
+    /// running from 0 to 1 across the viewport.
+    pub fn program(fragment: &str) -> Result<Program> {
+        Program::from_sources(FULLSCREEN_VERTEX_SHADER, fragment)
+    }
+
+    /// Covers the viewport with whatever program is currently in use.
+    pub fn draw(&self) {
+        self.triangle.draw();
+    }
+
+    /// Covers the viewport with `texture`, a `GL_TEXTURE_2D`, bound to unit 0. Depth
+    /// testing and blending apply as currently set.
+    pub fn copy(&self, texture: &Texture) {
+        self.copy.use_program();
+        texture.bind_unit(0);
+        self.copy.set_int("u_texture", 0);
+        self.triangle.draw();
+    }
+
+    /// Copies `texture` into `viewport` of `target`, or of the default framebuffer for
+    /// `None`, with depth testing off, e.g. to present an offscreen target. Leaves the
+    /// target bound with `viewport` set.
+    pub fn copy_to(&self, texture: &Texture, target: Option<&Framebuffer>, viewport: Viewport) {
+        match target {
+            Some(target) => target.bind(gl::FRAMEBUFFER),
+            None => Framebuffer::bind_default(gl::FRAMEBUFFER),
+        }
+        unsafe {
+            gl::Viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        self.copy(texture);
+    }
+}
@@ -0,0 +1,418 @@
+//! Runtime checks that Rust structs match the layout of uniform and storage blocks.
+//!
+//! std140 and std430 pad members in ways `#[repr(C)]` doesn't: a `vec3` is aligned to
+//! 16 bytes, std140 rounds array elements up to 16 bytes, and a `mat3` has 16-byte
+//! columns. A struct that disagrees uploads without complaint and the shader reads
+//! shifted garbage.
+//!
+//! [`BlockLayout::query`] asks GL where every member of a block really lives, through
+//! the program interface queries of GL 4.3 (`glGetProgramResource*`), or for uniform
+//! blocks on older contexts `glGetActiveUniformsiv`. [`BlockLayout::verify`] compares
+//! it with the [`Field`]s of the Rust struct, built with [`std::mem::offset_of!`], and
+//! names every member that disagrees. The crate runs [`check`] on its own blocks in
+//! debug builds.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::context;
+use crate::gl;
+use crate::shader::Program;
+
+/// The two kinds of interface block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockKind {
+    /// A `uniform` block, backed by a `GL_UNIFORM_BUFFER`.
+    Uniform,
+    /// A `buffer` block, backed by a `GL_SHADER_STORAGE_BUFFER`.
+    ShaderStorage,
+}
+
+impl BlockKind {
+    fn interfaces(self) -> (gl::types::GLenum, gl::types::GLenum) {
+        match self {
+            BlockKind::Uniform => (gl::UNIFORM_BLOCK, gl::UNIFORM),
+            BlockKind::ShaderStorage => (gl::SHADER_STORAGE_BLOCK, gl::BUFFER_VARIABLE),
+        }
+    }
+}
+
+/// Whether the context supports program interface queries, needed for storage blocks.
+pub fn is_supported() -> bool {
+    let info = context::info();
+    let version = if info.es { (3, 1) } else { (4, 3) };
+    gl::GetProgramResourceiv::is_loaded()
+        && (info.version >= version || info.has_extension("GL_ARB_program_interface_query"))
+}
+
+/// One member of a block as GL laid it out. Arrays of structs in uniform blocks list
+/// every element's members, e.g. `lights[0].color` and `lights[1].color`; storage
+/// blocks list only the first element of their top-level array.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockMember {
+    pub name: String,
+    /// The GLSL type, e.g. `GL_FLOAT_VEC3`.
+    pub ty: gl::types::GLenum,
+    /// Bytes from the start of the block.
+    pub offset: usize,
+    /// Array length; 1 unless the member is an array, 0 for a runtime-sized one.
+    pub array_size: usize,
+    /// Bytes between elements of an array member; 0 otherwise.
+    pub array_stride: usize,
+    /// Bytes between the columns of a matrix member; 0 otherwise.
+    pub matrix_stride: usize,
+    /// Bytes between elements of the top-level array of a storage block member, e.g. the
+    /// `Particle` struct size for `particles[0].velocity`; 0 otherwise.
+    pub top_level_array_stride: usize,
+}
+
+/// The layout of one uniform or storage block of a linked program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockLayout {
+    pub name: String,
+    pub kind: BlockKind,
+    /// Bytes the block needs, counting one element of a runtime-sized array.
+    pub size: usize,
+    pub members: Vec<BlockMember>,
+}
+
+/// Where a Rust struct puts a block member, for [`BlockLayout::verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The member's name as GL reports it; see [`BlockMember::name`].
+    pub name: String,
+    pub offset: usize,
+    /// Bytes between array elements, or 0 to skip the check.
+    pub stride: usize,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, offset: usize) -> Field {
+        Field {
+            name: name.into(),
+            offset,
+            stride: 0,
+        }
+    }
+
+    /// An array member whose elements are `stride` bytes apart.
+    pub fn array(name: impl Into<String>, offset: usize, stride: usize) -> Field {
+        Field {
+            stride,
+            ..Field::new(name, offset)
+        }
+    }
+
+    /// The `element` fields of a struct as members of `array[0]`, for an array at
+    /// `offset` whose elements are `stride` bytes apart, usually `size_of` the struct.
+    pub fn struct_array(
+        array: &str,
+        offset: usize,
+        stride: usize,
+        element: &[Field],
+    ) -> Vec<Field> {
+        element
+            .iter()
+            .map(|field| Field {
+                name: format!("{}[0].{}", array, field.name),
+                offset: offset + field.offset,
+                stride,
+            })
+            .collect()
+    }
+}
+
+impl BlockLayout {
+    /// Reads the layout of the block `name` from `program`. Fails if the program has no
+    /// such active block, or for a storage block without [`is_supported`].
+    pub fn query(program: &Program, kind: BlockKind, name: &str) -> Result<BlockLayout> {
+        if is_supported() {
+            query_resources(program.id(), kind, name)
+        } else if kind == BlockKind::Uniform {
+            query_uniform_block(program.id(), name)
+        } else {
+            Err(anyhow!(
+                "Storage block layouts need GL 4.3 or ARB_program_interface_query"
+            ))
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Option<&BlockMember> {
+        self.members.iter().find(|member| member.name == name)
+    }
+
+    /// Bytes between the elements of the array `member` belongs to: its own array, the
+    /// top-level array of a storage block, or the array of structs around it.
+    pub fn stride(&self, member: &BlockMember) -> Option<usize> {
+        if member.array_stride > 0 {
+            return Some(member.array_stride);
+        }
+        if member.top_level_array_stride > 0 {
+            return Some(member.top_level_array_stride);
+        }
+        let next = member.name.replacen("[0]", "[1]", 1);
+        let next = self.member(&next).filter(|_| next != member.name)?;
+        next.offset.checked_sub(member.offset)
+    }
+
+    /// Compares the layout with a Rust struct of `size` bytes, if given, and the offsets
+    /// and strides of its `fields`. The error names every member that disagrees.
+    pub fn verify(&self, size: Option<usize>, fields: &[Field]) -> Result<()> {
+        let mut problems = String::new();
+        if let Some(size) = size {
+            if size != self.size {
+                writeln!(
+                    problems,
+                    "  the block is {} bytes, the struct {}",
+                    self.size, size
+                )?;
+            }
+        }
+        for field in fields {
+            let Some(member) = self.member(&field.name) else {
+                writeln!(problems, "  `{}` is not a member", field.name)?;
+                continue;
+            };
+            if member.offset != field.offset {
+                writeln!(
+                    problems,
+                    "  `{}` is at offset {} in the block, {} in the struct",
+                    field.name, member.offset, field.offset
+                )?;
+            }
+            if field.stride != 0 {
+                match self.stride(member) {
+                    Some(stride) if stride == field.stride => {}
+                    Some(stride) => writeln!(
+                        problems,
+                        "  `{}` has a stride of {} in the block, {} in the struct",
+                        field.name, stride, field.stride
+                    )?,
+                    None => writeln!(problems, "  `{}` is not in an array", field.name)?,
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Layout of block `{}` doesn't match the Rust struct:\n{}",
+                self.name,
+                problems.trim_end()
+            ))
+        }
+    }
+}
+
+/// [`BlockLayout::verify`] for the block `name` of `program` in debug builds. Passes
+/// when the program doesn't use the block or the context can't describe it, and in
+/// release builds, which skip the queries.
+pub fn check(
+    program: &Program,
+    kind: BlockKind,
+    name: &str,
+    size: Option<usize>,
+    fields: &[Field],
+) -> Result<()> {
+    if !cfg!(debug_assertions) || (kind == BlockKind::ShaderStorage && !is_supported()) {
+        return Ok(());
+    }
+    match BlockLayout::query(program, kind, name) {
+        Ok(layout) => layout.verify(size, fields),
+        Err(_) => Ok(()),
+    }
+}
+
+fn query_resources(program: gl::types::GLuint, kind: BlockKind, name: &str) -> Result<BlockLayout> {
+    let (block_interface, member_interface) = kind.interfaces();
+    let c_name = std::ffi::CString::new(name)?;
+    let index = unsafe { gl::GetProgramResourceIndex(program, block_interface, c_name.as_ptr()) };
+    if index == gl::INVALID_INDEX {
+        return Err(anyhow!("Program has no active block `{}`", name));
+    }
+    let [size, count] = resource_ints(
+        program,
+        block_interface,
+        index,
+        [gl::BUFFER_DATA_SIZE, gl::NUM_ACTIVE_VARIABLES],
+    );
+    let mut indices = vec![0; count as usize];
+    unsafe {
+        gl::GetProgramResourceiv(
+            program,
+            block_interface,
+            index,
+            1,
+            &gl::ACTIVE_VARIABLES,
+            count,
+            std::ptr::null_mut(),
+            indices.as_mut_ptr(),
+        );
+    }
+
+    let members = indices
+        .into_iter()
+        .map(|index| {
+            let index = index as u32;
+            let [name_length, ty, offset, array_size, array_stride, matrix_stride] = resource_ints(
+                program,
+                member_interface,
+                index,
+                [
+                    gl::NAME_LENGTH,
+                    gl::TYPE,
+                    gl::OFFSET,
+                    gl::ARRAY_SIZE,
+                    gl::ARRAY_STRIDE,
+                    gl::MATRIX_STRIDE,
+                ],
+            );
+            let [top_level_array_stride] = match kind {
+                BlockKind::Uniform => [0],
+                BlockKind::ShaderStorage => resource_ints(
+                    program,
+                    member_interface,
+                    index,
+                    [gl::TOP_LEVEL_ARRAY_STRIDE],
+                ),
+            };
+            let mut name = vec![0u8; name_length.max(1) as usize];
+            let mut length = 0;
+            unsafe {
+                gl::GetProgramResourceName(
+                    program,
+                    member_interface,
+                    index,
+                    name.len() as i32,
+                    &mut length,
+                    name.as_mut_ptr().cast(),
+                );
+            }
+            name.truncate(length as usize);
+            BlockMember {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                ty: ty as gl::types::GLenum,
+                offset: offset as usize,
+                array_size: array_size as usize,
+                array_stride: array_stride.max(0) as usize,
+                matrix_stride: matrix_stride.max(0) as usize,
+                top_level_array_stride: top_level_array_stride.max(0) as usize,
+            }
+        })
+        .collect();
+    Ok(BlockLayout {
+        name: name.to_string(),
+        kind,
+        size: size as usize,
+        members: sorted(members),
+    })
+}
+
+fn resource_ints<const N: usize>(
+    program: gl::types::GLuint,
+    interface: gl::types::GLenum,
+    index: gl::types::GLuint,
+    props: [gl::types::GLenum; N],
+) -> [i32; N] {
+    let mut values = [0; N];
+    unsafe {
+        gl::GetProgramResourceiv(
+            program,
+            interface,
+            index,
+            N as i32,
+            props.as_ptr(),
+            N as i32,
+            std::ptr::null_mut(),
+            values.as_mut_ptr(),
+        );
+    }
+    values
+}
+
+/// The GL 3.1 path for uniform blocks.
+fn query_uniform_block(program: gl::types::GLuint, name: &str) -> Result<BlockLayout> {
+    let c_name = std::ffi::CString::new(name)?;
+    let index = unsafe { gl::GetUniformBlockIndex(program, c_name.as_ptr()) };
+    if index == gl::INVALID_INDEX {
+        return Err(anyhow!("Program has no active block `{}`", name));
+    }
+    let (mut size, mut count) = (0, 0);
+    unsafe {
+        gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        gl::GetActiveUniformBlockiv(
+            program,
+            index,
+            gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS,
+            &mut count,
+        );
+    }
+    let mut indices = vec![0; count as usize];
+    unsafe {
+        gl::GetActiveUniformBlockiv(
+            program,
+            index,
+            gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES,
+            indices.as_mut_ptr(),
+        );
+    }
+    let indices: Vec<u32> = indices.into_iter().map(|index| index as u32).collect();
+    let uniforms = |pname| {
+        let mut values = vec![0; indices.len()];
+        unsafe {
+            gl::GetActiveUniformsiv(
+                program,
+                indices.len() as i32,
+                indices.as_ptr(),
+                pname,
+                values.as_mut_ptr(),
+            );
+        }
+        values
+    };
+    let types = uniforms(gl::UNIFORM_TYPE);
+    let offsets = uniforms(gl::UNIFORM_OFFSET);
+    let sizes = uniforms(gl::UNIFORM_SIZE);
+    let array_strides = uniforms(gl::UNIFORM_ARRAY_STRIDE);
+    let matrix_strides = uniforms(gl::UNIFORM_MATRIX_STRIDE);
+
+    let members = indices
+        .iter()
+        .enumerate()
+        .map(|(i, &index)| {
+            let mut name = vec![0u8; 256];
+            let mut length = 0;
+            unsafe {
+                gl::GetActiveUniformName(
+                    program,
+                    index,
+                    name.len() as i32,
+                    &mut length,
+                    name.as_mut_ptr().cast(),
+                );
+            }
+            name.truncate(length as usize);
+            BlockMember {
+                name: String::from_utf8_lossy(&name).into_owned(),
+                ty: types[i] as gl::types::GLenum,
+                offset: offsets[i] as usize,
+                array_size: sizes[i] as usize,
+                array_stride: array_strides[i].max(0) as usize,
+                matrix_stride: matrix_strides[i].max(0) as usize,
+                top_level_array_stride: 0,
+            }
+        })
+        .collect();
+    Ok(BlockLayout {
+        name: name.to_string(),
+        kind: BlockKind::Uniform,
+        size: size as usize,
+        members: sorted(members),
+    })
+}
+
+fn sorted(mut members: Vec<BlockMember>) -> Vec<BlockMember> {
+    members.sort_by_key(|member| member.offset);
+    members
+}
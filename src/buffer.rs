@@ -0,0 +1,477 @@
+use std::cell::{Cell, RefCell};
+
+use anyhow::{anyhow, Result};
+
+use crate::context::{self, Deletion, GlContext};
+use crate::debug;
+use crate::dsa;
+use crate::gl;
+use crate::memory::{self, Resource};
+use crate::scoped::{self, Binding};
+use crate::shader::Program;
+use crate::state::StateCache;
+
+/// The format of one attribute of a [`VertexArray`], as set by
+/// [`VertexArray::attribute`] or [`VertexArray::integer_attribute`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub index: u32,
+    /// Components per vertex, 1 to 4.
+    pub size: i32,
+    /// The component type in the buffer, e.g. `GL_FLOAT`.
+    pub ty: gl::types::GLenum,
+    /// Read as `ivec`/`uvec` instead of converted to floats.
+    pub integer: bool,
+}
+
+/// A vertex array object. The second field records the attribute formats set through
+/// it, for [`VertexArray::check_program`].
+pub struct VertexArray(
+    pub(crate) gl::types::GLuint,
+    RefCell<Vec<VertexAttribute>>,
+    GlContext,
+);
+
+impl VertexArray {
+    pub fn new() -> Result<VertexArray> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateVertexArrays(1, &mut id);
+            } else {
+                gl::GenVertexArrays(1, &mut id);
+            }
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create vertex array"))
+        } else {
+            Ok(VertexArray(id, RefCell::new(Vec::new()), gl_context))
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the vertex array in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::VERTEX_ARRAY, self.0, label);
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.0);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Binds the vertex array through `cache` until the guard is dropped. See
+    /// [`crate::scoped`].
+    pub fn bind_scoped<'a>(&'a self, cache: &'a mut StateCache) -> Binding<'a> {
+        scoped::bind_vertex_array(cache, self.0)
+    }
+
+    /// Sources float attribute `index` from `buffer`: `size` components of type `ty`
+    /// every `stride` bytes, starting at byte `offset`. Leaves the vertex array unbound
+    /// on the fallback path.
+    #[allow(clippy::too_many_arguments)]
+    pub fn attribute(
+        &self,
+        index: u32,
+        buffer: &Buffer,
+        size: i32,
+        ty: gl::types::GLenum,
+        normalized: bool,
+        stride: i32,
+        offset: usize,
+    ) {
+        self.record(VertexAttribute {
+            index,
+            size,
+            ty,
+            integer: false,
+        });
+        let normalized = if normalized { gl::TRUE } else { gl::FALSE };
+        unsafe {
+            if dsa::is_available() {
+                // One binding per attribute keeps the interface identical to the
+                // pointer-based fallback.
+                gl::VertexArrayVertexBuffer(self.0, index, buffer.0, offset as isize, stride);
+                gl::VertexArrayAttribFormat(self.0, index, size, ty, normalized, 0);
+                gl::VertexArrayAttribBinding(self.0, index, index);
+                gl::EnableVertexArrayAttrib(self.0, index);
+            } else {
+                self.bind();
+                buffer.bind(gl::ARRAY_BUFFER);
+                gl::VertexAttribPointer(index, size, ty, normalized, stride, offset as *const _);
+                gl::EnableVertexAttribArray(index);
+                self.unbind();
+            }
+        }
+    }
+
+    /// Like [`VertexArray::attribute`] for integer attributes read as `ivec`/`uvec`.
+    pub fn integer_attribute(
+        &self,
+        index: u32,
+        buffer: &Buffer,
+        size: i32,
+        ty: gl::types::GLenum,
+        stride: i32,
+        offset: usize,
+    ) {
+        self.record(VertexAttribute {
+            index,
+            size,
+            ty,
+            integer: true,
+        });
+        unsafe {
+            if dsa::is_available() {
+                gl::VertexArrayVertexBuffer(self.0, index, buffer.0, offset as isize, stride);
+                gl::VertexArrayAttribIFormat(self.0, index, size, ty, 0);
+                gl::VertexArrayAttribBinding(self.0, index, index);
+                gl::EnableVertexArrayAttrib(self.0, index);
+            } else {
+                self.bind();
+                buffer.bind(gl::ARRAY_BUFFER);
+                gl::VertexAttribIPointer(index, size, ty, stride, offset as *const _);
+                gl::EnableVertexAttribArray(index);
+                self.unbind();
+            }
+        }
+    }
+
+    fn record(&self, attribute: VertexAttribute) {
+        let mut attributes = self.1.borrow_mut();
+        attributes.retain(|a| a.index != attribute.index);
+        attributes.push(attribute);
+    }
+
+    /// The attribute formats set so far.
+    pub fn attributes(&self) -> Vec<VertexAttribute> {
+        self.1.borrow().clone()
+    }
+
+    /// Checks that every input `program` reads has an attribute here of a matching kind
+    /// (float or signed or unsigned integer) and size, naming each input that doesn't.
+    /// A `vec4` may be fed three components; GL fills in a `w` of 1. Attributes past the
+    /// context's [`crate::context::Limits::max_vertex_attribs`] are reported too.
+    pub fn check_program(&self, program: &Program) -> Result<()> {
+        let attributes = self.1.borrow();
+        let mut problems = Vec::new();
+        let max = context::limits().max_vertex_attribs;
+        for attribute in attributes.iter().filter(|a| a.index >= max as u32) {
+            problems.push(format!(
+                "location {} is past the {} vertex attributes the context supports",
+                attribute.index, max
+            ));
+        }
+        for input in program.active_attributes() {
+            if input.location < 0 {
+                continue;
+            }
+            let (scalar, components, columns) = input.shape();
+            for location in input.location..input.location + columns * input.size {
+                let Some(attribute) = attributes.iter().find(|a| a.index == location as u32) else {
+                    problems.push(format!(
+                        "`{}` reads location {}, which has no attribute",
+                        input.name, location
+                    ));
+                    continue;
+                };
+                let signed = matches!(attribute.ty, gl::BYTE | gl::SHORT | gl::INT);
+                if scalar == gl::FLOAT && attribute.integer {
+                    problems.push(format!(
+                        "`{}` is a float input but location {} holds integers; use \
+                         `attribute` instead of `integer_attribute`",
+                        input.name, location
+                    ));
+                } else if scalar != gl::FLOAT && !attribute.integer {
+                    problems.push(format!(
+                        "`{}` is an integer input but location {} holds floats; use \
+                         `integer_attribute`",
+                        input.name, location
+                    ));
+                } else if attribute.integer && signed != (scalar == gl::INT) {
+                    problems.push(format!(
+                        "`{}` is {} but location {} holds {} integers",
+                        input.name,
+                        if scalar == gl::INT {
+                            "signed"
+                        } else {
+                            "unsigned"
+                        },
+                        location,
+                        if signed { "signed" } else { "unsigned" }
+                    ));
+                } else if attribute.size != components && (components, attribute.size) != (4, 3) {
+                    problems.push(format!(
+                        "`{}` reads {} components but location {} has {}",
+                        input.name, components, location, attribute.size
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("{}", problems.join("; ")))
+        }
+    }
+
+    /// Advances attribute `index` once every `divisor` instances instead of per vertex.
+    /// Must follow [`VertexArray::attribute`] for the same index.
+    pub fn divisor(&self, index: u32, divisor: u32) {
+        unsafe {
+            if dsa::is_available() {
+                gl::VertexArrayBindingDivisor(self.0, index, divisor);
+            } else {
+                self.bind();
+                gl::VertexAttribDivisor(index, divisor);
+                self.unbind();
+            }
+        }
+    }
+
+    /// Uses `buffer` as the index buffer of the vertex array.
+    pub fn element_buffer(&self, buffer: &Buffer) {
+        unsafe {
+            if dsa::is_available() {
+                gl::VertexArrayElementBuffer(self.0, buffer.0);
+            } else {
+                self.bind();
+                buffer.bind(gl::ELEMENT_ARRAY_BUFFER);
+                self.unbind();
+            }
+        }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        self.2.delete(Deletion::VertexArray(self.0));
+    }
+}
+
+/// A buffer object. The second field is the size of its storage in bytes, for
+/// [`crate::memory::usage`].
+pub struct Buffer(pub(crate) gl::types::GLuint, Cell<usize>, GlContext);
+
+impl Buffer {
+    pub fn new() -> Result<Buffer> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateBuffers(1, &mut id);
+            } else {
+                gl::GenBuffers(1, &mut id);
+            }
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create buffer"))
+        } else {
+            memory::track_object(Resource::Buffer, 1);
+            Ok(Buffer(id, Cell::new(0), gl_context))
+        }
+    }
+
+    /// Size of the buffer's storage in bytes, as last set by [`Buffer::data`] or
+    /// [`Buffer::allocate`].
+    pub fn size(&self) -> usize {
+        self.1.get()
+    }
+
+    fn set_size(&self, size: usize) {
+        memory::track_bytes(Resource::Buffer, self.1.replace(size), size);
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the buffer in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::BUFFER, self.0, label);
+    }
+
+    pub fn bind(&self, target: gl::types::GLenum) {
+        unsafe {
+            gl::BindBuffer(target, self.0);
+        }
+    }
+
+    pub fn unbind(&self, target: gl::types::GLenum) {
+        unsafe {
+            gl::BindBuffer(target, 0);
+        }
+    }
+
+    /// Binds the buffer to `target` through `cache` until the guard is dropped, then
+    /// rebinds whatever the cache had bound before. See [`crate::scoped`].
+    pub fn bind_scoped<'a>(
+        &'a self,
+        cache: &'a mut StateCache,
+        target: gl::types::GLenum,
+    ) -> Binding<'a> {
+        scoped::bind_buffer(cache, target, self.0)
+    }
+
+    /// Binds the buffer to the indexed binding point `index` of `target`
+    /// (e.g. `GL_UNIFORM_BUFFER`).
+    pub fn bind_base(&self, target: gl::types::GLenum, index: u32) {
+        unsafe {
+            gl::BindBufferBase(target, index, self.0);
+        }
+    }
+
+    /// Replaces the buffer's storage with `data`. Without DSA the buffer must be bound to
+    /// `target`.
+    pub fn data(&self, target: gl::types::GLenum, data: &[u8], usage: gl::types::GLenum) {
+        let size = data.len() as gl::types::GLsizeiptr;
+        let ptr = data.as_ptr() as *const gl::types::GLvoid;
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedBufferData(self.0, size, ptr, usage);
+            } else {
+                gl::BufferData(target, size, ptr, usage);
+            }
+        }
+        self.set_size(data.len());
+    }
+
+    /// Replaces `data.len()` bytes starting at `offset` in the buffer bound to `target`.
+    pub fn sub_data(&self, target: gl::types::GLenum, offset: usize, data: &[u8]) {
+        let offset = offset as gl::types::GLintptr;
+        let size = data.len() as gl::types::GLsizeiptr;
+        let ptr = data.as_ptr() as *const gl::types::GLvoid;
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedBufferSubData(self.0, offset, size, ptr);
+            } else {
+                gl::BufferSubData(target, offset, size, ptr);
+            }
+        }
+    }
+
+    /// Reads `data.len()` bytes starting at `offset` from the buffer bound to `target`.
+    /// Waits for the GPU to finish writing the range.
+    pub fn get_sub_data(&self, target: gl::types::GLenum, offset: usize, data: &mut [u8]) {
+        let offset = offset as gl::types::GLintptr;
+        let size = data.len() as gl::types::GLsizeiptr;
+        let ptr = data.as_mut_ptr() as *mut gl::types::GLvoid;
+        unsafe {
+            if dsa::is_available() {
+                gl::GetNamedBufferSubData(self.0, offset, size, ptr);
+            } else {
+                gl::GetBufferSubData(target, offset, size, ptr);
+            }
+        }
+    }
+
+    /// Allocates `size` bytes of uninitialized storage for the buffer bound to `target`.
+    pub fn allocate(&self, target: gl::types::GLenum, size: usize, usage: gl::types::GLenum) {
+        let bytes = size as gl::types::GLsizeiptr;
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedBufferData(self.0, bytes, std::ptr::null(), usage);
+            } else {
+                gl::BufferData(target, bytes, std::ptr::null(), usage);
+            }
+        }
+        self.set_size(size);
+    }
+
+    /// Fills the storage of the buffer bound to `target` with zeros (GL 4.3).
+    pub fn clear(&self, target: gl::types::GLenum) {
+        unsafe {
+            if dsa::is_available() {
+                gl::ClearNamedBufferData(
+                    self.0,
+                    gl::R8,
+                    gl::RED,
+                    gl::UNSIGNED_BYTE,
+                    std::ptr::null(),
+                );
+            } else {
+                gl::ClearBufferData(target, gl::R8, gl::RED, gl::UNSIGNED_BYTE, std::ptr::null());
+            }
+        }
+    }
+
+    /// Copies `size` bytes from `read_offset` in this buffer to `write_offset` in `dst`
+    /// on the GPU. Uses the `GL_COPY_READ_BUFFER` and `GL_COPY_WRITE_BUFFER` bindings.
+    pub fn copy_to(&self, dst: &Buffer, read_offset: usize, write_offset: usize, size: usize) {
+        let read_offset = read_offset as gl::types::GLintptr;
+        let write_offset = write_offset as gl::types::GLintptr;
+        let size = size as gl::types::GLsizeiptr;
+        unsafe {
+            if dsa::is_available() {
+                gl::CopyNamedBufferSubData(self.0, dst.0, read_offset, write_offset, size);
+            } else {
+                self.bind(gl::COPY_READ_BUFFER);
+                dst.bind(gl::COPY_WRITE_BUFFER);
+                gl::CopyBufferSubData(
+                    gl::COPY_READ_BUFFER,
+                    gl::COPY_WRITE_BUFFER,
+                    read_offset,
+                    write_offset,
+                    size,
+                );
+                dst.unbind(gl::COPY_WRITE_BUFFER);
+                self.unbind(gl::COPY_READ_BUFFER);
+            }
+        }
+    }
+
+    /// Maps a range of the buffer bound to `target` into client memory.
+    ///
+    /// The returned pointer is valid until [`Buffer::unmap`] is called.
+    pub fn map_range(
+        &self,
+        target: gl::types::GLenum,
+        offset: usize,
+        length: usize,
+        access: gl::types::GLbitfield,
+    ) -> Result<*mut u8> {
+        let offset = offset as gl::types::GLintptr;
+        let length = length as gl::types::GLsizeiptr;
+        let ptr = unsafe {
+            if dsa::is_available() {
+                gl::MapNamedBufferRange(self.0, offset, length, access)
+            } else {
+                gl::MapBufferRange(target, offset, length, access)
+            }
+        };
+        if ptr.is_null() {
+            Err(anyhow!("Failed to map buffer"))
+        } else {
+            Ok(ptr.cast())
+        }
+    }
+
+    pub fn unmap(&self, target: gl::types::GLenum) -> bool {
+        unsafe {
+            if dsa::is_available() {
+                gl::UnmapNamedBuffer(self.0) == gl::TRUE
+            } else {
+                gl::UnmapBuffer(target) == gl::TRUE
+            }
+        }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.2.delete(Deletion::Buffer(self.0));
+        memory::track_bytes(Resource::Buffer, self.1.get(), 0);
+        memory::track_object(Resource::Buffer, -1);
+    }
+}
@@ -0,0 +1,118 @@
+//! Per-frame built-in uniforms.
+//!
+//! The runners in [`crate::app`] keep thread-local [`Builtins`] up to date, and
+//! [`Program::use_program`](crate::shader::Program::use_program) uploads them to every
+//! program that declares them:
+//!
+//! ```glsl
+//! uniform float u_time;       // seconds since the runner started
+//! uniform vec2 u_resolution;  // framebuffer size in physical pixels
+//! uniform vec2 u_mouse;       // cursor position in pixels, origin at the bottom left
+//! ```
+//!
+//! Programs that declare none of them are unaffected. Apps driving their own loop, e.g.
+//! through [`crate::app::EmbeddedContext`], can call [`set`] themselves.
+
+use std::cell::Cell;
+
+use crate::event::WindowEvent;
+use crate::gl;
+use crate::gl::types::{GLint, GLuint};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Builtins {
+    pub time: f32,
+    pub resolution: [f32; 2],
+    pub mouse: [f32; 2],
+}
+
+thread_local! {
+    static BUILTINS: Cell<Builtins> = const {
+        Cell::new(Builtins {
+            time: 0.0,
+            resolution: [0.0, 0.0],
+            mouse: [0.0, 0.0],
+        })
+    };
+}
+
+/// The values uploaded by the next [`Program::use_program`](crate::shader::Program::use_program).
+pub fn get() -> Builtins {
+    BUILTINS.with(Cell::get)
+}
+
+pub fn set(builtins: Builtins) {
+    BUILTINS.with(|cell| cell.set(builtins));
+}
+
+fn modify(f: impl FnOnce(&mut Builtins)) {
+    BUILTINS.with(|cell| {
+        let mut builtins = cell.get();
+        f(&mut builtins);
+        cell.set(builtins);
+    });
+}
+
+/// Called by the runners alongside [`crate::app::App::update`] with the seconds since
+/// they started.
+pub(crate) fn set_time(time: f32) {
+    modify(|b| b.time = time);
+}
+
+/// Called by the runners alongside [`crate::app::App::resize`].
+pub(crate) fn resize(width: u32, height: u32) {
+    modify(|b| b.resolution = [width as f32, height as f32]);
+}
+
+/// Tracks the cursor; called by the runners for every window event.
+pub(crate) fn window_event(event: &WindowEvent) {
+    if let WindowEvent::CursorMoved { position, .. } = event {
+        modify(|b| b.mouse = [position.x as f32, b.resolution[1] - position.y as f32]);
+    }
+}
+
+/// Where a program declares the built-ins; `-1` for those it doesn't.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Locations {
+    time: GLint,
+    resolution: GLint,
+    mouse: GLint,
+}
+
+impl Locations {
+    pub(crate) const NONE: Locations = Locations {
+        time: -1,
+        resolution: -1,
+        mouse: -1,
+    };
+
+    /// Looks the built-ins up in a linked program.
+    pub(crate) fn query(program: GLuint) -> Locations {
+        let location =
+            |name: &[u8]| unsafe { gl::GetUniformLocation(program, name.as_ptr().cast()) };
+        Locations {
+            time: location(b"u_time\0"),
+            resolution: location(b"u_resolution\0"),
+            mouse: location(b"u_mouse\0"),
+        }
+    }
+
+    /// Uploads the current values to the program in use.
+    pub(crate) fn upload(&self) {
+        if *self == Locations::NONE {
+            return;
+        }
+        let b = get();
+        unsafe {
+            if self.time >= 0 {
+                gl::Uniform1f(self.time, b.time);
+            }
+            if self.resolution >= 0 {
+                gl::Uniform2f(self.resolution, b.resolution[0], b.resolution[1]);
+            }
+            if self.mouse >= 0 {
+                gl::Uniform2f(self.mouse, b.mouse[0], b.mouse[1]);
+            }
+        }
+    }
+}
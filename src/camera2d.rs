@@ -0,0 +1,167 @@
+//! An orthographic camera for the sprite path.
+//!
+//! [`Camera2D`] shows the world around `position` at `zoom` screen pixels per world
+//! unit, with y pointing down like window coordinates. Its [`Camera2D::projection`] goes
+//! to [`crate::sprite::SpriteBatch::begin`], and [`Camera2D::world_to_screen`] and
+//! [`Camera2D::screen_to_world`] convert cursor positions and the like. It has nothing to
+//! do with the 3D [`crate::viewport::Camera`].
+//!
+//! With `pixel_perfect`, the zoom is rounded to a whole number and the view snapped to
+//! whole screen pixels, so every texel of unrotated sprites at whole world positions
+//! covers the same square of screen pixels; [`Camera2D::texture_filter`] then asks for
+//! nearest filtering to keep the texels sharp.
+
+use crate::event::MouseButton;
+use crate::gl;
+use crate::input::Input;
+use crate::math::{Mat4, Vec2};
+use crate::sprite::Rect;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera2D {
+    /// The world point at the center of the viewport.
+    pub position: Vec2,
+    /// Screen pixels per world unit.
+    pub zoom: f32,
+    /// Limits for [`Camera2D::zoom_at`] and [`Camera2D::update`].
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    pub pixel_perfect: bool,
+    /// Viewport size in pixels.
+    viewport: Vec2,
+}
+
+impl Camera2D {
+    /// A camera at zoom 1 whose world units are pixels, with the world origin in the
+    /// top-left corner of a `width` x `height` viewport.
+    pub fn new(width: u32, height: u32) -> Camera2D {
+        let viewport = Vec2::new(width.max(1) as f32, height.max(1) as f32);
+        Camera2D {
+            position: viewport * 0.5,
+            zoom: 1.0,
+            min_zoom: 0.05,
+            max_zoom: 64.0,
+            pixel_perfect: false,
+            viewport,
+        }
+    }
+
+    /// Like [`Camera2D::new`] in pixel-perfect mode at a whole-number `zoom`, e.g. 3 for
+    /// pixel art scaled up threefold. The world origin stays in the top-left corner.
+    pub fn pixel_perfect(width: u32, height: u32, zoom: u32) -> Camera2D {
+        let zoom = zoom.max(1) as f32;
+        let mut camera = Camera2D::new(width, height);
+        camera.zoom = zoom;
+        camera.position = camera.viewport * 0.5 / zoom;
+        camera.pixel_perfect = true;
+        camera
+    }
+
+    /// Resizes the viewport, keeping `position` in its center.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.viewport = Vec2::new(width.max(1) as f32, height.max(1) as f32);
+    }
+
+    pub fn viewport(&self) -> Vec2 {
+        self.viewport
+    }
+
+    /// The zoom actually used: `zoom` rounded to a whole number of at least 1 in
+    /// pixel-perfect mode.
+    pub fn effective_zoom(&self) -> f32 {
+        if self.pixel_perfect {
+            self.zoom.round().max(1.0)
+        } else {
+            self.zoom
+        }
+    }
+
+    /// The world point at the top-left corner of the viewport.
+    fn origin(&self) -> Vec2 {
+        let zoom = self.effective_zoom();
+        let corner = self.position * zoom - self.viewport * 0.5;
+        if self.pixel_perfect {
+            corner.round() / zoom
+        } else {
+            corner / zoom
+        }
+    }
+
+    /// Maps the visible world to clip space, y down.
+    pub fn projection(&self) -> Mat4 {
+        let origin = self.origin();
+        let extent = self.viewport / self.effective_zoom();
+        Mat4::orthographic_rh_gl(
+            origin.x,
+            origin.x + extent.x,
+            origin.y + extent.y,
+            origin.y,
+            -1.0,
+            1.0,
+        )
+    }
+
+    /// The world area in view.
+    pub fn visible(&self) -> Rect {
+        let origin = self.origin();
+        let extent = self.viewport / self.effective_zoom();
+        Rect::new(origin.x, origin.y, extent.x, extent.y)
+    }
+
+    /// The viewport pixel, from the top left, showing world point `world`.
+    pub fn world_to_screen(&self, world: Vec2) -> Vec2 {
+        (world - self.origin()) * self.effective_zoom()
+    }
+
+    /// The world point shown at viewport pixel `screen`, e.g. the cursor position.
+    pub fn screen_to_world(&self, screen: Vec2) -> Vec2 {
+        self.origin() + screen / self.effective_zoom()
+    }
+
+    /// Moves the view by `delta` screen pixels, as when dragging the world along.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.position -= delta / self.effective_zoom();
+    }
+
+    /// Sets the zoom, clamped to the limits, keeping the world point under screen
+    /// position `anchor` in place.
+    pub fn zoom_at(&mut self, anchor: Vec2, zoom: f32) {
+        let before = self.screen_to_world(anchor);
+        self.zoom = zoom.clamp(self.min_zoom, self.max_zoom);
+        if self.pixel_perfect {
+            self.zoom = self.effective_zoom();
+        }
+        self.position += before - self.screen_to_world(anchor);
+    }
+
+    /// Pans while the right or middle mouse button is dragged and zooms towards the
+    /// cursor with the scroll wheel: by whole steps in pixel-perfect mode, by 10% a line
+    /// otherwise.
+    pub fn update(&mut self, input: &Input) {
+        if input.is_button_pressed(MouseButton::Right)
+            || input.is_button_pressed(MouseButton::Middle)
+        {
+            self.pan(input.mouse_motion());
+        }
+        let scroll = input.scroll();
+        if scroll != 0.0 {
+            let anchor = input.cursor().unwrap_or(self.viewport * 0.5);
+            let zoom = if self.pixel_perfect {
+                self.effective_zoom() + scroll.signum()
+            } else {
+                self.zoom * 1.1f32.powf(scroll)
+            };
+            self.zoom_at(anchor, zoom);
+        }
+    }
+
+    /// The filter sprite textures should use: `GL_NEAREST` in pixel-perfect mode,
+    /// `GL_LINEAR` otherwise. See [`crate::atlas::AtlasBuilder::filter`].
+    pub fn texture_filter(&self) -> gl::types::GLenum {
+        if self.pixel_perfect {
+            gl::NEAREST
+        } else {
+            gl::LINEAR
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Typed clears of individual attachments.
+//!
+//! `glClear` clears every attachment to the one `glClearColor` value, as floats. A
+//! [`PassClear`] instead says per attachment whether a pass keeps its previous contents,
+//! clears it to a value of the attachment's type through `glClearBuffer*`, or doesn't
+//! care. Attachments nobody cares about are invalidated with `glInvalidateFramebuffer`
+//! rather than cleared, which on tiled mobile GPUs saves loading them into tile memory
+//! at all. [`crate::graph::RenderGraph::add_cleared_pass`] applies one at the start of a
+//! pass.
+//!
+//! Clears honor the current color, depth and stencil write masks and the scissor test,
+//! as `glClear` does.
+
+use crate::context;
+use crate::depth;
+use crate::gl;
+
+/// A clear value for a color attachment, of the type matching its internal format:
+/// floats for normalized and float formats, signed or unsigned integers for integer
+/// formats. Clearing with the wrong type is undefined.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClearColor {
+    Float([f32; 4]),
+    Int([i32; 4]),
+    Uint([u32; 4]),
+}
+
+/// What a pass starts with in one attachment.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoadOp<T> {
+    /// The previous contents.
+    #[default]
+    Load,
+    /// The value.
+    Clear(T),
+    /// Undefined contents, for attachments the pass overwrites completely.
+    DontCare,
+}
+
+/// Clears the color attachment selected by draw buffer `index` of the bound draw
+/// framebuffer to `color`.
+pub fn clear_color(index: i32, color: ClearColor) {
+    unsafe {
+        match color {
+            ClearColor::Float(value) => gl::ClearBufferfv(gl::COLOR, index, value.as_ptr()),
+            ClearColor::Int(value) => gl::ClearBufferiv(gl::COLOR, index, value.as_ptr()),
+            ClearColor::Uint(value) => gl::ClearBufferuiv(gl::COLOR, index, value.as_ptr()),
+        }
+    }
+}
+
+/// Clears the depth attachment of the bound draw framebuffer, e.g. to [`depth::far`].
+pub fn clear_depth(depth: f32) {
+    unsafe {
+        gl::ClearBufferfv(gl::DEPTH, 0, &depth);
+    }
+}
+
+pub fn clear_stencil(stencil: i32) {
+    unsafe {
+        gl::ClearBufferiv(gl::STENCIL, 0, &stencil);
+    }
+}
+
+/// Clears depth and stencil of a combined depth-stencil attachment at once.
+pub fn clear_depth_stencil(depth: f32, stencil: i32) {
+    unsafe {
+        gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil);
+    }
+}
+
+/// Whether the context has `glInvalidateFramebuffer` (GL 4.3, ES 3.0 or
+/// `ARB_invalidate_subdata`).
+pub fn invalidate_supported() -> bool {
+    let info = context::info();
+    gl::InvalidateFramebuffer::is_loaded()
+        && (info.es || info.version >= (4, 3) || info.has_extension("GL_ARB_invalidate_subdata"))
+}
+
+/// Tells the driver the contents of `attachments` of the framebuffer bound to `target`
+/// are no longer needed. The default framebuffer names them `GL_COLOR`, `GL_DEPTH` and
+/// `GL_STENCIL`; others `GL_COLOR_ATTACHMENTi`, `GL_DEPTH_ATTACHMENT` and so on. Only a
+/// hint: does nothing where unsupported.
+pub fn invalidate(target: gl::types::GLenum, attachments: &[gl::types::GLenum]) {
+    if attachments.is_empty() || !invalidate_supported() {
+        return;
+    }
+    unsafe {
+        gl::InvalidateFramebuffer(target, attachments.len() as i32, attachments.as_ptr());
+    }
+}
+
+/// The start of a render pass, per attachment of the bound draw framebuffer. Color
+/// attachments are indexed by draw buffer; those past the end of `colors` are loaded.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PassClear {
+    pub colors: Vec<LoadOp<ClearColor>>,
+    pub depth: LoadOp<f32>,
+    pub stencil: LoadOp<i32>,
+}
+
+impl PassClear {
+    /// Loads everything.
+    pub fn new() -> PassClear {
+        PassClear::default()
+    }
+
+    /// Clears color attachment 0 to `color`, depth to [`depth::far`] and stencil to 0.
+    pub fn standard(color: [f32; 4]) -> PassClear {
+        PassClear::new()
+            .color(0, ClearColor::Float(color))
+            .depth(depth::far())
+            .stencil(0)
+    }
+
+    /// Sets what draw buffer `index` starts with.
+    pub fn color_op(mut self, index: usize, op: LoadOp<ClearColor>) -> PassClear {
+        if self.colors.len() <= index {
+            self.colors.resize(index + 1, LoadOp::Load);
+        }
+        self.colors[index] = op;
+        self
+    }
+
+    pub fn color(self, index: usize, color: ClearColor) -> PassClear {
+        self.color_op(index, LoadOp::Clear(color))
+    }
+
+    pub fn depth(mut self, depth: f32) -> PassClear {
+        self.depth = LoadOp::Clear(depth);
+        self
+    }
+
+    pub fn stencil(mut self, stencil: i32) -> PassClear {
+        self.stencil = LoadOp::Clear(stencil);
+        self
+    }
+
+    /// Discards every attachment the pass doesn't clear, for passes that overwrite all
+    /// of them, e.g. fullscreen post-processing.
+    pub fn dont_care(mut self) -> PassClear {
+        for op in &mut self.colors {
+            if *op == LoadOp::Load {
+                *op = LoadOp::DontCare;
+            }
+        }
+        if self.colors.is_empty() {
+            self.colors.push(LoadOp::DontCare);
+        }
+        if self.depth == LoadOp::Load {
+            self.depth = LoadOp::DontCare;
+        }
+        if self.stencil == LoadOp::Load {
+            self.stencil = LoadOp::DontCare;
+        }
+        self
+    }
+
+    /// Invalidates the don't-care attachments, then clears the others as requested, on
+    /// the framebuffer bound to `GL_DRAW_FRAMEBUFFER`. `default_framebuffer` selects the
+    /// default framebuffer's attachment names, where only draw buffer 0 exists.
+    pub fn apply(&self, default_framebuffer: bool) {
+        let mut discard = Vec::new();
+        for (index, op) in self.colors.iter().enumerate() {
+            if *op == LoadOp::DontCare {
+                discard.push(if default_framebuffer {
+                    gl::COLOR
+                } else {
+                    gl::COLOR_ATTACHMENT0 + index as u32
+                });
+            }
+        }
+        if self.depth == LoadOp::DontCare {
+            discard.push(if default_framebuffer {
+                gl::DEPTH
+            } else {
+                gl::DEPTH_ATTACHMENT
+            });
+        }
+        if self.stencil == LoadOp::DontCare {
+            discard.push(if default_framebuffer {
+                gl::STENCIL
+            } else {
+                gl::STENCIL_ATTACHMENT
+            });
+        }
+        if default_framebuffer {
+            discard.dedup();
+        }
+        invalidate(gl::DRAW_FRAMEBUFFER, &discard);
+
+        for (index, op) in self.colors.iter().enumerate() {
+            if let LoadOp::Clear(color) = *op {
+                clear_color(index as i32, color);
+            }
+        }
+        match (self.depth, self.stencil) {
+            (LoadOp::Clear(depth), LoadOp::Clear(stencil)) => clear_depth_stencil(depth, stencil),
+            (LoadOp::Clear(depth), _) => clear_depth(depth),
+            (_, LoadOp::Clear(stencil)) => clear_stencil(stencil),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! Clustered forward lighting.
+//!
+//! The uniform-block lights of [`crate::material::LightBuffer`] are all evaluated for
+//! every fragment, which caps a scene at a few dozen lights. [`ClusteredLights`] instead
+//! divides the view frustum into a grid of clusters, [`CLUSTERS_X`] × [`CLUSTERS_Y`]
+//! screen tiles by [`CLUSTERS_Z`] exponential depth slices, and lists the point and spot
+//! lights whose range reaches each one. Fragments then shade only against the lights of
+//! their own cluster through [`CLUSTERED_LIGHTS_GLSL`], so hundreds of small lights cost
+//! little more than a handful.
+//!
+//! The lists are built by a compute shader on GL 4.3 and on the CPU otherwise, or
+//! always on the CPU in builds without compute shaders (see [`crate::gl`]); both write
+//! the same textures, so shading is identical. Hand the lights to
+//! [`crate::material::MaterialShaders::set_clustered_lights`] to light materials with
+//! them.
+
+use anyhow::{anyhow, Result};
+
+#[cfg(gl_compute)]
+use crate::context;
+use crate::gl;
+#[cfg(gl_compute)]
+use crate::load_store::{self, Barriers, ImageAccess, ImageFormat};
+use crate::material::{Light, LightStd140};
+use crate::math::{Mat4, Vec2, Vec3};
+use crate::shader::Program;
+use crate::texture::Texture;
+use crate::viewport::Camera;
+
+pub const CLUSTERS_X: usize = 16;
+pub const CLUSTERS_Y: usize = 9;
+pub const CLUSTERS_Z: usize = 24;
+const CLUSTER_COUNT: usize = CLUSTERS_X * CLUSTERS_Y * CLUSTERS_Z;
+
+/// Lights beyond this many reaching one cluster are dropped from it. One row of the
+/// cluster texture holds the count followed by the indices.
+pub const MAX_LIGHTS_PER_CLUSTER: usize = 63;
+
+/// Maximum number of point and spot lights in one [`ClusteredLights::update`].
+pub const MAX_CLUSTERED_LIGHTS: usize = 1024;
+
+/// Texels per light in the light texture, one per `vec4` of the GLSL `Light` struct.
+const LIGHT_TEXELS: usize = 4;
+
+/// Declares the cluster uniforms set by [`ClusteredLights::apply`] and helpers to walk
+/// the lights of the fragment's cluster. Paste after [`crate::material::LIGHTS_GLSL`],
+/// whose `Light` struct it returns:
+///
+/// ```glsl
+/// int cluster = cluster_index();
+/// for (int i = 0; i < cluster_light_count(cluster); ++i) {
+///     Light light = cluster_light(cluster, i);
+/// }
+/// ```
+pub const CLUSTERED_LIGHTS_GLSL: &str = r#"
+#define CLUSTERS_X 16
+#define CLUSTERS_Y 9
+#define CLUSTERS_Z 24
+uniform bool u_clustered;
+uniform sampler2D u_cluster_lights;
+uniform usampler2D u_clusters;
+uniform vec2 u_cluster_tile_size;
+uniform vec2 u_cluster_near_far;
+
+int cluster_index() {
+    float near = u_cluster_near_far.x;
+    float far = u_cluster_near_far.y;
+    // View-space distance, the same under either depth convention.
+    float depth = 1.0 / gl_FragCoord.w;
+    int slice = int(floor(log(depth / near) / log(far / near) * float(CLUSTERS_Z)));
+    ivec2 tile = ivec2(gl_FragCoord.xy / u_cluster_tile_size);
+    tile = clamp(tile, ivec2(0), ivec2(CLUSTERS_X - 1, CLUSTERS_Y - 1));
+    slice = clamp(slice, 0, CLUSTERS_Z - 1);
+    return tile.x + tile.y * CLUSTERS_X + slice * CLUSTERS_X * CLUSTERS_Y;
+}
+
+int cluster_light_count(int cluster) {
+    return u_clustered ? int(texelFetch(u_clusters, ivec2(0, cluster), 0).r) : 0;
+}
+
+Light cluster_light(int cluster, int i) {
+    int index = int(texelFetch(u_clusters, ivec2(i + 1, cluster), 0).r);
+    Light light;
+    light.position_type = texelFetch(u_cluster_lights, ivec2(0, index), 0);
+    light.direction_range = texelFetch(u_cluster_lights, ivec2(1, index), 0);
+    light.color_intensity = texelFetch(u_cluster_lights, ivec2(2, index), 0);
+    light.cone = texelFetch(u_cluster_lights, ivec2(3, index), 0);
+    return light;
+}
+"#;
+
+#[cfg(gl_compute)]
+const CLUSTER_COMPUTE_SHADER: &str = r#"#version 430 core
+#define CLUSTERS_X 16
+#define CLUSTERS_Y 9
+#define CLUSTERS_Z 24
+#define MAX_LIGHTS_PER_CLUSTER 63
+layout (local_size_x = CLUSTERS_X, local_size_y = CLUSTERS_Y, local_size_z = 1) in;
+layout (r32ui, binding = 0) uniform writeonly uimage2D u_clusters;
+uniform sampler2D u_lights;
+uniform int u_light_count;
+uniform mat4 u_view;
+uniform vec2 u_tan_half_fov;
+uniform vec2 u_tile_ndc;
+uniform vec2 u_near_far;
+
+void main() {
+    uvec3 id = gl_GlobalInvocationID;
+    int cluster = int(id.x) + int(id.y) * CLUSTERS_X + int(id.z) * CLUSTERS_X * CLUSTERS_Y;
+    vec2 ndc_min = vec2(id.xy) * u_tile_ndc - 1.0;
+    vec2 ndc_max = vec2(id.xy + 1u) * u_tile_ndc - 1.0;
+    float ratio = u_near_far.y / u_near_far.x;
+    float near = u_near_far.x * pow(ratio, float(id.z) / float(CLUSTERS_Z));
+    float far = u_near_far.x * pow(ratio, float(id.z + 1u) / float(CLUSTERS_Z));
+
+    vec3 lo = vec3(1e30);
+    vec3 hi = vec3(-1e30);
+    for (int i = 0; i < 8; ++i) {
+        vec2 ndc = vec2((i & 1) == 0 ? ndc_min.x : ndc_max.x,
+                        (i & 2) == 0 ? ndc_min.y : ndc_max.y);
+        float depth = (i & 4) == 0 ? near : far;
+        vec3 corner = vec3(ndc * u_tan_half_fov * depth, -depth);
+        lo = min(lo, corner);
+        hi = max(hi, corner);
+    }
+
+    int count = 0;
+    for (int i = 0; i < u_light_count && count < MAX_LIGHTS_PER_CLUSTER; ++i) {
+        vec3 position = texelFetch(u_lights, ivec2(0, i), 0).xyz;
+        float range = texelFetch(u_lights, ivec2(1, i), 0).w;
+        vec3 center = (u_view * vec4(position, 1.0)).xyz;
+        vec3 delta = clamp(center, lo, hi) - center;
+        if (dot(delta, delta) <= range * range) {
+            imageStore(u_clusters, ivec2(count + 1, cluster), uvec4(i));
+            count++;
+        }
+    }
+    imageStore(u_clusters, ivec2(0, cluster), uvec4(count));
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClusterBackend {
+    Cpu,
+    #[cfg(gl_compute)]
+    Compute,
+}
+
+impl ClusterBackend {
+    /// The compute backend if the context is GL 4.3 or newer, otherwise the CPU one.
+    pub fn best_available() -> ClusterBackend {
+        #[cfg(gl_compute)]
+        if context::version() >= (4, 3)
+            && gl::DispatchCompute::is_loaded()
+            && load_store::is_supported()
+        {
+            return ClusterBackend::Compute;
+        }
+        ClusterBackend::Cpu
+    }
+}
+
+/// The per-frame camera parameters that place the cluster grid.
+#[derive(Clone, Copy, Debug)]
+struct Grid {
+    view: Mat4,
+    tan_half_fov: Vec2,
+    /// NDC extent of one tile; tiles at the right and top edges may overhang the screen.
+    tile_ndc: Vec2,
+    tile_size: Vec2,
+    near: f32,
+    far: f32,
+}
+
+impl Grid {
+    fn depth(&self, slice: usize) -> f32 {
+        self.near * (self.far / self.near).powf(slice as f32 / CLUSTERS_Z as f32)
+    }
+
+    fn slice(&self, depth: f32) -> usize {
+        let slice = (depth / self.near).ln() / (self.far / self.near).ln() * CLUSTERS_Z as f32;
+        (slice.max(0.0) as usize).min(CLUSTERS_Z - 1)
+    }
+
+    /// The view-space bounding box of cluster `(x, y, z)`.
+    fn bounds(&self, x: usize, y: usize, z: usize) -> (Vec3, Vec3) {
+        let ndc_min = Vec2::new(x as f32, y as f32) * self.tile_ndc - 1.0;
+        let ndc_max = Vec2::new((x + 1) as f32, (y + 1) as f32) * self.tile_ndc - 1.0;
+        let mut lo = Vec3::splat(f32::MAX);
+        let mut hi = Vec3::splat(f32::MIN);
+        for depth in [self.depth(z), self.depth(z + 1)] {
+            for corner in [
+                ndc_min,
+                Vec2::new(ndc_max.x, ndc_min.y),
+                Vec2::new(ndc_min.x, ndc_max.y),
+                ndc_max,
+            ] {
+                let corner = (corner * self.tan_half_fov * depth).extend(-depth);
+                lo = lo.min(corner);
+                hi = hi.max(corner);
+            }
+        }
+        (lo, hi)
+    }
+}
+
+/// Point and spot lights sorted into a clustered view-frustum grid.
+///
+/// Call [`ClusteredLights::update`] once per frame after the camera moves, then
+/// [`ClusteredLights::apply`] on programs that include [`CLUSTERED_LIGHTS_GLSL`].
+pub struct ClusteredLights {
+    backend: ClusterBackend,
+    /// `LIGHT_TEXELS` × [`MAX_CLUSTERED_LIGHTS`] `RGBA32F`, one row per light.
+    lights: Texture,
+    /// `MAX_LIGHTS_PER_CLUSTER + 1` × cluster count `R32UI`: a count, then indices.
+    clusters: Texture,
+    #[cfg(gl_compute)]
+    compute: Option<Program>,
+    light_count: usize,
+    grid: Grid,
+}
+
+impl ClusteredLights {
+    /// Creates the cluster textures, using the best available backend.
+    pub fn new() -> Result<ClusteredLights> {
+        ClusteredLights::with_backend(ClusterBackend::best_available())
+    }
+
+    pub fn with_backend(backend: ClusterBackend) -> Result<ClusteredLights> {
+        let lights = data_texture(
+            gl::RGBA32F,
+            gl::RGBA,
+            gl::FLOAT,
+            LIGHT_TEXELS,
+            MAX_CLUSTERED_LIGHTS,
+        )?;
+        lights.label("clustered lights");
+        let clusters = data_texture(
+            gl::R32UI,
+            gl::RED_INTEGER,
+            gl::UNSIGNED_INT,
+            MAX_LIGHTS_PER_CLUSTER + 1,
+            CLUSTER_COUNT,
+        )?;
+        clusters.label("light clusters");
+        #[cfg(gl_compute)]
+        let compute = match backend {
+            ClusterBackend::Cpu => None,
+            ClusterBackend::Compute => Some(Program::from_compute(CLUSTER_COMPUTE_SHADER)?),
+        };
+        Ok(ClusteredLights {
+            backend,
+            lights,
+            clusters,
+            #[cfg(gl_compute)]
+            compute,
+            light_count: 0,
+            grid: Grid {
+                view: Mat4::IDENTITY,
+                tan_half_fov: Vec2::ONE,
+                tile_ndc: Vec2::ONE,
+                tile_size: Vec2::ONE,
+                near: 0.1,
+                far: 100.0,
+            },
+        })
+    }
+
+    pub fn backend(&self) -> ClusterBackend {
+        self.backend
+    }
+
+    /// Number of lights in the last update.
+    pub fn light_count(&self) -> usize {
+        self.light_count
+    }
+
+    /// Uploads the point and spot lights of `lights` and assigns them to the clusters of
+    /// `camera` drawn into a `width × height` viewport. Directional lights reach every
+    /// cluster and are skipped; keep them in a [`crate::material::LightBuffer`].
+    pub fn update(
+        &mut self,
+        lights: &[Light],
+        camera: &Camera,
+        width: u32,
+        height: u32,
+    ) -> Result<()> {
+        let lights: Vec<&Light> = lights
+            .iter()
+            .filter(|light| !matches!(light, Light::Directional { .. }))
+            .collect();
+        if lights.len() > MAX_CLUSTERED_LIGHTS {
+            return Err(anyhow!(
+                "{} clustered lights exceed the limit of {}",
+                lights.len(),
+                MAX_CLUSTERED_LIGHTS
+            ));
+        }
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        let tile_size = Vec2::new(
+            (width / CLUSTERS_X as f32).ceil(),
+            (height / CLUSTERS_Y as f32).ceil(),
+        );
+        let tan_y = (camera.fov_y * 0.5).tan();
+        self.grid = Grid {
+            view: camera.view(),
+            tan_half_fov: Vec2::new(tan_y * width / height, tan_y),
+            tile_ndc: tile_size / Vec2::new(width, height) * 2.0,
+            tile_size,
+            near: camera.near,
+            far: camera.far,
+        };
+        self.light_count = lights.len();
+
+        let packed: Vec<LightStd140> = lights.iter().map(|&light| light.into()).collect();
+        if !packed.is_empty() {
+            self.lights.bind();
+            self.lights.sub_image_2d(
+                0,
+                0,
+                0,
+                LIGHT_TEXELS as i32,
+                packed.len() as i32,
+                gl::RGBA,
+                gl::FLOAT,
+                bytemuck::cast_slice(&packed),
+            );
+            self.lights.unbind();
+        }
+
+        #[cfg(gl_compute)]
+        if let Some(compute) = &self.compute {
+            return self.assign_gpu(compute);
+        }
+        self.assign_cpu(&lights);
+        Ok(())
+    }
+
+    fn assign_cpu(&self, lights: &[&Light]) {
+        let _span = tracing::debug_span!("pass", name = "light clustering").entered();
+        let grid = &self.grid;
+        let row = MAX_LIGHTS_PER_CLUSTER + 1;
+        let mut clusters = vec![0u32; CLUSTER_COUNT * row];
+        for (index, light) in lights.iter().enumerate() {
+            let (position, range) = match **light {
+                Light::Point {
+                    position, range, ..
+                }
+                | Light::Spot {
+                    position, range, ..
+                } => (position, range),
+                Light::Directional { .. } => continue,
+            };
+            let center = grid.view.transform_point3(position);
+            let (nearest, farthest) = (-center.z - range, -center.z + range);
+            if farthest < grid.near || nearest > grid.far {
+                continue;
+            }
+            for z in grid.slice(nearest.max(grid.near))..=grid.slice(farthest.min(grid.far)) {
+                for y in 0..CLUSTERS_Y {
+                    for x in 0..CLUSTERS_X {
+                        let (lo, hi) = grid.bounds(x, y, z);
+                        if center.clamp(lo, hi).distance_squared(center) > range * range {
+                            continue;
+                        }
+                        let cluster = x + y * CLUSTERS_X + z * CLUSTERS_X * CLUSTERS_Y;
+                        let slots = &mut clusters[cluster * row..(cluster + 1) * row];
+                        let count = slots[0] as usize;
+                        if count < MAX_LIGHTS_PER_CLUSTER {
+                            slots[count + 1] = index as u32;
+                            slots[0] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.clusters.bind();
+        self.clusters.sub_image_2d(
+            0,
+            0,
+            0,
+            row as i32,
+            CLUSTER_COUNT as i32,
+            gl::RED_INTEGER,
+            gl::UNSIGNED_INT,
+            bytemuck::cast_slice(&clusters),
+        );
+        self.clusters.unbind();
+    }
+
+    #[cfg(gl_compute)]
+    fn assign_gpu(&self, compute: &Program) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "light clustering").entered();
+        let grid = &self.grid;
+        compute.use_program();
+        self.lights.bind_unit(0);
+        compute.set_int("u_lights", 0);
+        compute.set_int("u_light_count", self.light_count as i32);
+        compute.set_mat4("u_view", &grid.view.to_cols_array());
+        compute.set_vec2("u_tan_half_fov", grid.tan_half_fov.to_array());
+        compute.set_vec2("u_tile_ndc", grid.tile_ndc.to_array());
+        compute.set_vec2("u_near_far", [grid.near, grid.far]);
+        self.clusters
+            .bind_image(0, 0, None, ImageAccess::WriteOnly, ImageFormat::R32UI)?;
+        unsafe {
+            gl::DispatchCompute(1, 1, CLUSTERS_Z as u32);
+        }
+        load_store::memory_barrier(Barriers::TEXTURE_FETCH);
+        Ok(())
+    }
+
+    /// Binds the light and cluster textures to units `unit` and `unit + 1` and sets the
+    /// `u_cluster*` uniforms on `program`, which must be in use.
+    pub fn apply(&self, program: &Program, unit: u32) {
+        self.lights.bind_unit(unit);
+        self.clusters.bind_unit(unit + 1);
+        self.set_uniforms(program, unit);
+    }
+
+    pub(crate) fn textures(&self) -> (&Texture, &Texture) {
+        (&self.lights, &self.clusters)
+    }
+
+    pub(crate) fn set_uniforms(&self, program: &Program, unit: u32) {
+        program.set_int("u_clustered", 1);
+        program.set_int("u_cluster_lights", unit as i32);
+        program.set_int("u_clusters", unit as i32 + 1);
+        program.set_vec2("u_cluster_tile_size", self.grid.tile_size.to_array());
+        program.set_vec2("u_cluster_near_far", [self.grid.near, self.grid.far]);
+    }
+}
+
+/// A 2D texture of raw texels read with `texelFetch`.
+fn data_texture(
+    internal_format: gl::types::GLenum,
+    format: gl::types::GLenum,
+    ty: gl::types::GLenum,
+    width: usize,
+    height: usize,
+) -> Result<Texture> {
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    texture.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    texture.image_2d(
+        0,
+        internal_format,
+        width as i32,
+        height as i32,
+        format,
+        ty,
+        None,
+    );
+    texture.unbind();
+    Ok(texture)
+}
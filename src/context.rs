@@ -0,0 +1,842 @@
+//! Queries about the current GL context.
+//!
+//! [`ContextInfo`] is gathered once per thread when first requested (the runner does so
+//! right after loading the function pointers) and describes the driver and its
+//! [`Limits`]. The wrappers check sizes and counts against the limits where GL would
+//! otherwise fail with a bare `GL_INVALID_VALUE` or leave the behavior undefined.
+//!
+//! [`ContextInfo::is_software`] recognizes software rasterizers such as Mesa's llvmpipe,
+//! which the runners warn about; [`request_software`] asks Mesa for one, so machines
+//! without a GPU can still run the examples and tests.
+//!
+//! [`ContextOptions`] holds the context-wide toggles the runners set right after
+//! loading, such as seamless cube map filtering and clip control for reversed-Z.
+//!
+//! [`reset_status`] reports whether a robust context was lost to a GPU reset; the native
+//! runner checks it every frame when the context was created with
+//! [`crate::app::Robustness::RobustLoseContextOnReset`], and passes the status to
+//! [`crate::app::App::context_lost`]. [`ContextInfo::robust_access`] and
+//! [`ContextInfo::reset_notification`] tell whether the driver granted what was asked.
+//!
+//! With [`ContextOptions::deferred_deletion`], dropped GL objects go through a deletion
+//! queue: [`delete`] holds their names until the GPU has finished the frame they were
+//! dropped in, so a streamed texture or readback buffer dropped mid-frame is never
+//! deleted under commands still using it.
+//!
+//! [`GlContext`] is the proof that a context is current on the calling thread. Every GL
+//! object wrapper takes one when it is created and keeps it, which makes the wrappers
+//! `!Send`: the compiler rejects moving a [`crate::buffer::Buffer`] to a thread where
+//! its name means nothing. The token also records which context the object was made in:
+//! once a lost context has been replaced, objects from the old one are not deleted when
+//! dropped, since their names may belong to live objects of the new one.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, VecDeque};
+use std::ffi::CStr;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::gl;
+use crate::sync::Fence;
+
+/// Lower-cased `GL_RENDERER` fragments of known software rasterizers.
+const SOFTWARE_RENDERERS: &[&str] = &[
+    "llvmpipe",
+    "softpipe",
+    "swrast",
+    "software rasterizer",
+    "swiftshader",
+    "microsoft basic render driver",
+    "gdi generic",
+    "apple software renderer",
+];
+
+thread_local! {
+    static INFO: RefCell<Option<Rc<ContextInfo>>> = const { RefCell::new(None) };
+    static CURRENT: Cell<bool> = const { Cell::new(false) };
+    /// Bumped by [`forget`] each time the context is replaced.
+    static GENERATION: Cell<u64> = const { Cell::new(0) };
+    static OPTIONS: Cell<Option<ContextOptions>> = const { Cell::new(None) };
+    static DELETIONS: RefCell<DeletionQueue> = const {
+        RefCell::new(DeletionQueue {
+            pending: Vec::new(),
+            retired: VecDeque::new(),
+        })
+    };
+}
+
+/// A token showing that a GL context is current on this thread. It is neither `Send`
+/// nor `Sync`, and neither is anything holding one.
+///
+/// The runners, [`crate::app::HeadlessContext`], [`crate::app::EmbeddedContext`] and
+/// the [`crate::upload`] thread mark their thread once its context is current; code
+/// managing its own context calls [`GlContext::assume_current`].
+#[derive(Clone, Copy, Debug)]
+pub struct GlContext {
+    generation: u64,
+    _thread: PhantomData<*const ()>,
+}
+
+impl GlContext {
+    fn new() -> GlContext {
+        GlContext {
+            generation: GENERATION.with(Cell::get),
+            _thread: PhantomData,
+        }
+    }
+
+    /// The token for this thread, or an error if no context was made current on it.
+    pub fn current() -> Result<GlContext> {
+        if CURRENT.with(Cell::get) {
+            Ok(GlContext::new())
+        } else {
+            Err(anyhow!("No GL context is current on this thread"))
+        }
+    }
+
+    /// Marks this thread as having a current context and returns its token.
+    ///
+    /// # Safety
+    ///
+    /// A context must be current on this thread, with its functions loaded, for as long
+    /// as objects created through the token live.
+    pub unsafe fn assume_current() -> GlContext {
+        CURRENT.with(|current| current.set(true));
+        GlContext::new()
+    }
+
+    /// Whether the context this token was taken in is still the current one, rather than
+    /// one lost and replaced since.
+    pub fn is_current(&self) -> bool {
+        self.generation == GENERATION.with(Cell::get)
+    }
+
+    /// Like [`delete`], but drops `object` without deleting it if it was made in a context
+    /// that has since been replaced. The wrappers' `Drop` impls call this.
+    pub fn delete(&self, object: Deletion) {
+        if self.is_current() {
+            delete(object);
+        }
+    }
+}
+
+/// Version, driver strings, extensions and key limits of the current context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContextInfo {
+    /// `(major, minor)`.
+    pub version: (i32, i32),
+    /// The full `GL_VERSION` string.
+    pub version_string: String,
+    pub glsl_version: String,
+    pub vendor: String,
+    pub renderer: String,
+    /// Whether this is an OpenGL ES context (including ANGLE).
+    pub es: bool,
+    pub core_profile: bool,
+    /// Whether out-of-bounds buffer accesses by shaders and draws are defined to be
+    /// harmless (`GL_CONTEXT_FLAG_ROBUST_ACCESS_BIT`).
+    pub robust_access: bool,
+    /// Whether a GPU reset loses the context and shows up in [`reset_status`]
+    /// (`GL_LOSE_CONTEXT_ON_RESET`), which recovery depends on.
+    pub reset_notification: bool,
+    pub extensions: BTreeSet<String>,
+    pub limits: Limits,
+}
+
+impl ContextInfo {
+    /// Queries the current context. Prefer [`info`], which caches the result.
+    pub fn query() -> ContextInfo {
+        let mut count = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        }
+        let extensions: BTreeSet<String> = (0..count as u32)
+            .filter_map(|i| string(unsafe { gl::GetStringi(gl::EXTENSIONS, i) }))
+            .collect();
+        let version_string = get_string(gl::VERSION);
+        let es = version_string.starts_with("OpenGL ES");
+        let version = version();
+        // GL_CONTEXT_PROFILE_MASK does not exist on ES.
+        let profile = if es {
+            0
+        } else {
+            integer(gl::CONTEXT_PROFILE_MASK)
+        };
+        // ES only has GL_CONTEXT_FLAGS from 3.2.
+        let flags = if !es || version >= (3, 2) {
+            integer(gl::CONTEXT_FLAGS)
+        } else {
+            0
+        };
+        let robustness = version >= if es { (3, 2) } else { (4, 5) }
+            || [
+                "GL_ARB_robustness",
+                "GL_KHR_robustness",
+                "GL_EXT_robustness",
+            ]
+            .iter()
+            .any(|name| extensions.contains(*name));
+        let reset_notification = robustness
+            && integer(gl::RESET_NOTIFICATION_STRATEGY) as u32 == gl::LOSE_CONTEXT_ON_RESET;
+        ContextInfo {
+            version,
+            es,
+            version_string,
+            glsl_version: get_string(gl::SHADING_LANGUAGE_VERSION),
+            vendor: get_string(gl::VENDOR),
+            renderer: get_string(gl::RENDERER),
+            core_profile: profile as u32 & gl::CONTEXT_CORE_PROFILE_BIT != 0,
+            robust_access: flags as u32 & gl::CONTEXT_FLAG_ROBUST_ACCESS_BIT_ARB != 0,
+            reset_notification,
+            extensions,
+            limits: Limits::query(),
+        }
+    }
+
+    /// Whether the renderer is a software rasterizer, from the `GL_RENDERER` string.
+    pub fn is_software(&self) -> bool {
+        let renderer = self.renderer.to_ascii_lowercase();
+        SOFTWARE_RENDERERS
+            .iter()
+            .any(|name| renderer.contains(name))
+    }
+
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.contains(name)
+    }
+}
+
+impl fmt::Display for ContextInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let profile = if self.es {
+            "ES"
+        } else if self.core_profile {
+            "core"
+        } else {
+            "compatibility"
+        };
+        writeln!(f, "OpenGL:      {} ({})", self.version_string, profile)?;
+        writeln!(f, "GLSL:        {}", self.glsl_version)?;
+        writeln!(f, "Vendor:      {}", self.vendor)?;
+        writeln!(f, "Renderer:    {}", self.renderer)?;
+        let robustness = match (self.robust_access, self.reset_notification) {
+            (true, true) => "robust access, lost on reset",
+            (true, false) => "robust access",
+            (false, true) => "lost on reset",
+            (false, false) => "none",
+        };
+        writeln!(f, "Robustness:  {}", robustness)?;
+        writeln!(f, "{}", self.limits)?;
+        write!(f, "Extensions ({}):", self.extensions.len())?;
+        for extension in &self.extensions {
+            write!(f, "\n  {}", extension)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implementation limits of a context, from `glGetIntegerv`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Width and height of 2D textures and renderbuffers.
+    pub max_texture_size: i32,
+    pub max_cube_map_texture_size: i32,
+    pub max_3d_texture_size: i32,
+    pub max_array_texture_layers: i32,
+    /// Texture units available across all shader stages combined.
+    pub max_texture_units: i32,
+    /// Bytes in one uniform block.
+    pub max_uniform_block_size: i32,
+    pub max_vertex_attribs: i32,
+    pub max_color_attachments: i32,
+    pub max_draw_buffers: i32,
+    /// Samples per pixel of multisampled textures and renderbuffers.
+    pub max_samples: i32,
+    /// Work group counts per dispatch along X, Y and Z; zero without compute shaders.
+    pub max_compute_work_group_count: [i32; 3],
+    /// `local_size` of a compute shader along X, Y and Z; zero without compute shaders.
+    pub max_compute_work_group_size: [i32; 3],
+    /// Invocations in one work group, the product of the three sizes.
+    pub max_compute_work_group_invocations: i32,
+}
+
+impl Limits {
+    /// Queries the current context. Prefer [`limits`], which caches the result.
+    pub fn query() -> Limits {
+        #[cfg(gl_compute)]
+        let (work_group_count, work_group_size, work_group_invocations) = {
+            let indexed = |name: gl::types::GLenum| {
+                let mut values = [0; 3];
+                for (i, value) in values.iter_mut().enumerate() {
+                    unsafe {
+                        gl::GetIntegeri_v(name, i as u32, value);
+                    }
+                }
+                values
+            };
+            let es = get_string(gl::VERSION).starts_with("OpenGL ES");
+            if version() >= if es { (3, 1) } else { (4, 3) } {
+                (
+                    indexed(gl::MAX_COMPUTE_WORK_GROUP_COUNT),
+                    indexed(gl::MAX_COMPUTE_WORK_GROUP_SIZE),
+                    integer(gl::MAX_COMPUTE_WORK_GROUP_INVOCATIONS),
+                )
+            } else {
+                Default::default()
+            }
+        };
+        // Built without compute shaders; see the `gl` module.
+        #[cfg(not(gl_compute))]
+        let (work_group_count, work_group_size, work_group_invocations) = Default::default();
+        Limits {
+            max_texture_size: integer(gl::MAX_TEXTURE_SIZE),
+            max_cube_map_texture_size: integer(gl::MAX_CUBE_MAP_TEXTURE_SIZE),
+            max_3d_texture_size: integer(gl::MAX_3D_TEXTURE_SIZE),
+            max_array_texture_layers: integer(gl::MAX_ARRAY_TEXTURE_LAYERS),
+            max_texture_units: integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS),
+            max_uniform_block_size: integer(gl::MAX_UNIFORM_BLOCK_SIZE),
+            max_vertex_attribs: integer(gl::MAX_VERTEX_ATTRIBS),
+            max_color_attachments: integer(gl::MAX_COLOR_ATTACHMENTS),
+            max_draw_buffers: integer(gl::MAX_DRAW_BUFFERS),
+            max_samples: integer(gl::MAX_SAMPLES),
+            max_compute_work_group_count: work_group_count,
+            max_compute_work_group_size: work_group_size,
+            max_compute_work_group_invocations: work_group_invocations,
+        }
+    }
+
+    /// Fails if a `width`×`height` 2D texture or renderbuffer is too large or negative.
+    pub fn check_texture_size(&self, width: i32, height: i32) -> Result<()> {
+        let max = self.max_texture_size;
+        if width < 0 || height < 0 || width > max || height > max {
+            return Err(anyhow!(
+                "Texture size {}x{} is outside 0x0..={}x{}",
+                width,
+                height,
+                max,
+                max
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_cube_map_size(&self, size: i32) -> Result<()> {
+        if size <= 0 || size > self.max_cube_map_texture_size {
+            return Err(anyhow!(
+                "Cube map size {} is outside 1..={}",
+                size,
+                self.max_cube_map_texture_size
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn check_samples(&self, samples: i32) -> Result<()> {
+        if samples > self.max_samples {
+            return Err(anyhow!(
+                "{} samples per pixel exceeds the maximum of {}",
+                samples,
+                self.max_samples
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails if `attachments` names more draw buffers, or a higher color attachment, than
+    /// the context has.
+    pub fn check_draw_buffers(&self, attachments: &[gl::types::GLenum]) -> Result<()> {
+        if attachments.len() > self.max_draw_buffers as usize {
+            return Err(anyhow!(
+                "{} draw buffers exceed the maximum of {}",
+                attachments.len(),
+                self.max_draw_buffers
+            ));
+        }
+        let supported =
+            gl::COLOR_ATTACHMENT0..gl::COLOR_ATTACHMENT0 + self.max_color_attachments as u32;
+        let color = gl::COLOR_ATTACHMENT0..=gl::COLOR_ATTACHMENT31;
+        if let Some(attachment) = attachments
+            .iter()
+            .find(|a| color.contains(a) && !supported.contains(a))
+        {
+            return Err(anyhow!(
+                "Color attachment {} is past the {} the context supports",
+                attachment - gl::COLOR_ATTACHMENT0,
+                self.max_color_attachments
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails if a dispatch of `groups` work groups is larger than the context allows,
+    /// including when it has no compute shaders at all.
+    pub fn check_work_groups(&self, groups: [u32; 3]) -> Result<()> {
+        for (axis, (&count, &max)) in ["X", "Y", "Z"]
+            .iter()
+            .zip(groups.iter().zip(&self.max_compute_work_group_count))
+        {
+            if count > max as u32 {
+                return Err(anyhow!(
+                    "{} work groups along {} exceed the maximum of {}",
+                    count,
+                    axis,
+                    max
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Max texture size:       {}", self.max_texture_size)?;
+        writeln!(
+            f,
+            "Max cube map size:      {}",
+            self.max_cube_map_texture_size
+        )?;
+        writeln!(f, "Max 3D texture size:    {}", self.max_3d_texture_size)?;
+        writeln!(
+            f,
+            "Max array layers:       {}",
+            self.max_array_texture_layers
+        )?;
+        writeln!(f, "Max texture units:      {}", self.max_texture_units)?;
+        writeln!(f, "Max uniform block size: {}", self.max_uniform_block_size)?;
+        writeln!(f, "Max vertex attributes:  {}", self.max_vertex_attribs)?;
+        writeln!(f, "Max color attachments:  {}", self.max_color_attachments)?;
+        writeln!(f, "Max draw buffers:       {}", self.max_draw_buffers)?;
+        writeln!(f, "Max samples:            {}", self.max_samples)?;
+        let [x, y, z] = self.max_compute_work_group_count;
+        writeln!(f, "Max work group count:   {}x{}x{}", x, y, z)?;
+        let [x, y, z] = self.max_compute_work_group_size;
+        writeln!(f, "Max work group size:    {}x{}x{}", x, y, z)?;
+        write!(
+            f,
+            "Max work group invocations: {}",
+            self.max_compute_work_group_invocations
+        )
+    }
+}
+
+/// The [`Limits`] of the current context, queried on first use with [`info`].
+pub fn limits() -> Limits {
+    info().limits
+}
+
+/// How clip-space depth maps to the depth buffer, set with `glClipControl`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DepthMode {
+    /// GL's default: `-1..1` in clip space, as produced by `Mat4::perspective_rh_gl`.
+    #[default]
+    NegativeOneToOne,
+    /// `0..1` like Direct3D and Vulkan, for `Mat4::perspective_rh`. The basis of
+    /// [`ContextOptions::reversed_z`].
+    ZeroToOne,
+}
+
+/// Context-wide state applied once after the function pointers are loaded. The runners
+/// apply [`crate::app::ContextConfig::options`]; code managing its own context calls
+/// [`ContextOptions::apply`]. Options the context can't honor are logged and left at
+/// GL's default, which [`options`] then reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContextOptions {
+    /// Filters across cube map face edges (GL 3.2). Always on in ES.
+    pub seamless_cube_maps: bool,
+    /// Restarts strips and fans at this element index (GL 3.1). ES always restarts at
+    /// the all-ones index of the index type, so only `u32::MAX` is honored there.
+    pub primitive_restart: Option<u32>,
+    /// Lets vertex shaders set `gl_PointSize`. Always on in ES.
+    pub program_point_size: bool,
+    /// Needs GL 4.5 or `ARB_clip_control`.
+    pub depth_mode: DepthMode,
+    /// Clamps depth instead of clipping at the near and far planes, e.g. for shadow
+    /// casters in front of the light's near plane (GL 3.2 or `EXT_depth_clamp`).
+    pub depth_clamp: bool,
+    /// Reversed-Z: implies [`DepthMode::ZeroToOne`], clears depth to 0 and tests with
+    /// `GL_GREATER`. See [`crate::depth`].
+    pub reversed_z: bool,
+    /// Delays deleting dropped objects until the GPU has finished the frame that last
+    /// used them; see [`delete`].
+    pub deferred_deletion: bool,
+}
+
+impl Default for ContextOptions {
+    /// Seamless cube maps on, everything else at GL's defaults.
+    fn default() -> Self {
+        ContextOptions {
+            seamless_cube_maps: true,
+            primitive_restart: None,
+            program_point_size: false,
+            depth_mode: DepthMode::NegativeOneToOne,
+            depth_clamp: false,
+            reversed_z: false,
+            deferred_deletion: false,
+        }
+    }
+}
+
+impl ContextOptions {
+    /// Sets every option in the current context and records what took effect for
+    /// [`options`].
+    pub fn apply(&self) {
+        let info = info();
+        let desktop = |version| !info.es && info.version >= version;
+        let mut applied = *self;
+        let enable = |capability, enabled| unsafe {
+            if enabled {
+                gl::Enable(capability);
+            } else {
+                gl::Disable(capability);
+            }
+        };
+
+        if !info.es {
+            enable(gl::TEXTURE_CUBE_MAP_SEAMLESS, self.seamless_cube_maps);
+        } else {
+            applied.seamless_cube_maps = true;
+        }
+
+        applied.primitive_restart = apply_primitive_restart(&info, self.primitive_restart);
+
+        if !info.es {
+            enable(gl::PROGRAM_POINT_SIZE, self.program_point_size);
+        } else {
+            applied.program_point_size = true;
+        }
+
+        let clip_control = (desktop((4, 5)) || info.has_extension("GL_ARB_clip_control"))
+            && gl::ClipControl::is_loaded();
+        if self.reversed_z {
+            applied.depth_mode = DepthMode::ZeroToOne;
+        }
+        if clip_control {
+            let depth = match applied.depth_mode {
+                DepthMode::NegativeOneToOne => gl::NEGATIVE_ONE_TO_ONE,
+                DepthMode::ZeroToOne => gl::ZERO_TO_ONE,
+            };
+            unsafe {
+                gl::ClipControl(gl::LOWER_LEFT, depth);
+            }
+        } else if applied.depth_mode != DepthMode::NegativeOneToOne {
+            tracing::warn!("Clip control needs GL 4.5 or ARB_clip_control; keeping -1..1 depth");
+            applied.depth_mode = DepthMode::NegativeOneToOne;
+            applied.reversed_z = false;
+        }
+        if applied.reversed_z {
+            unsafe {
+                gl::DepthFunc(gl::GREATER);
+                gl::ClearDepthf(0.0);
+            }
+        }
+
+        let depth_clamp = desktop((3, 2))
+            || info.has_extension("GL_ARB_depth_clamp")
+            || info.has_extension("GL_EXT_depth_clamp");
+        if depth_clamp {
+            enable(gl::DEPTH_CLAMP, self.depth_clamp);
+        } else if self.depth_clamp {
+            tracing::warn!("Depth clamping needs GL 3.2 or EXT_depth_clamp");
+            applied.depth_clamp = false;
+        }
+
+        if !self.deferred_deletion {
+            flush_deletions();
+        }
+
+        OPTIONS.with(|options| options.set(Some(applied)));
+    }
+}
+
+/// Sets the restart index and returns the one in effect.
+fn apply_primitive_restart(info: &ContextInfo, index: Option<u32>) -> Option<u32> {
+    match index {
+        Some(index) if info.es && index != u32::MAX => {
+            tracing::warn!("ES only restarts primitives at the all-ones index");
+            Some(u32::MAX)
+        }
+        _ if info.es => Some(u32::MAX),
+        Some(index) => {
+            unsafe {
+                gl::Enable(gl::PRIMITIVE_RESTART);
+                gl::PrimitiveRestartIndex(index);
+            }
+            Some(index)
+        }
+        None => {
+            unsafe {
+                gl::Disable(gl::PRIMITIVE_RESTART);
+            }
+            None
+        }
+    }
+}
+
+/// Changes [`ContextOptions::primitive_restart`] after the context was set up, e.g.
+/// around draws of index data that restarts at a different index than the rest.
+pub fn set_primitive_restart(index: Option<u32>) {
+    let mut applied = options();
+    applied.primitive_restart = apply_primitive_restart(&info(), index);
+    OPTIONS.with(|options| options.set(Some(applied)));
+}
+
+/// The options last applied to the context on this thread. Before any were, the
+/// defaults, which every runner applies.
+pub fn options() -> ContextOptions {
+    OPTIONS.with(Cell::get).unwrap_or_default()
+}
+
+/// The [`ContextInfo`] of the current context, queried on first use.
+pub fn info() -> Rc<ContextInfo> {
+    INFO.with(|info| {
+        info.borrow_mut()
+            .get_or_insert_with(|| Rc::new(ContextInfo::query()))
+            .clone()
+    })
+}
+
+/// Logs a warning if the current context renders in software.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn warn_if_software() {
+    let info = info();
+    if info.is_software() {
+        tracing::warn!(
+            "Rendering in software on {}; expect low frame rates",
+            info.renderer
+        );
+    }
+}
+
+/// Logs a warning if the current context won't report GPU resets although the runner
+/// was asked to recover from them.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn warn_if_no_reset_notification() {
+    if !info().reset_notification {
+        tracing::warn!("The context doesn't report GPU resets; they can't be recovered from");
+    }
+}
+
+/// Asks Mesa for its llvmpipe software rasterizer, through the environment variables
+/// its drivers read when a display is opened. Call it before creating the context;
+/// other drivers ignore it. The runners call it for the `software` setting.
+pub fn request_software() {
+    std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+    std::env::set_var("GALLIUM_DRIVER", "llvmpipe");
+}
+
+/// A GL object to delete, by name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deletion {
+    Buffer(gl::types::GLuint),
+    Texture(gl::types::GLuint),
+    Renderbuffer(gl::types::GLuint),
+    Framebuffer(gl::types::GLuint),
+    VertexArray(gl::types::GLuint),
+    Sampler(gl::types::GLuint),
+    Program(gl::types::GLuint),
+    Query(gl::types::GLuint),
+    TransformFeedback(gl::types::GLuint),
+}
+
+impl Deletion {
+    fn run(self) {
+        unsafe {
+            match self {
+                Deletion::Buffer(id) => gl::DeleteBuffers(1, &id),
+                Deletion::Texture(id) => gl::DeleteTextures(1, &id),
+                Deletion::Renderbuffer(id) => gl::DeleteRenderbuffers(1, &id),
+                Deletion::Framebuffer(id) => gl::DeleteFramebuffers(1, &id),
+                Deletion::VertexArray(id) => gl::DeleteVertexArrays(1, &id),
+                Deletion::Sampler(id) => gl::DeleteSamplers(1, &id),
+                Deletion::Program(id) => gl::DeleteProgram(id),
+                Deletion::Query(id) => gl::DeleteQueries(1, &id),
+                Deletion::TransformFeedback(id) => gl::DeleteTransformFeedbacks(1, &id),
+            }
+        }
+    }
+}
+
+struct DeletionQueue {
+    /// Deleted during the current frame.
+    pending: Vec<Deletion>,
+    /// Deleted during earlier frames, each behind the fence issued when it ended.
+    retired: VecDeque<(Fence, Vec<Deletion>)>,
+}
+
+/// Deletes `object` now or, with [`ContextOptions::deferred_deletion`], once the GPU
+/// has finished the frame in progress.
+pub fn delete(object: Deletion) {
+    if options().deferred_deletion {
+        DELETIONS.with(|queue| queue.borrow_mut().pending.push(object));
+    } else {
+        object.run();
+    }
+}
+
+/// Fences the deletions of the frame that just ended and performs those of earlier
+/// frames whose fences have signaled. Called by [`crate::per_frame::advance_frame`].
+pub(crate) fn retire_deletions() {
+    DELETIONS.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        if !queue.pending.is_empty() {
+            let pending = std::mem::take(&mut queue.pending);
+            queue.retired.push_back((Fence::new(), pending));
+        }
+        while queue
+            .retired
+            .front()
+            .is_some_and(|(fence, _)| fence.is_signaled())
+        {
+            let (_, deletions) = queue.retired.pop_front().unwrap();
+            deletions.into_iter().for_each(Deletion::run);
+        }
+    });
+}
+
+/// Performs every queued deletion now, without waiting for the fences. The runners call
+/// this when the context is lost, before the queued names could reach its replacement.
+pub fn flush_deletions() {
+    DELETIONS.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        let pending = std::mem::take(&mut queue.pending);
+        for (_, deletions) in queue.retired.drain(..) {
+            deletions.into_iter().for_each(Deletion::run);
+        }
+        pending.into_iter().for_each(Deletion::run);
+    });
+}
+
+/// Objects waiting in the deletion queue.
+pub fn pending_deletions() -> usize {
+    DELETIONS.with(|queue| {
+        let queue = queue.borrow();
+        queue.pending.len()
+            + queue
+                .retired
+                .iter()
+                .map(|(_, deletions)| deletions.len())
+                .sum::<usize>()
+    })
+}
+
+/// This thread's generation of [`GlContext`] tokens.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn generation() -> u64 {
+    GENERATION.with(Cell::get)
+}
+
+/// Starts this thread at another thread's `generation`, so that objects made in a
+/// context sharing with that thread's pass [`GlContext::is_current`] there. Set before
+/// taking any token.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn share_generation(generation: u64) {
+    GENERATION.with(|current| current.set(generation));
+}
+
+/// Forgets everything cached about the current context, after it has been replaced,
+/// and starts a new generation of [`GlContext`] tokens.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn forget() {
+    GENERATION.with(|generation| generation.set(generation.get() + 1));
+    INFO.with(|info| info.borrow_mut().take());
+    crate::dsa::forget();
+}
+
+/// Who caused a context reset, according to the driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResetStatus {
+    /// This context did something that hung the GPU.
+    Guilty,
+    /// Another context did.
+    Innocent,
+    Unknown,
+}
+
+/// Whether the current context has been lost to a GPU reset, through
+/// `glGetGraphicsResetStatus` (GL 4.5 / `ARB_robustness`). Always `None` when the entry
+/// point is missing or the context wasn't created with a reset notification strategy.
+/// A lost context stays lost; every GL object in it is gone.
+pub fn reset_status() -> Option<ResetStatus> {
+    if !gl::GetGraphicsResetStatus::is_loaded() {
+        return None;
+    }
+    match unsafe { gl::GetGraphicsResetStatus() } {
+        gl::GUILTY_CONTEXT_RESET => Some(ResetStatus::Guilty),
+        gl::INNOCENT_CONTEXT_RESET => Some(ResetStatus::Innocent),
+        gl::UNKNOWN_CONTEXT_RESET => Some(ResetStatus::Unknown),
+        _ => None,
+    }
+}
+
+/// The context's `(major, minor)` version.
+pub fn version() -> (i32, i32) {
+    let (mut major, mut minor) = (0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+    }
+    (major, minor)
+}
+
+/// Whether the context advertises the extension `name`, e.g. `"GL_KHR_debug"`.
+pub fn has_extension(name: &str) -> bool {
+    info().has_extension(name)
+}
+
+fn get_string(name: gl::types::GLenum) -> String {
+    string(unsafe { gl::GetString(name) }).unwrap_or_default()
+}
+
+fn string(ptr: *const gl::types::GLubyte) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(
+            unsafe { CStr::from_ptr(ptr.cast()) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+fn integer(name: gl::types::GLenum) -> i32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(generation: u64) -> GlContext {
+        GlContext {
+            generation,
+            _thread: PhantomData,
+        }
+    }
+
+    #[test]
+    fn shared_tokens_survive_an_earlier_recovery() {
+        // The render thread has recovered from a reset before the upload thread starts.
+        forget();
+        let generation = generation();
+        let uploaded = std::thread::spawn(move || {
+            share_generation(generation);
+            GlContext::new().generation
+        })
+        .join()
+        .unwrap();
+        assert!(token(uploaded).is_current());
+
+        // Objects uploaded before the next reset belong to the lost share group.
+        forget();
+        assert!(!token(uploaded).is_current());
+    }
+}
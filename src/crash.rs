@@ -0,0 +1,298 @@
+//! GL state dumps for bug reports.
+//!
+//! [`dump`] describes the context and what is bound and enabled in it right now, as
+//! queried from GL: program, vertex array, buffers, framebuffers, textures and samplers
+//! per unit, capabilities, viewport, blend and depth state, and pending errors. With the
+//! `gl-trace` feature the last [`CALL_HISTORY`] GL calls on the thread are included as
+//! well. [`install_panic_hook`] writes a dump whenever a thread with a current context
+//! panics, so the file can be attached to the bug report; `hello-gl` installs it.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Result};
+
+use crate::context::{self, GlContext};
+use crate::gl;
+use crate::gl::types::GLenum;
+
+/// GL calls kept per thread with the `gl-trace` feature.
+pub const CALL_HISTORY: usize = 64;
+
+/// Texture units whose bindings are listed.
+const DUMPED_TEXTURE_UNITS: i32 = 16;
+
+/// Capabilities listed with whether they are enabled, and whether OpenGL ES has them.
+const CAPABILITIES: [(&str, GLenum, bool); 13] = [
+    ("GL_DEPTH_TEST", gl::DEPTH_TEST, true),
+    ("GL_STENCIL_TEST", gl::STENCIL_TEST, true),
+    ("GL_BLEND", gl::BLEND, true),
+    ("GL_CULL_FACE", gl::CULL_FACE, true),
+    ("GL_SCISSOR_TEST", gl::SCISSOR_TEST, true),
+    ("GL_POLYGON_OFFSET_FILL", gl::POLYGON_OFFSET_FILL, true),
+    ("GL_RASTERIZER_DISCARD", gl::RASTERIZER_DISCARD, true),
+    (
+        "GL_PRIMITIVE_RESTART_FIXED_INDEX",
+        gl::PRIMITIVE_RESTART_FIXED_INDEX,
+        true,
+    ),
+    ("GL_PRIMITIVE_RESTART", gl::PRIMITIVE_RESTART, false),
+    ("GL_MULTISAMPLE", gl::MULTISAMPLE, false),
+    ("GL_FRAMEBUFFER_SRGB", gl::FRAMEBUFFER_SRGB, false),
+    ("GL_DEPTH_CLAMP", gl::DEPTH_CLAMP, false),
+    (
+        "GL_TEXTURE_CUBE_MAP_SEAMLESS",
+        gl::TEXTURE_CUBE_MAP_SEAMLESS,
+        false,
+    ),
+];
+
+/// Bindings listed by name.
+const BINDINGS: [(&str, GLenum); 8] = [
+    ("Program:", gl::CURRENT_PROGRAM),
+    ("Vertex array:", gl::VERTEX_ARRAY_BINDING),
+    ("Array buffer:", gl::ARRAY_BUFFER_BINDING),
+    ("Element array buffer:", gl::ELEMENT_ARRAY_BUFFER_BINDING),
+    ("Uniform buffer:", gl::UNIFORM_BUFFER_BINDING),
+    ("Draw framebuffer:", gl::DRAW_FRAMEBUFFER_BINDING),
+    ("Read framebuffer:", gl::READ_FRAMEBUFFER_BINDING),
+    ("Renderbuffer:", gl::RENDERBUFFER_BINDING),
+];
+
+/// Texture targets whose bindings are listed per unit.
+const TEXTURE_BINDINGS: [(&str, GLenum); 5] = [
+    ("2D", gl::TEXTURE_BINDING_2D),
+    ("2D array", gl::TEXTURE_BINDING_2D_ARRAY),
+    ("cube map", gl::TEXTURE_BINDING_CUBE_MAP),
+    ("3D", gl::TEXTURE_BINDING_3D),
+    ("2D multisample", gl::TEXTURE_BINDING_2D_MULTISAMPLE),
+];
+
+thread_local! {
+    static CALLS: RefCell<VecDeque<String>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Remembers a GL call made on this thread. Called by the `gl-trace` bindings.
+#[cfg(feature = "gl-trace")]
+pub(crate) fn record_call(call: std::fmt::Arguments) {
+    CALLS.with(|calls| {
+        // Busy only when a call is made while dumping the history.
+        let Ok(mut calls) = calls.try_borrow_mut() else {
+            return;
+        };
+        let mut entry = if calls.len() == CALL_HISTORY {
+            calls.pop_front().unwrap()
+        } else {
+            String::new()
+        };
+        entry.clear();
+        let _ = entry.write_fmt(call);
+        calls.push_back(entry);
+    });
+}
+
+/// The last GL calls on this thread, oldest first. Empty without the `gl-trace` feature.
+pub fn recent_calls() -> Vec<String> {
+    CALLS.with(|calls| {
+        calls
+            .try_borrow()
+            .map(|calls| calls.iter().cloned().collect())
+            .unwrap_or_default()
+    })
+}
+
+fn integer(name: GLenum) -> i32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value
+}
+
+fn integers<const N: usize>(name: GLenum) -> [i32; N] {
+    let mut values = [0; N];
+    unsafe {
+        gl::GetIntegerv(name, values.as_mut_ptr());
+    }
+    values
+}
+
+fn floats<const N: usize>(name: GLenum) -> [f32; N] {
+    let mut values = [0.0; N];
+    unsafe {
+        gl::GetFloatv(name, values.as_mut_ptr());
+    }
+    values
+}
+
+fn booleans<const N: usize>(name: GLenum) -> [bool; N] {
+    let mut values = [0; N];
+    unsafe {
+        gl::GetBooleanv(name, values.as_mut_ptr());
+    }
+    values.map(|value| value != 0)
+}
+
+/// Describes the context on this thread and its current state, as text. `panic` is the
+/// panic message to put at the top, if any. Must be called with a current context.
+pub fn dump(panic: Option<&str>) -> String {
+    // Taken first, before the queries below are recorded too.
+    let calls = recent_calls();
+    let mut out = String::new();
+    write_dump(&mut out, panic, &calls).unwrap();
+    out
+}
+
+fn write_dump(out: &mut String, panic: Option<&str>, calls: &[String]) -> std::fmt::Result {
+    let thread = std::thread::current();
+    writeln!(out, "hello-gl GL state dump")?;
+    writeln!(out, "Thread: {}", thread.name().unwrap_or("<unnamed>"))?;
+    if let Some(panic) = panic {
+        writeln!(out, "Panic: {}", panic)?;
+    }
+
+    let info = context::info();
+    writeln!(out, "\n[context]\n{}", info)?;
+    writeln!(out, "Options: {:?}", context::options())?;
+    if let Some(status) = context::reset_status() {
+        writeln!(out, "Lost to a GPU reset: {:?}", status)?;
+    }
+
+    writeln!(out, "\n[bindings]")?;
+    for &(name, binding) in &BINDINGS {
+        writeln!(out, "{:<22}{}", name, integer(binding))?;
+    }
+    let active = integer(gl::ACTIVE_TEXTURE);
+    writeln!(
+        out,
+        "{:<22}{}",
+        "Active texture unit:",
+        active - gl::TEXTURE0 as i32
+    )?;
+    let units = DUMPED_TEXTURE_UNITS.min(info.limits.max_texture_units);
+    for unit in 0..units {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+        }
+        let mut bound: Vec<String> = TEXTURE_BINDINGS
+            .iter()
+            .map(|&(target, binding)| (target, integer(binding)))
+            .filter(|&(_, texture)| texture != 0)
+            .map(|(target, texture)| format!("{} {}", target, texture))
+            .collect();
+        let sampler = integer(gl::SAMPLER_BINDING);
+        if sampler != 0 {
+            bound.push(format!("sampler {}", sampler));
+        }
+        if !bound.is_empty() {
+            writeln!(out, "Texture unit {:<9}{}", unit, bound.join(", "))?;
+        }
+    }
+    unsafe {
+        gl::ActiveTexture(active as u32);
+    }
+
+    writeln!(out, "\n[capabilities]")?;
+    for &(name, capability, on_es) in &CAPABILITIES {
+        if on_es || !info.es {
+            let enabled = unsafe { gl::IsEnabled(capability) } != 0;
+            writeln!(out, "{:<34}{}", name, enabled)?;
+        }
+    }
+
+    writeln!(out, "\n[state]")?;
+    let [x, y, width, height] = integers::<4>(gl::VIEWPORT);
+    writeln!(out, "Viewport:      {} {} {}x{}", x, y, width, height)?;
+    let [x, y, width, height] = integers::<4>(gl::SCISSOR_BOX);
+    writeln!(out, "Scissor box:   {} {} {}x{}", x, y, width, height)?;
+    writeln!(out, "Depth func:    {:#x}", integer(gl::DEPTH_FUNC))?;
+    writeln!(
+        out,
+        "Depth mask:    {}",
+        booleans::<1>(gl::DEPTH_WRITEMASK)[0]
+    )?;
+    writeln!(out, "Depth range:   {:?}", floats::<2>(gl::DEPTH_RANGE))?;
+    writeln!(
+        out,
+        "Blend func:    {:#x} {:#x}, alpha {:#x} {:#x}",
+        integer(gl::BLEND_SRC_RGB),
+        integer(gl::BLEND_DST_RGB),
+        integer(gl::BLEND_SRC_ALPHA),
+        integer(gl::BLEND_DST_ALPHA)
+    )?;
+    writeln!(
+        out,
+        "Blend eq:      {:#x}, alpha {:#x}",
+        integer(gl::BLEND_EQUATION_RGB),
+        integer(gl::BLEND_EQUATION_ALPHA)
+    )?;
+    writeln!(
+        out,
+        "Color mask:    {:?}",
+        booleans::<4>(gl::COLOR_WRITEMASK)
+    )?;
+    writeln!(
+        out,
+        "Clear color:   {:?}",
+        floats::<4>(gl::COLOR_CLEAR_VALUE)
+    )?;
+    writeln!(out, "Cull face:     {:#x}", integer(gl::CULL_FACE_MODE))?;
+    writeln!(out, "Front face:    {:#x}", integer(gl::FRONT_FACE))?;
+    if !info.es {
+        writeln!(out, "Polygon mode:  {:#x}", integer(gl::POLYGON_MODE))?;
+    }
+
+    // Bounded in case the context is lost, when some drivers report errors forever.
+    let errors: Vec<String> = (0..16)
+        .map(|_| unsafe { gl::GetError() })
+        .take_while(|&error| error != gl::NO_ERROR)
+        .map(|error| format!("{:#x}", error))
+        .collect();
+    writeln!(out, "\n[errors]")?;
+    if errors.is_empty() {
+        writeln!(out, "None pending")?;
+    } else {
+        writeln!(out, "{}", errors.join(" "))?;
+    }
+
+    writeln!(out, "\n[recent calls]")?;
+    if cfg!(feature = "gl-trace") {
+        for call in calls {
+            writeln!(out, "{}", call)?;
+        }
+    } else {
+        writeln!(out, "Build with the gl-trace feature to record GL calls.")?;
+    }
+    Ok(())
+}
+
+/// Writes a [`dump`] to a new `gl-state-<seconds>.txt` in `dir`, returning its path.
+pub fn write(dir: &Path, panic: Option<&str>) -> Result<PathBuf> {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let path = dir.join(format!("gl-state-{}.txt", seconds));
+    std::fs::write(&path, dump(panic))
+        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Makes panics on threads with a current context also [`write`] a dump to `dir`, after
+/// the previous hook has reported the panic.
+pub fn install_panic_hook(dir: impl Into<PathBuf>) {
+    let dir = dir.into();
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if GlContext::current().is_err() {
+            return;
+        }
+        match write(&dir, Some(&info.to_string())) {
+            Ok(path) => eprintln!("GL state written to {}", path.display()),
+            Err(e) => eprintln!("{:#}", e),
+        }
+    }));
+}
@@ -0,0 +1,118 @@
+//! A mouse cursor drawn by the app instead of the OS.
+//!
+//! The hardware cursor can't always show custom art (SDL and some compositors ignore
+//! custom images, and none of [`crate::app::Cursor`] is one) and doesn't follow the
+//! game's own scaling. A [`SoftwareCursor`] hides it through [`crate::app::Window`] and
+//! draws an image at the cursor position with a [`SpriteBatch`] as the last thing in a
+//! frame, so it lags by up to a frame but scales with everything else.
+
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::app::Window;
+use crate::context;
+use crate::gl;
+use crate::image::Image;
+use crate::math::{Mat4, Vec2};
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::texture::Texture;
+
+pub struct SoftwareCursor {
+    texture: Rc<Texture>,
+    batch: SpriteBatch,
+    size: Vec2,
+    hotspot: Vec2,
+    /// Screen pixels per image pixel, e.g. the window's scale factor or the zoom of
+    /// pixel art. `1.0` by default.
+    pub scale: f32,
+    enabled: bool,
+    /// Whether the OS cursor was last shown, to only tell the window on changes.
+    os_visible: Option<bool>,
+}
+
+impl SoftwareCursor {
+    /// Creates an enabled cursor from `image` whose pointing pixel is `hotspot`, in
+    /// pixels from its top-left corner.
+    pub fn new(image: &Image, hotspot: (u32, u32)) -> Result<SoftwareCursor> {
+        context::limits().check_texture_size(image.width as i32, image.height as i32)?;
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.label("software cursor");
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        texture.image_level(0, image);
+        texture.unbind();
+        Ok(SoftwareCursor {
+            texture: Rc::new(texture),
+            batch: SpriteBatch::new(1)?,
+            size: Vec2::new(image.width as f32, image.height as f32),
+            hotspot: Vec2::new(hotspot.0 as f32, hotspot.1 as f32),
+            scale: 1.0,
+            enabled: true,
+            os_visible: None,
+        })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P, hotspot: (u32, u32)) -> Result<SoftwareCursor> {
+        SoftwareCursor::new(&Image::load(path)?, hotspot)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Switches between this cursor and the OS one, taking effect at the next
+    /// [`SoftwareCursor::update_window`].
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Hides the OS cursor while enabled and shows it again once disabled. Call from
+    /// [`crate::app::App::update_window`].
+    pub fn update_window(&mut self, window: &mut dyn Window) {
+        let os_visible = !self.enabled;
+        if self.os_visible != Some(os_visible) {
+            window.set_cursor_visible(os_visible);
+            self.os_visible = Some(os_visible);
+        }
+    }
+
+    /// The cursor sprite with its hotspot at `position` in the units of the batch's
+    /// projection.
+    pub fn sprite(&self, position: Vec2) -> Sprite {
+        Sprite::new(position - self.hotspot * self.scale, self.size * self.scale)
+    }
+
+    /// Adds the cursor to a batch already drawing in screen pixels, to keep UI and
+    /// cursor in one draw call when they share a texture.
+    pub fn draw_into(&self, batch: &mut SpriteBatch, position: Vec2) {
+        batch.draw(&self.texture, &self.sprite(position));
+    }
+
+    /// Draws the cursor over a `width` x `height` framebuffer at `position`, in physical
+    /// pixels from the top left as [`crate::input::Input::cursor`] reports it. Nothing
+    /// is drawn while disabled or when the cursor is outside the window (`None`).
+    pub fn draw(&mut self, position: Option<Vec2>, width: u32, height: u32) {
+        let Some(position) = position.filter(|_| self.enabled) else {
+            return;
+        };
+        let _span = tracing::debug_span!("pass", name = "software cursor").entered();
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        let projection = Mat4::orthographic_rh_gl(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+        self.batch.begin(projection);
+        self.batch
+            .draw(&self.texture, &self.sprite(position.round()));
+        self.batch.end();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Object labels for graphics debuggers (KHR_debug).
+//!
+//! Every object wrapper has a `label` method; the loaders label what they create with
+//! the mesh name or file path, so RenderDoc and apitrace captures show readable names.
+//! Labels are silently dropped on contexts without KHR_debug.
+
+use std::cell::Cell;
+
+use crate::context;
+use crate::gl;
+
+thread_local! {
+    static SUPPORTED: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Whether object labels are supported (GL 4.3 or `GL_KHR_debug`). Detected on first use.
+pub fn is_supported() -> bool {
+    SUPPORTED.with(|supported| {
+        supported.get().unwrap_or_else(|| {
+            let detected = gl::ObjectLabel::is_loaded()
+                && (context::version() >= (4, 3) || context::has_extension("GL_KHR_debug"));
+            supported.set(Some(detected));
+            detected
+        })
+    })
+}
+
+/// Labels the object `id` of kind `identifier` (`GL_BUFFER`, `GL_TEXTURE`, ...).
+///
+/// Without DSA, objects only exist once they have been bound, so label after the first
+/// bind.
+pub fn object_label(identifier: gl::types::GLenum, id: gl::types::GLuint, label: &str) {
+    if !is_supported() {
+        return;
+    }
+    let label = truncate(label);
+    unsafe {
+        gl::ObjectLabel(identifier, id, label.len() as i32, label.as_ptr().cast());
+    }
+}
+
+/// Labels a sync object.
+pub fn sync_label(sync: gl::types::GLsync, label: &str) {
+    if !is_supported() {
+        return;
+    }
+    let label = truncate(label);
+    unsafe {
+        gl::ObjectPtrLabel(sync as *const _, label.len() as i32, label.as_ptr().cast());
+    }
+}
+
+/// Cuts `label` to `GL_MAX_LABEL_LENGTH` on a character boundary.
+fn truncate(label: &str) -> &str {
+    let mut max = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_LABEL_LENGTH, &mut max);
+    }
+    let max = (max.max(1) - 1) as usize;
+    if label.len() <= max {
+        return label;
+    }
+    let mut end = max;
+    while !label.is_char_boundary(end) {
+        end -= 1;
+    }
+    &label[..end]
+}
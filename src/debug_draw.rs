@@ -0,0 +1,414 @@
+//! Immediate-mode debug lines and points.
+//!
+//! Shapes are added during the frame and drawn by [`DebugDraw::flush`], which also
+//! clears them. Useful for visualizing transforms, bounds and lights.
+//!
+//! Core profiles only guarantee one-pixel `GL_LINES`, so wider or anti-aliased lines are
+//! expanded into screen-aligned quads in the vertex shader, one instance per segment, and
+//! faded at the edges by their distance from the center line. Points are round sprites
+//! sized with `gl_PointSize`.
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::context;
+use crate::culling::Aabb;
+use crate::gl;
+use crate::math::{Mat4, Vec3};
+use crate::shader::Program;
+use crate::stats;
+use crate::transient::{self, TransientBuffer};
+use crate::validate;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_color;
+uniform mat4 u_view_projection;
+out vec3 v_color;
+void main() {
+    v_color = a_color;
+    gl_Position = u_view_projection * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec3 v_color;
+out vec4 frag_color;
+void main() {
+    frag_color = vec4(v_color, 1.0);
+}
+"#;
+
+/// Expands segment `gl_InstanceID` into a quad of four strip vertices. `v_edge` is the
+/// distance from the center line in pixels. Endpoints behind the camera are first moved
+/// along the segment to just in front of it.
+const WIDE_LINE_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_start;
+layout (location = 1) in vec3 a_start_color;
+layout (location = 2) in vec3 a_end;
+layout (location = 3) in vec3 a_end_color;
+uniform mat4 u_view_projection;
+uniform vec2 u_viewport;
+uniform float u_half_width;
+out vec3 v_color;
+out float v_edge;
+void main() {
+    const float near_w = 1e-4;
+    vec4 start = u_view_projection * vec4(a_start, 1.0);
+    vec4 end = u_view_projection * vec4(a_end, 1.0);
+    if (start.w < near_w && end.w < near_w) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+    if (start.w < near_w) {
+        start = mix(start, end, (near_w - start.w) / (end.w - start.w));
+    } else if (end.w < near_w) {
+        end = mix(end, start, (near_w - end.w) / (start.w - end.w));
+    }
+
+    vec2 screen_start = start.xy / start.w * u_viewport * 0.5;
+    vec2 screen_end = end.xy / end.w * u_viewport * 0.5;
+    vec2 along = screen_end - screen_start;
+    along = length(along) > 1e-4 ? normalize(along) : vec2(1.0, 0.0);
+    vec2 across = vec2(-along.y, along.x);
+
+    bool at_end = gl_VertexID >= 2;
+    float side = (gl_VertexID & 1) == 0 ? -1.0 : 1.0;
+    vec4 position = at_end ? end : start;
+    vec2 offset = across * side + along * (at_end ? 1.0 : -1.0);
+    position.xy += offset * u_half_width * 2.0 / u_viewport * position.w;
+    gl_Position = position;
+    v_color = at_end ? a_end_color : a_start_color;
+    v_edge = side * u_half_width;
+}
+"#;
+
+/// `u_half_width` includes a one-pixel fringe for the fade.
+const WIDE_LINE_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec3 v_color;
+in float v_edge;
+uniform float u_half_width;
+uniform bool u_smooth;
+out vec4 frag_color;
+void main() {
+    float coverage = u_half_width - 0.5 - abs(v_edge);
+    float alpha = u_smooth ? clamp(coverage, 0.0, 1.0) : step(0.5, coverage);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    frag_color = vec4(v_color, alpha);
+}
+"#;
+
+const POINT_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_color;
+uniform mat4 u_view_projection;
+uniform float u_point_size;
+out vec3 v_color;
+void main() {
+    v_color = a_color;
+    gl_Position = u_view_projection * vec4(a_position, 1.0);
+    gl_PointSize = u_point_size;
+}
+"#;
+
+/// Cuts the square sprite down to a disc.
+const POINT_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec3 v_color;
+uniform float u_point_size;
+uniform bool u_smooth;
+out vec4 frag_color;
+void main() {
+    float coverage = u_point_size * (0.5 - length(gl_PointCoord - 0.5)) + 0.5;
+    float alpha = u_smooth ? clamp(coverage, 0.0, 1.0) : step(0.5, coverage);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    frag_color = vec4(v_color, alpha);
+}
+"#;
+
+/// Segments used for circles and spheres.
+const CIRCLE_SEGMENTS: usize = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+pub struct DebugDraw {
+    program: Program,
+    wide_line_program: Program,
+    point_program: Program,
+    vertex_array: VertexArray,
+    /// Reads the vertex buffer as one instance per pair of line vertices.
+    segment_array: VertexArray,
+    vertex_buffer: TransientBuffer,
+    vertices: Vec<LineVertex>,
+    points: Vec<LineVertex>,
+    /// Whether lines are hidden behind scene geometry. On by default.
+    pub depth_test: bool,
+    /// Line width in pixels, `1.0` by default. Other widths, or `smooth`, draw lines as
+    /// quads.
+    pub line_width: f32,
+    /// Point diameter in pixels, `6.0` by default.
+    pub point_size: f32,
+    /// Whether line and point edges are anti-aliased with alpha blending. Off by
+    /// default.
+    pub smooth: bool,
+}
+
+impl DebugDraw {
+    pub fn new() -> Result<DebugDraw> {
+        let vertex_buffer = TransientBuffer::new(transient::DEFAULT_CAPACITY)?;
+        let vertex_array = VertexArray::new()?;
+        let segment_array = VertexArray::new()?;
+        set_attributes(&vertex_array, &segment_array, vertex_buffer.buffer(), 0);
+        for location in 0..4 {
+            segment_array.divisor(location, 1);
+        }
+
+        Ok(DebugDraw {
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            wide_line_program: Program::from_sources(
+                WIDE_LINE_VERTEX_SHADER,
+                WIDE_LINE_FRAGMENT_SHADER,
+            )?,
+            point_program: Program::from_sources(POINT_VERTEX_SHADER, POINT_FRAGMENT_SHADER)?,
+            vertex_array,
+            segment_array,
+            vertex_buffer,
+            vertices: Vec::new(),
+            points: Vec::new(),
+            depth_test: true,
+            line_width: 1.0,
+            point_size: 6.0,
+            smooth: false,
+        })
+    }
+
+    pub fn line(&mut self, a: Vec3, b: Vec3, color: Vec3) {
+        let color = color.to_array();
+        self.vertices.push(LineVertex {
+            position: a.to_array(),
+            color,
+        });
+        self.vertices.push(LineVertex {
+            position: b.to_array(),
+            color,
+        });
+    }
+
+    /// A round dot `point_size` pixels across.
+    pub fn point(&mut self, position: Vec3, color: Vec3) {
+        self.points.push(LineVertex {
+            position: position.to_array(),
+            color: color.to_array(),
+        });
+    }
+
+    /// The cube `-1..1` transformed by `transform`, which may be projective.
+    pub fn wire_box(&mut self, transform: Mat4, color: Vec3) {
+        let corner = |i: usize| {
+            transform.project_point3(Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            ))
+        };
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    self.line(corner(i), corner(i | axis), color);
+                }
+            }
+        }
+    }
+
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec3) {
+        if aabb.is_empty() {
+            return;
+        }
+        let transform = Mat4::from_translation(aabb.center()) * Mat4::from_scale(aabb.extents());
+        self.wire_box(transform, color);
+    }
+
+    /// A circle around `normal`.
+    pub fn circle(&mut self, center: Vec3, normal: Vec3, radius: f32, color: Vec3) {
+        let normal = normal.normalize();
+        let u = normal.any_orthonormal_vector();
+        let v = normal.cross(u);
+        let point = |i: usize| {
+            let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            center + (u * angle.cos() + v * angle.sin()) * radius
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Three axis-aligned great circles.
+    pub fn sphere(&mut self, center: Vec3, radius: f32, color: Vec3) {
+        self.circle(center, Vec3::X, radius, color);
+        self.circle(center, Vec3::Y, radius, color);
+        self.circle(center, Vec3::Z, radius, color);
+    }
+
+    /// Red, green and blue lines of length `size` along the X, Y and Z axes of
+    /// `transform`.
+    pub fn axes(&mut self, transform: Mat4, size: f32) {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            self.line(origin, transform.transform_point3(axis * size), axis);
+        }
+    }
+
+    /// A square grid on the XZ plane centered at the origin, `size` wide with
+    /// `divisions` cells per side.
+    pub fn grid(&mut self, size: f32, divisions: u32, color: Vec3) {
+        let half = size * 0.5;
+        for i in 0..=divisions {
+            let t = -half + size * i as f32 / divisions as f32;
+            self.line(Vec3::new(t, 0.0, -half), Vec3::new(t, 0.0, half), color);
+            self.line(Vec3::new(-half, 0.0, t), Vec3::new(half, 0.0, t), color);
+        }
+    }
+
+    /// The edges of the volume seen through `view_projection`, such as a light or a
+    /// second camera.
+    pub fn frustum(&mut self, view_projection: Mat4, color: Vec3) {
+        self.wire_box(view_projection.inverse(), color);
+    }
+
+    /// Draws everything added since the last flush, then clears it.
+    pub fn flush(&mut self, view_projection: Mat4) {
+        if self.vertices.is_empty() && self.points.is_empty() {
+            return;
+        }
+        let line_count = self.vertices.len();
+        self.vertices.append(&mut self.points);
+        // Two vertices per segment, so segments start on a multiple of their size too.
+        let offset = self.vertex_buffer.push(
+            bytemuck::cast_slice(&self.vertices),
+            2 * std::mem::size_of::<LineVertex>(),
+        );
+        set_attributes(
+            &self.vertex_array,
+            &self.segment_array,
+            self.vertex_buffer.buffer(),
+            offset,
+        );
+
+        let blend = self.smooth;
+        unsafe {
+            if self.depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            } else {
+                gl::Disable(gl::DEPTH_TEST);
+            }
+            if blend {
+                gl::Enable(gl::BLEND);
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            }
+        }
+        if line_count > 0 {
+            if self.line_width == 1.0 && !self.smooth {
+                self.draw_thin_lines(view_projection, line_count);
+            } else {
+                self.draw_wide_lines(view_projection, line_count);
+            }
+        }
+        if self.vertices.len() > line_count {
+            self.draw_points(view_projection, line_count);
+        }
+        if blend {
+            unsafe {
+                gl::Disable(gl::BLEND);
+            }
+        }
+        self.vertices.clear();
+    }
+
+    fn draw_thin_lines(&self, view_projection: Mat4, count: usize) {
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+        self.vertex_array.bind();
+        validate::draw("debug lines");
+        unsafe {
+            gl::DrawArrays(gl::LINES, 0, count as i32);
+        }
+        self.vertex_array.unbind();
+        stats::record_draw(0);
+    }
+
+    fn draw_wide_lines(&self, view_projection: Mat4, count: usize) {
+        let mut viewport = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        let program = &self.wide_line_program;
+        program.use_program();
+        program.set_mat4("u_view_projection", &view_projection.to_cols_array());
+        program.set_vec2("u_viewport", [viewport[2] as f32, viewport[3] as f32]);
+        // One pixel of fringe on each side, faded out when smooth and cut when not.
+        program.set_float("u_half_width", self.line_width.max(1.0) * 0.5 + 1.0);
+        program.set_int("u_smooth", self.smooth as i32);
+        self.segment_array.bind();
+        validate::draw("debug wide lines");
+        unsafe {
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, count as i32 / 2);
+        }
+        self.segment_array.unbind();
+        stats::record_draw(count);
+    }
+
+    fn draw_points(&self, view_projection: Mat4, first: usize) {
+        // Always on in ES, where enabling it is an error.
+        let program_point_size = !context::options().program_point_size;
+        let program = &self.point_program;
+        program.use_program();
+        program.set_mat4("u_view_projection", &view_projection.to_cols_array());
+        program.set_float("u_point_size", self.point_size);
+        program.set_int("u_smooth", self.smooth as i32);
+        self.vertex_array.bind();
+        validate::draw("debug points");
+        unsafe {
+            if program_point_size {
+                gl::Enable(gl::PROGRAM_POINT_SIZE);
+            }
+            gl::DrawArrays(
+                gl::POINTS,
+                first as i32,
+                (self.vertices.len() - first) as i32,
+            );
+            if program_point_size {
+                gl::Disable(gl::PROGRAM_POINT_SIZE);
+            }
+        }
+        self.vertex_array.unbind();
+        stats::record_draw(0);
+    }
+}
+
+/// Points both vertex arrays at vertices starting `offset` bytes into `buffer`: one
+/// vertex per vertex, and one pair of them per segment instance.
+fn set_attributes(
+    vertex_array: &VertexArray,
+    segment_array: &VertexArray,
+    buffer: &Buffer,
+    offset: usize,
+) {
+    let stride = std::mem::size_of::<LineVertex>() as i32;
+    vertex_array.attribute(0, buffer, 3, gl::FLOAT, false, stride, offset);
+    vertex_array.attribute(1, buffer, 3, gl::FLOAT, false, stride, offset + 12);
+    let pair = 2 * stride;
+    for (location, field) in [0, 12, 24, 36].into_iter().enumerate() {
+        let location = location as u32;
+        segment_array.attribute(location, buffer, 3, gl::FLOAT, false, pair, offset + field);
+    }
+}
@@ -0,0 +1,92 @@
+//! Renderer debug views, for diagnosing content rather than shading.
+//!
+//! [`set`] switches every [`crate::material::MaterialShaders`] program to an alternate
+//! fragment path that shows one property of the surface instead of lighting it: normals,
+//! linearized depth, texture coordinates, overdraw, or a color per object or triangle.
+//! Like [`crate::state::set_wireframe`] the switch is global, so renderers need no
+//! changes; the example runners cycle through the views with F4.
+//!
+//! [`DebugView::Overdraw`] adds a little heat per fragment with additive blending and no
+//! depth test, so the material shaders set that state on every bind while it is active.
+//! It shades from black through red and yellow to white as layers pile up, saturating
+//! around [`OVERDRAW_SATURATION`] layers.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::gl;
+
+/// Layers of overdraw shown as white.
+pub const OVERDRAW_SATURATION: u32 = 24;
+
+/// What the material shaders output. The discriminants are the `u_debug_view` values the
+/// shaders switch on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum DebugView {
+    /// Regular shading.
+    #[default]
+    Lit = 0,
+    /// World-space normals after normal mapping, mapped from `-1..1` to `0..1`.
+    Normals = 1,
+    /// Linear view depth on a log scale from 0.1 (black) to 1000 units (white). Constant
+    /// under orthographic projections.
+    Depth = 2,
+    /// Texture coordinates wrapped to `0..1` in red and green.
+    Uvs = 3,
+    /// Fragments drawn per pixel, hidden ones included.
+    Overdraw = 4,
+    /// A color per draw, hashed from its model matrix, as a visibility buffer would
+    /// store it. Draws with the same transform share a color.
+    ObjectIds = 5,
+    /// A color per triangle within each draw.
+    Triangles = 6,
+}
+
+impl DebugView {
+    pub const ALL: [DebugView; 7] = [
+        DebugView::Lit,
+        DebugView::Normals,
+        DebugView::Depth,
+        DebugView::Uvs,
+        DebugView::Overdraw,
+        DebugView::ObjectIds,
+        DebugView::Triangles,
+    ];
+
+    /// The view after this one in [`DebugView::ALL`], wrapping around.
+    pub fn next(self) -> DebugView {
+        DebugView::ALL[(self as usize + 1) % DebugView::ALL.len()]
+    }
+}
+
+static VIEW: AtomicU8 = AtomicU8::new(DebugView::Lit as u8);
+
+/// The view [`set`] selected.
+pub fn get() -> DebugView {
+    DebugView::ALL[VIEW.load(Ordering::Relaxed) as usize]
+}
+
+/// Selects what the material shaders output from the next bind on. Leaving
+/// [`DebugView::Overdraw`] restores opaque drawing in the current context: blending off,
+/// depth test and depth writes on.
+pub fn set(view: DebugView) {
+    let previous = get();
+    VIEW.store(view as u8, Ordering::Relaxed);
+    if previous == DebugView::Overdraw && view != DebugView::Overdraw {
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+/// Sets the blending and depth state [`DebugView::Overdraw`] draws with.
+pub(crate) fn apply_overdraw_state() {
+    unsafe {
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::ONE, gl::ONE);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::DepthMask(gl::FALSE);
+    }
+}
@@ -0,0 +1,345 @@
+//! Deferred shading.
+//!
+//! Opaque geometry is rendered into a [`GBuffer`] with multiple render targets. A
+//! fullscreen resolve pass then shades every pixel against all point lights at once, and
+//! transparent objects are drawn afterwards with ordinary forward shading on top of the
+//! resolved image, depth-tested against the G-buffer depth. Ambient light can be
+//! occluded by an [`Ssao`] pass run on the G-buffer (see [`Deferred::set_ssao`]).
+
+use std::mem::offset_of;
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::block_layout::{self, BlockKind, Field};
+use crate::buffer::Buffer;
+use crate::depth::{self, DEPTH_GLSL};
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::math::{Mat4, Vec3};
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::ssao::Ssao;
+use crate::texture::Texture;
+
+/// Maximum number of point lights handled by one resolve.
+pub const MAX_POINT_LIGHTS: usize = 256;
+
+/// Outputs expected from geometry-pass fragment shaders. Paste after the `#version` line.
+///
+/// `g_albedo.rgb` is the base color; `g_normal.xyz` the world-space normal.
+pub const GBUFFER_OUTPUTS_GLSL: &str = r#"
+layout (location = 0) out vec4 g_albedo;
+layout (location = 1) out vec4 g_normal;
+"#;
+
+/// Follows the depth convention through [`DEPTH_GLSL`], pasted in front.
+const RESOLVE_SHADER: &str = r#"
+#define MAX_POINT_LIGHTS 256
+in vec2 uv;
+out vec4 frag_color;
+
+struct PointLight {
+    vec4 position_radius;
+    vec4 color;
+};
+
+layout (std140) uniform PointLights {
+    PointLight lights[MAX_POINT_LIGHTS];
+};
+
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_depth;
+uniform sampler2D u_occlusion;
+uniform mat4 u_inverse_view_projection;
+uniform int u_light_count;
+uniform vec3 u_ambient;
+
+void main() {
+    float depth = texture(u_depth, uv).r;
+    if (is_far_depth(depth)) {
+        discard;
+    }
+    vec4 clip = vec4(uv * 2.0 - 1.0, depth_to_ndc(depth), 1.0);
+    vec4 world = u_inverse_view_projection * clip;
+    vec3 position = world.xyz / world.w;
+    vec3 albedo = texture(u_albedo, uv).rgb;
+    vec3 normal = normalize(texture(u_normal, uv).xyz);
+
+    vec3 color = u_ambient * albedo * texture(u_occlusion, uv).r;
+    for (int i = 0; i < u_light_count; ++i) {
+        vec3 to_light = lights[i].position_radius.xyz - position;
+        float radius = lights[i].position_radius.w;
+        float d = length(to_light);
+        if (d < radius) {
+            float falloff = 1.0 - d / radius;
+            float diffuse = max(dot(normal, to_light / d), 0.0);
+            color += albedo * lights[i].color.rgb * diffuse * falloff * falloff;
+        }
+    }
+    frag_color = vec4(color, 1.0);
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: Vec3,
+    pub color: Vec3,
+    pub radius: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PointLightStd140 {
+    position_radius: [f32; 4],
+    color: [f32; 4],
+}
+
+fn attachment(width: i32, height: i32, internal_format: gl::types::GLenum) -> Result<Texture> {
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    texture.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+    texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    let (format, ty) = if internal_format == gl::DEPTH24_STENCIL8 {
+        (gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)
+    } else {
+        (gl::RGBA, gl::FLOAT)
+    };
+    texture.image_2d(0, internal_format, width, height, format, ty, None);
+    texture.unbind();
+    Ok(texture)
+}
+
+/// Geometry buffer: albedo (`RGBA8`), normal (`RGBA16F`) and depth-stencil.
+pub struct GBuffer {
+    pub framebuffer: Framebuffer,
+    pub albedo: Texture,
+    pub normal: Texture,
+    pub depth: Texture,
+    width: i32,
+    height: i32,
+}
+
+impl GBuffer {
+    pub fn new(width: i32, height: i32) -> Result<GBuffer> {
+        let albedo = attachment(width, height, gl::RGBA8)?;
+        let normal = attachment(width, height, gl::RGBA16F)?;
+        // Matches the default framebuffer for the depth blit after the resolve, even with
+        // reversed-Z.
+        let depth = attachment(width, height, gl::DEPTH24_STENCIL8)?;
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &albedo, 0);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT1, &normal, 0);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT, &depth, 0);
+        let status = framebuffer
+            .draw_buffers(&[gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1])
+            .and_then(|()| framebuffer.check_status(gl::FRAMEBUFFER));
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        Ok(GBuffer {
+            framebuffer,
+            albedo,
+            normal,
+            depth,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
+
+pub struct Deferred {
+    gbuffer: GBuffer,
+    resolve: Program,
+    triangle: FullscreenTriangle,
+    lights: Buffer,
+    ssao: Option<Ssao>,
+    /// Bound as the occlusion without SSAO.
+    white: Texture,
+    pub ambient: Vec3,
+}
+
+impl Deferred {
+    pub fn new(width: i32, height: i32) -> Result<Deferred> {
+        let resolve = Program::from_sources(
+            FULLSCREEN_VERTEX_SHADER,
+            &format!("#version 330 core\n{}\n{}", DEPTH_GLSL, RESOLVE_SHADER),
+        )?;
+        let light = [
+            Field::new(
+                "position_radius",
+                offset_of!(PointLightStd140, position_radius),
+            ),
+            Field::new("color", offset_of!(PointLightStd140, color)),
+        ];
+        block_layout::check(
+            &resolve,
+            BlockKind::Uniform,
+            "PointLights",
+            Some(MAX_POINT_LIGHTS * std::mem::size_of::<PointLightStd140>()),
+            &Field::struct_array("lights", 0, std::mem::size_of::<PointLightStd140>(), &light),
+        )?;
+        resolve.bind_uniform_block("PointLights", 0);
+
+        let lights = Buffer::new()?;
+        lights.bind(gl::UNIFORM_BUFFER);
+        lights.allocate(
+            gl::UNIFORM_BUFFER,
+            MAX_POINT_LIGHTS * std::mem::size_of::<PointLightStd140>(),
+            gl::DYNAMIC_DRAW,
+        );
+        lights.unbind(gl::UNIFORM_BUFFER);
+
+        Ok(Deferred {
+            gbuffer: GBuffer::new(width, height)?,
+            resolve,
+            triangle: FullscreenTriangle::new()?,
+            lights,
+            ssao: None,
+            white: Texture::solid([255; 4])?,
+            ambient: Vec3::splat(0.05),
+        })
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<()> {
+        self.gbuffer = GBuffer::new(width, height)?;
+        if let Some(ssao) = &mut self.ssao {
+            ssao.resize(width, height)?;
+        }
+        Ok(())
+    }
+
+    pub fn gbuffer(&self) -> &GBuffer {
+        &self.gbuffer
+    }
+
+    /// Occludes the ambient term of the resolve with `ssao`, computed from the G-buffer
+    /// depth and normals. Keep its camera current with [`Ssao::set_camera`] through
+    /// [`Deferred::ssao_mut`].
+    pub fn set_ssao(&mut self, ssao: Option<Ssao>) {
+        self.ssao = ssao;
+    }
+
+    pub fn ssao_mut(&mut self) -> Option<&mut Ssao> {
+        self.ssao.as_mut()
+    }
+
+    /// Binds and clears the G-buffer. Draw opaque geometry with shaders writing
+    /// [`GBUFFER_OUTPUTS_GLSL`] afterwards.
+    pub fn begin_geometry(&self) {
+        self.gbuffer.framebuffer.bind(gl::FRAMEBUFFER);
+        unsafe {
+            gl::Viewport(0, 0, self.gbuffer.width, self.gbuffer.height);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+        }
+    }
+
+    /// Shades the G-buffer into the default framebuffer and copies the G-buffer depth
+    /// over, leaving state ready for [`Deferred::begin_forward`].
+    pub fn resolve(&self, view_projection: Mat4, lights: &[PointLight]) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "deferred resolve").entered();
+        if lights.len() > MAX_POINT_LIGHTS {
+            return Err(anyhow!(
+                "{} point lights exceed the limit of {}",
+                lights.len(),
+                MAX_POINT_LIGHTS
+            ));
+        }
+        let packed: Vec<PointLightStd140> = lights
+            .iter()
+            .map(|light| PointLightStd140 {
+                position_radius: light.position.extend(light.radius).to_array(),
+                color: light.color.extend(1.0).to_array(),
+            })
+            .collect();
+        self.lights.bind(gl::UNIFORM_BUFFER);
+        self.lights
+            .sub_data(gl::UNIFORM_BUFFER, 0, bytemuck::cast_slice(&packed));
+        self.lights.unbind(gl::UNIFORM_BUFFER);
+        self.lights.bind_base(gl::UNIFORM_BUFFER, 0);
+
+        let occlusion = match &self.ssao {
+            Some(ssao) => ssao.compute(&self.gbuffer.depth, Some(&self.gbuffer.normal)),
+            None => &self.white,
+        };
+
+        let (width, height) = (self.gbuffer.width, self.gbuffer.height);
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        self.resolve.use_program();
+        self.gbuffer.albedo.bind_unit(0);
+        self.gbuffer.normal.bind_unit(1);
+        self.gbuffer.depth.bind_unit(2);
+        occlusion.bind_unit(3);
+        self.resolve.set_int("u_albedo", 0);
+        self.resolve.set_int("u_normal", 1);
+        self.resolve.set_int("u_depth", 2);
+        self.resolve.set_int("u_occlusion", 3);
+        depth::set_uniforms(&self.resolve);
+        self.resolve.set_mat4(
+            "u_inverse_view_projection",
+            &view_projection.inverse().to_cols_array(),
+        );
+        self.resolve.set_int("u_light_count", lights.len() as i32);
+        self.resolve.set_vec3("u_ambient", self.ambient.to_array());
+        self.triangle.draw();
+
+        self.gbuffer.framebuffer.bind(gl::READ_FRAMEBUFFER);
+        Framebuffer::bind_default(gl::DRAW_FRAMEBUFFER);
+        unsafe {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        Ok(())
+    }
+
+    /// Sets up depth-tested, alpha-blended drawing for transparent objects.
+    pub fn begin_forward(&self) {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    pub fn end_forward(&self) {
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! The scenes of the `hello-gl` binary. Each one exercises a different part of the
+//! library; `--scene` picks the first one and the number keys switch at runtime.
+
+mod cube;
+mod model;
+mod particles;
+mod quad;
+mod triangle;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use hello_gl::event::WindowEvent;
+
+pub use cube::Cube;
+pub use model::Model;
+pub use particles::Particles;
+pub use quad::Quad;
+pub use triangle::Triangle;
+
+/// One demo. Like [`hello_gl::app::App`], minus window management: the host sets the
+/// viewport and resets GL state between scenes, so scenes only clear and draw.
+pub trait Scene {
+    fn resize(&mut self, _width: u32, _height: u32) {}
+    fn window_event(&mut self, _event: &WindowEvent) {}
+    fn update(&mut self, _dt: f32) {}
+    fn render(&mut self);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Kind {
+    Triangle,
+    Quad,
+    Cube,
+    Model,
+    Particles,
+}
+
+impl Kind {
+    /// In number-key order.
+    pub const ALL: [Kind; 5] = [
+        Kind::Triangle,
+        Kind::Quad,
+        Kind::Cube,
+        Kind::Model,
+        Kind::Particles,
+    ];
+
+    pub fn create(self) -> Result<Box<dyn Scene>> {
+        Ok(match self {
+            Kind::Triangle => Box::new(Triangle::new()?),
+            Kind::Quad => Box::new(Quad::new()?),
+            Kind::Cube => Box::new(Cube::new()?),
+            Kind::Model => Box::new(Model::new()?),
+            Kind::Particles => Box::new(Particles::new()?),
+        })
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use hello_gl::gl;
+use hello_gl::math::{Mat4, Vec3};
+use hello_gl::mesh::Mesh;
+use hello_gl::shader::Program;
+use hello_gl::viewport::Camera;
+
+use super::Scene;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+uniform mat4 u_view_projection;
+uniform mat4 u_model;
+out vec3 v_normal;
+void main() {
+    v_normal = mat3(u_model) * a_normal;
+    gl_Position = u_view_projection * u_model * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec3 v_normal;
+out vec4 frag_color;
+void main() {
+    frag_color = vec4(normalize(v_normal) * 0.5 + 0.5, 1.0);
+}
+"#;
+
+/// A spinning cube from [`Mesh::cube`], shaded by its normals, with depth testing.
+pub struct Cube {
+    mesh: Mesh,
+    program: Program,
+    camera: Camera,
+    aspect: f32,
+    time: f32,
+}
+
+impl Cube {
+    pub fn new() -> Result<Cube> {
+        Ok(Cube {
+            mesh: Mesh::cube(1.0)?,
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            camera: Camera::look_at(Vec3::new(0.0, 1.5, 3.0), Vec3::ZERO),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl Scene for Cube {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    fn render(&mut self) {
+        let model = Mat4::from_rotation_y(self.time) * Mat4::from_rotation_x(self.time * 0.7);
+        let view_projection = self.camera.projection(self.aspect) * self.camera.view();
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+        self.program.set_mat4("u_model", &model.to_cols_array());
+        self.mesh.draw();
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+    }
+}
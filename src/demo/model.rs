@@ -0,0 +1,282 @@
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use hello_gl::culling::Aabb;
+use hello_gl::debug_draw::DebugDraw;
+use hello_gl::event::{ElementState, KeyCode, KeyboardInput, MouseButton, WindowEvent};
+use hello_gl::gizmo::{Gizmo, GizmoMode};
+use hello_gl::gl;
+use hello_gl::grid::InfiniteGrid;
+use hello_gl::input::Input;
+use hello_gl::material::{Light, LightBuffer, Material, MaterialShaders};
+use hello_gl::math::{Mat4, Transform, Vec3, Vec4};
+use hello_gl::mesh::Mesh;
+use hello_gl::ray::{Ray, Volume};
+use hello_gl::scene::{self, Drawable, NodeId};
+use hello_gl::viewport::{OrbitCamera, Viewport};
+
+use super::Scene;
+
+/// A Blinn-Phong and a PBR cube on a ground plane, lit by a sun and an orbiting point
+/// light through [`MaterialShaders`] and [`LightBuffer`]. [`Model::open`] shows a model
+/// file instead. Drag to orbit and scroll to zoom.
+///
+/// An opened model sits on an infinite ground grid, toggled with G. Clicking a part
+/// selects it for a gizmo: W, E and R switch between moving, rotating and scaling it,
+/// and Escape clears the selection.
+pub struct Model {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    materials: [Material; 3],
+    ground: Mesh,
+    cube: Mesh,
+    /// The opened model, drawn instead of the cubes.
+    loaded: Option<scene::Scene>,
+    input: Input,
+    orbit: OrbitCamera,
+    grid: InfiniteGrid,
+    show_grid: bool,
+    debug: DebugDraw,
+    gizmo: Gizmo,
+    selected: Option<NodeId>,
+    /// Whether the left button was down last update.
+    pressed: bool,
+    /// Pixels the cursor moved since the left button went down; short clicks select.
+    press_motion: f32,
+    /// Whether the gizmo took the current press, which then neither orbits nor selects.
+    press_on_gizmo: bool,
+    size: (u32, u32),
+    aspect: f32,
+    time: f32,
+}
+
+impl Model {
+    pub fn new() -> Result<Model> {
+        Ok(Model {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            materials: [
+                Material::default(),
+                Material::blinn_phong(Vec4::new(0.9, 0.4, 0.2, 1.0), 64.0),
+                Material::pbr(Vec4::new(0.9, 0.7, 0.3, 1.0), 1.0, 0.3),
+            ],
+            ground: Mesh::plane(10.0)?,
+            cube: Mesh::cube(1.0)?,
+            loaded: None,
+            input: Input::new(),
+            orbit: OrbitCamera::new(Vec3::ZERO, 6.5),
+            grid: InfiniteGrid::new()?,
+            show_grid: true,
+            debug: DebugDraw::new()?,
+            gizmo: Gizmo::new(),
+            selected: None,
+            pressed: false,
+            press_motion: 0.0,
+            press_on_gizmo: false,
+            size: (1, 1),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+
+    /// Loads a Wavefront OBJ, or with the `gltf` feature a glTF file, and frames it.
+    pub fn open(path: &Path) -> Result<Model> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let mut loaded = match extension.as_deref() {
+            Some("obj") => {
+                let (mesh, materials) = Mesh::from_obj(path)?;
+                let mut scene = scene::Scene::new();
+                scene.add_drawable(
+                    None,
+                    None,
+                    Transform::IDENTITY,
+                    Drawable {
+                        mesh: Rc::new(mesh),
+                        materials: materials.into_iter().map(Rc::new).collect(),
+                    },
+                );
+                scene
+            }
+            #[cfg(feature = "gltf")]
+            Some("gltf" | "glb") => hello_gl::gltf::load(path)?.into_scene().0,
+            _ => return Err(anyhow!("Can't open {} as a model", path.display())),
+        };
+        loaded.update();
+
+        let bounds = loaded
+            .iter()
+            .filter_map(|(id, _)| loaded.world_bounds(id))
+            .fold(Aabb::EMPTY, |all, bounds| {
+                Aabb::from_points([all.min, all.max, bounds.min, bounds.max])
+            });
+        if bounds.is_empty() {
+            return Err(anyhow!("{} contains no meshes", path.display()));
+        }
+
+        let mut model = Model::new()?;
+        model.orbit.frame(&bounds);
+        model.loaded = Some(loaded);
+        Ok(model)
+    }
+
+    /// The ray under the cursor, if it is over the window.
+    fn cursor_ray(&self) -> Option<Ray> {
+        let cursor = self.input.cursor()?;
+        let (width, height) = self.size;
+        let camera = self.orbit.camera();
+        Some(Ray::from_cursor(
+            cursor.x as f64,
+            cursor.y as f64,
+            &Viewport::full(width, height),
+            height,
+            camera.projection(self.aspect) * camera.view(),
+        ))
+    }
+
+    /// Drags the selected part with the gizmo. Returns whether the gizmo has the mouse.
+    fn update_gizmo(&mut self, pressed: bool) -> bool {
+        let Some(ray) = self.cursor_ray() else {
+            return false;
+        };
+        let eye = self.orbit.camera().eye;
+        let (Some(loaded), Some(node)) = (&mut self.loaded, self.selected) else {
+            return false;
+        };
+        let mut transform = Transform::from_matrix(loaded.node(node).world());
+        let grabbed = self.gizmo.update(&ray, eye, pressed, &mut transform);
+        if self.gizmo.is_dragging() {
+            // The gizmo works in world space; the node keeps a transform in its parent's.
+            let parent = loaded
+                .node(node)
+                .parent()
+                .map_or(Mat4::IDENTITY, |parent| loaded.node(parent).world());
+            let local = Transform::from_matrix(parent.inverse() * transform.matrix());
+            loaded.set_local(node, local);
+            loaded.update();
+        }
+        grabbed
+    }
+
+    /// Selects the nearest part under the cursor, or nothing.
+    fn select(&mut self) {
+        let (Some(loaded), Some(ray)) = (&self.loaded, self.cursor_ray()) else {
+            return;
+        };
+        self.selected = loaded
+            .raycast(&ray, Volume::Aabb)
+            .first()
+            .map(|hit| hit.node);
+    }
+}
+
+impl Scene for Model {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+        self.size = (width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    key: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                KeyCode::KeyG => self.show_grid = !self.show_grid,
+                KeyCode::KeyW => self.gizmo.mode = GizmoMode::Translate,
+                KeyCode::KeyE => self.gizmo.mode = GizmoMode::Rotate,
+                KeyCode::KeyR => self.gizmo.mode = GizmoMode::Scale,
+                KeyCode::Escape => self.selected = None,
+                _ => (),
+            }
+        }
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        let pressed = self.input.is_button_pressed(MouseButton::Left);
+        if pressed && !self.pressed {
+            self.press_motion = 0.0;
+            self.press_on_gizmo = false;
+        }
+        self.press_motion += self.input.mouse_motion().length();
+        self.press_on_gizmo |= self.update_gizmo(pressed);
+        if !pressed && self.pressed && !self.press_on_gizmo && self.press_motion < 4.0 {
+            self.select();
+        }
+        self.pressed = pressed;
+        if !self.press_on_gizmo {
+            self.orbit.update(&self.input);
+        }
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        let lights = [
+            Light::Directional {
+                direction: Vec3::new(-0.3, -1.0, -0.5),
+                color: Vec3::ONE,
+                intensity: 0.6,
+            },
+            Light::Point {
+                position: Vec3::new(self.time.cos() * 3.0, 2.0, self.time.sin() * 3.0),
+                color: Vec3::new(1.0, 0.6, 0.3),
+                intensity: 15.0,
+                range: 8.0,
+            },
+        ];
+        self.lights.upload(&lights, Vec3::splat(0.03)).unwrap();
+        let camera = self.orbit.camera();
+        self.shaders
+            .set_camera(camera.projection(self.aspect) * camera.view(), camera.eye);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        if let Some(loaded) = &self.loaded {
+            loaded.draw(&self.shaders);
+            let view_projection = camera.projection(self.aspect) * camera.view();
+            if self.show_grid {
+                self.grid.fit(self.orbit.distance);
+                self.grid.draw(view_projection, camera.eye);
+            }
+            if let Some(node) = self.selected {
+                if let Some(bounds) = loaded.world_bounds(node) {
+                    self.debug.aabb(&bounds, Vec3::new(1.0, 0.85, 0.2));
+                }
+                let transform = Transform::from_matrix(loaded.node(node).world());
+                self.gizmo.draw(&mut self.debug, &transform, camera.eye);
+                self.debug.depth_test = false;
+                self.debug.flush(view_projection);
+            }
+        } else {
+            let program = self.shaders.bind(&self.materials[0]);
+            program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+            self.ground.draw();
+
+            for (x, material) in [-1.2, 1.2].into_iter().zip(&self.materials[1..]) {
+                let model = Mat4::from_translation(Vec3::new(x, 0.5, 0.0))
+                    * Mat4::from_rotation_y(self.time * 0.5);
+                let program = self.shaders.bind(material);
+                program.set_mat4("u_model", &model.to_cols_array());
+                self.cube.draw();
+            }
+        }
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use hello_gl::animation::{Interpolation, Track};
+use hello_gl::gl;
+use hello_gl::math::{Vec3, Vec4};
+use hello_gl::particles::{EmitterConfig, ParticleSystem};
+use hello_gl::viewport::Camera;
+
+use super::Scene;
+
+/// An orbiting fountain simulated by [`ParticleSystem`] on the best available backend.
+pub struct Particles {
+    fountain: ParticleSystem,
+    camera: Camera,
+    aspect: f32,
+    time: f32,
+}
+
+impl Particles {
+    pub fn new() -> Result<Particles> {
+        let config = EmitterConfig {
+            rate: 5000.0,
+            lifetime: 3.0,
+            velocity: Vec3::new(0.0, 4.0, 0.0),
+            velocity_randomness: 1.0,
+            gravity: Vec3::new(0.0, -3.0, 0.0),
+            size_over_life: Track::new(vec![0.0, 1.0], vec![0.03, 0.08], Interpolation::Linear)?,
+            color_over_life: Track::new(
+                vec![0.0, 0.3, 1.0],
+                vec![
+                    Vec4::new(1.0, 1.0, 0.6, 1.0),
+                    Vec4::new(1.0, 0.5, 0.1, 0.8),
+                    Vec4::new(0.5, 0.1, 0.1, 0.0),
+                ],
+                Interpolation::Linear,
+            )?,
+            additive: true,
+            ..EmitterConfig::default()
+        };
+        Ok(Particles {
+            fountain: ParticleSystem::new(config, 16384)?,
+            camera: Camera::look_at(Vec3::new(0.0, 2.0, 6.0), Vec3::new(0.0, 1.0, 0.0)),
+            aspect: 1.0,
+            time: 0.0,
+        })
+    }
+}
+
+impl Scene for Particles {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.fountain.config.origin = Vec3::new(self.time.cos(), 0.0, self.time.sin()) * 0.5;
+        self.fountain.update(dt);
+    }
+
+    fn render(&mut self) {
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.fountain
+            .draw(self.camera.view(), self.camera.projection(self.aspect));
+    }
+}
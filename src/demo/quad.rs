@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use anyhow::Result;
+use hello_gl::buffer::{Buffer, VertexArray};
+use hello_gl::gl;
+use hello_gl::image::Image;
+use hello_gl::shader::Program;
+use hello_gl::texture::Texture;
+use hello_gl::validate;
+
+use super::Scene;
+
+/// Position and texture coordinate, as a strip. Images have their first row at the top.
+const VERTICES: [[f32; 4]; 4] = [
+    [-0.6, -0.6, 0.0, 1.0],
+    [0.6, -0.6, 1.0, 1.0],
+    [-0.6, 0.6, 0.0, 0.0],
+    [0.6, 0.6, 1.0, 0.0],
+];
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_uv;
+uniform float u_aspect;
+uniform float u_image_aspect;
+uniform float u_repeat;
+out vec2 v_uv;
+void main() {
+    v_uv = a_uv * u_repeat;
+    gl_Position = vec4(a_position.x * u_image_aspect / u_aspect, a_position.y, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 v_uv;
+out vec4 frag_color;
+uniform sampler2D u_texture;
+void main() {
+    frag_color = texture(u_texture, v_uv);
+}
+"#;
+
+/// A checkerboard built on the CPU, uploaded with mipmaps and drawn repeated on a quad,
+/// or an image from [`Quad::open`] drawn once at its aspect ratio.
+pub struct Quad {
+    vertex_array: VertexArray,
+    _vertex_buffer: Buffer,
+    program: Program,
+    texture: Texture,
+    image_aspect: f32,
+    repeat: f32,
+    aspect: f32,
+}
+
+fn checkerboard(size: u32, cells: u32) -> Image {
+    let cell = size / cells;
+    let pixels = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size / cell, i / size / cell);
+            if (x + y) % 2 == 0 {
+                [240, 240, 240, 255]
+            } else {
+                [200, 60, 60, 255]
+            }
+        })
+        .collect();
+    Image {
+        width: size,
+        height: size,
+        pixels,
+    }
+}
+
+impl Quad {
+    pub fn new() -> Result<Quad> {
+        let mut quad = Quad::with_image(&checkerboard(256, 8))?;
+        quad.texture.label("checkerboard");
+        quad.repeat = 4.0;
+        Ok(quad)
+    }
+
+    /// Shows the PNG at `path`.
+    pub fn open(path: &Path) -> Result<Quad> {
+        let quad = Quad::with_image(&Image::load(path)?)?;
+        quad.texture.label(&path.display().to_string());
+        Ok(quad)
+    }
+
+    fn with_image(image: &Image) -> Result<Quad> {
+        let vertex_buffer = Buffer::new()?;
+        vertex_buffer.bind(gl::ARRAY_BUFFER);
+        vertex_buffer.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&VERTICES),
+            gl::STATIC_DRAW,
+        );
+        vertex_buffer.unbind(gl::ARRAY_BUFFER);
+
+        let vertex_array = VertexArray::new()?;
+        vertex_array.attribute(0, &vertex_buffer, 2, gl::FLOAT, false, 16, 0);
+        vertex_array.attribute(1, &vertex_buffer, 2, gl::FLOAT, false, 16, 8);
+
+        Ok(Quad {
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            texture: Texture::from_image(image)?,
+            image_aspect: image.width as f32 / image.height.max(1) as f32,
+            repeat: 1.0,
+            aspect: 1.0,
+        })
+    }
+}
+
+impl Scene for Quad {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn render(&mut self) {
+        self.program.use_program();
+        self.program.set_float("u_aspect", self.aspect);
+        self.program.set_float("u_image_aspect", self.image_aspect);
+        self.program.set_float("u_repeat", self.repeat);
+        self.program.set_int("u_texture", 0);
+        self.texture.bind_unit(0);
+        self.vertex_array.bind();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        validate::draw("the quad");
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+        }
+        self.vertex_array.unbind();
+    }
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use hello_gl::buffer::{Buffer, VertexArray};
+use hello_gl::event::{KeyCode, MouseButton, WindowEvent};
+#[cfg(feature = "gamepad")]
+use hello_gl::gamepad::Side;
+use hello_gl::gl;
+use hello_gl::input::Input;
+use hello_gl::math::{Quat, Transform, Vec3};
+use hello_gl::shader::{Program, Shader};
+use hello_gl::validate;
+
+use super::Scene;
+
+type Vertex = [f32; 3];
+const VERTICES: [Vertex; 3] = [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
+const VERT_SHADER: &str = r#"#version 330 core
+    layout (location = 0) in vec3 pos;
+    uniform mat4 u_model;
+    uniform vec2 u_resolution;
+    void main() {
+      vec4 p = u_model * vec4(pos, 1.0);
+      p.x *= u_resolution.y / max(u_resolution.x, 1.0);
+      gl_Position = p;
+    }
+    "#;
+const FRAG_SHADER: &str = r#"#version 330 core
+    uniform float u_time;
+    uniform vec2 u_mouse;
+    out vec4 final_color;
+
+    void main() {
+        vec3 color = 0.5 + 0.5 * cos(u_time + vec3(0.0, 2.0, 4.0));
+        float glow = 1.0 - smoothstep(0.0, 150.0, distance(gl_FragCoord.xy, u_mouse));
+        final_color = vec4(mix(color, vec3(1.0), glow * 0.5), 1.0);
+    }
+    "#;
+
+/// The raw building blocks: a vertex buffer, a vertex array and a program linked by hand.
+/// Cycles its color with the [`hello_gl::builtins`] and glows under the cursor. WASD or
+/// the arrow keys move it, Q/E roll it, dragging with the left button turns it, the
+/// scroll wheel scales it and R resets it. With the `gamepad` feature the sticks move
+/// and turn it too.
+pub struct Triangle {
+    _vb: Buffer,
+    va: VertexArray,
+    program: Program,
+    input: Input,
+    transform: Transform,
+}
+
+/// Units per second.
+const MOVE_SPEED: f32 = 1.0;
+/// Radians per second.
+const ROLL_SPEED: f32 = 2.0;
+/// Radians per pixel dragged.
+const DRAG_SPEED: f32 = 0.01;
+
+impl Triangle {
+    pub fn new() -> Result<Triangle> {
+        #[allow(unused_mut)]
+        let mut input = Input::new();
+        #[cfg(feature = "gamepad")]
+        if let Err(e) = input.enable_gamepads() {
+            tracing::warn!("{:#}", e);
+        }
+
+        let va = VertexArray::new()?;
+        va.bind();
+
+        let vb = Buffer::new()?;
+        vb.bind(gl::ARRAY_BUFFER);
+        vb.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&VERTICES),
+            gl::STATIC_DRAW,
+        );
+
+        unsafe {
+            gl::VertexAttribPointer(
+                0,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                std::mem::size_of::<Vertex>() as i32,
+                std::ptr::null(),
+            );
+            gl::EnableVertexAttribArray(0);
+        }
+        va.unbind();
+
+        let vertex_shader = Shader::from_source(gl::VERTEX_SHADER, VERT_SHADER)?;
+        let fragment_shader = Shader::from_source(gl::FRAGMENT_SHADER, FRAG_SHADER)?;
+
+        let program = Program::new()?;
+        program.attach(&vertex_shader);
+        program.attach(&fragment_shader);
+        program.link()?;
+
+        Ok(Triangle {
+            _vb: vb,
+            va,
+            program,
+            input,
+            transform: Transform::IDENTITY,
+        })
+    }
+}
+
+impl Scene for Triangle {
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        use KeyCode::*;
+        let input = &self.input;
+        if input.is_pressed(KeyR) {
+            self.transform = Transform::IDENTITY;
+        }
+        #[allow(unused_mut)]
+        let mut movement = Vec3::new(
+            input.axis(&[KeyA, ArrowLeft], &[KeyD, ArrowRight]),
+            input.axis(&[KeyS, ArrowDown], &[KeyW, ArrowUp]),
+            0.0,
+        );
+        #[cfg(feature = "gamepad")]
+        {
+            movement += input.stick(Side::Left).extend(0.0);
+        }
+        self.transform.translation += movement * MOVE_SPEED * dt;
+
+        let mut rotation = Quat::from_rotation_z(input.axis(&[KeyE], &[KeyQ]) * ROLL_SPEED * dt);
+        if input.is_button_pressed(MouseButton::Left) {
+            let drag = input.mouse_motion() * DRAG_SPEED;
+            rotation = Quat::from_rotation_y(drag.x) * Quat::from_rotation_x(drag.y) * rotation;
+        }
+        #[cfg(feature = "gamepad")]
+        {
+            let turn = input.stick(Side::Right) * ROLL_SPEED * dt;
+            rotation = Quat::from_rotation_y(turn.x) * Quat::from_rotation_x(-turn.y) * rotation;
+        }
+        self.transform.rotation = (rotation * self.transform.rotation).normalize();
+        self.transform.scale =
+            (self.transform.scale * 1.1f32.powf(input.scroll())).clamp_length(0.1, 5.0);
+        self.input.end_frame();
+    }
+
+    fn render(&mut self) {
+        self.program.use_program();
+        self.program
+            .set_mat4("u_model", &self.transform.matrix().to_cols_array());
+        self.va.bind();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        validate::draw("the triangle");
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.va.unbind();
+    }
+}
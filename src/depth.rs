@@ -0,0 +1,157 @@
+//! Standard and reversed-Z depth conventions.
+//!
+//! GL maps clip-space depth from `-1..1` to the depth buffer, which loses the precision
+//! of float depth buffers around the far plane. With
+//! [`crate::context::ContextOptions::reversed_z`] the context instead maps `0..1`
+//! directly, clears depth to 0 and keeps fragments of greater depth, so the near plane
+//! lands at 1. Paired with a `GL_DEPTH_COMPONENT32F` buffer and
+//! [`crate::viewport::Camera::projection_reversed_z`], the float's exponent then spreads
+//! precision evenly over distance, which all but removes z-fighting in large scenes.
+//!
+//! The helpers here let a pass follow whichever convention is active. Shaders that read
+//! depth buffers include [`DEPTH_GLSL`] and get its uniform from [`set_uniforms`].
+//! Passes with projections of their own, like shadow maps, render between
+//! [`begin_standard`] and [`end_standard`].
+//!
+//! The default framebuffer usually keeps a 24-bit fixed-point depth buffer, where
+//! reversed-Z gains little; render into a [`crate::framebuffer::RenderTarget`] for the
+//! full benefit. Depth blits need matching formats, so buffers that are blitted to the
+//! default framebuffer, like the G-buffer of [`crate::deferred`], stay 24-bit.
+
+use crate::context;
+use crate::gl;
+use crate::shader::Program;
+
+/// Converts depth buffer values back to clip space under either convention. Set
+/// `u_reversed_z` with [`set_uniforms`].
+pub const DEPTH_GLSL: &str = r#"
+uniform bool u_reversed_z;
+
+// Normalized device depth of a depth buffer value.
+float depth_to_ndc(float depth) {
+    return u_reversed_z ? depth : depth * 2.0 - 1.0;
+}
+
+// Normalized device depth of the far and near planes.
+float far_ndc() {
+    return u_reversed_z ? 0.0 : 1.0;
+}
+
+float near_ndc() {
+    return u_reversed_z ? 1.0 : -1.0;
+}
+
+// Whether a depth buffer value is still the cleared one, with nothing drawn.
+bool is_far_depth(float depth) {
+    return depth == (u_reversed_z ? 0.0 : 1.0);
+}
+"#;
+
+/// Whether the current context uses reversed-Z.
+pub fn reversed_z() -> bool {
+    context::options().reversed_z
+}
+
+/// The depth texture format for render targets: `GL_DEPTH_COMPONENT32F` with
+/// reversed-Z, `GL_DEPTH_COMPONENT24` otherwise.
+pub fn format() -> gl::types::GLenum {
+    if reversed_z() {
+        gl::DEPTH_COMPONENT32F
+    } else {
+        gl::DEPTH_COMPONENT24
+    }
+}
+
+/// Like [`format`] with a stencil buffer.
+pub fn stencil_format() -> gl::types::GLenum {
+    if reversed_z() {
+        gl::DEPTH32F_STENCIL8
+    } else {
+        gl::DEPTH24_STENCIL8
+    }
+}
+
+/// The depth function that keeps nearer fragments, `GL_LESS` or `GL_GREATER`.
+pub fn nearer() -> gl::types::GLenum {
+    if reversed_z() {
+        gl::GREATER
+    } else {
+        gl::LESS
+    }
+}
+
+/// Like [`nearer`], also keeping fragments at equal depth, e.g. for a skybox drawn at
+/// the far plane.
+pub fn nearer_or_equal() -> gl::types::GLenum {
+    if reversed_z() {
+        gl::GEQUAL
+    } else {
+        gl::LEQUAL
+    }
+}
+
+/// The depth buffer value of the far plane, which depth is cleared to.
+pub fn far() -> f32 {
+    if reversed_z() {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// The depth buffer value of the near plane.
+pub fn near() -> f32 {
+    if reversed_z() {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Normalized device depth of a depth buffer value, like `depth_to_ndc` in
+/// [`DEPTH_GLSL`].
+pub fn to_ndc(depth: f32) -> f32 {
+    if reversed_z() {
+        depth
+    } else {
+        depth * 2.0 - 1.0
+    }
+}
+
+/// The depth buffer value of a normalized device depth.
+pub fn from_ndc(ndc: f32) -> f32 {
+    if reversed_z() {
+        ndc
+    } else {
+        ndc * 0.5 + 0.5
+    }
+}
+
+/// Sets the uniform of [`DEPTH_GLSL`] on `program`, which must be in use.
+pub fn set_uniforms(program: &Program) {
+    program.set_int("u_reversed_z", reversed_z() as i32);
+}
+
+/// Switches to GL's standard `-1..1` depth with `GL_LESS` and a clear depth of 1, for
+/// passes that build GL-style projections, until [`end_standard`]. Does nothing
+/// without reversed-Z.
+pub fn begin_standard() {
+    if reversed_z() {
+        set_convention(gl::NEGATIVE_ONE_TO_ONE, gl::LESS, 1.0);
+    }
+}
+
+/// Returns to reversed-Z after [`begin_standard`].
+pub fn end_standard() {
+    if reversed_z() {
+        set_convention(gl::ZERO_TO_ONE, gl::GREATER, 0.0);
+    }
+}
+
+fn set_convention(clip_depth: gl::types::GLenum, func: gl::types::GLenum, clear: f32) {
+    unsafe {
+        gl::ClipControl(gl::LOWER_LEFT, clip_depth);
+        gl::DepthFunc(func);
+        gl::ClearDepthf(clear);
+    }
+}
@@ -0,0 +1,347 @@
+//! Textures shared as Linux DMA-BUFs through EGL images.
+//!
+//! A DMA-BUF is a buffer in GPU or device memory passed between processes and drivers
+//! as file descriptors: frames from a hardware video decoder, a camera, or a Wayland
+//! client's surface. [`import`] wraps one in a [`Texture`] without copying, and
+//! [`export`] hands out a texture's storage the same way, e.g. to a compositor or an
+//! encoder.
+//!
+//! Both go through an `EGLImage` and so need the render context to be an EGL one, as it
+//! is on Wayland, on X11 when glutin picks EGL, and in the headless runner; under GLX
+//! they fail. Importing needs `EGL_EXT_image_dma_buf_import` (and
+//! `EGL_EXT_image_dma_buf_import_modifiers` for explicit modifiers) and
+//! `GL_OES_EGL_image`; exporting needs `EGL_KHR_gl_texture_2D_image` and
+//! `EGL_MESA_image_dma_buf_export`.
+//!
+//! Neither side synchronizes: the producer must have finished writing a buffer, e.g.
+//! waited on a [`crate::sync::Fence`] or the decoder's own fence, before the other side
+//! reads it.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use glutin::context::RawContext;
+use glutin::display::{AsRawDisplay, Display, RawDisplay};
+use glutin::prelude::*;
+
+use crate::context;
+use crate::gl;
+use crate::texture::{self, Texture};
+use crate::upload;
+
+/// The texture target of `OES_EGL_image_external`, sampled with `samplerExternalOES`
+/// and converted from YUV by the driver.
+pub const TEXTURE_EXTERNAL_OES: gl::types::GLenum = 0x8D65;
+
+/// A DRM format code from its four characters.
+pub const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*code)
+}
+
+/// 8-bit BGRA in memory order, the usual format of scanout and Wayland buffers.
+pub const ARGB8888: u32 = fourcc(b"AR24");
+/// [`ARGB8888`] with the alpha byte ignored.
+pub const XRGB8888: u32 = fourcc(b"XR24");
+/// 8-bit RGBA in memory order, matching `GL_RGBA8`.
+pub const ABGR8888: u32 = fourcc(b"AB24");
+pub const XBGR8888: u32 = fourcc(b"XB24");
+/// 4:2:0 YUV with a Y plane and an interleaved UV plane, the common decoder output.
+/// Import it to [`TEXTURE_EXTERNAL_OES`].
+pub const NV12: u32 = fourcc(b"NV12");
+
+/// `DRM_FORMAT_MOD_INVALID`: the layout is implied by the driver.
+const MODIFIER_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
+const EGL_NONE: i32 = 0x3038;
+const EGL_HEIGHT: i32 = 0x3056;
+const EGL_WIDTH: i32 = 0x3057;
+const EGL_EXTENSIONS: i32 = 0x3055;
+const EGL_GL_TEXTURE_2D_KHR: u32 = 0x30B1;
+const EGL_GL_TEXTURE_LEVEL_KHR: i32 = 0x30BC;
+const EGL_LINUX_DMA_BUF_EXT: u32 = 0x3270;
+const EGL_LINUX_DRM_FOURCC_EXT: i32 = 0x3271;
+
+/// The fd, offset, pitch, and low and high modifier attributes of planes 0 to 3.
+const PLANE_ATTRIBUTES: [[i32; 5]; 4] = [
+    [0x3272, 0x3273, 0x3274, 0x3443, 0x3444],
+    [0x3275, 0x3276, 0x3277, 0x3445, 0x3446],
+    [0x3278, 0x3279, 0x327A, 0x3447, 0x3448],
+    [0x3440, 0x3441, 0x3442, 0x3449, 0x344A],
+];
+
+type EglImage = *const c_void;
+type QueryString = unsafe extern "C" fn(*const c_void, i32) -> *const c_char;
+type CreateImage =
+    unsafe extern "C" fn(*const c_void, *const c_void, u32, *const c_void, *const i32) -> EglImage;
+type DestroyImage = unsafe extern "C" fn(*const c_void, EglImage) -> u32;
+type ImageTargetTexture = unsafe extern "C" fn(gl::types::GLenum, EglImage);
+type ExportQuery =
+    unsafe extern "C" fn(*const c_void, EglImage, *mut i32, *mut i32, *mut u64) -> u32;
+type Export = unsafe extern "C" fn(*const c_void, EglImage, *mut i32, *mut i32, *mut i32) -> u32;
+
+/// One plane of a [`DmaBuf`].
+#[derive(Debug)]
+pub struct DmaBufPlane {
+    pub fd: OwnedFd,
+    /// Bytes from the start of the buffer to the plane's first row.
+    pub offset: u32,
+    /// Bytes per row.
+    pub stride: u32,
+}
+
+/// A 2D image in DMA-BUF memory: its size, DRM format and up to four planes, which may
+/// share one buffer at different offsets.
+#[derive(Debug)]
+pub struct DmaBuf {
+    pub width: u32,
+    pub height: u32,
+    /// A DRM fourcc such as [`ARGB8888`] or [`NV12`].
+    pub fourcc: u32,
+    /// The DRM format modifier describing tiling or compression; `None` leaves it to
+    /// the driver, which only works for linear buffers from the same device.
+    pub modifier: Option<u64>,
+    pub planes: Vec<DmaBufPlane>,
+}
+
+/// The EGL display and render context registered by the runner, and the image entry
+/// points.
+struct Egl {
+    glutin: Display,
+    display: *const c_void,
+    context: *const c_void,
+    create_image: CreateImage,
+    destroy_image: DestroyImage,
+}
+
+impl Egl {
+    fn current() -> Result<Egl> {
+        let (glutin, context) = upload::registered()
+            .ok_or_else(|| anyhow!("No render context registered on this thread"))?;
+        let (RawDisplay::Egl(display), RawContext::Egl(context)) = (glutin.raw_display(), context)
+        else {
+            return Err(anyhow!("DMA-BUF sharing needs an EGL context"));
+        };
+        egl_extension(&glutin, display, "EGL_KHR_image_base")?;
+        Ok(Egl {
+            create_image: load(&glutin, "eglCreateImageKHR")?,
+            destroy_image: load(&glutin, "eglDestroyImageKHR")?,
+            glutin,
+            display,
+            context,
+        })
+    }
+
+    fn function<F: Copy>(&self, name: &str) -> Result<F> {
+        load(&self.glutin, name)
+    }
+
+    fn require(&self, extension: &str) -> Result<()> {
+        egl_extension(&self.glutin, self.display, extension)
+    }
+
+    fn create(
+        &self,
+        context: *const c_void,
+        target: u32,
+        buffer: *const c_void,
+        attributes: &[i32],
+    ) -> Result<Image<'_>> {
+        let image = unsafe {
+            (self.create_image)(self.display, context, target, buffer, attributes.as_ptr())
+        };
+        if image.is_null() {
+            return Err(anyhow!("Failed to create EGL image"));
+        }
+        Ok(Image { egl: self, image })
+    }
+}
+
+/// Loads the entry point `name` as a function pointer of type `F`.
+fn load<F: Copy>(display: &Display, name: &str) -> Result<F> {
+    let symbol = CString::new(name).unwrap();
+    let pointer = display.get_proc_address(&symbol);
+    if pointer.is_null() {
+        return Err(anyhow!("Failed to load {}", name));
+    }
+    Ok(unsafe { std::mem::transmute_copy(&pointer) })
+}
+
+/// Fails unless the EGL display `raw` of `display` has `extension`.
+fn egl_extension(display: &Display, raw: *const c_void, extension: &str) -> Result<()> {
+    let query: QueryString = load(display, "eglQueryString")?;
+    let extensions = unsafe { query(raw, EGL_EXTENSIONS) };
+    let supported = !extensions.is_null()
+        && unsafe { CStr::from_ptr(extensions) }
+            .to_string_lossy()
+            .split_whitespace()
+            .any(|name| name == extension);
+    if supported {
+        Ok(())
+    } else {
+        Err(anyhow!("EGL display lacks {}", extension))
+    }
+}
+
+/// An `EGLImage`, destroyed on drop. Textures bound to it keep its storage alive.
+struct Image<'a> {
+    egl: &'a Egl,
+    image: EglImage,
+}
+
+impl Drop for Image<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            (self.egl.destroy_image)(self.egl.display, self.image);
+        }
+    }
+}
+
+/// Wraps `dma_buf` in a new texture for `target`: `GL_TEXTURE_2D` for RGB formats, or
+/// [`TEXTURE_EXTERNAL_OES`] for YUV formats such as [`NV12`] and for any layout the
+/// driver can only sample, not render to. The fds may be closed afterwards; the texture
+/// keeps the memory alive. Leaves the texture bound to the active unit.
+pub fn import(dma_buf: &DmaBuf, target: gl::types::GLenum) -> Result<Texture> {
+    if dma_buf.planes.is_empty() || dma_buf.planes.len() > PLANE_ATTRIBUTES.len() {
+        return Err(anyhow!(
+            "Failed to import DMA-BUF: {} planes",
+            dma_buf.planes.len()
+        ));
+    }
+    if !context::info().has_extension("GL_OES_EGL_image") {
+        return Err(anyhow!("Failed to import DMA-BUF: no GL_OES_EGL_image"));
+    }
+    let egl = Egl::current()?;
+    egl.require("EGL_EXT_image_dma_buf_import")?;
+    if dma_buf.modifier.is_some() {
+        egl.require("EGL_EXT_image_dma_buf_import_modifiers")?;
+    }
+
+    let mut attributes = vec![
+        EGL_WIDTH,
+        dma_buf.width as i32,
+        EGL_HEIGHT,
+        dma_buf.height as i32,
+        EGL_LINUX_DRM_FOURCC_EXT,
+        dma_buf.fourcc as i32,
+    ];
+    for (plane, names) in dma_buf.planes.iter().zip(PLANE_ATTRIBUTES) {
+        attributes.extend([
+            names[0],
+            plane.fd.as_raw_fd(),
+            names[1],
+            plane.offset as i32,
+            names[2],
+            plane.stride as i32,
+        ]);
+        if let Some(modifier) = dma_buf.modifier {
+            attributes.extend([
+                names[3],
+                modifier as u32 as i32,
+                names[4],
+                (modifier >> 32) as u32 as i32,
+            ]);
+        }
+    }
+    attributes.push(EGL_NONE);
+
+    let image = egl.create(ptr::null(), EGL_LINUX_DMA_BUF_EXT, ptr::null(), &attributes)?;
+    let target_texture: ImageTargetTexture = egl.function("glEGLImageTargetTexture2DOES")?;
+    let texture = Texture::new(target)?;
+    texture.bind();
+    texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+    texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    unsafe {
+        target_texture(target, image.image);
+    }
+    Ok(texture)
+}
+
+/// Exports mip `level` of a 2D texture with immutable or complete storage as a
+/// DMA-BUF sharing its memory. Later rendering to the texture shows through.
+pub fn export(texture: &Texture, level: i32) -> Result<DmaBuf> {
+    if texture.target() != gl::TEXTURE_2D {
+        return Err(anyhow!(
+            "Failed to export texture {}: not a 2D texture",
+            texture.id()
+        ));
+    }
+    let egl = Egl::current()?;
+    egl.require("EGL_KHR_gl_texture_2D_image")?;
+    egl.require("EGL_MESA_image_dma_buf_export")?;
+    let query: ExportQuery = egl.function("eglExportDMABUFImageQueryMESA")?;
+    let export: Export = egl.function("eglExportDMABUFImageMESA")?;
+
+    let (width, height, _) =
+        texture::level_info(texture.id(), gl::TEXTURE_2D, gl::TEXTURE_2D, level);
+    let attributes = [EGL_GL_TEXTURE_LEVEL_KHR, level, EGL_NONE];
+    let buffer = texture.id() as usize as *const c_void;
+    let image = egl.create(egl.context, EGL_GL_TEXTURE_2D_KHR, buffer, &attributes)?;
+
+    let (mut fourcc, mut plane_count, mut modifier) = (0, 0, 0);
+    let queried = unsafe {
+        query(
+            egl.display,
+            image.image,
+            &mut fourcc,
+            &mut plane_count,
+            &mut modifier,
+        )
+    };
+    if queried == 0 || plane_count < 1 || plane_count as usize > PLANE_ATTRIBUTES.len() {
+        return Err(anyhow!(
+            "Failed to export texture {}: query failed",
+            texture.id()
+        ));
+    }
+    let (mut fds, mut strides, mut offsets) = ([-1; 4], [0; 4], [0; 4]);
+    let exported = unsafe {
+        export(
+            egl.display,
+            image.image,
+            fds.as_mut_ptr(),
+            strides.as_mut_ptr(),
+            offsets.as_mut_ptr(),
+        )
+    };
+    // Take ownership of whatever was returned before checking, so nothing leaks.
+    let owned: Vec<Option<OwnedFd>> = fds
+        .iter()
+        .map(|&fd| (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd) }))
+        .collect();
+    if exported == 0 {
+        return Err(anyhow!(
+            "Failed to export texture {}: export failed",
+            texture.id()
+        ));
+    }
+
+    // Planes in the same buffer as the previous one may come without their own fd.
+    let mut planes: Vec<DmaBufPlane> = Vec::new();
+    for (plane, fd) in owned.into_iter().take(plane_count as usize).enumerate() {
+        let fd = match (fd, planes.last()) {
+            (Some(fd), _) => fd,
+            (None, Some(previous)) => previous.fd.try_clone()?,
+            (None, None) => {
+                return Err(anyhow!(
+                    "Failed to export texture {}: no fd for plane {}",
+                    texture.id(),
+                    plane
+                ))
+            }
+        };
+        planes.push(DmaBufPlane {
+            fd,
+            offset: offsets[plane] as u32,
+            stride: strides[plane] as u32,
+        });
+    }
+    Ok(DmaBuf {
+        width: width as u32,
+        height: height as u32,
+        fourcc: fourcc as u32,
+        modifier: (modifier != MODIFIER_INVALID).then_some(modifier),
+        planes,
+    })
+}
@@ -0,0 +1,316 @@
+//! Sorted draw submission.
+//!
+//! Draws are recorded into a [`DrawList`] with a [`SortKey`] and submitted in key order,
+//! so consecutive draws share programs and textures and the [`StateCache`] can skip the
+//! rebinds. Opaque draws are grouped by state and then ordered front to back; blended
+//! draws (base color alpha below one) follow, back to front, or in any order into
+//! [`OitTargets`] with [`Transparency::WeightedBlended`]. With
+//! [`DrawList::depth_prepass`] set, the opaque draws first lay down depth alone and are
+//! then shaded with `GL_EQUAL` depth testing, so each pixel runs one expensive fragment
+//! shader however many surfaces overlap it, for the price of drawing them twice.
+//!
+//! Lists made with [`DrawList::new_in`] record into a [`FrameArena`], which the app
+//! resets once per frame, so a list of thousands of draws costs no heap allocations.
+
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+use crate::arena::{ArenaVec, FrameArena};
+use crate::depth;
+use crate::gl;
+use crate::material::{Material, MaterialShaders};
+use crate::math::Mat4;
+use crate::mesh::Mesh;
+use crate::oit::OitTargets;
+use crate::state::{StateCache, StateStats};
+use crate::validate;
+
+/// Packs draw ordering into one integer.
+///
+/// From the most significant bit: blended flag, then for opaque draws program (12 bits),
+/// texture (16 bits) and depth (24 bits); for blended draws inverted depth comes before
+/// program and texture, so they stay back to front.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SortKey(pub u64);
+
+impl SortKey {
+    /// `depth` is normalized to `0..=1`, near to far.
+    pub fn new(blended: bool, program: u32, texture: u32, depth: f32) -> SortKey {
+        let program = program as u64 & 0xfff;
+        let texture = texture as u64 & 0xffff;
+        let depth = (depth.clamp(0.0, 1.0) * 0xff_ffff as f32) as u64;
+        if blended {
+            SortKey(1 << 63 | (0xff_ffff - depth) << 28 | program << 16 | texture)
+        } else {
+            SortKey(program << 40 | texture << 24 | depth)
+        }
+    }
+}
+
+/// How a [`DrawList`] draws blended materials.
+#[derive(Clone, Copy, Default)]
+pub enum Transparency<'a> {
+    /// Alpha blending back to front by mesh center, which is wrong where transparent
+    /// surfaces intersect or overlap out of order.
+    #[default]
+    Sorted,
+    /// Weighted blended order-independent transparency into the given targets, which
+    /// must match the size of the framebuffer the list is submitted to.
+    WeightedBlended(&'a OitTargets),
+}
+
+#[derive(Clone, Copy)]
+struct Command<'a> {
+    key: SortKey,
+    mesh: &'a Mesh,
+    submesh: usize,
+    material: &'a Material,
+    model: Mat4,
+}
+
+/// Where a [`DrawList`] records its commands.
+enum Commands<'a> {
+    Heap(Vec<Command<'a>>),
+    Arena(ArenaVec<'a, Command<'a>>),
+}
+
+impl<'a> Commands<'a> {
+    fn push(&mut self, command: Command<'a>) {
+        match self {
+            Commands::Heap(commands) => commands.push(command),
+            Commands::Arena(commands) => commands.push(command),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Commands::Heap(commands) => commands.clear(),
+            Commands::Arena(commands) => commands.clear(),
+        }
+    }
+}
+
+impl<'a> Deref for Commands<'a> {
+    type Target = [Command<'a>];
+
+    fn deref(&self) -> &[Command<'a>] {
+        match self {
+            Commands::Heap(commands) => commands,
+            Commands::Arena(commands) => commands,
+        }
+    }
+}
+
+impl<'a> DerefMut for Commands<'a> {
+    fn deref_mut(&mut self) -> &mut [Command<'a>] {
+        match self {
+            Commands::Heap(commands) => commands,
+            Commands::Arena(commands) => commands,
+        }
+    }
+}
+
+/// Draw calls recorded for one frame.
+pub struct DrawList<'a> {
+    view_projection: Mat4,
+    commands: Commands<'a>,
+    pub transparency: Transparency<'a>,
+    /// Draws the opaque draws depth-only before shading them. Off by default.
+    pub depth_prepass: bool,
+}
+
+impl<'a> DrawList<'a> {
+    /// Creates an empty list on the heap; `view_projection` is used to compute the depth
+    /// part of keys. Lists made every frame should use [`DrawList::new_in`].
+    pub fn new(view_projection: Mat4) -> DrawList<'a> {
+        DrawList::with_commands(view_projection, Commands::Heap(Vec::new()))
+    }
+
+    /// Like [`DrawList::new`], recording into `arena`, which stays borrowed until the list
+    /// is dropped.
+    pub fn new_in(view_projection: Mat4, arena: &'a FrameArena) -> DrawList<'a> {
+        DrawList::with_commands(view_projection, Commands::Arena(ArenaVec::new_in(arena)))
+    }
+
+    fn with_commands(view_projection: Mat4, commands: Commands<'a>) -> DrawList<'a> {
+        DrawList {
+            view_projection,
+            commands,
+            transparency: Transparency::Sorted,
+            depth_prepass: false,
+        }
+    }
+
+    /// Empties the list for reuse with a new camera.
+    pub fn clear(&mut self, view_projection: Mat4) {
+        self.view_projection = view_projection;
+        self.commands.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Records one submesh. The program part of the key comes from `shaders`, so record
+    /// and submit with the same [`MaterialShaders`].
+    pub fn push(
+        &mut self,
+        shaders: &MaterialShaders,
+        mesh: &'a Mesh,
+        submesh: usize,
+        material: &'a Material,
+        model: Mat4,
+    ) {
+        let center = model.transform_point3(mesh.bounds().center());
+        let clip = self.view_projection * center.extend(1.0);
+        let depth = if clip.w > 0.0 {
+            clip.z / clip.w * 0.5 + 0.5
+        } else {
+            0.0
+        };
+        let program = shaders.program(material.shading, mesh.is_skinned()).id();
+        let texture = material
+            .base_color_texture
+            .as_ref()
+            .map_or(0, |texture| texture.id());
+        let blended = material.base_color.w < 1.0;
+        self.commands.push(Command {
+            key: SortKey::new(blended, program, texture, depth),
+            mesh,
+            submesh,
+            material,
+            model,
+        });
+    }
+
+    /// Records every submesh of `mesh`, looking materials up by
+    /// [`crate::mesh::Submesh::material`] and using `fallback` when there is none.
+    pub fn push_mesh(
+        &mut self,
+        shaders: &MaterialShaders,
+        mesh: &'a Mesh,
+        materials: &'a [Material],
+        fallback: &'a Material,
+        model: Mat4,
+    ) {
+        for (i, submesh) in mesh.submeshes().iter().enumerate() {
+            let material = submesh
+                .material
+                .and_then(|m| materials.get(m))
+                .unwrap_or(fallback);
+            self.push(shaders, mesh, i, material, model);
+        }
+    }
+
+    /// Sorts and draws the list. Material parameters are only uploaded when the material
+    /// changes between consecutive draws. Returns the bind statistics of this submission.
+    pub fn submit(&mut self, shaders: &MaterialShaders, cache: &mut StateCache) -> StateStats {
+        self.commands.sort_by_key(|command| command.key);
+        let before = cache.stats();
+
+        let split = self
+            .commands
+            .partition_point(|command| command.key.0 >> 63 == 0);
+        let (opaque, blended) = self.commands.split_at(split);
+        if self.depth_prepass && !opaque.is_empty() {
+            draw_depth(opaque, shaders, cache);
+            unsafe {
+                gl::DepthFunc(gl::EQUAL);
+                gl::DepthMask(gl::FALSE);
+            }
+            draw_commands(opaque, shaders, cache, false);
+            unsafe {
+                gl::DepthFunc(depth::nearer());
+                gl::DepthMask(gl::TRUE);
+            }
+        } else {
+            draw_commands(opaque, shaders, cache, false);
+        }
+        if !blended.is_empty() {
+            match self.transparency {
+                Transparency::Sorted => {
+                    unsafe {
+                        gl::Enable(gl::BLEND);
+                        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+                        gl::DepthMask(gl::FALSE);
+                    }
+                    draw_commands(blended, shaders, cache, false);
+                    unsafe {
+                        gl::Disable(gl::BLEND);
+                        gl::DepthMask(gl::TRUE);
+                    }
+                }
+                Transparency::WeightedBlended(targets) => {
+                    targets.begin();
+                    draw_commands(blended, shaders, cache, true);
+                    cache.unbind_vertex_array();
+                    targets.end();
+                    // The composite pass binds its program and textures behind the cache.
+                    cache.invalidate();
+                }
+            }
+        }
+        cache.unbind_vertex_array();
+
+        let after = cache.stats();
+        StateStats {
+            program_binds: after.program_binds - before.program_binds,
+            vertex_array_binds: after.vertex_array_binds - before.vertex_array_binds,
+            texture_binds: after.texture_binds - before.texture_binds,
+            buffer_binds: after.buffer_binds - before.buffer_binds,
+            framebuffer_binds: after.framebuffer_binds - before.framebuffer_binds,
+            raster_changes: after.raster_changes - before.raster_changes,
+            skipped: after.skipped - before.skipped,
+        }
+    }
+}
+
+/// Writes the depth of `commands` with color writes off.
+fn draw_depth(commands: &[Command], shaders: &MaterialShaders, cache: &mut StateCache) {
+    unsafe {
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+    }
+    for command in commands {
+        let program = shaders.depth_program(command.mesh.is_skinned());
+        cache.use_program(program);
+        validate::pairing(command.mesh.vertex_array(), program);
+        program.set_mat4("u_model", &command.model.to_cols_array());
+        command.mesh.draw_submesh_cached(command.submesh, cache);
+    }
+    unsafe {
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+    }
+}
+
+fn draw_commands(
+    commands: &[Command],
+    shaders: &MaterialShaders,
+    cache: &mut StateCache,
+    oit: bool,
+) {
+    let mut previous: Option<(*const Material, bool)> = None;
+    for command in commands {
+        let skinned = command.mesh.is_skinned();
+        let variant = (command.material as *const Material, skinned);
+        let shading = command.material.shading;
+        let program = if previous.is_some_and(|p| ptr::eq(p.0, variant.0) && p.1 == skinned) {
+            if oit {
+                shaders.oit_program(shading, skinned)
+            } else {
+                shaders.program(shading, skinned)
+            }
+        } else if oit {
+            shaders.bind_oit_cached(command.material, skinned, cache)
+        } else {
+            shaders.bind_cached(command.material, skinned, cache)
+        };
+        previous = Some(variant);
+        validate::pairing(command.mesh.vertex_array(), program);
+        program.set_mat4("u_model", &command.model.to_cols_array());
+        command.mesh.draw_submesh_cached(command.submesh, cache);
+    }
+}
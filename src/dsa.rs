@@ -0,0 +1,54 @@
+//! Detection of direct state access (GL 4.5 / `ARB_direct_state_access`).
+//!
+//! When available, the object wrappers in [`crate::buffer`], [`crate::texture`] and
+//! [`crate::framebuffer`] create their objects with `glCreate*` and modify them through
+//! the named entry points instead of binding them first. Otherwise they fall back to the
+//! classic bind-to-modify calls. Callers keep binding objects as before either way.
+
+use std::cell::Cell;
+
+use crate::context;
+use crate::gl;
+
+thread_local! {
+    static AVAILABLE: Cell<Option<bool>> = const { Cell::new(None) };
+}
+
+/// Whether the wrappers use direct state access on the current context. Detected on
+/// first use.
+pub fn is_available() -> bool {
+    AVAILABLE.with(|available| {
+        available.get().unwrap_or_else(|| {
+            let detected = detect();
+            available.set(Some(detected));
+            detected
+        })
+    })
+}
+
+/// Forces DSA on or off, e.g. to exercise the fallback path on a 4.5 context. Enabling
+/// it has no effect if the context lacks support. Call it before creating any objects.
+pub fn set_enabled(enabled: bool) {
+    let value = enabled && detect();
+    AVAILABLE.with(|available| available.set(Some(value)));
+}
+
+/// Detects support again on next use, for a replaced context.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn forget() {
+    AVAILABLE.with(|available| available.set(None));
+}
+
+fn detect() -> bool {
+    let loaded = gl::CreateBuffers::is_loaded()
+        && gl::NamedBufferData::is_loaded()
+        && gl::CreateTextures::is_loaded()
+        && gl::TextureSubImage2D::is_loaded()
+        && gl::CreateVertexArrays::is_loaded()
+        && gl::VertexArrayAttribFormat::is_loaded()
+        && gl::CreateFramebuffers::is_loaded();
+    if !loaded {
+        return false;
+    }
+    context::version() >= (4, 5) || context::has_extension("GL_ARB_direct_state_access")
+}
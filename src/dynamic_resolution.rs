@@ -0,0 +1,333 @@
+//! Adaptive resolution scaling.
+//!
+//! [`DynamicResolution`] renders the scene into an offscreen target at a fraction of
+//! the window's size and upscales it to the default framebuffer. The fraction follows
+//! the GPU time of recent frames, measured with a [`GpuTimer`]: GPU time grows about
+//! with the pixel count, so the scale moves towards the square root of the budget over
+//! the measured time, in steps, and only once frames at the current scale have been
+//! measured. On contexts without timer queries the scale stays where
+//! [`DynamicResolution::set_scale`] puts it.
+//!
+//! The target is allocated once at the largest scale and the scene is drawn into its
+//! lower left corner, so changing the scale costs nothing. [`Upscale::Sharpened`]
+//! follows AMD FidelityFX Super Resolution 1 in spirit: a Catmull-Rom upscale, sharper
+//! than bilinear, then contrast-adaptive sharpening as in FSR's RCAS. Sharpening
+//! expects colors in `0..1`, so tonemap before upscaling.
+
+use anyhow::Result;
+
+use crate::framebuffer::{Framebuffer, RenderTarget};
+use crate::gl;
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::profiler::GpuTimer;
+use crate::shader::Program;
+use crate::viewport::Viewport;
+
+/// Weight of each new GPU time in the smoothed one.
+const SMOOTHING: f32 = 0.2;
+
+/// Scales are multiples of this, and change by at least this much.
+const SCALE_STEP: f32 = 1.0 / 32.0;
+
+/// Share of the frame budget aimed for, leaving room for spikes and the CPU's work.
+const HEADROOM: f32 = 0.9;
+
+/// Samples the scene's rendered corner with a 9-tap Catmull-Rom filter made of bilinear
+/// fetches, clamped so the unrendered rest of the target never bleeds in.
+const UPSCALE_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+// Size of the whole texture and of the rendered corner, in texels.
+uniform vec2 u_texture_size;
+uniform vec2 u_render_size;
+
+vec4 fetch(vec2 texel) {
+    texel = clamp(texel, vec2(0.5), u_render_size - 0.5);
+    return texture(u_input, texel / u_texture_size);
+}
+
+void main() {
+    vec2 position = uv * u_render_size;
+    vec2 center = floor(position - 0.5) + 0.5;
+    vec2 f = position - center;
+    vec2 w0 = f * (-0.5 + f * (1.0 - 0.5 * f));
+    vec2 w1 = 1.0 + f * f * (-2.5 + 1.5 * f);
+    vec2 w2 = f * (0.5 + f * (2.0 - 1.5 * f));
+    vec2 w3 = f * f * (-0.5 + 0.5 * f);
+    vec2 w12 = w1 + w2;
+    vec2 t0 = center - 1.0;
+    vec2 t12 = center + w2 / w12;
+    vec2 t3 = center + 2.0;
+    vec4 color = fetch(vec2(t12.x, t0.y)) * w12.x * w0.y
+        + fetch(vec2(t0.x, t12.y)) * w0.x * w12.y
+        + fetch(t12) * w12.x * w12.y
+        + fetch(vec2(t3.x, t12.y)) * w3.x * w12.y
+        + fetch(vec2(t12.x, t3.y)) * w12.x * w3.y;
+    float weight = w12.x * w0.y + w0.x * w12.y + w12.x * w12.y + w3.x * w12.y + w12.x * w3.y;
+    frag_color = max(color / weight, 0.0);
+}
+"#;
+
+/// Contrast-adaptive sharpening after FSR 1's RCAS: a negative lobe on the four
+/// neighbors, as strong as it can be without pushing the center out of their range.
+const SHARPEN_SHADER: &str = r#"#version 330 core
+out vec4 frag_color;
+uniform sampler2D u_input;
+uniform float u_sharpening;
+
+void main() {
+    ivec2 p = ivec2(gl_FragCoord.xy);
+    ivec2 last = textureSize(u_input, 0) - 1;
+    vec4 e = texelFetch(u_input, p, 0);
+    vec3 b = texelFetch(u_input, clamp(p + ivec2(0, -1), ivec2(0), last), 0).rgb;
+    vec3 d = texelFetch(u_input, clamp(p + ivec2(-1, 0), ivec2(0), last), 0).rgb;
+    vec3 f = texelFetch(u_input, clamp(p + ivec2(1, 0), ivec2(0), last), 0).rgb;
+    vec3 h = texelFetch(u_input, clamp(p + ivec2(0, 1), ivec2(0), last), 0).rgb;
+    vec3 low = min(min(min(b, d), min(f, h)), e.rgb);
+    vec3 high = max(max(max(b, d), max(f, h)), e.rgb);
+    vec3 hit_low = low / (4.0 * high + 1e-5);
+    vec3 hit_high = (1.0 - high) / (4.0 * low - 4.0 - 1e-5);
+    vec3 lobes = max(-hit_low, hit_high);
+    float lobe = clamp(max(lobes.r, max(lobes.g, lobes.b)), -0.1875, 0.0) * u_sharpening;
+    vec3 color = (lobe * (b + d + f + h) + e.rgb) / (4.0 * lobe + 1.0);
+    frag_color = vec4(color, e.a);
+}
+"#;
+
+/// How the scene is scaled up to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Upscale {
+    /// A linear blit. Cheapest, and blurry well below full scale.
+    Bilinear,
+    /// Catmull-Rom upscaling and contrast-adaptive sharpening, `sharpening` from 0
+    /// (none) to 1.
+    Sharpened { sharpening: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DynamicResolutionConfig {
+    /// GPU seconds per frame to stay within, `1/60` by default.
+    pub frame_budget: f32,
+    /// Bounds of the scale of each side, `0.5` and `1.0` by default. A maximum above 1
+    /// supersamples when there is time to spare.
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub upscale: Upscale,
+}
+
+impl Default for DynamicResolutionConfig {
+    fn default() -> Self {
+        DynamicResolutionConfig {
+            frame_budget: 1.0 / 60.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+            upscale: Upscale::Sharpened { sharpening: 0.5 },
+        }
+    }
+}
+
+pub struct DynamicResolution {
+    pub config: DynamicResolutionConfig,
+    scale: f32,
+    /// `None` where timer queries are unsupported.
+    timer: Option<GpuTimer>,
+    /// Frames begun so far, and the first one rendered at the current scale.
+    frame: u64,
+    settled_frame: u64,
+    gpu_time: Option<f32>,
+    /// Holds the scene in its lower left corner.
+    target: RenderTarget,
+    /// The upscaled scene before sharpening, at window size.
+    upscaled: Option<RenderTarget>,
+    upscale_program: Program,
+    sharpen_program: Program,
+    triangle: FullscreenTriangle,
+    width: u32,
+    height: u32,
+}
+
+impl DynamicResolution {
+    /// Starts at the largest scale for a `width × height` window.
+    pub fn new(
+        width: u32,
+        height: u32,
+        config: DynamicResolutionConfig,
+    ) -> Result<DynamicResolution> {
+        let timer = if GpuTimer::is_supported() {
+            Some(GpuTimer::new()?)
+        } else {
+            tracing::warn!("Timer queries unsupported; resolution scale stays fixed");
+            None
+        };
+        let mut dynamic_resolution = DynamicResolution {
+            config,
+            scale: config.max_scale,
+            timer,
+            frame: 0,
+            settled_frame: 0,
+            gpu_time: None,
+            target: RenderTarget::new(1, 1, gl::RGBA8, true)?,
+            upscaled: None,
+            upscale_program: Program::from_sources(FULLSCREEN_VERTEX_SHADER, UPSCALE_SHADER)?,
+            sharpen_program: Program::from_sources(FULLSCREEN_VERTEX_SHADER, SHARPEN_SHADER)?,
+            triangle: FullscreenTriangle::new()?,
+            width: 0,
+            height: 0,
+        };
+        dynamic_resolution.resize(width, height)?;
+        Ok(dynamic_resolution)
+    }
+
+    /// Reallocates the targets for a new window size.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        let (width, height) = (width.max(1), height.max(1));
+        let max_scale = self.config.max_scale.max(self.config.min_scale);
+        let size = |side: u32| (side as f32 * max_scale).ceil().max(1.0) as i32;
+        self.target = RenderTarget::new(size(width), size(height), gl::RGBA8, true)?;
+        self.upscaled = None;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// The current scale of each side.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the scale, clamped to the configured bounds. Measurements move it again
+    /// where timer queries are supported.
+    pub fn set_scale(&mut self, scale: f32) {
+        let scale = quantize(scale, &self.config);
+        if scale != self.scale {
+            self.scale = scale;
+            self.settled_frame = self.frame;
+            self.gpu_time = None;
+        }
+    }
+
+    /// Smoothed GPU seconds of recent frames at the current scale.
+    pub fn gpu_time(&self) -> Option<f32> {
+        self.gpu_time
+    }
+
+    /// The size the scene is rendered at this frame.
+    pub fn render_size(&self) -> (i32, i32) {
+        let side = |side: u32, max: i32| ((side as f32 * self.scale).round() as i32).clamp(1, max);
+        (
+            side(self.width, self.target.width()),
+            side(self.height, self.target.height()),
+        )
+    }
+
+    /// Adjusts the scale from the measurements that arrived, then binds the scene
+    /// target with the viewport set to [`DynamicResolution::render_size`]. Render the
+    /// scene after calling this, with the window's aspect ratio.
+    pub fn begin(&mut self) -> Result<()> {
+        self.adjust();
+        if let Some(timer) = &mut self.timer {
+            timer.begin()?;
+        }
+        self.target.framebuffer.bind(gl::FRAMEBUFFER);
+        let (width, height) = self.render_size();
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+        }
+        Ok(())
+    }
+
+    /// Upscales the scene to the whole default framebuffer and ends the measurement.
+    pub fn end(&mut self) -> Result<()> {
+        let (render_width, render_height) = self.render_size();
+        let (width, height) = (self.width as i32, self.height as i32);
+        let window = Viewport::new(0, 0, width, height);
+        let source = Viewport::new(0, 0, render_width, render_height);
+        match self.config.upscale {
+            Upscale::Bilinear => {
+                self.target.framebuffer.blit_to(
+                    None,
+                    source,
+                    window,
+                    gl::COLOR_BUFFER_BIT,
+                    gl::LINEAR,
+                );
+            }
+            Upscale::Sharpened { sharpening } => {
+                if self.upscaled.is_none() {
+                    self.upscaled = Some(RenderTarget::new(width, height, gl::RGBA8, false)?);
+                }
+                let upscaled = self.upscaled.as_ref().unwrap();
+                unsafe {
+                    gl::Disable(gl::DEPTH_TEST);
+                }
+                upscaled.bind();
+                let program = &self.upscale_program;
+                program.use_program();
+                self.target.color.bind_unit(0);
+                program.set_int("u_input", 0);
+                program.set_vec2(
+                    "u_texture_size",
+                    [self.target.width() as f32, self.target.height() as f32],
+                );
+                program.set_vec2("u_render_size", [render_width as f32, render_height as f32]);
+                self.triangle.draw();
+
+                Framebuffer::bind_default(gl::FRAMEBUFFER);
+                unsafe {
+                    gl::Viewport(0, 0, width, height);
+                }
+                let program = &self.sharpen_program;
+                program.use_program();
+                upscaled.color.bind_unit(0);
+                program.set_int("u_input", 0);
+                program.set_float("u_sharpening", sharpening.clamp(0.0, 1.0));
+                self.triangle.draw();
+            }
+        }
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        if let Some(timer) = &mut self.timer {
+            timer.end();
+        }
+        self.frame += 1;
+        Ok(())
+    }
+
+    fn adjust(&mut self) {
+        let Some(timer) = &mut self.timer else {
+            return;
+        };
+        for time in timer.poll() {
+            // Rendered at an earlier scale.
+            if time.frame < self.settled_frame {
+                continue;
+            }
+            let seconds = time.seconds as f32;
+            self.gpu_time = Some(match self.gpu_time {
+                Some(smoothed) => smoothed + (seconds - smoothed) * SMOOTHING,
+                None => seconds,
+            });
+        }
+        let Some(gpu_time) = self.gpu_time else {
+            return;
+        };
+        let ideal = self.scale * (self.config.frame_budget * HEADROOM / gpu_time).sqrt();
+        let scale = quantize(ideal, &self.config);
+        if (scale - self.scale).abs() >= SCALE_STEP {
+            tracing::debug!(
+                "Resolution scale {:.2} -> {:.2} at {:.2} ms",
+                self.scale,
+                scale,
+                gpu_time * 1e3
+            );
+            self.set_scale(scale);
+        }
+    }
+}
+
+/// `scale` rounded to a [`SCALE_STEP`] within the configured bounds.
+fn quantize(scale: f32, config: &DynamicResolutionConfig) -> f32 {
+    let max = config.max_scale.max(config.min_scale);
+    let scale = (scale / SCALE_STEP).round() * SCALE_STEP;
+    scale.clamp(config.min_scale, max)
+}
@@ -0,0 +1,159 @@
+//! Rendering a `hecs` world.
+//!
+//! Entities are drawn when they have a [`Transform`] and a [`MeshHandle`], with the
+//! material of their [`MaterialHandle`] or a default one. The first entity with a
+//! [`Camera`] is rendered from, and every [`Light`] entity lights the scene. Transforms
+//! are in world space; there is no parent hierarchy, for which see
+//! [`crate::scene::Scene`].
+//!
+//! GL objects can't leave the render thread, while components must be `Send + Sync`,
+//! so meshes and materials live in the [`Renderer`] and components hold handles to
+//! them. [`Renderer::render`] is the render system: call it once per frame after the
+//! app's own systems have updated the world.
+
+use anyhow::{anyhow, Result};
+use hecs::World;
+
+use crate::arena::{ArenaStats, FrameArena};
+use crate::draw::{DrawList, Transparency};
+use crate::material::{LightBuffer, Material, MaterialShaders};
+use crate::math::Vec3;
+use crate::mesh::Mesh;
+use crate::oit::OitTargets;
+use crate::state::{StateCache, StateStats};
+
+pub use crate::material::Light;
+pub use crate::math::Transform;
+pub use crate::viewport::Camera;
+
+/// A mesh added with [`Renderer::add_mesh`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle(usize);
+
+/// A material added with [`Renderer::add_material`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialHandle(usize);
+
+/// The meshes and materials entities refer to, and the GL state to draw them.
+pub struct Renderer {
+    shaders: MaterialShaders,
+    lights: LightBuffer,
+    cache: StateCache,
+    meshes: Vec<Mesh>,
+    materials: Vec<Material>,
+    fallback: Material,
+    oit: Option<OitTargets>,
+    /// Holds the draw list, reset every [`Renderer::render`].
+    arena: FrameArena,
+    /// Uploaded with the lights on every [`Renderer::render`].
+    pub ambient: Vec3,
+}
+
+impl Renderer {
+    pub fn new() -> Result<Renderer> {
+        Ok(Renderer {
+            shaders: MaterialShaders::new()?,
+            lights: LightBuffer::new()?,
+            cache: StateCache::new(),
+            meshes: Vec::new(),
+            materials: Vec::new(),
+            fallback: Material::default(),
+            oit: None,
+            arena: FrameArena::default(),
+            ambient: Vec3::splat(0.1),
+        })
+    }
+
+    pub fn add_mesh(&mut self, mesh: Mesh) -> MeshHandle {
+        self.meshes.push(mesh);
+        MeshHandle(self.meshes.len() - 1)
+    }
+
+    pub fn add_material(&mut self, material: Material) -> MaterialHandle {
+        self.materials.push(material);
+        MaterialHandle(self.materials.len() - 1)
+    }
+
+    pub fn mesh(&self, handle: MeshHandle) -> &Mesh {
+        &self.meshes[handle.0]
+    }
+
+    pub fn material(&self, handle: MaterialHandle) -> &Material {
+        &self.materials[handle.0]
+    }
+
+    pub fn material_mut(&mut self, handle: MaterialHandle) -> &mut Material {
+        &mut self.materials[handle.0]
+    }
+
+    /// Draws transparent materials with weighted blended order-independent transparency
+    /// into `targets`, or sorted with `None`. Replace the targets when the framebuffer
+    /// is resized.
+    pub fn set_oit_targets(&mut self, targets: Option<OitTargets>) {
+        self.oit = targets;
+    }
+
+    /// Memory use of the last frame's draw list.
+    pub fn arena_stats(&self) -> ArenaStats {
+        self.arena.stats()
+    }
+
+    /// Draws `world` into the current framebuffer, whose width over height is `aspect`,
+    /// sorted with a [`DrawList`]. Clearing and depth testing are left to the caller.
+    /// Fails if no entity has a [`Camera`] or there are more lights than the material
+    /// shaders support.
+    pub fn render(&mut self, world: &World, aspect: f32) -> Result<StateStats> {
+        let Renderer {
+            shaders,
+            lights,
+            cache,
+            meshes,
+            materials,
+            fallback,
+            oit,
+            arena,
+            ambient,
+        } = self;
+
+        let camera = world
+            .query::<&Camera>()
+            .iter()
+            .map(|(_, camera)| *camera)
+            .next()
+            .ok_or_else(|| anyhow!("No entity has a Camera"))?;
+        let view_projection = camera.projection(aspect) * camera.view();
+        shaders.set_camera(view_projection, camera.eye);
+        // set_camera binds programs behind the cache's back.
+        cache.invalidate();
+
+        let scene_lights: Vec<Light> = world
+            .query::<&Light>()
+            .iter()
+            .map(|(_, light)| *light)
+            .collect();
+        lights.upload(&scene_lights, *ambient)?;
+
+        arena.reset();
+        let mut list = DrawList::new_in(view_projection, arena);
+        if let Some(targets) = oit {
+            list.transparency = Transparency::WeightedBlended(targets);
+        }
+        for (_, (transform, mesh, material)) in world
+            .query::<(&Transform, &MeshHandle, Option<&MaterialHandle>)>()
+            .iter()
+        {
+            let mesh = &meshes[mesh.0];
+            let model = transform.matrix();
+            match material {
+                Some(material) => {
+                    let material = &materials[material.0];
+                    for i in 0..mesh.submeshes().len() {
+                        list.push(shaders, mesh, i, material, model);
+                    }
+                }
+                None => list.push_mesh(shaders, mesh, &[], fallback, model),
+            }
+        }
+        Ok(list.submit(shaders, cache))
+    }
+}
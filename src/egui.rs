@@ -0,0 +1,460 @@
+//! Immediate-mode UI through `egui`.
+//!
+//! [`Painter`] draws egui's tessellated output with the crate's own buffer, texture and
+//! shader wrappers; [`Egui`] translates window events into egui input and ties the
+//! two together. Only the root viewport is supported, and clipboard and cursor-icon
+//! requests are ignored.
+
+use std::collections::HashMap;
+
+use ::egui::epaint::{ImageDelta, Primitive};
+use ::egui::{ClippedPrimitive, Context, ImageData, TextureFilter, TextureId, TexturesDelta};
+use anyhow::Result;
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::event::{
+    ElementState, KeyCode, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent,
+};
+use crate::gl;
+use crate::shader::Program;
+use crate::stats;
+use crate::texture::Texture;
+use crate::transient::{self, TransientBuffer};
+use crate::validate;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_uv;
+layout (location = 2) in vec4 a_color;
+uniform vec2 u_screen_size;
+out vec2 v_uv;
+out vec4 v_color;
+void main() {
+    v_uv = a_uv;
+    v_color = a_color;
+    gl_Position = vec4(2.0 * a_position / u_screen_size - 1.0, 0.0, 1.0);
+    gl_Position.y = -gl_Position.y;
+}
+"#;
+
+// egui's colors and textures are premultiplied sRGB; blending happens in gamma space,
+// as the default framebuffer is not sRGB-encoded.
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 frag_color;
+uniform sampler2D u_texture;
+void main() {
+    frag_color = v_color * texture(u_texture, v_uv);
+}
+"#;
+
+/// Renders egui meshes and manages the textures egui asks for.
+pub struct Painter {
+    program: Program,
+    vertex_array: VertexArray,
+    vertex_buffer: TransientBuffer,
+    index_buffer: TransientBuffer,
+    textures: HashMap<TextureId, Texture>,
+}
+
+impl Painter {
+    pub fn new() -> Result<Painter> {
+        let vertex_buffer = TransientBuffer::new(transient::DEFAULT_CAPACITY)?;
+        let index_buffer = TransientBuffer::new(transient::DEFAULT_CAPACITY / 2)?;
+        let vertex_array = VertexArray::new()?;
+        set_attributes(&vertex_array, vertex_buffer.buffer(), 0);
+        vertex_array.element_buffer(index_buffer.buffer());
+
+        Ok(Painter {
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            vertex_array,
+            vertex_buffer,
+            index_buffer,
+            textures: HashMap::new(),
+        })
+    }
+
+    /// The GL texture egui refers to as `id`, if it has been uploaded.
+    pub fn texture(&self, id: TextureId) -> Option<&Texture> {
+        self.textures.get(&id)
+    }
+
+    /// Creates, updates and frees textures. Call before [`Painter::paint`] with the
+    /// `set` half of the delta and after it with the `free` half, or use
+    /// [`Painter::paint_and_update_textures`].
+    pub fn set_textures(&mut self, delta: &TexturesDelta) -> Result<()> {
+        for (id, image) in &delta.set {
+            self.set_texture(*id, image)?;
+        }
+        Ok(())
+    }
+
+    pub fn free_textures(&mut self, delta: &TexturesDelta) {
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) -> Result<()> {
+        let pixels: Vec<u8> = match &delta.image {
+            ImageData::Color(image) => bytemuck::cast_slice(&image.pixels).to_vec(),
+            ImageData::Font(image) => image
+                .srgba_pixels(None)
+                .flat_map(|color| color.to_array())
+                .collect(),
+        };
+        let [width, height] = delta.image.size().map(|size| size as i32);
+
+        match (delta.pos, self.textures.get(&id)) {
+            (Some([x, y]), Some(texture)) => {
+                texture.bind();
+                texture.sub_image_2d(
+                    0,
+                    x as i32,
+                    y as i32,
+                    width,
+                    height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    &pixels,
+                );
+                texture.unbind();
+            }
+            _ => {
+                let filter = |filter: TextureFilter| match filter {
+                    TextureFilter::Nearest => gl::NEAREST as i32,
+                    TextureFilter::Linear => gl::LINEAR as i32,
+                };
+                let texture = Texture::new(gl::TEXTURE_2D)?;
+                texture.bind();
+                texture.parameter(gl::TEXTURE_MAG_FILTER, filter(delta.options.magnification));
+                texture.parameter(gl::TEXTURE_MIN_FILTER, filter(delta.options.minification));
+                texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                texture.image_2d(
+                    0,
+                    gl::RGBA8,
+                    width,
+                    height,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    Some(&pixels),
+                );
+                texture.unbind();
+                self.textures.insert(id, texture);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws `primitives` into the currently bound framebuffer of `size` physical pixels.
+    pub fn paint(&self, size: [u32; 2], pixels_per_point: f32, primitives: &[ClippedPrimitive]) {
+        let [width, height] = size;
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(
+                gl::ONE,
+                gl::ONE_MINUS_SRC_ALPHA,
+                gl::ONE_MINUS_DST_ALPHA,
+                gl::ONE,
+            );
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::SCISSOR_TEST);
+        }
+        self.program.use_program();
+        self.program.set_vec2(
+            "u_screen_size",
+            [
+                width as f32 / pixels_per_point,
+                height as f32 / pixels_per_point,
+            ],
+        );
+        self.program.set_int("u_texture", 0);
+        for ClippedPrimitive {
+            clip_rect,
+            primitive,
+        } in primitives
+        {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                continue;
+            };
+
+            // Clip rect in points, top-left origin, to GL's bottom-left pixels.
+            let x = (clip_rect.min.x * pixels_per_point)
+                .round()
+                .clamp(0.0, width as f32);
+            let y = (clip_rect.min.y * pixels_per_point)
+                .round()
+                .clamp(0.0, height as f32);
+            let right = (clip_rect.max.x * pixels_per_point)
+                .round()
+                .clamp(x, width as f32);
+            let bottom = (clip_rect.max.y * pixels_per_point)
+                .round()
+                .clamp(y, height as f32);
+            if right <= x || bottom <= y {
+                continue;
+            }
+            unsafe {
+                gl::Scissor(
+                    x as i32,
+                    (height as f32 - bottom) as i32,
+                    (right - x) as i32,
+                    (bottom - y) as i32,
+                );
+            }
+
+            texture.bind_unit(0);
+            let vertex_offset = self.vertex_buffer.push(
+                bytemuck::cast_slice(&mesh.vertices),
+                std::mem::size_of::<::egui::epaint::Vertex>(),
+            );
+            let index_offset = self
+                .index_buffer
+                .push(bytemuck::cast_slice(&mesh.indices), 4);
+            set_attributes(
+                &self.vertex_array,
+                self.vertex_buffer.buffer(),
+                vertex_offset,
+            );
+            self.vertex_array.bind();
+            validate::draw("an egui mesh");
+            unsafe {
+                gl::DrawElements(
+                    gl::TRIANGLES,
+                    mesh.indices.len() as i32,
+                    gl::UNSIGNED_INT,
+                    index_offset as *const _,
+                );
+            }
+            self.vertex_array.unbind();
+            stats::record_draw(mesh.indices.len() / 3);
+        }
+
+        unsafe {
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    /// Uploads new textures, paints and frees textures egui no longer needs.
+    pub fn paint_and_update_textures(
+        &mut self,
+        size: [u32; 2],
+        pixels_per_point: f32,
+        primitives: &[ClippedPrimitive],
+        textures_delta: &TexturesDelta,
+    ) -> Result<()> {
+        self.set_textures(textures_delta)?;
+        self.paint(size, pixels_per_point, primitives);
+        self.free_textures(textures_delta);
+        Ok(())
+    }
+}
+
+/// An egui context fed from winit events and drawn with a [`Painter`].
+pub struct Egui {
+    pub context: Context,
+    pub painter: Painter,
+    input: ::egui::RawInput,
+    pointer: ::egui::Pos2,
+    modifiers: ::egui::Modifiers,
+    pixels_per_point: f32,
+    size: [u32; 2],
+}
+
+impl Egui {
+    /// `scale_factor` is the window's, from `Window::scale_factor`.
+    pub fn new(scale_factor: f32) -> Result<Egui> {
+        Ok(Egui {
+            context: Context::default(),
+            painter: Painter::new()?,
+            input: ::egui::RawInput::default(),
+            pointer: ::egui::Pos2::ZERO,
+            modifiers: ::egui::Modifiers::default(),
+            pixels_per_point: scale_factor,
+            size: [1, 1],
+        })
+    }
+
+    /// Whether egui is using the pointer, so the application should ignore mouse input.
+    pub fn wants_pointer(&self) -> bool {
+        self.context.wants_pointer_input()
+    }
+
+    /// Whether egui has keyboard focus, so the application should ignore key presses.
+    pub fn wants_keyboard(&self) -> bool {
+        self.context.wants_keyboard_input()
+    }
+
+    /// Sets the framebuffer size in physical pixels. Resize events passed to
+    /// [`Egui::window_event`] do this too.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.size = [width, height];
+    }
+
+    /// Records a window event as egui input.
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        let events = &mut self.input.events;
+        match event {
+            WindowEvent::Resized(size) => self.size = [size.width, size.height],
+            WindowEvent::ScaleFactorChanged { scale_factor } => {
+                self.pixels_per_point = *scale_factor as f32;
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer = ::egui::pos2(
+                    position.x as f32 / self.pixels_per_point,
+                    position.y as f32 / self.pixels_per_point,
+                );
+                events.push(::egui::Event::PointerMoved(self.pointer));
+            }
+            WindowEvent::CursorLeft => events.push(::egui::Event::PointerGone),
+            WindowEvent::MouseInput { state, button, .. } => {
+                let button = match button {
+                    MouseButton::Left => ::egui::PointerButton::Primary,
+                    MouseButton::Right => ::egui::PointerButton::Secondary,
+                    MouseButton::Middle => ::egui::PointerButton::Middle,
+                    MouseButton::Back => ::egui::PointerButton::Extra1,
+                    MouseButton::Forward => ::egui::PointerButton::Extra2,
+                    MouseButton::Other(_) => return,
+                };
+                events.push(::egui::Event::PointerButton {
+                    pos: self.pointer,
+                    button,
+                    pressed: *state == ElementState::Pressed,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let (unit, delta) = match delta {
+                    MouseScrollDelta::LineDelta(x, y) => {
+                        (::egui::MouseWheelUnit::Line, ::egui::vec2(*x, *y))
+                    }
+                    MouseScrollDelta::PixelDelta(delta) => (
+                        ::egui::MouseWheelUnit::Point,
+                        ::egui::vec2(delta.x as f32, delta.y as f32) / self.pixels_per_point,
+                    ),
+                };
+                events.push(::egui::Event::MouseWheel {
+                    unit,
+                    delta,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::ReceivedCharacter(c) if !c.is_control() => {
+                events.push(::egui::Event::Text(c.to_string()));
+            }
+            WindowEvent::ModifiersChanged(state) => {
+                self.modifiers = modifiers(*state);
+                self.input.modifiers = self.modifiers;
+            }
+            WindowEvent::KeyboardInput { input, .. } => {
+                let Some(key) = input.key.and_then(key) else {
+                    return;
+                };
+                let pressed = input.state == ElementState::Pressed;
+                if pressed && self.modifiers.command {
+                    match key {
+                        ::egui::Key::C => events.push(::egui::Event::Copy),
+                        ::egui::Key::X => events.push(::egui::Event::Cut),
+                        _ => {}
+                    }
+                }
+                events.push(::egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed,
+                    repeat: input.repeat,
+                    modifiers: self.modifiers,
+                });
+            }
+            WindowEvent::Focused(focused) => self.input.focused = *focused,
+            _ => {}
+        }
+    }
+
+    /// Runs `ui` for one frame and paints the result into the default framebuffer.
+    pub fn frame<F: FnMut(&Context)>(&mut self, dt: f32, ui: F) -> Result<()> {
+        let [width, height] = self.size;
+        let mut input = std::mem::take(&mut self.input);
+        input.screen_rect = Some(::egui::Rect::from_min_size(
+            ::egui::Pos2::ZERO,
+            ::egui::vec2(width as f32, height as f32) / self.pixels_per_point,
+        ));
+        input.predicted_dt = dt;
+        input
+            .viewports
+            .entry(::egui::ViewportId::ROOT)
+            .or_default()
+            .native_pixels_per_point = Some(self.pixels_per_point);
+        self.input.modifiers = self.modifiers;
+        self.input.focused = input.focused;
+
+        let output = self.context.run(input, ui);
+        let primitives = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.painter.paint_and_update_textures(
+            self.size,
+            output.pixels_per_point,
+            &primitives,
+            &output.textures_delta,
+        )
+    }
+}
+
+/// Points the vertex attributes at egui vertices starting `offset` bytes into `buffer`.
+fn set_attributes(vertex_array: &VertexArray, buffer: &Buffer, offset: usize) {
+    let stride = std::mem::size_of::<::egui::epaint::Vertex>() as i32;
+    vertex_array.attribute(0, buffer, 2, gl::FLOAT, false, stride, offset);
+    vertex_array.attribute(1, buffer, 2, gl::FLOAT, false, stride, offset + 8);
+    vertex_array.attribute(2, buffer, 4, gl::UNSIGNED_BYTE, true, stride, offset + 16);
+}
+
+fn modifiers(state: ModifiersState) -> ::egui::Modifiers {
+    ::egui::Modifiers {
+        alt: state.alt_key(),
+        ctrl: state.control_key(),
+        shift: state.shift_key(),
+        mac_cmd: cfg!(target_os = "macos") && state.super_key(),
+        command: if cfg!(target_os = "macos") {
+            state.super_key()
+        } else {
+            state.control_key()
+        },
+    }
+}
+
+fn key(key: KeyCode) -> Option<::egui::Key> {
+    use ::egui::Key;
+    Some(match key {
+        KeyCode::ArrowDown => Key::ArrowDown,
+        KeyCode::ArrowLeft => Key::ArrowLeft,
+        KeyCode::ArrowRight => Key::ArrowRight,
+        KeyCode::ArrowUp => Key::ArrowUp,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Backspace => Key::Backspace,
+        KeyCode::Enter | KeyCode::NumpadEnter => Key::Enter,
+        KeyCode::Space => Key::Space,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::KeyA => Key::A,
+        KeyCode::KeyC => Key::C,
+        KeyCode::KeyV => Key::V,
+        KeyCode::KeyX => Key::X,
+        KeyCode::KeyY => Key::Y,
+        KeyCode::KeyZ => Key::Z,
+        _ => return None,
+    })
+}
@@ -0,0 +1,125 @@
+//! Window events as the crate hands them to apps, whichever backend produced them.
+//!
+//! The winit runners translate winit's events with [`WindowEvent::from_winit`], the SDL
+//! runner (feature `sdl2`) translates SDL's, and [`crate::replay`] rebuilds them from
+//! recordings. winit's own keyboard events can't be constructed outside winit, so this
+//! is the type [`crate::app::App::window_event`], [`crate::input::Input`] and the other
+//! consumers take. Keys are physical [`KeyCode`]s, named after the US layout, so WASD
+//! stays in place on other layouts; typed text arrives separately as
+//! [`WindowEvent::ReceivedCharacter`].
+
+use std::path::PathBuf;
+
+pub use winit::dpi::{PhysicalPosition, PhysicalSize};
+pub use winit::event::{ElementState, MouseButton, MouseScrollDelta};
+pub use winit::keyboard::{KeyCode, ModifiersState};
+
+/// A key press or release.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyboardInput {
+    /// `None` for keys the backend can't identify.
+    pub key: Option<KeyCode>,
+    pub state: ElementState,
+    /// Whether this is a press repeated by holding the key down.
+    pub repeat: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum WindowEvent {
+    /// The new framebuffer size in physical pixels.
+    Resized(PhysicalSize<u32>),
+    /// The window moved to a display with another scale factor; a `Resized` with the
+    /// new size follows if it changed.
+    ScaleFactorChanged {
+        scale_factor: f64,
+    },
+    CloseRequested,
+    Focused(bool),
+    DroppedFile(PathBuf),
+    KeyboardInput {
+        input: KeyboardInput,
+    },
+    /// A character of typed text, after the keyboard layout and any input method.
+    ReceivedCharacter(char),
+    ModifiersChanged(ModifiersState),
+    MouseInput {
+        state: ElementState,
+        button: MouseButton,
+    },
+    /// Cursor position in physical pixels from the top left.
+    CursorMoved {
+        position: PhysicalPosition<f64>,
+    },
+    CursorEntered,
+    CursorLeft,
+    MouseWheel {
+        delta: MouseScrollDelta,
+    },
+}
+
+impl WindowEvent {
+    /// The events for a winit event: none for events apps don't see, such as redraws,
+    /// and a key press followed by its text for keyboard input.
+    pub fn from_winit(event: &winit::event::WindowEvent) -> Vec<WindowEvent> {
+        use winit::event::WindowEvent as Winit;
+        use winit::keyboard::PhysicalKey;
+
+        let event = match event {
+            Winit::Resized(size) => WindowEvent::Resized(*size),
+            Winit::ScaleFactorChanged { scale_factor, .. } => WindowEvent::ScaleFactorChanged {
+                scale_factor: *scale_factor,
+            },
+            Winit::CloseRequested => WindowEvent::CloseRequested,
+            Winit::Focused(focused) => WindowEvent::Focused(*focused),
+            Winit::DroppedFile(path) => WindowEvent::DroppedFile(path.clone()),
+            Winit::KeyboardInput { event, .. } => {
+                let key = match event.physical_key {
+                    PhysicalKey::Code(code) => Some(code),
+                    PhysicalKey::Unidentified(_) => None,
+                };
+                let input = WindowEvent::KeyboardInput {
+                    input: KeyboardInput {
+                        key,
+                        state: event.state,
+                        repeat: event.repeat,
+                    },
+                };
+                let text = event
+                    .text
+                    .as_deref()
+                    .filter(|_| event.state == ElementState::Pressed)
+                    .unwrap_or_default();
+                return std::iter::once(input)
+                    .chain(text.chars().map(WindowEvent::ReceivedCharacter))
+                    .collect();
+            }
+            Winit::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(modifiers.state()),
+            Winit::MouseInput { state, button, .. } => WindowEvent::MouseInput {
+                state: *state,
+                button: *button,
+            },
+            Winit::CursorMoved { position, .. } => WindowEvent::CursorMoved {
+                position: *position,
+            },
+            Winit::CursorEntered { .. } => WindowEvent::CursorEntered,
+            Winit::CursorLeft { .. } => WindowEvent::CursorLeft,
+            Winit::MouseWheel { delta, .. } => WindowEvent::MouseWheel { delta: *delta },
+            _ => return Vec::new(),
+        };
+        vec![event]
+    }
+
+    /// Whether this is a press of `key`, not counting repeats.
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        matches!(
+            self,
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    key: Some(pressed),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                },
+            } if *pressed == key
+        )
+    }
+}
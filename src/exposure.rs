@@ -0,0 +1,274 @@
+//! Automatic exposure from a luminance histogram.
+//!
+//! Each frame [`AutoExposure::update`] sorts the pixels of the HDR scene into
+//! [`HISTOGRAM_BINS`] bins of log luminance: work groups count into shared memory, then
+//! add their counts to an `R32UI` image with `imageAtomicAdd`. A second, single work
+//! group averages the histogram, leaving out the darkest and brightest pixels, and eases
+//! the adapted luminance in a 1×1 `R32F` image towards it, faster when the scene gets
+//! brighter than when it gets darker, like an eye. The tonemapper reads that image
+//! directly, so the exposure never round-trips through the CPU.
+//!
+//! [`crate::postprocess::PostProcess::set_auto_exposure`] runs the whole thing before
+//! the passes and feeds [`crate::postprocess::Pass::tonemap`], whose `u_exposure` then
+//! becomes exposure compensation.
+
+use std::cell::Cell;
+
+use anyhow::{anyhow, Result};
+
+use crate::builtins;
+use crate::context;
+use crate::gl;
+use crate::load_store::{self, Barriers, ImageAccess, ImageFormat};
+use crate::shader::Program;
+use crate::texture::Texture;
+
+/// Bins of the luminance histogram. Bin 0 counts black pixels, which say nothing about
+/// exposure; the others split [`ExposureConfig::min_log_luminance`] to
+/// [`ExposureConfig::max_log_luminance`] evenly.
+pub const HISTOGRAM_BINS: usize = 256;
+
+const HISTOGRAM_SHADER: &str = r#"#version 430 core
+layout (local_size_x = 16, local_size_y = 16) in;
+layout (r32ui, binding = 0) uniform uimage2D u_histogram;
+uniform sampler2D u_input;
+uniform float u_min_log_luminance;
+uniform float u_inverse_log_range;
+
+shared uint bins[256];
+
+void main() {
+    bins[gl_LocalInvocationIndex] = 0u;
+    barrier();
+
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    if (all(lessThan(pixel, textureSize(u_input, 0)))) {
+        vec3 color = texelFetch(u_input, pixel, 0).rgb;
+        float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        uint bin = 0u;
+        if (luminance > 1e-5) {
+            float t = (log2(luminance) - u_min_log_luminance) * u_inverse_log_range;
+            bin = uint(clamp(t, 0.0, 1.0) * 254.0) + 1u;
+        }
+        atomicAdd(bins[bin], 1u);
+    }
+    barrier();
+
+    uint count = bins[gl_LocalInvocationIndex];
+    if (count > 0u) {
+        imageAtomicAdd(u_histogram, ivec2(gl_LocalInvocationIndex, 0), count);
+    }
+}
+"#;
+
+const AVERAGE_SHADER: &str = r#"#version 430 core
+layout (local_size_x = 256) in;
+layout (r32ui, binding = 0) uniform uimage2D u_histogram;
+layout (r32f, binding = 1) uniform image2D u_luminance;
+uniform float u_min_log_luminance;
+uniform float u_log_range;
+uniform float u_low_percentile;
+uniform float u_high_percentile;
+uniform float u_speed_up;
+uniform float u_speed_down;
+uniform float u_dt;
+uniform bool u_reset;
+
+shared uint counts[256];
+
+void main() {
+    uint index = gl_LocalInvocationIndex;
+    counts[index] = imageLoad(u_histogram, ivec2(index, 0)).r;
+    // Empty the histogram for the next frame.
+    imageStore(u_histogram, ivec2(index, 0), uvec4(0u));
+    barrier();
+    if (index != 0u) {
+        return;
+    }
+
+    float total = 0.0;
+    for (int bin = 1; bin < 256; ++bin) {
+        total += float(counts[bin]);
+    }
+    float low = total * u_low_percentile;
+    float high = total * u_high_percentile;
+    float below = 0.0;
+    float sum = 0.0;
+    float weight = 0.0;
+    for (int bin = 1; bin < 256; ++bin) {
+        float count = float(counts[bin]);
+        // The pixels of this bin between the two percentiles.
+        float kept = clamp(below + count, low, high) - clamp(below, low, high);
+        below += count;
+        float t = min((float(bin) - 0.5) / 254.0, 1.0);
+        sum += (u_min_log_luminance + t * u_log_range) * kept;
+        weight += kept;
+    }
+
+    float current = imageLoad(u_luminance, ivec2(0)).r;
+    if (weight == 0.0) {
+        return;
+    }
+    float target = exp2(sum / weight);
+    if (u_reset || !(current > 0.0)) {
+        current = target;
+    }
+    float speed = target > current ? u_speed_up : u_speed_down;
+    float adapted = current + (target - current) * (1.0 - exp(-u_dt * speed));
+    imageStore(u_luminance, ivec2(0), vec4(adapted));
+}
+"#;
+
+/// Whether the context supports [`AutoExposure`]: compute shaders and image atomics,
+/// both part of GL 4.3.
+pub fn is_supported() -> bool {
+    context::version() >= (4, 3) && gl::DispatchCompute::is_loaded() && load_store::is_supported()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ExposureConfig {
+    /// Log2 luminance of the darkest histogram bin; darker pixels count towards it.
+    pub min_log_luminance: f32,
+    /// Log2 luminance of the brightest bin; brighter pixels count towards it.
+    pub max_log_luminance: f32,
+    /// Fraction of the darkest pixels left out of the average, e.g. deep shadows.
+    pub low_percentile: f32,
+    /// Fraction of pixels below which the average stops, leaving out the brightest ones,
+    /// e.g. the sun and its reflections.
+    pub high_percentile: f32,
+    /// The luminance the average is exposed to: 0.18 puts it at middle gray.
+    pub key: f32,
+    /// Adaptation rates in 1/seconds when the scene gets brighter and darker. The
+    /// adapted luminance covers about 63% of the way to the target in `1 / rate`
+    /// seconds.
+    pub speed_up: f32,
+    pub speed_down: f32,
+}
+
+impl Default for ExposureConfig {
+    fn default() -> Self {
+        ExposureConfig {
+            min_log_luminance: -10.0,
+            max_log_luminance: 6.0,
+            low_percentile: 0.5,
+            high_percentile: 0.95,
+            key: 0.18,
+            speed_up: 3.0,
+            speed_down: 1.0,
+        }
+    }
+}
+
+pub struct AutoExposure {
+    pub config: ExposureConfig,
+    histogram_program: Program,
+    average_program: Program,
+    histogram: Texture,
+    luminance: Texture,
+    /// [`builtins::Builtins::time`] at the last update; `None` jumps straight to the
+    /// target on the next one.
+    last_time: Cell<Option<f32>>,
+}
+
+impl AutoExposure {
+    pub fn new(config: ExposureConfig) -> Result<AutoExposure> {
+        if !is_supported() {
+            return Err(anyhow!("Auto exposure requires OpenGL 4.3"));
+        }
+        let histogram = Texture::new(gl::TEXTURE_2D)?;
+        histogram.bind();
+        histogram.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        histogram.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        histogram.image_2d(
+            0,
+            gl::R32UI,
+            HISTOGRAM_BINS as i32,
+            1,
+            gl::RED_INTEGER,
+            gl::UNSIGNED_INT,
+            Some(&[0; HISTOGRAM_BINS * 4]),
+        );
+        histogram.unbind();
+        histogram.label("luminance histogram");
+
+        let luminance = Texture::new(gl::TEXTURE_2D)?;
+        luminance.bind();
+        luminance.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        luminance.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        luminance.image_2d(0, gl::R32F, 1, 1, gl::RED, gl::FLOAT, Some(&[0; 4]));
+        luminance.unbind();
+        luminance.label("adapted luminance");
+
+        Ok(AutoExposure {
+            config,
+            histogram_program: Program::from_compute(HISTOGRAM_SHADER)?,
+            average_program: Program::from_compute(AVERAGE_SHADER)?,
+            histogram,
+            luminance,
+            last_time: Cell::new(None),
+        })
+    }
+
+    /// Builds the histogram of `hdr` and adapts the luminance by the time passed since
+    /// the last update.
+    pub fn update(&self, hdr: &Texture, width: i32, height: i32) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "auto exposure").entered();
+        let config = &self.config;
+        let log_range = config.max_log_luminance - config.min_log_luminance;
+        let time = builtins::get().time;
+        let dt = self.last_time.replace(Some(time)).map(|last| time - last);
+
+        self.histogram_program.use_program();
+        hdr.bind_unit(0);
+        self.histogram_program.set_int("u_input", 0);
+        self.histogram_program
+            .set_float("u_min_log_luminance", config.min_log_luminance);
+        self.histogram_program
+            .set_float("u_inverse_log_range", 1.0 / log_range);
+        self.histogram
+            .bind_image(0, 0, None, ImageAccess::ReadWrite, ImageFormat::R32UI)?;
+        unsafe {
+            gl::DispatchCompute((width as u32).div_ceil(16), (height as u32).div_ceil(16), 1);
+        }
+        load_store::memory_barrier(Barriers::SHADER_IMAGE_ACCESS);
+
+        let program = &self.average_program;
+        program.use_program();
+        program.set_float("u_min_log_luminance", config.min_log_luminance);
+        program.set_float("u_log_range", log_range);
+        program.set_float("u_low_percentile", config.low_percentile);
+        program.set_float("u_high_percentile", config.high_percentile);
+        program.set_float("u_speed_up", config.speed_up);
+        program.set_float("u_speed_down", config.speed_down);
+        program.set_float("u_dt", dt.unwrap_or(0.0).max(0.0));
+        program.set_int("u_reset", dt.is_none() as i32);
+        self.luminance
+            .bind_image(1, 0, None, ImageAccess::ReadWrite, ImageFormat::R32F)?;
+        unsafe {
+            gl::DispatchCompute(1, 1, 1);
+        }
+        load_store::memory_barrier(Barriers::SHADER_IMAGE_ACCESS | Barriers::TEXTURE_FETCH);
+        Ok(())
+    }
+
+    /// Makes the next [`AutoExposure::update`] jump to the scene's luminance instead of
+    /// adapting, e.g. after a camera cut.
+    pub fn reset(&self) {
+        self.last_time.set(None);
+    }
+
+    /// The 1×1 `R32F` texture holding the adapted luminance.
+    pub fn luminance(&self) -> &Texture {
+        &self.luminance
+    }
+
+    /// Binds the adapted luminance to texture `unit` and sets `u_auto_exposure`,
+    /// `u_luminance` and `u_exposure_key` on `program`, which must be in use. The
+    /// exposure is `u_exposure_key / texelFetch(u_luminance, ivec2(0), 0).r`.
+    pub fn apply(&self, program: &Program, unit: u32) {
+        self.luminance.bind_unit(unit);
+        program.set_int("u_auto_exposure", 1);
+        program.set_int("u_luminance", unit as i32);
+        program.set_float("u_exposure_key", self.config.key);
+    }
+}
@@ -0,0 +1,185 @@
+//! Transform feedback: capturing vertex shader outputs into buffers.
+//!
+//! [`FeedbackPingPong`] runs a capture program over one buffer into another and swaps
+//! them, which is enough for iterative GPU simulation (particles, cloth, flocking) on
+//! GL 3.3 hardware without compute shaders. Programs for it are built with
+//! [`crate::shader::Program::from_feedback_vertex`].
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::context::{Deletion, GlContext};
+use crate::debug;
+use crate::gl;
+use crate::shader::Program;
+use crate::validate;
+
+pub struct TransformFeedback(pub(crate) gl::types::GLuint, GlContext);
+
+impl TransformFeedback {
+    pub fn new() -> Result<TransformFeedback> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            gl::GenTransformFeedbacks(1, &mut id);
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create transform feedback"))
+        } else {
+            Ok(TransformFeedback(id, gl_context))
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the transform feedback object in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::TRANSFORM_FEEDBACK, self.0, label);
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, self.0);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindTransformFeedback(gl::TRANSFORM_FEEDBACK, 0);
+        }
+    }
+
+    /// Attaches `buffer` to capture slot `index` of the bound transform feedback object.
+    pub fn bind_buffer(&self, index: u32, buffer: &Buffer) {
+        buffer.bind_base(gl::TRANSFORM_FEEDBACK_BUFFER, index);
+    }
+
+    /// Starts capturing. `primitive` is `GL_POINTS`, `GL_LINES` or `GL_TRIANGLES` and
+    /// must match the draws issued until [`TransformFeedback::end`].
+    pub fn begin(&self, primitive: gl::types::GLenum) {
+        unsafe {
+            gl::BeginTransformFeedback(primitive);
+        }
+    }
+
+    pub fn end(&self) {
+        unsafe {
+            gl::EndTransformFeedback();
+        }
+    }
+
+    pub fn pause(&self) {
+        unsafe {
+            gl::PauseTransformFeedback();
+        }
+    }
+
+    pub fn resume(&self) {
+        unsafe {
+            gl::ResumeTransformFeedback();
+        }
+    }
+
+    /// Draws as many vertices as were last captured into this object, without reading
+    /// the count back to the CPU.
+    pub fn draw(&self, mode: gl::types::GLenum) {
+        validate::draw("transform feedback");
+        unsafe {
+            gl::DrawTransformFeedback(mode, self.0);
+        }
+    }
+}
+
+impl Drop for TransformFeedback {
+    fn drop(&mut self) {
+        self.1.delete(Deletion::TransformFeedback(self.0));
+    }
+}
+
+/// Two vertex buffers updated alternately by a transform-feedback program.
+///
+/// Each [`FeedbackPingPong::step`] draws the current buffer as points with rasterization
+/// disabled, capturing the program's outputs into the other buffer, then swaps. The
+/// captured layout must match the input layout set up by `attributes`.
+pub struct FeedbackPingPong {
+    buffers: [Buffer; 2],
+    vertex_arrays: [VertexArray; 2],
+    feedback: [TransformFeedback; 2],
+    current: usize,
+    vertex_count: i32,
+}
+
+impl FeedbackPingPong {
+    /// Creates both buffers filled with `initial`, holding `vertex_count` vertices.
+    /// `attributes` is called once per buffer, with its vertex array and
+    /// `GL_ARRAY_BUFFER` bound, to declare the vertex attributes.
+    pub fn new<F: Fn()>(
+        initial: &[u8],
+        vertex_count: usize,
+        attributes: F,
+    ) -> Result<FeedbackPingPong> {
+        let buffers = [Buffer::new()?, Buffer::new()?];
+        let vertex_arrays = [VertexArray::new()?, VertexArray::new()?];
+        let feedback = [TransformFeedback::new()?, TransformFeedback::new()?];
+        for i in 0..2 {
+            vertex_arrays[i].bind();
+            buffers[i].bind(gl::ARRAY_BUFFER);
+            buffers[i].data(gl::ARRAY_BUFFER, initial, gl::DYNAMIC_COPY);
+            attributes();
+            vertex_arrays[i].unbind();
+            buffers[i].unbind(gl::ARRAY_BUFFER);
+
+            // Feedback object i writes into the buffer that step reads from next.
+            feedback[i].bind();
+            feedback[i].bind_buffer(0, &buffers[1 - i]);
+            feedback[i].unbind();
+        }
+        Ok(FeedbackPingPong {
+            buffers,
+            vertex_arrays,
+            feedback,
+            current: 0,
+            vertex_count: vertex_count as i32,
+        })
+    }
+
+    /// Runs `program` over the current buffer into the other one and swaps them. Set the
+    /// program's uniforms before calling.
+    pub fn step(&mut self, program: &Program) {
+        program.use_program();
+        let feedback = &self.feedback[self.current];
+        unsafe {
+            gl::Enable(gl::RASTERIZER_DISCARD);
+        }
+        feedback.bind();
+        self.vertex_arrays[self.current].bind();
+        validate::draw("a transform feedback step");
+        feedback.begin(gl::POINTS);
+        unsafe {
+            gl::DrawArrays(gl::POINTS, 0, self.vertex_count);
+        }
+        feedback.end();
+        self.vertex_arrays[self.current].unbind();
+        feedback.unbind();
+        unsafe {
+            gl::Disable(gl::RASTERIZER_DISCARD);
+        }
+        self.current = 1 - self.current;
+    }
+
+    /// The buffer holding the latest results.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffers[self.current]
+    }
+
+    /// The vertex array reading the latest results, for drawing them.
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_arrays[self.current]
+    }
+
+    pub fn vertex_count(&self) -> i32 {
+        self.vertex_count
+    }
+}
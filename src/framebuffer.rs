@@ -0,0 +1,700 @@
+use std::cell::Cell;
+
+use anyhow::{anyhow, Result};
+
+use crate::context::{self, Deletion, GlContext};
+use crate::debug;
+use crate::depth;
+use crate::dsa;
+use crate::gl;
+use crate::image::{Image, TexelImage};
+use crate::memory::{self, Resource};
+use crate::scoped::{self, Binding};
+use crate::state::StateCache;
+use crate::texture::{self, TexelClass, Texture};
+use crate::viewport::Viewport;
+
+/// Reads the `RGBA8` pixels of `viewport` from the bound read framebuffer (the back
+/// buffer of the window by default) into an image with top-down rows. Stalls until
+/// rendering has finished.
+pub fn read_pixels(viewport: Viewport) -> Image {
+    let mut image = Image {
+        width: viewport.width as u32,
+        height: viewport.height as u32,
+        pixels: vec![0; viewport.width as usize * viewport.height as usize * 4],
+    };
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image.pixels.as_mut_ptr().cast(),
+        );
+    }
+    image.flip_vertically();
+    image
+}
+
+pub struct Framebuffer(pub(crate) gl::types::GLuint, GlContext);
+
+impl Framebuffer {
+    pub fn new() -> Result<Framebuffer> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateFramebuffers(1, &mut id);
+            } else {
+                gl::GenFramebuffers(1, &mut id);
+            }
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create framebuffer"))
+        } else {
+            Ok(Framebuffer(id, gl_context))
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the framebuffer in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::FRAMEBUFFER, self.0, label);
+    }
+
+    pub fn bind(&self, target: gl::types::GLenum) {
+        unsafe {
+            gl::BindFramebuffer(target, self.0);
+        }
+    }
+
+    pub fn unbind(&self, target: gl::types::GLenum) {
+        Framebuffer::bind_default(target);
+    }
+
+    /// Binds the framebuffer to `target` through `cache` until the guard is dropped, then
+    /// rebinds the framebuffers the cache had bound before. See [`crate::scoped`].
+    pub fn bind_scoped<'a>(
+        &'a self,
+        cache: &'a mut StateCache,
+        target: gl::types::GLenum,
+    ) -> Binding<'a> {
+        scoped::bind_framebuffer(cache, target, self.0)
+    }
+
+    /// Binds the window's default framebuffer to `target`.
+    pub fn bind_default(target: gl::types::GLenum) {
+        unsafe {
+            gl::BindFramebuffer(target, 0);
+        }
+    }
+
+    /// Attaches mip `level` of `texture` to the framebuffer bound to `target`.
+    pub fn attach_texture(
+        &self,
+        target: gl::types::GLenum,
+        attachment: gl::types::GLenum,
+        texture: &Texture,
+        level: i32,
+    ) {
+        // glNamedFramebufferTexture attaches cube maps and arrays as layered.
+        let plain_2d = matches!(
+            texture.target(),
+            gl::TEXTURE_2D | gl::TEXTURE_2D_MULTISAMPLE
+        );
+        unsafe {
+            if dsa::is_available() && plain_2d {
+                gl::NamedFramebufferTexture(self.0, attachment, texture.id(), level);
+            } else {
+                gl::FramebufferTexture2D(target, attachment, texture.target(), texture.id(), level);
+            }
+        }
+    }
+
+    /// Attaches face `face` (see [`Texture::image_cube_face`]) of mip `level` of the cube
+    /// map `texture` to the framebuffer bound to `target`.
+    pub fn attach_cube_face(
+        &self,
+        target: gl::types::GLenum,
+        attachment: gl::types::GLenum,
+        texture: &Texture,
+        face: u32,
+        level: i32,
+    ) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedFramebufferTextureLayer(
+                    self.0,
+                    attachment,
+                    texture.id(),
+                    level,
+                    face as i32,
+                );
+            } else {
+                gl::FramebufferTexture2D(
+                    target,
+                    attachment,
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                    texture.id(),
+                    level,
+                );
+            }
+        }
+    }
+
+    /// Attaches all layers of mip `level` of the cube map, array or 3D `texture` to the
+    /// framebuffer bound to `target`, for a geometry shader to select with `gl_Layer`.
+    pub fn attach_layered(
+        &self,
+        target: gl::types::GLenum,
+        attachment: gl::types::GLenum,
+        texture: &Texture,
+        level: i32,
+    ) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedFramebufferTexture(self.0, attachment, texture.id(), level);
+            } else {
+                gl::FramebufferTexture(target, attachment, texture.id(), level);
+            }
+        }
+    }
+
+    /// Attaches layer `layer` of mip `level` of the array or 3D `texture` to the
+    /// framebuffer bound to `target`.
+    pub fn attach_texture_layer(
+        &self,
+        target: gl::types::GLenum,
+        attachment: gl::types::GLenum,
+        texture: &Texture,
+        level: i32,
+        layer: i32,
+    ) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedFramebufferTextureLayer(self.0, attachment, texture.id(), level, layer);
+            } else {
+                gl::FramebufferTextureLayer(target, attachment, texture.id(), level, layer);
+            }
+        }
+    }
+
+    /// Attaches `renderbuffer` to the framebuffer bound to `target`.
+    pub fn attach_renderbuffer(
+        &self,
+        target: gl::types::GLenum,
+        attachment: gl::types::GLenum,
+        renderbuffer: &Renderbuffer,
+    ) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedFramebufferRenderbuffer(
+                    self.0,
+                    attachment,
+                    gl::RENDERBUFFER,
+                    renderbuffer.0,
+                );
+            } else {
+                gl::FramebufferRenderbuffer(target, attachment, gl::RENDERBUFFER, renderbuffer.0);
+            }
+        }
+    }
+
+    /// Selects the color attachments written by fragment outputs 0..n of the framebuffer
+    /// bound to `GL_DRAW_FRAMEBUFFER`. Fails without a GL call if the context has fewer
+    /// draw buffers or color attachments.
+    pub fn draw_buffers(&self, attachments: &[gl::types::GLenum]) -> Result<()> {
+        context::limits().check_draw_buffers(attachments)?;
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedFramebufferDrawBuffers(
+                    self.0,
+                    attachments.len() as i32,
+                    attachments.as_ptr(),
+                );
+            } else {
+                gl::DrawBuffers(attachments.len() as i32, attachments.as_ptr());
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the `src` rectangle of this framebuffer to the `dst` rectangle of `target`,
+    /// or of the default framebuffer when `target` is `None`.
+    ///
+    /// `mask` combines `GL_COLOR_BUFFER_BIT`, `GL_DEPTH_BUFFER_BIT` and
+    /// `GL_STENCIL_BUFFER_BIT`; `filter` is `GL_NEAREST` or `GL_LINEAR`, the latter for
+    /// color only. A multisampled source is resolved, which requires equal rectangles.
+    /// Without DSA this leaves both framebuffer bindings at the default.
+    pub fn blit_to(
+        &self,
+        target: Option<&Framebuffer>,
+        src: Viewport,
+        dst: Viewport,
+        mask: gl::types::GLbitfield,
+        filter: gl::types::GLenum,
+    ) {
+        let draw = target.map_or(0, |target| target.0);
+        let (src_x1, src_y1) = (src.x + src.width, src.y + src.height);
+        let (dst_x1, dst_y1) = (dst.x + dst.width, dst.y + dst.height);
+        unsafe {
+            if dsa::is_available() {
+                gl::BlitNamedFramebuffer(
+                    self.0, draw, src.x, src.y, src_x1, src_y1, dst.x, dst.y, dst_x1, dst_y1, mask,
+                    filter,
+                );
+            } else {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.0);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw);
+                gl::BlitFramebuffer(
+                    src.x, src.y, src_x1, src_y1, dst.x, dst.y, dst_x1, dst_y1, mask, filter,
+                );
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            }
+        }
+    }
+
+    /// Checks the completeness of the framebuffer bound to `target`.
+    pub fn check_status(&self, target: gl::types::GLenum) -> Result<()> {
+        let status = unsafe {
+            if dsa::is_available() {
+                gl::CheckNamedFramebufferStatus(self.0, target)
+            } else {
+                gl::CheckFramebufferStatus(target)
+            }
+        };
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            Ok(())
+        } else {
+            Err(anyhow!("Framebuffer is incomplete: 0x{:x}", status))
+        }
+    }
+    /// Reads back color attachment `index`, a texture level or a renderbuffer, converted
+    /// as [`TexelImage`] describes, e.g. to inspect an intermediate render target. Stalls
+    /// until rendering has finished and leaves the default framebuffer bound. Multisampled
+    /// attachments must be resolved first; without DSA texture attachments must be 2D or
+    /// cube map faces.
+    pub fn read_attachment(&self, index: u32) -> Result<TexelImage> {
+        let attachment = gl::COLOR_ATTACHMENT0 + index;
+        self.bind(gl::FRAMEBUFFER);
+        let result = self.read_bound_attachment(index, attachment);
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        result
+    }
+
+    fn read_bound_attachment(
+        &self,
+        index: u32,
+        attachment: gl::types::GLenum,
+    ) -> Result<TexelImage> {
+        let parameter = |name| {
+            let mut value = 0;
+            unsafe {
+                gl::GetFramebufferAttachmentParameteriv(
+                    gl::READ_FRAMEBUFFER,
+                    attachment,
+                    name,
+                    &mut value,
+                );
+            }
+            value
+        };
+        let mut samples = 0;
+        unsafe {
+            gl::GetIntegerv(gl::SAMPLES, &mut samples);
+        }
+        if samples > 0 {
+            return Err(anyhow!("Resolve multisampled attachment {} first", index));
+        }
+        let name = parameter(gl::FRAMEBUFFER_ATTACHMENT_OBJECT_NAME) as gl::types::GLuint;
+        let (width, height, internal_format) =
+            match parameter(gl::FRAMEBUFFER_ATTACHMENT_OBJECT_TYPE) as gl::types::GLenum {
+                gl::TEXTURE => {
+                    let level = parameter(gl::FRAMEBUFFER_ATTACHMENT_TEXTURE_LEVEL);
+                    match parameter(gl::FRAMEBUFFER_ATTACHMENT_TEXTURE_CUBE_MAP_FACE) as u32 {
+                        0 => texture::level_info(name, gl::TEXTURE_2D, gl::TEXTURE_2D, level),
+                        face => texture::level_info(name, gl::TEXTURE_CUBE_MAP, face, level),
+                    }
+                }
+                gl::RENDERBUFFER => {
+                    let (mut width, mut height, mut internal_format) = (0, 0, 0);
+                    unsafe {
+                        gl::BindRenderbuffer(gl::RENDERBUFFER, name);
+                        gl::GetRenderbufferParameteriv(
+                            gl::RENDERBUFFER,
+                            gl::RENDERBUFFER_WIDTH,
+                            &mut width,
+                        );
+                        gl::GetRenderbufferParameteriv(
+                            gl::RENDERBUFFER,
+                            gl::RENDERBUFFER_HEIGHT,
+                            &mut height,
+                        );
+                        gl::GetRenderbufferParameteriv(
+                            gl::RENDERBUFFER,
+                            gl::RENDERBUFFER_INTERNAL_FORMAT,
+                            &mut internal_format,
+                        );
+                        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                    }
+                    (width, height, internal_format as gl::types::GLenum)
+                }
+                _ => return Err(anyhow!("Framebuffer has no color attachment {}", index)),
+            };
+        let class = TexelClass::of(internal_format);
+        unsafe {
+            gl::ReadBuffer(attachment);
+        }
+        let image = texture::read_texels(class, width, height, |format, ty, data| unsafe {
+            gl::ReadPixels(0, 0, width, height, format, ty, data);
+        });
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+        }
+        Ok(image)
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.1.delete(Deletion::Framebuffer(self.0));
+    }
+}
+
+/// Framebuffer-attachable image storage that cannot be sampled, e.g. for depth and
+/// stencil buffers that are only tested against. Cheaper than a texture on some drivers.
+pub struct Renderbuffer(pub(crate) gl::types::GLuint, Cell<usize>, GlContext);
+
+impl Renderbuffer {
+    pub fn new() -> Result<Renderbuffer> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateRenderbuffers(1, &mut id);
+            } else {
+                gl::GenRenderbuffers(1, &mut id);
+            }
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create renderbuffer"))
+        } else {
+            memory::track_object(Resource::Renderbuffer, 1);
+            Ok(Renderbuffer(id, Cell::new(0), gl_context))
+        }
+    }
+
+    /// Creates a renderbuffer with storage allocated, multisampled when `samples > 0`.
+    pub fn with_storage(
+        internal_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+        samples: i32,
+    ) -> Result<Renderbuffer> {
+        let limits = context::limits();
+        limits.check_texture_size(width, height)?;
+        limits.check_samples(samples)?;
+        let renderbuffer = Renderbuffer::new()?;
+        renderbuffer.bind();
+        if samples > 0 {
+            renderbuffer.storage_multisample(samples, internal_format, width, height);
+        } else {
+            renderbuffer.storage(internal_format, width, height);
+        }
+        renderbuffer.unbind();
+        Ok(renderbuffer)
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the renderbuffer in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::RENDERBUFFER, self.0, label);
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.0);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+    }
+
+    /// Binds the renderbuffer through `cache` until the guard is dropped. See
+    /// [`crate::scoped`].
+    pub fn bind_scoped<'a>(&'a self, cache: &'a mut StateCache) -> Binding<'a> {
+        scoped::bind_renderbuffer(cache, self.0)
+    }
+
+    /// Allocates storage; without DSA the renderbuffer must be bound.
+    pub fn storage(&self, internal_format: gl::types::GLenum, width: i32, height: i32) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedRenderbufferStorage(self.0, internal_format, width, height);
+            } else {
+                gl::RenderbufferStorage(gl::RENDERBUFFER, internal_format, width, height);
+            }
+        }
+        self.set_size(1, internal_format, width, height);
+    }
+
+    /// Allocates multisampled storage; without DSA the renderbuffer must be bound.
+    pub fn storage_multisample(
+        &self,
+        samples: i32,
+        internal_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        unsafe {
+            if dsa::is_available() {
+                gl::NamedRenderbufferStorageMultisample(
+                    self.0,
+                    samples,
+                    internal_format,
+                    width,
+                    height,
+                );
+            } else {
+                gl::RenderbufferStorageMultisample(
+                    gl::RENDERBUFFER,
+                    samples,
+                    internal_format,
+                    width,
+                    height,
+                );
+            }
+        }
+        self.set_size(samples, internal_format, width, height);
+    }
+
+    /// Estimated bytes of the renderbuffer's storage.
+    pub fn size(&self) -> usize {
+        self.1.get()
+    }
+
+    fn set_size(&self, samples: i32, internal_format: gl::types::GLenum, width: i32, height: i32) {
+        let pixels = width as usize * height as usize * samples.max(1) as usize;
+        let size = pixels * memory::bytes_per_pixel(internal_format);
+        memory::track_bytes(Resource::Renderbuffer, self.1.replace(size), size);
+    }
+}
+
+impl Drop for Renderbuffer {
+    fn drop(&mut self) {
+        self.2.delete(Deletion::Renderbuffer(self.0));
+        memory::track_bytes(Resource::Renderbuffer, self.1.get(), 0);
+        memory::track_object(Resource::Renderbuffer, -1);
+    }
+}
+
+/// A framebuffer with a sampled color texture and an optional depth texture, which
+/// holds a stencil buffer too for targets made with [`RenderTarget::with_stencil`].
+///
+/// The textures of a multisampled target (see [`RenderTarget::multisampled`]) need
+/// `sampler2DMS`; to use them with ordinary samplers, [`RenderTarget::resolve_to`] a
+/// single-sample target first.
+pub struct RenderTarget {
+    pub framebuffer: Framebuffer,
+    pub color: Texture,
+    pub depth: Option<Texture>,
+    width: i32,
+    height: i32,
+    samples: i32,
+    stencil: bool,
+}
+
+impl RenderTarget {
+    pub fn new(
+        width: i32,
+        height: i32,
+        internal_format: gl::types::GLenum,
+        with_depth: bool,
+    ) -> Result<RenderTarget> {
+        RenderTarget::multisampled(width, height, 0, internal_format, with_depth)
+    }
+
+    /// Like [`RenderTarget::new`] with `samples` samples per pixel. `0` creates ordinary
+    /// textures.
+    pub fn multisampled(
+        width: i32,
+        height: i32,
+        samples: i32,
+        internal_format: gl::types::GLenum,
+        with_depth: bool,
+    ) -> Result<RenderTarget> {
+        RenderTarget::create(width, height, samples, internal_format, with_depth, false)
+    }
+
+    /// Like [`RenderTarget::multisampled`] with a depth texture in
+    /// [`depth::stencil_format`], for stencil masking.
+    pub fn with_stencil(
+        width: i32,
+        height: i32,
+        samples: i32,
+        internal_format: gl::types::GLenum,
+    ) -> Result<RenderTarget> {
+        RenderTarget::create(width, height, samples, internal_format, true, true)
+    }
+
+    fn create(
+        width: i32,
+        height: i32,
+        samples: i32,
+        internal_format: gl::types::GLenum,
+        with_depth: bool,
+        stencil: bool,
+    ) -> Result<RenderTarget> {
+        let limits = context::limits();
+        limits.check_texture_size(width, height)?;
+        limits.check_samples(samples)?;
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+
+        let color = if samples > 0 {
+            let color = Texture::new(gl::TEXTURE_2D_MULTISAMPLE)?;
+            color.bind();
+            color.image_2d_multisample(samples, internal_format, width, height);
+            color
+        } else {
+            let color = Texture::new(gl::TEXTURE_2D)?;
+            color.bind();
+            color.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            color.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            color.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            color.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            color.image_2d(0, internal_format, width, height, gl::RGBA, gl::FLOAT, None);
+            color
+        };
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &color, 0);
+
+        let (depth_format, attachment, format, ty) = if !stencil {
+            (
+                depth::format(),
+                gl::DEPTH_ATTACHMENT,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+            )
+        } else if depth::reversed_z() {
+            (
+                depth::stencil_format(),
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::DEPTH_STENCIL,
+                gl::FLOAT_32_UNSIGNED_INT_24_8_REV,
+            )
+        } else {
+            (
+                depth::stencil_format(),
+                gl::DEPTH_STENCIL_ATTACHMENT,
+                gl::DEPTH_STENCIL,
+                gl::UNSIGNED_INT_24_8,
+            )
+        };
+        let depth = if !with_depth {
+            None
+        } else if samples > 0 {
+            let depth = Texture::new(gl::TEXTURE_2D_MULTISAMPLE)?;
+            depth.bind();
+            depth.image_2d_multisample(samples, depth_format, width, height);
+            framebuffer.attach_texture(gl::FRAMEBUFFER, attachment, &depth, 0);
+            Some(depth)
+        } else {
+            let depth = Texture::new(gl::TEXTURE_2D)?;
+            depth.bind();
+            depth.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            depth.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            depth.image_2d(0, depth_format, width, height, format, ty, None);
+            framebuffer.attach_texture(gl::FRAMEBUFFER, attachment, &depth, 0);
+            Some(depth)
+        };
+        color.unbind();
+
+        let status = framebuffer.check_status(gl::FRAMEBUFFER);
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        Ok(RenderTarget {
+            framebuffer,
+            color,
+            depth,
+            width,
+            height,
+            samples,
+            stencil,
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Samples per pixel; `0` for an ordinary target.
+    pub fn samples(&self) -> i32 {
+        self.samples
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.stencil
+    }
+
+    /// The whole target as a rectangle for [`Framebuffer::blit_to`].
+    pub fn rect(&self) -> Viewport {
+        Viewport::new(0, 0, self.width, self.height)
+    }
+
+    /// Binds the framebuffer for drawing and sets the viewport to cover it.
+    pub fn bind(&self) {
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        unsafe {
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    /// Resolves (or copies) color, and depth and stencil when both targets have them,
+    /// into `target`, which must have the same size.
+    pub fn resolve_to(&self, target: &RenderTarget) {
+        let mut mask = gl::COLOR_BUFFER_BIT;
+        if self.depth.is_some() && target.depth.is_some() {
+            mask |= gl::DEPTH_BUFFER_BIT;
+        }
+        if self.stencil && target.stencil {
+            mask |= gl::STENCIL_BUFFER_BIT;
+        }
+        self.framebuffer.blit_to(
+            Some(&target.framebuffer),
+            self.rect(),
+            target.rect(),
+            mask,
+            gl::NEAREST,
+        );
+    }
+
+    /// Copies the color to the whole default framebuffer of size `width × height`,
+    /// scaling linearly if the sizes differ. Multisampled targets must match the size.
+    pub fn present(&self, width: u32, height: u32) {
+        let dst = Viewport::full(width, height);
+        let filter = if dst == self.rect() {
+            gl::NEAREST
+        } else {
+            gl::LINEAR
+        };
+        self.framebuffer
+            .blit_to(None, self.rect(), dst, gl::COLOR_BUFFER_BIT, filter);
+    }
+}
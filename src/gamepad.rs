@@ -0,0 +1,113 @@
+//! Gamepad input through `gilrs` (feature `gamepad`).
+//!
+//! Call [`crate::input::Input::enable_gamepads`] once; [`crate::input::Input::end_frame`]
+//! then polls every connected controller. Sticks come back with a radial dead zone
+//! applied, and buttons can be bound to keys so keyboard controls work unchanged: by
+//! default the D-pad acts as the arrow keys.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use gilrs::{Axis, EventType, Gilrs};
+
+pub use gilrs::Button;
+
+use crate::event::KeyCode;
+use crate::math::Vec2;
+
+/// Stick deflection below which input is ignored, as a fraction of full travel.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+/// Selects a stick or an analog trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// The connection to the platform's gamepad API.
+pub struct Gamepads {
+    gilrs: Gilrs,
+}
+
+impl fmt::Debug for Gamepads {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Gamepads")
+            .field("connected", &self.gilrs.gamepads().count())
+            .finish()
+    }
+}
+
+impl Gamepads {
+    pub fn new() -> Result<Gamepads> {
+        let gilrs = Gilrs::new().map_err(|e| anyhow!("Failed to open gamepads: {}", e))?;
+        Ok(Gamepads { gilrs })
+    }
+
+    /// Applies pending events of all controllers to `state`. Controllers share one state,
+    /// so any of them can drive the app.
+    pub(crate) fn poll(&mut self, state: &mut GamepadState) {
+        while let Some(event) = self.gilrs.next_event() {
+            match event.event {
+                EventType::ButtonPressed(button, _) => {
+                    state.buttons.insert(button);
+                }
+                EventType::ButtonReleased(button, _) => {
+                    state.buttons.remove(&button);
+                }
+                EventType::AxisChanged(axis, value, _) => match axis {
+                    Axis::LeftStickX => state.sticks[0].x = value,
+                    Axis::LeftStickY => state.sticks[0].y = value,
+                    Axis::RightStickX => state.sticks[1].x = value,
+                    Axis::RightStickY => state.sticks[1].y = value,
+                    _ => (),
+                },
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    state.triggers[0] = value
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    state.triggers[1] = value
+                }
+                EventType::Disconnected => *state = GamepadState::default(),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Buttons held and axis positions, merged over all controllers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct GamepadState {
+    pub(crate) buttons: HashSet<Button>,
+    /// Left and right sticks, `y` pointing up.
+    pub(crate) sticks: [Vec2; 2],
+    /// Left and right analog triggers, from `0.0` to `1.0`.
+    pub(crate) triggers: [f32; 2],
+}
+
+/// Which keys gamepad buttons stand in for.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Bindings(pub(crate) Vec<(Button, KeyCode)>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings(vec![
+            (Button::DPadUp, KeyCode::ArrowUp),
+            (Button::DPadDown, KeyCode::ArrowDown),
+            (Button::DPadLeft, KeyCode::ArrowLeft),
+            (Button::DPadRight, KeyCode::ArrowRight),
+        ])
+    }
+}
+
+/// Zeroes `value` inside `dead_zone` and rescales the rest so output still starts at 0
+/// and reaches 1 at full deflection.
+pub fn apply_dead_zone(value: Vec2, dead_zone: f32) -> Vec2 {
+    let length = value.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    value / length * scaled
+}
@@ -0,0 +1,560 @@
+//! Shared vertex and index storage for many meshes.
+//!
+//! A [`GeometryPool`] keeps the vertices, tangents and indices of many meshes in three
+//! large buffers behind one vertex array, so a scene of thousands of meshes owns three
+//! buffer objects instead of thousands, and drawing them binds one vertex array.
+//! [`GeometryPool::insert`] takes the first free range that fits from each buffer's free
+//! list, doubling the buffer when none does; [`GeometryPool::remove`] hands the ranges
+//! back, merged with free neighbours. Indices stay relative to each mesh's first vertex
+//! and are drawn with a base vertex, so the pool needs GL 3.2 or ES 3.2.
+//!
+//! Removing meshes leaves holes. [`GeometryPool::stats`] reports how scattered the free
+//! space is, and [`GeometryPool::compact`] moves every mesh to the front when that gets
+//! in the way of new ones.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::culling::Aabb;
+use crate::gl;
+use crate::indirect;
+use crate::math::Vec3;
+use crate::mesh::{Submesh, Vertex, TANGENT_LOCATION};
+use crate::primitives::Geometry;
+use crate::state::StateCache;
+
+/// Vertices and indices a pool starts with room for unless given otherwise.
+pub const DEFAULT_VERTICES: usize = 1 << 16;
+pub const DEFAULT_INDICES: usize = 1 << 18;
+
+const VERTEX_SIZE: usize = std::mem::size_of::<Vertex>();
+const TANGENT_SIZE: usize = std::mem::size_of::<[f32; 4]>();
+const INDEX_SIZE: usize = std::mem::size_of::<u32>();
+
+/// A mesh stored in a [`GeometryPool`]. Stays valid across growing and compaction until
+/// removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GeometryHandle(u32);
+
+#[derive(Clone, Copy, Debug)]
+struct Allocation {
+    first_vertex: usize,
+    vertex_count: usize,
+    first_index: usize,
+    index_count: usize,
+    bounds: Aabb,
+}
+
+/// Free ranges of one buffer, in elements, sorted by start and never adjacent.
+#[derive(Debug)]
+struct FreeList {
+    capacity: usize,
+    free: Vec<(usize, usize)>,
+}
+
+impl FreeList {
+    fn new(capacity: usize) -> FreeList {
+        FreeList {
+            capacity,
+            free: vec![(0, capacity)],
+        }
+    }
+
+    /// Takes `len` elements from the first range that fits.
+    fn allocate(&mut self, len: usize) -> Option<usize> {
+        let i = self.free.iter().position(|&(_, free)| free >= len)?;
+        let (start, free) = self.free[i];
+        if free == len {
+            self.free.remove(i);
+        } else {
+            self.free[i] = (start + len, free - len);
+        }
+        Some(start)
+    }
+
+    fn release(&mut self, start: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let i = self.free.partition_point(|&(free, _)| free < start);
+        self.free.insert(i, (start, len));
+        // Merge with the following range, then with the preceding one.
+        if i + 1 < self.free.len() && start + len == self.free[i + 1].0 {
+            self.free[i].1 += self.free.remove(i + 1).1;
+        }
+        if i > 0 && self.free[i - 1].0 + self.free[i - 1].1 == start {
+            self.free[i - 1].1 += self.free.remove(i).1;
+        }
+    }
+
+    fn grow(&mut self, capacity: usize) {
+        let old = std::mem::replace(&mut self.capacity, capacity);
+        self.release(old, capacity - old);
+    }
+
+    fn free(&self) -> usize {
+        self.free.iter().map(|&(_, len)| len).sum()
+    }
+
+    fn largest(&self) -> usize {
+        self.free.iter().map(|&(_, len)| len).max().unwrap_or(0)
+    }
+}
+
+/// Occupancy of a [`GeometryPool`], in vertices and indices.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PoolStats {
+    pub meshes: usize,
+    pub vertex_capacity: usize,
+    pub vertices_used: usize,
+    pub index_capacity: usize,
+    pub indices_used: usize,
+    /// Separate free ranges; more than one means the free space is fragmented.
+    pub free_vertex_ranges: usize,
+    pub free_index_ranges: usize,
+    /// The most vertices and indices one more mesh could use without growing.
+    pub largest_free_vertices: usize,
+    pub largest_free_indices: usize,
+    /// How often a buffer has been reallocated because nothing fit.
+    pub growths: u32,
+    pub compactions: u32,
+}
+
+impl PoolStats {
+    /// The share of free space outside the largest free range, for vertices or indices,
+    /// whichever is worse: 0 when compacting wouldn't help, close to 1 when the free
+    /// space is mostly slivers.
+    pub fn fragmentation(&self) -> f32 {
+        let fragmentation = |capacity: usize, used: usize, largest: usize| {
+            let free = capacity - used;
+            if free == 0 {
+                0.0
+            } else {
+                1.0 - largest as f32 / free as f32
+            }
+        };
+        fragmentation(
+            self.vertex_capacity,
+            self.vertices_used,
+            self.largest_free_vertices,
+        )
+        .max(fragmentation(
+            self.index_capacity,
+            self.indices_used,
+            self.largest_free_indices,
+        ))
+    }
+}
+
+/// Standard [`Vertex`] meshes with tangents, suballocated from shared buffers and drawn
+/// as triangles. Skinned meshes need their own [`crate::mesh::Mesh`].
+pub struct GeometryPool {
+    vertex_array: VertexArray,
+    vertices: Buffer,
+    tangents: Buffer,
+    indices: Buffer,
+    vertex_space: FreeList,
+    index_space: FreeList,
+    allocations: HashMap<GeometryHandle, Allocation>,
+    next_handle: u32,
+    growths: u32,
+    compactions: u32,
+    label: String,
+}
+
+impl GeometryPool {
+    /// Creates a pool with room for `vertices` vertices and `indices` indices, which
+    /// grows as needed.
+    pub fn new(vertices: usize, indices: usize) -> Result<GeometryPool> {
+        if !indirect::base_vertex_supported() {
+            return Err(anyhow!("Geometry pools need GL 3.2 or ES 3.2"));
+        }
+        let (vertices, indices) = (vertices.max(1), indices.max(1));
+        let pool = GeometryPool {
+            vertex_array: VertexArray::new()?,
+            vertices: storage(vertices * VERTEX_SIZE)?,
+            tangents: storage(vertices * TANGENT_SIZE)?,
+            indices: storage(indices * INDEX_SIZE)?,
+            vertex_space: FreeList::new(vertices),
+            index_space: FreeList::new(indices),
+            allocations: HashMap::new(),
+            next_handle: 0,
+            growths: 0,
+            compactions: 0,
+            label: "geometry pool".into(),
+        };
+        pool.set_attributes();
+        Ok(pool)
+    }
+
+    /// Points the vertex array at the current buffers, which are new after growing or
+    /// compacting.
+    fn set_attributes(&self) {
+        let vertex_array = &self.vertex_array;
+        let stride = VERTEX_SIZE as i32;
+        vertex_array.element_buffer(&self.indices);
+        vertex_array.attribute(0, &self.vertices, 3, gl::FLOAT, false, stride, 0);
+        vertex_array.attribute(1, &self.vertices, 3, gl::FLOAT, false, stride, 12);
+        vertex_array.attribute(2, &self.vertices, 2, gl::FLOAT, false, stride, 24);
+        vertex_array.attribute(TANGENT_LOCATION, &self.tangents, 4, gl::FLOAT, false, 16, 0);
+        self.apply_label();
+    }
+
+    /// Labels the vertex array and buffers as `label`, `label vertices`, `label tangents`
+    /// and `label indices` for graphics debuggers, including buffers replaced later.
+    pub fn label(&mut self, label: &str) {
+        self.label = label.to_string();
+        self.apply_label();
+    }
+
+    fn apply_label(&self) {
+        let label = &self.label;
+        self.vertex_array.label(label);
+        self.vertices.label(&format!("{} vertices", label));
+        self.tangents.label(&format!("{} tangents", label));
+        self.indices.label(&format!("{} indices", label));
+    }
+
+    /// Copies `geometry` into the pool, growing it if no free range fits. Growing
+    /// rebinds vertex arrays behind any [`StateCache`], so invalidate caches afterwards.
+    pub fn insert(&mut self, geometry: &Geometry) -> Result<GeometryHandle> {
+        let (vertices, indices) = (&geometry.vertices, &geometry.indices);
+        if geometry.tangents.len() != vertices.len() {
+            return Err(anyhow!(
+                "{} tangents for {} vertices",
+                geometry.tangents.len(),
+                vertices.len()
+            ));
+        }
+        if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertices.len()) {
+            return Err(anyhow!(
+                "Index {} out of {} vertices",
+                index,
+                vertices.len()
+            ));
+        }
+
+        let first_vertex = self.allocate_vertices(vertices.len())?;
+        let first_index = match self.allocate_indices(indices.len()) {
+            Ok(first) => first,
+            Err(err) => {
+                self.vertex_space.release(first_vertex, vertices.len());
+                return Err(err);
+            }
+        };
+
+        write(
+            &self.vertices,
+            first_vertex * VERTEX_SIZE,
+            bytemuck::cast_slice(vertices),
+        );
+        write(
+            &self.tangents,
+            first_vertex * TANGENT_SIZE,
+            bytemuck::cast_slice(&geometry.tangents),
+        );
+        write(
+            &self.indices,
+            first_index * INDEX_SIZE,
+            bytemuck::cast_slice(indices),
+        );
+
+        let handle = GeometryHandle(self.next_handle);
+        self.next_handle += 1;
+        self.allocations.insert(
+            handle,
+            Allocation {
+                first_vertex,
+                vertex_count: vertices.len(),
+                first_index,
+                index_count: indices.len(),
+                bounds: Aabb::from_points(vertices.iter().map(|v| Vec3::from(v.position))),
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Takes `len` vertices from the free list, growing the vertex and tangent buffers if
+    /// nothing fits.
+    fn allocate_vertices(&mut self, len: usize) -> Result<usize> {
+        if let Some(first) = self.vertex_space.allocate(len) {
+            return Ok(first);
+        }
+        let capacity = grown(self.vertex_space.capacity, len);
+        grow(&mut self.vertices, capacity * VERTEX_SIZE)?;
+        grow(&mut self.tangents, capacity * TANGENT_SIZE)?;
+        self.vertex_space.grow(capacity);
+        self.growths += 1;
+        self.set_attributes();
+        Ok(self.vertex_space.allocate(len).unwrap())
+    }
+
+    fn allocate_indices(&mut self, len: usize) -> Result<usize> {
+        if let Some(first) = self.index_space.allocate(len) {
+            return Ok(first);
+        }
+        let capacity = grown(self.index_space.capacity, len);
+        grow(&mut self.indices, capacity * INDEX_SIZE)?;
+        self.index_space.grow(capacity);
+        self.growths += 1;
+        self.set_attributes();
+        Ok(self.index_space.allocate(len).unwrap())
+    }
+
+    /// Frees the ranges of `handle` for later inserts. Returns false if it was already
+    /// removed.
+    pub fn remove(&mut self, handle: GeometryHandle) -> bool {
+        let Some(allocation) = self.allocations.remove(&handle) else {
+            return false;
+        };
+        self.vertex_space
+            .release(allocation.first_vertex, allocation.vertex_count);
+        self.index_space
+            .release(allocation.first_index, allocation.index_count);
+        true
+    }
+
+    pub fn contains(&self, handle: GeometryHandle) -> bool {
+        self.allocations.contains_key(&handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.allocations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allocations.is_empty()
+    }
+
+    /// Object-space bounds of a mesh's vertices.
+    pub fn bounds(&self, handle: GeometryHandle) -> Option<Aabb> {
+        self.allocations.get(&handle).map(|a| a.bounds)
+    }
+
+    /// Where a mesh lives in the shared buffers, e.g. for a
+    /// [`crate::indirect::DrawElementsIndirectCommand`]. Changes when the pool is
+    /// compacted.
+    pub fn submesh(&self, handle: GeometryHandle) -> Option<Submesh> {
+        self.allocations.get(&handle).map(|a| Submesh {
+            first_index: a.first_index as u32,
+            index_count: a.index_count as i32,
+            base_vertex: a.first_vertex as i32,
+            material: None,
+        })
+    }
+
+    /// The vertex array shared by every mesh in the pool, e.g. for adding per-instance
+    /// attributes. Growing and compacting re-point its mesh attributes but keep its id.
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    /// Draws one mesh, binding the shared vertex array through `cache` and leaving it
+    /// bound for the next one. Does nothing for removed handles.
+    pub fn draw_cached(&self, handle: GeometryHandle, cache: &mut StateCache) {
+        let Some(submesh) = self.submesh(handle) else {
+            return;
+        };
+        cache.bind_vertex_array(&self.vertex_array);
+        indirect::draw_elements_base_vertex(
+            gl::TRIANGLES,
+            submesh.index_count,
+            submesh.first_index,
+            submesh.base_vertex,
+        );
+    }
+
+    /// Draws one mesh. Does nothing for removed handles.
+    pub fn draw(&self, handle: GeometryHandle) {
+        let Some(submesh) = self.submesh(handle) else {
+            return;
+        };
+        self.vertex_array.bind();
+        indirect::draw_elements_base_vertex(
+            gl::TRIANGLES,
+            submesh.index_count,
+            submesh.first_index,
+            submesh.base_vertex,
+        );
+        self.vertex_array.unbind();
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            meshes: self.allocations.len(),
+            vertex_capacity: self.vertex_space.capacity,
+            vertices_used: self.vertex_space.capacity - self.vertex_space.free(),
+            index_capacity: self.index_space.capacity,
+            indices_used: self.index_space.capacity - self.index_space.free(),
+            free_vertex_ranges: self.vertex_space.free.len(),
+            free_index_ranges: self.index_space.free.len(),
+            largest_free_vertices: self.vertex_space.largest(),
+            largest_free_indices: self.index_space.largest(),
+            growths: self.growths,
+            compactions: self.compactions,
+        }
+    }
+
+    /// Moves every mesh to the front of the buffers, in their current order, leaving the
+    /// free space in one range at the end. The copies stay on the GPU; handles stay
+    /// valid but [`GeometryPool::submesh`] ranges change. Like growing, this rebinds
+    /// vertex arrays behind any [`StateCache`].
+    pub fn compact(&mut self) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "geometry pool compaction").entered();
+        let vertices = storage(self.vertices.size())?;
+        let tangents = storage(self.tangents.size())?;
+        let indices = storage(self.indices.size())?;
+
+        let mut by_vertex: Vec<&mut Allocation> = self.allocations.values_mut().collect();
+        by_vertex.sort_by_key(|a| a.first_vertex);
+        let mut next_vertex = 0;
+        for allocation in &mut by_vertex {
+            let (from, count) = (allocation.first_vertex, allocation.vertex_count);
+            self.vertices.copy_to(
+                &vertices,
+                from * VERTEX_SIZE,
+                next_vertex * VERTEX_SIZE,
+                count * VERTEX_SIZE,
+            );
+            self.tangents.copy_to(
+                &tangents,
+                from * TANGENT_SIZE,
+                next_vertex * TANGENT_SIZE,
+                count * TANGENT_SIZE,
+            );
+            allocation.first_vertex = next_vertex;
+            next_vertex += count;
+        }
+
+        let mut by_index = by_vertex;
+        by_index.sort_by_key(|a| a.first_index);
+        let mut next_index = 0;
+        for allocation in &mut by_index {
+            let (from, count) = (allocation.first_index, allocation.index_count);
+            self.indices.copy_to(
+                &indices,
+                from * INDEX_SIZE,
+                next_index * INDEX_SIZE,
+                count * INDEX_SIZE,
+            );
+            allocation.first_index = next_index;
+            next_index += count;
+        }
+
+        self.vertices = vertices;
+        self.tangents = tangents;
+        self.indices = indices;
+        self.vertex_space = compacted(self.vertex_space.capacity, next_vertex);
+        self.index_space = compacted(self.index_space.capacity, next_index);
+        self.compactions += 1;
+        self.set_attributes();
+        Ok(())
+    }
+}
+
+fn storage(size: usize) -> Result<Buffer> {
+    let buffer = Buffer::new()?;
+    buffer.bind(gl::COPY_WRITE_BUFFER);
+    buffer.allocate(gl::COPY_WRITE_BUFFER, size, gl::STATIC_DRAW);
+    buffer.unbind(gl::COPY_WRITE_BUFFER);
+    Ok(buffer)
+}
+
+/// Replaces `buffer` with one of `size` bytes holding its contents.
+fn grow(buffer: &mut Buffer, size: usize) -> Result<()> {
+    let grown = storage(size)?;
+    buffer.copy_to(&grown, 0, 0, buffer.size());
+    *buffer = grown;
+    Ok(())
+}
+
+/// At least double `capacity`, enough for `len` more elements even with no free space
+/// at the end.
+fn grown(capacity: usize, len: usize) -> usize {
+    (capacity * 2).max((capacity + len).next_power_of_two())
+}
+
+fn compacted(capacity: usize, used: usize) -> FreeList {
+    let mut space = FreeList {
+        capacity,
+        free: Vec::new(),
+    };
+    space.release(used, capacity - used);
+    space
+}
+
+fn write(buffer: &Buffer, offset: usize, data: &[u8]) {
+    if data.is_empty() {
+        return;
+    }
+    buffer.bind(gl::COPY_WRITE_BUFFER);
+    buffer.sub_data(gl::COPY_WRITE_BUFFER, offset, data);
+    buffer.unbind(gl::COPY_WRITE_BUFFER);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allocate_takes_the_first_fit() {
+        let mut space = FreeList::new(10);
+        assert_eq!(space.allocate(4), Some(0));
+        assert_eq!(space.allocate(6), Some(4));
+        assert!(space.free.is_empty());
+    }
+
+    #[test]
+    fn free_ranges_are_reused() {
+        let mut space = FreeList::new(10);
+        let a = space.allocate(3).unwrap();
+        let b = space.allocate(3).unwrap();
+        space.release(a, 3);
+        assert_eq!(space.free, [(0, 3), (6, 4)]);
+        assert_eq!(space.allocate(2), Some(0));
+        assert_eq!(space.allocate(3), Some(6));
+        assert_eq!(b, 3);
+        assert_eq!(space.free(), 2);
+        assert_eq!(space.largest(), 1);
+    }
+
+    #[test]
+    fn release_coalesces_neighbours() {
+        let mut space = FreeList::new(12);
+        let ranges: Vec<_> = (0..4).map(|_| space.allocate(3).unwrap()).collect();
+        space.release(ranges[0], 3);
+        space.release(ranges[2], 3);
+        assert_eq!(space.free, [(0, 3), (6, 3)]);
+        // Merges with the preceding range only.
+        space.release(ranges[3], 3);
+        assert_eq!(space.free, [(0, 3), (6, 6)]);
+        // Bridges both neighbours.
+        space.release(ranges[1], 3);
+        assert_eq!(space.free, [(0, 12)]);
+    }
+
+    #[test]
+    fn exhaustion_and_growth() {
+        let mut space = FreeList::new(8);
+        assert_eq!(space.allocate(9), None);
+        let a = space.allocate(5).unwrap();
+        space.allocate(2).unwrap();
+        space.release(a, 5);
+        // 6 elements are free, but not in one range.
+        assert_eq!(space.free(), 6);
+        assert_eq!(space.allocate(6), None);
+        space.grow(grown(8, 6));
+        assert_eq!(space.capacity, 16);
+        assert_eq!(space.free, [(0, 5), (7, 9)]);
+        assert_eq!(space.allocate(6), Some(7));
+    }
+
+    #[test]
+    fn compacted_leaves_one_range() {
+        let space = compacted(16, 5);
+        assert_eq!(space.free, [(5, 11)]);
+        assert_eq!(compacted(4, 4).free, []);
+    }
+}
@@ -0,0 +1,278 @@
+//! Translate, rotate and scale handles for editing a transform with the mouse.
+//!
+//! A [`Gizmo`] draws its handles through [`DebugDraw`] at a constant size on screen and
+//! picks them with a [`Ray`] from the cursor, so it needs no render pass or readback of
+//! its own. Arrows move along and rings rotate around the world axes; scale handles
+//! follow the object's rotation, since scale is applied before it.
+
+use crate::debug_draw::DebugDraw;
+use crate::math::{Mat4, Quat, Transform, Vec3};
+use crate::ray::Ray;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn unit(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::X,
+            Axis::Y => Vec3::Y,
+            Axis::Z => Vec3::Z,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Handle color of hovered and dragged axes.
+const ACTIVE_COLOR: Vec3 = Vec3::new(1.0, 0.85, 0.2);
+
+/// How close the cursor ray must pass to a handle to pick it, relative to the handle
+/// length.
+const PICK_TOLERANCE: f32 = 0.08;
+
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    axis: Axis,
+    start: Transform,
+    /// Where the ray met the handle at the press: the parameter along the axis for
+    /// translation and scale, the direction from the center for rotation.
+    grab: Vec3,
+}
+
+#[derive(Clone, Debug)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    /// Handle length as a fraction of the distance from the eye, which keeps it the same
+    /// size on screen. `0.15` by default.
+    pub screen_size: f32,
+    hovered: Option<Axis>,
+    drag: Option<Drag>,
+    was_pressed: bool,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Gizmo {
+            mode: GizmoMode::Translate,
+            screen_size: 0.15,
+            hovered: None,
+            drag: None,
+            was_pressed: false,
+        }
+    }
+}
+
+impl Gizmo {
+    pub fn new() -> Gizmo {
+        Gizmo::default()
+    }
+
+    /// The axis under the cursor at the last update, or the one being dragged.
+    pub fn hovered(&self) -> Option<Axis> {
+        self.drag.map(|drag| drag.axis).or(self.hovered)
+    }
+
+    /// Whether a handle is being dragged, in which case the mouse shouldn't also move
+    /// the camera.
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Feeds the cursor `ray` and whether the mouse button is `pressed`, seen from `eye`.
+    /// Pressing on a handle starts a drag that edits `transform` until the button is
+    /// released. Returns whether the gizmo has the mouse.
+    pub fn update(
+        &mut self,
+        ray: &Ray,
+        eye: Vec3,
+        pressed: bool,
+        transform: &mut Transform,
+    ) -> bool {
+        let just_pressed = pressed && !self.was_pressed;
+        self.was_pressed = pressed;
+        if !pressed {
+            self.drag = None;
+        }
+        let size = self.size(transform, eye);
+        if let Some(drag) = self.drag {
+            *transform = self.dragged(&drag, ray, size).unwrap_or(*transform);
+            return true;
+        }
+
+        self.hovered = self.pick(ray, transform, size);
+        if just_pressed {
+            self.drag = self.hovered.and_then(|axis| {
+                let start = *transform;
+                let grab = self.grab(axis, &start, ray)?;
+                Some(Drag { axis, start, grab })
+            });
+        }
+        self.drag.is_some()
+    }
+
+    /// Adds the handles of `transform` seen from `eye` to `debug`. Turn off its depth
+    /// test to keep them visible inside the object.
+    pub fn draw(&self, debug: &mut DebugDraw, transform: &Transform, eye: Vec3) {
+        let size = self.size(transform, eye);
+        let center = transform.translation;
+        for axis in Axis::ALL {
+            let direction = self.direction(axis, transform);
+            let color = if self.hovered() == Some(axis) {
+                ACTIVE_COLOR
+            } else {
+                axis.unit()
+            };
+            match self.mode {
+                GizmoMode::Translate => {
+                    let tip = center + direction * size;
+                    debug.line(center, tip, color);
+                    debug.circle(tip - direction * size * 0.1, direction, size * 0.04, color);
+                    debug.line(tip - direction * size * 0.1, tip, color);
+                }
+                GizmoMode::Rotate => debug.circle(center, direction, size, color),
+                GizmoMode::Scale => {
+                    let tip = center + direction * size;
+                    debug.line(center, tip, color);
+                    let handle = Mat4::from_scale_rotation_translation(
+                        Vec3::splat(size * 0.04),
+                        transform.rotation,
+                        tip,
+                    );
+                    debug.wire_box(handle, color);
+                }
+            }
+        }
+    }
+
+    fn size(&self, transform: &Transform, eye: Vec3) -> f32 {
+        (transform.translation.distance(eye) * self.screen_size).max(1e-4)
+    }
+
+    fn direction(&self, axis: Axis, transform: &Transform) -> Vec3 {
+        match self.mode {
+            GizmoMode::Scale => transform.rotation * axis.unit(),
+            _ => axis.unit(),
+        }
+    }
+
+    /// The handle `ray` passes closest to, if any is within reach.
+    fn pick(&self, ray: &Ray, transform: &Transform, size: f32) -> Option<Axis> {
+        let center = transform.translation;
+        let tolerance = size * PICK_TOLERANCE;
+        let mut nearest: Option<(Axis, f32)> = None;
+        for axis in Axis::ALL {
+            let direction = self.direction(axis, transform);
+            let distance = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => {
+                    let Some((along, distance)) = closest_to_ray(center, direction, ray) else {
+                        continue;
+                    };
+                    if !(0.0..=size * 1.05).contains(&along) || distance > tolerance {
+                        continue;
+                    }
+                    ray.origin.distance(center + direction * along)
+                }
+                GizmoMode::Rotate => {
+                    let Some(point) = intersect_plane(ray, center, direction) else {
+                        continue;
+                    };
+                    if (point.distance(center) - size).abs() > tolerance {
+                        continue;
+                    }
+                    ray.origin.distance(point)
+                }
+            };
+            if !matches!(nearest, Some((_, nearest)) if nearest <= distance) {
+                nearest = Some((axis, distance));
+            }
+        }
+        nearest.map(|(axis, _)| axis)
+    }
+
+    fn grab(&self, axis: Axis, start: &Transform, ray: &Ray) -> Option<Vec3> {
+        let center = start.translation;
+        let direction = self.direction(axis, start);
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                let (along, _) = closest_to_ray(center, direction, ray)?;
+                Some(Vec3::splat(along))
+            }
+            GizmoMode::Rotate => {
+                let point = intersect_plane(ray, center, direction)?;
+                (point - center).try_normalize()
+            }
+        }
+    }
+
+    fn dragged(&self, drag: &Drag, ray: &Ray, size: f32) -> Option<Transform> {
+        let start = drag.start;
+        let center = start.translation;
+        let direction = self.direction(drag.axis, &start);
+        let mut transform = start;
+        match self.mode {
+            GizmoMode::Translate => {
+                let (along, _) = closest_to_ray(center, direction, ray)?;
+                transform.translation = center + direction * (along - drag.grab.x);
+            }
+            GizmoMode::Rotate => {
+                let point = intersect_plane(ray, center, direction)?;
+                let current = (point - center).try_normalize()?;
+                let angle = direction
+                    .dot(drag.grab.cross(current))
+                    .atan2(drag.grab.dot(current));
+                transform.rotation = Quat::from_axis_angle(direction, angle) * start.rotation;
+            }
+            GizmoMode::Scale => {
+                let (along, _) = closest_to_ray(center, direction, ray)?;
+                // Grabbed right at the center, any movement would be an infinite factor.
+                let grab = drag.grab.x.max(size * 0.1);
+                let factor = (along / grab).max(0.01);
+                let i = drag.axis.index();
+                transform.scale[i] = start.scale[i] * factor;
+            }
+        }
+        Some(transform)
+    }
+}
+
+/// The parameter along the line `origin + direction * s` of its point closest to `ray`,
+/// and how far the ray passes from it. `None` when they are parallel.
+fn closest_to_ray(origin: Vec3, direction: Vec3, ray: &Ray) -> Option<(f32, f32)> {
+    let w = origin - ray.origin;
+    let b = direction.dot(ray.direction);
+    let denominator = 1.0 - b * b;
+    if denominator < 1e-6 {
+        return None;
+    }
+    let (d, e) = (direction.dot(w), ray.direction.dot(w));
+    let s = (b * e - d) / denominator;
+    let t = ((e - b * d) / denominator).max(0.0);
+    let distance = (origin + direction * s).distance(ray.at(t));
+    Some((s, distance))
+}
+
+fn intersect_plane(ray: &Ray, point: Vec3, normal: Vec3) -> Option<Vec3> {
+    let facing = ray.direction.dot(normal);
+    if facing.abs() < 1e-6 {
+        return None;
+    }
+    let t = (point - ray.origin).dot(normal) / facing;
+    (t >= 0.0).then(|| ray.at(t))
+}
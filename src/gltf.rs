@@ -0,0 +1,474 @@
+//! glTF 2.0 import (`gltf` feature).
+//!
+//! [`load`] reads `.gltf`/`.glb` files into the crate's [`Mesh`] and [`Material`] types.
+//! Each glTF mesh becomes one [`Mesh`] with a submesh per triangle primitive. Nodes keep
+//! their hierarchy as indices into [`GltfScene::nodes`]. Meshes with joint attributes are
+//! built from [`SkinnedVertex`] data and animated through [`GltfScene::skins`] and
+//! [`GltfScene::animations`].
+
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::animation::{AnimationClip, Channel, ChannelTarget, Interpolation, Skin, Track};
+use crate::image::Image;
+use crate::material::{Material, Shading};
+use crate::math::{Mat4, Quat, Transform, Vec3, Vec4};
+use crate::mesh::{compute_tangents, Mesh, PrimitiveMode, SkinnedVertex, Submesh, Vertex};
+use crate::pixels;
+use crate::scene::{Drawable, NodeId, Scene};
+use crate::texture::Texture;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GltfCamera {
+    Perspective {
+        yfov: f32,
+        aspect_ratio: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+impl GltfCamera {
+    /// Builds the projection matrix. `aspect` is used when the file does not fix one.
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        match *self {
+            GltfCamera::Perspective {
+                yfov,
+                aspect_ratio,
+                znear,
+                zfar,
+            } => Mat4::perspective_rh_gl(
+                yfov,
+                aspect_ratio.unwrap_or(aspect),
+                znear,
+                zfar.unwrap_or(znear * 1.0e5),
+            ),
+            GltfCamera::Orthographic {
+                xmag,
+                ymag,
+                znear,
+                zfar,
+            } => Mat4::orthographic_rh_gl(-xmag, xmag, -ymag, ymag, znear, zfar),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// Transform relative to the parent node.
+    pub transform: Transform,
+    pub children: Vec<usize>,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub skin: Option<usize>,
+}
+
+pub struct GltfScene {
+    pub meshes: Vec<Mesh>,
+    /// Materials referenced by [`Submesh::material`].
+    pub materials: Vec<Material>,
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene (or the first scene if none is marked default).
+    pub roots: Vec<usize>,
+    pub cameras: Vec<GltfCamera>,
+    pub skins: Vec<Skin>,
+    pub animations: Vec<AnimationClip>,
+}
+
+impl GltfScene {
+    /// The local transform of every node as stored in the file.
+    pub fn rest_pose(&self) -> Vec<Transform> {
+        self.nodes.iter().map(|node| node.transform).collect()
+    }
+
+    /// Computes world matrices for every node from local transforms indexed by node.
+    /// Nodes not reachable from [`GltfScene::roots`] keep their local matrix.
+    pub fn world_matrices(&self, pose: &[Transform]) -> Vec<Mat4> {
+        let mut world: Vec<Mat4> = pose.iter().map(Transform::matrix).collect();
+        let mut stack: Vec<(usize, Mat4)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Mat4::IDENTITY))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            world[index] = parent * pose[index].matrix();
+            let matrix = world[index];
+            stack.extend(
+                self.nodes[index]
+                    .children
+                    .iter()
+                    .map(|&child| (child, matrix)),
+            );
+        }
+        world
+    }
+
+    /// Converts the node hierarchy of the default scene into a [`Scene`], attaching a
+    /// [`Drawable`] to every node with a mesh. Also returns the scene node created for
+    /// each glTF node, indexed like [`GltfScene::nodes`].
+    pub fn into_scene(self) -> (Scene, Vec<Option<NodeId>>) {
+        let meshes: Vec<Rc<Mesh>> = self.meshes.into_iter().map(Rc::new).collect();
+        let materials: Vec<Rc<Material>> = self.materials.into_iter().map(Rc::new).collect();
+        let mut scene = Scene::new();
+        let mut ids = vec![None; self.nodes.len()];
+        let mut stack: Vec<(usize, Option<NodeId>)> =
+            self.roots.iter().rev().map(|&root| (root, None)).collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let id = scene.add(parent, node.name.as_deref(), node.transform);
+            ids[index] = Some(id);
+            if let Some(mesh) = node.mesh {
+                scene.node_mut(id).drawable = Some(Drawable {
+                    mesh: meshes[mesh].clone(),
+                    materials: materials.clone(),
+                });
+            }
+            stack.extend(node.children.iter().rev().map(|&child| (child, Some(id))));
+        }
+        scene.update();
+        (scene, ids)
+    }
+
+    /// Calls `f` with every node and its world transform in the rest pose, parents
+    /// before children.
+    pub fn walk<F: FnMut(&GltfNode, Mat4)>(&self, mut f: F) {
+        let mut stack: Vec<(usize, Mat4)> = self
+            .roots
+            .iter()
+            .rev()
+            .map(|&root| (root, Mat4::IDENTITY))
+            .collect();
+        while let Some((index, parent)) = stack.pop() {
+            let node = &self.nodes[index];
+            let world = parent * node.transform.matrix();
+            f(node, world);
+            stack.extend(node.children.iter().rev().map(|&child| (child, world)));
+        }
+    }
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<GltfScene> {
+    let path = path.as_ref();
+    let (document, buffers, images) =
+        ::gltf::import(path).map_err(|e| anyhow!("Failed to import {}: {}", path.display(), e))?;
+
+    let textures = images
+        .iter()
+        .zip(document.images())
+        .map(|(data, image)| {
+            let texture = Texture::from_image(&convert_image(data)?)?;
+            let name = match (image.name(), image.source()) {
+                (Some(name), _) => name.to_owned(),
+                (None, ::gltf::image::Source::Uri { uri, .. }) => uri.to_owned(),
+                (None, _) => format!("image {}", image.index()),
+            };
+            texture.label(&format!("{}: {}", path.display(), name));
+            Ok(Rc::new(texture))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let texture = |info: Option<::gltf::texture::Info>| {
+        info.map(|info| textures[info.texture().source().index()].clone())
+    };
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            Material {
+                shading: Shading::Pbr,
+                base_color: Vec4::from(pbr.base_color_factor()),
+                base_color_texture: texture(pbr.base_color_texture()),
+                emissive: Vec3::from(material.emissive_factor()),
+                metallic: pbr.metallic_factor(),
+                roughness: pbr.roughness_factor(),
+                metallic_roughness_texture: texture(pbr.metallic_roughness_texture()),
+                normal_texture: material
+                    .normal_texture()
+                    .map(|normal| textures[normal.texture().source().index()].clone()),
+                normal_scale: material
+                    .normal_texture()
+                    .map_or(1.0, |normal| normal.scale()),
+                ..Material::default()
+            }
+        })
+        .collect();
+
+    let meshes = document
+        .meshes()
+        .map(|mesh| {
+            let loaded = load_mesh(&mesh, &buffers)?;
+            let name = mesh
+                .name()
+                .map_or_else(|| format!("mesh {}", mesh.index()), str::to_owned);
+            loaded.label(&format!("{}: {}", path.display(), name));
+            Ok(loaded)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            GltfNode {
+                name: node.name().map(str::to_owned),
+                transform: Transform {
+                    translation: Vec3::from(translation),
+                    rotation: Quat::from_array(rotation),
+                    scale: Vec3::from(scale),
+                },
+                children: node.children().map(|child| child.index()).collect(),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                camera: node.camera().map(|camera| camera.index()),
+                skin: node.skin().map(|skin| skin.index()),
+            }
+        })
+        .collect();
+
+    let roots = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| node.index()).collect())
+        .unwrap_or_default();
+
+    let cameras = document
+        .cameras()
+        .map(|camera| match camera.projection() {
+            ::gltf::camera::Projection::Perspective(p) => GltfCamera::Perspective {
+                yfov: p.yfov(),
+                aspect_ratio: p.aspect_ratio(),
+                znear: p.znear(),
+                zfar: p.zfar(),
+            },
+            ::gltf::camera::Projection::Orthographic(o) => GltfCamera::Orthographic {
+                xmag: o.xmag(),
+                ymag: o.ymag(),
+                znear: o.znear(),
+                zfar: o.zfar(),
+            },
+        })
+        .collect();
+
+    let skins = document
+        .skins()
+        .map(|skin| {
+            let joints: Vec<usize> = skin.joints().map(|joint| joint.index()).collect();
+            let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+            let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+                Some(matrices) => matrices.map(|m| Mat4::from_cols_array_2d(&m)).collect(),
+                None => vec![Mat4::IDENTITY; joints.len()],
+            };
+            Skin {
+                joints,
+                inverse_bind_matrices,
+            }
+        })
+        .collect();
+
+    let animations = document
+        .animations()
+        .map(|animation| load_animation(&animation, &buffers))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        nodes,
+        roots,
+        cameras,
+        skins,
+        animations,
+    })
+}
+
+fn load_animation(
+    animation: &::gltf::Animation,
+    buffers: &[::gltf::buffer::Data],
+) -> Result<AnimationClip> {
+    use ::gltf::animation::util::ReadOutputs;
+
+    let mut channels = Vec::new();
+    for channel in animation.channels() {
+        let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+        let (Some(inputs), Some(outputs)) = (reader.read_inputs(), reader.read_outputs()) else {
+            continue;
+        };
+        let times: Vec<f32> = inputs.collect();
+        let interpolation = match channel.sampler().interpolation() {
+            ::gltf::animation::Interpolation::Step => Interpolation::Step,
+            ::gltf::animation::Interpolation::Linear => Interpolation::Linear,
+            ::gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+        };
+        let target = match outputs {
+            ReadOutputs::Translations(values) => ChannelTarget::Translation(Track::new(
+                times,
+                values.map(Vec3::from).collect(),
+                interpolation,
+            )?),
+            ReadOutputs::Rotations(values) => ChannelTarget::Rotation(Track::new(
+                times,
+                values.into_f32().map(Quat::from_array).collect(),
+                interpolation,
+            )?),
+            ReadOutputs::Scales(values) => ChannelTarget::Scale(Track::new(
+                times,
+                values.map(Vec3::from).collect(),
+                interpolation,
+            )?),
+            ReadOutputs::MorphTargetWeights(_) => continue,
+        };
+        channels.push(Channel {
+            node: channel.target().node().index(),
+            target,
+        });
+    }
+
+    Ok(AnimationClip {
+        name: animation.name().map(str::to_owned),
+        channels,
+    })
+}
+
+fn load_mesh(mesh: &::gltf::Mesh, buffers: &[::gltf::buffer::Data]) -> Result<Mesh> {
+    let mut vertices = Vec::new();
+    let mut skinning = Vec::new();
+    let mut tangents = Vec::new();
+    let mut indices = Vec::new();
+    let mut submeshes = Vec::new();
+
+    for primitive in mesh.primitives() {
+        // Strips and fans become lists, so the primitives can share one mesh.
+        let mode = match primitive.mode() {
+            ::gltf::mesh::Mode::Triangles => PrimitiveMode::Triangles,
+            ::gltf::mesh::Mode::TriangleStrip => PrimitiveMode::TriangleStrip,
+            ::gltf::mesh::Mode::TriangleFan => PrimitiveMode::TriangleFan,
+            _ => continue,
+        };
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+        let positions = reader
+            .read_positions()
+            .ok_or_else(|| anyhow!("Primitive without positions"))?;
+
+        let base = vertices.len() as u32;
+        vertices.extend(positions.map(|position| Vertex {
+            position,
+            ..Vertex::default()
+        }));
+        let primitive_vertices = &mut vertices[base as usize..];
+        let joints: Vec<[u32; 4]> = match reader.read_joints(0) {
+            Some(joints) => joints.into_u16().map(|j| j.map(u32::from)).collect(),
+            None => vec![[0; 4]; primitive_vertices.len()],
+        };
+        let weights: Vec<[f32; 4]> = match reader.read_weights(0) {
+            Some(weights) => weights.into_f32().collect(),
+            None => vec![[1.0, 0.0, 0.0, 0.0]; primitive_vertices.len()],
+        };
+        skinning.extend(joints.into_iter().zip(weights));
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            for (vertex, uv) in primitive_vertices.iter_mut().zip(uvs.into_f32()) {
+                vertex.uv = uv;
+            }
+        }
+
+        let primitive_indices: Vec<u32> = match reader.read_indices() {
+            Some(read) => read.into_u32().collect(),
+            None => (0..primitive_vertices.len() as u32).collect(),
+        };
+        let primitive_indices = mode.triangle_list(&primitive_indices, None);
+        match reader.read_normals() {
+            Some(normals) => {
+                for (vertex, normal) in primitive_vertices.iter_mut().zip(normals) {
+                    vertex.normal = normal;
+                }
+            }
+            None => crate::mesh::compute_normals(primitive_vertices, &primitive_indices),
+        }
+        match reader.read_tangents() {
+            Some(read) => tangents.extend(read),
+            None => tangents.extend(compute_tangents(primitive_vertices, &primitive_indices)),
+        }
+
+        submeshes.push(Submesh {
+            first_index: indices.len() as u32,
+            index_count: primitive_indices.len() as i32,
+            base_vertex: 0,
+            material: primitive.material().index(),
+        });
+        indices.extend(primitive_indices.iter().map(|&i| base + i));
+    }
+
+    let skinned = mesh
+        .primitives()
+        .any(|primitive| primitive.get(&::gltf::Semantic::Joints(0)).is_some());
+    if skinned {
+        let vertices: Vec<SkinnedVertex> = vertices
+            .iter()
+            .zip(skinning)
+            .map(|(vertex, (joints, weights))| SkinnedVertex {
+                position: vertex.position,
+                normal: vertex.normal,
+                uv: vertex.uv,
+                joints,
+                weights,
+            })
+            .collect();
+        Mesh::skinned_with_tangents(&vertices, &tangents, &indices, submeshes)
+    } else {
+        Mesh::with_submeshes_and_tangents(&vertices, &tangents, &indices, submeshes)
+    }
+}
+
+fn convert_image(data: &::gltf::image::Data) -> Result<Image> {
+    use ::gltf::image::Format;
+
+    let (channels, bytes_per_channel) = match data.format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let channel = |bytes: &[u8]| -> u8 {
+        match bytes_per_channel {
+            1 => bytes[0],
+            2 => (u16::from_ne_bytes([bytes[0], bytes[1]]) >> 8) as u8,
+            _ => {
+                let v = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                pixels::f32_to_unorm8(v)
+            }
+        }
+    };
+
+    let pixels = data
+        .pixels
+        .chunks_exact(channels * bytes_per_channel)
+        .flat_map(|pixel| {
+            let c = |i: usize| channel(&pixel[i * bytes_per_channel..]);
+            match channels {
+                1 => [c(0), c(0), c(0), 255],
+                2 => [c(0), c(1), 0, 255],
+                3 => [c(0), c(1), c(2), 255],
+                _ => [c(0), c(1), c(2), c(3)],
+            }
+        })
+        .collect();
+
+    Ok(Image {
+        width: data.width,
+        height: data.height,
+        pixels,
+    })
+}
@@ -0,0 +1,249 @@
+//! GPU-driven frustum culling.
+//!
+//! [`GpuCulling`] holds one candidate draw per instance: a world-space bounding sphere and
+//! the [`DrawElementsIndirectCommand`] that draws it. Each frame a compute pass tests every
+//! sphere against the camera frustum and appends the commands of the survivors to a
+//! [`DrawIndirectBuffer`], which is then submitted with one `glMultiDrawElementsIndirect`.
+//! The CPU never learns which instances are visible, so the scene can be as large as the
+//! GPU can test.
+
+use std::mem::offset_of;
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::atomic::{self, AtomicCounters};
+use crate::block_layout::{self, BlockKind, Field};
+use crate::buffer::Buffer;
+use crate::context;
+use crate::culling::{Frustum, Sphere};
+use crate::gl;
+use crate::indirect::{self, DrawElementsIndirectCommand, DrawIndirectBuffer};
+use crate::load_store::{self, Barriers};
+use crate::math::Mat4;
+use crate::shader::Program;
+
+const CANDIDATES_BINDING: u32 = 0;
+const COMMANDS_BINDING: u32 = 1;
+const VISIBLE_BINDING: u32 = 0;
+
+const CULL_SHADER: &str = r#"#version 430 core
+layout (local_size_x = 64) in;
+
+struct Command {
+    uint count;
+    uint instance_count;
+    uint first_index;
+    int base_vertex;
+    uint base_instance;
+};
+
+struct Candidate {
+    vec4 sphere;
+    Command command;
+};
+
+layout (std430, binding = 0) readonly buffer Candidates {
+    Candidate candidates[];
+};
+
+layout (std430, binding = 1) writeonly buffer Commands {
+    Command commands[];
+};
+
+layout (binding = 0, offset = 0) uniform atomic_uint u_visible;
+
+uniform vec4 u_planes[6];
+uniform uint u_candidate_count;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= u_candidate_count) {
+        return;
+    }
+    Candidate candidate = candidates[index];
+    for (int i = 0; i < 6; i++) {
+        if (dot(u_planes[i].xyz, candidate.sphere.xyz) + u_planes[i].w < -candidate.sphere.w) {
+            return;
+        }
+    }
+    commands[atomicCounterIncrement(u_visible)] = candidate.command;
+}
+"#;
+
+/// Whether the context supports [`GpuCulling`]: compute shaders, atomic counters and
+/// indirect multi-draw, all part of GL 4.3.
+pub fn is_supported() -> bool {
+    context::version() >= (4, 3)
+        && gl::DispatchCompute::is_loaded()
+        && atomic::is_supported()
+        && indirect::indirect_supported()
+}
+
+/// One instance to cull: its bounding sphere and the command that draws it. Same layout
+/// as the std430 `Candidate` struct of the culling shader.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct CullCandidate {
+    /// World-space center in `xyz`, radius in `w`.
+    pub sphere: [f32; 4],
+    pub command: DrawElementsIndirectCommand,
+    _padding: [u32; 3],
+}
+
+impl CullCandidate {
+    /// `sphere` must already be in world space; see [`Sphere::transform`].
+    pub fn new(sphere: Sphere, command: DrawElementsIndirectCommand) -> CullCandidate {
+        CullCandidate {
+            sphere: sphere.center.extend(sphere.radius).to_array(),
+            command,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// In debug builds, fails if the shader's structs don't match [`CullCandidate`] and
+/// [`DrawElementsIndirectCommand`].
+fn check_blocks(program: &Program) -> Result<()> {
+    use DrawElementsIndirectCommand as Command;
+    let command = [
+        Field::new("count", offset_of!(Command, count)),
+        Field::new("instance_count", offset_of!(Command, instance_count)),
+        Field::new("first_index", offset_of!(Command, first_index)),
+        Field::new("base_vertex", offset_of!(Command, base_vertex)),
+        Field::new("base_instance", offset_of!(Command, base_instance)),
+    ];
+    let stride = std::mem::size_of::<Command>();
+    let commands = Field::struct_array("commands", 0, stride, &command);
+    block_layout::check(
+        program,
+        BlockKind::ShaderStorage,
+        "Commands",
+        None,
+        &commands,
+    )?;
+
+    let offset = offset_of!(CullCandidate, command);
+    let mut candidate = vec![Field::new("sphere", offset_of!(CullCandidate, sphere))];
+    for field in &command {
+        let name = format!("command.{}", field.name);
+        candidate.push(Field::new(name, offset + field.offset));
+    }
+    let stride = std::mem::size_of::<CullCandidate>();
+    let candidates = Field::struct_array("candidates", 0, stride, &candidate);
+    block_layout::check(
+        program,
+        BlockKind::ShaderStorage,
+        "Candidates",
+        None,
+        &candidates,
+    )
+}
+
+pub struct GpuCulling {
+    program: Program,
+    candidates: Buffer,
+    commands: DrawIndirectBuffer,
+    visible: AtomicCounters,
+    len: usize,
+}
+
+impl GpuCulling {
+    pub fn new(candidates: &[CullCandidate]) -> Result<GpuCulling> {
+        if !is_supported() {
+            return Err(anyhow!("GPU culling requires OpenGL 4.3"));
+        }
+        let program = Program::from_compute(CULL_SHADER)?;
+        check_blocks(&program)?;
+        let buffer = Buffer::new()?;
+        buffer.label("cull candidates");
+        let mut culling = GpuCulling {
+            program,
+            candidates: buffer,
+            commands: DrawIndirectBuffer::new(&[])?,
+            visible: AtomicCounters::new(1)?,
+            len: 0,
+        };
+        culling.set_candidates(candidates)?;
+        Ok(culling)
+    }
+
+    /// Replaces all candidates, e.g. after instances moved. Fails if the context can't
+    /// dispatch enough work groups to test them all.
+    pub fn set_candidates(&mut self, candidates: &[CullCandidate]) -> Result<()> {
+        context::limits().check_work_groups([candidates.len().div_ceil(64) as u32, 1, 1])?;
+        self.candidates.bind(gl::SHADER_STORAGE_BUFFER);
+        self.candidates.data(
+            gl::SHADER_STORAGE_BUFFER,
+            bytemuck::cast_slice(candidates),
+            gl::STATIC_DRAW,
+        );
+        self.candidates.unbind(gl::SHADER_STORAGE_BUFFER);
+        if candidates.len() != self.len {
+            self.commands.update(&vec![
+                DrawElementsIndirectCommand::default();
+                candidates.len()
+            ]);
+        }
+        self.len = candidates.len();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The compacted commands written by the last [`GpuCulling::cull`]. Slots past the
+    /// visible ones hold empty commands.
+    pub fn commands(&self) -> &DrawIndirectBuffer {
+        &self.commands
+    }
+
+    /// Tests every candidate against the frustum of `view_projection` and writes the
+    /// visible ones to the front of the command buffer.
+    pub fn cull(&self, view_projection: Mat4) {
+        if self.is_empty() {
+            return;
+        }
+        let _span = tracing::debug_span!("pass", name = "gpu culling").entered();
+        let commands = self.commands.buffer();
+        commands.bind(gl::SHADER_STORAGE_BUFFER);
+        commands.clear(gl::SHADER_STORAGE_BUFFER);
+        commands.unbind(gl::SHADER_STORAGE_BUFFER);
+        self.visible.reset();
+
+        let planes = Frustum::from_view_projection(view_projection)
+            .planes()
+            .map(|plane| plane.to_array());
+        self.program.use_program();
+        self.program.set_vec4_array("u_planes", &planes);
+        self.program.set_uint("u_candidate_count", self.len as u32);
+        self.candidates
+            .bind_base(gl::SHADER_STORAGE_BUFFER, CANDIDATES_BINDING);
+        commands.bind_base(gl::SHADER_STORAGE_BUFFER, COMMANDS_BINDING);
+        self.visible.bind(VISIBLE_BINDING);
+        unsafe {
+            gl::DispatchCompute(self.len.div_ceil(64) as u32, 1, 1);
+        }
+        load_store::memory_barrier(Barriers::COMMAND | Barriers::SHADER_STORAGE);
+    }
+
+    /// Submits the commands that survived the last [`GpuCulling::cull`] with one
+    /// `glMultiDrawElementsIndirect` from the bound vertex array. The triangle count in
+    /// [`crate::stats`] stays zero, since only the GPU knows what was drawn.
+    pub fn draw(&self) {
+        if !self.is_empty() {
+            self.commands.draw();
+        }
+    }
+
+    /// How many candidates the last [`GpuCulling::cull`] kept. Stalls until the pass has
+    /// finished; meant for statistics, not every frame.
+    pub fn visible(&self) -> usize {
+        self.visible.get(0) as usize
+    }
+}
@@ -0,0 +1,565 @@
+//! A declarative render graph.
+//!
+//! Passes declare the textures they read and write instead of binding framebuffers by
+//! hand. [`RenderGraph::execute`] orders the passes by their dependencies, drops passes
+//! whose results are never used, allocates transient textures from a [`TransientPool`]
+//! (reusing a texture once its last reader has run) and binds a framebuffer with the
+//! written attachments before running each pass, which may start by clearing or
+//! discarding them; see [`crate::clear`].
+//!
+//! A graph is built every frame; the pool persists across frames.
+
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt::Write;
+
+use anyhow::{anyhow, Result};
+
+use crate::clear::PassClear;
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::texture::Texture;
+
+/// A texture declared in a graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ResourceId(usize);
+
+/// Size and format of a graph texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureDesc {
+    pub width: i32,
+    pub height: i32,
+    pub internal_format: gl::types::GLenum,
+}
+
+impl TextureDesc {
+    pub fn new(width: i32, height: i32, internal_format: gl::types::GLenum) -> TextureDesc {
+        TextureDesc {
+            width,
+            height,
+            internal_format,
+        }
+    }
+
+    fn depth_attachment(&self) -> Option<gl::types::GLenum> {
+        match self.internal_format {
+            gl::DEPTH_COMPONENT16 | gl::DEPTH_COMPONENT24 | gl::DEPTH_COMPONENT32F => {
+                Some(gl::DEPTH_ATTACHMENT)
+            }
+            gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8 => Some(gl::DEPTH_STENCIL_ATTACHMENT),
+            _ => None,
+        }
+    }
+}
+
+enum Resource<'a> {
+    Transient(TextureDesc),
+    Imported(&'a Texture, TextureDesc),
+    Backbuffer(i32, i32),
+}
+
+struct ResourceNode<'a> {
+    name: String,
+    resource: Resource<'a>,
+    output: bool,
+}
+
+type Execute<'a> = Box<dyn FnOnce(&PassContext) + 'a>;
+
+struct PassNode<'a> {
+    name: String,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    clear: PassClear,
+    execute: Option<Execute<'a>>,
+}
+
+/// Handed to a pass while it runs, with its framebuffer and viewport already set.
+pub struct PassContext<'p> {
+    textures: &'p HashMap<ResourceId, &'p Texture>,
+    width: i32,
+    height: i32,
+}
+
+impl PassContext<'_> {
+    /// The texture backing `resource`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resource` is the backbuffer or was not declared by this pass.
+    pub fn texture(&self, resource: ResourceId) -> &Texture {
+        self.textures
+            .get(&resource)
+            .expect("resource is not a texture declared by this pass")
+    }
+
+    /// Size of the bound framebuffer, which is also the viewport.
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+}
+
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    resources: Vec<ResourceNode<'a>>,
+    passes: Vec<PassNode<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> RenderGraph<'a> {
+        RenderGraph::default()
+    }
+
+    /// Declares a texture allocated from the pool for the duration of the frame.
+    pub fn create_texture(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+        self.add_resource(name, Resource::Transient(desc))
+    }
+
+    /// Declares a texture owned outside the graph, such as a shadow map kept across
+    /// frames. Passes writing to it are never culled.
+    pub fn import_texture(
+        &mut self,
+        name: &str,
+        texture: &'a Texture,
+        desc: TextureDesc,
+    ) -> ResourceId {
+        let id = self.add_resource(name, Resource::Imported(texture, desc));
+        self.mark_output(id);
+        id
+    }
+
+    /// Declares the window's default framebuffer as a write target.
+    pub fn backbuffer(&mut self, width: i32, height: i32) -> ResourceId {
+        let id = self.add_resource("backbuffer", Resource::Backbuffer(width, height));
+        self.mark_output(id);
+        id
+    }
+
+    /// Keeps the passes writing `resource` even if no other pass reads it.
+    pub fn mark_output(&mut self, resource: ResourceId) {
+        self.resources[resource.0].output = true;
+    }
+
+    fn add_resource(&mut self, name: &str, resource: Resource<'a>) -> ResourceId {
+        self.resources.push(ResourceNode {
+            name: name.to_owned(),
+            resource,
+            output: false,
+        });
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// Adds a pass sampling `reads` and rendering into `writes`. Color writes become
+    /// attachments 0..n in order; a depth-format write becomes the depth attachment.
+    ///
+    /// Passes may be added in any order: a pass runs after every writer of its reads.
+    /// Passes writing the same resource run in the order they were added.
+    pub fn add_pass<F: FnOnce(&PassContext) + 'a>(
+        &mut self,
+        name: &str,
+        reads: &[ResourceId],
+        writes: &[ResourceId],
+        execute: F,
+    ) {
+        self.add_cleared_pass(name, reads, writes, PassClear::new(), execute);
+    }
+
+    /// Like [`RenderGraph::add_pass`], starting the pass with `clear` once its
+    /// framebuffer is bound. Color entries follow the order of the color `writes`.
+    pub fn add_cleared_pass<F: FnOnce(&PassContext) + 'a>(
+        &mut self,
+        name: &str,
+        reads: &[ResourceId],
+        writes: &[ResourceId],
+        clear: PassClear,
+        execute: F,
+    ) {
+        self.passes.push(PassNode {
+            name: name.to_owned(),
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            clear,
+            execute: Some(Box::new(execute)),
+        });
+    }
+
+    /// Indices of passes that contribute to an output, in execution order.
+    fn schedule(&self) -> Result<Vec<usize>> {
+        let count = self.passes.len();
+        let mut dependencies = vec![Vec::new(); count];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for (j, other) in self.passes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let reads_output = pass.reads.iter().any(|r| other.writes.contains(r));
+                let earlier_writer = j < i && pass.writes.iter().any(|w| other.writes.contains(w));
+                if reads_output || earlier_writer {
+                    dependencies[i].push(j);
+                }
+            }
+        }
+
+        let mut live = vec![false; count];
+        let mut stack: Vec<usize> = (0..count)
+            .filter(|&i| {
+                self.passes[i]
+                    .writes
+                    .iter()
+                    .any(|w| self.resources[w.0].output)
+            })
+            .collect();
+        while let Some(i) = stack.pop() {
+            if !live[i] {
+                live[i] = true;
+                stack.extend(&dependencies[i]);
+            }
+        }
+
+        let live_count = live.iter().filter(|&&l| l).count();
+        let mut order = Vec::with_capacity(live_count);
+        let mut done = vec![false; count];
+        while order.len() < live_count {
+            let next = (0..count)
+                .find(|&i| live[i] && !done[i] && dependencies[i].iter().all(|&d| done[d]));
+            let Some(next) = next else {
+                return Err(anyhow!("Render graph has a dependency cycle"));
+            };
+            done[next] = true;
+            order.push(next);
+        }
+        Ok(order)
+    }
+
+    /// Runs the live passes in dependency order.
+    pub fn execute(mut self, pool: &mut TransientPool) -> Result<()> {
+        let order = self.schedule()?;
+
+        let mut last_use = HashMap::new();
+        for (step, &i) in order.iter().enumerate() {
+            let pass = &self.passes[i];
+            for &resource in pass.reads.iter().chain(&pass.writes) {
+                last_use.insert(resource, step);
+            }
+        }
+
+        pool.begin_frame();
+        let mut allocated: HashMap<ResourceId, usize> = HashMap::new();
+        for (step, &i) in order.iter().enumerate() {
+            let pass = &mut self.passes[i];
+            let _span = tracing::debug_span!("pass", name = %pass.name).entered();
+            for &resource in pass.reads.iter().chain(&pass.writes) {
+                if let Resource::Transient(desc) = self.resources[resource.0].resource {
+                    if let Entry::Vacant(entry) = allocated.entry(resource) {
+                        entry.insert(pool.acquire(desc)?);
+                    }
+                }
+            }
+
+            let mut textures = HashMap::new();
+            for &resource in pass.reads.iter().chain(&pass.writes) {
+                match self.resources[resource.0].resource {
+                    Resource::Transient(_) => {
+                        textures.insert(resource, &pool.textures[allocated[&resource]].texture);
+                    }
+                    Resource::Imported(texture, _) => {
+                        textures.insert(resource, texture);
+                    }
+                    Resource::Backbuffer(..) => {}
+                }
+            }
+
+            let mut size = None;
+            let mut attachments = Vec::new();
+            let mut color_count = 0;
+            let mut writes_backbuffer = false;
+            for &resource in &pass.writes {
+                let desc = match self.resources[resource.0].resource {
+                    Resource::Transient(desc) | Resource::Imported(_, desc) => desc,
+                    Resource::Backbuffer(width, height) => {
+                        size = Some((width, height));
+                        writes_backbuffer = true;
+                        continue;
+                    }
+                };
+                size = size.or(Some((desc.width, desc.height)));
+                let attachment = desc.depth_attachment().unwrap_or_else(|| {
+                    color_count += 1;
+                    gl::COLOR_ATTACHMENT0 + color_count - 1
+                });
+                attachments.push((attachment, textures[&resource]));
+            }
+
+            if writes_backbuffer {
+                if !attachments.is_empty() {
+                    return Err(anyhow!(
+                        "Pass {} writes the backbuffer and textures at once",
+                        pass.name
+                    ));
+                }
+                Framebuffer::bind_default(gl::FRAMEBUFFER);
+            } else if !attachments.is_empty() {
+                bind_framebuffer(&mut pool.framebuffers, &attachments, color_count)?;
+            }
+
+            let (width, height) = size.unwrap_or((0, 0));
+            if size.is_some() {
+                unsafe {
+                    gl::Viewport(0, 0, width, height);
+                }
+                pass.clear.apply(writes_backbuffer);
+            }
+            if let Some(execute) = pass.execute.take() {
+                execute(&PassContext {
+                    textures: &textures,
+                    width,
+                    height,
+                });
+            }
+
+            allocated.retain(|resource, &mut slot| {
+                let expired = last_use[resource] == step;
+                if expired {
+                    pool.textures[slot].in_use = false;
+                }
+                !expired
+            });
+        }
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        Ok(())
+    }
+
+    /// Describes the graph in Graphviz DOT format. Passes that would be culled are dashed.
+    pub fn to_dot(&self) -> String {
+        let live = self.schedule().unwrap_or_default();
+        let mut dot = String::from("digraph render_graph {\n    rankdir=LR;\n");
+        for (i, resource) in self.resources.iter().enumerate() {
+            let label = match resource.resource {
+                Resource::Transient(desc) => format!(
+                    "{}\\n{}x{} 0x{:x}",
+                    resource.name, desc.width, desc.height, desc.internal_format
+                ),
+                Resource::Imported(_, desc) => format!(
+                    "{} (imported)\\n{}x{}",
+                    resource.name, desc.width, desc.height
+                ),
+                Resource::Backbuffer(width, height) => {
+                    format!("{}\\n{}x{}", resource.name, width, height)
+                }
+            };
+            let _ = writeln!(dot, "    r{} [shape=ellipse, label=\"{}\"];", i, label);
+        }
+        for (i, pass) in self.passes.iter().enumerate() {
+            let style = if live.contains(&i) { "solid" } else { "dashed" };
+            let _ = writeln!(
+                dot,
+                "    p{} [shape=box, style={}, label=\"{}\"];",
+                i, style, pass.name
+            );
+            for read in &pass.reads {
+                let _ = writeln!(dot, "    r{} -> p{};", read.0, i);
+            }
+            for write in &pass.writes {
+                let _ = writeln!(dot, "    p{} -> r{};", i, write.0);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+struct PooledTexture {
+    desc: TextureDesc,
+    texture: Texture,
+    in_use: bool,
+    used_this_frame: bool,
+}
+
+type FramebufferKey = Vec<(gl::types::GLenum, gl::types::GLuint)>;
+
+/// Textures and framebuffers reused by render graphs across frames.
+///
+/// Textures not used during a frame are freed at the start of the next one, so resizing
+/// the window doesn't accumulate stale allocations.
+#[derive(Default)]
+pub struct TransientPool {
+    textures: Vec<PooledTexture>,
+    framebuffers: HashMap<FramebufferKey, Framebuffer>,
+}
+
+impl TransientPool {
+    pub fn new() -> TransientPool {
+        TransientPool::default()
+    }
+
+    /// Number of textures currently owned by the pool.
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    fn begin_frame(&mut self) {
+        let before = self.textures.len();
+        self.textures.retain(|pooled| pooled.used_this_frame);
+        if self.textures.len() != before {
+            // Deleted texture names may be reused, which would alias cached framebuffers.
+            self.framebuffers.clear();
+        }
+        for pooled in &mut self.textures {
+            pooled.in_use = false;
+            pooled.used_this_frame = false;
+        }
+    }
+
+    fn acquire(&mut self, desc: TextureDesc) -> Result<usize> {
+        if let Some(slot) = self
+            .textures
+            .iter()
+            .position(|pooled| !pooled.in_use && pooled.desc == desc)
+        {
+            self.textures[slot].in_use = true;
+            self.textures[slot].used_this_frame = true;
+            return Ok(slot);
+        }
+        self.textures.push(PooledTexture {
+            desc,
+            texture: create_texture(desc)?,
+            in_use: true,
+            used_this_frame: true,
+        });
+        Ok(self.textures.len() - 1)
+    }
+}
+
+/// Binds a framebuffer with `attachments`, creating and caching it on first use.
+fn bind_framebuffer(
+    framebuffers: &mut HashMap<FramebufferKey, Framebuffer>,
+    attachments: &[(gl::types::GLenum, &Texture)],
+    color_count: u32,
+) -> Result<()> {
+    let key: FramebufferKey = attachments
+        .iter()
+        .map(|&(attachment, texture)| (attachment, texture.id()))
+        .collect();
+    if let Some(framebuffer) = framebuffers.get(&key) {
+        framebuffer.bind(gl::FRAMEBUFFER);
+        return Ok(());
+    }
+    let framebuffer = Framebuffer::new()?;
+    framebuffer.bind(gl::FRAMEBUFFER);
+    for &(attachment, texture) in attachments {
+        framebuffer.attach_texture(gl::FRAMEBUFFER, attachment, texture, 0);
+    }
+    let buffers: Vec<_> = (0..color_count)
+        .map(|i| gl::COLOR_ATTACHMENT0 + i)
+        .collect();
+    if buffers.is_empty() {
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+        }
+    } else {
+        framebuffer.draw_buffers(&buffers)?;
+    }
+    framebuffer.check_status(gl::FRAMEBUFFER)?;
+    framebuffers.insert(key, framebuffer);
+    Ok(())
+}
+
+fn create_texture(desc: TextureDesc) -> Result<Texture> {
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    let (filter, format, ty) = match desc.depth_attachment() {
+        Some(gl::DEPTH_STENCIL_ATTACHMENT) => {
+            (gl::NEAREST, gl::DEPTH_STENCIL, gl::UNSIGNED_INT_24_8)
+        }
+        Some(_) => (gl::NEAREST, gl::DEPTH_COMPONENT, gl::FLOAT),
+        None => (gl::LINEAR, gl::RGBA, gl::FLOAT),
+    };
+    texture.parameter(gl::TEXTURE_MIN_FILTER, filter as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, filter as i32);
+    texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+    texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+    texture.image_2d(
+        0,
+        desc.internal_format,
+        desc.width,
+        desc.height,
+        format,
+        ty,
+        None,
+    );
+    texture.unbind();
+    Ok(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESC: TextureDesc = TextureDesc {
+        width: 4,
+        height: 4,
+        internal_format: gl::RGBA8,
+    };
+
+    #[test]
+    fn readers_run_after_writers() {
+        let mut graph = RenderGraph::new();
+        let scene = graph.create_texture("scene", DESC);
+        let bloom = graph.create_texture("bloom", DESC);
+        let backbuffer = graph.backbuffer(4, 4);
+        graph.add_pass("composite", &[scene, bloom], &[backbuffer], |_| {});
+        graph.add_pass("bloom", &[scene], &[bloom], |_| {});
+        graph.add_pass("scene", &[], &[scene], |_| {});
+        assert_eq!(graph.schedule().unwrap(), [2, 1, 0]);
+    }
+
+    #[test]
+    fn writers_of_one_resource_keep_their_order() {
+        let mut graph = RenderGraph::new();
+        let backbuffer = graph.backbuffer(4, 4);
+        graph.add_pass("opaque", &[], &[backbuffer], |_| {});
+        graph.add_pass("transparent", &[], &[backbuffer], |_| {});
+        graph.add_pass("overlay", &[], &[backbuffer], |_| {});
+        assert_eq!(graph.schedule().unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn unused_passes_are_culled() {
+        let mut graph = RenderGraph::new();
+        let unused = graph.create_texture("unused", DESC);
+        let kept = graph.create_texture("kept", DESC);
+        let backbuffer = graph.backbuffer(4, 4);
+        graph.add_pass("unused", &[], &[unused], |_| {});
+        graph.add_pass("kept", &[], &[kept], |_| {});
+        graph.add_pass("present", &[kept], &[backbuffer], |_| {});
+        assert_eq!(graph.schedule().unwrap(), [1, 2]);
+        assert!(graph.to_dot().contains("p0 [shape=box, style=dashed"));
+
+        graph.mark_output(unused);
+        assert_eq!(graph.schedule().unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let mut graph = RenderGraph::new();
+        let a = graph.create_texture("a", DESC);
+        let b = graph.create_texture("b", DESC);
+        let backbuffer = graph.backbuffer(4, 4);
+        graph.add_pass("a", &[b], &[a], |_| {});
+        graph.add_pass("b", &[a], &[b], |_| {});
+        graph.add_pass("present", &[a], &[backbuffer], |_| {});
+        let error = graph.schedule().unwrap_err();
+        assert!(error.to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn cycles_among_culled_passes_are_ignored() {
+        let mut graph = RenderGraph::new();
+        let a = graph.create_texture("a", DESC);
+        let b = graph.create_texture("b", DESC);
+        let backbuffer = graph.backbuffer(4, 4);
+        graph.add_pass("a", &[b], &[a], |_| {});
+        graph.add_pass("b", &[a], &[b], |_| {});
+        graph.add_pass("present", &[], &[backbuffer], |_| {});
+        assert_eq!(graph.schedule().unwrap(), [2]);
+    }
+}
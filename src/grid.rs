@@ -0,0 +1,149 @@
+//! An infinite ground grid drawn in screen space.
+//!
+//! [`InfiniteGrid::draw`] covers the viewport with one triangle. Each pixel unprojects its
+//! view ray, intersects it with the `y = 0` plane and shades grid lines there, antialiased
+//! by their screen-space derivatives, with every tenth line brighter and the X and Z axes
+//! in red and blue. The hit point's depth is written, so the grid is hidden by geometry
+//! in front of it and hides geometry below the ground. It fades out with distance before
+//! the lines get denser than the pixels.
+
+use anyhow::Result;
+
+use crate::depth::{self, DEPTH_GLSL};
+use crate::gl;
+use crate::math::{Mat4, Vec3};
+use crate::postprocess::FullscreenTriangle;
+use crate::shader::Program;
+
+const VERTEX_SHADER: &str = r#"
+uniform mat4 u_inverse_view_projection;
+out vec3 v_near;
+out vec3 v_far;
+
+vec3 unproject(vec2 ndc, float z) {
+    vec4 point = u_inverse_view_projection * vec4(ndc, z, 1.0);
+    return point.xyz / point.w;
+}
+
+void main() {
+    vec2 ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    // Two points along the pixel's view ray; the far plane may be at infinity.
+    v_near = unproject(ndc, near_ndc());
+    v_far = unproject(ndc, u_reversed_z ? 0.5 : 0.0);
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+uniform mat4 u_view_projection;
+uniform vec3 u_eye;
+uniform float u_cell_size;
+uniform float u_fade_distance;
+uniform vec3 u_color;
+in vec3 v_near;
+in vec3 v_far;
+out vec4 frag_color;
+
+// 1 on a line of the unit grid in `coord`, fading to 0 a pixel away.
+float lines(vec2 coord) {
+    vec2 distance = abs(fract(coord - 0.5) - 0.5) / fwidth(coord);
+    return 1.0 - min(min(distance.x, distance.y), 1.0);
+}
+
+void main() {
+    vec3 ray = v_far - v_near;
+    float t = -v_near.y / ray.y;
+    if (!(t > 0.0)) {
+        discard;
+    }
+    vec3 point = v_near + ray * t;
+    vec4 clip = u_view_projection * vec4(point, 1.0);
+    float ndc_depth = clip.z / clip.w;
+    gl_FragDepth = u_reversed_z ? ndc_depth : ndc_depth * 0.5 + 0.5;
+
+    vec2 coord = point.xz / u_cell_size;
+    float alpha = max(lines(coord) * 0.4, lines(coord * 0.1) * 0.8);
+    vec3 color = u_color;
+    vec2 pixel = fwidth(point.xz);
+    if (abs(point.z) < pixel.y) {
+        color = vec3(0.9, 0.25, 0.25);
+        alpha = 1.0;
+    } else if (abs(point.x) < pixel.x) {
+        color = vec3(0.25, 0.45, 0.95);
+        alpha = 1.0;
+    }
+    float distance = length(point - u_eye);
+    alpha *= 1.0 - smoothstep(u_fade_distance * 0.5, u_fade_distance, distance);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    frag_color = vec4(color, alpha);
+}
+"#;
+
+pub struct InfiniteGrid {
+    program: Program,
+    triangle: FullscreenTriangle,
+    /// Spacing of the minor lines in world units; every tenth line is a major one.
+    pub cell_size: f32,
+    /// Distance from the eye at which the grid has faded out completely.
+    pub fade_distance: f32,
+    pub color: Vec3,
+}
+
+impl InfiniteGrid {
+    pub fn new() -> Result<InfiniteGrid> {
+        let program = Program::from_sources(
+            &format!("#version 330 core\n{}\n{}", DEPTH_GLSL, VERTEX_SHADER),
+            &format!("#version 330 core\n{}\n{}", DEPTH_GLSL, FRAGMENT_SHADER),
+        )?;
+        program.label("infinite grid");
+        Ok(InfiniteGrid {
+            program,
+            triangle: FullscreenTriangle::new()?,
+            cell_size: 1.0,
+            fade_distance: 100.0,
+            color: Vec3::splat(0.6),
+        })
+    }
+
+    /// Picks a power-of-ten cell size and a fade distance for a camera `distance` away
+    /// from what it looks at, so about ten major lines stay in view.
+    pub fn fit(&mut self, distance: f32) {
+        let distance = distance.max(1e-3);
+        self.cell_size = 10f32.powf((distance * 0.1).log10().floor());
+        self.fade_distance = distance * 10.0;
+    }
+
+    /// Blends the grid over the current framebuffer, depth-tested against what was drawn
+    /// before without writing depth.
+    pub fn draw(&self, view_projection: Mat4, eye: Vec3) {
+        let _span = tracing::debug_span!("pass", name = "infinite grid").entered();
+        self.program.use_program();
+        depth::set_uniforms(&self.program);
+        self.program.set_mat4(
+            "u_inverse_view_projection",
+            &view_projection.inverse().to_cols_array(),
+        );
+        self.program
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+        self.program.set_vec3("u_eye", eye.to_array());
+        self.program.set_float("u_cell_size", self.cell_size);
+        self.program
+            .set_float("u_fade_distance", self.fade_distance);
+        self.program.set_vec3("u_color", self.color.to_array());
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthFunc(depth::nearer_or_equal());
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.triangle.draw();
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(depth::nearer());
+        }
+    }
+}
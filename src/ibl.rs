@@ -0,0 +1,544 @@
+//! Image-based lighting from an HDR environment.
+//!
+//! [`Environment::from_hdr`] turns an equirectangular [`HdrImage`] into a cube map and
+//! precomputes the split-sum approximation on the GPU: a diffuse irradiance cube map, a
+//! specular cube map prefiltered for increasing roughness down its mip chain, and a
+//! BRDF lookup table. [`crate::material::MaterialShaders::set_environment`] feeds them
+//! to the PBR programs, and [`Environment::draw_skybox`] draws the environment itself
+//! behind the scene.
+//!
+//! The panorama is filtered to the cube map's resolution at an explicit level of detail
+//! rather than one derived from screen-space derivatives, which jump where the
+//! longitude wraps around and leave a visible seam. Converting a large panorama takes
+//! a moment; `hello-gl convert-env` does it once and [`Environment::save_ktx2`] writes
+//! the result, with the irradiance and prefiltered maps if asked, for
+//! [`Environment::load`] to pick up from a `.ktx2` file.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use crate::depth::{self, DEPTH_GLSL};
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::image::HdrImage;
+use crate::ktx2::{self, Ktx2};
+use crate::math::{Mat3, Mat4};
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::texture::{self, Texture};
+
+/// Maps the fullscreen triangle's `uv` to a direction through face `u_face` of the cube
+/// map being rendered, in [`Texture::image_cube_face`] order.
+const CUBE_FACE_GLSL: &str = r#"
+const float PI = 3.14159265359;
+uniform int u_face;
+
+vec3 face_direction(vec2 uv) {
+    vec2 p = uv * 2.0 - 1.0;
+    if (u_face == 0) return normalize(vec3(1.0, -p.y, -p.x));
+    if (u_face == 1) return normalize(vec3(-1.0, -p.y, p.x));
+    if (u_face == 2) return normalize(vec3(p.x, 1.0, p.y));
+    if (u_face == 3) return normalize(vec3(p.x, -1.0, -p.y));
+    if (u_face == 4) return normalize(vec3(p.x, -p.y, 1.0));
+    return normalize(vec3(-p.x, -p.y, -1.0));
+}
+"#;
+
+/// Low-discrepancy samples and GGX importance sampling around `n`.
+const SAMPLING_GLSL: &str = r#"
+float radical_inverse(uint bits) {
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return float(bits) * 2.3283064365386963e-10;
+}
+
+vec2 hammersley(uint i, uint n) {
+    return vec2(float(i) / float(n), radical_inverse(i));
+}
+
+vec3 importance_sample_ggx(vec2 xi, vec3 n, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 up = abs(n.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, n));
+    vec3 bitangent = cross(n, tangent);
+    return normalize(
+        tangent * cos(phi) * sin_theta + bitangent * sin(phi) * sin_theta + n * cos_theta
+    );
+}
+"#;
+
+const EQUIRECTANGULAR_SHADER: &str = r#"
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_equirectangular;
+// The panorama's mip level matching the cube map's texel size at the face centers.
+uniform float u_lod;
+
+void main() {
+    vec3 d = face_direction(uv);
+    vec2 st = vec2(atan(d.z, d.x) / (2.0 * PI) + 0.5, 0.5 - asin(clamp(d.y, -1.0, 1.0)) / PI);
+    frag_color = vec4(textureLod(u_equirectangular, st, u_lod).rgb, 1.0);
+}
+"#;
+
+const IRRADIANCE_SHADER: &str = r#"
+in vec2 uv;
+out vec4 frag_color;
+uniform samplerCube u_environment;
+uniform float u_source_lod;
+
+void main() {
+    vec3 n = face_direction(uv);
+    vec3 up = abs(n.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 right = normalize(cross(up, n));
+    up = cross(n, right);
+
+    const float DELTA = 0.05;
+    vec3 sum = vec3(0.0);
+    float count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += DELTA) {
+            vec3 t = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 d = t.x * right + t.y * up + t.z * n;
+            sum += textureLod(u_environment, d, u_source_lod).rgb * cos(theta) * sin(theta);
+            count += 1.0;
+        }
+    }
+    frag_color = vec4(PI * sum / count, 1.0);
+}
+"#;
+
+const PREFILTER_SHADER: &str = r#"
+in vec2 uv;
+out vec4 frag_color;
+uniform samplerCube u_environment;
+uniform float u_roughness;
+uniform float u_source_size;
+
+const uint SAMPLES = 512u;
+
+void main() {
+    vec3 n = face_direction(uv);
+    // Assume the view direction is the normal, as the split sum does.
+    vec3 v = n;
+    float a2 = pow(u_roughness, 4.0);
+    float texel_solid_angle = 4.0 * PI / (6.0 * u_source_size * u_source_size);
+
+    vec3 sum = vec3(0.0);
+    float weight = 0.0;
+    for (uint i = 0u; i < SAMPLES; ++i) {
+        vec3 h = importance_sample_ggx(hammersley(i, SAMPLES), n, u_roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+        float n_dot_l = dot(n, l);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        // Sample a blurrier level where samples are sparse, to avoid bright speckles.
+        float n_dot_h = max(dot(n, h), 0.0);
+        float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        float pdf = a2 / (PI * d * d) / 4.0 + 1e-4;
+        float sample_solid_angle = 1.0 / (float(SAMPLES) * pdf + 1e-4);
+        float lod = u_roughness == 0.0 ? 0.0 : 0.5 * log2(sample_solid_angle / texel_solid_angle);
+        sum += textureLod(u_environment, l, max(lod, 0.0)).rgb * n_dot_l;
+        weight += n_dot_l;
+    }
+    frag_color = vec4(sum / weight, 1.0);
+}
+"#;
+
+const BRDF_SHADER: &str = r#"
+in vec2 uv;
+out vec2 frag_color;
+
+const uint SAMPLES = 1024u;
+
+float geometry_schlick_ggx(float n_dot, float roughness) {
+    float k = roughness * roughness / 2.0;
+    return n_dot / (n_dot * (1.0 - k) + k);
+}
+
+void main() {
+    float n_dot_v = max(uv.x, 1e-3);
+    float roughness = uv.y;
+    vec3 v = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 n = vec3(0.0, 0.0, 1.0);
+
+    vec2 sum = vec2(0.0);
+    for (uint i = 0u; i < SAMPLES; ++i) {
+        vec3 h = importance_sample_ggx(hammersley(i, SAMPLES), n, roughness);
+        vec3 l = normalize(2.0 * dot(v, h) * h - v);
+        float n_dot_l = max(l.z, 0.0);
+        if (n_dot_l <= 0.0) {
+            continue;
+        }
+        float n_dot_h = max(h.z, 0.0);
+        float v_dot_h = max(dot(v, h), 0.0);
+        float g = geometry_schlick_ggx(n_dot_v, roughness)
+            * geometry_schlick_ggx(n_dot_l, roughness);
+        float visibility = g * v_dot_h / (n_dot_h * n_dot_v);
+        float fresnel = pow(1.0 - v_dot_h, 5.0);
+        sum += vec2(1.0 - fresnel, fresnel) * visibility;
+    }
+    frag_color = sum / float(SAMPLES);
+}
+"#;
+
+/// The skybox shaders follow the depth convention through [`DEPTH_GLSL`], pasted in
+/// front.
+const SKYBOX_VERTEX_SHADER: &str = r#"
+out vec2 ndc;
+void main() {
+    ndc = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(ndc, far_ndc(), 1.0);
+}
+"#;
+
+const SKYBOX_SHADER: &str = r#"
+in vec2 ndc;
+out vec4 frag_color;
+uniform samplerCube u_environment;
+uniform mat4 u_inverse_view_projection;
+uniform float u_intensity;
+
+void main() {
+    // Unproject onto the near plane, which stays finite with an infinite far plane.
+    vec4 direction = u_inverse_view_projection * vec4(ndc, near_ndc(), 1.0);
+    vec3 color = textureLod(u_environment, direction.xyz / direction.w, 0.0).rgb;
+    frag_color = vec4(color * u_intensity, 1.0);
+}
+"#;
+
+/// Cube map and lookup table sizes of an [`Environment`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvironmentConfig {
+    /// Edge length of the environment cube map the others are computed from.
+    pub size: i32,
+    pub irradiance_size: i32,
+    /// Edge length of the prefiltered cube map's sharpest level; each further level
+    /// halves it and stands for a rougher surface, down to 8 texels.
+    pub prefiltered_size: i32,
+    pub brdf_lut_size: i32,
+}
+
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        EnvironmentConfig {
+            size: 512,
+            irradiance_size: 32,
+            prefiltered_size: 128,
+            brdf_lut_size: 512,
+        }
+    }
+}
+
+/// An HDR environment and the textures the PBR shaders light with.
+pub struct Environment {
+    cube_map: Texture,
+    irradiance: Texture,
+    prefiltered: Texture,
+    prefiltered_levels: i32,
+    brdf_lut: Texture,
+    skybox: Program,
+    triangle: FullscreenTriangle,
+    /// Scales the environment's contribution to lighting and the skybox.
+    pub intensity: f32,
+}
+
+impl Environment {
+    /// Loads a Radiance `.hdr` equirectangular panorama, or a `.ktx2` cube map written by
+    /// [`Environment::save_ktx2`], with the default sizes.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Environment> {
+        let path = path.as_ref();
+        let config = EnvironmentConfig::default();
+        if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("ktx2"))
+        {
+            Environment::load_ktx2(path, config)
+        } else {
+            Environment::from_hdr(&HdrImage::load(path)?, config)
+        }
+    }
+
+    /// Builds the environment from an equirectangular panorama. Takes a moment on the
+    /// GPU, so do it at startup. Leaves the default framebuffer bound; the viewport must
+    /// be reset afterwards.
+    pub fn from_hdr(image: &HdrImage, config: EnvironmentConfig) -> Result<Environment> {
+        let _span = tracing::debug_span!("pass", name = "environment").entered();
+        let (triangle, framebuffer) = bake_targets()?;
+
+        let equirectangular = Texture::new(gl::TEXTURE_2D)?;
+        equirectangular.bind();
+        equirectangular.parameter(gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        equirectangular.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        equirectangular.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+        equirectangular.image_2d(
+            0,
+            gl::RGB16F,
+            image.width as i32,
+            image.height as i32,
+            gl::RGB,
+            gl::FLOAT,
+            Some(bytemuck::cast_slice(&image.pixels)),
+        );
+        equirectangular.generate_mipmaps();
+        equirectangular.unbind();
+
+        let levels = texture::mip_levels(config.size, config.size);
+        let cube_map = Texture::cube_map(gl::RGBA16F, config.size, levels)?;
+        cube_map.label("environment");
+        let program = compile(EQUIRECTANGULAR_SHADER, false)?;
+        program.use_program();
+        equirectangular.bind_unit(0);
+        program.set_int("u_equirectangular", 0);
+        // A face spans a quarter of the panorama's width.
+        let lod = (image.width as f32 / (4 * config.size) as f32)
+            .log2()
+            .max(0.0);
+        program.set_float("u_lod", lod);
+        render_faces(&framebuffer, &triangle, &program, &cube_map, config.size, 0)?;
+        cube_map.bind();
+        cube_map.generate_mipmaps();
+        cube_map.unbind();
+
+        Environment::bake(triangle, framebuffer, cube_map, config.size, None, config)
+    }
+
+    /// Loads a cube map written by [`Environment::save_ktx2`], with its irradiance and
+    /// prefiltered maps if they were saved next to it; otherwise computes them as
+    /// [`Environment::from_hdr`] does. `config.size` is ignored.
+    pub fn load_ktx2<P: AsRef<Path>>(path: P, config: EnvironmentConfig) -> Result<Environment> {
+        let path = path.as_ref();
+        let _span = tracing::debug_span!("pass", name = "environment").entered();
+        let ktx2 = Ktx2::load(path)?;
+        if ktx2.faces != 6 {
+            return Err(anyhow!("{} is not a cube map", path.display()));
+        }
+        let cube_map = ktx2.upload()?;
+        cube_map.label("environment");
+
+        let (irradiance_path, prefiltered_path) = map_paths(path);
+        let maps = if irradiance_path.exists() && prefiltered_path.exists() {
+            let irradiance = Ktx2::load(&irradiance_path)?.upload()?;
+            let prefiltered = Ktx2::load(&prefiltered_path)?;
+            let levels = prefiltered.levels.len() as i32;
+            Some((irradiance, prefiltered.upload()?, levels))
+        } else {
+            None
+        };
+
+        let (triangle, framebuffer) = bake_targets()?;
+        let size = ktx2.width as i32;
+        Environment::bake(triangle, framebuffer, cube_map, size, maps, config)
+    }
+
+    /// Computes the irradiance and prefiltered maps of `cube_map`, `size` texels wide,
+    /// unless given as `maps`, then the BRDF lookup table.
+    fn bake(
+        triangle: FullscreenTriangle,
+        framebuffer: Framebuffer,
+        cube_map: Texture,
+        size: i32,
+        maps: Option<(Texture, Texture, i32)>,
+        config: EnvironmentConfig,
+    ) -> Result<Environment> {
+        let (irradiance, prefiltered, prefiltered_levels) = match maps {
+            Some(maps) => maps,
+            None => {
+                let irradiance = Texture::cube_map(gl::RGBA16F, config.irradiance_size, 1)?;
+                let program = compile(IRRADIANCE_SHADER, false)?;
+                program.use_program();
+                cube_map.bind_unit(0);
+                program.set_int("u_environment", 0);
+                // A 64-texel level has plenty of detail for a cosine-weighted integral.
+                let source_lod = (size as f32 / 64.0).log2().max(0.0);
+                program.set_float("u_source_lod", source_lod);
+                render_faces(
+                    &framebuffer,
+                    &triangle,
+                    &program,
+                    &irradiance,
+                    config.irradiance_size,
+                    0,
+                )?;
+
+                let levels = texture::mip_levels(config.prefiltered_size / 8, 1).max(1);
+                let prefiltered = Texture::cube_map(gl::RGBA16F, config.prefiltered_size, levels)?;
+                let program = compile(PREFILTER_SHADER, true)?;
+                program.use_program();
+                cube_map.bind_unit(0);
+                program.set_int("u_environment", 0);
+                program.set_float("u_source_size", size as f32);
+                for level in 0..levels {
+                    let roughness = level as f32 / (levels - 1).max(1) as f32;
+                    program.set_float("u_roughness", roughness);
+                    let size = config.prefiltered_size >> level;
+                    render_faces(&framebuffer, &triangle, &program, &prefiltered, size, level)?;
+                }
+                (irradiance, prefiltered, levels)
+            }
+        };
+        irradiance.label("environment irradiance");
+        prefiltered.label("environment prefiltered");
+
+        let brdf_lut = Texture::new(gl::TEXTURE_2D)?;
+        brdf_lut.bind();
+        brdf_lut.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        brdf_lut.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        let size = config.brdf_lut_size;
+        brdf_lut.image_2d(0, gl::RG16F, size, size, gl::RG, gl::FLOAT, None);
+        brdf_lut.unbind();
+        brdf_lut.label("BRDF LUT");
+        let program = compile(BRDF_SHADER, true)?;
+        program.use_program();
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &brdf_lut, 0);
+        framebuffer.check_status(gl::FRAMEBUFFER)?;
+        unsafe {
+            gl::Viewport(0, 0, size, size);
+        }
+        triangle.draw();
+
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        let skybox = Program::from_sources(
+            &format!(
+                "#version 330 core\n{}\n{}",
+                DEPTH_GLSL, SKYBOX_VERTEX_SHADER
+            ),
+            &format!("#version 330 core\n{}\n{}", DEPTH_GLSL, SKYBOX_SHADER),
+        )?;
+        Ok(Environment {
+            cube_map,
+            irradiance,
+            prefiltered,
+            prefiltered_levels,
+            brdf_lut,
+            skybox,
+            triangle,
+            intensity: 1.0,
+        })
+    }
+
+    /// Writes the mipmapped cube map to `path` as half floats, and with `maps` the
+    /// irradiance and prefiltered maps next to it, as `<name>.irradiance.ktx2` and
+    /// `<name>.prefiltered.ktx2`, where [`Environment::load_ktx2`] looks for them. Needs
+    /// desktop GL.
+    pub fn save_ktx2<P: AsRef<Path>>(&self, path: P, maps: bool) -> Result<()> {
+        let path = path.as_ref();
+        Ktx2::read(&self.cube_map, ktx2::Format::Rgba16f)?.save(path)?;
+        if maps {
+            let (irradiance_path, prefiltered_path) = map_paths(path);
+            Ktx2::read(&self.irradiance, ktx2::Format::Rgba16f)?.save(irradiance_path)?;
+            Ktx2::read(&self.prefiltered, ktx2::Format::Rgba16f)?.save(prefiltered_path)?;
+        }
+        Ok(())
+    }
+
+    /// The environment itself, mipmapped.
+    pub fn cube_map(&self) -> &Texture {
+        &self.cube_map
+    }
+
+    /// Cosine-weighted irradiance for diffuse lighting, looked up by normal.
+    pub fn irradiance(&self) -> &Texture {
+        &self.irradiance
+    }
+
+    /// Specular radiance looked up by reflection vector, at mip level roughness times
+    /// [`Environment::prefiltered_max_lod`].
+    pub fn prefiltered(&self) -> &Texture {
+        &self.prefiltered
+    }
+
+    pub fn prefiltered_max_lod(&self) -> f32 {
+        (self.prefiltered_levels - 1) as f32
+    }
+
+    /// Scale (red) and bias (green) applied to F0 for a given `n·v` (u) and roughness
+    /// (v).
+    pub fn brdf_lut(&self) -> &Texture {
+        &self.brdf_lut
+    }
+
+    /// Draws the environment wherever the depth buffer is still at the far plane. Call
+    /// after the opaque geometry, with depth testing enabled.
+    pub fn draw_skybox(&self, view: Mat4, projection: Mat4) {
+        let rotation = Mat4::from_mat3(Mat3::from_mat4(view));
+        let inverse_view_projection = (projection * rotation).inverse();
+        self.skybox.use_program();
+        self.cube_map.bind_unit(0);
+        self.skybox.set_int("u_environment", 0);
+        self.skybox.set_mat4(
+            "u_inverse_view_projection",
+            &inverse_view_projection.to_cols_array(),
+        );
+        self.skybox.set_float("u_intensity", self.intensity);
+        depth::set_uniforms(&self.skybox);
+        unsafe {
+            gl::DepthFunc(depth::nearer_or_equal());
+            gl::DepthMask(gl::FALSE);
+        }
+        self.triangle.draw();
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(depth::nearer());
+        }
+    }
+}
+
+fn compile(fragment: &str, sampling: bool) -> Result<Program> {
+    let fragment = format!(
+        "#version 330 core\n{}\n{}\n{}",
+        CUBE_FACE_GLSL,
+        if sampling { SAMPLING_GLSL } else { "" },
+        fragment
+    );
+    Program::from_sources(FULLSCREEN_VERTEX_SHADER, &fragment)
+}
+
+/// The triangle and framebuffer the maps are rendered with, set up for it.
+fn bake_targets() -> Result<(FullscreenTriangle, Framebuffer)> {
+    let triangle = FullscreenTriangle::new()?;
+    let framebuffer = Framebuffer::new()?;
+    framebuffer.bind(gl::FRAMEBUFFER);
+    unsafe {
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::BLEND);
+    }
+    Ok((triangle, framebuffer))
+}
+
+/// Where the irradiance and prefiltered maps of the cube map at `path` are saved.
+fn map_paths(path: &Path) -> (PathBuf, PathBuf) {
+    (
+        path.with_extension("irradiance.ktx2"),
+        path.with_extension("prefiltered.ktx2"),
+    )
+}
+
+/// Renders `program` into all six faces of mip `level` of `target`, `size` texels wide.
+fn render_faces(
+    framebuffer: &Framebuffer,
+    triangle: &FullscreenTriangle,
+    program: &Program,
+    target: &Texture,
+    size: i32,
+    level: i32,
+) -> Result<()> {
+    unsafe {
+        gl::Viewport(0, 0, size, size);
+    }
+    for face in 0..6 {
+        framebuffer.attach_cube_face(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, target, face, level);
+        framebuffer.check_status(gl::FRAMEBUFFER)?;
+        program.set_int("u_face", face as i32);
+        triangle.draw();
+    }
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::pixels;
+
+/// A decoded image with tightly packed 8-bit RGBA pixels.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| anyhow!("Failed to open {}: {}", path.as_ref().display(), e))?;
+        Image::from_png(BufReader::new(file))
+    }
+
+    pub fn from_png<R: Read>(reader: R) -> Result<Image> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        buf.truncate(info.buffer_size());
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels: png_to_rgba(buf, info.color_type)?,
+        })
+    }
+
+    /// Writes the image as an RGBA PNG.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.as_ref().display(), e))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+
+    /// Reverses the row order, converting between GL's bottom-up rows and top-down ones.
+    pub fn flip_vertically(&mut self) {
+        pixels::flip_rows(&mut self.pixels, self.width as usize * 4);
+    }
+}
+
+/// Expands 8-bit PNG samples of `color_type`, as decoded with
+/// `png::Transformations::normalize_to_color8`, to RGBA.
+pub(crate) fn png_to_rgba(buf: Vec<u8>, color_type: png::ColorType) -> Result<Vec<u8>> {
+    Ok(match color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => pixels::rgb_to_rgba(&buf),
+        png::ColorType::GrayscaleAlpha => pixels::gray_alpha_to_rgba(&buf),
+        png::ColorType::Grayscale => pixels::gray_to_rgba(&buf),
+        png::ColorType::Indexed => return Err(anyhow!("Unexpanded indexed PNG")),
+    })
+}
+
+/// A decoded high dynamic range image with linear RGB pixels and top-down rows.
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[f32; 3]>,
+}
+
+impl HdrImage {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<HdrImage> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| anyhow!("Failed to open {}: {}", path.as_ref().display(), e))?;
+        HdrImage::from_radiance(BufReader::new(file))
+            .map_err(|e| anyhow!("Failed to load {}: {}", path.as_ref().display(), e))
+    }
+
+    /// Decodes a Radiance `.hdr` file in the usual `-Y height +X width` orientation,
+    /// with flat or run-length encoded scanlines.
+    pub fn from_radiance<R: Read>(mut reader: R) -> Result<HdrImage> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut lines = data.split(|&b| b == b'\n');
+        let mut offset = 0;
+        let mut next_line = || {
+            let line = lines.next().ok_or_else(|| anyhow!("Truncated header"))?;
+            offset += line.len() + 1;
+            Ok::<_, anyhow::Error>(String::from_utf8_lossy(line).into_owned())
+        };
+
+        let magic = next_line()?;
+        if !magic.starts_with("#?") {
+            return Err(anyhow!("Not a Radiance file"));
+        }
+        loop {
+            let line = next_line()?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some(format) = line.strip_prefix("FORMAT=") {
+                if format != "32-bit_rle_rgbe" {
+                    return Err(anyhow!("Unsupported pixel format {}", format));
+                }
+            }
+        }
+        let resolution = next_line()?;
+        let (width, height) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", height, "+X", width] => (width.parse::<u32>()?, height.parse::<u32>()?),
+            _ => return Err(anyhow!("Unsupported orientation `{}`", resolution)),
+        };
+
+        let mut bytes = data[offset.min(data.len())..].iter().copied();
+        let mut next = || bytes.next().ok_or_else(|| anyhow!("Truncated pixel data"));
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        let mut scanline = vec![[0u8; 4]; width as usize];
+        for _ in 0..height {
+            let header = [next()?, next()?, next()?, next()?];
+            let encoded_width = u32::from(header[2]) << 8 | u32::from(header[3]);
+            if (8..0x8000).contains(&width)
+                && header[..2] == [2, 2]
+                && header[2] & 0x80 == 0
+                && encoded_width == width
+            {
+                // Each channel is stored separately as runs and literal spans.
+                for channel in 0..4 {
+                    let mut x = 0;
+                    while x < scanline.len() {
+                        let count = next()? as usize;
+                        let (count, run) = if count > 128 {
+                            (count - 128, Some(next()?))
+                        } else {
+                            (count, None)
+                        };
+                        if count == 0 || x + count > scanline.len() {
+                            return Err(anyhow!("Corrupt run-length encoding"));
+                        }
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = match run {
+                                Some(value) => value,
+                                None => next()?,
+                            };
+                        }
+                        x += count;
+                    }
+                }
+            } else {
+                scanline[0] = header;
+                for pixel in &mut scanline[1..] {
+                    *pixel = [next()?, next()?, next()?, next()?];
+                }
+            }
+            pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+                if e == 0 {
+                    [0.0; 3]
+                } else {
+                    let scale = 2f32.powi(i32::from(e) - 136);
+                    [r, g, b].map(|c| (f32::from(c) + 0.5) * scale)
+                }
+            }));
+        }
+
+        Ok(HdrImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Writes the image as a Radiance `.hdr` file, which [`HdrImage::load`] reads back.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.as_ref().display(), e))?;
+        let mut writer = BufWriter::new(file);
+        self.to_radiance(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Encodes the image as RGBE with flat scanlines, which every reader accepts.
+    /// Negative and non-finite components are stored as 0.
+    pub fn to_radiance<W: Write>(&self, mut writer: W) -> Result<()> {
+        if self.pixels.len() != self.width as usize * self.height as usize {
+            return Err(anyhow!("Expected {}x{} pixels", self.width, self.height));
+        }
+        write!(
+            writer,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )?;
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            let [r, g, b] = pixel.map(|c| if c.is_finite() { c.max(0.0) } else { 0.0 });
+            let max = r.max(g).max(b);
+            if max < 1e-32 {
+                data.extend([0; 4]);
+                continue;
+            }
+            // The shared exponent makes the largest component's mantissa [128, 256).
+            let mut exponent = max.log2().floor() as i32 + 1;
+            if max / 2f32.powi(exponent) >= 1.0 {
+                exponent += 1;
+            }
+            let scale = 256.0 / 2f32.powi(exponent);
+            data.extend([r, g, b].map(|c| (c * scale).min(255.0) as u8));
+            data.push((exponent + 128).clamp(0, 255) as u8);
+        }
+        writer.write_all(&data)?;
+        Ok(())
+    }
+}
+
+/// Texels read back from the GPU, with top-down rows, widened to four channels per
+/// texel by the class of their internal format. See [`crate::texture::Texture::read_pixels`].
+pub enum TexelImage {
+    /// 8-bit normalized formats (`GL_R8` to `GL_RGBA8`, sRGB included), as stored.
+    Rgba8(Image),
+    /// Float, half float, 16-bit normalized and depth formats. Depth is repeated in red,
+    /// green and blue with an alpha of 1.
+    Float {
+        width: u32,
+        height: u32,
+        pixels: Vec<[f32; 4]>,
+    },
+    /// Integer formats. Signed ones keep their two's complement bits; cast back with
+    /// `as i32`.
+    Integer {
+        width: u32,
+        height: u32,
+        pixels: Vec<[u32; 4]>,
+    },
+}
+
+impl TexelImage {
+    pub fn width(&self) -> u32 {
+        match self {
+            TexelImage::Rgba8(image) => image.width,
+            TexelImage::Float { width, .. } | TexelImage::Integer { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match self {
+            TexelImage::Rgba8(image) => image.height,
+            TexelImage::Float { height, .. } | TexelImage::Integer { height, .. } => *height,
+        }
+    }
+
+    /// The texels as `RGBA8`: floats clamped to [0, 1] without any transfer function,
+    /// integers saturated at 255.
+    pub fn to_image(&self) -> Image {
+        let pixels = match self {
+            TexelImage::Rgba8(image) => image.pixels.clone(),
+            TexelImage::Float { pixels, .. } => pixels
+                .iter()
+                .flat_map(|texel| texel.map(pixels::f32_to_unorm8))
+                .collect(),
+            TexelImage::Integer { pixels, .. } => pixels
+                .iter()
+                .flat_map(|texel| texel.map(|c| c.min(255) as u8))
+                .collect(),
+        };
+        Image {
+            width: self.width(),
+            height: self.height(),
+            pixels,
+        }
+    }
+
+    /// Writes float texels as a Radiance file, dropping alpha, if `path` ends in `.hdr`;
+    /// everything else as a PNG of [`TexelImage::to_image`].
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let hdr = path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+        match self {
+            TexelImage::Float {
+                width,
+                height,
+                pixels,
+            } if hdr => HdrImage {
+                width: *width,
+                height: *height,
+                pixels: pixels.iter().map(|&[r, g, b, _]| [r, g, b]).collect(),
+            }
+            .save(path),
+            _ => self.to_image().save(path),
+        }
+    }
+}
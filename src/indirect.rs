@@ -0,0 +1,242 @@
+//! Multi-draw, base-vertex and indirect draw submission.
+//!
+//! `glMultiDraw*` issues many draws from client-side arrays in one call (GL 3.3).
+//! [`draw_elements_base_vertex`] offsets every index by a base vertex (GL 3.2, ES 3.2),
+//! so meshes sharing one vertex buffer can keep indices relative to their own first
+//! vertex; [`draw_elements_instanced_base_vertex_base_instance`] adds instancing with a
+//! base instance (GL 4.2).
+//! [`DrawIndirectBuffer`] keeps the draw parameters in a GPU buffer and submits them all
+//! with `glMultiDrawElementsIndirect` (GL 4.3), so large static scenes cost one call;
+//! builds below 4.3 have it only with the `gl-ext-indirect` feature.
+
+#[cfg(gl_indirect)]
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+#[cfg(gl_indirect)]
+use crate::buffer::Buffer;
+use crate::context;
+use crate::gl;
+use crate::mesh::PrimitiveMode;
+use crate::stats;
+use crate::validate;
+
+/// Draws `counts[i]` vertices starting at `firsts[i]` for every `i`.
+pub fn multi_draw_arrays(mode: gl::types::GLenum, firsts: &[i32], counts: &[i32]) {
+    assert_eq!(firsts.len(), counts.len());
+    validate::draw("multi-draw arrays");
+    unsafe {
+        gl::MultiDrawArrays(mode, firsts.as_ptr(), counts.as_ptr(), counts.len() as i32);
+    }
+    record(mode, counts);
+}
+
+/// Draws `counts[i]` indices starting at `first_indices[i]` of the bound element buffer
+/// for every `i`. Indices are `GL_UNSIGNED_INT`.
+pub fn multi_draw_elements(mode: gl::types::GLenum, first_indices: &[u32], counts: &[i32]) {
+    assert_eq!(first_indices.len(), counts.len());
+    let offsets: Vec<*const gl::types::GLvoid> = first_indices
+        .iter()
+        .map(|&first| (first as usize * std::mem::size_of::<u32>()) as *const _)
+        .collect();
+    validate::draw("multi-draw elements");
+    unsafe {
+        gl::MultiDrawElements(
+            mode,
+            counts.as_ptr(),
+            gl::UNSIGNED_INT,
+            offsets.as_ptr(),
+            counts.len() as i32,
+        );
+    }
+    record(mode, counts);
+}
+
+/// Whether the context supports [`draw_elements_base_vertex`] with a non-zero base
+/// vertex (GL 3.2, ES 3.2).
+pub fn base_vertex_supported() -> bool {
+    context::version() >= (3, 2) && gl::DrawElementsBaseVertex::is_loaded()
+}
+
+/// Whether the context supports [`draw_elements_instanced_base_vertex_base_instance`]
+/// with a non-zero base instance (GL 4.2 or `GL_ARB_base_instance`).
+pub fn base_instance_supported() -> bool {
+    let info = context::info();
+    let core = !info.es && context::version() >= (4, 2);
+    (core || info.has_extension("GL_ARB_base_instance"))
+        && gl::DrawElementsInstancedBaseVertexBaseInstance::is_loaded()
+}
+
+fn index_offset(first_index: u32) -> *const gl::types::GLvoid {
+    (first_index as usize * std::mem::size_of::<u32>()) as *const _
+}
+
+/// Draws `count` indices starting at `first_index` of the bound element buffer, adding
+/// `base_vertex` to each before fetching vertices. Indices are `GL_UNSIGNED_INT`.
+///
+/// A zero `base_vertex` is a plain `glDrawElements`, so it works on every context;
+/// anything else needs [`base_vertex_supported`].
+pub fn draw_elements_base_vertex(
+    mode: gl::types::GLenum,
+    count: i32,
+    first_index: u32,
+    base_vertex: i32,
+) {
+    validate::draw("base-vertex elements");
+    unsafe {
+        if base_vertex == 0 {
+            gl::DrawElements(mode, count, gl::UNSIGNED_INT, index_offset(first_index));
+        } else {
+            gl::DrawElementsBaseVertex(
+                mode,
+                count,
+                gl::UNSIGNED_INT,
+                index_offset(first_index),
+                base_vertex,
+            );
+        }
+    }
+    record(mode, &[count]);
+}
+
+/// Draws `instance_count` instances of `count` indices starting at `first_index`, adding
+/// `base_vertex` to each index and `base_instance` to the instance index used for
+/// attributes with a divisor (not to `gl_InstanceID`).
+///
+/// Falls back to `glDrawElementsInstanced` or `glDrawElementsInstancedBaseVertex` when
+/// the offsets are zero, so only a non-zero `base_instance` needs
+/// [`base_instance_supported`].
+pub fn draw_elements_instanced_base_vertex_base_instance(
+    mode: gl::types::GLenum,
+    count: i32,
+    first_index: u32,
+    instance_count: i32,
+    base_vertex: i32,
+    base_instance: u32,
+) {
+    validate::draw("instanced base-vertex elements");
+    let offset = index_offset(first_index);
+    unsafe {
+        if base_instance != 0 {
+            gl::DrawElementsInstancedBaseVertexBaseInstance(
+                mode,
+                count,
+                gl::UNSIGNED_INT,
+                offset,
+                instance_count,
+                base_vertex,
+                base_instance,
+            );
+        } else if base_vertex != 0 {
+            gl::DrawElementsInstancedBaseVertex(
+                mode,
+                count,
+                gl::UNSIGNED_INT,
+                offset,
+                instance_count,
+                base_vertex,
+            );
+        } else {
+            gl::DrawElementsInstanced(mode, count, gl::UNSIGNED_INT, offset, instance_count);
+        }
+    }
+    let triangles =
+        PrimitiveMode::from_gl(mode).map_or(0, |mode| mode.triangle_count(count as usize));
+    stats::record_draw(triangles * instance_count.max(0) as usize);
+}
+
+fn record(mode: gl::types::GLenum, counts: &[i32]) {
+    let mode = PrimitiveMode::from_gl(mode);
+    for &count in counts {
+        stats::record_draw(mode.map_or(0, |mode| mode.triangle_count(count as usize)));
+    }
+}
+
+/// Layout of one `glDrawElementsIndirect` command.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawElementsIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    /// Offset added to the instance index for attributes with a divisor; lets every
+    /// command pick its own per-instance data.
+    pub base_instance: u32,
+}
+
+/// Whether the context supports [`DrawIndirectBuffer`] (GL 4.3).
+#[cfg(gl_indirect)]
+pub fn indirect_supported() -> bool {
+    context::version() >= (4, 3) && gl::MultiDrawElementsIndirect::is_loaded()
+}
+
+/// Draw commands stored on the GPU.
+#[cfg(gl_indirect)]
+pub struct DrawIndirectBuffer {
+    buffer: Buffer,
+    len: usize,
+    triangles: usize,
+}
+
+#[cfg(gl_indirect)]
+impl DrawIndirectBuffer {
+    pub fn new(commands: &[DrawElementsIndirectCommand]) -> Result<DrawIndirectBuffer> {
+        if !indirect_supported() {
+            return Err(anyhow!("Indirect multi-draw requires OpenGL 4.3"));
+        }
+        let mut buffer = DrawIndirectBuffer {
+            buffer: Buffer::new()?,
+            len: 0,
+            triangles: 0,
+        };
+        buffer.update(commands);
+        Ok(buffer)
+    }
+
+    /// Replaces all commands.
+    pub fn update(&mut self, commands: &[DrawElementsIndirectCommand]) {
+        self.buffer.bind(gl::DRAW_INDIRECT_BUFFER);
+        self.buffer.data(
+            gl::DRAW_INDIRECT_BUFFER,
+            bytemuck::cast_slice(commands),
+            gl::STATIC_DRAW,
+        );
+        self.buffer.unbind(gl::DRAW_INDIRECT_BUFFER);
+        self.len = commands.len();
+        self.triangles = commands
+            .iter()
+            .map(|c| c.count as usize / 3 * c.instance_count as usize)
+            .sum();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Submits every command as triangles with `GL_UNSIGNED_INT` indices from the bound
+    /// vertex array.
+    pub fn draw(&self) {
+        self.buffer.bind(gl::DRAW_INDIRECT_BUFFER);
+        validate::draw("indirect commands");
+        unsafe {
+            gl::MultiDrawElementsIndirect(
+                gl::TRIANGLES,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+                self.len as i32,
+                0,
+            );
+        }
+        self.buffer.unbind(gl::DRAW_INDIRECT_BUFFER);
+        stats::record_draw(self.triangles);
+    }
+}
@@ -0,0 +1,180 @@
+//! Keyboard, mouse and (with the `gamepad` feature) controller state.
+//!
+//! Feed every event to [`Input::window_event`] and query the state in `update`, then call
+//! [`Input::end_frame`] to reset the per-frame mouse motion and scroll and to poll
+//! gamepads.
+
+use std::collections::HashSet;
+
+#[cfg(feature = "gamepad")]
+use anyhow::Result;
+
+use crate::event::{
+    ElementState, KeyCode, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent,
+};
+#[cfg(feature = "gamepad")]
+use crate::gamepad::{self, Bindings, Button, GamepadState, Gamepads, Side};
+use crate::math::Vec2;
+
+/// Lines per scroll-wheel notch, for converting pixel deltas from touchpads.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Debug, Default)]
+pub struct Input {
+    keys: HashSet<KeyCode>,
+    buttons: HashSet<MouseButton>,
+    cursor: Option<Vec2>,
+    motion: Vec2,
+    scroll: f32,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
+    #[cfg(feature = "gamepad")]
+    gamepad: GamepadState,
+    #[cfg(feature = "gamepad")]
+    bindings: Bindings,
+    /// `None` for [`gamepad::DEFAULT_DEAD_ZONE`].
+    #[cfg(feature = "gamepad")]
+    dead_zone: Option<f32>,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        key: Some(key),
+                        ..
+                    },
+            } => {
+                match state {
+                    ElementState::Pressed => self.keys.insert(*key),
+                    ElementState::Released => self.keys.remove(key),
+                };
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                match state {
+                    ElementState::Pressed => self.buttons.insert(*button),
+                    ElementState::Released => self.buttons.remove(button),
+                };
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                if let Some(previous) = self.cursor {
+                    self.motion += position - previous;
+                }
+                self.cursor = Some(position);
+            }
+            WindowEvent::CursorLeft => self.cursor = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+                };
+            }
+            // Keys released while unfocused would otherwise stay pressed.
+            WindowEvent::Focused(false) => {
+                self.keys.clear();
+                self.buttons.clear();
+            }
+            _ => (),
+        }
+    }
+
+    /// Resets the mouse motion and scroll and polls gamepads; call at the end of
+    /// `update`.
+    pub fn end_frame(&mut self) {
+        self.motion = Vec2::ZERO;
+        self.scroll = 0.0;
+        #[cfg(feature = "gamepad")]
+        if let Some(gamepads) = &mut self.gamepads {
+            gamepads.poll(&mut self.gamepad);
+        }
+    }
+
+    /// Whether `key` is held, or a gamepad button bound to it.
+    pub fn is_pressed(&self, key: KeyCode) -> bool {
+        #[cfg(feature = "gamepad")]
+        if self
+            .bindings
+            .0
+            .iter()
+            .any(|&(button, bound)| bound == key && self.gamepad.buttons.contains(&button))
+        {
+            return true;
+        }
+        self.keys.contains(&key)
+    }
+
+    pub fn is_button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.contains(&button)
+    }
+
+    /// `1.0` if any of `positive` is held, `-1.0` if any of `negative` is, `0.0` if both
+    /// or neither.
+    pub fn axis(&self, negative: &[KeyCode], positive: &[KeyCode]) -> f32 {
+        let held = |keys: &[KeyCode]| keys.iter().any(|key| self.is_pressed(*key));
+        held(positive) as i32 as f32 - held(negative) as i32 as f32
+    }
+
+    /// Cursor position in physical pixels from the top left, while over the window.
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    /// Cursor movement in physical pixels since the last [`Input::end_frame`].
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.motion
+    }
+
+    /// Scroll-wheel lines since the last [`Input::end_frame`]; positive away from the
+    /// user.
+    pub fn scroll(&self) -> f32 {
+        self.scroll
+    }
+
+    /// Starts polling gamepads in [`Input::end_frame`].
+    #[cfg(feature = "gamepad")]
+    pub fn enable_gamepads(&mut self) -> Result<()> {
+        self.gamepads = Some(Gamepads::new()?);
+        Ok(())
+    }
+
+    #[cfg(feature = "gamepad")]
+    pub fn is_gamepad_pressed(&self, button: Button) -> bool {
+        self.gamepad.buttons.contains(&button)
+    }
+
+    /// Makes `button` count as `key` for [`Input::is_pressed`] and [`Input::axis`], in
+    /// addition to the D-pad's default arrow keys.
+    #[cfg(feature = "gamepad")]
+    pub fn bind_button(&mut self, button: Button, key: KeyCode) {
+        self.bindings.0.push((button, key));
+    }
+
+    /// Stick position with the dead zone applied, `y` pointing up; each component from
+    /// `-1.0` to `1.0`.
+    #[cfg(feature = "gamepad")]
+    pub fn stick(&self, side: Side) -> Vec2 {
+        let value = self.gamepad.sticks[side as usize];
+        let dead_zone = self.dead_zone.unwrap_or(gamepad::DEFAULT_DEAD_ZONE);
+        gamepad::apply_dead_zone(value, dead_zone)
+    }
+
+    /// How far the analog trigger on `side` is pulled, from `0.0` to `1.0`.
+    #[cfg(feature = "gamepad")]
+    pub fn trigger(&self, side: Side) -> f32 {
+        self.gamepad.triggers[side as usize]
+    }
+
+    /// Sets the stick dead zone as a fraction of full travel.
+    #[cfg(feature = "gamepad")]
+    pub fn set_dead_zone(&mut self, dead_zone: f32) {
+        self.dead_zone = Some(dead_zone.clamp(0.0, 0.99));
+    }
+}
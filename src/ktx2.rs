@@ -0,0 +1,378 @@
+//! Reading and writing KTX2 files of uncompressed floating-point textures.
+//!
+//! Only what the crate bakes is supported: 2D textures and cube maps with a full or
+//! partial mip chain, one layer, no supercompression, in `RGBA16F` or `RGBA32F`. That
+//! covers precomputed [`crate::ibl::Environment`] maps, which load in milliseconds
+//! where converting the panorama takes a noticeable moment at startup. Other tools
+//! (`ktx info`, RenderDoc) open the files too.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::context;
+use crate::gl;
+use crate::texture::{self, Texture};
+
+const IDENTIFIER: [u8; 12] = [
+    0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a,
+];
+
+/// Bytes before the level index: identifier, header and index.
+const HEADER_SIZE: usize = 80;
+
+/// A texel format, by its `VkFormat`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// `VK_FORMAT_R16G16B16A16_SFLOAT`.
+    Rgba16f,
+    /// `VK_FORMAT_R32G32B32A32_SFLOAT`.
+    Rgba32f,
+}
+
+impl Format {
+    fn vk_format(self) -> u32 {
+        match self {
+            Format::Rgba16f => 97,
+            Format::Rgba32f => 109,
+        }
+    }
+
+    fn from_vk_format(vk_format: u32) -> Result<Format> {
+        match vk_format {
+            97 => Ok(Format::Rgba16f),
+            109 => Ok(Format::Rgba32f),
+            _ => Err(anyhow!("Unsupported VkFormat {}", vk_format)),
+        }
+    }
+
+    /// Bytes of one channel.
+    fn type_size(self) -> usize {
+        match self {
+            Format::Rgba16f => 2,
+            Format::Rgba32f => 4,
+        }
+    }
+
+    pub fn bytes_per_texel(self) -> usize {
+        4 * self.type_size()
+    }
+
+    pub fn internal_format(self) -> gl::types::GLenum {
+        match self {
+            Format::Rgba16f => gl::RGBA16F,
+            Format::Rgba32f => gl::RGBA32F,
+        }
+    }
+
+    /// The pixel type to upload and read back texels with, keeping their bits.
+    pub fn gl_type(self) -> gl::types::GLenum {
+        match self {
+            Format::Rgba16f => gl::HALF_FLOAT,
+            Format::Rgba32f => gl::FLOAT,
+        }
+    }
+}
+
+/// An uncompressed 2D texture or cube map and its mip levels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ktx2 {
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+    /// `6` for cube maps, `1` otherwise.
+    pub faces: u32,
+    /// From level 0 down; each holds its faces one after another, in
+    /// [`Texture::image_cube_face`] order, with tightly packed rows in the order GL
+    /// uploads them.
+    pub levels: Vec<Vec<u8>>,
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("Truncated file"))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<usize> {
+    bytes
+        .get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().unwrap()) as usize)
+        .ok_or_else(|| anyhow!("Truncated file"))
+}
+
+impl Ktx2 {
+    /// Bytes of level `level`, all faces.
+    fn level_size(&self, level: usize) -> usize {
+        let width = (self.width >> level).max(1) as usize;
+        let height = (self.height >> level).max(1) as usize;
+        width * height * self.faces as usize * self.format.bytes_per_texel()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Ktx2> {
+        let path = path.as_ref();
+        let bytes =
+            std::fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ktx2::from_bytes(&bytes).map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Ktx2> {
+        if !bytes.starts_with(&IDENTIFIER) {
+            return Err(anyhow!("Not a KTX2 file"));
+        }
+        let format = Format::from_vk_format(u32_at(bytes, 12)?)?;
+        let width = u32_at(bytes, 20)?;
+        let height = u32_at(bytes, 24)?.max(1);
+        let (depth, layers) = (u32_at(bytes, 28)?, u32_at(bytes, 32)?);
+        let faces = u32_at(bytes, 36)?;
+        let level_count = u32_at(bytes, 40)?.max(1) as usize;
+        let supercompression = u32_at(bytes, 44)?;
+        if depth > 1 || layers > 1 {
+            return Err(anyhow!("3D and array textures are not supported"));
+        }
+        if faces != 1 && faces != 6 {
+            return Err(anyhow!("Invalid face count {}", faces));
+        }
+        if supercompression != 0 {
+            return Err(anyhow!("Supercompressed files are not supported"));
+        }
+        let mut ktx2 = Ktx2 {
+            format,
+            width,
+            height,
+            faces,
+            levels: Vec::with_capacity(level_count),
+        };
+        for level in 0..level_count {
+            let entry = HEADER_SIZE + level * 24;
+            let (offset, length) = (u64_at(bytes, entry)?, u64_at(bytes, entry + 8)?);
+            if length != ktx2.level_size(level) {
+                return Err(anyhow!(
+                    "Level {} has {} bytes, expected {}",
+                    level,
+                    length,
+                    ktx2.level_size(level)
+                ));
+            }
+            let data = bytes
+                .get(offset..offset + length)
+                .ok_or_else(|| anyhow!("Truncated file"))?;
+            ktx2.levels.push(data.to_vec());
+        }
+        Ok(ktx2)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let dfd = self.data_format_descriptor();
+        let kvd = key_value(b"KTXwriter", b"hello-gl");
+        let level_index = HEADER_SIZE + self.levels.len() * 24;
+        let dfd_offset = level_index;
+        let kvd_offset = dfd_offset + dfd.len();
+
+        // Levels are stored smallest first, each aligned to the texel size.
+        let align = self.format.bytes_per_texel().max(4);
+        let mut offsets = vec![0; self.levels.len()];
+        let mut end = kvd_offset + kvd.len();
+        for (level, offset) in offsets.iter_mut().enumerate().rev() {
+            end = end.next_multiple_of(align);
+            *offset = end;
+            end += self.levels[level].len();
+        }
+
+        let mut out = Vec::with_capacity(end);
+        out.extend_from_slice(&IDENTIFIER);
+        let header = [
+            self.format.vk_format(),
+            self.format.type_size() as u32,
+            self.width,
+            self.height,
+            0,
+            0,
+            self.faces,
+            self.levels.len() as u32,
+            0,
+            dfd_offset as u32,
+            dfd.len() as u32,
+            kvd_offset as u32,
+            kvd.len() as u32,
+        ];
+        for value in header {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        // No supercompression global data.
+        out.extend_from_slice(&[0; 16]);
+        for (level, offset) in offsets.iter().enumerate() {
+            let length = self.levels[level].len() as u64;
+            for value in [*offset as u64, length, length] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&dfd);
+        out.extend_from_slice(&kvd);
+        for (level, offset) in offsets.iter().enumerate().rev() {
+            out.resize(*offset, 0);
+            out.extend_from_slice(&self.levels[level]);
+        }
+        out
+    }
+
+    /// The basic descriptor block of Khronos Data Format 1.3: linear BT.709 RGBA with
+    /// signed float channels.
+    fn data_format_descriptor(&self) -> Vec<u8> {
+        const SAMPLE_FLOAT: u8 = 0x80;
+        const SAMPLE_SIGNED: u8 = 0x40;
+        let bits = self.format.type_size() * 8;
+        let block_size = 24 + 16 * 4;
+        let mut dfd = Vec::with_capacity(4 + block_size);
+        dfd.extend_from_slice(&(4 + block_size as u32).to_le_bytes());
+        // Khronos vendor, basic descriptor type, version 2.
+        dfd.extend_from_slice(&0u32.to_le_bytes());
+        dfd.extend_from_slice(&2u16.to_le_bytes());
+        dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+        // RGBSDA color model, BT.709 primaries, linear transfer, straight alpha.
+        dfd.extend_from_slice(&[1, 1, 1, 0]);
+        // A 1x1x1 texel block, then the bytes in each of the 8 planes.
+        dfd.extend_from_slice(&[0, 0, 0, 0]);
+        dfd.extend_from_slice(&[self.format.bytes_per_texel() as u8, 0, 0, 0, 0, 0, 0, 0]);
+        for (i, channel) in [0u8, 1, 2, 15].into_iter().enumerate() {
+            dfd.extend_from_slice(&((i * bits) as u16).to_le_bytes());
+            dfd.push((bits - 1) as u8);
+            dfd.push(channel | SAMPLE_FLOAT | SAMPLE_SIGNED);
+            dfd.extend_from_slice(&[0, 0, 0, 0]);
+            dfd.extend_from_slice(&(-1.0f32).to_bits().to_le_bytes());
+            dfd.extend_from_slice(&1.0f32.to_bits().to_le_bytes());
+        }
+        dfd
+    }
+
+    /// Reads every level of `texture`, a 2D texture or cube map with a level range set
+    /// as [`Texture::cube_map`] does, from GL. Needs desktop GL.
+    pub fn read(texture: &Texture, format: Format) -> Result<Ktx2> {
+        let faces: u32 = match texture.target() {
+            gl::TEXTURE_2D => 1,
+            gl::TEXTURE_CUBE_MAP => 6,
+            target => return Err(anyhow!("Can't save texture target {:#x}", target)),
+        };
+        let level_target = if faces == 6 {
+            gl::TEXTURE_CUBE_MAP_POSITIVE_X
+        } else {
+            gl::TEXTURE_2D
+        };
+        texture.bind();
+        let (mut width, mut height, mut max_level) = (0, 0, 0);
+        unsafe {
+            gl::GetTexLevelParameteriv(level_target, 0, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTexLevelParameteriv(level_target, 0, gl::TEXTURE_HEIGHT, &mut height);
+            gl::GetTexParameteriv(texture.target(), gl::TEXTURE_MAX_LEVEL, &mut max_level);
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        }
+        let level_count = (max_level + 1).min(texture::mip_levels(width, height));
+        let mut ktx2 = Ktx2 {
+            format,
+            width: width as u32,
+            height: height as u32,
+            faces,
+            levels: Vec::new(),
+        };
+        for level in 0..level_count.max(1) as usize {
+            let mut data = vec![0; ktx2.level_size(level)];
+            let face_size = data.len() / faces as usize;
+            for (face, chunk) in data.chunks_exact_mut(face_size).enumerate() {
+                unsafe {
+                    gl::GetTexImage(
+                        level_target + face as u32,
+                        level as i32,
+                        gl::RGBA,
+                        format.gl_type(),
+                        chunk.as_mut_ptr().cast(),
+                    );
+                }
+            }
+            ktx2.levels.push(data);
+        }
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        }
+        texture.unbind();
+        Ok(ktx2)
+    }
+
+    /// Creates a texture with the levels, linearly filtered between them when there are
+    /// several. Cube maps are clamped at the edges, 2D textures repeat.
+    pub fn upload(&self) -> Result<Texture> {
+        let levels = self.levels.len() as i32;
+        let (width, height) = (self.width as i32, self.height as i32);
+        let texture = if self.faces == 6 {
+            Texture::cube_map(self.format.internal_format(), width, levels)?
+        } else {
+            context::limits().check_texture_size(width, height)?;
+            let texture = Texture::new(gl::TEXTURE_2D)?;
+            texture.bind();
+            if levels > 1 {
+                texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+            }
+            texture.level_range(0, levels - 1);
+            texture.unbind();
+            texture
+        };
+        texture.bind();
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        }
+        for (level, data) in self.levels.iter().enumerate() {
+            let width = (width >> level).max(1);
+            let height = (height >> level).max(1);
+            let internal_format = self.format.internal_format();
+            let ty = self.format.gl_type();
+            if self.faces == 6 {
+                let face_size = data.len() / 6;
+                for (face, data) in data.chunks_exact(face_size).enumerate() {
+                    texture.image_cube_face(
+                        face as u32,
+                        level as i32,
+                        internal_format,
+                        width,
+                        gl::RGBA,
+                        ty,
+                        Some(data),
+                    );
+                }
+            } else {
+                texture.image_2d(
+                    level as i32,
+                    internal_format,
+                    width,
+                    height,
+                    gl::RGBA,
+                    ty,
+                    Some(data),
+                );
+            }
+        }
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        }
+        texture.unbind();
+        Ok(texture)
+    }
+}
+
+/// A key/value entry, padded to 4 bytes.
+fn key_value(key: &[u8], value: &[u8]) -> Vec<u8> {
+    let length = key.len() + 1 + value.len() + 1;
+    let mut entry = Vec::with_capacity(4 + length.next_multiple_of(4));
+    entry.extend_from_slice(&(length as u32).to_le_bytes());
+    entry.extend_from_slice(key);
+    entry.push(0);
+    entry.extend_from_slice(value);
+    entry.push(0);
+    entry.resize(4 + length.next_multiple_of(4), 0);
+    entry
+}
@@ -0,0 +1,137 @@
+pub mod animated_texture;
+pub mod animation;
+pub mod app;
+pub mod arena;
+pub mod assets;
+pub mod atlas;
+#[cfg(gl_compute)]
+pub mod atomic;
+pub mod blit;
+pub mod block_layout;
+pub mod buffer;
+pub mod builtins;
+pub mod camera2d;
+pub mod clear;
+pub mod clustered;
+pub mod context;
+pub mod crash;
+pub mod culling;
+pub mod cursor;
+pub mod debug;
+pub mod debug_draw;
+pub mod debug_view;
+pub mod deferred;
+pub mod depth;
+#[cfg(target_os = "linux")]
+pub mod dmabuf;
+pub mod draw;
+pub mod dsa;
+pub mod dynamic_resolution;
+#[cfg(feature = "ecs")]
+pub mod ecs;
+#[cfg(feature = "egui")]
+pub mod egui;
+pub mod event;
+#[cfg(gl_compute)]
+pub mod exposure;
+pub mod feedback;
+pub mod framebuffer;
+#[cfg(feature = "gamepad")]
+pub mod gamepad;
+pub mod geometry_pool;
+pub mod gizmo;
+#[cfg(feature = "gltf")]
+pub mod gltf;
+#[cfg(all(gl_compute, gl_indirect))]
+pub mod gpu_culling;
+pub mod graph;
+pub mod grid;
+pub mod ibl;
+pub mod image;
+pub mod indirect;
+pub mod input;
+pub mod ktx2;
+#[cfg(gl_compute)]
+pub mod load_store;
+pub mod material;
+pub mod math;
+pub mod memory;
+pub mod mesh;
+pub mod noise;
+pub mod oit;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pacing;
+pub mod particles;
+pub mod per_frame;
+pub mod picking;
+pub mod pipeline;
+pub mod pixels;
+pub mod portal;
+pub mod postprocess;
+pub mod primitives;
+pub mod probe;
+pub mod profiler;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod quick;
+pub mod ray;
+#[cfg(feature = "renderdoc")]
+pub mod renderdoc;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod replay;
+pub mod scene;
+pub mod scene_file;
+pub mod scoped;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod settings;
+pub mod shader;
+pub mod shader_variants;
+pub mod shadow;
+pub mod sprite;
+pub mod ssao;
+pub mod state;
+pub mod stats;
+pub mod stream;
+pub mod sync;
+pub mod terrain;
+pub mod text;
+pub mod texture;
+pub mod texture_units;
+pub mod thread_pool;
+pub mod tracks;
+pub mod transient;
+#[cfg(all(feature = "egui", not(target_arch = "wasm32")))]
+pub mod tweak;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod upload;
+pub mod validate;
+pub mod vertex_format;
+#[cfg(all(feature = "video", not(target_arch = "wasm32")))]
+pub mod video;
+pub mod viewport;
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+pub mod web;
+
+/// OpenGL core bindings at the level of the `gl33` to `gl46` features, 4.5 by default.
+/// Below 4.3, compute shaders with storage buffers, images and atomic counters are left
+/// out along with the modules built on them, unless `gl-ext-compute` adds them as
+/// extensions; likewise `gl-ext-indirect` for `indirect::DrawIndirectBuffer`. With the
+/// `gl-trace` feature every call is logged with its arguments and result through
+/// `tracing`, at trace level with target `gl`. With `web` on `wasm32` this is `web::gl`,
+/// which forwards the calls to WebGL2.
+#[cfg(not(all(feature = "web", target_arch = "wasm32")))]
+#[allow(clippy::all)]
+pub mod gl {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+#[cfg(all(feature = "web", target_arch = "wasm32"))]
+pub use web::gl;
+
+/// OpenGL ES bindings at the level of the `gles30` to `gles32` features, 3.2 with `gles`
+/// alone, for ES-only enums and extensions. Loaded by the runner alongside [`gl`], which
+/// covers the functions shared with desktop GL.
+#[cfg(feature = "gles")]
+#[allow(clippy::all)]
+pub mod gles {
+    include!(concat!(env!("OUT_DIR"), "/gles_bindings.rs"));
+}
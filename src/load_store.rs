@@ -0,0 +1,168 @@
+//! Image load/store and memory barriers (GL 4.2 / `ARB_shader_image_load_store`).
+//!
+//! [`Texture::bind_image`] binds one level of a texture to an image unit, where shaders
+//! read and write it through `image2D` and friends instead of sampling it. Such writes
+//! are incoherent: issue a [`memory_barrier`] naming how the data is used next before
+//! drawing or dispatching anything that reads it.
+
+use std::ops::{BitOr, BitOrAssign};
+
+use anyhow::{anyhow, Result};
+
+use crate::context;
+use crate::gl;
+use crate::texture::Texture;
+
+/// Whether the context supports image load/store and memory barriers.
+pub fn is_supported() -> bool {
+    gl::BindImageTexture::is_loaded()
+        && gl::MemoryBarrier::is_loaded()
+        && (context::version() >= (4, 2)
+            || context::has_extension("GL_ARB_shader_image_load_store"))
+}
+
+/// How shaders may access a bound image; should match its `readonly` / `writeonly`
+/// qualifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+impl ImageAccess {
+    pub fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            ImageAccess::ReadOnly => gl::READ_ONLY,
+            ImageAccess::WriteOnly => gl::WRITE_ONLY,
+            ImageAccess::ReadWrite => gl::READ_WRITE,
+        }
+    }
+}
+
+/// The format shaders see an image in; matches the layout qualifier (`rgba8`, `r32f`,
+/// ...) of the image uniform and must be size-compatible with the texture's internal
+/// format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Rgba32F,
+    Rgba16F,
+    Rg32F,
+    Rg16F,
+    R32F,
+    R16F,
+    Rgba32UI,
+    Rgba8UI,
+    R32UI,
+    Rgba32I,
+    R32I,
+    Rgba8,
+    Rgba8Snorm,
+}
+
+impl ImageFormat {
+    pub fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            ImageFormat::Rgba32F => gl::RGBA32F,
+            ImageFormat::Rgba16F => gl::RGBA16F,
+            ImageFormat::Rg32F => gl::RG32F,
+            ImageFormat::Rg16F => gl::RG16F,
+            ImageFormat::R32F => gl::R32F,
+            ImageFormat::R16F => gl::R16F,
+            ImageFormat::Rgba32UI => gl::RGBA32UI,
+            ImageFormat::Rgba8UI => gl::RGBA8UI,
+            ImageFormat::R32UI => gl::R32UI,
+            ImageFormat::Rgba32I => gl::RGBA32I,
+            ImageFormat::R32I => gl::R32I,
+            ImageFormat::Rgba8 => gl::RGBA8,
+            ImageFormat::Rgba8Snorm => gl::RGBA8_SNORM,
+        }
+    }
+}
+
+/// A set of `GL_*_BARRIER_BIT`s, combined with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Barriers(gl::types::GLbitfield);
+
+impl Barriers {
+    pub const NONE: Barriers = Barriers(0);
+    /// Vertex attributes sourced from buffers written by shaders.
+    pub const VERTEX_ATTRIB_ARRAY: Barriers = Barriers(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: Barriers = Barriers(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: Barriers = Barriers(gl::UNIFORM_BARRIER_BIT);
+    /// Sampling textures written through images.
+    pub const TEXTURE_FETCH: Barriers = Barriers(gl::TEXTURE_FETCH_BARRIER_BIT);
+    /// Image loads and stores after earlier image stores.
+    pub const SHADER_IMAGE_ACCESS: Barriers = Barriers(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND: Barriers = Barriers(gl::COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER: Barriers = Barriers(gl::PIXEL_BUFFER_BARRIER_BIT);
+    /// `glTex(Sub)Image`, `glCopyTex(Sub)Image` and `glGetTexImage` of written textures.
+    pub const TEXTURE_UPDATE: Barriers = Barriers(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE: Barriers = Barriers(gl::BUFFER_UPDATE_BARRIER_BIT);
+    /// Framebuffer reads and writes of attachments written through images.
+    pub const FRAMEBUFFER: Barriers = Barriers(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const TRANSFORM_FEEDBACK: Barriers = Barriers(gl::TRANSFORM_FEEDBACK_BARRIER_BIT);
+    pub const ATOMIC_COUNTER: Barriers = Barriers(gl::ATOMIC_COUNTER_BARRIER_BIT);
+    /// Shader storage buffers; GL 4.3.
+    pub const SHADER_STORAGE: Barriers = Barriers(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const ALL: Barriers = Barriers(gl::ALL_BARRIER_BITS);
+
+    pub fn bits(self) -> gl::types::GLbitfield {
+        self.0
+    }
+
+    pub fn contains(self, other: Barriers) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for Barriers {
+    type Output = Barriers;
+
+    fn bitor(self, other: Barriers) -> Barriers {
+        Barriers(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for Barriers {
+    fn bitor_assign(&mut self, other: Barriers) {
+        self.0 |= other.0;
+    }
+}
+
+/// Orders earlier incoherent shader writes before the later accesses in `barriers`.
+pub fn memory_barrier(barriers: Barriers) {
+    unsafe {
+        gl::MemoryBarrier(barriers.bits());
+    }
+}
+
+impl Texture {
+    /// Binds mip `level` to image unit `unit`. `layer` selects one layer of an array,
+    /// cube or 3D texture; `None` binds all of them. Fails if the context lacks image
+    /// load/store.
+    pub fn bind_image(
+        &self,
+        unit: u32,
+        level: i32,
+        layer: Option<i32>,
+        access: ImageAccess,
+        format: ImageFormat,
+    ) -> Result<()> {
+        if !is_supported() {
+            return Err(anyhow!("Image load/store requires GL 4.2"));
+        }
+        unsafe {
+            gl::BindImageTexture(
+                unit,
+                self.id(),
+                level,
+                if layer.is_some() { gl::FALSE } else { gl::TRUE },
+                layer.unwrap_or(0),
+                access.to_gl(),
+                format.to_gl(),
+            );
+        }
+        Ok(())
+    }
+}
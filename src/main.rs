@@ -1,262 +1,513 @@
-use std::ffi::CStr;
+// The demo CLI needs a desktop window and files; on the web, see `examples/web_triangle.rs`.
+#![cfg_attr(target_arch = "wasm32", no_main)]
+#![cfg(not(target_arch = "wasm32"))]
 
-use anyhow::{anyhow, Result};
-use glutin::event::{Event, WindowEvent};
-use glutin::event_loop::{ControlFlow, EventLoop};
-use glutin::window::WindowBuilder;
-use glutin::ContextBuilder;
+use std::path::{Path, PathBuf};
 
-mod gl {
-    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+use anyhow::{anyhow, Result};
+use clap::{Parser, Subcommand};
+use hello_gl::app::{self, App, ContextConfig, HeadlessContext};
+use hello_gl::context;
+use hello_gl::crash;
+use hello_gl::event::{ElementState, KeyCode, KeyboardInput, WindowEvent};
+use hello_gl::framebuffer;
+use hello_gl::gl;
+use hello_gl::ibl::{Environment, EnvironmentConfig};
+use hello_gl::image::{HdrImage, Image};
+use hello_gl::replay::{Recorder, Replay};
+use hello_gl::settings::{self, Settings};
+use hello_gl::viewport::{self, Viewport};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+mod bench;
+mod demo;
+mod toy;
+
+use bench::Bench;
+use demo::{Kind, Model, Quad, Scene};
+use toy::Toy;
+
+/// Hosts the current scene and switches to another on the number keys or when a PNG,
+/// OBJ or glTF file is dropped on the window.
+struct Demo {
+    scene: Box<dyn Scene>,
+    size: (u32, u32),
 }
 
-struct VertexArray(gl::types::GLuint);
-
-impl VertexArray {
-    fn new() -> Result<VertexArray> {
-        let mut id = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut id);
-        }
-        if id == 0 {
-            return Err(anyhow!("Failed to create vertex array"));
-        } else {
-            Ok(VertexArray(id))
-        }
+impl Demo {
+    fn switch(&mut self, kind: Kind) {
+        self.replace(&format!("{:?}", kind), kind.create());
     }
 
-    fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.0);
+    /// Opens a dropped file: images in the quad scene, models in the model scene.
+    fn open(&mut self, path: &Path) {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let name = path.display().to_string();
+        match extension.as_deref() {
+            Some("png") => {
+                let scene = Quad::open(path).map(|quad| Box::new(quad) as Box<dyn Scene>);
+                self.replace(&name, scene);
+            }
+            Some("obj" | "gltf" | "glb") => {
+                let scene = Model::open(path).map(|model| Box::new(model) as Box<dyn Scene>);
+                self.replace(&name, scene);
+            }
+            _ => tracing::warn!("Don't know how to open {}", name),
         }
     }
 
-    fn unbind(&self) {
+    /// Switches to `scene` after resetting the GL state scenes may leave behind, or keeps
+    /// the current scene if creating the new one failed.
+    fn replace(&mut self, name: &str, scene: Result<Box<dyn Scene>>) {
         unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            gl::UseProgram(0);
             gl::BindVertexArray(0);
         }
+        match scene {
+            Ok(scene) => {
+                tracing::info!("Scene: {}", name);
+                self.scene = scene;
+                let (width, height) = self.size;
+                self.scene.resize(width, height);
+            }
+            Err(e) => tracing::error!("Failed to open {}: {:#}", name, e),
+        }
     }
 }
 
-struct Buffer(gl::types::GLuint);
+impl App for Demo {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+        viewport::reset(width, height);
+        self.scene.resize(width, height);
+    }
 
-impl Buffer {
-    fn new() -> Result<Buffer> {
-        let mut id = 0;
-        unsafe {
-            gl::GenBuffers(1, &mut id);
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    key: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let index = match key {
+                KeyCode::Digit1 => Some(0),
+                KeyCode::Digit2 => Some(1),
+                KeyCode::Digit3 => Some(2),
+                KeyCode::Digit4 => Some(3),
+                KeyCode::Digit5 => Some(4),
+                _ => None,
+            };
+            if let Some(index) = index {
+                self.switch(Kind::ALL[index]);
+                return;
+            }
         }
-        if id == 0 {
-            Err(anyhow!("Failed to create buffer"))
-        } else {
-            Ok(Buffer(id))
+        if let WindowEvent::DroppedFile(path) = event {
+            self.open(path);
+            return;
         }
+        self.scene.window_event(event);
     }
 
-    fn bind(&self, target: gl::types::GLenum) {
-        unsafe {
-            gl::BindBuffer(target, self.0);
-        }
+    fn update(&mut self, dt: f32) {
+        self.scene.update(dt);
     }
 
-    fn unbind(&self, target: gl::types::GLenum) {
-        unsafe {
-            gl::BindBuffer(target, 0);
-        }
+    fn render(&mut self) {
+        self.scene.render();
     }
+}
 
-    fn data(&self, target: gl::types::GLenum, data: &[u8], usage: gl::types::GLenum) {
-        unsafe {
-            gl::BufferData(
-                target,
-                data.len() as gl::types::GLsizeiptr,
-                data.as_ptr() as *const gl::types::GLvoid,
-                usage,
-            );
-        }
-    }
+const TITLE: &str = "A fantastic window!";
+
+/// The time step of `--headless` frames, in seconds.
+const HEADLESS_DT: f32 = 1.0 / 60.0;
+
+/// How often the title's frame rate is refreshed, in seconds.
+const TITLE_INTERVAL: f32 = 0.5;
+
+/// Demo scenes for the library. Flags override `hello-gl.toml` and `HELLO_GL_*` variables.
+/// Logging follows `RUST_LOG`, e.g. `RUST_LOG=hello_gl=debug` for per-frame and per-pass
+/// spans.
+#[derive(Debug, Parser)]
+struct Cli {
+    /// Window width in logical pixels.
+    #[arg(long)]
+    width: Option<u32>,
+    /// Window height in logical pixels.
+    #[arg(long)]
+    height: Option<u32>,
+    /// Wait for vertical blank when presenting.
+    #[arg(long, overrides_with = "no_vsync")]
+    vsync: bool,
+    #[arg(long, overrides_with = "vsync")]
+    no_vsync: bool,
+    /// MSAA samples of the window.
+    #[arg(long)]
+    samples: Option<u8>,
+    /// Context version to request, e.g. `4.3`, instead of the fallback chain.
+    #[arg(long, value_parser = parse_version)]
+    gl_version: Option<(u8, u8)>,
+    /// The first scene shown; the number keys 1-5 switch between them.
+    #[arg(long, value_enum, default_value_t = Kind::Triangle)]
+    scene: Kind,
+    /// Saves the last rendered frame as a PNG; exits after the first frame unless
+    /// `--frames` is given.
+    #[arg(long)]
+    screenshot: Option<PathBuf>,
+    /// Exits after rendering this many frames.
+    #[arg(long)]
+    frames: Option<u64>,
+    /// Renders this many frames with vsync and the frame limiter off, then writes
+    /// per-frame CPU and GPU timings to `--bench-output` and exits.
+    #[arg(long, value_name = "FRAMES")]
+    bench: Option<u64>,
+    /// Where `--bench` writes its report; JSON for a `.json` path, CSV otherwise.
+    #[arg(long, value_name = "PATH", default_value = "bench.csv")]
+    bench_output: PathBuf,
+    /// Records input and frame times to a file for `--replay`.
+    #[arg(long, value_name = "PATH", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// Plays back input and frame times recorded with `--record` instead of live input,
+    /// then exits. `--frames` and `--screenshot` default to the end of the recording.
+    #[arg(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+    /// Requests an OpenGL ES context; read by the runner.
+    #[arg(long)]
+    gles: bool,
+    /// Prints the context information; read by the runner.
+    #[arg(long)]
+    gl_info: bool,
+    /// Renders with Mesa's llvmpipe, for machines without a GPU.
+    #[arg(long)]
+    software: bool,
+    /// Checks the bindings before every draw and reports why one would render nothing.
+    #[arg(long)]
+    validate: bool,
+    /// Renders on a hidden window of exactly `--width` x `--height` pixels with a fixed
+    /// time step, for `--frames` frames (default 1), then exits. Combine with
+    /// `--screenshot` for reproducible images.
+    #[arg(long)]
+    headless: bool,
+    /// Runs on the SDL2 backend.
+    #[cfg(feature = "sdl2")]
+    #[arg(long)]
+    sdl: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-struct Shader(gl::types::GLuint);
-
-impl Shader {
-    fn from_source(kind: gl::types::GLenum, source: &str) -> Result<Shader> {
-        let id = unsafe { gl::CreateShader(kind) };
-        if id == 0 {
-            return Err(anyhow!("Failed to create shader"));
-        } else {
-            unsafe {
-                gl::ShaderSource(
-                    id,
-                    1,
-                    &(source.as_bytes().as_ptr().cast()),
-                    &(source.len().try_into().unwrap()),
-                );
-                gl::CompileShader(id);
-
-                let mut success = 0;
-                gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
-                if success == 0 {
-                    let mut buf: Vec<u8> = Vec::with_capacity(1024);
-                    let mut log_len = 0_i32;
-                    gl::GetShaderInfoLog(id, 1024, &mut log_len, buf.as_mut_ptr().cast());
-                    buf.set_len(log_len.try_into().unwrap());
-                    Err(anyhow!("{:?}", String::from_utf8(buf)))
-                } else {
-                    Ok(Shader(id))
-                }
-            }
-        }
-    }
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Runs a ShaderToy-style fragment shader, reloading it when it changes.
+    Toy {
+        /// GLSL file defining `mainImage`.
+        shader: PathBuf,
+        /// Image bound to the next of `iChannel0..3`.
+        #[arg(long = "channel", value_name = "IMAGE")]
+        channels: Vec<PathBuf>,
+    },
+    /// Renders a thumbnail of an OBJ or glTF model on a headless context and exits. The
+    /// model is framed automatically and lit like the model scene.
+    Render {
+        model: PathBuf,
+        /// Width and height of the image in pixels.
+        #[arg(long, default_value_t = 512)]
+        size: u32,
+        /// The PNG to write; defaults to the model's path with a `.png` extension.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Converts an equirectangular Radiance `.hdr` panorama into a mipmapped KTX2 cube
+    /// map on a headless context and exits, so image-based lighting starts without
+    /// converting it again.
+    ConvertEnv {
+        input: PathBuf,
+        /// The KTX2 file to write; defaults to the input's path with a `.ktx2` extension.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Width of the cube map's faces in texels.
+        #[arg(long, default_value_t = EnvironmentConfig::default().size)]
+        size: i32,
+        /// Also write the irradiance and prefiltered maps next to the cube map.
+        #[arg(long)]
+        maps: bool,
+    },
+}
 
-    fn delete(&self) {
-        unsafe {
-            gl::DeleteShader(self.0);
-        }
-    }
+fn parse_version(value: &str) -> Result<(u8, u8)> {
+    let (major, minor) = value
+        .split_once('.')
+        .ok_or_else(|| anyhow!("expected MAJOR.MINOR"))?;
+    Ok((major.parse()?, minor.parse()?))
 }
 
-struct Program(gl::types::GLuint);
+/// Counts frames around the app, taking the screenshot and ending the run as the flags
+/// ask, and shows the frame rate in the title.
+struct Frames {
+    app: Box<dyn App>,
+    rendered: u64,
+    limit: Option<u64>,
+    screenshot: Option<PathBuf>,
+    size: (u32, u32),
+    /// Set on the window at the first frame.
+    icon: Option<Image>,
+    /// Time and frames since the title last showed the frame rate.
+    title_time: f32,
+    title_frames: u64,
+}
 
-impl Program {
-    fn new() -> Result<Program> {
-        let id = unsafe { gl::CreateProgram() };
-        if id == 0 {
-            return Err(anyhow!("Failed to create program"));
-        } else {
-            Ok(Program(id))
-        }
+impl App for Frames {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+        self.app.resize(width, height);
     }
 
-    fn attach(&self, shader: &Shader) {
-        unsafe {
-            gl::AttachShader(self.0, shader.0);
-        }
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.app.window_event(event);
     }
 
-    fn link(&self) -> Result<()> {
-        unsafe {
-            gl::LinkProgram(self.0);
-
-            let mut success = 0;
-            gl::GetProgramiv(self.0, gl::LINK_STATUS, &mut success);
-            if success == 0 {
-                let mut buf: Vec<u8> = Vec::with_capacity(1024);
-                let mut log_len = 0_i32;
-                gl::GetProgramInfoLog(self.0, 1024, &mut log_len, buf.as_mut_ptr().cast());
-                buf.set_len(log_len.try_into().unwrap());
-                Err(anyhow!("{:?}", String::from_utf8(buf)))
-            } else {
-                Ok(())
+    fn update(&mut self, dt: f32) {
+        self.app.update(dt);
+        self.title_time += dt;
+        self.title_frames += 1;
+    }
+
+    fn update_window(&mut self, window: &mut dyn app::Window) {
+        if let Some(icon) = self.icon.take() {
+            if let Err(e) = window.set_icon(Some(&icon)) {
+                tracing::warn!("{:#}", e);
             }
         }
+        if self.title_time >= TITLE_INTERVAL {
+            let frame_time = self.title_time / self.title_frames as f32;
+            window.set_title(&format!(
+                "{} - {:.0} fps ({:.2} ms)",
+                title(),
+                1.0 / frame_time,
+                frame_time * 1000.0
+            ));
+            self.title_time = 0.0;
+            self.title_frames = 0;
+        }
+        self.app.update_window(window);
     }
 
-    fn use_program(&self) {
-        unsafe {
-            gl::UseProgram(self.0);
+    fn render(&mut self) {
+        self.app.render();
+        self.rendered += 1;
+        if self.exit_requested() {
+            if let Some(path) = &self.screenshot {
+                let (width, height) = self.size;
+                let image = framebuffer::read_pixels(Viewport::full(width, height));
+                match image.save(path) {
+                    Ok(()) => tracing::info!("Saved {}", path.display()),
+                    Err(e) => tracing::error!("{:#}", e),
+                }
+            }
         }
     }
-}
 
-/// Simple loading example
-fn main() {
-    let event_loop = EventLoop::new();
-    let window_builder = WindowBuilder::new().with_title("A fantastic window!");
+    fn exit_requested(&self) -> bool {
+        self.limit.is_some_and(|limit| self.rendered >= limit) || self.app.exit_requested()
+    }
 
-    let windowed_context = ContextBuilder::new()
-        .build_windowed(window_builder, &event_loop)
-        .unwrap();
+    fn context_lost(&mut self, status: context::ResetStatus) {
+        self.app.context_lost(status);
+    }
 
-    let windowed_context = unsafe { windowed_context.make_current().unwrap() };
+    fn context_restored(&mut self) -> Result<()> {
+        self.app.context_restored()
+    }
+}
 
-    println!(
-        "Pixel format of the window's GL context: {:?}",
-        windowed_context.get_pixel_format()
-    );
+/// Logs to stderr, at info level unless `RUST_LOG` says otherwise. Spans are reported
+/// when they close, with their duration.
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .init();
+}
 
-    // gl::load_with(|s| window.get_proc_address(s) as *const _);
-    gl::load_with(|ptr| windowed_context.get_proc_address(ptr) as *const _);
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    init_logging();
+    crash::install_panic_hook(".");
+    let mut settings = Settings::load()?;
+    if let Some(width) = cli.width {
+        settings.width = width;
+    }
+    if let Some(height) = cli.height {
+        settings.height = height;
+    }
+    if cli.vsync || cli.no_vsync {
+        settings.vsync = cli.vsync;
+    }
+    if cli.samples.is_some() {
+        settings.samples = cli.samples;
+    }
+    if cli.gl_version.is_some() {
+        settings.gl_version = cli.gl_version;
+    }
+    if cli.software {
+        settings.software = true;
+    }
+    if cli.validate {
+        settings.validate = true;
+    }
+    if cli.bench.is_some() {
+        settings.vsync = false;
+        settings.max_fps = None;
+    }
+    settings::set(settings)?;
 
-    let version = unsafe {
-        let data = CStr::from_ptr(gl::GetString(gl::VERSION) as *const _)
-            .to_bytes()
-            .to_vec();
-        String::from_utf8(data).unwrap()
-    };
-    println!("OpenGL version {}", version);
-
-    type Vertex = [f32; 3];
-    const VERTICES: [Vertex; 3] = [[-0.5, -0.5, 0.0], [0.5, -0.5, 0.0], [0.0, 0.5, 0.0]];
-    const VERT_SHADER: &str = r#"#version 330 core
-    layout (location = 0) in vec3 pos;
-    void main() {
-      gl_Position = vec4(pos.x, pos.y, pos.z, 1.0);
+    if let Some(Command::Render { model, size, out }) = &cli.command {
+        let out = out.clone().unwrap_or_else(|| model.with_extension("png"));
+        return render_thumbnail(model, *size, &out);
+    }
+    if let Some(Command::ConvertEnv {
+        input,
+        out,
+        size,
+        maps,
+    }) = &cli.command
+    {
+        let out = out.clone().unwrap_or_else(|| input.with_extension("ktx2"));
+        return convert_environment(input, &out, *size, *maps);
     }
-    "#;
-    const FRAG_SHADER: &str = r#"#version 330 core
-    out vec4 final_color;
 
-    void main() {
-        final_color = vec4(1.0, 0.5, 0.2, 1.0);
+    if cli.headless {
+        let settings = settings::get();
+        let context = HeadlessContext::new(
+            settings.width,
+            settings.height,
+            &ContextConfig::from_settings(),
+        )?;
+        let frames = cli
+            .frames
+            .unwrap_or(if cli.replay.is_some() { u64::MAX } else { 1 });
+        let mut app = build(cli)?;
+        context.run(&mut app, frames, HEADLESS_DT);
+        return Ok(());
     }
-    "#;
-
-    let va = VertexArray::new().unwrap();
-    va.bind();
-
-    let vb = Buffer::new().unwrap();
-    vb.bind(gl::ARRAY_BUFFER);
-    vb.data(
-        gl::ARRAY_BUFFER,
-        bytemuck::cast_slice(&VERTICES),
-        gl::STATIC_DRAW,
-    );
-
-    unsafe {
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            std::mem::size_of::<Vertex>() as i32,
-            0 as *const _,
-        );
-        gl::EnableVertexAttribArray(0);
-
-        let vertex_shader = Shader::from_source(gl::VERTEX_SHADER, VERT_SHADER).unwrap();
-        let fragment_shader = Shader::from_source(gl::FRAGMENT_SHADER, FRAG_SHADER).unwrap();
-
-        let program = Program::new().unwrap();
-        program.attach(&vertex_shader);
-        program.attach(&fragment_shader);
-        program.link().unwrap();
-        program.use_program();
-
-        vertex_shader.delete();
-        fragment_shader.delete();
+
+    #[cfg(feature = "sdl2")]
+    if cli.sdl {
+        app::sdl::run(TITLE, move |_| build(cli));
     }
+    app::run(TITLE, move |_| build(cli));
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        // println!("{:?}", event);
-        *control_flow = ControlFlow::Wait;
-
-        match event {
-            Event::LoopDestroyed => (),
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::Resized(physical_size) => windowed_context.resize(physical_size),
-                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                _ => (),
-            },
-            Event::RedrawRequested(_) => {
-                unsafe {
-                    gl::ClearColor(0.2, 0.3, 0.3, 1.0);
-                    gl::Clear(gl::COLOR_BUFFER_BIT);
-                    gl::DrawArrays(gl::TRIANGLES, 0, 3);
-                }
-                windowed_context.swap_buffers().unwrap();
+/// Frames `model` on a headless `size` × `size` context, renders one frame and saves it.
+fn render_thumbnail(model: &Path, size: u32, out: &Path) -> Result<()> {
+    let context = HeadlessContext::new(size, size, &ContextConfig::from_settings())?;
+    tracing::info!("OpenGL version {}", context::info().version_string);
+    let scene =
+        Model::open(model).map_err(|e| anyhow!("Failed to open {}: {:#}", model.display(), e))?;
+    let mut app = Demo {
+        scene: Box::new(scene),
+        size: (0, 0),
+    };
+    context.run(&mut app, 1, HEADLESS_DT);
+    context.read_pixels().save(out)?;
+    tracing::info!("Saved {}", out.display());
+    Ok(())
+}
+
+fn convert_environment(input: &Path, out: &Path, size: i32, maps: bool) -> Result<()> {
+    // Nothing is drawn to the context's own framebuffer.
+    let _context = HeadlessContext::new(1, 1, &ContextConfig::from_settings())?;
+    tracing::info!("OpenGL version {}", context::info().version_string);
+    let config = EnvironmentConfig {
+        size,
+        ..EnvironmentConfig::default()
+    };
+    let environment = Environment::from_hdr(&HdrImage::load(input)?, config)?;
+    environment.save_ktx2(out, maps)?;
+    tracing::info!("Saved {}", out.display());
+    Ok(())
+}
+
+fn title() -> &'static str {
+    settings::get().title.as_deref().unwrap_or(TITLE)
+}
+
+/// The default triangle, orange on transparent.
+fn icon() -> Image {
+    const SIZE: u32 = 32;
+    let pixels = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = ((i % SIZE) as f32 + 0.5, (i / SIZE) as f32 + 0.5);
+            // Apex at the top center, base along the bottom.
+            let inside = y > 4.0 && y < 28.0 && (x - 16.0).abs() < (y - 4.0) * 0.5;
+            if inside {
+                [255, 128, 51, 255]
+            } else {
+                [0, 0, 0, 0]
             }
-            _ => (),
+        })
+        .collect();
+    Image {
+        width: SIZE,
+        height: SIZE,
+        pixels,
+    }
+}
+
+fn build(cli: Cli) -> Result<Frames> {
+    tracing::info!("OpenGL version {}", context::info().version_string);
+    // A replay ends the run itself.
+    let replaying = cli.replay.is_some();
+    let (name, app): (String, Box<dyn App>) = match cli.command {
+        Some(Command::Toy { shader, channels }) => (
+            shader.display().to_string(),
+            Box::new(Toy::new(shader, &channels)?),
+        ),
+        Some(Command::Render { .. }) => unreachable!("`render` runs without a window"),
+        Some(Command::ConvertEnv { .. }) => {
+            unreachable!("`convert-env` runs without a window")
         }
-    });
+        None => (
+            format!("{:?}", cli.scene),
+            Box::new(Demo {
+                scene: cli.scene.create()?,
+                size: (0, 0),
+            }),
+        ),
+    };
+    let app: Box<dyn App> = match (cli.record, cli.replay) {
+        (Some(path), _) => Box::new(Recorder::new(app, path)?),
+        (None, Some(path)) => Box::new(Replay::load(app, path)?),
+        (None, None) => app,
+    };
+    let app = match cli.bench {
+        Some(frames) => Box::new(Bench::new(app, &name, frames, cli.bench_output)),
+        None => app,
+    };
+    Ok(Frames {
+        app,
+        rendered: 0,
+        limit: cli
+            .frames
+            .or((!replaying && (cli.screenshot.is_some() || cli.headless)).then_some(1)),
+        screenshot: cli.screenshot,
+        size: (0, 0),
+        icon: Some(icon()),
+        title_time: 0.0,
+        title_frames: 0,
+    })
 }
@@ -0,0 +1,841 @@
+//! Surface materials and lights for forward shading.
+//!
+//! [`MaterialShaders`] holds a Blinn-Phong and a metallic-roughness PBR program sharing
+//! one vertex shader over [`crate::mesh::Vertex`] and the mesh tangents, which both use
+//! for tangent-space normal maps. Lights are uploaded once per frame
+//! into a [`LightBuffer`] uniform block bound at [`LIGHTS_BINDING`]. The PBR programs
+//! add image-based lighting from an [`Environment`] when one is set. Both also shade
+//! against the point and spot lights of the fragment's cluster when
+//! [`ClusteredLights`] are set, for scenes with far more lights than the block holds.
+//! While a [`crate::debug_view`] is selected, every bind uses a debug program instead.
+
+use std::mem::offset_of;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{JOINTS_BINDING, SKINNING_GLSL};
+use crate::block_layout::{self, BlockKind, Field};
+use crate::buffer::Buffer;
+use crate::clustered::{ClusteredLights, CLUSTERED_LIGHTS_GLSL};
+use crate::debug_view::{self, DebugView, OVERDRAW_SATURATION};
+use crate::gl;
+use crate::ibl::Environment;
+use crate::math::{Mat4, Vec3, Vec4};
+use crate::oit::OIT_OUTPUT_GLSL;
+use crate::per_frame::PerFrame;
+use crate::shader::Program;
+use crate::shader_variants::{Defines, ShaderVariants};
+use crate::state::StateCache;
+use crate::texture::Texture;
+
+/// Uniform buffer binding point of the `Lights` block.
+pub const LIGHTS_BINDING: u32 = 1;
+
+/// Maximum number of lights in a [`LightBuffer`].
+pub const MAX_LIGHTS: usize = 32;
+
+/// Declaration of the `Lights` uniform block and `light_radiance`, which returns the
+/// incoming radiance at `position` and writes the direction towards the light to `l`.
+pub const LIGHTS_GLSL: &str = r#"
+#define MAX_LIGHTS 32
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+struct Light {
+    vec4 position_type;
+    vec4 direction_range;
+    vec4 color_intensity;
+    vec4 cone;
+};
+
+layout (std140) uniform Lights {
+    Light lights[MAX_LIGHTS];
+    ivec4 light_count;
+    vec4 ambient;
+};
+
+vec3 light_radiance(Light light, vec3 position, out vec3 l) {
+    int type = int(light.position_type.w);
+    vec3 radiance = light.color_intensity.rgb * light.color_intensity.a;
+    if (type == LIGHT_DIRECTIONAL) {
+        l = -normalize(light.direction_range.xyz);
+        return radiance;
+    }
+    vec3 to_light = light.position_type.xyz - position;
+    float d = length(to_light);
+    l = to_light / d;
+    float range = light.direction_range.w;
+    float falloff = clamp(1.0 - pow(d / range, 4.0), 0.0, 1.0);
+    radiance *= falloff * falloff / (d * d + 1.0);
+    if (type == LIGHT_SPOT) {
+        float cos_angle = dot(-l, normalize(light.direction_range.xyz));
+        radiance *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+    }
+    return radiance;
+}
+"#;
+
+const VERTEX_SHADER: &str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv;
+layout (location = 5) in vec4 a_tangent;
+uniform mat4 u_model;
+uniform mat4 u_view_projection;
+out vec3 v_world;
+out vec3 v_normal;
+out vec4 v_tangent;
+out vec2 v_uv;
+// The depth pre-pass programs share this shader, and their depths must match exactly.
+invariant gl_Position;
+void main() {
+#ifdef SKINNED
+    mat4 model = u_model * skin_matrix();
+#else
+    mat4 model = u_model;
+#endif
+    vec4 world = model * vec4(a_position, 1.0);
+    v_world = world.xyz;
+    v_normal = transpose(inverse(mat3(model))) * a_normal;
+    v_tangent = vec4(mat3(model) * a_tangent.xyz, a_tangent.w);
+    v_uv = a_uv;
+    gl_Position = u_view_projection * world;
+}
+"#;
+
+/// Perturbs the interpolated normal by the tangent-space normal map. The flat fallback
+/// texture decodes to (0, 0, 1), leaving the normal as is.
+const NORMAL_MAP_GLSL: &str = r#"
+uniform sampler2D u_normal_texture;
+uniform float u_normal_scale;
+
+vec3 perturb_normal(vec3 normal, vec4 tangent, vec2 uv) {
+    vec3 n = normalize(normal);
+    vec3 t = normalize(tangent.xyz - n * dot(n, tangent.xyz));
+    vec3 b = cross(n, t) * tangent.w;
+    vec3 mapped = texture(u_normal_texture, uv).xyz * 2.0 - 1.0;
+    mapped.xy *= u_normal_scale;
+    return normalize(mat3(t, b, n) * mapped);
+}
+"#;
+
+const BLINN_PHONG_SHADER: &str = r#"
+in vec3 v_world;
+in vec3 v_normal;
+in vec4 v_tangent;
+in vec2 v_uv;
+uniform vec3 u_camera_position;
+uniform vec4 u_base_color;
+uniform sampler2D u_base_color_texture;
+uniform vec3 u_specular;
+uniform float u_shininess;
+uniform vec3 u_emissive;
+
+vec3 shade(Light light, vec3 n, vec3 v, vec3 albedo) {
+    vec3 l;
+    vec3 radiance = light_radiance(light, v_world, l);
+    vec3 h = normalize(l + v);
+    float diffuse = max(dot(n, l), 0.0);
+    float specular = diffuse > 0.0 ? pow(max(dot(n, h), 0.0), u_shininess) : 0.0;
+    return radiance * (albedo * diffuse + u_specular * specular);
+}
+
+void main() {
+    vec4 base = u_base_color * texture(u_base_color_texture, v_uv);
+    vec3 n = perturb_normal(v_normal, v_tangent, v_uv);
+    vec3 v = normalize(u_camera_position - v_world);
+    vec3 color = ambient.rgb * base.rgb + u_emissive;
+    for (int i = 0; i < light_count.x; ++i) {
+        color += shade(lights[i], n, v, base.rgb);
+    }
+    int cluster = cluster_index();
+    for (int i = 0; i < cluster_light_count(cluster); ++i) {
+        color += shade(cluster_light(cluster, i), n, v, base.rgb);
+    }
+    write_color(vec4(color, base.a));
+}
+"#;
+
+const PBR_SHADER: &str = r#"
+in vec3 v_world;
+in vec3 v_normal;
+in vec4 v_tangent;
+in vec2 v_uv;
+uniform vec3 u_camera_position;
+uniform vec4 u_base_color;
+uniform sampler2D u_base_color_texture;
+uniform float u_metallic;
+uniform float u_roughness;
+// glTF convention: roughness in G, metallic in B.
+uniform sampler2D u_metallic_roughness_texture;
+uniform vec3 u_emissive;
+uniform samplerCube u_irradiance;
+uniform samplerCube u_prefiltered;
+uniform sampler2D u_brdf_lut;
+uniform float u_prefiltered_max_lod;
+uniform float u_environment_intensity;
+
+const float PI = 3.14159265359;
+
+float distribution_ggx(float n_dot_h, float a) {
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Split-sum image-based lighting; see crate::ibl.
+vec3 environment_light(vec3 n, vec3 v, float n_dot_v, vec3 f0, vec3 albedo, float metallic,
+                       float roughness) {
+    vec3 f = f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo * texture(u_irradiance, n).rgb;
+    vec3 r = reflect(-v, n);
+    vec3 prefiltered = textureLod(u_prefiltered, r, roughness * u_prefiltered_max_lod).rgb;
+    vec2 brdf = texture(u_brdf_lut, vec2(n_dot_v, roughness)).rg;
+    return (diffuse + prefiltered * (f * brdf.x + brdf.y)) * u_environment_intensity;
+}
+
+vec3 shade(Light light, vec3 n, vec3 v, float n_dot_v, vec3 f0, vec3 albedo, float metallic,
+           float roughness) {
+    vec3 l;
+    vec3 radiance = light_radiance(light, v_world, l);
+    vec3 h = normalize(l + v);
+    float n_dot_l = max(dot(n, l), 0.0);
+    if (n_dot_l <= 0.0) {
+        return vec3(0.0);
+    }
+    float d = distribution_ggx(max(dot(n, h), 0.0), roughness * roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, roughness);
+    vec3 f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    vec3 specular = d * g * f / (4.0 * n_dot_v * n_dot_l);
+    vec3 diffuse = (1.0 - f) * (1.0 - metallic) * albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+void main() {
+    vec4 base = u_base_color * texture(u_base_color_texture, v_uv);
+    vec4 mr = texture(u_metallic_roughness_texture, v_uv);
+    float metallic = u_metallic * mr.b;
+    float roughness = clamp(u_roughness * mr.g, 0.04, 1.0);
+    vec3 n = perturb_normal(v_normal, v_tangent, v_uv);
+    vec3 v = normalize(u_camera_position - v_world);
+    float n_dot_v = max(dot(n, v), 1e-4);
+    vec3 f0 = mix(vec3(0.04), base.rgb, metallic);
+
+    vec3 color = ambient.rgb * base.rgb + u_emissive
+        + environment_light(n, v, n_dot_v, f0, base.rgb, metallic, roughness);
+    for (int i = 0; i < light_count.x; ++i) {
+        color += shade(lights[i], n, v, n_dot_v, f0, base.rgb, metallic, roughness);
+    }
+    int cluster = cluster_index();
+    for (int i = 0; i < cluster_light_count(cluster); ++i) {
+        Light light = cluster_light(cluster, i);
+        color += shade(light, n, v, n_dot_v, f0, base.rgb, metallic, roughness);
+    }
+    write_color(vec4(color, base.a));
+}
+"#;
+
+/// Fragment shader of [`MaterialShaders::depth_program`]: no color, depth only.
+const DEPTH_ONLY_SHADER: &str = r#"
+void main() {}
+"#;
+
+/// The alternate fragment path of [`crate::debug_view`], switching on `u_debug_view`.
+const DEBUG_VIEW_SHADER: &str = r#"
+in vec3 v_world;
+in vec3 v_normal;
+in vec4 v_tangent;
+in vec2 v_uv;
+uniform mat4 u_model;
+uniform int u_debug_view;
+
+uint hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x *= 0x846ca68bu;
+    x ^= x >> 16;
+    return x;
+}
+
+vec3 id_color(uint id) {
+    uint h = hash(id);
+    return vec3(uvec3(h, h >> 8, h >> 16) & 0xffu) / 255.0;
+}
+
+void main() {
+    vec3 color;
+    if (u_debug_view == 1) {
+        color = perturb_normal(v_normal, v_tangent, v_uv) * 0.5 + 0.5;
+    } else if (u_debug_view == 2) {
+        // The clip-space w, which is the view depth under perspective projections.
+        float depth = 1.0 / gl_FragCoord.w;
+        color = vec3(clamp(log2(depth / 0.1) / log2(1000.0 / 0.1), 0.0, 1.0));
+    } else if (u_debug_view == 3) {
+        color = vec3(fract(v_uv), 0.0);
+    } else if (u_debug_view == 4) {
+        color = vec3(4.0, 2.0, 1.0) / float(OVERDRAW_SATURATION);
+    } else {
+        uint id = 0u;
+        for (int column = 0; column < 4; ++column) {
+            for (int row = 0; row < 3; ++row) {
+                id = hash(id ^ floatBitsToUint(u_model[column][row]));
+            }
+        }
+        if (u_debug_view == 6) {
+            id = hash(id ^ uint(gl_PrimitiveID));
+        }
+        color = id_color(id);
+    }
+    write_color(vec4(color, 1.0));
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Light {
+    Directional {
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+    },
+    Point {
+        position: Vec3,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+    },
+    /// A cone light; angles are half-angles in radians.
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        color: Vec3,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub(crate) struct LightStd140 {
+    position_type: [f32; 4],
+    direction_range: [f32; 4],
+    color_intensity: [f32; 4],
+    cone: [f32; 4],
+}
+
+impl From<&Light> for LightStd140 {
+    fn from(light: &Light) -> Self {
+        match *light {
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => LightStd140 {
+                position_type: [0.0, 0.0, 0.0, 0.0],
+                direction_range: direction.extend(0.0).to_array(),
+                color_intensity: color.extend(intensity).to_array(),
+                cone: [0.0; 4],
+            },
+            Light::Point {
+                position,
+                color,
+                intensity,
+                range,
+            } => LightStd140 {
+                position_type: position.extend(1.0).to_array(),
+                direction_range: [0.0, 0.0, 0.0, range],
+                color_intensity: color.extend(intensity).to_array(),
+                cone: [0.0; 4],
+            },
+            Light::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                range,
+                inner_angle,
+                outer_angle,
+            } => LightStd140 {
+                position_type: position.extend(2.0).to_array(),
+                direction_range: direction.extend(range).to_array(),
+                color_intensity: color.extend(intensity).to_array(),
+                cone: [inner_angle.cos(), outer_angle.cos(), 0.0, 0.0],
+            },
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightsStd140 {
+    lights: [LightStd140; MAX_LIGHTS],
+    count: [i32; 4],
+    ambient: [f32; 4],
+}
+
+/// In debug builds, fails if the `Lights` block of `program` doesn't match
+/// [`LightBuffer`]'s layout.
+pub(crate) fn check_lights_block(program: &Program) -> Result<()> {
+    let light = [
+        Field::new("position_type", offset_of!(LightStd140, position_type)),
+        Field::new("direction_range", offset_of!(LightStd140, direction_range)),
+        Field::new("color_intensity", offset_of!(LightStd140, color_intensity)),
+        Field::new("cone", offset_of!(LightStd140, cone)),
+    ];
+    let mut fields = Field::struct_array(
+        "lights",
+        offset_of!(LightsStd140, lights),
+        std::mem::size_of::<LightStd140>(),
+        &light,
+    );
+    fields.push(Field::new("light_count", offset_of!(LightsStd140, count)));
+    fields.push(Field::new("ambient", offset_of!(LightsStd140, ambient)));
+    block_layout::check(
+        program,
+        BlockKind::Uniform,
+        "Lights",
+        Some(std::mem::size_of::<LightsStd140>()),
+        &fields,
+    )
+}
+
+/// The `Lights` uniform block.
+/// The light block, one buffer per frame in flight so that each frame's upload doesn't
+/// wait for draws of earlier frames still reading theirs.
+pub struct LightBuffer {
+    buffers: PerFrame<Buffer>,
+}
+
+impl LightBuffer {
+    pub fn new() -> Result<LightBuffer> {
+        let buffers = PerFrame::try_new(|_| {
+            let buffer = Buffer::new()?;
+            buffer.bind(gl::UNIFORM_BUFFER);
+            buffer.allocate(
+                gl::UNIFORM_BUFFER,
+                std::mem::size_of::<LightsStd140>(),
+                gl::DYNAMIC_DRAW,
+            );
+            buffer.unbind(gl::UNIFORM_BUFFER);
+            Ok(buffer)
+        })?;
+        Ok(LightBuffer { buffers })
+    }
+
+    /// Uploads `lights` and the ambient term into this frame's buffer and binds it at
+    /// [`LIGHTS_BINDING`].
+    pub fn upload(&self, lights: &[Light], ambient: Vec3) -> Result<()> {
+        if lights.len() > MAX_LIGHTS {
+            return Err(anyhow!(
+                "{} lights exceed the limit of {}",
+                lights.len(),
+                MAX_LIGHTS
+            ));
+        }
+        let mut block = LightsStd140::zeroed();
+        for (slot, light) in block.lights.iter_mut().zip(lights) {
+            *slot = light.into();
+        }
+        block.count[0] = lights.len() as i32;
+        block.ambient = ambient.extend(1.0).to_array();
+
+        let buffer = self.buffers.current();
+        buffer.bind(gl::UNIFORM_BUFFER);
+        buffer.sub_data(gl::UNIFORM_BUFFER, 0, bytemuck::bytes_of(&block));
+        buffer.unbind(gl::UNIFORM_BUFFER);
+        buffer.bind_base(gl::UNIFORM_BUFFER, LIGHTS_BINDING);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shading {
+    BlinnPhong,
+    Pbr,
+}
+
+/// Surface parameters. Texture slots multiply their matching factor; missing textures
+/// behave as white, except the normal map, which behaves as flat.
+#[derive(Clone)]
+pub struct Material {
+    pub shading: Shading,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<Rc<Texture>>,
+    pub emissive: Vec3,
+    /// Blinn-Phong specular color.
+    pub specular: Vec3,
+    /// Blinn-Phong specular exponent.
+    pub shininess: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_texture: Option<Rc<Texture>>,
+    /// Tangent-space normal map, OpenGL convention (green points along the bitangent).
+    pub normal_texture: Option<Rc<Texture>>,
+    /// Scales the normal map's X and Y to strengthen or flatten the bumps.
+    pub normal_scale: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            shading: Shading::BlinnPhong,
+            base_color: Vec4::ONE,
+            base_color_texture: None,
+            emissive: Vec3::ZERO,
+            specular: Vec3::splat(0.5),
+            shininess: 32.0,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: 1.0,
+        }
+    }
+}
+
+impl Material {
+    pub fn blinn_phong(base_color: Vec4, shininess: f32) -> Material {
+        Material {
+            base_color,
+            shininess,
+            ..Material::default()
+        }
+    }
+
+    pub fn pbr(base_color: Vec4, metallic: f32, roughness: f32) -> Material {
+        Material {
+            shading: Shading::Pbr,
+            base_color,
+            metallic,
+            roughness,
+            ..Material::default()
+        }
+    }
+}
+
+/// The material programs plus the fallback textures used for empty texture slots and
+/// a missing [`Environment`] or [`ClusteredLights`].
+///
+/// The variants are the `SKINNED` and `OIT` permutations of one [`ShaderVariants`] per
+/// shading model. Skinned variants read [`crate::mesh::SkinnedVertex`] attributes and the
+/// `Joints` block from [`crate::animation::JointBuffer`]. OIT variants write the
+/// accumulation targets of [`crate::oit::OitTargets`] instead of a color.
+pub struct MaterialShaders {
+    /// Every variant, indexed by [`variant`].
+    programs: Vec<Rc<Program>>,
+    /// The [`DEBUG_VIEW_SHADER`] variants, indexed by skinning and then OIT.
+    debug_programs: Vec<Rc<Program>>,
+    /// The depth-only programs, indexed by skinning.
+    depth_programs: Vec<Rc<Program>>,
+    white: Texture,
+    flat_normal: Texture,
+    black_cube: Texture,
+    environment: Option<Rc<Environment>>,
+    clustered_lights: Option<ClusteredLights>,
+}
+
+impl MaterialShaders {
+    pub fn new() -> Result<MaterialShaders> {
+        let vertex = format!(
+            "#ifdef SKINNED\n{}\n#endif\n{}",
+            SKINNING_GLSL, VERTEX_SHADER
+        );
+        let variants = |fragment: &str| {
+            let fragment = format!(
+                "{}\n{}\n{}\n{}\n{}",
+                OIT_OUTPUT_GLSL, LIGHTS_GLSL, CLUSTERED_LIGHTS_GLSL, NORMAL_MAP_GLSL, fragment
+            );
+            ShaderVariants::new(&vertex, &fragment)
+        };
+        let defines = |skinned: bool, oit: bool| {
+            Defines::new()
+                .with_if("SKINNED", skinned)
+                .with_if("OIT", oit)
+        };
+
+        let mut blinn_phong = variants(BLINN_PHONG_SHADER);
+        let mut pbr = variants(PBR_SHADER);
+        let mut programs = Vec::with_capacity(8);
+        for oit in [false, true] {
+            for skinned in [false, true] {
+                for shading in [Shading::BlinnPhong, Shading::Pbr] {
+                    debug_assert_eq!(programs.len(), variant(shading, skinned, oit));
+                    let variants = match shading {
+                        Shading::BlinnPhong => &mut blinn_phong,
+                        Shading::Pbr => &mut pbr,
+                    };
+                    let program = variants.get(&defines(skinned, oit))?;
+                    check_lights_block(&program)?;
+                    program.bind_uniform_block("Lights", LIGHTS_BINDING);
+                    program.bind_uniform_block("Joints", JOINTS_BINDING);
+                    programs.push(program);
+                }
+            }
+        }
+        let mut depth_only = ShaderVariants::new(&vertex, DEPTH_ONLY_SHADER);
+        let mut depth_programs = Vec::with_capacity(2);
+        for skinned in [false, true] {
+            let program = depth_only.get(&defines(skinned, false))?;
+            program.bind_uniform_block("Joints", JOINTS_BINDING);
+            depth_programs.push(program);
+        }
+        let mut debug_view = variants(DEBUG_VIEW_SHADER);
+        let mut debug_programs = Vec::with_capacity(4);
+        for oit in [false, true] {
+            for skinned in [false, true] {
+                let defines =
+                    defines(skinned, oit).with_value("OVERDRAW_SATURATION", OVERDRAW_SATURATION);
+                let program = debug_view.get(&defines)?;
+                program.bind_uniform_block("Joints", JOINTS_BINDING);
+                debug_programs.push(program);
+            }
+        }
+
+        Ok(MaterialShaders {
+            programs,
+            debug_programs,
+            depth_programs,
+            white: Texture::solid([255; 4])?,
+            flat_normal: Texture::solid([128, 128, 255, 255])?,
+            black_cube: black_cube_map()?,
+            environment: None,
+            clustered_lights: None,
+        })
+    }
+
+    /// The program for `shading`, or the debug program while a [`debug_view`] is set.
+    pub fn program(&self, shading: Shading, skinned: bool) -> &Program {
+        self.select(shading, skinned, false)
+    }
+
+    /// The variant of [`MaterialShaders::program`] for weighted blended transparency.
+    pub fn oit_program(&self, shading: Shading, skinned: bool) -> &Program {
+        self.select(shading, skinned, true)
+    }
+
+    fn select(&self, shading: Shading, skinned: bool, oit: bool) -> &Program {
+        match debug_view::get() {
+            DebugView::Lit => &self.programs[variant(shading, skinned, oit)],
+            _ => &self.debug_programs[skinned as usize | (oit as usize) << 1],
+        }
+    }
+
+    /// The program of a depth pre-pass, which writes depth only. Its vertex shader is the
+    /// materials' with `gl_Position` declared invariant, so a following pass can draw the
+    /// same geometry with `GL_EQUAL` depth testing.
+    pub fn depth_program(&self, skinned: bool) -> &Program {
+        &self.depth_programs[skinned as usize]
+    }
+
+    /// Lights PBR materials with `environment` from now on, or stops with `None`.
+    pub fn set_environment(&mut self, environment: Option<Rc<Environment>>) {
+        self.environment = environment;
+    }
+
+    pub fn environment(&self) -> Option<&Rc<Environment>> {
+        self.environment.as_ref()
+    }
+
+    /// Shades materials against `clustered_lights` in addition to the `Lights` block from
+    /// now on, or stops with `None`. Update them through
+    /// [`MaterialShaders::clustered_lights_mut`] each frame.
+    pub fn set_clustered_lights(&mut self, clustered_lights: Option<ClusteredLights>) {
+        self.clustered_lights = clustered_lights;
+    }
+
+    pub fn clustered_lights(&self) -> Option<&ClusteredLights> {
+        self.clustered_lights.as_ref()
+    }
+
+    pub fn clustered_lights_mut(&mut self) -> Option<&mut ClusteredLights> {
+        self.clustered_lights.as_mut()
+    }
+
+    /// Sets the camera uniforms on all programs.
+    pub fn set_camera(&self, view_projection: Mat4, camera_position: Vec3) {
+        let programs = self.programs.iter().chain(&self.debug_programs);
+        for program in programs.chain(&self.depth_programs) {
+            program.use_program();
+            program.set_mat4("u_view_projection", &view_projection.to_cols_array());
+            program.set_vec3("u_camera_position", camera_position.to_array());
+        }
+    }
+
+    /// Uses the program for `material`, uploads its parameters and binds its textures to
+    /// units 0 to 2, the environment's to units 3 to 5 for PBR and the clustered lights'
+    /// to units 6 and 7. Returns the program so per-draw uniforms such as `u_model` can
+    /// be set.
+    pub fn bind(&self, material: &Material) -> &Program {
+        self.bind_variant(material, false)
+    }
+
+    /// Like [`MaterialShaders::bind`], selecting the skinned variant for skinned meshes.
+    pub fn bind_variant(&self, material: &Material, skinned: bool) -> &Program {
+        self.bind_cached(material, skinned, &mut StateCache::new())
+    }
+
+    /// Like [`MaterialShaders::bind_variant`], skipping program and texture binds that
+    /// `cache` says are already in place.
+    pub fn bind_cached(
+        &self,
+        material: &Material,
+        skinned: bool,
+        cache: &mut StateCache,
+    ) -> &Program {
+        self.upload(self.program(material.shading, skinned), material, cache)
+    }
+
+    /// Like [`MaterialShaders::bind_cached`] with the OIT variant, for drawing between
+    /// [`crate::oit::OitTargets::begin`] and [`crate::oit::OitTargets::end`].
+    pub fn bind_oit_cached(
+        &self,
+        material: &Material,
+        skinned: bool,
+        cache: &mut StateCache,
+    ) -> &Program {
+        self.upload(self.oit_program(material.shading, skinned), material, cache)
+    }
+
+    fn upload<'s>(
+        &'s self,
+        program: &'s Program,
+        material: &Material,
+        cache: &mut StateCache,
+    ) -> &'s Program {
+        cache.use_program(program);
+        let view = debug_view::get();
+        if view != DebugView::Lit {
+            program.set_int("u_debug_view", view as i32);
+            if view == DebugView::Overdraw {
+                debug_view::apply_overdraw_state();
+            }
+        }
+        program.set_vec4("u_base_color", material.base_color.to_array());
+        program.set_vec3("u_emissive", material.emissive.to_array());
+
+        let texture = |cache: &mut StateCache,
+                       slot: &Option<Rc<Texture>>,
+                       fallback: &Texture,
+                       unit: u32,
+                       name: &str| {
+            cache.bind_texture(unit, slot.as_deref().unwrap_or(fallback));
+            program.set_int(name, unit as i32);
+        };
+        texture(
+            cache,
+            &material.base_color_texture,
+            &self.white,
+            0,
+            "u_base_color_texture",
+        );
+        texture(
+            cache,
+            &material.normal_texture,
+            &self.flat_normal,
+            2,
+            "u_normal_texture",
+        );
+        program.set_float("u_normal_scale", material.normal_scale);
+        match &self.clustered_lights {
+            Some(clustered_lights) => {
+                let (lights, clusters) = clustered_lights.textures();
+                cache.bind_texture(6, lights);
+                cache.bind_texture(7, clusters);
+                clustered_lights.set_uniforms(program, 6);
+            }
+            None => {
+                cache.bind_texture(6, &self.white);
+                cache.bind_texture(7, &self.white);
+                program.set_int("u_clustered", 0);
+                program.set_int("u_cluster_lights", 6);
+                program.set_int("u_clusters", 7);
+            }
+        }
+        match material.shading {
+            Shading::BlinnPhong => {
+                program.set_vec3("u_specular", material.specular.to_array());
+                program.set_float("u_shininess", material.shininess);
+            }
+            Shading::Pbr => {
+                program.set_float("u_metallic", material.metallic);
+                program.set_float("u_roughness", material.roughness);
+                texture(
+                    cache,
+                    &material.metallic_roughness_texture,
+                    &self.white,
+                    1,
+                    "u_metallic_roughness_texture",
+                );
+                let (irradiance, prefiltered, brdf_lut) = match &self.environment {
+                    Some(environment) => {
+                        program
+                            .set_float("u_prefiltered_max_lod", environment.prefiltered_max_lod());
+                        program.set_float("u_environment_intensity", environment.intensity);
+                        (
+                            environment.irradiance(),
+                            environment.prefiltered(),
+                            environment.brdf_lut(),
+                        )
+                    }
+                    None => {
+                        program.set_float("u_prefiltered_max_lod", 0.0);
+                        program.set_float("u_environment_intensity", 0.0);
+                        (&self.black_cube, &self.black_cube, &self.white)
+                    }
+                };
+                for (unit, texture, name) in [
+                    (3, irradiance, "u_irradiance"),
+                    (4, prefiltered, "u_prefiltered"),
+                    (5, brdf_lut, "u_brdf_lut"),
+                ] {
+                    cache.bind_texture(unit, texture);
+                    program.set_int(name, unit as i32);
+                }
+            }
+        }
+        program
+    }
+}
+
+/// Index of a program in [`MaterialShaders`].
+fn variant(shading: Shading, skinned: bool, oit: bool) -> usize {
+    let shading = match shading {
+        Shading::BlinnPhong => 0,
+        Shading::Pbr => 1,
+    };
+    shading | (skinned as usize) << 1 | (oit as usize) << 2
+}
+
+/// A 1x1 cube map that is black on every face.
+fn black_cube_map() -> Result<Texture> {
+    let texture = Texture::cube_map(gl::RGBA8, 1, 1)?;
+    texture.bind();
+    for face in 0..6 {
+        texture.image_cube_face(
+            face,
+            0,
+            gl::RGBA8,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&[0; 4]),
+        );
+    }
+    texture.unbind();
+    Ok(texture)
+}
@@ -0,0 +1,147 @@
+//! GPU memory reporting.
+//!
+//! [`gpu_memory`] asks the driver how much video memory is free, where
+//! `GL_NVX_gpu_memory_info` or `GL_ATI_meminfo` is available. Independently of the
+//! driver, the [`crate::buffer::Buffer`], [`crate::texture::Texture`] and
+//! [`crate::framebuffer::Renderbuffer`] wrappers estimate the storage they allocate and
+//! [`usage`] sums it up per thread, which makes leaks visible as steadily growing
+//! numbers in the stats overlay.
+
+use std::cell::Cell;
+
+use crate::context;
+use crate::gl;
+
+/// What the driver reports about video memory, in kilobytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GpuMemory {
+    /// Dedicated video memory; only reported by NVIDIA.
+    pub total_kb: Option<i64>,
+    /// Video memory currently free. On AMD, the free pool for textures.
+    pub available_kb: i64,
+    /// Memory evicted to system memory since the context was created; NVIDIA only.
+    pub evicted_kb: Option<i64>,
+}
+
+/// Queries free video memory, or `None` if the driver exposes neither extension.
+pub fn gpu_memory() -> Option<GpuMemory> {
+    let info = context::info();
+    if info.has_extension("GL_NVX_gpu_memory_info") {
+        Some(GpuMemory {
+            total_kb: Some(integer(gl::GPU_MEMORY_INFO_DEDICATED_VIDMEM_NVX)),
+            available_kb: integer(gl::GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX),
+            evicted_kb: Some(integer(gl::GPU_MEMORY_INFO_EVICTED_MEMORY_NVX)),
+        })
+    } else if info.has_extension("GL_ATI_meminfo") {
+        // The first of the four values is the total free memory in the pool.
+        let mut values = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::TEXTURE_FREE_MEMORY_ATI, values.as_mut_ptr());
+        }
+        Some(GpuMemory {
+            total_kb: None,
+            available_kb: values[0] as i64,
+            evicted_kb: None,
+        })
+    } else {
+        None
+    }
+}
+
+fn integer(name: gl::types::GLenum) -> i64 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value as i64
+}
+
+/// Live objects and the bytes they hold, as allocated through the crate's wrappers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub buffers: usize,
+    pub buffer_bytes: usize,
+    pub textures: usize,
+    pub texture_bytes: usize,
+    pub renderbuffers: usize,
+    pub renderbuffer_bytes: usize,
+}
+
+impl Usage {
+    pub fn total_bytes(&self) -> usize {
+        self.buffer_bytes + self.texture_bytes + self.renderbuffer_bytes
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Resource {
+    Buffer,
+    Texture,
+    Renderbuffer,
+}
+
+thread_local! {
+    static USAGE: Cell<Usage> = const {
+        Cell::new(Usage {
+            buffers: 0,
+            buffer_bytes: 0,
+            textures: 0,
+            texture_bytes: 0,
+            renderbuffers: 0,
+            renderbuffer_bytes: 0,
+        })
+    };
+}
+
+/// The storage held by objects alive on this thread.
+pub fn usage() -> Usage {
+    USAGE.with(|usage| usage.get())
+}
+
+/// Records an object being created (`1`) or deleted (`-1`).
+pub(crate) fn track_object(resource: Resource, delta: isize) {
+    update(|usage| {
+        let count = match resource {
+            Resource::Buffer => &mut usage.buffers,
+            Resource::Texture => &mut usage.textures,
+            Resource::Renderbuffer => &mut usage.renderbuffers,
+        };
+        *count = count.saturating_add_signed(delta);
+    });
+}
+
+/// Records an object's storage changing from `old` to `new` bytes.
+pub(crate) fn track_bytes(resource: Resource, old: usize, new: usize) {
+    update(|usage| {
+        let bytes = match resource {
+            Resource::Buffer => &mut usage.buffer_bytes,
+            Resource::Texture => &mut usage.texture_bytes,
+            Resource::Renderbuffer => &mut usage.renderbuffer_bytes,
+        };
+        *bytes = bytes.saturating_sub(old) + new;
+    });
+}
+
+fn update(f: impl FnOnce(&mut Usage)) {
+    USAGE.with(|usage| {
+        let mut value = usage.get();
+        f(&mut value);
+        usage.set(value);
+    });
+}
+
+/// Estimated bytes per pixel of a sized internal format. Drivers may pad some formats
+/// (e.g. `RGB8` to four bytes), so totals are lower bounds.
+pub(crate) fn bytes_per_pixel(internal_format: gl::types::GLenum) -> usize {
+    match internal_format {
+        gl::R8 | gl::R8I | gl::R8UI | gl::STENCIL_INDEX8 => 1,
+        gl::RG8 | gl::R16F | gl::R16 | gl::R16I | gl::R16UI | gl::DEPTH_COMPONENT16 => 2,
+        gl::RGB8 | gl::SRGB8 | gl::DEPTH_COMPONENT24 => 3,
+        gl::RGBA16F | gl::RGBA16 | gl::RG32F | gl::RGBA16I | gl::RGBA16UI | gl::RG32UI => 8,
+        gl::DEPTH32F_STENCIL8 => 8,
+        gl::RGB16F => 6,
+        gl::RGB32F => 12,
+        gl::RGBA32F | gl::RGBA32I | gl::RGBA32UI => 16,
+        _ => 4,
+    }
+}
@@ -0,0 +1,747 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::context;
+use crate::culling::Aabb;
+use crate::gl;
+use crate::image::Image;
+use crate::indirect;
+use crate::material::Material;
+use crate::math::{Vec2, Vec3, Vec4};
+use crate::primitives;
+use crate::shader::Program;
+use crate::state::StateCache;
+use crate::texture::Texture;
+use crate::vertex_format::VertexFormat;
+
+/// The standard interleaved vertex: position, normal, texture coordinate.
+///
+/// Attribute locations are 0, 1 and 2 respectively, as for
+/// [`crate::vertex_format::PosNormUv`]. Every mesh also carries a `vec4` tangent in a
+/// separate buffer at location [`TANGENT_LOCATION`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+/// Attribute location of mesh tangents: the tangent in `xyz` and the handedness of the
+/// bitangent, `cross(normal, tangent) * w`, in `w`.
+pub const TANGENT_LOCATION: u32 = 5;
+
+/// A [`Vertex`] extended with up to four joint influences for skeletal animation.
+///
+/// `joints` use attribute location 3 (integer) and `weights` location 4.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SkinnedVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
+
+/// How a mesh's indices are assembled into primitives.
+///
+/// Strips and fans continue from one primitive to the next; with
+/// [`crate::context::ContextOptions::primitive_restart`] set, the restart index ends one
+/// strip and starts another within the same draw. The adjacency variants carry extra
+/// vertices for geometry shaders (GL 3.2 or ES 3.2) and otherwise draw like their plain
+/// counterparts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PrimitiveMode {
+    Points,
+    Lines,
+    LineLoop,
+    LineStrip,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+    LinesAdjacency,
+    LineStripAdjacency,
+    TrianglesAdjacency,
+    TriangleStripAdjacency,
+}
+
+impl PrimitiveMode {
+    pub fn gl_enum(self) -> gl::types::GLenum {
+        match self {
+            PrimitiveMode::Points => gl::POINTS,
+            PrimitiveMode::Lines => gl::LINES,
+            PrimitiveMode::LineLoop => gl::LINE_LOOP,
+            PrimitiveMode::LineStrip => gl::LINE_STRIP,
+            PrimitiveMode::Triangles => gl::TRIANGLES,
+            PrimitiveMode::TriangleStrip => gl::TRIANGLE_STRIP,
+            PrimitiveMode::TriangleFan => gl::TRIANGLE_FAN,
+            PrimitiveMode::LinesAdjacency => gl::LINES_ADJACENCY,
+            PrimitiveMode::LineStripAdjacency => gl::LINE_STRIP_ADJACENCY,
+            PrimitiveMode::TrianglesAdjacency => gl::TRIANGLES_ADJACENCY,
+            PrimitiveMode::TriangleStripAdjacency => gl::TRIANGLE_STRIP_ADJACENCY,
+        }
+    }
+
+    /// The mode drawn by a GL primitive enum, `None` for anything else.
+    pub fn from_gl(mode: gl::types::GLenum) -> Option<PrimitiveMode> {
+        [
+            PrimitiveMode::Points,
+            PrimitiveMode::Lines,
+            PrimitiveMode::LineLoop,
+            PrimitiveMode::LineStrip,
+            PrimitiveMode::Triangles,
+            PrimitiveMode::TriangleStrip,
+            PrimitiveMode::TriangleFan,
+            PrimitiveMode::LinesAdjacency,
+            PrimitiveMode::LineStripAdjacency,
+            PrimitiveMode::TrianglesAdjacency,
+            PrimitiveMode::TriangleStripAdjacency,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.gl_enum() == mode)
+    }
+
+    pub fn has_adjacency(self) -> bool {
+        matches!(
+            self,
+            PrimitiveMode::LinesAdjacency
+                | PrimitiveMode::LineStripAdjacency
+                | PrimitiveMode::TrianglesAdjacency
+                | PrimitiveMode::TriangleStripAdjacency
+        )
+    }
+
+    /// Whether the current context can draw this mode.
+    pub fn is_supported(self) -> bool {
+        !self.has_adjacency() || context::version() >= (3, 2)
+    }
+
+    /// Triangles drawn from `count` indices without restarts; 0 for points and lines.
+    pub fn triangle_count(self, count: usize) -> usize {
+        match self {
+            PrimitiveMode::Triangles => count / 3,
+            PrimitiveMode::TriangleStrip | PrimitiveMode::TriangleFan => count.saturating_sub(2),
+            PrimitiveMode::TrianglesAdjacency => count / 6,
+            PrimitiveMode::TriangleStripAdjacency => count.saturating_sub(4) / 2,
+            _ => 0,
+        }
+    }
+
+    /// The triangles `indices` describe in this mode, as a triangle list with the same
+    /// winding, splitting strips and fans at `restart`. Empty for points and lines. For
+    /// CPU-side work that expects lists, like [`compute_tangents`].
+    pub fn triangle_list(self, indices: &[u32], restart: Option<u32>) -> Vec<u32> {
+        let mut triangles = Vec::with_capacity(self.triangle_count(indices.len()) * 3);
+        for run in indices.split(|&index| Some(index) == restart) {
+            match self {
+                PrimitiveMode::Triangles => {
+                    triangles.extend(run.chunks_exact(3).flatten());
+                }
+                PrimitiveMode::TrianglesAdjacency => {
+                    for triangle in run.chunks_exact(6) {
+                        triangles.extend([triangle[0], triangle[2], triangle[4]]);
+                    }
+                }
+                PrimitiveMode::TriangleStrip => {
+                    for (i, window) in run.windows(3).enumerate() {
+                        // Every other triangle of a strip is wound the other way round.
+                        if i % 2 == 0 {
+                            triangles.extend([window[0], window[1], window[2]]);
+                        } else {
+                            triangles.extend([window[1], window[0], window[2]]);
+                        }
+                    }
+                }
+                PrimitiveMode::TriangleStripAdjacency => {
+                    let strip: Vec<u32> = run.iter().step_by(2).copied().collect();
+                    triangles.extend(PrimitiveMode::TriangleStrip.triangle_list(&strip, None));
+                }
+                PrimitiveMode::TriangleFan => {
+                    if let Some((&center, rim)) = run.split_first() {
+                        for pair in rim.windows(2) {
+                            triangles.extend([center, pair[0], pair[1]]);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        triangles
+    }
+}
+
+/// Replaces `from` in `indices` with `u32::MAX`, the restart index ES always uses, e.g.
+/// for strips exported with 16-bit indices restarting at `0xFFFF`.
+pub fn remap_restart_index(indices: &mut [u32], from: u32) {
+    for index in indices.iter_mut().filter(|index| **index == from) {
+        *index = u32::MAX;
+    }
+}
+
+/// A range of a mesh's index buffer drawn with one material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: i32,
+    /// Added to every index of the range before fetching vertices, so the indices of a
+    /// mesh packed into a shared vertex buffer can stay relative to its first vertex.
+    /// Non-zero values need [`indirect::base_vertex_supported`].
+    pub base_vertex: i32,
+    /// Index into the materials returned alongside the mesh, if any.
+    pub material: Option<usize>,
+}
+
+/// An indexed mesh living in GPU buffers, triangles unless built
+/// [`Mesh::with_mode`].
+pub struct Mesh {
+    vertex_array: VertexArray,
+    _vertex_buffer: Buffer,
+    _index_buffer: Buffer,
+    tangent_buffer: Buffer,
+    index_count: i32,
+    submeshes: Vec<Submesh>,
+    mode: PrimitiveMode,
+    skinned: bool,
+    bounds: Aabb,
+}
+
+impl Mesh {
+    pub fn new(vertices: &[Vertex], indices: &[u32]) -> Result<Mesh> {
+        let submesh = Submesh {
+            first_index: 0,
+            index_count: indices.len() as i32,
+            base_vertex: 0,
+            material: None,
+        };
+        Mesh::with_submeshes(vertices, indices, vec![submesh])
+    }
+
+    /// Builds a mesh drawing `indices` as `mode`, e.g. strips straight from an exporter.
+    /// Tangents are computed from the triangles the indices describe, split at the
+    /// context's restart index, so set that first.
+    pub fn with_mode(vertices: &[Vertex], indices: &[u32], mode: PrimitiveMode) -> Result<Mesh> {
+        let restart = context::options().primitive_restart;
+        let tangents = compute_tangents(vertices, &mode.triangle_list(indices, restart));
+        let mut mesh = Mesh::with_tangents(vertices, &tangents, indices)?;
+        mesh.set_mode(mode)?;
+        Ok(mesh)
+    }
+
+    /// Builds a mesh with tangents from [`compute_tangents`].
+    pub fn with_submeshes(
+        vertices: &[Vertex],
+        indices: &[u32],
+        submeshes: Vec<Submesh>,
+    ) -> Result<Mesh> {
+        let tangents = compute_tangents(vertices, &absolute_indices(indices, &submeshes));
+        Mesh::with_submeshes_and_tangents(vertices, &tangents, indices, submeshes)
+    }
+
+    /// Like [`Mesh::new`] with tangents supplied by the caller, e.g. from an asset that
+    /// ships its own.
+    pub fn with_tangents(
+        vertices: &[Vertex],
+        tangents: &[[f32; 4]],
+        indices: &[u32],
+    ) -> Result<Mesh> {
+        let submesh = Submesh {
+            first_index: 0,
+            index_count: indices.len() as i32,
+            base_vertex: 0,
+            material: None,
+        };
+        Mesh::with_submeshes_and_tangents(vertices, tangents, indices, vec![submesh])
+    }
+
+    pub fn with_submeshes_and_tangents(
+        vertices: &[Vertex],
+        tangents: &[[f32; 4]],
+        indices: &[u32],
+        submeshes: Vec<Submesh>,
+    ) -> Result<Mesh> {
+        let bounds = Aabb::from_points(vertices.iter().map(|v| Vec3::from(v.position)));
+        Mesh::upload(
+            bytemuck::cast_slice(vertices),
+            false,
+            tangents,
+            indices,
+            submeshes,
+            bounds,
+        )
+    }
+
+    /// Packs several meshes into one vertex and one index buffer, one submesh each. Every
+    /// submesh keeps its indices relative to its own vertices and draws with a base
+    /// vertex, so the parts share one allocation and one vertex array.
+    pub fn packed(parts: &[(&[Vertex], &[u32])]) -> Result<Mesh> {
+        let mut vertices = Vec::new();
+        let mut tangents = Vec::new();
+        let mut indices = Vec::new();
+        let mut submeshes = Vec::with_capacity(parts.len());
+        for &(part_vertices, part_indices) in parts {
+            submeshes.push(Submesh {
+                first_index: indices.len() as u32,
+                index_count: part_indices.len() as i32,
+                base_vertex: vertices.len() as i32,
+                material: None,
+            });
+            vertices.extend_from_slice(part_vertices);
+            tangents.extend(compute_tangents(part_vertices, part_indices));
+            indices.extend_from_slice(part_indices);
+        }
+        Mesh::with_submeshes_and_tangents(&vertices, &tangents, &indices, submeshes)
+    }
+
+    /// Builds a skinned mesh with tangents from [`compute_tangents`].
+    pub fn skinned(
+        vertices: &[SkinnedVertex],
+        indices: &[u32],
+        submeshes: Vec<Submesh>,
+    ) -> Result<Mesh> {
+        let unskinned: Vec<Vertex> = vertices
+            .iter()
+            .map(|v| Vertex {
+                position: v.position,
+                normal: v.normal,
+                uv: v.uv,
+            })
+            .collect();
+        let tangents = compute_tangents(&unskinned, &absolute_indices(indices, &submeshes));
+        Mesh::skinned_with_tangents(vertices, &tangents, indices, submeshes)
+    }
+
+    pub fn skinned_with_tangents(
+        vertices: &[SkinnedVertex],
+        tangents: &[[f32; 4]],
+        indices: &[u32],
+        submeshes: Vec<Submesh>,
+    ) -> Result<Mesh> {
+        let bounds = Aabb::from_points(vertices.iter().map(|v| Vec3::from(v.position)));
+        Mesh::upload(
+            bytemuck::cast_slice(vertices),
+            true,
+            tangents,
+            indices,
+            submeshes,
+            bounds,
+        )
+    }
+
+    fn upload(
+        vertices: &[u8],
+        skinned: bool,
+        tangents: &[[f32; 4]],
+        indices: &[u32],
+        submeshes: Vec<Submesh>,
+        bounds: Aabb,
+    ) -> Result<Mesh> {
+        let stride = if skinned {
+            std::mem::size_of::<SkinnedVertex>()
+        } else {
+            std::mem::size_of::<Vertex>()
+        };
+        if tangents.len() != vertices.len() / stride {
+            return Err(anyhow!(
+                "{} tangents for {} vertices",
+                tangents.len(),
+                vertices.len() / stride
+            ));
+        }
+        if submeshes.iter().any(|s| s.base_vertex != 0) && !indirect::base_vertex_supported() {
+            return Err(anyhow!("Base vertices need GL 3.2 or ES 3.2"));
+        }
+
+        let vertex_buffer = Buffer::new()?;
+        vertex_buffer.bind(gl::ARRAY_BUFFER);
+        vertex_buffer.data(gl::ARRAY_BUFFER, vertices, gl::STATIC_DRAW);
+        vertex_buffer.unbind(gl::ARRAY_BUFFER);
+
+        let tangent_buffer = Buffer::new()?;
+        tangent_buffer.bind(gl::ARRAY_BUFFER);
+        tangent_buffer.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(tangents),
+            gl::STATIC_DRAW,
+        );
+        tangent_buffer.unbind(gl::ARRAY_BUFFER);
+
+        let index_buffer = Buffer::new()?;
+        index_buffer.bind(gl::ARRAY_BUFFER);
+        index_buffer.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(indices),
+            gl::STATIC_DRAW,
+        );
+        index_buffer.unbind(gl::ARRAY_BUFFER);
+
+        let vertex_array = VertexArray::new()?;
+        vertex_array.element_buffer(&index_buffer);
+        if skinned {
+            SkinnedVertex::attach(&vertex_array, &vertex_buffer);
+        } else {
+            Vertex::attach(&vertex_array, &vertex_buffer);
+        }
+        vertex_array.attribute(
+            TANGENT_LOCATION,
+            &tangent_buffer,
+            4,
+            gl::FLOAT,
+            false,
+            16,
+            0,
+        );
+
+        Ok(Mesh {
+            vertex_array,
+            _vertex_buffer: vertex_buffer,
+            _index_buffer: index_buffer,
+            tangent_buffer,
+            index_count: indices.len() as i32,
+            submeshes,
+            mode: PrimitiveMode::Triangles,
+            skinned,
+            bounds,
+        })
+    }
+
+    /// Loads a Wavefront OBJ file, merging all of its objects into one vertex buffer with
+    /// one submesh per object. Materials from the referenced MTL files are converted to
+    /// Blinn-Phong [`Material`]s; diffuse textures are loaded relative to the OBJ file.
+    ///
+    /// Missing normals are computed by averaging face normals. Equivalent to
+    /// [`ObjModel::read`] followed by [`ObjModel::upload`].
+    pub fn from_obj<P: AsRef<Path>>(path: P) -> Result<(Mesh, Vec<Material>)> {
+        ObjModel::read(path)?.upload()
+    }
+
+    /// A square in the XZ plane, facing +Y, centered on the origin. See
+    /// [`primitives::plane`] for a subdivided one.
+    pub fn plane(size: f32) -> Result<Mesh> {
+        primitives::plane(size, 1).mesh()
+    }
+
+    /// An axis-aligned cube with edge length `size`, centered on the origin.
+    pub fn cube(size: f32) -> Result<Mesh> {
+        primitives::cube(size).mesh()
+    }
+
+    pub fn index_count(&self) -> i32 {
+        self.index_count
+    }
+
+    pub fn mode(&self) -> PrimitiveMode {
+        self.mode
+    }
+
+    /// Draws the same indices as `mode` from now on. Fails if the context can't.
+    pub fn set_mode(&mut self, mode: PrimitiveMode) -> Result<()> {
+        if !mode.is_supported() {
+            return Err(anyhow!("{:?} needs GL 3.2 or ES 3.2", mode));
+        }
+        self.mode = mode;
+        Ok(())
+    }
+
+    /// Object-space bounds of the vertices. For skinned meshes this is the bind pose.
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    /// The vertex array with the mesh's attributes and index buffer, for adding
+    /// per-instance attributes or issuing custom draws.
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    /// Checks the mesh's vertex layout against the inputs of `program`. See
+    /// [`VertexArray::check_program`].
+    pub fn check_program(&self, program: &Program) -> Result<()> {
+        self.vertex_array.check_program(program)
+    }
+
+    /// Labels the vertex array and buffers as `label`, `label vertices` and
+    /// `label indices` for graphics debuggers.
+    pub fn label(&self, label: &str) {
+        self.vertex_array.label(label);
+        self._vertex_buffer.label(&format!("{} vertices", label));
+        self._index_buffer.label(&format!("{} indices", label));
+        self.tangent_buffer.label(&format!("{} tangents", label));
+    }
+
+    /// Whether the mesh was built from [`SkinnedVertex`] data.
+    pub fn is_skinned(&self) -> bool {
+        self.skinned
+    }
+
+    pub fn submeshes(&self) -> &[Submesh] {
+        &self.submeshes
+    }
+
+    /// Draws a single submesh.
+    pub fn draw_submesh(&self, index: usize) {
+        self.vertex_array.bind();
+        self.draw_range(&self.submeshes[index]);
+        self.vertex_array.unbind();
+    }
+
+    /// Like [`Mesh::draw_submesh`], binding the vertex array through `cache` and leaving it
+    /// bound for the next draw.
+    pub fn draw_submesh_cached(&self, index: usize, cache: &mut StateCache) {
+        cache.bind_vertex_array(&self.vertex_array);
+        self.draw_range(&self.submeshes[index]);
+    }
+
+    /// Draws `instance_count` instances of a submesh, with per-instance attributes (see
+    /// [`VertexArray::divisor`]) starting at instance `base_instance`. A non-zero
+    /// `base_instance` needs [`indirect::base_instance_supported`].
+    pub fn draw_submesh_instanced(&self, index: usize, instance_count: i32, base_instance: u32) {
+        let submesh = &self.submeshes[index];
+        self.vertex_array.bind();
+        indirect::draw_elements_instanced_base_vertex_base_instance(
+            self.mode.gl_enum(),
+            submesh.index_count,
+            submesh.first_index,
+            instance_count,
+            submesh.base_vertex,
+            base_instance,
+        );
+        self.vertex_array.unbind();
+    }
+
+    /// Draws all submeshes, in one call unless they have different base vertices.
+    pub fn draw(&self) {
+        self.vertex_array.bind();
+        if self.submeshes.iter().all(|s| s.base_vertex == 0) {
+            indirect::draw_elements_base_vertex(self.mode.gl_enum(), self.index_count, 0, 0);
+        } else {
+            for submesh in &self.submeshes {
+                self.draw_range(submesh);
+            }
+        }
+        self.vertex_array.unbind();
+    }
+
+    fn draw_range(&self, submesh: &Submesh) {
+        indirect::draw_elements_base_vertex(
+            self.mode.gl_enum(),
+            submesh.index_count,
+            submesh.first_index,
+            submesh.base_vertex,
+        );
+    }
+}
+
+/// A Wavefront OBJ file read and decoded on the CPU, with its diffuse textures, but not
+/// yet uploaded. Reading needs no GL context, so it can happen on another thread.
+pub struct ObjModel {
+    label: String,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    submeshes: Vec<Submesh>,
+    materials: Vec<tobj::Material>,
+    /// Decoded diffuse textures by their name in the MTL file.
+    images: HashMap<String, (PathBuf, Image)>,
+}
+
+impl ObjModel {
+    /// Parses the OBJ and MTL files and decodes the textures; see [`Mesh::from_obj`].
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<ObjModel> {
+        let path = path.as_ref();
+        let (models, obj_materials) = tobj::load_obj(path, &tobj::GPU_LOAD_OPTIONS)
+            .map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))?;
+        let obj_materials = obj_materials
+            .map_err(|e| anyhow!("Failed to load materials for {}: {}", path.display(), e))?;
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut submeshes = Vec::new();
+        for model in &models {
+            let mesh = &model.mesh;
+            let base = vertices.len() as u32;
+            let first_index = indices.len() as u32;
+            for i in 0..mesh.positions.len() / 3 {
+                let normal = if mesh.normals.is_empty() {
+                    [0.0; 3]
+                } else {
+                    [
+                        mesh.normals[i * 3],
+                        mesh.normals[i * 3 + 1],
+                        mesh.normals[i * 3 + 2],
+                    ]
+                };
+                let uv = if mesh.texcoords.is_empty() {
+                    [0.0; 2]
+                } else {
+                    [mesh.texcoords[i * 2], mesh.texcoords[i * 2 + 1]]
+                };
+                vertices.push(Vertex {
+                    position: [
+                        mesh.positions[i * 3],
+                        mesh.positions[i * 3 + 1],
+                        mesh.positions[i * 3 + 2],
+                    ],
+                    normal,
+                    uv,
+                });
+            }
+            indices.extend(mesh.indices.iter().map(|&i| base + i));
+            if mesh.normals.is_empty() {
+                compute_normals(&mut vertices[base as usize..], &mesh.indices);
+            }
+            submeshes.push(Submesh {
+                first_index,
+                index_count: mesh.indices.len() as i32,
+                base_vertex: 0,
+                material: mesh.material_id,
+            });
+        }
+
+        let directory = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut images = HashMap::new();
+        for obj in &obj_materials {
+            let Some(name) = &obj.diffuse_texture else {
+                continue;
+            };
+            if !images.contains_key(name) {
+                let texture_path = directory.join(name);
+                let image = Image::load(&texture_path)?;
+                images.insert(name.clone(), (texture_path, image));
+            }
+        }
+
+        Ok(ObjModel {
+            label: path.display().to_string(),
+            vertices,
+            indices,
+            submeshes,
+            materials: obj_materials,
+            images,
+        })
+    }
+
+    /// Creates the mesh and textures and converts the materials. Needs the GL context.
+    pub fn upload(self) -> Result<(Mesh, Vec<Material>)> {
+        let mut textures: HashMap<String, Rc<Texture>> = HashMap::new();
+        let mut materials = Vec::with_capacity(self.materials.len());
+        for obj in &self.materials {
+            let mut material = Material::default();
+            if let Some([r, g, b]) = obj.diffuse {
+                material.base_color = Vec4::new(r, g, b, obj.dissolve.unwrap_or(1.0));
+            }
+            if let Some(specular) = obj.specular {
+                material.specular = Vec3::from(specular);
+            }
+            if let Some(shininess) = obj.shininess {
+                material.shininess = shininess.max(1.0);
+            }
+            if let Some(emissive) = obj.emissive {
+                material.emissive = Vec3::from(emissive);
+            }
+            if let Some(name) = &obj.diffuse_texture {
+                let texture = match textures.get(name) {
+                    Some(texture) => texture.clone(),
+                    None => {
+                        // `read` decoded every texture a material names.
+                        let (texture_path, image) = &self.images[name];
+                        let texture = Rc::new(Texture::from_image(image)?);
+                        texture.label(&texture_path.display().to_string());
+                        textures.insert(name.clone(), texture.clone());
+                        texture
+                    }
+                };
+                material.base_color_texture = Some(texture);
+            }
+            materials.push(material);
+        }
+
+        let mesh = Mesh::with_submeshes(&self.vertices, &self.indices, self.submeshes)?;
+        mesh.label(&self.label);
+        Ok((mesh, materials))
+    }
+}
+
+/// `indices` with every submesh's base vertex added, for CPU-side work over the whole
+/// vertex buffer.
+fn absolute_indices<'a>(indices: &'a [u32], submeshes: &[Submesh]) -> Cow<'a, [u32]> {
+    if submeshes.iter().all(|s| s.base_vertex == 0) {
+        return Cow::Borrowed(indices);
+    }
+    let mut absolute = indices.to_vec();
+    for submesh in submeshes {
+        let first = submesh.first_index as usize;
+        let range = first..first + submesh.index_count as usize;
+        for index in &mut absolute[range] {
+            *index = index.wrapping_add_signed(submesh.base_vertex);
+        }
+    }
+    Cow::Owned(absolute)
+}
+
+/// Replaces vertex normals with the area-weighted average of adjacent face normals.
+pub(crate) fn compute_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut normals = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from(vertices[i].position));
+        let face = (pb - pa).cross(pc - pa);
+        normals[a] += face;
+        normals[b] += face;
+        normals[c] += face;
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        vertex.normal = normal.normalize_or_zero().to_array();
+    }
+}
+
+/// Per-vertex tangents for normal mapping, following the texture coordinates'
+/// `u` direction, orthogonalized against the normals, with the bitangent's handedness in
+/// `w`. Vertices whose triangles have degenerate texture coordinates get an arbitrary
+/// tangent perpendicular to their normal.
+pub fn compute_tangents(vertices: &[Vertex], indices: &[u32]) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| triangle[i] as usize);
+        let [pa, pb, pc] = [a, b, c].map(|i| Vec3::from(vertices[i].position));
+        let [ta, tb, tc] = [a, b, c].map(|i| Vec2::from(vertices[i].uv));
+        let (e1, e2) = (pb - pa, pc - pa);
+        let (d1, d2) = (tb - ta, tc - ta);
+        let det = d1.x * d2.y - d2.x * d1.y;
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (e1 * d2.y - e2 * d1.y) / det;
+        let bitangent = (e2 * d1.x - e1 * d2.x) / det;
+        for i in [a, b, c] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+    vertices
+        .iter()
+        .zip(tangents.into_iter().zip(bitangents))
+        .map(|(vertex, (tangent, bitangent))| {
+            let normal = Vec3::from(vertex.normal);
+            let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+            let tangent = if tangent == Vec3::ZERO {
+                normal.any_orthonormal_vector()
+            } else {
+                tangent
+            };
+            let w = if normal.cross(tangent).dot(bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            tangent.extend(w).to_array()
+        })
+        .collect()
+}
@@ -0,0 +1,545 @@
+//! Procedural noise and noise textures.
+//!
+//! The functions here evaluate Perlin, simplex and Worley noise at a point on the CPU.
+//! Perlin and Worley noise take a `period` in lattice cells after which they repeat, so
+//! textures built from them tile; simplex noise's skewed lattice doesn't repeat and
+//! leaves seams. [`NoiseConfig`] sums octaves of one kind into a value in `0..1`, and
+//! [`texture_2d`] and [`texture_3d`] bake it into repeating `R8` textures, e.g. for
+//! terrain ([`crate::terrain::Heightmap::from_noise`]) or volumes to perturb particles
+//! and fog with.
+//!
+//! [`blue_noise`] ranks the pixels of a tile with the void-and-cluster method, so any
+//! threshold of it gives evenly spread points without clumps; it hides banding when
+//! added as dither, as [`crate::postprocess::PostProcess::set_dither`] does, at far less
+//! visible cost than white noise.
+
+use anyhow::Result;
+
+use crate::gl;
+use crate::math::{Vec2, Vec3};
+use crate::texture::Texture;
+
+/// Directions to the edge midpoints of a cube, the gradients of 3D Perlin and simplex
+/// noise. Their XY parts serve 2D simplex noise.
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0],
+    [-1.0, 1.0, 0.0],
+    [1.0, -1.0, 0.0],
+    [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0],
+    [-1.0, 0.0, 1.0],
+    [1.0, 0.0, -1.0],
+    [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0],
+    [0.0, -1.0, 1.0],
+    [0.0, 1.0, -1.0],
+    [0.0, -1.0, -1.0],
+];
+
+/// Width of the Gaussian the void-and-cluster method measures clumping with, in pixels.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// Share of pixels in the initial pattern of the void-and-cluster method.
+const BLUE_NOISE_INITIAL_DENSITY: f32 = 0.1;
+
+/// Which noise a [`NoiseConfig`] sums.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Noise {
+    Perlin,
+    Simplex,
+    /// Distance to the nearest of one random point per cell: cells of bright rims
+    /// around dark centers.
+    Worley,
+}
+
+/// Octaves of noise over a texture: `frequency` lattice cells across it at the first
+/// octave, twice as many at each next one with `gain` times the amplitude.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NoiseConfig {
+    pub noise: Noise,
+    pub frequency: u32,
+    pub octaves: u32,
+    pub gain: f32,
+    pub seed: u32,
+}
+
+impl Default for NoiseConfig {
+    fn default() -> Self {
+        NoiseConfig {
+            noise: Noise::Perlin,
+            frequency: 4,
+            octaves: 4,
+            gain: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+impl NoiseConfig {
+    /// The summed octaves at `uv`, which spans the texture over `0..1`, in `0..1`.
+    pub fn sample_2d(&self, uv: Vec2) -> f32 {
+        self.octaves(|frequency, seed| {
+            let p = uv * frequency as f32;
+            match self.noise {
+                Noise::Perlin => perlin_2d(p, frequency, seed) * 0.5 + 0.5,
+                Noise::Simplex => simplex_2d(p, seed) * 0.5 + 0.5,
+                Noise::Worley => worley_2d(p, frequency, seed),
+            }
+        })
+    }
+
+    /// Like [`NoiseConfig::sample_2d`] over a volume.
+    pub fn sample_3d(&self, uvw: Vec3) -> f32 {
+        self.octaves(|frequency, seed| {
+            let p = uvw * frequency as f32;
+            match self.noise {
+                Noise::Perlin => perlin_3d(p, frequency, seed) * 0.5 + 0.5,
+                Noise::Simplex => simplex_3d(p, seed) * 0.5 + 0.5,
+                Noise::Worley => worley_3d(p, frequency, seed),
+            }
+        })
+    }
+
+    fn octaves(&self, mut octave: impl FnMut(u32, u32) -> f32) -> f32 {
+        let (mut sum, mut total, mut amplitude) = (0.0, 0.0, 1.0);
+        for i in 0..self.octaves.max(1) {
+            let frequency = self.frequency.max(1) << i.min(16);
+            sum += amplitude * octave(frequency, self.seed.wrapping_add(i));
+            total += amplitude;
+            amplitude *= self.gain;
+        }
+        (sum / total).clamp(0.0, 1.0)
+    }
+
+    /// `size × size` samples at texel centers, rows first.
+    pub fn generate_2d(&self, size: u32) -> Vec<f32> {
+        let texel = |i: u32| (i as f32 + 0.5) / size as f32;
+        (0..size * size)
+            .map(|i| self.sample_2d(Vec2::new(texel(i % size), texel(i / size))))
+            .collect()
+    }
+
+    /// `size³` samples at texel centers, rows first, then slices.
+    pub fn generate_3d(&self, size: u32) -> Vec<f32> {
+        let texel = |i: u32| (i as f32 + 0.5) / size as f32;
+        (0..size * size * size)
+            .map(|i| {
+                let (x, y, z) = (i % size, i / size % size, i / (size * size));
+                self.sample_3d(Vec3::new(texel(x), texel(y), texel(z)))
+            })
+            .collect()
+    }
+}
+
+/// Gradient noise in about `-1..1`, repeating every `period` cells (never for 0).
+pub fn perlin_2d(p: Vec2, period: u32, seed: u32) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let (x, y) = (cell.x as i64, cell.y as i64);
+    let corner = |dx: i64, dy: i64| {
+        let h = lattice(seed, [wrap(x + dx, period), wrap(y + dy, period), 0]);
+        let angle = h as f32 / u32::MAX as f32 * std::f32::consts::TAU;
+        Vec2::new(angle.cos(), angle.sin()).dot(f - Vec2::new(dx as f32, dy as f32))
+    };
+    let (u, v) = (fade(f.x), fade(f.y));
+    let bottom = lerp(corner(0, 0), corner(1, 0), u);
+    let top = lerp(corner(0, 1), corner(1, 1), u);
+    lerp(bottom, top, v) * std::f32::consts::SQRT_2
+}
+
+/// Like [`perlin_2d`] in three dimensions.
+pub fn perlin_3d(p: Vec3, period: u32, seed: u32) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let (x, y, z) = (cell.x as i64, cell.y as i64, cell.z as i64);
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let h = lattice(
+            seed,
+            [
+                wrap(x + dx, period),
+                wrap(y + dy, period),
+                wrap(z + dz, period),
+            ],
+        );
+        let offset = f - Vec3::new(dx as f32, dy as f32, dz as f32);
+        Vec3::from(GRADIENTS[h as usize % 12]).dot(offset)
+    };
+    let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+    let face = |dz: i64| {
+        let bottom = lerp(corner(0, 0, dz), corner(1, 0, dz), u);
+        let top = lerp(corner(0, 1, dz), corner(1, 1, dz), u);
+        lerp(bottom, top, v)
+    };
+    lerp(face(0), face(1), w)
+}
+
+/// Simplex noise in about `-1..1`: smoother than [`perlin_2d`] with no axis-aligned
+/// artifacts, but never repeating.
+pub fn simplex_2d(p: Vec2, seed: u32) -> f32 {
+    let skew = 0.5 * (3f32.sqrt() - 1.0);
+    let unskew = (3.0 - 3f32.sqrt()) / 6.0;
+    let cell = (p + Vec2::splat((p.x + p.y) * skew)).floor();
+    let origin = cell - Vec2::splat((cell.x + cell.y) * unskew);
+    let d0 = p - origin;
+    let step = if d0.x > d0.y { Vec2::X } else { Vec2::Y };
+    let corners = [
+        (Vec2::ZERO, d0),
+        (step, d0 - step + Vec2::splat(unskew)),
+        (Vec2::ONE, d0 - Vec2::ONE + Vec2::splat(2.0 * unskew)),
+    ];
+    let mut sum = 0.0;
+    for (offset, d) in corners {
+        let t = 0.5 - d.length_squared();
+        if t > 0.0 {
+            let c = cell + offset;
+            let h = lattice(seed, [c.x as i64 as u32, c.y as i64 as u32, 0]);
+            let gradient = GRADIENTS[h as usize % 12];
+            sum += t.powi(4) * Vec2::new(gradient[0], gradient[1]).dot(d);
+        }
+    }
+    70.0 * sum
+}
+
+/// Like [`simplex_2d`] in three dimensions.
+pub fn simplex_3d(p: Vec3, seed: u32) -> f32 {
+    let (skew, unskew) = (1.0 / 3.0, 1.0 / 6.0);
+    let cell = (p + Vec3::splat((p.x + p.y + p.z) * skew)).floor();
+    let origin = cell - Vec3::splat((cell.x + cell.y + cell.z) * unskew);
+    let d0 = p - origin;
+    // The two intermediate corners, stepping along the largest offsets first.
+    let (first, second) = if d0.x >= d0.y {
+        if d0.y >= d0.z {
+            (Vec3::X, Vec3::new(1.0, 1.0, 0.0))
+        } else if d0.x >= d0.z {
+            (Vec3::X, Vec3::new(1.0, 0.0, 1.0))
+        } else {
+            (Vec3::Z, Vec3::new(1.0, 0.0, 1.0))
+        }
+    } else if d0.y < d0.z {
+        (Vec3::Z, Vec3::new(0.0, 1.0, 1.0))
+    } else if d0.x < d0.z {
+        (Vec3::Y, Vec3::new(0.0, 1.0, 1.0))
+    } else {
+        (Vec3::Y, Vec3::new(1.0, 1.0, 0.0))
+    };
+    let corners = [
+        (Vec3::ZERO, d0),
+        (first, d0 - first + Vec3::splat(unskew)),
+        (second, d0 - second + Vec3::splat(2.0 * unskew)),
+        (Vec3::ONE, d0 - Vec3::ONE + Vec3::splat(3.0 * unskew)),
+    ];
+    let mut sum = 0.0;
+    for (offset, d) in corners {
+        let t = 0.6 - d.length_squared();
+        if t > 0.0 {
+            let c = cell + offset;
+            let h = lattice(
+                seed,
+                [c.x as i64 as u32, c.y as i64 as u32, c.z as i64 as u32],
+            );
+            sum += t.powi(4) * Vec3::from(GRADIENTS[h as usize % 12]).dot(d);
+        }
+    }
+    32.0 * sum
+}
+
+/// Distance from `p` to the nearest feature point, one per cell, in cells and clamped
+/// to `0..1`. Repeats every `period` cells (never for 0).
+pub fn worley_2d(p: Vec2, period: u32, seed: u32) -> f32 {
+    let cell = p.floor();
+    let mut nearest = f32::MAX;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            let neighbor = cell + Vec2::new(dx as f32, dy as f32);
+            let h = lattice(
+                seed,
+                [
+                    wrap(neighbor.x as i64, period),
+                    wrap(neighbor.y as i64, period),
+                    0,
+                ],
+            );
+            let feature = neighbor + Vec2::new(unit(h), unit(hash(h)));
+            nearest = nearest.min(feature.distance_squared(p));
+        }
+    }
+    nearest.sqrt().min(1.0)
+}
+
+/// Like [`worley_2d`] in three dimensions.
+pub fn worley_3d(p: Vec3, period: u32, seed: u32) -> f32 {
+    let cell = p.floor();
+    let mut nearest = f32::MAX;
+    for dz in -1..=1 {
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let neighbor = cell + Vec3::new(dx as f32, dy as f32, dz as f32);
+                let h = lattice(
+                    seed,
+                    [
+                        wrap(neighbor.x as i64, period),
+                        wrap(neighbor.y as i64, period),
+                        wrap(neighbor.z as i64, period),
+                    ],
+                );
+                let jitter = Vec3::new(unit(h), unit(hash(h)), unit(hash(hash(h))));
+                nearest = nearest.min((neighbor + jitter).distance_squared(p));
+            }
+        }
+    }
+    nearest.sqrt().min(1.0)
+}
+
+/// Ranks of a `size × size` tile of blue noise in `0..1`, rows first: the first `n`
+/// pixels in rank order are as evenly spread as `n` points can be, at every `n`, and
+/// the tile repeats seamlessly. Takes time quadratic in the pixel count; 64 is typical.
+pub fn blue_noise(size: u32, seed: u32) -> Vec<f32> {
+    let size = size.max(1) as usize;
+    let count = size * size;
+    // The Gaussian of each toroidal offset, so energy updates are lookups.
+    let falloff: Vec<f32> = (0..count)
+        .map(|i| {
+            let toroidal = |d: usize| d.min(size - d) as f32;
+            let (dx, dy) = (toroidal(i % size), toroidal(i / size));
+            (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+        })
+        .collect();
+    let mut field = Field {
+        size,
+        falloff,
+        energy: vec![0.0; count],
+        set: vec![false; count],
+    };
+
+    // A random initial pattern, relaxed by moving its tightest clusters into its
+    // largest voids until the move would put a point back where it came from.
+    let initial = ((count as f32 * BLUE_NOISE_INITIAL_DENSITY) as usize).clamp(1, count);
+    let mut state = hash(seed ^ 0x9e37_79b9);
+    let mut placed = 0;
+    while placed < initial {
+        state = hash(state);
+        let i = state as usize % count;
+        if !field.set[i] {
+            field.toggle(i);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = field.tightest_cluster();
+        field.toggle(cluster);
+        let void = field.largest_void();
+        field.toggle(void);
+        if void == cluster {
+            break;
+        }
+    }
+    let pattern = field.set.clone();
+    let energy = field.energy.clone();
+
+    let mut rank = vec![0usize; count];
+    // Ranks below the initial count: remove the tightest clusters, last rank first.
+    for r in (0..initial).rev() {
+        let cluster = field.tightest_cluster();
+        field.toggle(cluster);
+        rank[cluster] = r;
+    }
+    // Ranks from there up: fill the largest voids.
+    field.set = pattern;
+    field.energy = energy;
+    for r in initial..count {
+        let void = field.largest_void();
+        field.toggle(void);
+        rank[void] = r;
+    }
+    rank.iter()
+        .map(|&r| (r as f32 + 0.5) / count as f32)
+        .collect()
+}
+
+/// A binary pattern and the Gaussian-weighted density of its set pixels everywhere.
+struct Field {
+    size: usize,
+    falloff: Vec<f32>,
+    energy: Vec<f32>,
+    set: Vec<bool>,
+}
+
+impl Field {
+    fn toggle(&mut self, i: usize) {
+        self.set[i] = !self.set[i];
+        let sign = if self.set[i] { 1.0 } else { -1.0 };
+        let (x, y) = (i % self.size, i / self.size);
+        for (j, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (j % self.size + self.size - x) % self.size;
+            let dy = (j / self.size + self.size - y) % self.size;
+            *energy += sign * self.falloff[dy * self.size + dx];
+        }
+    }
+
+    /// The set pixel with the most energy.
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// The unset pixel with the least energy.
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, better: impl Fn(f32, f32) -> bool) -> usize {
+        let mut best = None;
+        for (i, &energy) in self.energy.iter().enumerate() {
+            if self.set[i] != set {
+                continue;
+            }
+            match best {
+                Some((_, e)) if !better(energy, e) => {}
+                _ => best = Some((i, energy)),
+            }
+        }
+        best.map_or(0, |(i, _)| i)
+    }
+}
+
+/// A repeating `size × size` `R8` texture of `config`, with mipmaps.
+pub fn texture_2d(config: &NoiseConfig, size: u32) -> Result<Texture> {
+    let texels = to_bytes(&config.generate_2d(size));
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    set_sampling(&texture, true, false);
+    with_byte_alignment(|| {
+        let size = size as i32;
+        texture.image_2d(
+            0,
+            gl::R8,
+            size,
+            size,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            Some(&texels),
+        );
+    });
+    texture.generate_mipmaps();
+    texture.unbind();
+    texture.label(&format!("{:?} noise", config.noise));
+    Ok(texture)
+}
+
+/// A repeating `size³` `R8` volume texture of `config`, with mipmaps.
+pub fn texture_3d(config: &NoiseConfig, size: u32) -> Result<Texture> {
+    let texels = to_bytes(&config.generate_3d(size));
+    let texture = Texture::new(gl::TEXTURE_3D)?;
+    texture.bind();
+    set_sampling(&texture, true, true);
+    with_byte_alignment(|| {
+        let size = size as i32;
+        texture.image_3d(
+            0,
+            gl::R8,
+            size,
+            size,
+            size,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            Some(&texels),
+        );
+    });
+    texture.generate_mipmaps();
+    texture.unbind();
+    texture.label(&format!("{:?} noise volume", config.noise));
+    Ok(texture)
+}
+
+/// A repeating `size × size` `R8` texture of [`blue_noise`], sampled with nearest
+/// filtering, for tiling over the screen with `texelFetch` and `gl_FragCoord`.
+pub fn blue_noise_texture(size: u32) -> Result<Texture> {
+    let texels = to_bytes(&blue_noise(size, 0));
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    set_sampling(&texture, false, false);
+    with_byte_alignment(|| {
+        let size = size.max(1) as i32;
+        texture.image_2d(
+            0,
+            gl::R8,
+            size,
+            size,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            Some(&texels),
+        );
+    });
+    texture.unbind();
+    texture.label("Blue noise");
+    Ok(texture)
+}
+
+fn set_sampling(texture: &Texture, filtered: bool, volume: bool) {
+    let (min, mag) = if filtered {
+        (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR)
+    } else {
+        (gl::NEAREST, gl::NEAREST)
+    };
+    texture.parameter(gl::TEXTURE_MIN_FILTER, min as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, mag as i32);
+    texture.parameter(gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+    texture.parameter(gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+    if volume {
+        texture.parameter(gl::TEXTURE_WRAP_R, gl::REPEAT as i32);
+    }
+}
+
+/// Runs `upload` with tightly packed rows, as single-channel rows of any width are.
+fn with_byte_alignment(upload: impl FnOnce()) {
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+    }
+    upload();
+    unsafe {
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+    }
+}
+
+fn to_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+        .collect()
+}
+
+/// A well-mixed 32-bit hash (Wellons' lowbias32).
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb_352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846c_a68b);
+    x ^= x >> 16;
+    x
+}
+
+fn lattice(seed: u32, [x, y, z]: [u32; 3]) -> u32 {
+    hash(seed ^ hash(x ^ hash(y ^ hash(z))))
+}
+
+/// `i` modulo `period`, or as is for a period of 0.
+fn wrap(i: i64, period: u32) -> u32 {
+    if period == 0 {
+        i as u32
+    } else {
+        i.rem_euclid(period as i64) as u32
+    }
+}
+
+/// A hash mapped to `0..1`.
+fn unit(h: u32) -> f32 {
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+/// Perlin's quintic, with zero first and second derivatives at 0 and 1.
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,218 @@
+//! Weighted blended order-independent transparency (McGuire and Bavoil, 2013).
+//!
+//! Sorting blended draws back to front breaks down for intersecting surfaces, which no
+//! order can draw correctly. Here transparent fragments instead accumulate in any order
+//! into two targets: premultiplied color scaled by a depth-based weight, and the product
+//! of their transparencies (the revealage). A composite pass then blends the weighted
+//! average color over the opaque image.
+//!
+//! Fragment shaders write through [`OIT_OUTPUT_GLSL`]. The material shaders have
+//! variants that do, which [`crate::draw::DrawList`] uses for blended draws with
+//! [`crate::draw::Transparency::WeightedBlended`].
+
+use std::cell::Cell;
+
+use anyhow::Result;
+
+use crate::framebuffer::{Framebuffer, Renderbuffer};
+use crate::gl;
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::texture::Texture;
+
+/// Defines `write_color(vec4 color)` for a fragment shader's final, non-premultiplied
+/// color. With `OIT` defined it writes the two accumulation targets, otherwise a plain
+/// `frag_color` output.
+pub const OIT_OUTPUT_GLSL: &str = r#"
+#ifdef OIT
+layout (location = 0) out vec4 oit_accumulation;
+layout (location = 1) out float oit_revealage;
+
+void write_color(vec4 color) {
+    float a = clamp(color.a, 0.0, 1.0);
+    // Nearer and more opaque fragments dominate the average.
+    float z = 1.0 - gl_FragCoord.z * 0.9;
+    float weight = clamp(pow(min(1.0, a * 10.0) + 0.01, 3.0) * 1e8 * z * z * z, 1e-2, 3e3);
+    oit_accumulation = vec4(color.rgb * a, a) * weight;
+    oit_revealage = a;
+}
+#else
+out vec4 frag_color;
+
+void write_color(vec4 color) {
+    frag_color = color;
+}
+#endif
+"#;
+
+const COMPOSITE_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_accumulation;
+uniform sampler2D u_revealage;
+
+void main() {
+    float revealage = texture(u_revealage, uv).r;
+    if (revealage >= 1.0) {
+        discard;
+    }
+    vec4 accumulation = texture(u_accumulation, uv);
+    vec3 average = accumulation.rgb / max(accumulation.a, 1e-5);
+    frag_color = vec4(average, 1.0 - revealage);
+}
+"#;
+
+/// The accumulation and revealage targets plus a copy of the scene's depth buffer, so
+/// transparent surfaces are hidden behind opaque ones.
+pub struct OitTargets {
+    framebuffer: Framebuffer,
+    accumulation: Texture,
+    revealage: Texture,
+    _depth: Renderbuffer,
+    composite: Program,
+    triangle: FullscreenTriangle,
+    width: i32,
+    height: i32,
+    /// The draw framebuffer bound at [`OitTargets::begin`], composited into at the end.
+    scene: Cell<gl::types::GLuint>,
+}
+
+impl OitTargets {
+    /// `depth_format` must match the depth buffer of the framebuffer the scene is drawn
+    /// into for the depth copy to succeed: usually `GL_DEPTH24_STENCIL8` for the default
+    /// framebuffer and [`crate::depth::format`] for a [`crate::framebuffer::RenderTarget`].
+    pub fn new(width: i32, height: i32, depth_format: gl::types::GLenum) -> Result<OitTargets> {
+        let accumulation = target_texture(gl::RGBA16F, gl::RGBA, width, height)?;
+        accumulation.label("OIT accumulation");
+        let revealage = target_texture(gl::R16F, gl::RED, width, height)?;
+        revealage.label("OIT revealage");
+        let depth = Renderbuffer::with_storage(depth_format, width, height, 0)?;
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &accumulation, 0);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT1, &revealage, 0);
+        let depth_attachment =
+            if matches!(depth_format, gl::DEPTH24_STENCIL8 | gl::DEPTH32F_STENCIL8) {
+                gl::DEPTH_STENCIL_ATTACHMENT
+            } else {
+                gl::DEPTH_ATTACHMENT
+            };
+        framebuffer.attach_renderbuffer(gl::FRAMEBUFFER, depth_attachment, &depth);
+        let status = framebuffer
+            .draw_buffers(&[gl::COLOR_ATTACHMENT0, gl::COLOR_ATTACHMENT1])
+            .and_then(|()| framebuffer.check_status(gl::FRAMEBUFFER));
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        status?;
+        framebuffer.label("OIT");
+
+        Ok(OitTargets {
+            framebuffer,
+            accumulation,
+            revealage,
+            _depth: depth,
+            composite: Program::from_sources(FULLSCREEN_VERTEX_SHADER, COMPOSITE_SHADER)?,
+            triangle: FullscreenTriangle::new()?,
+            width,
+            height,
+            scene: Cell::new(0),
+        })
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Copies the depth of the currently bound draw framebuffer, which must be the
+    /// targets' size, then binds and clears the targets and sets up blending with depth
+    /// testing on and depth writes off. Draw transparent surfaces with shaders writing
+    /// through [`OIT_OUTPUT_GLSL`] with `OIT` defined, then call [`OitTargets::end`].
+    pub fn begin(&self) {
+        let mut scene = 0;
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut scene);
+        }
+        self.scene.set(scene as gl::types::GLuint);
+
+        let (width, height) = (self.width, self.height);
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, scene as gl::types::GLuint);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, self.framebuffer.id());
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer.id());
+            gl::Viewport(0, 0, width, height);
+            gl::ClearBufferfv(gl::COLOR, 0, [0.0f32; 4].as_ptr());
+            gl::ClearBufferfv(gl::COLOR, 1, [1.0f32; 4].as_ptr());
+
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::FALSE);
+            gl::Enable(gl::BLEND);
+            blend_func_indexed(0, gl::ONE, gl::ONE);
+            blend_func_indexed(1, gl::ZERO, gl::ONE_MINUS_SRC_COLOR);
+        }
+    }
+
+    /// Rebinds the framebuffer that was bound at [`OitTargets::begin`] and blends the
+    /// transparent surfaces over it. Leaves blending off and depth testing and writes on.
+    pub fn end(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.scene.get());
+            gl::Disable(gl::DEPTH_TEST);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.composite.use_program();
+        self.accumulation.bind_unit(0);
+        self.revealage.bind_unit(1);
+        self.composite.set_int("u_accumulation", 0);
+        self.composite.set_int("u_revealage", 1);
+        self.triangle.draw();
+        unsafe {
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+        }
+    }
+}
+
+/// A single-level render target texture sampled with nearest filtering.
+fn target_texture(
+    internal_format: gl::types::GLenum,
+    format: gl::types::GLenum,
+    width: i32,
+    height: i32,
+) -> Result<Texture> {
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    texture.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    texture.image_2d(0, internal_format, width, height, format, gl::FLOAT, None);
+    texture.unbind();
+    Ok(texture)
+}
+
+/// `glBlendFunci`, which below 4.0 is only bound as `GL_ARB_draw_buffers_blend`'s entry point.
+unsafe fn blend_func_indexed(
+    buf: gl::types::GLuint,
+    src: gl::types::GLenum,
+    dst: gl::types::GLenum,
+) {
+    #[cfg(gl_blend_indexed)]
+    gl::BlendFunci(buf, src, dst);
+    #[cfg(not(gl_blend_indexed))]
+    gl::BlendFunciARB(buf, src, dst);
+}
@@ -0,0 +1,161 @@
+//! Frame pacing: an FPS limiter and statistics on how evenly frames arrive.
+//!
+//! The runners keep the event loop polling so animation never stalls, and call
+//! [`FrameLimiter::wait`] once per frame. With a target set (from the `max_fps` setting
+//! or [`set_target_fps`]) it sleeps until shortly before the next frame is due and spins
+//! for the rest, since OS sleeps can overshoot by a millisecond or more. Without one it
+//! returns immediately and vsync, if enabled, paces the frames.
+//!
+//! [`stats`] summarizes the recent frame intervals: the 95th percentile shows stutter
+//! that an average hides, and missed deadlines count frames that took longer than the
+//! target.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long before a deadline the limiter stops sleeping and starts spinning.
+const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+/// Number of recent frames the statistics cover.
+const HISTORY: usize = 240;
+
+/// A summary of the last few seconds of frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PacingStats {
+    /// Frames since the runner started.
+    pub frames: u64,
+    /// Frames that started after their deadline since the runner started; always zero
+    /// without a target.
+    pub missed: u64,
+    /// Average, 95th percentile and longest interval between frames, in seconds.
+    pub average: f32,
+    pub p95: f32,
+    pub worst: f32,
+}
+
+thread_local! {
+    static TARGET: Cell<Option<f32>> = const { Cell::new(None) };
+    static STATS: Cell<PacingStats> = const {
+        Cell::new(PacingStats {
+            frames: 0,
+            missed: 0,
+            average: 0.0,
+            p95: 0.0,
+            worst: 0.0,
+        })
+    };
+}
+
+/// Limits the runners to `fps` frames per second, or lifts the limit with `None`. Zero,
+/// negative, infinite and NaN targets, and ones too low for a frame period to represent,
+/// also lift it.
+pub fn set_target_fps(fps: Option<f32>) {
+    TARGET.with(|target| target.set(fps.filter(|&fps| period(fps).is_some())));
+}
+
+pub fn target_fps() -> Option<f32> {
+    TARGET.with(Cell::get)
+}
+
+/// The statistics as of the last [`FrameLimiter::wait`].
+pub fn stats() -> PacingStats {
+    STATS.with(Cell::get)
+}
+
+/// Sleeps between frames to hold [`target_fps`]; owned by a runner.
+#[derive(Debug)]
+pub struct FrameLimiter {
+    deadline: Option<Instant>,
+    last_frame: Option<Instant>,
+    intervals: VecDeque<f32>,
+    frames: u64,
+    missed: u64,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        FrameLimiter::new()
+    }
+}
+
+impl FrameLimiter {
+    pub fn new() -> FrameLimiter {
+        FrameLimiter {
+            deadline: None,
+            last_frame: None,
+            intervals: VecDeque::with_capacity(HISTORY),
+            frames: 0,
+            missed: 0,
+        }
+    }
+
+    /// Blocks until the next frame is due, then records the frame interval. Deadlines
+    /// advance by whole periods, so a late frame doesn't shift the ones after it; after
+    /// falling more than a period behind the schedule restarts from now instead of
+    /// rushing to catch up.
+    pub fn wait(&mut self) {
+        match target_fps().and_then(period) {
+            Some(period) => {
+                let now = Instant::now();
+                let deadline = self.deadline.unwrap_or(now);
+                if now > deadline {
+                    self.missed += 1;
+                }
+                if now <= deadline + period {
+                    sleep_until(deadline);
+                    self.deadline = Some(deadline + period);
+                } else {
+                    self.deadline = Some(now + period);
+                }
+            }
+            None => self.deadline = None,
+        }
+        self.record(Instant::now());
+    }
+
+    fn record(&mut self, now: Instant) {
+        if let Some(last) = self.last_frame {
+            if self.intervals.len() == HISTORY {
+                self.intervals.pop_front();
+            }
+            self.intervals.push_back((now - last).as_secs_f32());
+        }
+        self.last_frame = Some(now);
+        self.frames += 1;
+
+        let mut sorted: Vec<f32> = self.intervals.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: f32| {
+            let index = ((sorted.len() as f32 * p).ceil() as usize).saturating_sub(1);
+            sorted.get(index).copied().unwrap_or(0.0)
+        };
+        let stats = PacingStats {
+            frames: self.frames,
+            missed: self.missed,
+            average: sorted.iter().sum::<f32>() / sorted.len().max(1) as f32,
+            p95: percentile(0.95),
+            worst: sorted.last().copied().unwrap_or(0.0),
+        };
+        STATS.with(|cell| cell.set(stats));
+    }
+}
+
+/// Sleeps until [`SPIN_MARGIN`] before `deadline`, then spins until it.
+/// The interval between frames at `fps`, if that is a usable limit.
+fn period(fps: f32) -> Option<Duration> {
+    if !(fps > 0.0 && fps.is_finite()) {
+        return None;
+    }
+    Duration::try_from_secs_f32(1.0 / fps).ok()
+}
+
+fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline > now + SPIN_MARGIN {
+        std::thread::sleep(deadline - now - SPIN_MARGIN);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
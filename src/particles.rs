@@ -0,0 +1,624 @@
+//! Particle emitters.
+//!
+//! A [`ParticleSystem`] keeps a fixed pool of particles, respawning the oldest slots at
+//! the configured rate. Simulation runs either on the CPU, uploading one instance per
+//! live particle for instanced billboards (GL 3.3), or in a compute shader that writes a
+//! storage buffer the vertex shader reads directly (GL 4.3, in builds with compute
+//! shaders; see [`crate::gl`]).
+//!
+//! Curves over a particle's normalized age are given as linear [`Track`]s and baked into
+//! [`CURVE_SAMPLES`] samples, so both backends evaluate them the same way.
+
+#[cfg(gl_compute)]
+use std::mem::offset_of;
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::animation::{Interpolation, Keyframe, Track};
+#[cfg(gl_compute)]
+use crate::block_layout::{self, BlockKind, Field};
+use crate::buffer::{Buffer, VertexArray};
+#[cfg(gl_compute)]
+use crate::context;
+use crate::gl;
+#[cfg(gl_compute)]
+use crate::load_store::{self, Barriers};
+use crate::math::{Mat4, Vec3, Vec4};
+use crate::shader::Program;
+use crate::stats;
+use crate::validate;
+
+/// Number of samples curves are baked into.
+pub const CURVE_SAMPLES: usize = 16;
+
+/// Storage buffer binding point of the GPU particle pool.
+#[cfg(gl_compute)]
+const PARTICLES_BINDING: u32 = 0;
+
+#[cfg(gl_compute)]
+const CURVES_GLSL: &str = r#"
+#define CURVE_SAMPLES 16
+float sample_curve(float curve[CURVE_SAMPLES], float t) {
+    float x = clamp(t, 0.0, 1.0) * float(CURVE_SAMPLES - 1);
+    int i = min(int(x), CURVE_SAMPLES - 2);
+    return mix(curve[i], curve[i + 1], x - float(i));
+}
+vec4 sample_curve(vec4 curve[CURVE_SAMPLES], float t) {
+    float x = clamp(t, 0.0, 1.0) * float(CURVE_SAMPLES - 1);
+    int i = min(int(x), CURVE_SAMPLES - 2);
+    return mix(curve[i], curve[i + 1], x - float(i));
+}
+"#;
+
+#[cfg(gl_compute)]
+const PARTICLE_GLSL: &str = r#"
+struct Particle {
+    vec4 position_age;
+    vec4 velocity_lifetime;
+};
+"#;
+
+const CPU_VERTEX_SHADER: &str = r#"
+layout (location = 0) in vec2 a_corner;
+layout (location = 1) in vec4 a_position_size;
+layout (location = 2) in vec4 a_color;
+uniform mat4 u_view_projection;
+uniform vec3 u_camera_right;
+uniform vec3 u_camera_up;
+out vec2 v_corner;
+out vec4 v_color;
+void main() {
+    vec3 offset = (u_camera_right * a_corner.x + u_camera_up * a_corner.y) * a_position_size.w;
+    v_corner = a_corner;
+    v_color = a_color;
+    gl_Position = u_view_projection * vec4(a_position_size.xyz + offset, 1.0);
+}
+"#;
+
+#[cfg(gl_compute)]
+const GPU_VERTEX_SHADER: &str = r#"
+layout (location = 0) in vec2 a_corner;
+layout (std430, binding = 0) readonly buffer Particles {
+    Particle particles[];
+};
+uniform mat4 u_view_projection;
+uniform vec3 u_camera_right;
+uniform vec3 u_camera_up;
+uniform float u_size_curve[CURVE_SAMPLES];
+uniform vec4 u_color_curve[CURVE_SAMPLES];
+out vec2 v_corner;
+out vec4 v_color;
+void main() {
+    Particle p = particles[gl_InstanceID];
+    if (p.position_age.w >= p.velocity_lifetime.w) {
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+    float t = p.position_age.w / p.velocity_lifetime.w;
+    float size = sample_curve(u_size_curve, t);
+    vec3 offset = (u_camera_right * a_corner.x + u_camera_up * a_corner.y) * size;
+    v_corner = a_corner;
+    v_color = sample_curve(u_color_curve, t);
+    gl_Position = u_view_projection * vec4(p.position_age.xyz + offset, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+in vec2 v_corner;
+in vec4 v_color;
+out vec4 frag_color;
+void main() {
+    float falloff = smoothstep(1.0, 0.5, length(v_corner));
+    frag_color = vec4(v_color.rgb, v_color.a * falloff);
+}
+"#;
+
+#[cfg(gl_compute)]
+const COMPUTE_SHADER: &str = r#"
+layout (local_size_x = 64) in;
+layout (std430, binding = 0) buffer Particles {
+    Particle particles[];
+};
+uniform float u_dt;
+uniform uint u_count;
+uniform uint u_spawn_start;
+uniform uint u_spawn_count;
+uniform uint u_seed;
+uniform vec3 u_origin;
+uniform vec3 u_spread;
+uniform vec3 u_velocity;
+uniform float u_velocity_randomness;
+uniform vec3 u_gravity;
+uniform float u_lifetime;
+uniform float u_speed_curve[CURVE_SAMPLES];
+
+uint hash(uint x) {
+    x ^= x >> 16;
+    x *= 0x7feb352du;
+    x ^= x >> 15;
+    x *= 0x846ca68bu;
+    x ^= x >> 16;
+    return x;
+}
+
+vec3 random_vec3(inout uint state) {
+    vec3 r;
+    for (int i = 0; i < 3; ++i) {
+        state = hash(state);
+        r[i] = float(state) / 4294967295.0 * 2.0 - 1.0;
+    }
+    return r;
+}
+
+void main() {
+    uint i = gl_GlobalInvocationID.x;
+    if (i >= u_count) {
+        return;
+    }
+    Particle p = particles[i];
+    if ((i + u_count - u_spawn_start) % u_count < u_spawn_count) {
+        uint state = hash(i ^ u_seed);
+        p.position_age = vec4(u_origin + random_vec3(state) * u_spread, 0.0);
+        p.velocity_lifetime =
+            vec4(u_velocity + random_vec3(state) * u_velocity_randomness, u_lifetime);
+    } else if (p.position_age.w < p.velocity_lifetime.w) {
+        float t = p.position_age.w / p.velocity_lifetime.w;
+        p.velocity_lifetime.xyz += u_gravity * u_dt;
+        p.position_age.xyz += p.velocity_lifetime.xyz * sample_curve(u_speed_curve, t) * u_dt;
+        p.position_age.w += u_dt;
+    }
+    particles[i] = p;
+}
+"#;
+
+/// How an emitter spawns and animates its particles.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmitterConfig {
+    /// Particles spawned per second.
+    pub rate: f32,
+    /// Lifetime of each particle in seconds.
+    pub lifetime: f32,
+    pub origin: Vec3,
+    /// Spawn positions are uniformly distributed in `origin ± spread`.
+    pub spread: Vec3,
+    pub velocity: Vec3,
+    /// Each velocity component is offset by up to this much, at random.
+    pub velocity_randomness: f32,
+    pub gravity: Vec3,
+    /// Multiplies the velocity over the normalized age.
+    pub speed_over_life: Track<f32>,
+    /// Billboard half-size in world units over the normalized age.
+    pub size_over_life: Track<f32>,
+    pub color_over_life: Track<Vec4>,
+    /// Additive blending instead of alpha blending.
+    pub additive: bool,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        EmitterConfig {
+            rate: 200.0,
+            lifetime: 2.0,
+            origin: Vec3::ZERO,
+            spread: Vec3::splat(0.1),
+            velocity: Vec3::new(0.0, 2.0, 0.0),
+            velocity_randomness: 0.5,
+            gravity: Vec3::new(0.0, -1.0, 0.0),
+            speed_over_life: constant(1.0),
+            size_over_life: constant(0.05),
+            color_over_life: Track {
+                times: vec![0.0, 1.0],
+                values: vec![Vec4::ONE, Vec4::new(1.0, 1.0, 1.0, 0.0)],
+                interpolation: Interpolation::Linear,
+            },
+            additive: false,
+        }
+    }
+}
+
+/// A track holding `value` for the whole life.
+pub fn constant<T: Keyframe>(value: T) -> Track<T> {
+    Track {
+        times: vec![0.0],
+        values: vec![value],
+        interpolation: Interpolation::Linear,
+    }
+}
+
+fn bake<T: Keyframe>(track: &Track<T>) -> [T; CURVE_SAMPLES] {
+    std::array::from_fn(|i| track.sample(i as f32 / (CURVE_SAMPLES - 1) as f32))
+}
+
+fn sample_baked<T: Keyframe>(curve: &[T; CURVE_SAMPLES], t: f32) -> T {
+    let x = t.clamp(0.0, 1.0) * (CURVE_SAMPLES - 1) as f32;
+    let i = (x as usize).min(CURVE_SAMPLES - 2);
+    T::linear(curve[i], curve[i + 1], x - i as f32)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParticleBackend {
+    Cpu,
+    #[cfg(gl_compute)]
+    Compute,
+}
+
+impl ParticleBackend {
+    /// The compute backend if the context is GL 4.3 or newer, otherwise the CPU one.
+    pub fn best_available() -> ParticleBackend {
+        #[cfg(gl_compute)]
+        if context::version() >= (4, 3) && gl::DispatchCompute::is_loaded() {
+            return ParticleBackend::Compute;
+        }
+        ParticleBackend::Cpu
+    }
+}
+
+/// Same layout as the GLSL `Particle` struct.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct Particle {
+    position: [f32; 3],
+    age: f32,
+    velocity: [f32; 3],
+    lifetime: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct Instance {
+    position_size: [f32; 4],
+    color: [f32; 4],
+}
+
+/// Small xorshift generator for CPU spawning.
+struct Rng(u32);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// A vector with components in `-1..1`.
+    fn vec3(&mut self) -> Vec3 {
+        Vec3::new(
+            self.next() as f32 / u32::MAX as f32,
+            self.next() as f32 / u32::MAX as f32,
+            self.next() as f32 / u32::MAX as f32,
+        ) * 2.0
+            - 1.0
+    }
+}
+
+pub struct ParticleSystem {
+    pub config: EmitterConfig,
+    backend: ParticleBackend,
+    capacity: usize,
+    cursor: usize,
+    accumulator: f32,
+    rng: Rng,
+    particles: Vec<Particle>,
+    live: usize,
+    program: Program,
+    #[cfg(gl_compute)]
+    compute: Option<Program>,
+    vertex_array: VertexArray,
+    _corner_buffer: Buffer,
+    /// Instances on the CPU backend, the particle pool on the compute backend.
+    buffer: Buffer,
+}
+
+impl ParticleSystem {
+    /// Creates an emitter with room for `capacity` particles, using the best available
+    /// backend. `capacity` should be at least `rate * lifetime`, or particles are
+    /// recycled before they die.
+    pub fn new(config: EmitterConfig, capacity: usize) -> Result<ParticleSystem> {
+        ParticleSystem::with_backend(config, capacity, ParticleBackend::best_available())
+    }
+
+    pub fn with_backend(
+        config: EmitterConfig,
+        capacity: usize,
+        backend: ParticleBackend,
+    ) -> Result<ParticleSystem> {
+        if capacity == 0 {
+            return Err(anyhow!("Particle capacity must be positive"));
+        }
+        #[cfg(gl_compute)]
+        if backend == ParticleBackend::Compute {
+            context::limits().check_work_groups([capacity.div_ceil(64) as u32, 1, 1])?;
+        }
+        let program = match backend {
+            ParticleBackend::Cpu => Program::from_sources(
+                &format!("#version 330 core\n{}", CPU_VERTEX_SHADER),
+                &format!("#version 330 core\n{}", FRAGMENT_SHADER),
+            )?,
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => Program::from_sources(
+                &format!(
+                    "#version 430 core\n{}\n{}\n{}",
+                    CURVES_GLSL, PARTICLE_GLSL, GPU_VERTEX_SHADER
+                ),
+                &format!("#version 430 core\n{}", FRAGMENT_SHADER),
+            )?,
+        };
+        #[cfg(gl_compute)]
+        let compute = match backend {
+            ParticleBackend::Cpu => None,
+            ParticleBackend::Compute => Some(Program::from_compute(&format!(
+                "#version 430 core\n{}\n{}\n{}",
+                CURVES_GLSL, PARTICLE_GLSL, COMPUTE_SHADER
+            ))?),
+        };
+
+        #[cfg(gl_compute)]
+        if let Some(compute) = &compute {
+            let particle = [
+                Field::new("position_age", offset_of!(Particle, position)),
+                Field::new("velocity_lifetime", offset_of!(Particle, velocity)),
+            ];
+            let fields =
+                Field::struct_array("particles", 0, std::mem::size_of::<Particle>(), &particle);
+            for program in [&program, compute] {
+                block_layout::check(
+                    program,
+                    BlockKind::ShaderStorage,
+                    "Particles",
+                    None,
+                    &fields,
+                )?;
+            }
+        }
+
+        let vertex_array = VertexArray::new()?;
+        vertex_array.bind();
+        let corner_buffer = Buffer::new()?;
+        corner_buffer.bind(gl::ARRAY_BUFFER);
+        let corners: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+        corner_buffer.data(
+            gl::ARRAY_BUFFER,
+            bytemuck::cast_slice(&corners),
+            gl::STATIC_DRAW,
+        );
+        unsafe {
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+        }
+
+        let buffer = Buffer::new()?;
+        match backend {
+            ParticleBackend::Cpu => {
+                buffer.bind(gl::ARRAY_BUFFER);
+                buffer.allocate(
+                    gl::ARRAY_BUFFER,
+                    capacity * std::mem::size_of::<Instance>(),
+                    gl::STREAM_DRAW,
+                );
+                let stride = std::mem::size_of::<Instance>() as i32;
+                unsafe {
+                    gl::VertexAttribPointer(1, 4, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+                    gl::EnableVertexAttribArray(1);
+                    gl::VertexAttribDivisor(1, 1);
+                    gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, 16 as *const _);
+                    gl::EnableVertexAttribArray(2);
+                    gl::VertexAttribDivisor(2, 1);
+                }
+            }
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => {
+                buffer.bind(gl::SHADER_STORAGE_BUFFER);
+                let zeroed = vec![Particle::default(); capacity];
+                buffer.data(
+                    gl::SHADER_STORAGE_BUFFER,
+                    bytemuck::cast_slice(&zeroed),
+                    gl::DYNAMIC_COPY,
+                );
+                buffer.unbind(gl::SHADER_STORAGE_BUFFER);
+            }
+        }
+        vertex_array.unbind();
+        corner_buffer.unbind(gl::ARRAY_BUFFER);
+
+        let particles = match backend {
+            ParticleBackend::Cpu => vec![Particle::default(); capacity],
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => Vec::new(),
+        };
+        Ok(ParticleSystem {
+            config,
+            backend,
+            capacity,
+            cursor: 0,
+            accumulator: 0.0,
+            rng: Rng(0x2545_f491),
+            particles,
+            live: 0,
+            program,
+            #[cfg(gl_compute)]
+            compute,
+            vertex_array,
+            _corner_buffer: corner_buffer,
+            buffer,
+        })
+    }
+
+    pub fn backend(&self) -> ParticleBackend {
+        self.backend
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Live particles after the last update. Only tracked by the CPU backend; the compute
+    /// backend reports the capacity.
+    pub fn live(&self) -> usize {
+        match self.backend {
+            ParticleBackend::Cpu => self.live,
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => self.capacity,
+        }
+    }
+
+    /// Spawns new particles and advances the simulation by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        self.accumulator += self.config.rate * dt;
+        let spawn = (self.accumulator as usize).min(self.capacity);
+        self.accumulator -= spawn as f32;
+        let spawn_start = self.cursor;
+        self.cursor = (self.cursor + spawn) % self.capacity;
+
+        match self.backend {
+            ParticleBackend::Cpu => self.update_cpu(dt, spawn_start, spawn),
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => self.update_gpu(dt, spawn_start, spawn),
+        }
+    }
+
+    fn update_cpu(&mut self, dt: f32, spawn_start: usize, spawn: usize) {
+        let config = &self.config;
+        let speed = bake(&config.speed_over_life);
+        let size = bake(&config.size_over_life);
+        let color = bake(&config.color_over_life);
+
+        for i in 0..spawn {
+            let particle = &mut self.particles[(spawn_start + i) % self.capacity];
+            *particle = Particle {
+                position: (config.origin + self.rng.vec3() * config.spread).to_array(),
+                age: 0.0,
+                velocity: (config.velocity + self.rng.vec3() * config.velocity_randomness)
+                    .to_array(),
+                lifetime: config.lifetime,
+            };
+        }
+
+        let mut instances = Vec::with_capacity(self.capacity);
+        for (i, particle) in self.particles.iter_mut().enumerate() {
+            let spawned = (i + self.capacity - spawn_start) % self.capacity < spawn;
+            if particle.age >= particle.lifetime {
+                continue;
+            }
+            let t = particle.age / particle.lifetime;
+            if !spawned {
+                let velocity = Vec3::from(particle.velocity) + config.gravity * dt;
+                let position =
+                    Vec3::from(particle.position) + velocity * sample_baked(&speed, t) * dt;
+                particle.velocity = velocity.to_array();
+                particle.position = position.to_array();
+                particle.age += dt;
+            }
+            instances.push(Instance {
+                position_size: Vec3::from(particle.position)
+                    .extend(sample_baked(&size, t))
+                    .to_array(),
+                color: sample_baked(&color, t).to_array(),
+            });
+        }
+        self.live = instances.len();
+
+        self.buffer.bind(gl::ARRAY_BUFFER);
+        self.buffer
+            .sub_data(gl::ARRAY_BUFFER, 0, bytemuck::cast_slice(&instances));
+        self.buffer.unbind(gl::ARRAY_BUFFER);
+    }
+
+    #[cfg(gl_compute)]
+    fn update_gpu(&mut self, dt: f32, spawn_start: usize, spawn: usize) {
+        let Some(compute) = &self.compute else {
+            return;
+        };
+        let config = &self.config;
+        compute.use_program();
+        compute.set_float("u_dt", dt);
+        let uint = |name: &str, value: u32| unsafe {
+            gl::Uniform1ui(compute.uniform_location(name), value);
+        };
+        uint("u_count", self.capacity as u32);
+        uint("u_spawn_start", spawn_start as u32);
+        uint("u_spawn_count", spawn as u32);
+        uint("u_seed", self.rng.next());
+        compute.set_vec3("u_origin", config.origin.to_array());
+        compute.set_vec3("u_spread", config.spread.to_array());
+        compute.set_vec3("u_velocity", config.velocity.to_array());
+        compute.set_float("u_velocity_randomness", config.velocity_randomness);
+        compute.set_vec3("u_gravity", config.gravity.to_array());
+        compute.set_float("u_lifetime", config.lifetime);
+        unsafe {
+            gl::Uniform1fv(
+                compute.uniform_location("u_speed_curve"),
+                CURVE_SAMPLES as i32,
+                bake(&config.speed_over_life).as_ptr(),
+            );
+        }
+
+        self.buffer
+            .bind_base(gl::SHADER_STORAGE_BUFFER, PARTICLES_BINDING);
+        unsafe {
+            gl::DispatchCompute(self.capacity.div_ceil(64) as u32, 1, 1);
+        }
+        load_store::memory_barrier(Barriers::SHADER_STORAGE);
+    }
+
+    /// Draws the particles as camera-facing billboards. Depth writes are disabled while
+    /// drawing so particles don't occlude each other.
+    pub fn draw(&self, view: Mat4, projection: Mat4) {
+        let inverse_view = view.inverse();
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &(projection * view).to_cols_array());
+        self.program
+            .set_vec3("u_camera_right", inverse_view.x_axis.truncate().to_array());
+        self.program
+            .set_vec3("u_camera_up", inverse_view.y_axis.truncate().to_array());
+
+        let instances = match self.backend {
+            ParticleBackend::Cpu => self.live,
+            #[cfg(gl_compute)]
+            ParticleBackend::Compute => {
+                unsafe {
+                    gl::Uniform1fv(
+                        self.program.uniform_location("u_size_curve"),
+                        CURVE_SAMPLES as i32,
+                        bake(&self.config.size_over_life).as_ptr(),
+                    );
+                    let color = bake(&self.config.color_over_life);
+                    gl::Uniform4fv(
+                        self.program.uniform_location("u_color_curve"),
+                        CURVE_SAMPLES as i32,
+                        color.as_ptr().cast(),
+                    );
+                }
+                self.buffer
+                    .bind_base(gl::SHADER_STORAGE_BUFFER, PARTICLES_BINDING);
+                self.capacity
+            }
+        };
+        if instances == 0 {
+            return;
+        }
+
+        unsafe {
+            gl::Enable(gl::BLEND);
+            if self.config.additive {
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+            } else {
+                gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            }
+            gl::DepthMask(gl::FALSE);
+        }
+        self.vertex_array.bind();
+        validate::draw("particles");
+        unsafe {
+            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, instances as i32);
+        }
+        self.vertex_array.unbind();
+        stats::record_draw(instances * 2);
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Resources replicated per frame in flight.
+//!
+//! The GPU runs a frame or more behind the CPU, so an object the CPU rewrites or reads
+//! every frame, such as a uniform buffer, a timer query or a readback buffer, is still in
+//! use by earlier frames when the next one wants it. Rewriting it makes the driver wait
+//! or copy; reading it back waits for the GPU to catch up. A [`PerFrame`] keeps one copy
+//! per frame in flight and hands out the one belonging to the current frame, which the
+//! GPU finished with frames ago.
+//!
+//! Slots are selected by [`frame_index`], a thread-local count of presented frames that
+//! the runners in [`crate::app`] advance once per frame, so every `PerFrame` on the
+//! thread turns together. Apps driving their own loop call [`advance_frame`] after each
+//! swap; without it every frame uses the same slot, which stays correct but waits.
+
+use std::cell::Cell;
+
+use anyhow::Result;
+
+/// Frames the CPU may run ahead of the GPU; the slots of [`PerFrame::new`].
+pub const FRAMES_IN_FLIGHT: usize = 3;
+
+thread_local! {
+    static FRAME: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Frames presented on this thread.
+pub fn frame_index() -> u64 {
+    FRAME.with(Cell::get)
+}
+
+/// Moves on to the next frame. Called by the runners after presenting each frame.
+///
+/// Also fences the objects deleted during the frame and deletes those of frames the GPU
+/// has finished; see [`crate::context::delete`].
+pub fn advance_frame() {
+    FRAME.with(|frame| frame.set(frame.get() + 1));
+    crate::context::retire_deletions();
+}
+
+/// One `T` per frame in flight.
+#[derive(Clone, Debug)]
+pub struct PerFrame<T> {
+    slots: Vec<T>,
+}
+
+impl<T> PerFrame<T> {
+    /// [`FRAMES_IN_FLIGHT`] slots created by `create`, given each slot's index.
+    pub fn new(create: impl FnMut(usize) -> T) -> PerFrame<T> {
+        PerFrame::with_frames(FRAMES_IN_FLIGHT, create)
+    }
+
+    /// `frames` slots, at least one, for resources read back with more latency.
+    pub fn with_frames(frames: usize, create: impl FnMut(usize) -> T) -> PerFrame<T> {
+        PerFrame {
+            slots: (0..frames.max(1)).map(create).collect(),
+        }
+    }
+
+    /// Like [`PerFrame::new`] for fallible constructors, e.g. of GL objects.
+    pub fn try_new(create: impl FnMut(usize) -> Result<T>) -> Result<PerFrame<T>> {
+        PerFrame::try_with_frames(FRAMES_IN_FLIGHT, create)
+    }
+
+    pub fn try_with_frames(
+        frames: usize,
+        create: impl FnMut(usize) -> Result<T>,
+    ) -> Result<PerFrame<T>> {
+        Ok(PerFrame {
+            slots: (0..frames.max(1)).map(create).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Number of slots.
+    pub fn frames(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The index of the current frame's slot.
+    pub fn index(&self) -> usize {
+        self.index_of(0)
+    }
+
+    fn index_of(&self, age: usize) -> usize {
+        let frames = self.slots.len() as u64;
+        ((frame_index() + frames - age as u64 % frames) % frames) as usize
+    }
+
+    /// The current frame's slot.
+    pub fn current(&self) -> &T {
+        &self.slots[self.index()]
+    }
+
+    pub fn current_mut(&mut self) -> &mut T {
+        let index = self.index();
+        &mut self.slots[index]
+    }
+
+    /// The slot of the frame `age` frames ago, `1` for the previous one. The slots of
+    /// [`PerFrame::frames`] frames ago and the current frame are the same.
+    pub fn previous(&self, age: usize) -> &T {
+        &self.slots[self.index_of(age)]
+    }
+
+    pub fn previous_mut(&mut self, age: usize) -> &mut T {
+        let index = self.index_of(age);
+        &mut self.slots[index]
+    }
+
+    /// The slot at `index`, e.g. one remembered from [`PerFrame::index`] in an earlier
+    /// frame.
+    pub fn slot(&self, index: usize) -> &T {
+        &self.slots[index]
+    }
+
+    pub fn slot_mut(&mut self, index: usize) -> &mut T {
+        &mut self.slots[index]
+    }
+
+    /// Every slot in index order, not frame order.
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.slots.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.slots.iter_mut()
+    }
+}
@@ -0,0 +1,252 @@
+//! Color-ID mouse picking.
+//!
+//! [`PickBuffer`] renders object IDs into an offscreen `R32UI` target. A pick request
+//! copies the pixel under the cursor into the current frame's pixel-pack buffer of a
+//! [`PerFrame`] ring; [`PickBuffer::poll`] returns the result a frame or two later, once
+//! a fence shows the copy has finished, so picking never stalls the pipeline. ID 0 is
+//! reserved for the background.
+
+use std::mem;
+
+use anyhow::Result;
+
+use crate::buffer::Buffer;
+use crate::framebuffer::{Framebuffer, Renderbuffer};
+use crate::gl;
+use crate::math::Mat4;
+use crate::mesh::Mesh;
+use crate::per_frame::PerFrame;
+use crate::scene::{NodeId, Scene};
+use crate::shader::Program;
+use crate::sync::Fence;
+use crate::texture::Texture;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+uniform mat4 u_mvp;
+void main() {
+    gl_Position = u_mvp * vec4(a_position, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform uint u_id;
+out uint o_id;
+void main() {
+    o_id = u_id;
+}
+"#;
+
+/// The result of a [`PickBuffer::request`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pick {
+    /// The requested position in window coordinates, `y` down.
+    pub x: u32,
+    pub y: u32,
+    /// The ID drawn at that position, or `None` for the background.
+    pub id: Option<u32>,
+}
+
+impl Pick {
+    /// The picked node when the IDs were drawn with [`PickBuffer::draw_scene`].
+    pub fn node(&self) -> Option<NodeId> {
+        self.id.map(|id| NodeId(id as usize))
+    }
+}
+
+struct Pending {
+    x: u32,
+    y: u32,
+    /// Orders requests across slots.
+    sequence: u64,
+    fence: Fence,
+}
+
+/// A frame's pixel-pack buffer and the request it holds, if not yet polled.
+struct Readback {
+    buffer: Buffer,
+    pending: Option<Pending>,
+}
+
+pub struct PickBuffer {
+    framebuffer: Framebuffer,
+    ids: Texture,
+    _depth: Renderbuffer,
+    program: Program,
+    readbacks: PerFrame<Readback>,
+    requests: u64,
+    view_projection: Mat4,
+    width: i32,
+    height: i32,
+}
+
+impl PickBuffer {
+    /// Creates a pick target; it should match the window's framebuffer size.
+    pub fn new(width: i32, height: i32) -> Result<PickBuffer> {
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.label("pick buffer");
+
+        let ids = Texture::new(gl::TEXTURE_2D)?;
+        ids.bind();
+        ids.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        ids.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        ids.image_2d(
+            0,
+            gl::R32UI,
+            width,
+            height,
+            gl::RED_INTEGER,
+            gl::UNSIGNED_INT,
+            None,
+        );
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &ids, 0);
+        ids.unbind();
+
+        let depth = Renderbuffer::with_storage(gl::DEPTH_COMPONENT24, width, height, 0)?;
+        framebuffer.attach_renderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &depth);
+
+        let status = framebuffer.check_status(gl::FRAMEBUFFER);
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        let program = Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?;
+        program.label("pick ids");
+
+        let readbacks = PerFrame::try_new(|_| {
+            let buffer = Buffer::new()?;
+            buffer.bind(gl::PIXEL_PACK_BUFFER);
+            buffer.allocate(
+                gl::PIXEL_PACK_BUFFER,
+                mem::size_of::<u32>(),
+                gl::STREAM_READ,
+            );
+            buffer.unbind(gl::PIXEL_PACK_BUFFER);
+            Ok(Readback {
+                buffer,
+                pending: None,
+            })
+        })?;
+
+        Ok(PickBuffer {
+            framebuffer,
+            ids,
+            _depth: depth,
+            program,
+            readbacks,
+            requests: 0,
+            view_projection: Mat4::IDENTITY,
+            width,
+            height,
+        })
+    }
+
+    /// The `R32UI` ID texture, e.g. for outlining the picked object in a shader.
+    pub fn ids(&self) -> &Texture {
+        &self.ids
+    }
+
+    /// Binds the pick target, clears it to ID 0 and prepares to draw with
+    /// `view_projection`. Call [`PickBuffer::end`] when done.
+    pub fn begin(&mut self, view_projection: Mat4) {
+        self.view_projection = view_projection;
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        let background = [0u32; 4];
+        unsafe {
+            gl::Viewport(0, 0, self.width, self.height);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::ClearBufferuiv(gl::COLOR, 0, background.as_ptr());
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+        self.program.use_program();
+    }
+
+    /// Draws `mesh` with `model` as `id`.
+    pub fn draw(&self, mesh: &Mesh, model: Mat4, id: u32) {
+        self.program
+            .set_mat4("u_mvp", &(self.view_projection * model).to_cols_array());
+        self.program.set_uint("u_id", id + 1);
+        mesh.draw();
+    }
+
+    /// Draws every drawable of `scene` with an ID that [`Pick::node`] maps back to its
+    /// node. Skinned meshes are drawn in their bind pose.
+    pub fn draw_scene(&self, scene: &Scene) {
+        for (id, node) in scene.iter() {
+            if let Some(drawable) = &node.drawable {
+                self.draw(&drawable.mesh, node.world(), id.0 as u32);
+            }
+        }
+    }
+
+    /// Restores the default framebuffer. The caller restores its own viewport.
+    pub fn end(&self) {
+        self.framebuffer.unbind(gl::FRAMEBUFFER);
+    }
+
+    /// Starts reading back the ID at window position `(x, y)`, `y` down, from what was
+    /// last drawn. Each frame holds one request; a second one in the same frame, or one
+    /// arriving when the frame's buffer still holds an unpolled pick, replaces it.
+    pub fn request(&mut self, x: u32, y: u32) -> Result<()> {
+        if x as i32 >= self.width || y as i32 >= self.height {
+            return Ok(());
+        }
+        let readback = self.readbacks.current_mut();
+        let buffer = &readback.buffer;
+        buffer.bind(gl::PIXEL_PACK_BUFFER);
+        self.framebuffer.bind(gl::READ_FRAMEBUFFER);
+        unsafe {
+            gl::ReadBuffer(gl::COLOR_ATTACHMENT0);
+            gl::ReadPixels(
+                x as i32,
+                self.height - 1 - y as i32,
+                1,
+                1,
+                gl::RED_INTEGER,
+                gl::UNSIGNED_INT,
+                std::ptr::null_mut(),
+            );
+        }
+        self.framebuffer.unbind(gl::READ_FRAMEBUFFER);
+        buffer.unbind(gl::PIXEL_PACK_BUFFER);
+        readback.pending = Some(Pending {
+            x,
+            y,
+            sequence: self.requests,
+            fence: Fence::new(),
+        });
+        self.requests += 1;
+        Ok(())
+    }
+
+    /// Returns the oldest finished pick, if any. Call once per frame.
+    pub fn poll(&mut self) -> Option<Pick> {
+        let readback = self
+            .readbacks
+            .iter_mut()
+            .filter(|readback| readback.pending.is_some())
+            .min_by_key(|readback| readback.pending.as_ref().map(|p| p.sequence))?;
+        if !readback.pending.as_ref()?.fence.is_signaled() {
+            return None;
+        }
+        let pending = readback.pending.take()?;
+        let buffer = &readback.buffer;
+        buffer.bind(gl::PIXEL_PACK_BUFFER);
+        let id = buffer
+            .map_range(
+                gl::PIXEL_PACK_BUFFER,
+                0,
+                mem::size_of::<u32>(),
+                gl::MAP_READ_BIT,
+            )
+            .map(|ptr| unsafe { ptr.cast::<u32>().read_unaligned() })
+            .unwrap_or(0);
+        buffer.unmap(gl::PIXEL_PACK_BUFFER);
+        buffer.unbind(gl::PIXEL_PACK_BUFFER);
+        Some(Pick {
+            x: pending.x,
+            y: pending.y,
+            id: (id != 0).then_some(id - 1),
+        })
+    }
+}
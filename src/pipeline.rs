@@ -0,0 +1,379 @@
+//! A renderer made of pluggable passes.
+//!
+//! A [`Pipeline`] is an ordered list of [`RenderPass`]es that it turns into a
+//! [`RenderGraph`] every frame. Passes find the textures of earlier passes by name
+//! through a [`PassBuilder`] rather than holding [`ResourceId`]s, so a pass from another
+//! crate, say water reading the scene's `"depth"`, can be put between the built-in ones
+//! with [`Pipeline::insert_after`] without the pipeline or the other passes knowing
+//! about it.
+//!
+//! [`Pipeline::standard`] starts with a [`ScenePass`] drawing into HDR `"color"` and
+//! `"depth"`, and a [`PostPass`] tonemapping `"color"` to the backbuffer. A pass that
+//! wants to change `"color"` reads it and [`PassBuilder::create`]s a new `"color"`,
+//! which passes after it then see.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::clear::PassClear;
+use crate::gl;
+use crate::graph::{PassContext, RenderGraph, ResourceId, TextureDesc, TransientPool};
+use crate::math::{Mat4, Vec3};
+use crate::postprocess::{FullscreenTriangle, Pass, Tonemap};
+use crate::viewport::Camera;
+
+/// Format of the `"color"` textures of the built-in passes.
+pub const COLOR_FORMAT: gl::types::GLenum = gl::RGBA16F;
+
+/// Format of the `"depth"` texture of [`ScenePass`].
+pub const DEPTH_FORMAT: gl::types::GLenum = gl::DEPTH_COMPONENT24;
+
+/// What every pass sees of the frame being rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// Size of the backbuffer and of the built-in passes' textures.
+    pub width: i32,
+    pub height: i32,
+    pub view: Mat4,
+    pub projection: Mat4,
+    /// The camera position, for lighting.
+    pub eye: Vec3,
+    /// Seconds since the start, for animated passes.
+    pub time: f32,
+}
+
+impl Frame {
+    /// A `width` × `height` frame seen through `camera` with
+    /// [`Camera::projection_for_context`].
+    pub fn new(width: i32, height: i32, camera: &Camera, time: f32) -> Frame {
+        let aspect = width.max(1) as f32 / height.max(1) as f32;
+        Frame {
+            width,
+            height,
+            view: camera.view(),
+            projection: camera.projection_for_context(aspect),
+            eye: camera.eye,
+            time,
+        }
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection * self.view
+    }
+}
+
+/// A stage of a [`Pipeline`].
+pub trait RenderPass {
+    /// Names the pass in traces and [`Pipeline::insert_before`]; unique in a pipeline.
+    fn name(&self) -> &str;
+
+    /// Called before the first frame and whenever the frame size changes, to recreate
+    /// size-dependent objects the pass keeps across frames.
+    fn resize(&mut self, _width: i32, _height: i32) -> Result<()> {
+        Ok(())
+    }
+
+    /// Declares what the pass reads and writes this frame. Called for every pass, in
+    /// order, before any of them executes.
+    fn setup(&mut self, builder: &mut PassBuilder) -> Result<()>;
+
+    /// Renders the pass with the framebuffer of its writes bound, the viewport covering
+    /// it and its clears done. Not called for passes whose writes nothing uses.
+    fn execute(&mut self, context: &PassContext, frame: &Frame);
+}
+
+/// Declares one pass's reads and writes, by the names of the frame's textures.
+pub struct PassBuilder<'g, 'a> {
+    pass: &'g str,
+    graph: &'g mut RenderGraph<'a>,
+    names: &'g mut HashMap<String, ResourceId>,
+    frame: &'g Frame,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+    clear: PassClear,
+}
+
+impl PassBuilder<'_, '_> {
+    pub fn frame(&self) -> &Frame {
+        self.frame
+    }
+
+    /// The texture called `name` by an earlier pass.
+    pub fn resource(&self, name: &str) -> Result<ResourceId> {
+        self.names.get(name).copied().ok_or_else(|| {
+            anyhow!(
+                "Pass {} uses {}, which no earlier pass creates",
+                self.pass,
+                name
+            )
+        })
+    }
+
+    /// Samples the texture called `name`.
+    pub fn read(&mut self, name: &str) -> Result<ResourceId> {
+        let resource = self.resource(name)?;
+        self.reads.push(resource);
+        Ok(resource)
+    }
+
+    /// Renders into the texture called `name`, after the earlier passes writing it.
+    /// `"backbuffer"` is the window's default framebuffer, which can't be written
+    /// together with textures.
+    pub fn write(&mut self, name: &str) -> Result<ResourceId> {
+        let resource = self.resource(name)?;
+        self.writes.push(resource);
+        Ok(resource)
+    }
+
+    /// Creates a texture of the frame's lifetime and renders into it. Later passes
+    /// asking for `name` get it instead of any earlier texture of that name.
+    pub fn create(&mut self, name: &str, desc: TextureDesc) -> ResourceId {
+        let resource = self.graph.create_texture(name, desc);
+        self.names.insert(name.to_owned(), resource);
+        self.writes.push(resource);
+        resource
+    }
+
+    /// [`PassBuilder::create`] at the frame size.
+    pub fn create_frame_sized(
+        &mut self,
+        name: &str,
+        internal_format: gl::types::GLenum,
+    ) -> ResourceId {
+        let desc = TextureDesc::new(self.frame.width, self.frame.height, internal_format);
+        self.create(name, desc)
+    }
+
+    /// Starts the pass with `clear`; color entries follow the order of the color
+    /// writes.
+    pub fn clear(&mut self, clear: PassClear) {
+        self.clear = clear;
+    }
+}
+
+/// Passes run in order as a render graph, with the textures they share.
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn RenderPass>>,
+    pool: TransientPool,
+    /// The size the passes were last resized to.
+    size: Option<(i32, i32)>,
+}
+
+impl Pipeline {
+    /// A pipeline without passes.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// A [`ScenePass`] calling `draw`, then an ACES [`PostPass`] to the backbuffer.
+    pub fn standard(draw: impl FnMut(&Frame) + 'static) -> Result<Pipeline> {
+        let mut pipeline = Pipeline::new();
+        pipeline.push(ScenePass::new(draw))?;
+        pipeline.push(PostPass::new(Pass::tonemap(Tonemap::Aces, 1.0)?)?.to_backbuffer())?;
+        Ok(pipeline)
+    }
+
+    /// Adds `pass` after every other pass.
+    pub fn push(&mut self, pass: impl RenderPass + 'static) -> Result<()> {
+        let index = self.passes.len();
+        self.insert(index, Box::new(pass))
+    }
+
+    /// Adds `pass` just before the pass named `anchor`.
+    pub fn insert_before(&mut self, anchor: &str, pass: impl RenderPass + 'static) -> Result<()> {
+        let index = self.index_of(anchor)?;
+        self.insert(index, Box::new(pass))
+    }
+
+    /// Adds `pass` just after the pass named `anchor`.
+    pub fn insert_after(&mut self, anchor: &str, pass: impl RenderPass + 'static) -> Result<()> {
+        let index = self.index_of(anchor)?;
+        self.insert(index + 1, Box::new(pass))
+    }
+
+    fn insert(&mut self, index: usize, mut pass: Box<dyn RenderPass>) -> Result<()> {
+        if self.index_of(pass.name()).is_ok() {
+            return Err(anyhow!("Pipeline already has a pass named {}", pass.name()));
+        }
+        if let Some((width, height)) = self.size {
+            pass.resize(width, height)?;
+        }
+        self.passes.insert(index, pass);
+        Ok(())
+    }
+
+    /// Takes the pass named `name` out of the pipeline.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn RenderPass>> {
+        let index = self.index_of(name).ok()?;
+        Some(self.passes.remove(index))
+    }
+
+    fn index_of(&self, name: &str) -> Result<usize> {
+        self.passes
+            .iter()
+            .position(|pass| pass.name() == name)
+            .ok_or_else(|| anyhow!("Pipeline has no pass named {}", name))
+    }
+
+    /// The pass names in order.
+    pub fn pass_names(&self) -> Vec<&str> {
+        self.passes.iter().map(|pass| pass.name()).collect()
+    }
+
+    /// Renders `frame` through the passes, resizing them first if the frame size
+    /// changed.
+    pub fn render(&mut self, frame: &Frame) -> Result<()> {
+        let size = (frame.width, frame.height);
+        if self.size != Some(size) {
+            for pass in &mut self.passes {
+                pass.resize(frame.width, frame.height)?;
+            }
+            self.size = Some(size);
+        }
+
+        let mut graph = RenderGraph::new();
+        let mut names = HashMap::new();
+        names.insert(
+            "backbuffer".to_owned(),
+            graph.backbuffer(frame.width, frame.height),
+        );
+        let mut declared = Vec::with_capacity(self.passes.len());
+        for pass in &mut self.passes {
+            let name = pass.name().to_owned();
+            let mut builder = PassBuilder {
+                pass: &name,
+                graph: &mut graph,
+                names: &mut names,
+                frame,
+                reads: Vec::new(),
+                writes: Vec::new(),
+                clear: PassClear::new(),
+            };
+            pass.setup(&mut builder)?;
+            let PassBuilder {
+                reads,
+                writes,
+                clear,
+                ..
+            } = builder;
+            declared.push((name, reads, writes, clear));
+        }
+        for (pass, (name, reads, writes, clear)) in self.passes.iter_mut().zip(declared) {
+            graph.add_cleared_pass(&name, &reads, &writes, clear, move |context| {
+                pass.execute(context, frame)
+            });
+        }
+        graph.execute(&mut self.pool)
+    }
+}
+
+/// Draws the scene with a closure into a new [`COLOR_FORMAT`] `"color"` and a
+/// [`DEPTH_FORMAT`] `"depth"`, cleared first, with depth testing on.
+pub struct ScenePass {
+    draw: Box<dyn FnMut(&Frame)>,
+    pub clear_color: [f32; 4],
+}
+
+impl ScenePass {
+    pub fn new(draw: impl FnMut(&Frame) + 'static) -> ScenePass {
+        ScenePass {
+            draw: Box::new(draw),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl RenderPass for ScenePass {
+    fn name(&self) -> &str {
+        "scene"
+    }
+
+    fn setup(&mut self, builder: &mut PassBuilder) -> Result<()> {
+        builder.create_frame_sized("color", COLOR_FORMAT);
+        builder.create_frame_sized("depth", DEPTH_FORMAT);
+        builder.clear(PassClear::standard(self.clear_color));
+        Ok(())
+    }
+
+    fn execute(&mut self, _context: &PassContext, frame: &Frame) {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        (self.draw)(frame);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+    }
+}
+
+/// A [`crate::postprocess::Pass`] from `"color"` into a new `"color"`, or into the
+/// backbuffer if it is the last pass. Named after the pass; disabled passes are
+/// skipped.
+pub struct PostPass {
+    pass: Pass,
+    triangle: FullscreenTriangle,
+    to_backbuffer: bool,
+    input: Option<ResourceId>,
+}
+
+impl PostPass {
+    pub fn new(pass: Pass) -> Result<PostPass> {
+        Ok(PostPass {
+            pass,
+            triangle: FullscreenTriangle::new()?,
+            to_backbuffer: false,
+            input: None,
+        })
+    }
+
+    /// Writes the window's default framebuffer.
+    pub fn to_backbuffer(mut self) -> PostPass {
+        self.to_backbuffer = true;
+        self
+    }
+
+    pub fn pass_mut(&mut self) -> &mut Pass {
+        &mut self.pass
+    }
+}
+
+impl RenderPass for PostPass {
+    fn name(&self) -> &str {
+        self.pass.name()
+    }
+
+    fn setup(&mut self, builder: &mut PassBuilder) -> Result<()> {
+        self.input = None;
+        if !self.pass.enabled {
+            return Ok(());
+        }
+        self.input = Some(builder.read("color")?);
+        if self.to_backbuffer {
+            builder.write("backbuffer")?;
+        } else {
+            builder.create_frame_sized("color", COLOR_FORMAT);
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, context: &PassContext, _frame: &Frame) {
+        let Some(input) = self.input else {
+            return;
+        };
+        let (width, height) = context.size();
+        let program = self.pass.program();
+        program.use_program();
+        context.texture(input).bind_unit(0);
+        program.set_int("u_input", 0);
+        program.set_vec2(
+            "u_texel_size",
+            [1.0 / width.max(1) as f32, 1.0 / height.max(1) as f32],
+        );
+        program.set_int("u_auto_exposure", 0);
+        program.set_int("u_dither", 0);
+        self.pass.upload_uniforms();
+        self.triangle.draw();
+    }
+}
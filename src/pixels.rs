@@ -0,0 +1,168 @@
+//! CPU-side pixel format conversions.
+//!
+//! The loaders, [`crate::framebuffer::read_pixels`] and the screenshot and capture paths
+//! all convert through these, so they agree on rounding and on the sRGB curve. Every
+//! function works on whole slices in fixed-size chunks without branches in the inner
+//! loop, which the compiler turns into SIMD code; sRGB decoding goes through a table.
+
+use std::sync::OnceLock;
+
+/// Expands tightly packed RGB to RGBA with opaque alpha.
+pub fn rgb_to_rgba(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks_exact(3)
+        .flat_map(|p| [p[0], p[1], p[2], 255])
+        .collect()
+}
+
+/// Drops the alpha channel of tightly packed RGBA.
+pub fn rgba_to_rgb(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect()
+}
+
+/// Expands grayscale to opaque RGBA.
+pub fn gray_to_rgba(gray: &[u8]) -> Vec<u8> {
+    gray.iter().flat_map(|&p| [p, p, p, 255]).collect()
+}
+
+/// Expands grayscale with alpha to RGBA.
+pub fn gray_alpha_to_rgba(gray_alpha: &[u8]) -> Vec<u8> {
+    gray_alpha
+        .chunks_exact(2)
+        .flat_map(|p| [p[0], p[0], p[0], p[1]])
+        .collect()
+}
+
+/// Maps an 8-bit channel to `0.0..=1.0`.
+pub fn unorm8_to_f32(value: u8) -> f32 {
+    value as f32 * (1.0 / 255.0)
+}
+
+/// Maps `0.0..=1.0` to an 8-bit channel, rounding to nearest and clamping outside values.
+pub fn f32_to_unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8
+}
+
+/// [`unorm8_to_f32`] over a slice.
+pub fn bytes_to_floats(bytes: &[u8]) -> Vec<f32> {
+    bytes.iter().map(|&b| unorm8_to_f32(b)).collect()
+}
+
+/// [`f32_to_unorm8`] over a slice.
+pub fn floats_to_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().map(|&v| f32_to_unorm8(v)).collect()
+}
+
+/// The sRGB transfer function's inverse: an encoded value in `0.0..=1.0` to linear.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The sRGB transfer function: a linear value in `0.0..=1.0` to its encoding. Matches
+/// `linear_to_srgb` in the [`crate::postprocess`] shaders.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decodes an sRGB-encoded 8-bit channel to linear, by table.
+pub fn srgb8_to_linear(value: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(unorm8_to_f32(i as u8))))
+        [value as usize]
+}
+
+/// Encodes a linear value as an 8-bit sRGB channel.
+pub fn linear_to_srgb8(value: f32) -> u8 {
+    f32_to_unorm8(linear_to_srgb(value.clamp(0.0, 1.0)))
+}
+
+/// Decodes sRGB `RGBA8` to linear floats; alpha is linear already and only rescaled.
+pub fn srgba8_to_linear(rgba: &[u8]) -> Vec<[f32; 4]> {
+    rgba.chunks_exact(4)
+        .map(|p| {
+            [
+                srgb8_to_linear(p[0]),
+                srgb8_to_linear(p[1]),
+                srgb8_to_linear(p[2]),
+                unorm8_to_f32(p[3]),
+            ]
+        })
+        .collect()
+}
+
+/// Encodes linear RGBA floats as sRGB `RGBA8`; alpha is only rescaled.
+pub fn linear_to_srgba8(rgba: &[[f32; 4]]) -> Vec<u8> {
+    rgba.iter()
+        .flat_map(|&[r, g, b, a]| {
+            [
+                linear_to_srgb8(r),
+                linear_to_srgb8(g),
+                linear_to_srgb8(b),
+                f32_to_unorm8(a),
+            ]
+        })
+        .collect()
+}
+
+/// Reverses the order of the rows of `row_len` elements each, converting between GL's
+/// bottom-up rows and top-down ones. Works for bytes as well as whole pixels.
+pub fn flip_rows<T>(pixels: &mut [T], row_len: usize) {
+    if row_len == 0 {
+        return;
+    }
+    let rows = pixels.len() / row_len;
+    for y in 0..rows / 2 {
+        let (top, bottom) = pixels.split_at_mut((rows - 1 - y) * row_len);
+        top[y * row_len..(y + 1) * row_len].swap_with_slice(&mut bottom[..row_len]);
+    }
+}
+
+/// `value * alpha / 255`, rounded to nearest, exactly and without a division.
+fn scale8(value: u8, alpha: u8) -> u8 {
+    let x = value as u32 * alpha as u32 + 128;
+    ((x + (x >> 8)) >> 8) as u8
+}
+
+/// Multiplies the color channels of `RGBA8` pixels by their alpha in place, as
+/// `GL_ONE, GL_ONE_MINUS_SRC_ALPHA` blending and filtering without fringes want.
+pub fn premultiply(rgba: &mut [u8]) {
+    for p in rgba.chunks_exact_mut(4) {
+        let alpha = p[3];
+        p[0] = scale8(p[0], alpha);
+        p[1] = scale8(p[1], alpha);
+        p[2] = scale8(p[2], alpha);
+    }
+}
+
+/// Undoes [`premultiply`] as far as the precision left allows; fully transparent pixels
+/// become black.
+pub fn unpremultiply(rgba: &mut [u8]) {
+    for p in rgba.chunks_exact_mut(4) {
+        let alpha = p[3] as u32;
+        let unscale = |c: u8| {
+            let scaled = (c as u32 * 255 + alpha / 2) / alpha.max(1);
+            scaled.min(255) as u8
+        };
+        p[0] = unscale(p[0]);
+        p[1] = unscale(p[1]);
+        p[2] = unscale(p[2]);
+    }
+}
+
+/// [`premultiply`] for linear float pixels.
+pub fn premultiply_f32(rgba: &mut [[f32; 4]]) {
+    for [r, g, b, a] in rgba {
+        *r *= *a;
+        *g *= *a;
+        *b *= *a;
+    }
+}
@@ -0,0 +1,202 @@
+//! Mirrors and portals: views of the scene through a planar opening.
+//!
+//! Openings are drawn before the scene, each in three steps. [`write_mask`] marks the
+//! pixels the opening's shape covers in the stencil buffer; [`test_mask`] then limits
+//! the scene drawn from the opening's virtual camera ([`Mirror::view`],
+//! [`Portal::view`]) to those pixels, with [`oblique_projection`] moving the near plane
+//! onto the opening so nothing behind it pokes through. After clearing depth,
+//! [`write_depth_only`] draws the shapes into depth so the scene drawn last, after
+//! [`end_mask`], is hidden behind them. Openings seen through other openings are not
+//! drawn.
+//!
+//! The default framebuffer is not guaranteed a stencil buffer, so render into a
+//! [`crate::framebuffer::RenderTarget::with_stencil`] and clear its stencil to 0.
+
+use crate::depth;
+use crate::gl;
+use crate::math::{Mat4, Vec3, Vec4};
+
+/// A plane through `point` facing `normal`, as `(n, d)` with `n · p + d = 0` on it.
+pub fn plane(point: Vec3, normal: Vec3) -> Vec4 {
+    let normal = normal.normalize();
+    normal.extend(-normal.dot(point))
+}
+
+/// The matrix reflecting points across `plane`, as returned by [`plane`].
+pub fn reflection(plane: Vec4) -> Mat4 {
+    let (n, d) = (plane.truncate(), plane.w);
+    Mat4::from_cols(
+        (Vec3::X - 2.0 * n.x * n).extend(0.0),
+        (Vec3::Y - 2.0 * n.y * n).extend(0.0),
+        (Vec3::Z - 2.0 * n.z * n).extend(0.0),
+        (-2.0 * d * n).extend(1.0),
+    )
+}
+
+/// `projection` with its near plane replaced by `plane`, a world-space plane facing
+/// away from the camera at `view`, so geometry behind it is clipped without a
+/// `gl_ClipDistance` in every shader (Lengyel's oblique frustum). The far plane tilts
+/// to meet the old frustum's far corner, and depth precision suffers the more obliquely
+/// `plane` cuts the frustum.
+///
+/// `projection` must follow the depth convention of the context, as
+/// [`crate::viewport::Camera::projection_for_context`] does.
+pub fn oblique_projection(projection: Mat4, view: Mat4, plane: Vec4) -> Mat4 {
+    let clip_plane = view.inverse().transpose() * plane;
+    let reversed_z = depth::reversed_z();
+    let far = if reversed_z { 0.0 } else { 1.0 };
+    // The far frustum corner on the plane's side, which stays on the far plane.
+    let corner =
+        projection.inverse() * Vec4::new(clip_plane.x.signum(), clip_plane.y.signum(), far, 1.0);
+    let w_row = projection.row(3);
+    let scale = w_row.dot(corner) / clip_plane.dot(corner);
+    let z_row = if reversed_z {
+        w_row - clip_plane * scale
+    } else {
+        clip_plane * (2.0 * scale) - w_row
+    };
+    let mut rows = projection.transpose();
+    rows.z_axis = z_row;
+    rows.transpose()
+}
+
+/// A planar mirror reflecting what is in front of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mirror {
+    /// The mirror's plane, facing the side it reflects.
+    pub plane: Vec4,
+}
+
+impl Mirror {
+    pub fn new(point: Vec3, normal: Vec3) -> Mirror {
+        Mirror {
+            plane: plane(point, normal),
+        }
+    }
+
+    /// Whether a camera at `eye` sees the reflecting side.
+    pub fn faces(&self, eye: Vec3) -> bool {
+        self.plane.dot(eye.extend(1.0)) > 0.0
+    }
+
+    /// The view of the mirror image for the camera at `view`. Reflection flips the
+    /// winding of triangles; draw with [`set_reflected`].
+    pub fn view(&self, view: Mat4) -> Mat4 {
+        view * reflection(self.plane)
+    }
+
+    /// Where a camera at `eye` appears to be in the mirror image, for lighting.
+    pub fn eye(&self, eye: Vec3) -> Vec3 {
+        reflection(self.plane).transform_point3(eye)
+    }
+
+    /// `projection` clipped at the mirror for [`Mirror::view`].
+    pub fn projection(&self, view: Mat4, projection: Mat4) -> Mat4 {
+        oblique_projection(projection, self.view(view), self.plane)
+    }
+}
+
+/// A pair of openings: looking into `entrance` shows the scene in front of `exit`.
+/// Each opening lies in the XY plane of its transform and faces +Z; what enters the
+/// entrance going towards -Z comes out of the exit going towards +Z.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Portal {
+    pub entrance: Mat4,
+    pub exit: Mat4,
+}
+
+impl Portal {
+    pub fn new(entrance: Mat4, exit: Mat4) -> Portal {
+        Portal { entrance, exit }
+    }
+
+    /// The same pair seen from the other side: the exit leads back to the entrance.
+    pub fn reversed(&self) -> Portal {
+        Portal::new(self.exit, self.entrance)
+    }
+
+    /// Whether a camera at `eye` is in front of the entrance.
+    pub fn faces(&self, eye: Vec3) -> bool {
+        self.entrance.inverse().transform_point3(eye).z > 0.0
+    }
+
+    /// Maps points in front of the entrance to where they come out of the exit.
+    pub fn transform(&self) -> Mat4 {
+        self.exit * Mat4::from_rotation_y(std::f32::consts::PI) * self.entrance.inverse()
+    }
+
+    /// The view through the entrance for the camera at `view`.
+    pub fn view(&self, view: Mat4) -> Mat4 {
+        view * self.transform().inverse()
+    }
+
+    /// Where a camera at `eye` appears to be on the exit's side, for lighting.
+    pub fn eye(&self, eye: Vec3) -> Vec3 {
+        self.transform().transform_point3(eye)
+    }
+
+    /// The exit's plane, facing the scene it shows.
+    pub fn exit_plane(&self) -> Vec4 {
+        plane(
+            self.exit.transform_point3(Vec3::ZERO),
+            self.exit.transform_vector3(Vec3::Z),
+        )
+    }
+
+    /// `projection` clipped at the exit for [`Portal::view`].
+    pub fn projection(&self, view: Mat4, projection: Mat4) -> Mat4 {
+        oblique_projection(projection, self.view(view), self.exit_plane())
+    }
+}
+
+/// Marks the pixels the next draws cover with `reference` in the stencil buffer,
+/// without writing color or depth.
+pub fn write_mask(reference: u8) {
+    unsafe {
+        gl::Enable(gl::STENCIL_TEST);
+        gl::StencilFunc(gl::ALWAYS, reference as i32, 0xff);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+        gl::StencilMask(0xff);
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        gl::DepthMask(gl::FALSE);
+    }
+}
+
+/// Limits the next draws to pixels marked with `reference`, writing color and depth
+/// but leaving the stencil buffer alone.
+pub fn test_mask(reference: u8) {
+    unsafe {
+        gl::Enable(gl::STENCIL_TEST);
+        gl::StencilFunc(gl::EQUAL, reference as i32, 0xff);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+        gl::StencilMask(0);
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        gl::DepthMask(gl::TRUE);
+    }
+}
+
+/// Makes the next draws write depth only, everywhere, e.g. to seal an opening.
+pub fn write_depth_only() {
+    unsafe {
+        gl::Disable(gl::STENCIL_TEST);
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        gl::DepthMask(gl::TRUE);
+    }
+}
+
+/// Disables the stencil test and restores color, depth and stencil writes.
+pub fn end_mask() {
+    unsafe {
+        gl::Disable(gl::STENCIL_TEST);
+        gl::StencilMask(0xff);
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        gl::DepthMask(gl::TRUE);
+    }
+}
+
+/// Swaps which winding faces front, for drawing a mirror image with face culling.
+pub fn set_reflected(reflected: bool) {
+    unsafe {
+        gl::FrontFace(if reflected { gl::CW } else { gl::CCW });
+    }
+}
@@ -0,0 +1,439 @@
+//! Render-to-texture post-processing.
+//!
+//! The scene is rendered into an offscreen target, then each enabled [`Pass`] reads the
+//! previous result and writes into the other target of a ping-pong pair. The final image
+//! is blitted to the default framebuffer, or copied there with a [`Blitter`] on ES when
+//! the targets hold floats.
+
+use anyhow::Result;
+
+use crate::blit::Blitter;
+use crate::buffer::VertexArray;
+use crate::context;
+#[cfg(gl_compute)]
+use crate::exposure::AutoExposure;
+use crate::framebuffer::{Framebuffer, RenderTarget};
+use crate::gl;
+use crate::noise;
+use crate::shader::{Program, Uniform};
+use crate::ssao::Ssao;
+use crate::stats;
+use crate::texture::Texture;
+use crate::validate;
+use crate::viewport::Viewport;
+
+/// Vertex shader emitting a single triangle that covers the viewport, driven by `gl_VertexID`.
+pub const FULLSCREEN_VERTEX_SHADER: &str = r#"#version 330 core
+out vec2 uv;
+void main() {
+    uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+/// Draws a vertexless fullscreen triangle using [`FULLSCREEN_VERTEX_SHADER`]. See
+/// [`Blitter`] for one that also copies textures.
+pub struct FullscreenTriangle {
+    vertex_array: VertexArray,
+}
+
+impl FullscreenTriangle {
+    pub fn new() -> Result<FullscreenTriangle> {
+        Ok(FullscreenTriangle {
+            vertex_array: VertexArray::new()?,
+        })
+    }
+
+    /// Draws with whatever program is currently in use.
+    pub fn draw(&self) {
+        self.vertex_array.bind();
+        validate::draw("a fullscreen triangle");
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, 3);
+        }
+        self.vertex_array.unbind();
+        stats::record_draw(1);
+    }
+}
+
+/// A single fragment pass.
+///
+/// The fragment shader receives `in vec2 uv`, the previous result as
+/// `uniform sampler2D u_input` and the size of one texel as `uniform vec2 u_texel_size`,
+/// and writes to its first output. Values set with [`Pass::set_uniform`] are uploaded
+/// every time the pass runs.
+pub struct Pass {
+    name: String,
+    program: Program,
+    uniforms: Vec<(String, Uniform)>,
+    pub enabled: bool,
+}
+
+impl Pass {
+    pub fn new(name: &str, fragment: &str) -> Result<Pass> {
+        Ok(Pass {
+            name: name.to_owned(),
+            program: Blitter::program(fragment)?,
+            uniforms: Vec::new(),
+            enabled: true,
+        })
+    }
+
+    pub fn grayscale() -> Result<Pass> {
+        Pass::new("grayscale", GRAYSCALE_SHADER)
+    }
+
+    pub fn vignette() -> Result<Pass> {
+        Pass::new("vignette", VIGNETTE_SHADER)
+    }
+
+    pub fn fxaa() -> Result<Pass> {
+        Pass::new("fxaa", FXAA_SHADER)
+    }
+
+    /// Maps HDR input to display range with `operator`, scaling by `exposure` first.
+    /// The output is sRGB-encoded, so this should be the last pass that does color math.
+    /// With [`PostProcess::set_auto_exposure`], `exposure` scales the measured exposure.
+    pub fn tonemap(operator: Tonemap, exposure: f32) -> Result<Pass> {
+        let mut pass = Pass::new("tonemap", TONEMAP_SHADER)?;
+        pass.set_uniform("u_operator", Uniform::Int(operator as i32));
+        pass.set_uniform("u_exposure", Uniform::Float(exposure));
+        Ok(pass)
+    }
+
+    pub fn set_uniform(&mut self, name: &str, value: Uniform) {
+        match self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.uniforms.push((name.to_owned(), value)),
+        }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&Uniform> {
+        self.uniforms
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The pass program, for setting additional uniforms. Bind it before setting values.
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Uploads the values of [`Pass::set_uniform`] to the program, which must be in use.
+    pub(crate) fn upload_uniforms(&self) {
+        for (name, value) in &self.uniforms {
+            self.program.set_uniform(name, value);
+        }
+    }
+}
+
+/// Tonemapping operators understood by [`Pass::tonemap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemap {
+    Reinhard = 0,
+    Aces = 1,
+}
+
+pub struct PostProcess {
+    targets: [RenderTarget; 2],
+    internal_format: gl::types::GLenum,
+    passes: Vec<Pass>,
+    blitter: Blitter,
+    ssao: Option<Ssao>,
+    #[cfg(gl_compute)]
+    auto_exposure: Option<AutoExposure>,
+    dither: Option<Texture>,
+}
+
+/// Side of the blue noise tile [`PostProcess::set_dither`] repeats over the screen.
+const DITHER_NOISE_SIZE: u32 = 64;
+
+impl PostProcess {
+    pub fn new(width: i32, height: i32) -> Result<PostProcess> {
+        PostProcess::with_format(width, height, gl::RGBA8)
+    }
+
+    /// Creates the chain with `RGBA16F` targets so the scene can hold values above 1.0.
+    pub fn hdr(width: i32, height: i32) -> Result<PostProcess> {
+        PostProcess::with_format(width, height, gl::RGBA16F)
+    }
+
+    /// Creates the chain with offscreen targets of the given color format.
+    pub fn with_format(
+        width: i32,
+        height: i32,
+        internal_format: gl::types::GLenum,
+    ) -> Result<PostProcess> {
+        Ok(PostProcess {
+            targets: Self::create_targets(width, height, internal_format)?,
+            internal_format,
+            passes: Vec::new(),
+            blitter: Blitter::new()?,
+            ssao: None,
+            #[cfg(gl_compute)]
+            auto_exposure: None,
+            dither: None,
+        })
+    }
+
+    fn create_targets(
+        width: i32,
+        height: i32,
+        internal_format: gl::types::GLenum,
+    ) -> Result<[RenderTarget; 2]> {
+        Ok([
+            RenderTarget::new(width, height, internal_format, true)?,
+            RenderTarget::new(width, height, internal_format, false)?,
+        ])
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<()> {
+        self.targets = Self::create_targets(width, height, self.internal_format)?;
+        if let Some(ssao) = &mut self.ssao {
+            ssao.resize(width, height)?;
+        }
+        Ok(())
+    }
+
+    pub fn push(&mut self, pass: Pass) {
+        self.passes.push(pass);
+    }
+
+    pub fn passes(&self) -> &[Pass] {
+        &self.passes
+    }
+
+    pub fn passes_mut(&mut self) -> &mut [Pass] {
+        &mut self.passes
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut Pass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Darkens the scene by ambient occlusion computed from its depth before the passes
+    /// run. Keep the camera current with [`Ssao::set_camera`] through
+    /// [`PostProcess::ssao_mut`].
+    pub fn set_ssao(&mut self, ssao: Option<Ssao>) {
+        self.ssao = ssao;
+    }
+
+    pub fn ssao_mut(&mut self) -> Option<&mut Ssao> {
+        self.ssao.as_mut()
+    }
+
+    /// Measures the scene's luminance before the passes run and exposes the tonemap
+    /// pass for it, treating its `u_exposure` as compensation. Needs an HDR chain.
+    #[cfg(gl_compute)]
+    pub fn set_auto_exposure(&mut self, auto_exposure: Option<AutoExposure>) {
+        self.auto_exposure = auto_exposure;
+    }
+
+    #[cfg(gl_compute)]
+    pub fn auto_exposure_mut(&mut self) -> Option<&mut AutoExposure> {
+        self.auto_exposure.as_mut()
+    }
+
+    /// Adds blue noise of up to half an 8-bit step to the tonemap pass's output, hiding
+    /// the banding of smooth gradients such as skies and fog. Off by default.
+    pub fn set_dither(&mut self, enabled: bool) -> Result<()> {
+        self.dither = match (enabled, self.dither.take()) {
+            (false, _) => None,
+            (true, Some(noise)) => Some(noise),
+            (true, None) => Some(noise::blue_noise_texture(DITHER_NOISE_SIZE)?),
+        };
+        Ok(())
+    }
+
+    /// Binds the scene target. Render the scene after calling this.
+    pub fn begin(&self) {
+        self.targets[0].bind();
+    }
+
+    /// Runs the enabled passes and blits the result to the default framebuffer.
+    pub fn end(&self) {
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+
+        let mut source = 0;
+        if let (Some(ssao), Some(depth)) = (&self.ssao, &self.targets[0].depth) {
+            ssao.compute(depth, None);
+            self.targets[1].bind();
+            ssao.apply(&self.targets[0].color);
+            source = 1;
+        }
+        #[cfg(gl_compute)]
+        if let Some(exposure) = &self.auto_exposure {
+            let scene = &self.targets[source];
+            if let Err(error) = exposure.update(&scene.color, scene.width(), scene.height()) {
+                tracing::error!("Auto exposure failed: {:#}", error);
+            }
+        }
+        for pass in self.passes.iter().filter(|pass| pass.enabled) {
+            let _span = tracing::debug_span!("pass", name = %pass.name).entered();
+            let input = &self.targets[source];
+            let output = &self.targets[1 - source];
+            output.bind();
+            pass.program.use_program();
+            input.color.bind_unit(0);
+            pass.program.set_int("u_input", 0);
+            pass.program.set_vec2(
+                "u_texel_size",
+                [1.0 / input.width() as f32, 1.0 / input.height() as f32],
+            );
+            #[cfg(gl_compute)]
+            match &self.auto_exposure {
+                Some(exposure) => exposure.apply(&pass.program, 1),
+                None => pass.program.set_int("u_auto_exposure", 0),
+            }
+            #[cfg(not(gl_compute))]
+            pass.program.set_int("u_auto_exposure", 0);
+            match &self.dither {
+                Some(noise) => {
+                    noise.bind_unit(2);
+                    pass.program.set_int("u_dither_noise", 2);
+                    pass.program.set_int("u_dither", 1);
+                }
+                None => pass.program.set_int("u_dither", 0),
+            }
+            pass.upload_uniforms();
+            self.blitter.draw();
+            source = 1 - source;
+        }
+
+        let result = &self.targets[source];
+        let (width, height) = (result.width(), result.height());
+        if context::info().es && self.internal_format != gl::RGBA8 {
+            // ES only blits between matching formats, so float targets are drawn instead.
+            let viewport = Viewport::new(0, 0, width, height);
+            self.blitter.copy_to(&result.color, None, viewport);
+            return;
+        }
+        result.framebuffer.bind(gl::READ_FRAMEBUFFER);
+        Framebuffer::bind_default(gl::DRAW_FRAMEBUFFER);
+        unsafe {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+    }
+}
+
+const GRAYSCALE_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+
+void main() {
+    vec4 color = texture(u_input, uv);
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    frag_color = vec4(vec3(luma), color.a);
+}
+"#;
+
+const VIGNETTE_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+
+void main() {
+    vec4 color = texture(u_input, uv);
+    float d = distance(uv, vec2(0.5));
+    color.rgb *= smoothstep(0.8, 0.3, d);
+    frag_color = color;
+}
+"#;
+
+const FXAA_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+uniform vec2 u_texel_size;
+
+const float FXAA_SPAN_MAX = 8.0;
+const float FXAA_REDUCE_MUL = 1.0 / 8.0;
+const float FXAA_REDUCE_MIN = 1.0 / 128.0;
+
+void main() {
+    vec3 luma = vec3(0.299, 0.587, 0.114);
+    float nw = dot(texture(u_input, uv + vec2(-1.0, -1.0) * u_texel_size).rgb, luma);
+    float ne = dot(texture(u_input, uv + vec2(1.0, -1.0) * u_texel_size).rgb, luma);
+    float sw = dot(texture(u_input, uv + vec2(-1.0, 1.0) * u_texel_size).rgb, luma);
+    float se = dot(texture(u_input, uv + vec2(1.0, 1.0) * u_texel_size).rgb, luma);
+    vec4 center = texture(u_input, uv);
+    float m = dot(center.rgb, luma);
+
+    float luma_min = min(m, min(min(nw, ne), min(sw, se)));
+    float luma_max = max(m, max(max(nw, ne), max(sw, se)));
+
+    vec2 dir = vec2(-((nw + ne) - (sw + se)), (nw + sw) - (ne + se));
+    float reduce = max((nw + ne + sw + se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * u_texel_size;
+
+    vec3 a = 0.5 * (texture(u_input, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+                    texture(u_input, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 b = a * 0.5 + 0.25 * (texture(u_input, uv + dir * -0.5).rgb +
+                               texture(u_input, uv + dir * 0.5).rgb);
+    float luma_b = dot(b, luma);
+    frag_color = vec4((luma_b < luma_min || luma_b > luma_max) ? a : b, center.a);
+}
+"#;
+
+const TONEMAP_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+uniform int u_operator;
+uniform float u_exposure;
+uniform bool u_auto_exposure;
+uniform sampler2D u_luminance;
+uniform float u_exposure_key;
+uniform bool u_dither;
+uniform sampler2D u_dither_noise;
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 linear_to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+void main() {
+    vec4 hdr = texture(u_input, uv);
+    float exposure = u_exposure;
+    if (u_auto_exposure) {
+        exposure *= u_exposure_key / max(texelFetch(u_luminance, ivec2(0), 0).r, 1e-5);
+    }
+    vec3 color = hdr.rgb * exposure;
+    color = u_operator == 1 ? aces(color) : reinhard(color);
+    color = linear_to_srgb(color);
+    if (u_dither) {
+        ivec2 texel = ivec2(gl_FragCoord.xy) % textureSize(u_dither_noise, 0);
+        color += (texelFetch(u_dither_noise, texel, 0).r - 0.5) / 255.0;
+    }
+    frag_color = vec4(color, hdr.a);
+}
+"#;
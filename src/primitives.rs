@@ -0,0 +1,330 @@
+//! Procedural meshes: cube, plane, UV sphere, icosphere, torus, cylinder and capsule.
+//!
+//! Each generator returns [`Geometry`] centered on the origin, with outward normals,
+//! counter-clockwise front faces and texture coordinates, and tangents from
+//! [`compute_tangents`]. [`Geometry::mesh`] uploads it as a [`Mesh`] with the tangents
+//! at [`crate::mesh::TANGENT_LOCATION`]; the vertices can also be edited first. Curved
+//! surfaces duplicate the vertices along their texture seams, and their texture
+//! coordinates run from `v = 0` at the top.
+
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use anyhow::Result;
+
+use crate::math::Vec3;
+use crate::mesh::{compute_tangents, Mesh, Vertex};
+
+/// Vertices, tangents and triangle indices for [`Mesh::with_tangents`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Geometry {
+    pub vertices: Vec<Vertex>,
+    pub tangents: Vec<[f32; 4]>,
+    pub indices: Vec<u32>,
+}
+
+impl Geometry {
+    fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Geometry {
+        let tangents = compute_tangents(&vertices, &indices);
+        Geometry {
+            vertices,
+            tangents,
+            indices,
+        }
+    }
+
+    pub fn mesh(&self) -> Result<Mesh> {
+        Mesh::with_tangents(&self.vertices, &self.tangents, &self.indices)
+    }
+}
+
+fn vertex(position: Vec3, normal: Vec3, uv: [f32; 2]) -> Vertex {
+    Vertex {
+        position: position.to_array(),
+        normal: normal.to_array(),
+        uv,
+    }
+}
+
+/// A grid of `(rows + 1) × (columns + 1)` vertices from `vertex(row, column)`, where
+/// rows run downwards and columns to the right as seen from the front.
+fn grid(rows: u32, columns: u32, vertex: impl Fn(u32, u32) -> Vertex) -> Geometry {
+    let mut vertices = Vec::with_capacity(((rows + 1) * (columns + 1)) as usize);
+    for row in 0..=rows {
+        for column in 0..=columns {
+            vertices.push(vertex(row, column));
+        }
+    }
+    let mut indices = Vec::with_capacity((rows * columns * 6) as usize);
+    let stride = columns + 1;
+    for row in 0..rows {
+        for column in 0..columns {
+            let a = row * stride + column;
+            let (b, c, d) = (a + 1, a + stride, a + stride + 1);
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// Appends `other`, offsetting its indices.
+fn append(geometry: &mut Geometry, other: Geometry) {
+    let base = geometry.vertices.len() as u32;
+    geometry.vertices.extend(other.vertices);
+    geometry.tangents.extend(other.tangents);
+    geometry
+        .indices
+        .extend(other.indices.into_iter().map(|index| index + base));
+}
+
+/// An axis-aligned cube with edge length `size`, each face mapping the whole texture.
+pub fn cube(size: f32) -> Geometry {
+    const FACES: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+
+    let h = size * 0.5;
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, right, up) in FACES {
+        let base = vertices.len() as u32;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            let (su, sv) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+            let position = [0, 1, 2].map(|i| (normal[i] + right[i] * su + up[i] * sv) * h);
+            vertices.push(Vertex {
+                position,
+                normal,
+                uv: [u, v],
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A square in the XZ plane facing +Y, split into `subdivisions` × `subdivisions`
+/// cells.
+pub fn plane(size: f32, subdivisions: u32) -> Geometry {
+    let n = subdivisions.max(1);
+    grid(n, n, |row, column| {
+        let (s, t) = (column as f32 / n as f32, row as f32 / n as f32);
+        vertex(
+            Vec3::new((s - 0.5) * size, 0.0, (t - 0.5) * size),
+            Vec3::Y,
+            [s, 1.0 - t],
+        )
+    })
+}
+
+/// Direction at `polar` radians from +Y and `azimuth` radians around it, starting at +Z
+/// and turning towards +X.
+fn direction(polar: f32, azimuth: f32) -> Vec3 {
+    Vec3::new(
+        polar.sin() * azimuth.sin(),
+        polar.cos(),
+        polar.sin() * azimuth.cos(),
+    )
+}
+
+/// A sphere of `sectors` slices around the Y axis and `stacks` bands from pole to
+/// pole, with an equirectangular texture mapping.
+pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> Geometry {
+    let (sectors, stacks) = (sectors.max(3), stacks.max(2));
+    grid(stacks, sectors, |row, column| {
+        let (u, v) = (column as f32 / sectors as f32, row as f32 / stacks as f32);
+        let normal = direction(v * PI, u * TAU);
+        vertex(normal * radius, normal, [u, v])
+    })
+}
+
+/// A sphere from an icosahedron whose triangles are split in four `subdivisions`
+/// times, for evenly sized triangles without the UV sphere's crowded poles. Texture
+/// coordinates are equirectangular, as for [`uv_sphere`].
+pub fn icosphere(radius: f32, subdivisions: u32) -> Geometry {
+    let t = (1.0 + 5f32.sqrt()) * 0.5;
+    let mut positions: Vec<Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| Vec3::new(x, y, z).normalize())
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        let mut midpoints = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let position = (positions[a as usize] + positions[b as usize]).normalize();
+                positions.push(position);
+                positions.len() as u32 - 1
+            })
+        };
+        triangles = triangles
+            .into_iter()
+            .flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            })
+            .collect();
+    }
+
+    let mut vertices: Vec<Vertex> = positions
+        .iter()
+        .map(|&normal| {
+            let u = (normal.x.atan2(normal.z) / TAU).rem_euclid(1.0);
+            let v = normal.y.clamp(-1.0, 1.0).acos() / PI;
+            vertex(normal * radius, normal, [u, v])
+        })
+        .collect();
+    // Triangles straddling the seam at +Z get copies of their low-`u` vertices moved
+    // past `u = 1`, instead of stretching across the whole texture.
+    let mut wrapped = HashMap::new();
+    let mut indices = Vec::with_capacity(triangles.len() * 3);
+    for triangle in triangles {
+        let us = triangle.map(|i| vertices[i as usize].uv[0]);
+        let max = us.iter().copied().fold(f32::MIN, f32::max);
+        for (index, u) in triangle.into_iter().zip(us) {
+            if max - u > 0.5 {
+                let copy = *wrapped.entry(index).or_insert_with(|| {
+                    let mut vertex = vertices[index as usize];
+                    vertex.uv[0] += 1.0;
+                    vertices.push(vertex);
+                    vertices.len() as u32 - 1
+                });
+                indices.push(copy);
+            } else {
+                indices.push(index);
+            }
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A ring around the Y axis: a tube of radius `minor_radius` whose center circles the
+/// origin at `major_radius`. `u` runs around the ring and `v` around the tube.
+pub fn torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> Geometry {
+    let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+    grid(minor_segments, major_segments, |row, column| {
+        let (u, v) = (
+            column as f32 / major_segments as f32,
+            row as f32 / minor_segments as f32,
+        );
+        let (azimuth, tube) = (u * TAU, -v * TAU);
+        let outward = Vec3::new(azimuth.sin(), 0.0, azimuth.cos());
+        let normal = outward * tube.cos() + Vec3::Y * tube.sin();
+        vertex(
+            outward * major_radius + normal * minor_radius,
+            normal,
+            [u, v],
+        )
+    })
+}
+
+/// A disc facing `up` (+1 or -1 along Y) at height `y`.
+fn cap(radius: f32, y: f32, up: f32, segments: u32) -> Geometry {
+    let normal = Vec3::Y * up;
+    let mut vertices = vec![vertex(Vec3::Y * y, normal, [0.5, 0.5])];
+    for segment in 0..=segments {
+        let azimuth = segment as f32 / segments as f32 * TAU;
+        let (s, c) = azimuth.sin_cos();
+        vertices.push(vertex(
+            Vec3::new(s * radius, y, c * radius),
+            normal,
+            [0.5 + s * 0.5, 0.5 - c * 0.5 * up],
+        ));
+    }
+    let mut indices = Vec::with_capacity(segments as usize * 3);
+    for segment in 1..=segments {
+        if up > 0.0 {
+            indices.extend_from_slice(&[0, segment, segment + 1]);
+        } else {
+            indices.extend_from_slice(&[0, segment + 1, segment]);
+        }
+    }
+    Geometry::new(vertices, indices)
+}
+
+/// A closed cylinder along the Y axis. The side maps the whole texture; each cap maps
+/// a disc inscribed in it.
+pub fn cylinder(radius: f32, height: f32, segments: u32) -> Geometry {
+    let segments = segments.max(3);
+    let h = height * 0.5;
+    let mut geometry = grid(1, segments, |row, column| {
+        let u = column as f32 / segments as f32;
+        let normal = direction(FRAC_PI_2, u * TAU);
+        let y = if row == 0 { h } else { -h };
+        vertex(normal * radius + Vec3::Y * y, normal, [u, row as f32])
+    });
+    append(&mut geometry, cap(radius, h, 1.0, segments));
+    append(&mut geometry, cap(radius, -h, -1.0, segments));
+    geometry
+}
+
+/// A cylinder of `height` along the Y axis with hemispheres of `radius` on its ends,
+/// `2 × radius + height` tall in all. `rings` bands make up each hemisphere. `v` runs
+/// along the surface from the top.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Geometry {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let h = height * 0.5;
+    // Arc length from the top down to the equator, the cylinder, and the lower arc.
+    let quarter = FRAC_PI_2 * radius;
+    let length = 2.0 * quarter + height;
+    grid(2 * rings + 1, segments, |row, column| {
+        let u = column as f32 / segments as f32;
+        let (polar, center, distance) = if row <= rings {
+            let polar = FRAC_PI_2 * row as f32 / rings as f32;
+            (polar, h, polar * radius)
+        } else {
+            let polar = FRAC_PI_2 * (1.0 + (row - rings - 1) as f32 / rings as f32);
+            (polar, -h, quarter + height + (polar - FRAC_PI_2) * radius)
+        };
+        let normal = direction(polar, u * TAU);
+        vertex(
+            normal * radius + Vec3::Y * center,
+            normal,
+            [u, distance / length],
+        )
+    })
+}
@@ -0,0 +1,249 @@
+//! Capturing the scene around a point into a cube map, and saving it.
+//!
+//! [`CubeCapture::capture`] renders the scene six times with 90° cameras, one per cube
+//! face, into an `RGBA16F` cube map. The result can be sampled directly, or written to
+//! disk to bake an environment probe: [`CubeCapture::save_hdr`] writes an
+//! equirectangular Radiance panorama that [`crate::ibl::Environment::load`] turns back
+//! into image-based lighting, and [`CubeCapture::save_ktx`] writes the six faces as a
+//! KTX cube map for tools that take one.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::depth;
+use crate::framebuffer::{Framebuffer, Renderbuffer};
+use crate::gl;
+use crate::image::HdrImage;
+use crate::math::Vec3;
+use crate::pixels;
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::shadow::CUBE_FACES;
+use crate::texture::Texture;
+use crate::viewport::Camera;
+
+/// Unwraps the cube map onto a 2:1 panorama, the inverse of the mapping
+/// [`crate::ibl::Environment::from_hdr`] samples panoramas with.
+const EQUIRECTANGULAR_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform samplerCube u_cube_map;
+
+const float PI = 3.14159265359;
+
+void main() {
+    float longitude = (uv.x - 0.5) * 2.0 * PI;
+    float latitude = (uv.y - 0.5) * PI;
+    vec3 d = vec3(cos(latitude) * cos(longitude), sin(latitude), cos(latitude) * sin(longitude));
+    frag_color = vec4(textureLod(u_cube_map, d, 0.0).rgb, 1.0);
+}
+"#;
+
+const KTX_IDENTIFIER: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// A cube map render target for capturing the scene from a point.
+pub struct CubeCapture {
+    size: i32,
+    cube_map: Texture,
+    depth: Renderbuffer,
+    framebuffer: Framebuffer,
+}
+
+impl CubeCapture {
+    /// Creates a capture with `size`×`size` faces.
+    pub fn new(size: i32) -> Result<CubeCapture> {
+        let cube_map = Texture::cube_map(gl::RGBA16F, size, 1)?;
+        cube_map.label("cube capture");
+        let depth = Renderbuffer::with_storage(depth::format(), size, size, 0)?;
+        depth.label("cube capture depth");
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.label("cube capture");
+        Ok(CubeCapture {
+            size,
+            cube_map,
+            depth,
+            framebuffer,
+        })
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// The captured faces, in [`Texture::image_cube_face`] order.
+    pub fn cube_map(&self) -> &Texture {
+        &self.cube_map
+    }
+
+    /// Renders the scene around `position` into the cube map. `draw` is called once per
+    /// face with a camera looking through it, after the face is cleared to black, and
+    /// should draw everything with that camera's view and
+    /// [`Camera::projection_for_context`] at aspect 1.
+    ///
+    /// Leaves the default framebuffer bound; the viewport must be reset afterwards.
+    pub fn capture(
+        &self,
+        position: Vec3,
+        near: f32,
+        far: f32,
+        mut draw: impl FnMut(&Camera),
+    ) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "cube capture").entered();
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        self.framebuffer
+            .attach_renderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &self.depth);
+        unsafe {
+            gl::Viewport(0, 0, self.size, self.size);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        }
+        for (face, (direction, up)) in CUBE_FACES.into_iter().enumerate() {
+            self.framebuffer.attach_cube_face(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                &self.cube_map,
+                face as u32,
+                0,
+            );
+            if let Err(e) = self.framebuffer.check_status(gl::FRAMEBUFFER) {
+                Framebuffer::bind_default(gl::FRAMEBUFFER);
+                return Err(e);
+            }
+            unsafe {
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            }
+            draw(&Camera {
+                eye: position,
+                target: position + direction,
+                up,
+                fov_y: 90f32.to_radians(),
+                near,
+                far,
+            });
+        }
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        Ok(())
+    }
+
+    /// Reads face `face` back as `RGBA` floats, rows in the order the face was uploaded
+    /// with. Stalls until rendering has finished.
+    pub fn read_face(&self, face: u32) -> Result<Vec<[f32; 4]>> {
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        self.framebuffer.attach_cube_face(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            &self.cube_map,
+            face,
+            0,
+        );
+        let pixels = self
+            .framebuffer
+            .check_status(gl::FRAMEBUFFER)
+            .map(|()| read_float_pixels(self.size, self.size));
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        pixels
+    }
+
+    /// Unwraps the cube map into an equirectangular panorama with top-down rows, four
+    /// times as wide as a face so the equator keeps the faces' resolution.
+    ///
+    /// Leaves the default framebuffer bound; the viewport must be reset afterwards.
+    pub fn to_equirectangular(&self) -> Result<HdrImage> {
+        let (width, height) = (self.size * 4, self.size * 2);
+        let panorama = Texture::new(gl::TEXTURE_2D)?;
+        panorama.bind();
+        panorama.image_2d(0, gl::RGBA16F, width, height, gl::RGBA, gl::FLOAT, None);
+        panorama.unbind();
+        let program = Program::from_sources(FULLSCREEN_VERTEX_SHADER, EQUIRECTANGULAR_SHADER)?;
+        let triangle = FullscreenTriangle::new()?;
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, &panorama, 0);
+        let pixels = framebuffer.check_status(gl::FRAMEBUFFER).map(|()| {
+            unsafe {
+                gl::Viewport(0, 0, width, height);
+                gl::Disable(gl::DEPTH_TEST);
+                gl::Disable(gl::BLEND);
+            }
+            program.use_program();
+            self.cube_map.bind_unit(0);
+            program.set_int("u_cube_map", 0);
+            triangle.draw();
+            read_float_pixels(width, height)
+        });
+        Framebuffer::bind_default(gl::FRAMEBUFFER);
+        let mut texels = pixels?;
+        // GL rows go bottom-up.
+        pixels::flip_rows(&mut texels, width as usize);
+        let pixels = texels.into_iter().map(|[r, g, b, _]| [r, g, b]).collect();
+        Ok(HdrImage {
+            width: width as u32,
+            height: height as u32,
+            pixels,
+        })
+    }
+
+    /// Writes the capture as an equirectangular Radiance `.hdr` panorama, see
+    /// [`CubeCapture::to_equirectangular`].
+    pub fn save_hdr<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.to_equirectangular()?.save(path)
+    }
+
+    /// Writes the capture as a KTX 1 cube map with `RGBA32F` faces and no mip levels.
+    pub fn save_ktx<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = File::create(path.as_ref())
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.as_ref().display(), e))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&KTX_IDENTIFIER)?;
+        let size = self.size as u32;
+        let header = [
+            0x04030201, // endianness
+            gl::FLOAT,
+            4, // type size
+            gl::RGBA,
+            gl::RGBA32F,
+            gl::RGBA,
+            size,
+            size,
+            0, // depth
+            0, // array elements
+            6, // faces
+            1, // mip levels
+            0, // key/value data
+        ];
+        for value in header {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        // Rows of RGBA32F texels are always a multiple of 4 bytes, so neither rows nor
+        // faces need padding.
+        let face_bytes = size * size * 16;
+        writer.write_all(&face_bytes.to_le_bytes())?;
+        for face in 0..6 {
+            writer.write_all(bytemuck::cast_slice(&self.read_face(face)?))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads `RGBA` floats from the bound read framebuffer, bottom row first.
+fn read_float_pixels(width: i32, height: i32) -> Vec<[f32; 4]> {
+    let mut pixels = vec![[0.0; 4]; width as usize * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::FLOAT,
+            pixels.as_mut_ptr().cast(),
+        );
+    }
+    pixels
+}
@@ -0,0 +1,397 @@
+//! GPU timing with timer queries (GL 3.3 / `ARB_timer_query`) and pipeline statistics.
+//!
+//! [`Query`] wraps a query object. [`GpuTimer`] measures how long the GPU spends on the
+//! commands between [`GpuTimer::begin`] and [`GpuTimer::end`], once per frame.
+//! [`PipelineStatistics`] counts the vertices, primitives and shader invocations of
+//! named passes with `GL_PRIMITIVES_GENERATED` and, where the context has GL 4.6 or
+//! `ARB_pipeline_statistics_query`, the pipeline statistics queries. Results arrive a
+//! few frames late: both keep several queries in flight and only read those the driver
+//! reports as available, so measuring never stalls the pipeline.
+
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Result};
+
+use crate::context::{self, Deletion, GlContext};
+use crate::debug;
+use crate::gl;
+use crate::per_frame::PerFrame;
+
+pub struct Query(pub(crate) gl::types::GLuint, GlContext);
+
+impl Query {
+    pub fn new() -> Result<Query> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            gl::GenQueries(1, &mut id);
+        }
+        if id == 0 {
+            Err(anyhow!("Failed to create query"))
+        } else {
+            Ok(Query(id, gl_context))
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the query in graphics debuggers. See [`crate::debug`]. Only takes effect
+    /// once the query has been begun.
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::QUERY, self.0, label);
+    }
+
+    /// Starts the query on `target`, e.g. `GL_TIME_ELAPSED` or `GL_SAMPLES_PASSED`.
+    pub fn begin(&self, target: gl::types::GLenum) {
+        unsafe {
+            gl::BeginQuery(target, self.0);
+        }
+    }
+
+    /// Ends the active query on `target`.
+    pub fn end(target: gl::types::GLenum) {
+        unsafe {
+            gl::EndQuery(target);
+        }
+    }
+
+    /// Whether the result can be read without waiting for the GPU.
+    pub fn is_available(&self) -> bool {
+        let mut available = 0;
+        unsafe {
+            gl::GetQueryObjectiv(self.0, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+
+    /// The result, waiting for the GPU if it isn't available yet.
+    pub fn result(&self) -> u64 {
+        let mut result = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.0, gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+}
+
+impl Drop for Query {
+    fn drop(&mut self) {
+        self.1.delete(Deletion::Query(self.0));
+    }
+}
+
+/// Measurements allowed in flight before [`GpuTimer::begin`] waits for the oldest.
+const LATENCY: usize = 5;
+
+/// The GPU time of one measured frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuTime {
+    /// Counts the timer's [`GpuTimer::begin`] calls from zero.
+    pub frame: u64,
+    pub seconds: f64,
+}
+
+/// A timer query and the measurement it holds, if not yet read.
+struct TimerSlot {
+    query: Query,
+    pending: Option<u64>,
+}
+
+impl TimerSlot {
+    fn resolve(&mut self, ready: &mut Vec<GpuTime>) {
+        if let Some(frame) = self.pending.take() {
+            ready.push(GpuTime {
+                frame,
+                seconds: self.query.result() as f64 * 1e-9,
+            });
+        }
+    }
+}
+
+/// Per-frame GPU timing through a [`PerFrame`] ring of `GL_TIME_ELAPSED` queries. Only
+/// one can be active at a time, so timers don't nest.
+pub struct GpuTimer {
+    slots: PerFrame<TimerSlot>,
+    active: Option<usize>,
+    ready: Vec<GpuTime>,
+    frame: u64,
+}
+
+impl GpuTimer {
+    /// Whether the current context has timer queries.
+    pub fn is_supported() -> bool {
+        let info = context::info();
+        (!info.es && info.version >= (3, 3)) || info.has_extension("GL_ARB_timer_query")
+    }
+
+    pub fn new() -> Result<GpuTimer> {
+        if !GpuTimer::is_supported() {
+            return Err(anyhow!("Timer queries are not supported by this context"));
+        }
+        let slots = PerFrame::try_with_frames(LATENCY, |_| {
+            Ok(TimerSlot {
+                query: Query::new()?,
+                pending: None,
+            })
+        })?;
+        Ok(GpuTimer {
+            slots,
+            active: None,
+            ready: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// Starts measuring the next frame, first waiting for the measurement that last used
+    /// this frame's query if it is still unread.
+    pub fn begin(&mut self) -> Result<()> {
+        assert!(self.active.is_none(), "GpuTimer::begin called twice");
+        let index = self.slots.index();
+        let slot = self.slots.current_mut();
+        slot.resolve(&mut self.ready);
+        slot.query.begin(gl::TIME_ELAPSED);
+        self.active = Some(index);
+        Ok(())
+    }
+
+    /// Stops measuring the frame started by [`GpuTimer::begin`].
+    pub fn end(&mut self) {
+        let index = self.active.take().expect("GpuTimer::end without begin");
+        Query::end(gl::TIME_ELAPSED);
+        self.slots.slot_mut(index).pending = Some(self.frame);
+        self.frame += 1;
+    }
+
+    fn take_ready(&mut self) -> Vec<GpuTime> {
+        self.ready.sort_by_key(|time| time.frame);
+        std::mem::take(&mut self.ready)
+    }
+
+    /// Results that became available since the last call, oldest first. Never waits.
+    pub fn poll(&mut self) -> Vec<GpuTime> {
+        for slot in self.slots.iter_mut() {
+            if slot.pending.is_some() && slot.query.is_available() {
+                slot.resolve(&mut self.ready);
+            }
+        }
+        self.take_ready()
+    }
+
+    /// Waits for every frame still in flight and returns all outstanding results.
+    pub fn finish(&mut self) -> Vec<GpuTime> {
+        for slot in self.slots.iter_mut() {
+            slot.resolve(&mut self.ready);
+        }
+        self.take_ready()
+    }
+}
+
+/// A counter [`PipelineStatistics`] can collect per pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PipelineCounter {
+    /// Primitives leaving the last vertex processing stage, before clipping.
+    PrimitivesGenerated,
+    VerticesSubmitted,
+    PrimitivesSubmitted,
+    VertexShaderInvocations,
+    ClippingInputPrimitives,
+    ClippingOutputPrimitives,
+    FragmentShaderInvocations,
+}
+
+impl PipelineCounter {
+    pub const ALL: [PipelineCounter; 7] = [
+        PipelineCounter::PrimitivesGenerated,
+        PipelineCounter::VerticesSubmitted,
+        PipelineCounter::PrimitivesSubmitted,
+        PipelineCounter::VertexShaderInvocations,
+        PipelineCounter::ClippingInputPrimitives,
+        PipelineCounter::ClippingOutputPrimitives,
+        PipelineCounter::FragmentShaderInvocations,
+    ];
+
+    /// The query target counting this.
+    pub fn target(self) -> gl::types::GLenum {
+        match self {
+            PipelineCounter::PrimitivesGenerated => gl::PRIMITIVES_GENERATED,
+            PipelineCounter::VerticesSubmitted => gl::VERTICES_SUBMITTED_ARB,
+            PipelineCounter::PrimitivesSubmitted => gl::PRIMITIVES_SUBMITTED_ARB,
+            PipelineCounter::VertexShaderInvocations => gl::VERTEX_SHADER_INVOCATIONS_ARB,
+            PipelineCounter::ClippingInputPrimitives => gl::CLIPPING_INPUT_PRIMITIVES_ARB,
+            PipelineCounter::ClippingOutputPrimitives => gl::CLIPPING_OUTPUT_PRIMITIVES_ARB,
+            PipelineCounter::FragmentShaderInvocations => gl::FRAGMENT_SHADER_INVOCATIONS_ARB,
+        }
+    }
+
+    /// Whether the current context can count this.
+    pub fn is_supported(self) -> bool {
+        let info = context::info();
+        match self {
+            PipelineCounter::PrimitivesGenerated => !info.es || info.version >= (3, 2),
+            _ => {
+                (!info.es && info.version >= (4, 6))
+                    || info.has_extension("GL_ARB_pipeline_statistics_query")
+            }
+        }
+    }
+
+    /// A short name for display.
+    pub fn name(self) -> &'static str {
+        match self {
+            PipelineCounter::PrimitivesGenerated => "primitives generated",
+            PipelineCounter::VerticesSubmitted => "vertices",
+            PipelineCounter::PrimitivesSubmitted => "primitives",
+            PipelineCounter::VertexShaderInvocations => "vertex shaders",
+            PipelineCounter::ClippingInputPrimitives => "clipping in",
+            PipelineCounter::ClippingOutputPrimitives => "clipping out",
+            PipelineCounter::FragmentShaderInvocations => "fragment shaders",
+        }
+    }
+}
+
+/// The counters of one measured pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassStatistics {
+    /// Counts [`PipelineStatistics::end_frame`] calls from zero.
+    pub frame: u64,
+    pub name: String,
+    pub counters: Vec<(PipelineCounter, u64)>,
+}
+
+impl PassStatistics {
+    pub fn get(&self, counter: PipelineCounter) -> Option<u64> {
+        self.counters
+            .iter()
+            .find(|(c, _)| *c == counter)
+            .map(|&(_, value)| value)
+    }
+}
+
+/// Passes allowed in flight before [`PipelineStatistics::begin_pass`] waits for the
+/// oldest.
+const MAX_PENDING_PASSES: usize = 64;
+
+/// Per-pass pipeline statistics through a pool of queries, one per supported
+/// [`PipelineCounter`]. Only one pass can be active at a time, so passes don't nest, and
+/// no other `GL_PRIMITIVES_GENERATED` query may run during one.
+pub struct PipelineStatistics {
+    counters: Vec<PipelineCounter>,
+    free: Vec<Vec<Query>>,
+    pending: VecDeque<(u64, String, Vec<Query>)>,
+    active: Option<(String, Vec<Query>)>,
+    ready: Vec<PassStatistics>,
+    frame: u64,
+}
+
+impl PipelineStatistics {
+    /// Whether the current context can count anything.
+    pub fn is_supported() -> bool {
+        PipelineCounter::ALL.iter().any(|c| c.is_supported())
+    }
+
+    /// Collects every counter the context supports.
+    pub fn new() -> Result<PipelineStatistics> {
+        let counters: Vec<PipelineCounter> = PipelineCounter::ALL
+            .into_iter()
+            .filter(|c| c.is_supported())
+            .collect();
+        if counters.is_empty() {
+            return Err(anyhow!(
+                "Pipeline statistics are not supported by this context"
+            ));
+        }
+        Ok(PipelineStatistics {
+            counters,
+            free: Vec::new(),
+            pending: VecDeque::new(),
+            active: None,
+            ready: Vec::new(),
+            frame: 0,
+        })
+    }
+
+    /// The counters every result has.
+    pub fn counters(&self) -> &[PipelineCounter] {
+        &self.counters
+    }
+
+    /// Starts counting the pass `name`.
+    pub fn begin_pass(&mut self, name: &str) -> Result<()> {
+        assert!(
+            self.active.is_none(),
+            "PipelineStatistics::begin_pass called twice"
+        );
+        if self.pending.len() == MAX_PENDING_PASSES {
+            let (frame, name, queries) = self.pending.pop_front().unwrap();
+            self.resolve(frame, name, queries);
+        }
+        let queries = match self.free.pop() {
+            Some(queries) => queries,
+            None => self
+                .counters
+                .iter()
+                .map(|_| Query::new())
+                .collect::<Result<_>>()?,
+        };
+        for (query, counter) in queries.iter().zip(&self.counters) {
+            query.begin(counter.target());
+        }
+        self.active = Some((name.to_string(), queries));
+        Ok(())
+    }
+
+    /// Stops counting the pass started by [`PipelineStatistics::begin_pass`].
+    pub fn end_pass(&mut self) {
+        let (name, queries) = self
+            .active
+            .take()
+            .expect("PipelineStatistics::end_pass without begin_pass");
+        for counter in &self.counters {
+            Query::end(counter.target());
+        }
+        self.pending.push_back((self.frame, name, queries));
+    }
+
+    /// Marks the end of a frame; later passes belong to the next one.
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    fn resolve(&mut self, frame: u64, name: String, queries: Vec<Query>) {
+        let counters = self
+            .counters
+            .iter()
+            .zip(&queries)
+            .map(|(&counter, query)| (counter, query.result()))
+            .collect();
+        self.ready.push(PassStatistics {
+            frame,
+            name,
+            counters,
+        });
+        self.free.push(queries);
+    }
+
+    /// Passes whose results became available since the last call, oldest first. Never
+    /// waits.
+    pub fn poll(&mut self) -> Vec<PassStatistics> {
+        while let Some((_, _, queries)) = self.pending.front() {
+            if !queries.iter().all(Query::is_available) {
+                break;
+            }
+            let (frame, name, queries) = self.pending.pop_front().unwrap();
+            self.resolve(frame, name, queries);
+        }
+        std::mem::take(&mut self.ready)
+    }
+
+    /// Waits for every pass still in flight and returns all outstanding results.
+    pub fn finish(&mut self) -> Vec<PassStatistics> {
+        while let Some((frame, name, queries)) = self.pending.pop_front() {
+            self.resolve(frame, name, queries);
+        }
+        std::mem::take(&mut self.ready)
+    }
+}
@@ -0,0 +1,417 @@
+//! A one-closure API for tiny demos and teaching.
+//!
+//! [`run`] opens a window and calls a closure every frame with a [`Frame`] to draw on,
+//! hiding every program, buffer and batch behind it:
+//!
+//! ```no_run
+//! use hello_gl::math::Vec2;
+//! use hello_gl::quick::{self, rgb};
+//!
+//! quick::run(|frame| {
+//!     frame.clear(rgb(0.1, 0.1, 0.15));
+//!     let center = frame.size() * 0.5;
+//!     frame.circle(center, 100.0 + 20.0 * frame.time().sin(), rgb(0.9, 0.4, 0.2));
+//!     frame.text("Hello!", Vec2::new(20.0, 20.0), 32.0, rgb(1.0, 1.0, 1.0));
+//! });
+//! ```
+//!
+//! Coordinates are framebuffer pixels with the origin at the top left and y pointing
+//! down, like [`crate::text`] and [`crate::input::Input::cursor`]. Shapes are filled,
+//! alpha blended and drawn in call order. Text needs a TrueType font: the `font` setting
+//! of [`crate::settings`], or else the first of a few common system fonts found; without
+//! one, text is skipped with a warning.
+//!
+//! Every frame streams all of its geometry anew, which is plenty for demos; real
+//! renderers should use [`crate::sprite::SpriteBatch`] and friends directly.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+
+use crate::app::{self, App};
+use crate::buffer::VertexArray;
+use crate::event::WindowEvent;
+use crate::gl;
+use crate::input::Input;
+use crate::math::{Mat4, Vec2, Vec4};
+use crate::settings;
+use crate::shader::Program;
+use crate::sprite::SpriteBatch;
+use crate::stats;
+use crate::text::Font;
+use crate::transient::{self, TransientBuffer};
+use crate::validate;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec4 a_color;
+uniform mat4 u_projection;
+out vec4 v_color;
+void main() {
+    v_color = a_color;
+    gl_Position = u_projection * vec4(a_position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec4 v_color;
+out vec4 frag_color;
+void main() {
+    frag_color = v_color;
+}
+"#;
+
+/// Looked for in order when the `font` setting is unset.
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+/// Side of the glyph atlas.
+const FONT_ATLAS_SIZE: u32 = 1024;
+
+/// Pixels per segment of circle outlines, bounding how smooth large circles get.
+const CIRCLE_SEGMENT_LENGTH: f32 = 4.0;
+
+/// An opaque color.
+pub fn rgb(r: f32, g: f32, b: f32) -> Vec4 {
+    Vec4::new(r, g, b, 1.0)
+}
+
+/// A color with alpha, not premultiplied.
+pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Vec4 {
+    Vec4::new(r, g, b, a)
+}
+
+/// Opens a window titled "hello-gl" and calls `draw` every frame until it closes.
+pub fn run<F>(draw: F) -> !
+where
+    F: FnMut(&mut Frame) + 'static,
+{
+    run_titled("hello-gl", draw)
+}
+
+/// Like [`run`] with a window title.
+pub fn run_titled<F>(title: &str, draw: F) -> !
+where
+    F: FnMut(&mut Frame) + 'static,
+{
+    app::run(title, |_| {
+        Ok(Quick {
+            draw,
+            painter: Painter::new()?,
+            input: Input::new(),
+            size: Vec2::ONE,
+            time: 0.0,
+            dt: 0.0,
+            frame: 0,
+        })
+    })
+}
+
+/// What the closure given to [`run`] draws on.
+pub struct Frame<'a> {
+    painter: &'a mut Painter,
+    input: &'a Input,
+    size: Vec2,
+    time: f32,
+    dt: f32,
+    frame: u64,
+}
+
+impl Frame<'_> {
+    /// The window size in pixels.
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn width(&self) -> f32 {
+        self.size.x
+    }
+
+    pub fn height(&self) -> f32 {
+        self.size.y
+    }
+
+    /// Seconds since the first frame.
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Seconds since the previous frame.
+    pub fn dt(&self) -> f32 {
+        self.dt
+    }
+
+    /// Frames drawn before this one.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Keys, buttons and the cursor, e.g. `frame.input().is_pressed(KeyCode::Space)`.
+    pub fn input(&self) -> &Input {
+        self.input
+    }
+
+    /// The cursor position, or `None` outside the window.
+    pub fn mouse(&self) -> Option<Vec2> {
+        self.input.cursor()
+    }
+
+    /// Fills the whole window with `color`, discarding what was drawn before.
+    pub fn clear(&mut self, color: Vec4) {
+        self.painter.discard();
+        unsafe {
+            gl::ClearColor(color.x, color.y, color.z, color.w);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    pub fn triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Vec4) {
+        self.painter.triangle(a, b, c, color);
+    }
+
+    /// An axis-aligned rectangle with its top left corner at `position`.
+    pub fn rect(&mut self, position: Vec2, size: Vec2, color: Vec4) {
+        let (a, c) = (position, position + size);
+        let (b, d) = (Vec2::new(c.x, a.y), Vec2::new(a.x, c.y));
+        self.painter.triangle(a, b, c, color);
+        self.painter.triangle(a, c, d, color);
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Vec4) {
+        let circumference = std::f32::consts::TAU * radius.abs();
+        let segments = ((circumference / CIRCLE_SEGMENT_LENGTH) as usize).clamp(12, 256);
+        let point = |i: usize| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+            center + Vec2::new(angle.cos(), angle.sin()) * radius
+        };
+        for i in 0..segments {
+            self.painter.triangle(center, point(i), point(i + 1), color);
+        }
+    }
+
+    /// A segment `width` pixels wide with square ends.
+    pub fn line(&mut self, a: Vec2, b: Vec2, width: f32, color: Vec4) {
+        let along = (b - a).normalize_or_zero();
+        let across = along.perp() * width * 0.5;
+        self.painter
+            .triangle(a - across, b - across, b + across, color);
+        self.painter
+            .triangle(a - across, b + across, a + across, color);
+    }
+
+    /// Draws `text` with its top left corner at `position`, `size` pixels high, and
+    /// returns its extent; zero when no font was found.
+    pub fn text(&mut self, text: &str, position: Vec2, size: f32, color: Vec4) -> Vec2 {
+        match self.painter.text(text, position, size, color) {
+            Ok(extent) => extent,
+            Err(e) => {
+                tracing::error!("Failed to draw text: {:#}", e);
+                Vec2::ZERO
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+struct ShapeVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+
+/// Streams shapes and text in call order: recording text flushes the shapes before it,
+/// and recording shapes ends the text batch before them.
+struct Painter {
+    program: Program,
+    vertex_array: VertexArray,
+    vertex_buffer: TransientBuffer,
+    vertices: Vec<ShapeVertex>,
+    batch: SpriteBatch,
+    /// Whether `batch` is between `begin` and `end`.
+    batch_open: bool,
+    /// `None` until the first text is drawn, then whether a font was found.
+    font: Option<Option<Font>>,
+    projection: Mat4,
+}
+
+impl Painter {
+    fn new() -> Result<Painter> {
+        Ok(Painter {
+            program: Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?,
+            vertex_array: VertexArray::new()?,
+            vertex_buffer: TransientBuffer::new(transient::DEFAULT_CAPACITY)?,
+            vertices: Vec::new(),
+            batch: SpriteBatch::new(1024)?,
+            batch_open: false,
+            font: None,
+            projection: Mat4::IDENTITY,
+        })
+    }
+
+    fn begin(&mut self, size: Vec2) {
+        self.projection = Mat4::orthographic_rh_gl(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    fn triangle(&mut self, a: Vec2, b: Vec2, c: Vec2, color: Vec4) {
+        self.end_text();
+        let color = color.to_array();
+        for position in [a, b, c] {
+            self.vertices.push(ShapeVertex {
+                position: position.to_array(),
+                color,
+            });
+        }
+    }
+
+    fn text(&mut self, text: &str, position: Vec2, size: f32, color: Vec4) -> Result<Vec2> {
+        let font = self.font.get_or_insert_with(load_font);
+        let Some(font) = font else {
+            return Ok(Vec2::ZERO);
+        };
+        if !self.batch_open {
+            draw_shapes(
+                &self.program,
+                &self.vertex_array,
+                &self.vertex_buffer,
+                &mut self.vertices,
+                self.projection,
+            );
+            self.batch.begin(self.projection);
+            self.batch_open = true;
+        }
+        font.draw(&mut self.batch, text, position, size, color)
+    }
+
+    fn end_text(&mut self) {
+        if self.batch_open {
+            self.batch.end();
+            self.batch_open = false;
+        }
+    }
+
+    /// Draws everything recorded.
+    fn finish(&mut self) {
+        self.end_text();
+        draw_shapes(
+            &self.program,
+            &self.vertex_array,
+            &self.vertex_buffer,
+            &mut self.vertices,
+            self.projection,
+        );
+    }
+
+    /// Drops the shapes recorded, for a clear to cover. Batched text is drawn, as the
+    /// batch can't drop it.
+    fn discard(&mut self) {
+        self.end_text();
+        self.vertices.clear();
+    }
+}
+
+fn draw_shapes(
+    program: &Program,
+    vertex_array: &VertexArray,
+    vertex_buffer: &TransientBuffer,
+    vertices: &mut Vec<ShapeVertex>,
+    projection: Mat4,
+) {
+    if vertices.is_empty() {
+        return;
+    }
+    let stride = std::mem::size_of::<ShapeVertex>();
+    let offset = vertex_buffer.push(bytemuck::cast_slice(vertices), stride);
+    let buffer = vertex_buffer.buffer();
+    vertex_array.attribute(0, buffer, 2, gl::FLOAT, false, stride as i32, offset);
+    vertex_array.attribute(1, buffer, 4, gl::FLOAT, false, stride as i32, offset + 8);
+    program.use_program();
+    program.set_mat4("u_projection", &projection.to_cols_array());
+    vertex_array.bind();
+    validate::draw("quick shapes");
+    unsafe {
+        gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as i32);
+    }
+    vertex_array.unbind();
+    stats::record_draw(vertices.len() / 3);
+    vertices.clear();
+}
+
+/// The `font` setting, or the first system font found.
+fn load_font() -> Option<Font> {
+    let path = match &settings::get().font {
+        Some(path) => path.clone(),
+        None => {
+            let found = SYSTEM_FONTS
+                .iter()
+                .map(Path::new)
+                .find(|path| path.exists());
+            match found {
+                Some(path) => PathBuf::from(path),
+                None => {
+                    tracing::warn!("No font found for text; set `font` in hello-gl.toml");
+                    return None;
+                }
+            }
+        }
+    };
+    match Font::load(&path, FONT_ATLAS_SIZE) {
+        Ok(font) => Some(font),
+        Err(e) => {
+            tracing::warn!("{:#}; text is skipped", e);
+            None
+        }
+    }
+}
+
+struct Quick<F> {
+    draw: F,
+    painter: Painter,
+    input: Input,
+    size: Vec2,
+    time: f32,
+    dt: f32,
+    frame: u64,
+}
+
+impl<F: FnMut(&mut Frame) + 'static> App for Quick<F> {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.size = Vec2::new(width.max(1) as f32, height.max(1) as f32);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        self.input.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.dt = dt;
+    }
+
+    fn render(&mut self) {
+        self.painter.begin(self.size);
+        let mut frame = Frame {
+            painter: &mut self.painter,
+            input: &self.input,
+            size: self.size,
+            time: self.time,
+            dt: self.dt,
+            frame: self.frame,
+        };
+        (self.draw)(&mut frame);
+        self.painter.finish();
+        self.input.end_frame();
+        self.frame += 1;
+    }
+}
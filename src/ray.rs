@@ -0,0 +1,244 @@
+//! CPU ray casting for picking without a GPU readback.
+//!
+//! [`Ray::from_cursor`] unprojects a cursor position through a camera with
+//! [`Viewport::ray`]; the intersection tests return the distance along the ray to the
+//! nearest hit. [`Scene::raycast`] tests every drawable's world bounds and returns the
+//! hits nearest first.
+
+use crate::culling::{Aabb, Sphere};
+use crate::math::{Mat4, Vec2, Vec3};
+use crate::scene::{NodeId, Scene};
+use crate::viewport::Viewport;
+
+/// A half-line from `origin` along the unit vector `direction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// The ray through window position `(x, y)` (`y` down, as winit reports it) for a
+    /// scene drawn into `viewport` with `view_projection`; see [`Viewport::ray`].
+    pub fn from_cursor(
+        x: f64,
+        y: f64,
+        viewport: &Viewport,
+        framebuffer_height: u32,
+        view_projection: Mat4,
+    ) -> Ray {
+        let screen = Vec2::new(x as f32, y as f32);
+        viewport.ray(view_projection, screen, framebuffer_height)
+    }
+
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+
+    /// Slab test. Returns 0 when the origin is inside the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let inverse = self.direction.recip();
+        let t1 = (aabb.min - self.origin) * inverse;
+        let t2 = (aabb.max - self.origin) * inverse;
+        let near = t1.min(t2).max_element();
+        let far = t1.max(t2).min_element();
+        (far >= near.max(0.0)).then_some(near.max(0.0))
+    }
+
+    /// Returns 0 when the origin is inside the sphere.
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let to_center = sphere.center - self.origin;
+        let along = to_center.dot(self.direction);
+        let distance_squared = to_center.length_squared() - along * along;
+        let radius_squared = sphere.radius * sphere.radius;
+        if distance_squared > radius_squared {
+            return None;
+        }
+        let half_chord = (radius_squared - distance_squared).sqrt();
+        let (near, far) = (along - half_chord, along + half_chord);
+        (far >= 0.0).then_some(near.max(0.0))
+    }
+
+    /// Möller–Trumbore. Both faces count as hits.
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(q) * inverse;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// The nearest hit against indexed triangles transformed by `model`, for meshes
+    /// whose positions the caller keeps on the CPU.
+    pub fn intersect_triangles(
+        &self,
+        positions: &[Vec3],
+        indices: &[u32],
+        model: Mat4,
+    ) -> Option<f32> {
+        indices
+            .chunks_exact(3)
+            .filter_map(|triangle| {
+                let [a, b, c] =
+                    [0, 1, 2].map(|i| model.transform_point3(positions[triangle[i] as usize]));
+                self.intersect_triangle(a, b, c)
+            })
+            .min_by(f32::total_cmp)
+    }
+}
+
+/// Which bounding volume [`Scene::raycast`] tests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Volume {
+    Aabb,
+    /// The sphere around the mesh's box; cheaper to transform, looser fitting.
+    Sphere,
+}
+
+/// A drawable hit by [`Scene::raycast`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    pub node: NodeId,
+    /// Distance along the ray to the bounding volume.
+    pub distance: f32,
+}
+
+impl Scene {
+    /// Every drawable whose world-space bounding `volume` the ray hits, nearest first.
+    /// Call [`Scene::update`] first.
+    pub fn raycast(&self, ray: &Ray, volume: Volume) -> Vec<Hit> {
+        let mut hits: Vec<Hit> = self
+            .iter()
+            .filter_map(|(id, node)| {
+                let bounds = node.drawable.as_ref()?.mesh.bounds();
+                let distance = match volume {
+                    Volume::Aabb => ray.intersect_aabb(&bounds.transform(node.world())),
+                    Volume::Sphere => {
+                        ray.intersect_sphere(&bounds.bounding_sphere().transform(node.world()))
+                    }
+                }?;
+                Some(Hit { node: id, distance })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_box() -> Aabb {
+        Aabb {
+            min: Vec3::splat(-1.0),
+            max: Vec3::splat(1.0),
+        }
+    }
+
+    #[test]
+    fn aabb_hits_and_misses() {
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        let behind = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::Z);
+        assert_eq!(behind.intersect_aabb(&unit_box()), None);
+        let beside = Ray::new(Vec3::new(2.0, 0.0, 5.0), Vec3::NEG_Z);
+        assert_eq!(beside.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn aabb_from_inside_is_zero() {
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(0.0));
+    }
+
+    #[test]
+    fn aabb_along_an_axis_plane() {
+        // A zero direction component divides by zero; the slab test has to cope.
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::X);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        let above = Ray::new(Vec3::new(-5.0, 1.5, 0.0), Vec3::X);
+        assert_eq!(above.intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn sphere_hits_and_misses() {
+        let sphere = Sphere {
+            center: Vec3::new(0.0, 0.0, -10.0),
+            radius: 2.0,
+        };
+        let ray = Ray::new(Vec3::ZERO, Vec3::NEG_Z);
+        assert_eq!(ray.intersect_sphere(&sphere), Some(8.0));
+        assert_eq!(
+            Ray::new(Vec3::new(0.0, 0.0, -10.0), Vec3::X).intersect_sphere(&sphere),
+            Some(0.0)
+        );
+        assert_eq!(
+            Ray::new(Vec3::ZERO, Vec3::Z).intersect_sphere(&sphere),
+            None
+        );
+        assert_eq!(
+            Ray::new(Vec3::X * 3.0, Vec3::NEG_Z).intersect_sphere(&sphere),
+            None
+        );
+    }
+
+    #[test]
+    fn triangle_hits_both_faces() {
+        let (a, b, c) = (Vec3::ZERO, Vec3::X, Vec3::Y);
+        let front = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::NEG_Z);
+        assert_eq!(front.intersect_triangle(a, b, c), Some(1.0));
+        let back = Ray::new(Vec3::new(0.25, 0.25, -2.0), Vec3::Z);
+        assert_eq!(back.intersect_triangle(a, b, c), Some(2.0));
+    }
+
+    #[test]
+    fn triangle_misses() {
+        let (a, b, c) = (Vec3::ZERO, Vec3::X, Vec3::Y);
+        let outside = Ray::new(Vec3::new(0.75, 0.75, 1.0), Vec3::NEG_Z);
+        assert_eq!(outside.intersect_triangle(a, b, c), None);
+        let behind = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::Z);
+        assert_eq!(behind.intersect_triangle(a, b, c), None);
+        let parallel = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::X);
+        assert_eq!(parallel.intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn triangles_return_the_nearest_transformed_hit() {
+        let positions = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        // Both windings of one triangle; a partial triangle at the end is ignored.
+        let indices = [0, 1, 2, 0, 2, 1];
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 5.0), Vec3::NEG_Z);
+        let model = Mat4::from_translation(Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            ray.intersect_triangles(&positions, &indices, model),
+            Some(4.0)
+        );
+        assert_eq!(
+            ray.intersect_triangles(&positions, &indices[..2], Mat4::IDENTITY),
+            None
+        );
+    }
+}
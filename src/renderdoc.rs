@@ -0,0 +1,58 @@
+//! RenderDoc in-application capture API (feature `renderdoc`).
+//!
+//! The API is only present when the process was launched from, or injected by,
+//! RenderDoc; every function here is a no-op otherwise. [`crate::app::run`] triggers a
+//! capture on F12 and reads the capture path template from `HELLO_GL_CAPTURE_PATH`.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use ::renderdoc::{InputButton, RenderDoc, V141};
+
+/// Environment variable holding the capture path template, e.g. `captures/frame`.
+pub const CAPTURE_PATH_ENV: &str = "HELLO_GL_CAPTURE_PATH";
+
+thread_local! {
+    static API: RefCell<Option<Option<RenderDoc<V141>>>> = const { RefCell::new(None) };
+}
+
+fn with_api<R>(f: impl FnOnce(&mut RenderDoc<V141>) -> R) -> Option<R> {
+    API.with(|api| {
+        let mut api = api.borrow_mut();
+        let api = api.get_or_insert_with(|| {
+            RenderDoc::new().ok().map(|mut api: RenderDoc<V141>| {
+                // The runner's own binding replaces RenderDoc's default F12/PrtScr keys
+                // so captures are not triggered twice.
+                api.set_capture_keys::<InputButton>(&[]);
+                api
+            })
+        });
+        api.as_mut().map(f)
+    })
+}
+
+/// Whether the process is running under RenderDoc.
+pub fn is_available() -> bool {
+    with_api(|_| ()).is_some()
+}
+
+/// Captures the next frame presented. Returns `false` if RenderDoc is not attached.
+pub fn trigger_capture() -> bool {
+    with_api(|api| api.trigger_capture()).is_some()
+}
+
+/// Sets the path template for new captures; RenderDoc appends a frame suffix and
+/// `.rdc`.
+pub fn set_capture_path<P: AsRef<Path>>(template: P) {
+    with_api(|api| api.set_capture_file_path_template(template.as_ref()));
+}
+
+/// Paths of the captures taken so far.
+pub fn captures() -> Vec<PathBuf> {
+    with_api(|api| {
+        (0..api.get_num_captures())
+            .filter_map(|i| api.get_capture(i).map(|(path, _)| path))
+            .collect()
+    })
+    .unwrap_or_default()
+}
@@ -0,0 +1,369 @@
+//! Recording and deterministic playback of input.
+//!
+//! A [`Recorder`] wraps an [`App`] and writes, for every frame, the time step the runner
+//! passed to [`App::update`] and the input events that arrived before it. A [`Replay`]
+//! wraps the same app later and feeds it those events and time steps instead of the
+//! live ones, so an interactive bug plays out the same way every time. It also drives
+//! the [`crate::builtins`] time and mouse, and ends the run after the last frame.
+//!
+//! Recordings are JSON Lines, one [`Frame`] per line, written and flushed as the frames
+//! happen so a recording survives the crash it is meant to reproduce. They are short
+//! enough to write by hand or build with [`Recording`], e.g. to script a camera move
+//! for a golden-image test with `hello-gl --headless --replay camera.jsonl`.
+//!
+//! Only input is recorded: the window size, the files and the scene the app starts with
+//! have to be the same when replaying, and apps reading the clock themselves drift
+//! regardless.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{self, App};
+use crate::builtins;
+use crate::context::ResetStatus;
+use crate::event::{
+    ElementState, KeyCode, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    PhysicalPosition, WindowEvent,
+};
+use crate::viewport::EyeView;
+
+/// The input part of a [`WindowEvent`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        key: Option<KeyCode>,
+        pressed: bool,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        repeat: bool,
+    },
+    Character(char),
+    Modifiers(ModifiersState),
+    Button {
+        button: MouseButton,
+        pressed: bool,
+    },
+    /// Cursor position in physical pixels from the top left.
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    CursorEntered,
+    CursorLeft,
+    /// Scroll in lines, or in pixels if `pixels` is set.
+    Wheel {
+        x: f64,
+        y: f64,
+        pixels: bool,
+    },
+    Focused(bool),
+}
+
+fn state(pressed: bool) -> ElementState {
+    if pressed {
+        ElementState::Pressed
+    } else {
+        ElementState::Released
+    }
+}
+
+impl InputEvent {
+    /// The event to record for `event`, or `None` if it isn't input.
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        Some(match *event {
+            WindowEvent::KeyboardInput { input } => InputEvent::Key {
+                key: input.key,
+                pressed: input.state == ElementState::Pressed,
+                repeat: input.repeat,
+            },
+            WindowEvent::ReceivedCharacter(c) => InputEvent::Character(c),
+            WindowEvent::ModifiersChanged(modifiers) => InputEvent::Modifiers(modifiers),
+            WindowEvent::MouseInput { state, button } => InputEvent::Button {
+                button,
+                pressed: state == ElementState::Pressed,
+            },
+            WindowEvent::CursorMoved { position } => InputEvent::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::CursorEntered => InputEvent::CursorEntered,
+            WindowEvent::CursorLeft => InputEvent::CursorLeft,
+            WindowEvent::MouseWheel { delta } => match delta {
+                MouseScrollDelta::LineDelta(x, y) => InputEvent::Wheel {
+                    x: x as f64,
+                    y: y as f64,
+                    pixels: false,
+                },
+                MouseScrollDelta::PixelDelta(position) => InputEvent::Wheel {
+                    x: position.x,
+                    y: position.y,
+                    pixels: true,
+                },
+            },
+            WindowEvent::Focused(focused) => InputEvent::Focused(focused),
+            _ => return None,
+        })
+    }
+
+    /// The event as the runner would have delivered it.
+    pub fn to_window_event(&self) -> WindowEvent {
+        match *self {
+            InputEvent::Key {
+                key,
+                pressed,
+                repeat,
+            } => WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    key,
+                    state: state(pressed),
+                    repeat,
+                },
+            },
+            InputEvent::Character(c) => WindowEvent::ReceivedCharacter(c),
+            InputEvent::Modifiers(modifiers) => WindowEvent::ModifiersChanged(modifiers),
+            InputEvent::Button { button, pressed } => WindowEvent::MouseInput {
+                state: state(pressed),
+                button,
+            },
+            InputEvent::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                position: PhysicalPosition::new(x, y),
+            },
+            InputEvent::CursorEntered => WindowEvent::CursorEntered,
+            InputEvent::CursorLeft => WindowEvent::CursorLeft,
+            InputEvent::Wheel { x, y, pixels } => WindowEvent::MouseWheel {
+                delta: if pixels {
+                    MouseScrollDelta::PixelDelta(PhysicalPosition::new(x, y))
+                } else {
+                    MouseScrollDelta::LineDelta(x as f32, y as f32)
+                },
+            },
+            InputEvent::Focused(focused) => WindowEvent::Focused(focused),
+        }
+    }
+}
+
+/// One frame of a recording: the events delivered before its update, then the update.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// Seconds passed to [`App::update`].
+    pub dt: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recording {
+    pub frames: Vec<Frame>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let frames = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| anyhow!("{}:{}: {}", path.display(), i + 1, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Recording { frames })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = String::new();
+        for frame in &self.frames {
+            text.push_str(&serde_json::to_string(frame)?);
+            text.push('\n');
+        }
+        std::fs::write(path, text).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Seconds covered by the frames.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|frame| frame.dt).sum()
+    }
+}
+
+/// Records the input and time steps of the app it wraps to a file.
+pub struct Recorder {
+    app: Box<dyn App>,
+    path: PathBuf,
+    /// `None` after a write failed.
+    file: Option<BufWriter<File>>,
+    events: Vec<InputEvent>,
+}
+
+impl Recorder {
+    /// Starts recording `app` to `path`, replacing the file.
+    pub fn new(app: Box<dyn App>, path: impl Into<PathBuf>) -> Result<Recorder> {
+        let path = path.into();
+        let file = File::create(&path)
+            .map_err(|e| anyhow!("Failed to create {}: {}", path.display(), e))?;
+        tracing::info!("Recording input to {}", path.display());
+        Ok(Recorder {
+            app,
+            path,
+            file: Some(BufWriter::new(file)),
+            events: Vec::new(),
+        })
+    }
+
+    fn write(&mut self, frame: &Frame) {
+        let Some(file) = &mut self.file else {
+            return;
+        };
+        let result = serde_json::to_writer(&mut *file, frame)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(file))
+            .and_then(|()| file.flush());
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to write {}: {}; recording stopped",
+                self.path.display(),
+                e
+            );
+            self.file = None;
+        }
+    }
+}
+
+impl App for Recorder {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.app.resize(width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if let Some(input) = InputEvent::from_window_event(event) {
+            self.events.push(input);
+        }
+        self.app.window_event(event);
+    }
+
+    fn update(&mut self, dt: f32) {
+        let frame = Frame {
+            dt,
+            events: std::mem::take(&mut self.events),
+        };
+        self.write(&frame);
+        self.app.update(dt);
+    }
+
+    fn render(&mut self) {
+        self.app.render();
+    }
+
+    fn render_eye(&mut self, eye: &EyeView) {
+        self.app.render_eye(eye);
+    }
+
+    fn update_window(&mut self, window: &mut dyn app::Window) {
+        self.app.update_window(window);
+    }
+
+    fn exit_requested(&self) -> bool {
+        self.app.exit_requested()
+    }
+
+    fn context_lost(&mut self, status: ResetStatus) {
+        self.app.context_lost(status);
+    }
+
+    fn context_restored(&mut self) -> Result<()> {
+        self.app.context_restored()
+    }
+}
+
+/// Plays a [`Recording`] into the app it wraps, ignoring live input, and requests exit
+/// after the last frame.
+pub struct Replay {
+    app: Box<dyn App>,
+    frames: VecDeque<Frame>,
+    /// Sum of the replayed time steps.
+    time: f32,
+}
+
+impl Replay {
+    pub fn new(app: Box<dyn App>, recording: Recording) -> Replay {
+        Replay {
+            app,
+            frames: recording.frames.into(),
+            time: 0.0,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(app: Box<dyn App>, path: P) -> Result<Replay> {
+        let recording = Recording::load(&path)?;
+        tracing::info!(
+            "Replaying {} frames from {}",
+            recording.frames.len(),
+            path.as_ref().display()
+        );
+        Ok(Replay::new(app, recording))
+    }
+
+    /// Frames not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl App for Replay {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.app.resize(width, height);
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        if InputEvent::from_window_event(event).is_none() {
+            self.app.window_event(event);
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        let Some(frame) = self.frames.pop_front() else {
+            self.app.update(dt);
+            return;
+        };
+        for input in &frame.events {
+            let event = input.to_window_event();
+            builtins::window_event(&event);
+            self.app.window_event(&event);
+        }
+        // The runner set the time from its own clock just before.
+        builtins::set_time(self.time);
+        self.time += frame.dt;
+        self.app.update(frame.dt);
+    }
+
+    fn render(&mut self) {
+        self.app.render();
+    }
+
+    fn render_eye(&mut self, eye: &EyeView) {
+        self.app.render_eye(eye);
+    }
+
+    fn update_window(&mut self, window: &mut dyn app::Window) {
+        self.app.update_window(window);
+    }
+
+    fn exit_requested(&self) -> bool {
+        self.frames.is_empty() || self.app.exit_requested()
+    }
+
+    fn context_lost(&mut self, status: ResetStatus) {
+        self.app.context_lost(status);
+    }
+
+    fn context_restored(&mut self) -> Result<()> {
+        self.app.context_restored()
+    }
+}
@@ -0,0 +1,306 @@
+//! A scene graph of nodes with hierarchical transforms.
+//!
+//! Each node has a local [`Transform`] relative to its parent. Changing it marks the node
+//! dirty; [`Scene::update`] recomputes world matrices only for dirty nodes and their
+//! descendants.
+
+use std::rc::Rc;
+
+use crate::culling::{Aabb, CullStats, Frustum};
+use crate::draw::DrawList;
+use crate::material::{Material, MaterialShaders};
+use crate::math::{Mat4, Transform};
+use crate::mesh::Mesh;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub(crate) usize);
+
+/// Something that can be drawn at a node's world transform.
+#[derive(Clone)]
+pub struct Drawable {
+    pub mesh: Rc<Mesh>,
+    /// Indexed by [`crate::mesh::Submesh::material`]. Submeshes without a material index
+    /// use the first entry, or the default material if there is none.
+    pub materials: Vec<Rc<Material>>,
+}
+
+impl Drawable {
+    pub fn new(mesh: Rc<Mesh>, material: Rc<Material>) -> Drawable {
+        Drawable {
+            mesh,
+            materials: vec![material],
+        }
+    }
+}
+
+pub struct Node {
+    pub name: Option<String>,
+    pub drawable: Option<Drawable>,
+    local: Transform,
+    world: Mat4,
+    dirty: bool,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+}
+
+impl Node {
+    pub fn local(&self) -> &Transform {
+        &self.local
+    }
+
+    /// The world matrix as of the last [`Scene::update`].
+    pub fn world(&self) -> Mat4 {
+        self.world
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+}
+
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Option<Node>>,
+    fallback: Material,
+    free: Vec<usize>,
+    roots: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene::default()
+    }
+
+    /// Adds a node under `parent`, or as a root when `parent` is `None`.
+    pub fn add(&mut self, parent: Option<NodeId>, name: Option<&str>, local: Transform) -> NodeId {
+        let node = Node {
+            name: name.map(str::to_owned),
+            drawable: None,
+            local,
+            world: Mat4::IDENTITY,
+            dirty: true,
+            parent,
+            children: Vec::new(),
+        };
+        let id = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                NodeId(index)
+            }
+            None => {
+                self.nodes.push(Some(node));
+                NodeId(self.nodes.len() - 1)
+            }
+        };
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+        id
+    }
+
+    /// Adds a node carrying `drawable`.
+    pub fn add_drawable(
+        &mut self,
+        parent: Option<NodeId>,
+        name: Option<&str>,
+        local: Transform,
+        drawable: Drawable,
+    ) -> NodeId {
+        let id = self.add(parent, name, local);
+        self.node_mut(id).drawable = Some(drawable);
+        id
+    }
+
+    /// Removes `id` and all of its descendants.
+    pub fn remove(&mut self, id: NodeId) {
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            if let Some(node) = self.nodes[id.0].take() {
+                stack.extend(node.children);
+                self.free.push(id.0);
+            }
+        }
+    }
+
+    /// Moves `id` under `parent` (or to the roots), keeping its local transform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is `id` or one of its descendants.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            assert!(a != id, "cannot parent a node to its own subtree");
+            ancestor = self.node(a).parent;
+        }
+        self.detach(id);
+        self.node_mut(id).parent = parent;
+        self.node_mut(id).dirty = true;
+        match parent {
+            Some(parent) => self.node_mut(parent).children.push(id),
+            None => self.roots.push(id),
+        }
+    }
+
+    fn detach(&mut self, id: NodeId) {
+        match self.node(id).parent {
+            Some(parent) => self.node_mut(parent).children.retain(|&c| c != id),
+            None => self.roots.retain(|&r| r != id),
+        }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id.0).and_then(Option::as_ref)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        self.get(id).expect("invalid node id")
+    }
+
+    /// Mutable access to a node's name and drawable. Use [`Scene::local_mut`] or
+    /// [`Scene::set_local`] to change its transform.
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes
+            .get_mut(id.0)
+            .and_then(Option::as_mut)
+            .expect("invalid node id")
+    }
+
+    pub fn set_local(&mut self, id: NodeId, local: Transform) {
+        let node = self.node_mut(id);
+        node.local = local;
+        node.dirty = true;
+    }
+
+    /// Mutable access to the local transform; marks the node dirty.
+    pub fn local_mut(&mut self, id: NodeId) -> &mut Transform {
+        let node = self.node_mut(id);
+        node.dirty = true;
+        &mut node.local
+    }
+
+    pub fn roots(&self) -> &[NodeId] {
+        &self.roots
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.iter()
+            .find(|(_, node)| node.name.as_deref() == Some(name))
+            .map(|(id, _)| id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| node.as_ref().map(|node| (NodeId(i), node)))
+    }
+
+    /// Recomputes world matrices of dirty nodes and their descendants.
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Mat4, bool)> = self
+            .roots
+            .iter()
+            .map(|&root| (root, Mat4::IDENTITY, false))
+            .collect();
+        while let Some((id, parent_world, parent_changed)) = stack.pop() {
+            let node = self.node_mut(id);
+            let changed = parent_changed || node.dirty;
+            if changed {
+                node.world = parent_world * node.local.matrix();
+                node.dirty = false;
+            }
+            let world = node.world;
+            stack.extend(node.children.iter().map(|&child| (child, world, changed)));
+        }
+    }
+
+    /// World-space bounds of a node's drawable, as of the last [`Scene::update`].
+    pub fn world_bounds(&self, id: NodeId) -> Option<Aabb> {
+        let node = self.node(id);
+        let drawable = node.drawable.as_ref()?;
+        Some(drawable.mesh.bounds().transform(node.world))
+    }
+
+    /// Draws every drawable with its node's world matrix. Call [`Scene::update`] first and
+    /// set the camera on `shaders`.
+    pub fn draw(&self, shaders: &MaterialShaders) {
+        for (_, node) in self.iter() {
+            if let Some(drawable) = &node.drawable {
+                draw_drawable(shaders, drawable, node.world);
+            }
+        }
+    }
+
+    /// Like [`Scene::draw`], skipping drawables whose bounds lie outside `frustum`.
+    ///
+    /// Skinned meshes are always drawn since their bind-pose bounds don't follow the
+    /// animation.
+    pub fn draw_culled(&self, shaders: &MaterialShaders, frustum: &Frustum) -> CullStats {
+        let mut stats = CullStats::default();
+        for (_, node) in self.iter() {
+            let Some(drawable) = &node.drawable else {
+                continue;
+            };
+            let bounds = drawable.mesh.bounds().transform(node.world);
+            if drawable.mesh.is_skinned() || frustum.intersects_aabb(&bounds) {
+                draw_drawable(shaders, drawable, node.world);
+                stats.visible += 1;
+            } else {
+                stats.culled += 1;
+            }
+        }
+        stats
+    }
+
+    /// Records drawables into `list` for sorted submission, culling against `frustum`
+    /// when given.
+    pub fn record<'a>(
+        &'a self,
+        list: &mut DrawList<'a>,
+        shaders: &MaterialShaders,
+        frustum: Option<&Frustum>,
+    ) -> CullStats {
+        let mut stats = CullStats::default();
+        for (_, node) in self.iter() {
+            let Some(drawable) = &node.drawable else {
+                continue;
+            };
+            if let Some(frustum) = frustum {
+                let bounds = drawable.mesh.bounds().transform(node.world);
+                if !drawable.mesh.is_skinned() && !frustum.intersects_aabb(&bounds) {
+                    stats.culled += 1;
+                    continue;
+                }
+            }
+            stats.visible += 1;
+            for (i, submesh) in drawable.mesh.submeshes().iter().enumerate() {
+                let material = drawable
+                    .materials
+                    .get(submesh.material.unwrap_or(0))
+                    .map_or(&self.fallback, |m| m.as_ref());
+                list.push(shaders, &drawable.mesh, i, material, node.world);
+            }
+        }
+        stats
+    }
+}
+
+fn draw_drawable(shaders: &MaterialShaders, drawable: &Drawable, world: Mat4) {
+    let fallback = Material::default();
+    for (i, submesh) in drawable.mesh.submeshes().iter().enumerate() {
+        let material = drawable
+            .materials
+            .get(submesh.material.unwrap_or(0))
+            .map_or(&fallback, |m| m.as_ref());
+        let program = shaders.bind_variant(material, drawable.mesh.is_skinned());
+        program.set_mat4("u_model", &world.to_cols_array());
+        drawable.mesh.draw_submesh(i);
+    }
+}
@@ -0,0 +1,343 @@
+//! Saving and loading scenes as RON or JSON.
+//!
+//! A [`SceneFile`] holds a [`Scene`]'s node hierarchy with names, local transforms and
+//! material parameters, plus a camera and lights, which the scene itself doesn't
+//! track. Meshes and textures are GPU resources and are stored by name: the
+//! application registers them in [`Resources`] before saving or loading, and a file
+//! naming a resource that isn't registered fails to load. Nodes are added in file
+//! order, depth first, so a loaded scene always gets the same [`crate::scene::NodeId`]s.
+//!
+//! ```ron
+//! (
+//!     camera: Some((
+//!         eye: (0.0, 6.0, 10.0),
+//!         target: (0.0, 0.0, 0.0),
+//!         up: (0.0, 1.0, 0.0),
+//!         fov_y: 0.785,
+//!         near: 0.1,
+//!         far: 100.0,
+//!     )),
+//!     lights: [
+//!         Directional(direction: (-0.3, -1.0, -0.5), color: (1.0, 1.0, 1.0), intensity: 3.0),
+//!     ],
+//!     nodes: [(
+//!         name: Some("sun"),
+//!         mesh: Some("cube"),
+//!         materials: [(shading: Pbr, base_color: (1.0, 0.8, 0.2, 1.0))],
+//!         children: [(name: Some("pivot"))],
+//!     )],
+//! )
+//! ```
+//!
+//! Omitted fields take their defaults: the identity transform, no mesh, and
+//! [`Material::default`]'s parameters.
+
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::material::{Light, Material, Shading};
+use crate::math::{Transform, Vec3, Vec4};
+use crate::mesh::Mesh;
+use crate::scene::{Drawable, NodeId, Scene};
+use crate::texture::Texture;
+use crate::viewport::Camera;
+
+/// Named meshes and textures a [`SceneFile`] refers to.
+#[derive(Default)]
+pub struct Resources {
+    meshes: Vec<(String, Rc<Mesh>)>,
+    textures: Vec<(String, Rc<Texture>)>,
+}
+
+impl Resources {
+    pub fn new() -> Resources {
+        Resources::default()
+    }
+
+    /// Registers `mesh` as `name`, replacing an earlier mesh of that name.
+    pub fn add_mesh(&mut self, name: &str, mesh: Rc<Mesh>) {
+        self.meshes.retain(|(n, _)| n != name);
+        self.meshes.push((name.to_owned(), mesh));
+    }
+
+    /// Registers `texture` as `name`, replacing an earlier texture of that name.
+    pub fn add_texture(&mut self, name: &str, texture: Rc<Texture>) {
+        self.textures.retain(|(n, _)| n != name);
+        self.textures.push((name.to_owned(), texture));
+    }
+
+    pub fn mesh(&self, name: &str) -> Option<&Rc<Mesh>> {
+        find(&self.meshes, name)
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Rc<Texture>> {
+        find(&self.textures, name)
+    }
+
+    fn mesh_name(&self, mesh: &Rc<Mesh>) -> Result<String> {
+        name_of(&self.meshes, mesh).ok_or_else(|| anyhow!("A node's mesh is not registered"))
+    }
+
+    fn texture_name(&self, texture: &Rc<Texture>) -> Result<String> {
+        name_of(&self.textures, texture)
+            .ok_or_else(|| anyhow!("A material's texture is not registered"))
+    }
+}
+
+fn find<'a, T>(entries: &'a [(String, Rc<T>)], name: &str) -> Option<&'a Rc<T>> {
+    entries.iter().find(|(n, _)| n == name).map(|(_, r)| r)
+}
+
+fn name_of<T>(entries: &[(String, Rc<T>)], resource: &Rc<T>) -> Option<String> {
+    entries
+        .iter()
+        .find(|(_, r)| Rc::ptr_eq(r, resource))
+        .map(|(n, _)| n.clone())
+}
+
+/// [`Material`]'s parameters, with textures by name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc {
+    pub shading: Shading,
+    pub base_color: Vec4,
+    pub base_color_texture: Option<String>,
+    pub emissive: Vec3,
+    pub specular: Vec3,
+    pub shininess: f32,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub metallic_roughness_texture: Option<String>,
+    pub normal_texture: Option<String>,
+    pub normal_scale: f32,
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        let material = Material::default();
+        MaterialDesc {
+            shading: material.shading,
+            base_color: material.base_color,
+            base_color_texture: None,
+            emissive: material.emissive,
+            specular: material.specular,
+            shininess: material.shininess,
+            metallic: material.metallic,
+            roughness: material.roughness,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            normal_scale: material.normal_scale,
+        }
+    }
+}
+
+impl MaterialDesc {
+    fn from_material(material: &Material, resources: &Resources) -> Result<MaterialDesc> {
+        let texture = |texture: &Option<Rc<Texture>>| {
+            texture
+                .as_ref()
+                .map(|t| resources.texture_name(t))
+                .transpose()
+        };
+        Ok(MaterialDesc {
+            shading: material.shading,
+            base_color: material.base_color,
+            base_color_texture: texture(&material.base_color_texture)?,
+            emissive: material.emissive,
+            specular: material.specular,
+            shininess: material.shininess,
+            metallic: material.metallic,
+            roughness: material.roughness,
+            metallic_roughness_texture: texture(&material.metallic_roughness_texture)?,
+            normal_texture: texture(&material.normal_texture)?,
+            normal_scale: material.normal_scale,
+        })
+    }
+
+    fn to_material(&self, resources: &Resources) -> Result<Material> {
+        let texture = |name: &Option<String>| {
+            name.as_deref()
+                .map(|name| {
+                    resources
+                        .texture(name)
+                        .cloned()
+                        .ok_or_else(|| anyhow!("No texture named `{}` is registered", name))
+                })
+                .transpose()
+        };
+        Ok(Material {
+            shading: self.shading,
+            base_color: self.base_color,
+            base_color_texture: texture(&self.base_color_texture)?,
+            emissive: self.emissive,
+            specular: self.specular,
+            shininess: self.shininess,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            metallic_roughness_texture: texture(&self.metallic_roughness_texture)?,
+            normal_texture: texture(&self.normal_texture)?,
+            normal_scale: self.normal_scale,
+        })
+    }
+}
+
+/// A node and its subtree.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NodeDesc {
+    pub name: Option<String>,
+    pub transform: Transform,
+    /// The registered name of the node's mesh; `None` for nodes without a drawable.
+    pub mesh: Option<String>,
+    pub materials: Vec<MaterialDesc>,
+    pub children: Vec<NodeDesc>,
+}
+
+/// A scene with its camera and lights, as stored on disk.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub camera: Option<Camera>,
+    pub ambient: Vec3,
+    pub lights: Vec<Light>,
+    pub nodes: Vec<NodeDesc>,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        SceneFile {
+            camera: None,
+            ambient: Vec3::splat(0.1),
+            lights: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl SceneFile {
+    /// Describes the nodes of `scene`, with no camera or lights. Fails if a drawable's
+    /// mesh or texture isn't registered in `resources`.
+    pub fn from_scene(scene: &Scene, resources: &Resources) -> Result<SceneFile> {
+        let nodes = scene
+            .roots()
+            .iter()
+            .map(|&id| describe(scene, id, resources))
+            .collect::<Result<_>>()?;
+        Ok(SceneFile {
+            nodes,
+            ..SceneFile::default()
+        })
+    }
+
+    /// Builds the node hierarchy, looking meshes and textures up in `resources`.
+    pub fn to_scene(&self, resources: &Resources) -> Result<Scene> {
+        let mut scene = Scene::new();
+        for node in &self.nodes {
+            build(&mut scene, None, node, resources)?;
+        }
+        scene.update();
+        Ok(scene)
+    }
+
+    pub fn from_ron(text: &str) -> Result<SceneFile> {
+        Ok(ron::from_str(text)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_json(text: &str) -> Result<SceneFile> {
+        Ok(serde_json::from_str(text)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Reads a scene file, as JSON if the extension is `.json` and as RON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<SceneFile> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let file = if is_json(path) {
+            SceneFile::from_json(&text)
+        } else {
+            SceneFile::from_ron(&text)
+        };
+        file.map_err(|e| anyhow!("Invalid {}: {:#}", path.display(), e))
+    }
+
+    /// Writes the scene file, as JSON if the extension is `.json` and as RON otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_json(path) {
+            self.to_json()?
+        } else {
+            self.to_ron()?
+        };
+        std::fs::write(path, text).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+fn describe(scene: &Scene, id: NodeId, resources: &Resources) -> Result<NodeDesc> {
+    let node = scene.node(id);
+    let (mesh, materials) = match &node.drawable {
+        Some(drawable) => (
+            Some(resources.mesh_name(&drawable.mesh)?),
+            drawable
+                .materials
+                .iter()
+                .map(|material| MaterialDesc::from_material(material, resources))
+                .collect::<Result<_>>()?,
+        ),
+        None => (None, Vec::new()),
+    };
+    Ok(NodeDesc {
+        name: node.name.clone(),
+        transform: *node.local(),
+        mesh,
+        materials,
+        children: node
+            .children()
+            .iter()
+            .map(|&child| describe(scene, child, resources))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn build(
+    scene: &mut Scene,
+    parent: Option<NodeId>,
+    desc: &NodeDesc,
+    resources: &Resources,
+) -> Result<()> {
+    let id = scene.add(parent, desc.name.as_deref(), desc.transform);
+    if let Some(name) = &desc.mesh {
+        let mesh = resources
+            .mesh(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No mesh named `{}` is registered", name))?;
+        let materials = desc
+            .materials
+            .iter()
+            .map(|material| material.to_material(resources).map(Rc::new))
+            .collect::<Result<_>>()?;
+        scene.node_mut(id).drawable = Some(Drawable { mesh, materials });
+    }
+    for child in &desc.children {
+        build(scene, Some(id), child, resources)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+//! Binds undone at the end of a scope.
+//!
+//! The `bind_scoped` methods of [`Buffer`], [`Texture`], [`VertexArray`], [`Framebuffer`]
+//! and [`Renderbuffer`], and [`Program::use_scoped`], bind their object through a
+//! [`StateCache`] and return a [`Binding`] guard that, when dropped, rebinds whatever the
+//! cache had bound before. The guard borrows the cache and derefs to it, so binds nested
+//! in its scope go through the guard and are undone first:
+//!
+//! ```ignore
+//! let mut cache = program.use_scoped(&mut cache);
+//! let _bound = buffer.bind_scoped(&mut cache, gl::COPY_WRITE_BUFFER);
+//! ```
+//!
+//! Unlike a plain `unbind`, which binds 0 and so leaks the change to surrounding code
+//! that had something else bound, this leaves the cache's bindings as they were, and
+//! nothing is queried from GL. Where the cache doesn't know the previous binding, e.g.
+//! after [`StateCache::invalidate`], the guard restores 0. Guards borrow their object,
+//! which therefore outlives the binding.
+//!
+//! [`Buffer`]: crate::buffer::Buffer
+//! [`Texture`]: crate::texture::Texture
+//! [`VertexArray`]: crate::buffer::VertexArray
+//! [`Framebuffer`]: crate::framebuffer::Framebuffer
+//! [`Renderbuffer`]: crate::framebuffer::Renderbuffer
+//! [`Program::use_scoped`]: crate::shader::Program::use_scoped
+
+use std::ops::{Deref, DerefMut};
+
+use crate::gl;
+use crate::state::StateCache;
+
+/// What to rebind on drop; `None` where the cache didn't know.
+#[derive(Clone, Copy, Debug)]
+enum Previous {
+    Buffer(gl::types::GLenum, Option<gl::types::GLuint>),
+    Texture {
+        unit: u32,
+        target: gl::types::GLenum,
+        previous: Option<(gl::types::GLenum, gl::types::GLuint)>,
+    },
+    VertexArray(Option<gl::types::GLuint>),
+    Program(Option<gl::types::GLuint>),
+    /// The outer `Option`s are whether the guard bound that target.
+    Framebuffer {
+        draw: Option<Option<gl::types::GLuint>>,
+        read: Option<Option<gl::types::GLuint>>,
+    },
+    Renderbuffer(Option<gl::types::GLuint>),
+}
+
+/// Restores the previous binding of a target through the [`StateCache`] it borrows when
+/// dropped. Derefs to the cache.
+#[must_use = "the previous binding is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct Binding<'a> {
+    cache: &'a mut StateCache,
+    previous: Previous,
+}
+
+impl<'a> Binding<'a> {
+    fn new(cache: &'a mut StateCache, previous: Previous) -> Self {
+        Binding { cache, previous }
+    }
+}
+
+impl Deref for Binding<'_> {
+    type Target = StateCache;
+
+    fn deref(&self) -> &StateCache {
+        self.cache
+    }
+}
+
+impl DerefMut for Binding<'_> {
+    fn deref_mut(&mut self) -> &mut StateCache {
+        self.cache
+    }
+}
+
+impl Drop for Binding<'_> {
+    fn drop(&mut self) {
+        let cache = &mut *self.cache;
+        match self.previous {
+            Previous::Buffer(target, id) => cache.bind_buffer_id(target, id.unwrap_or(0)),
+            Previous::Texture {
+                unit,
+                target,
+                previous: Some((previous_target, id)),
+            } => {
+                if previous_target != target {
+                    cache.bind_texture_id(unit, target, 0);
+                }
+                cache.bind_texture_id(unit, previous_target, id);
+            }
+            Previous::Texture {
+                unit,
+                target,
+                previous: None,
+            } => cache.bind_texture_id(unit, target, 0),
+            Previous::VertexArray(id) => cache.bind_vertex_array_id(id.unwrap_or(0)),
+            Previous::Program(id) => cache.use_program_id(id.unwrap_or(0)),
+            Previous::Framebuffer { draw, read } => {
+                if let Some(draw) = draw {
+                    cache.bind_framebuffer_id(gl::DRAW_FRAMEBUFFER, draw.unwrap_or(0));
+                }
+                if let Some(read) = read {
+                    cache.bind_framebuffer_id(gl::READ_FRAMEBUFFER, read.unwrap_or(0));
+                }
+            }
+            Previous::Renderbuffer(id) => cache.bind_renderbuffer_id(id.unwrap_or(0)),
+        }
+    }
+}
+
+pub(crate) fn bind_buffer(
+    cache: &mut StateCache,
+    target: gl::types::GLenum,
+    id: gl::types::GLuint,
+) -> Binding<'_> {
+    let previous = cache.buffer(target);
+    cache.bind_buffer_id(target, id);
+    Binding::new(cache, Previous::Buffer(target, previous))
+}
+
+pub(crate) fn bind_texture(
+    cache: &mut StateCache,
+    unit: u32,
+    target: gl::types::GLenum,
+    id: gl::types::GLuint,
+) -> Binding<'_> {
+    let previous = cache.texture(unit);
+    cache.bind_texture_id(unit, target, id);
+    Binding::new(
+        cache,
+        Previous::Texture {
+            unit,
+            target,
+            previous,
+        },
+    )
+}
+
+pub(crate) fn bind_vertex_array(cache: &mut StateCache, id: gl::types::GLuint) -> Binding<'_> {
+    let previous = cache.vertex_array();
+    cache.bind_vertex_array_id(id);
+    Binding::new(cache, Previous::VertexArray(previous))
+}
+
+/// Only switches programs; the caller uploads anything the program defers to binding.
+pub(crate) fn use_program(cache: &mut StateCache, id: gl::types::GLuint) -> Binding<'_> {
+    let previous = cache.program();
+    cache.use_program_id(id);
+    Binding::new(cache, Previous::Program(previous))
+}
+
+/// `GL_FRAMEBUFFER` binds, and restores, both the draw and the read framebuffer.
+pub(crate) fn bind_framebuffer(
+    cache: &mut StateCache,
+    target: gl::types::GLenum,
+    id: gl::types::GLuint,
+) -> Binding<'_> {
+    let (draw, read) = cache.framebuffers();
+    let draw = matches!(target, gl::FRAMEBUFFER | gl::DRAW_FRAMEBUFFER).then_some(draw);
+    let read = matches!(target, gl::FRAMEBUFFER | gl::READ_FRAMEBUFFER).then_some(read);
+    cache.bind_framebuffer_id(target, id);
+    Binding::new(cache, Previous::Framebuffer { draw, read })
+}
+
+pub(crate) fn bind_renderbuffer(cache: &mut StateCache, id: gl::types::GLuint) -> Binding<'_> {
+    let previous = cache.renderbuffer();
+    cache.bind_renderbuffer_id(id);
+    Binding::new(cache, Previous::Renderbuffer(previous))
+}
@@ -0,0 +1,197 @@
+//! Window and renderer settings from `hello-gl.toml`.
+//!
+//! [`crate::app::run`] reads the file from the working directory (or from the path in
+//! `HELLO_GL_CONFIG`) before creating its window, so examples and apps don't have to
+//! hardcode window sizes or context versions. Every key is optional:
+//!
+//! ```toml
+//! title = "My demo"        # replaces the title passed to `run`
+//! width = 1280             # logical pixels
+//! height = 720
+//! fullscreen = false       # borderless fullscreen on the current monitor
+//! vsync = true
+//! max_fps = 144           # frame limiter; omit for no limit
+//! samples = 4              # MSAA samples; omit to take the most available
+//! gl_version = [4, 3]      # only this version instead of the fallback chain
+//! gles = false             # same as `--gles`
+//! robust = false           # recreate the context after GPU resets
+//! software = false         # ask Mesa for llvmpipe, e.g. on CI machines without a GPU
+//! validate = false         # check bindings before every draw
+//! anisotropy = 8.0         # of loaded textures; 1 turns anisotropic filtering off
+//! clear_color = [0.1, 0.1, 0.1, 1.0]
+//! font = "fonts/Inter.ttf" # for text drawn through `crate::quick`
+//! ```
+//!
+//! Environment variables named `HELLO_GL_` plus the upper-cased key override the file,
+//! e.g. `HELLO_GL_WIDTH=1920`, `HELLO_GL_VSYNC=false`, `HELLO_GL_GL_VERSION=3.3` or
+//! `HELLO_GL_CLEAR_COLOR=0,0,0,1`.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+/// The file looked for in the working directory.
+pub const FILE_NAME: &str = "hello-gl.toml";
+
+/// Names a settings file to read instead of [`FILE_NAME`].
+pub const PATH_ENV: &str = "HELLO_GL_CONFIG";
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub title: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    /// Target for [`crate::pacing`]'s frame limiter.
+    pub max_fps: Option<f32>,
+    /// `None` picks the config with the most samples.
+    pub samples: Option<u8>,
+    /// Requests exactly this `(major, minor)` version.
+    pub gl_version: Option<(u8, u8)>,
+    pub gles: bool,
+    /// Requests a context that reports GPU resets, so the runner can replace it.
+    pub robust: bool,
+    /// Calls [`crate::context::request_software`] before creating the context.
+    pub software: bool,
+    /// Enables [`crate::validate`] on the render thread.
+    pub validate: bool,
+    /// Passed to [`crate::texture::set_default_anisotropy`].
+    pub anisotropy: f32,
+    /// Set as the GL clear color before the app is created.
+    pub clear_color: [f32; 4],
+    /// TrueType font of [`crate::quick::Frame::text`]; `None` looks for a system font.
+    pub font: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            title: None,
+            width: 800,
+            height: 600,
+            fullscreen: false,
+            vsync: true,
+            max_fps: None,
+            samples: None,
+            gl_version: None,
+            gles: false,
+            robust: false,
+            software: false,
+            validate: false,
+            anisotropy: 1.0,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            font: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file if there is one, then applies environment overrides.
+    pub fn load() -> Result<Settings> {
+        let path =
+            std::env::var_os(PATH_ENV).map_or_else(|| PathBuf::from(FILE_NAME), PathBuf::from);
+        let mut settings = if path.exists() {
+            Settings::from_file(&path)?
+        } else if std::env::var_os(PATH_ENV).is_some() {
+            return Err(anyhow!("{} does not exist", path.display()));
+        } else {
+            Settings::default()
+        };
+        settings.apply_env(|key| std::env::var(key).ok())?;
+        Ok(settings)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Settings> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// Overrides fields from `HELLO_GL_*` variables looked up with `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T> {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid value for {}: {:?}", key, value))
+        }
+        fn list<T: std::str::FromStr>(key: &str, value: &str, sep: char) -> Result<Vec<T>> {
+            value.split(sep).map(|part| parse(key, part)).collect()
+        }
+
+        if let Some(value) = var("HELLO_GL_TITLE") {
+            self.title = Some(value);
+        }
+        if let Some(value) = var("HELLO_GL_WIDTH") {
+            self.width = parse("HELLO_GL_WIDTH", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_HEIGHT") {
+            self.height = parse("HELLO_GL_HEIGHT", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_FULLSCREEN") {
+            self.fullscreen = parse("HELLO_GL_FULLSCREEN", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_VSYNC") {
+            self.vsync = parse("HELLO_GL_VSYNC", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_MAX_FPS") {
+            self.max_fps = Some(parse("HELLO_GL_MAX_FPS", &value)?);
+        }
+        if let Some(value) = var("HELLO_GL_SAMPLES") {
+            self.samples = Some(parse("HELLO_GL_SAMPLES", &value)?);
+        }
+        if let Some(value) = var("HELLO_GL_GL_VERSION") {
+            match list::<u8>("HELLO_GL_GL_VERSION", &value, '.')?[..] {
+                [major, minor] => self.gl_version = Some((major, minor)),
+                _ => return Err(anyhow!("HELLO_GL_GL_VERSION must look like 4.5")),
+            }
+        }
+        if let Some(value) = var("HELLO_GL_GLES") {
+            self.gles = parse("HELLO_GL_GLES", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_ROBUST") {
+            self.robust = parse("HELLO_GL_ROBUST", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_SOFTWARE") {
+            self.software = parse("HELLO_GL_SOFTWARE", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_VALIDATE") {
+            self.validate = parse("HELLO_GL_VALIDATE", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_ANISOTROPY") {
+            self.anisotropy = parse("HELLO_GL_ANISOTROPY", &value)?;
+        }
+        if let Some(value) = var("HELLO_GL_CLEAR_COLOR") {
+            let color = list::<f32>("HELLO_GL_CLEAR_COLOR", &value, ',')?;
+            self.clear_color = match color[..] {
+                [r, g, b] => [r, g, b, 1.0],
+                [r, g, b, a] => [r, g, b, a],
+                _ => return Err(anyhow!("HELLO_GL_CLEAR_COLOR needs 3 or 4 components")),
+            };
+        }
+        if let Some(value) = var("HELLO_GL_FONT") {
+            self.font = Some(PathBuf::from(value));
+        }
+        Ok(())
+    }
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Installs `settings` as the process-wide settings, e.g. after applying command-line
+/// flags to [`Settings::load`]. Fails if [`get`] was already called.
+pub fn set(settings: Settings) -> Result<()> {
+    SETTINGS
+        .set(settings)
+        .map_err(|_| anyhow!("Settings are already loaded"))
+}
+
+/// The process-wide settings, loaded by the first call unless [`set`] came first. Panics if the settings file or
+/// an override is invalid.
+pub fn get() -> &'static Settings {
+    SETTINGS.get_or_init(|| Settings::load().unwrap())
+}
@@ -0,0 +1,776 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::ffi::CString;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::builtins::Locations;
+use crate::context::{self, Deletion, GlContext};
+use crate::debug;
+use crate::gl;
+use crate::scoped::{self, Binding};
+use crate::state::StateCache;
+use crate::validate;
+
+/// Default precisions inserted after the `#version` line on OpenGL ES, where fragment
+/// shaders have no default float precision and several sampler types none at all.
+pub const ES_PRECISION_PRELUDE: &str = "precision highp float;
+precision highp int;
+precision highp sampler2D;
+precision highp sampler3D;
+precision highp samplerCube;
+precision highp sampler2DArray;
+precision highp sampler2DShadow;
+precision highp isampler2D;
+precision highp usampler2D;
+";
+
+/// Adapts desktop GLSL to the target API. For ES, `#version 330 core` becomes
+/// `#version 300 es` (400-430 become 310 es, later versions 320 es) followed by any
+/// `#extension` lines and then [`ES_PRECISION_PRELUDE`]. Desktop sources are returned
+/// unchanged.
+pub fn translate_source(source: &str, es: bool) -> Cow<'_, str> {
+    if !es {
+        return Cow::Borrowed(source);
+    }
+    let Some(start) = source.find("#version") else {
+        return Cow::Borrowed(source);
+    };
+    let line_end = |from: usize| {
+        source[from..]
+            .find('\n')
+            .map_or(source.len(), |i| from + i + 1)
+    };
+    let version_end = line_end(start);
+    // Precision statements have to follow any `#extension` directives.
+    let mut end = version_end;
+    while end < source.len() && source[end..].trim_start().starts_with("#extension") {
+        end = line_end(end);
+    }
+    let version: u32 = source[start..version_end]
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(330);
+    let es_version = match version {
+        0..=330 => "300 es",
+        331..=430 => "310 es",
+        _ => "320 es",
+    };
+    Cow::Owned(format!(
+        "{}#version {}\n{}{}{}",
+        &source[..start],
+        es_version,
+        &source[version_end..end],
+        ES_PRECISION_PRELUDE,
+        &source[end..]
+    ))
+}
+
+/// The pipeline stage a [`CompileError`] comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+    /// Linking the shaders into a program.
+    Link,
+}
+
+impl Stage {
+    /// The stage of a shader type such as `GL_VERTEX_SHADER`.
+    pub fn from_kind(kind: gl::types::GLenum) -> Option<Stage> {
+        match kind {
+            gl::VERTEX_SHADER => Some(Stage::Vertex),
+            gl::TESS_CONTROL_SHADER => Some(Stage::TessControl),
+            gl::TESS_EVALUATION_SHADER => Some(Stage::TessEvaluation),
+            gl::GEOMETRY_SHADER => Some(Stage::Geometry),
+            gl::FRAGMENT_SHADER => Some(Stage::Fragment),
+            #[cfg(gl_compute)]
+            gl::COMPUTE_SHADER => Some(Stage::Compute),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Vertex => "vertex shader",
+            Stage::TessControl => "tessellation control shader",
+            Stage::TessEvaluation => "tessellation evaluation shader",
+            Stage::Geometry => "geometry shader",
+            Stage::Fragment => "fragment shader",
+            Stage::Compute => "compute shader",
+            Stage::Link => "program",
+        })
+    }
+}
+
+/// A shader that failed to compile or a program that failed to link, with the
+/// driver's info log. Returned inside the `anyhow::Error` of [`Shader::from_source`],
+/// [`Program::link`] and the constructors built on them; `downcast_ref` gets it back.
+///
+/// With [`crate::validate`] on, each log line pointing at a source line is followed by
+/// that line of the source the driver compiled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompileError {
+    pub stage: Stage,
+    /// Where the source came from, usually a file path, if the caller said.
+    pub source_name: Option<String>,
+    pub log: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.stage)?;
+        if let Some(name) = &self.source_name {
+            write!(f, " {}", name)?;
+        }
+        let verb = if self.stage == Stage::Link {
+            "link"
+        } else {
+            "compile"
+        };
+        write!(f, " failed to {}", verb)?;
+        if self.log.is_empty() {
+            Ok(())
+        } else {
+            write!(f, ":\n{}", self.log)
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// The `line` of error messages in the formats of Mesa (`0:12(5): error`), NVIDIA
+/// (`0(12) : error`) and AMD, Intel and ANGLE (`ERROR: 0:12: ...`).
+fn log_line_number(message: &str) -> Option<usize> {
+    let rest = message.trim_start();
+    let rest = ["ERROR: ", "WARNING: "]
+        .iter()
+        .find_map(|prefix| rest.strip_prefix(prefix))
+        .unwrap_or(rest);
+    let after_file = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    if after_file.len() == rest.len() {
+        return None;
+    }
+    let line = after_file
+        .strip_prefix(':')
+        .or_else(|| after_file.strip_prefix('('))?;
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    line[..digits].parse().ok()
+}
+
+/// Follows each line of `log` that points at a line of `source` with that line.
+pub(crate) fn annotate_log(log: &str, source: &str) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let mut annotated = String::new();
+    for message in log.lines() {
+        annotated.push_str(message);
+        annotated.push('\n');
+        let excerpt = log_line_number(message)
+            .and_then(|line| Some((line, *lines.get(line.checked_sub(1)?)?)));
+        if let Some((line, text)) = excerpt {
+            annotated.push_str(&format!("{:>6} | {}\n", line, text.trim_end()));
+        }
+    }
+    annotated.truncate(annotated.trim_end().len());
+    annotated
+}
+
+/// The info log of shader `id`, sized with `GL_INFO_LOG_LENGTH`.
+pub(crate) fn shader_info_log(id: gl::types::GLuint) -> String {
+    let mut length = 0;
+    unsafe {
+        gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut length);
+    }
+    let mut log = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        gl::GetShaderInfoLog(id, log.len() as i32, &mut written, log.as_mut_ptr().cast());
+    }
+    log.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&log).trim_end().to_string()
+}
+
+/// The info log of program `id`, sized with `GL_INFO_LOG_LENGTH`.
+pub(crate) fn program_info_log(id: gl::types::GLuint) -> String {
+    let mut length = 0;
+    unsafe {
+        gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, &mut length);
+    }
+    let mut log = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        gl::GetProgramInfoLog(id, log.len() as i32, &mut written, log.as_mut_ptr().cast());
+    }
+    log.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&log).trim_end().to_string()
+}
+
+/// Runs `glValidateProgram` on program `id`, failing with its info log.
+pub(crate) fn validate_program(id: gl::types::GLuint) -> Result<()> {
+    let mut valid = 0;
+    unsafe {
+        gl::ValidateProgram(id);
+        gl::GetProgramiv(id, gl::VALIDATE_STATUS, &mut valid);
+    }
+    if valid != 0 {
+        return Ok(());
+    }
+    let log = program_info_log(id);
+    if log.is_empty() {
+        Err(anyhow!("Program {} failed validation", id))
+    } else {
+        Err(anyhow!("Program {} failed validation:\n{}", id, log))
+    }
+}
+
+pub struct Shader(pub(crate) gl::types::GLuint, GlContext);
+
+impl Shader {
+    /// Compiles `source`, translated with [`translate_source`] for the current context.
+    /// Failures are [`CompileError`]s.
+    pub fn from_source(kind: gl::types::GLenum, source: &str) -> Result<Shader> {
+        Shader::compile(kind, source, None)
+    }
+
+    /// Like [`Shader::from_source`], naming the source, e.g. by its path, in errors.
+    pub fn from_named_source(kind: gl::types::GLenum, source: &str, name: &str) -> Result<Shader> {
+        Shader::compile(kind, source, Some(name))
+    }
+
+    fn compile(kind: gl::types::GLenum, source: &str, name: Option<&str>) -> Result<Shader> {
+        let gl_context = GlContext::current()?;
+        let stage =
+            Stage::from_kind(kind).ok_or_else(|| anyhow!("Unknown shader type 0x{:x}", kind))?;
+        let source = translate_source(source, context::info().es);
+        let id = unsafe { gl::CreateShader(kind) };
+        if id == 0 {
+            return Err(anyhow!("Failed to create {}", stage));
+        }
+        let mut success = 0;
+        unsafe {
+            gl::ShaderSource(
+                id,
+                1,
+                &(source.as_bytes().as_ptr().cast()),
+                &(source.len().try_into().unwrap()),
+            );
+            gl::CompileShader(id);
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        }
+        if success != 0 {
+            return Ok(Shader(id, gl_context));
+        }
+        let mut log = shader_info_log(id);
+        unsafe {
+            gl::DeleteShader(id);
+        }
+        if validate::is_enabled() {
+            log = annotate_log(&log, &source);
+        }
+        Err(CompileError {
+            stage,
+            source_name: name.map(str::to_string),
+            log,
+        }
+        .into())
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the shader in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::SHADER, self.0, label);
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        if self.1.is_current() {
+            unsafe {
+                gl::DeleteShader(self.0);
+            }
+        }
+    }
+}
+
+/// A uniform value that can be stored and uploaded later with [`Program::set_uniform`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uniform {
+    Int(i32),
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4([f32; 16]),
+}
+
+/// An active vertex shader input, from [`Program::active_attributes`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveAttribute {
+    pub name: String,
+    /// `-1` for built-in inputs such as `gl_VertexID`.
+    pub location: i32,
+    /// The GLSL type, e.g. `GL_FLOAT_VEC3`.
+    pub ty: gl::types::GLenum,
+    /// Array length; 1 unless the input is an array.
+    pub size: i32,
+}
+
+impl ActiveAttribute {
+    /// The scalar type (`GL_FLOAT`, `GL_INT` or `GL_UNSIGNED_INT`), the components per
+    /// location and the locations per array element: one per column for matrices.
+    pub fn shape(&self) -> (gl::types::GLenum, i32, i32) {
+        match self.ty {
+            gl::FLOAT => (gl::FLOAT, 1, 1),
+            gl::FLOAT_VEC2 => (gl::FLOAT, 2, 1),
+            gl::FLOAT_VEC3 => (gl::FLOAT, 3, 1),
+            gl::FLOAT_VEC4 => (gl::FLOAT, 4, 1),
+            gl::INT => (gl::INT, 1, 1),
+            gl::INT_VEC2 => (gl::INT, 2, 1),
+            gl::INT_VEC3 => (gl::INT, 3, 1),
+            gl::INT_VEC4 => (gl::INT, 4, 1),
+            gl::UNSIGNED_INT => (gl::UNSIGNED_INT, 1, 1),
+            gl::UNSIGNED_INT_VEC2 => (gl::UNSIGNED_INT, 2, 1),
+            gl::UNSIGNED_INT_VEC3 => (gl::UNSIGNED_INT, 3, 1),
+            gl::UNSIGNED_INT_VEC4 => (gl::UNSIGNED_INT, 4, 1),
+            gl::FLOAT_MAT2 => (gl::FLOAT, 2, 2),
+            gl::FLOAT_MAT2x3 => (gl::FLOAT, 3, 2),
+            gl::FLOAT_MAT2x4 => (gl::FLOAT, 4, 2),
+            gl::FLOAT_MAT3 => (gl::FLOAT, 3, 3),
+            gl::FLOAT_MAT3x2 => (gl::FLOAT, 2, 3),
+            gl::FLOAT_MAT3x4 => (gl::FLOAT, 4, 3),
+            gl::FLOAT_MAT4 => (gl::FLOAT, 4, 4),
+            gl::FLOAT_MAT4x2 => (gl::FLOAT, 2, 4),
+            gl::FLOAT_MAT4x3 => (gl::FLOAT, 3, 4),
+            _ => (gl::FLOAT, 4, 1),
+        }
+    }
+}
+
+/// The active inputs of the linked program `id`.
+pub(crate) fn active_attributes(id: gl::types::GLuint) -> Vec<ActiveAttribute> {
+    let mut count = 0;
+    unsafe {
+        gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTES, &mut count);
+    }
+    (0..count as u32)
+        .map(|index| {
+            let mut length = 0;
+            let mut size = 0;
+            let mut ty = 0;
+            let mut name = vec![0u8; 256];
+            unsafe {
+                gl::GetActiveAttrib(
+                    id,
+                    index,
+                    name.len() as i32,
+                    &mut length,
+                    &mut size,
+                    &mut ty,
+                    name.as_mut_ptr().cast(),
+                );
+            }
+            name.truncate(length as usize);
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let location = unsafe {
+                let name = CString::new(name.as_str()).unwrap();
+                gl::GetAttribLocation(id, name.as_ptr())
+            };
+            ActiveAttribute {
+                name,
+                location,
+                ty,
+                size,
+            }
+        })
+        .collect()
+}
+
+/// An active uniform outside uniform blocks, from [`Program::active_uniforms`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActiveUniform {
+    /// Array uniforms are named after their first element, e.g. `u_weights[0]`.
+    pub name: String,
+    pub location: i32,
+    /// The GLSL type, e.g. `GL_FLOAT_VEC3` or `GL_SAMPLER_2D`.
+    pub ty: gl::types::GLenum,
+    /// Array length; 1 unless the uniform is an array.
+    pub size: i32,
+}
+
+/// The active uniforms of the linked program `id`, leaving out members of uniform
+/// blocks, which have no location.
+pub(crate) fn active_uniforms(id: gl::types::GLuint) -> Vec<ActiveUniform> {
+    let mut count = 0;
+    unsafe {
+        gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+    }
+    (0..count as u32)
+        .filter_map(|index| {
+            let mut length = 0;
+            let mut size = 0;
+            let mut ty = 0;
+            let mut name = vec![0u8; 256];
+            unsafe {
+                gl::GetActiveUniform(
+                    id,
+                    index,
+                    name.len() as i32,
+                    &mut length,
+                    &mut size,
+                    &mut ty,
+                    name.as_mut_ptr().cast(),
+                );
+            }
+            name.truncate(length as usize);
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let location = unsafe {
+                let name = CString::new(name.as_str()).unwrap();
+                gl::GetUniformLocation(id, name.as_ptr())
+            };
+            (location >= 0).then_some(ActiveUniform {
+                name,
+                location,
+                ty,
+                size,
+            })
+        })
+        .collect()
+}
+
+/// Fails if a uniform block of the linked program `id` is larger than
+/// [`crate::context::Limits::max_uniform_block_size`]. Some drivers link such programs
+/// anyway and read garbage past the limit.
+fn check_uniform_blocks(id: gl::types::GLuint) -> Result<()> {
+    let max = context::limits().max_uniform_block_size;
+    let mut count = 0;
+    unsafe {
+        gl::GetProgramiv(id, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+    }
+    for index in 0..count as u32 {
+        let mut size = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(id, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        }
+        if size <= max {
+            continue;
+        }
+        let mut length = 0;
+        let mut name = vec![0u8; 256];
+        unsafe {
+            gl::GetActiveUniformBlockName(
+                id,
+                index,
+                name.len() as i32,
+                &mut length,
+                name.as_mut_ptr().cast(),
+            );
+        }
+        name.truncate(length as usize);
+        return Err(anyhow!(
+            "Uniform block `{}` is {} bytes, more than the {} the context allows",
+            String::from_utf8_lossy(&name),
+            size,
+            max
+        ));
+    }
+    Ok(())
+}
+
+/// A linked shader program. Declared [`crate::builtins`] are uploaded on every
+/// [`Program::use_program`].
+pub struct Program(pub(crate) gl::types::GLuint, Cell<Locations>, GlContext);
+
+impl Program {
+    pub fn new() -> Result<Program> {
+        let gl_context = GlContext::current()?;
+        let id = unsafe { gl::CreateProgram() };
+        if id == 0 {
+            Err(anyhow!("Failed to create program"))
+        } else {
+            Ok(Program(id, Cell::new(Locations::NONE), gl_context))
+        }
+    }
+
+    /// Compiles and links a program from vertex and fragment shader sources.
+    pub fn from_sources(vertex: &str, fragment: &str) -> Result<Program> {
+        let vertex = Shader::from_source(gl::VERTEX_SHADER, vertex)?;
+        let fragment = Shader::from_source(gl::FRAGMENT_SHADER, fragment)?;
+        let program = Program::new()?;
+        program.attach(&vertex);
+        program.attach(&fragment);
+        program.link()?;
+        Ok(program)
+    }
+
+    /// Like [`Program::from_sources`] with a geometry shader between the two stages.
+    pub fn from_sources_with_geometry(
+        vertex: &str,
+        geometry: &str,
+        fragment: &str,
+    ) -> Result<Program> {
+        let vertex = Shader::from_source(gl::VERTEX_SHADER, vertex)?;
+        let geometry = Shader::from_source(gl::GEOMETRY_SHADER, geometry)?;
+        let fragment = Shader::from_source(gl::FRAGMENT_SHADER, fragment)?;
+        let program = Program::new()?;
+        program.attach(&vertex);
+        program.attach(&geometry);
+        program.attach(&fragment);
+        program.link()?;
+        Ok(program)
+    }
+
+    /// Reads, compiles and links a program from vertex and fragment shader files, naming
+    /// the files in errors.
+    pub fn from_files(vertex: &Path, fragment: &Path) -> Result<Program> {
+        let read = |path: &Path| {
+            std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))
+        };
+        let vertex = Shader::from_named_source(
+            gl::VERTEX_SHADER,
+            &read(vertex)?,
+            &vertex.display().to_string(),
+        )?;
+        let fragment = Shader::from_named_source(
+            gl::FRAGMENT_SHADER,
+            &read(fragment)?,
+            &fragment.display().to_string(),
+        )?;
+        let program = Program::new()?;
+        program.attach(&vertex);
+        program.attach(&fragment);
+        program.link()?;
+        Ok(program)
+    }
+
+    /// Compiles and links a compute program. Requires GL 4.3.
+    #[cfg(gl_compute)]
+    pub fn from_compute(source: &str) -> Result<Program> {
+        let compute = Shader::from_source(gl::COMPUTE_SHADER, source)?;
+        let program = Program::new()?;
+        program.attach(&compute);
+        program.link()?;
+        Ok(program)
+    }
+
+    /// Compiles a vertex-only program whose `varyings` are captured by transform
+    /// feedback, interleaved into one buffer in the given order.
+    pub fn from_feedback_vertex(vertex: &str, varyings: &[&str]) -> Result<Program> {
+        let vertex = Shader::from_source(gl::VERTEX_SHADER, vertex)?;
+        let program = Program::new()?;
+        program.attach(&vertex);
+        program.transform_feedback_varyings(varyings, gl::INTERLEAVED_ATTRIBS);
+        program.link()?;
+        Ok(program)
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.0
+    }
+
+    /// Names the program in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::PROGRAM, self.0, label);
+    }
+
+    /// Selects the outputs captured by transform feedback. Takes effect at the next
+    /// [`Program::link`]. `buffer_mode` is `GL_INTERLEAVED_ATTRIBS` or
+    /// `GL_SEPARATE_ATTRIBS`.
+    pub fn transform_feedback_varyings(&self, varyings: &[&str], buffer_mode: gl::types::GLenum) {
+        let names: Vec<CString> = varyings
+            .iter()
+            .map(|name| CString::new(*name).unwrap())
+            .collect();
+        let pointers: Vec<*const gl::types::GLchar> =
+            names.iter().map(|name| name.as_ptr()).collect();
+        unsafe {
+            gl::TransformFeedbackVaryings(
+                self.0,
+                pointers.len() as i32,
+                pointers.as_ptr(),
+                buffer_mode,
+            );
+        }
+    }
+
+    pub fn attach(&self, shader: &Shader) {
+        unsafe {
+            gl::AttachShader(self.0, shader.0);
+        }
+    }
+
+    /// Links the attached shaders. Failures are [`CompileError`]s of [`Stage::Link`].
+    pub fn link(&self) -> Result<()> {
+        let mut success = 0;
+        unsafe {
+            gl::LinkProgram(self.0);
+            gl::GetProgramiv(self.0, gl::LINK_STATUS, &mut success);
+        }
+        if success == 0 {
+            return Err(CompileError {
+                stage: Stage::Link,
+                source_name: None,
+                log: program_info_log(self.0),
+            }
+            .into());
+        }
+        check_uniform_blocks(self.0)?;
+        self.1.set(Locations::query(self.0));
+        Ok(())
+    }
+
+    pub fn use_program(&self) {
+        unsafe {
+            gl::UseProgram(self.0);
+        }
+        self.1.get().upload();
+    }
+
+    /// Uses the program through `cache` until the guard is dropped, then returns to the
+    /// program the cache had in use before. See [`crate::scoped`].
+    pub fn use_scoped<'a>(&'a self, cache: &'a mut StateCache) -> Binding<'a> {
+        let binding = scoped::use_program(cache, self.0);
+        self.1.get().upload();
+        binding
+    }
+
+    /// Checks with `glValidateProgram` whether the program can run in the current GL
+    /// state, e.g. that no two samplers of different types read the same texture unit
+    /// and every stage is present, failing with the driver's info log. The result
+    /// depends on the bound textures and uniform values at the time of the call. Debug
+    /// builds do this before the first draw with each program; see [`crate::validate`].
+    pub fn validate(&self) -> Result<()> {
+        validate_program(self.0)
+    }
+
+    /// The vertex shader inputs the linked program reads.
+    pub fn active_attributes(&self) -> Vec<ActiveAttribute> {
+        active_attributes(self.0)
+    }
+
+    /// The uniforms the linked program reads, other than those in uniform blocks.
+    pub fn active_uniforms(&self) -> Vec<ActiveUniform> {
+        active_uniforms(self.0)
+    }
+
+    /// Returns the location of the uniform `name`, or `-1` if it is not active.
+    pub fn uniform_location(&self, name: &str) -> gl::types::GLint {
+        let name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(self.0, name.as_ptr()) }
+    }
+
+    /// Assigns the uniform block `name` to binding point `binding`. Does nothing if the
+    /// block is not active.
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            let index = gl::GetUniformBlockIndex(self.0, name.as_ptr());
+            if index != gl::INVALID_INDEX {
+                gl::UniformBlockBinding(self.0, index, binding);
+            }
+        }
+    }
+
+    // The uniform setters below apply to the program currently in use.
+
+    pub fn set_int(&self, name: &str, value: i32) {
+        unsafe {
+            gl::Uniform1i(self.uniform_location(name), value);
+        }
+    }
+
+    pub fn set_uint(&self, name: &str, value: u32) {
+        unsafe {
+            gl::Uniform1ui(self.uniform_location(name), value);
+        }
+    }
+
+    pub fn set_float(&self, name: &str, value: f32) {
+        unsafe {
+            gl::Uniform1f(self.uniform_location(name), value);
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: [f32; 2]) {
+        unsafe {
+            gl::Uniform2f(self.uniform_location(name), value[0], value[1]);
+        }
+    }
+
+    pub fn set_vec3(&self, name: &str, value: [f32; 3]) {
+        unsafe {
+            gl::Uniform3f(self.uniform_location(name), value[0], value[1], value[2]);
+        }
+    }
+
+    /// Uploads consecutive elements of a `vec3` array uniform, starting at element 0.
+    pub fn set_vec3_array(&self, name: &str, values: &[[f32; 3]]) {
+        unsafe {
+            gl::Uniform3fv(
+                self.uniform_location(name),
+                values.len() as i32,
+                values.as_ptr().cast(),
+            );
+        }
+    }
+
+    /// Uploads consecutive elements of a `vec4` array uniform, starting at element 0.
+    pub fn set_vec4_array(&self, name: &str, values: &[[f32; 4]]) {
+        unsafe {
+            gl::Uniform4fv(
+                self.uniform_location(name),
+                values.len() as i32,
+                values.as_ptr().cast(),
+            );
+        }
+    }
+
+    pub fn set_vec4(&self, name: &str, value: [f32; 4]) {
+        unsafe {
+            gl::Uniform4f(
+                self.uniform_location(name),
+                value[0],
+                value[1],
+                value[2],
+                value[3],
+            );
+        }
+    }
+
+    pub fn set_uniform(&self, name: &str, value: &Uniform) {
+        match *value {
+            Uniform::Int(v) => self.set_int(name, v),
+            Uniform::Float(v) => self.set_float(name, v),
+            Uniform::Vec2(v) => self.set_vec2(name, v),
+            Uniform::Vec3(v) => self.set_vec3(name, v),
+            Uniform::Vec4(v) => self.set_vec4(name, v),
+            Uniform::Mat4(ref v) => self.set_mat4(name, v),
+        }
+    }
+
+    /// Uploads a column-major 4x4 matrix.
+    pub fn set_mat4(&self, name: &str, value: &[f32; 16]) {
+        unsafe {
+            gl::UniformMatrix4fv(self.uniform_location(name), 1, gl::FALSE, value.as_ptr());
+        }
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        validate::forget_program(self.0);
+        self.2.delete(Deletion::Program(self.0));
+    }
+}
@@ -0,0 +1,161 @@
+//! Shader permutations selected by preprocessor defines.
+//!
+//! A [`ShaderVariants`] holds one vertex and one fragment source written with `#ifdef`
+//! blocks for optional features (`SKINNED`, `HAS_NORMAL_MAP`, ...). Each distinct
+//! [`Defines`] set compiles into its own program the first time it is asked for, with a
+//! prelude of the `#version` line, the `#extension` directives the context supports, and
+//! one `#define` per entry; later requests for the same set return the cached program.
+//! Sources must not have a `#version` line of their own.
+//!
+//! An extension the context supports is enabled and, as GLSL specifies, defines a macro
+//! of its own name, so sources test for it with `#ifdef GL_ARB_...`. The prelude is
+//! translated for ES like any other source, see [`crate::shader::translate_source`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::context;
+use crate::shader::Program;
+
+/// A set of `#define`s, each with an optional value. Ordered, so equal sets compare and
+/// hash equal however they were built.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+    pub fn new() -> Defines {
+        Defines::default()
+    }
+
+    /// Adds `name` with no value.
+    pub fn with(self, name: &str) -> Defines {
+        self.with_value(name, "")
+    }
+
+    /// Adds `name` if `enabled`, for building a set from feature flags.
+    pub fn with_if(self, name: &str, enabled: bool) -> Defines {
+        if enabled {
+            self.with(name)
+        } else {
+            self
+        }
+    }
+
+    /// Adds `name` defined as `value`, replacing any previous value.
+    pub fn with_value(mut self, name: &str, value: impl ToString) -> Defines {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl ToString) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.remove(name);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// One `#define` line per entry.
+    pub fn glsl(&self) -> String {
+        let mut glsl = String::new();
+        for (name, value) in &self.0 {
+            let _ = writeln!(glsl, "#define {} {}", name, value);
+        }
+        glsl
+    }
+}
+
+/// Vertex and fragment sources compiled per [`Defines`] set on demand.
+pub struct ShaderVariants {
+    vertex: String,
+    fragment: String,
+    version: u32,
+    extensions: Vec<String>,
+    programs: HashMap<Defines, Rc<Program>>,
+}
+
+impl ShaderVariants {
+    /// Sources for `#version 330 core`, without a `#version` line.
+    pub fn new(vertex: &str, fragment: &str) -> ShaderVariants {
+        ShaderVariants {
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            version: 330,
+            extensions: Vec::new(),
+            programs: HashMap::new(),
+        }
+    }
+
+    /// Compiles for desktop GLSL `version` (e.g. 430) instead; ES contexts get the
+    /// matching ES version.
+    pub fn version(mut self, version: u32) -> ShaderVariants {
+        self.version = version;
+        self
+    }
+
+    /// Enables extension `name` in every variant compiled on a context that supports it.
+    pub fn extension(mut self, name: &str) -> ShaderVariants {
+        self.extensions.push(name.to_string());
+        self
+    }
+
+    /// The lines every source of the `defines` variant starts with on the current
+    /// context.
+    pub fn prelude(&self, defines: &Defines) -> String {
+        let mut prelude = format!("#version {} core\n", self.version);
+        for extension in &self.extensions {
+            if context::has_extension(extension) {
+                let _ = writeln!(prelude, "#extension {} : enable", extension);
+            }
+        }
+        prelude.push_str(&defines.glsl());
+        prelude
+    }
+
+    /// The program for `defines`, compiling it on first use.
+    pub fn get(&mut self, defines: &Defines) -> Result<Rc<Program>> {
+        if let Some(program) = self.programs.get(defines) {
+            return Ok(program.clone());
+        }
+        let prelude = self.prelude(defines);
+        let program = Rc::new(Program::from_sources(
+            &format!("{}{}", prelude, self.vertex),
+            &format!("{}{}", prelude, self.fragment),
+        )?);
+        tracing::debug!("Compiled shader variant {:?}", defines);
+        self.programs.insert(defines.clone(), program.clone());
+        Ok(program)
+    }
+
+    /// Compiled variants.
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    /// Replaces the sources and drops every compiled variant, e.g. on hot reload.
+    /// Programs still held elsewhere stay valid.
+    pub fn set_sources(&mut self, vertex: &str, fragment: &str) {
+        self.vertex = vertex.to_string();
+        self.fragment = fragment.to_string();
+        self.programs.clear();
+    }
+}
@@ -0,0 +1,911 @@
+//! Directional-light shadow mapping.
+//!
+//! Render shadow casters into [`ShadowMap`] between [`ShadowMap::begin`] and
+//! [`ShadowMap::end`] using [`ShadowMap::depth_program`], then call [`ShadowMap::apply`]
+//! on the lit program and sample it with the functions in [`SHADOW_GLSL`].
+//!
+//! A single map covering a large scene leaves few texels near the camera.
+//! [`CascadedShadowMap`] instead splits the view frustum by distance into up to
+//! [`MAX_CASCADES`] slices, each with its own light projection and layer of a depth
+//! texture array, sampled with [`CASCADED_SHADOW_GLSL`].
+//!
+//! Spot lights use a [`ShadowMap`] with a perspective projection (see
+//! [`ShadowMap::set_spot`]). Point lights cast shadows in every direction, which a
+//! [`PointShadowMap`] captures in a depth cube map sampled with [`POINT_SHADOW_GLSL`].
+
+use anyhow::{anyhow, Result};
+use bytemuck::{Pod, Zeroable};
+
+use crate::buffer::Buffer;
+use crate::context;
+use crate::depth;
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::material::Light;
+use crate::math::{Mat4, Vec3};
+use crate::shader::Program;
+use crate::texture::Texture;
+use crate::viewport::Camera;
+
+/// Maximum number of cascades of a [`CascadedShadowMap`].
+pub const MAX_CASCADES: usize = 4;
+
+/// Uniform block binding of the `ShadowCascades` block in [`CASCADED_SHADOW_GLSL`].
+pub const CASCADES_BINDING: u32 = 3;
+
+/// GLSL helpers for sampling a shadow map. Paste after the `#version` line of a fragment
+/// shader; it declares the `u_shadow_*` uniforms set by [`ShadowMap::apply`].
+pub const SHADOW_GLSL: &str = r#"
+uniform sampler2DShadow u_shadow_map;
+uniform float u_shadow_bias;
+uniform float u_shadow_normal_bias;
+uniform int u_shadow_pcf_radius;
+
+// Returns 1.0 when fully lit and 0.0 when fully shadowed.
+float shadow_factor(vec4 light_space_pos, vec3 normal, vec3 light_dir) {
+    vec3 p = light_space_pos.xyz / light_space_pos.w * 0.5 + 0.5;
+    if (p.z > 1.0) {
+        return 1.0;
+    }
+    float slope = 1.0 - max(dot(normalize(normal), -normalize(light_dir)), 0.0);
+    float bias = u_shadow_bias + u_shadow_normal_bias * slope;
+    vec2 texel = 1.0 / vec2(textureSize(u_shadow_map, 0));
+    float lit = 0.0;
+    int taps = 0;
+    for (int x = -u_shadow_pcf_radius; x <= u_shadow_pcf_radius; ++x) {
+        for (int y = -u_shadow_pcf_radius; y <= u_shadow_pcf_radius; ++y) {
+            lit += texture(u_shadow_map, vec3(p.xy + vec2(x, y) * texel, p.z - bias));
+            taps++;
+        }
+    }
+    return lit / float(taps);
+}
+"#;
+
+/// GLSL helpers for sampling a [`CascadedShadowMap`]. Paste after the `#version` line of
+/// a fragment shader; it declares the `ShadowCascades` block and the `u_cascade_*`
+/// uniforms set by [`CascadedShadowMap::apply`].
+///
+/// Both functions take `view_depth`, the fragment's distance in front of the camera:
+/// `-(view * world_position).z`.
+pub const CASCADED_SHADOW_GLSL: &str = r#"
+#define MAX_CASCADES 4
+layout (std140) uniform ShadowCascades {
+    mat4 u_cascade_light_space[MAX_CASCADES];
+    // The far view depth of each cascade.
+    vec4 u_cascade_splits;
+    int u_cascade_count;
+};
+uniform sampler2DArrayShadow u_cascade_map;
+uniform float u_cascade_bias;
+uniform float u_cascade_normal_bias;
+uniform int u_cascade_pcf_radius;
+uniform bool u_cascade_debug;
+
+int cascade_index(float view_depth) {
+    for (int i = 0; i < u_cascade_count - 1; ++i) {
+        if (view_depth < u_cascade_splits[i]) {
+            return i;
+        }
+    }
+    return u_cascade_count - 1;
+}
+
+// Returns 1.0 when fully lit and 0.0 when fully shadowed.
+float cascaded_shadow_factor(vec3 world_pos, float view_depth, vec3 normal, vec3 light_dir) {
+    int cascade = cascade_index(view_depth);
+    if (view_depth > u_cascade_splits[cascade]) {
+        return 1.0;
+    }
+    vec4 light_space_pos = u_cascade_light_space[cascade] * vec4(world_pos, 1.0);
+    vec3 p = light_space_pos.xyz / light_space_pos.w * 0.5 + 0.5;
+    if (p.z > 1.0) {
+        return 1.0;
+    }
+    float slope = 1.0 - max(dot(normalize(normal), -normalize(light_dir)), 0.0);
+    // Farther cascades spread each texel over more of the scene.
+    float bias = (u_cascade_bias + u_cascade_normal_bias * slope) * float(cascade + 1);
+    vec2 texel = 1.0 / vec2(textureSize(u_cascade_map, 0).xy);
+    float lit = 0.0;
+    int taps = 0;
+    for (int x = -u_cascade_pcf_radius; x <= u_cascade_pcf_radius; ++x) {
+        for (int y = -u_cascade_pcf_radius; y <= u_cascade_pcf_radius; ++y) {
+            vec2 st = p.xy + vec2(x, y) * texel;
+            lit += texture(u_cascade_map, vec4(st, float(cascade), p.z - bias));
+            taps++;
+        }
+    }
+    return lit / float(taps);
+}
+
+// A color to multiply the lit result by: a tint per cascade while debugging, else white.
+vec3 cascade_debug_color(float view_depth) {
+    if (!u_cascade_debug) {
+        return vec3(1.0);
+    }
+    const vec3 tints[MAX_CASCADES] = vec3[](
+        vec3(1.0, 0.4, 0.4),
+        vec3(0.4, 1.0, 0.4),
+        vec3(0.4, 0.4, 1.0),
+        vec3(1.0, 1.0, 0.4)
+    );
+    return tints[cascade_index(view_depth)];
+}
+"#;
+
+/// GLSL helpers for sampling a [`PointShadowMap`]. Paste after the `#version` line of a
+/// fragment shader; it declares the `u_point_shadow_*` uniforms set by
+/// [`PointShadowMap::apply`].
+pub const POINT_SHADOW_GLSL: &str = r#"
+uniform samplerCubeShadow u_point_shadow_map;
+uniform vec3 u_point_shadow_position;
+uniform float u_point_shadow_far;
+uniform float u_point_shadow_size;
+uniform float u_point_shadow_bias;
+uniform float u_point_shadow_normal_bias;
+uniform int u_point_shadow_pcf_radius;
+
+// Returns 1.0 when fully lit and 0.0 when fully shadowed.
+float point_shadow_factor(vec3 world_pos, vec3 normal) {
+    vec3 to_fragment = world_pos - u_point_shadow_position;
+    float light_distance = length(to_fragment);
+    if (light_distance >= u_point_shadow_far) {
+        return 1.0;
+    }
+    float slope = 1.0 - max(dot(normalize(normal), -to_fragment / light_distance), 0.0);
+    float bias = u_point_shadow_bias + u_point_shadow_normal_bias * slope;
+    float depth = light_distance / u_point_shadow_far - bias;
+    if (u_point_shadow_pcf_radius == 0) {
+        return texture(u_point_shadow_map, vec4(to_fragment, depth));
+    }
+    // A face texel covers about 2 * distance / size world units at this distance.
+    float disk = float(u_point_shadow_pcf_radius) * 2.0 * light_distance / u_point_shadow_size;
+    const vec3 offsets[20] = vec3[](
+        vec3(1, 1, 1), vec3(1, -1, 1), vec3(-1, -1, 1), vec3(-1, 1, 1),
+        vec3(1, 1, -1), vec3(1, -1, -1), vec3(-1, -1, -1), vec3(-1, 1, -1),
+        vec3(1, 1, 0), vec3(1, -1, 0), vec3(-1, -1, 0), vec3(-1, 1, 0),
+        vec3(1, 0, 1), vec3(-1, 0, 1), vec3(1, 0, -1), vec3(-1, 0, -1),
+        vec3(0, 1, 1), vec3(0, -1, 1), vec3(0, -1, -1), vec3(0, 1, -1)
+    );
+    float lit = 0.0;
+    for (int i = 0; i < 20; ++i) {
+        lit += texture(u_point_shadow_map, vec4(to_fragment + offsets[i] * disk, depth));
+    }
+    return lit / 20.0;
+}
+"#;
+
+const DEPTH_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+uniform mat4 u_light_space;
+uniform mat4 u_model;
+void main() {
+    gl_Position = u_light_space * u_model * vec4(pos, 1.0);
+}
+"#;
+
+const DEPTH_FRAGMENT_SHADER: &str = r#"#version 330 core
+void main() {}
+"#;
+
+/// Passes the world position on to [`POINT_GEOMETRY_SHADER`].
+const POINT_WORLD_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+uniform mat4 u_model;
+void main() {
+    gl_Position = u_model * vec4(pos, 1.0);
+}
+"#;
+
+/// Emits every triangle once per cube face, selected with `gl_Layer`.
+const POINT_GEOMETRY_SHADER: &str = r#"#version 330 core
+layout (triangles) in;
+layout (triangle_strip, max_vertices = 18) out;
+uniform mat4 u_face_matrices[6];
+out vec3 v_world;
+void main() {
+    for (int face = 0; face < 6; ++face) {
+        gl_Layer = face;
+        for (int i = 0; i < 3; ++i) {
+            v_world = gl_in[i].gl_Position.xyz;
+            gl_Position = u_face_matrices[face] * gl_in[i].gl_Position;
+            EmitVertex();
+        }
+        EndPrimitive();
+    }
+}
+"#;
+
+/// Renders one cube face per pass, for contexts without geometry shaders.
+const POINT_FACE_VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 pos;
+uniform mat4 u_model;
+uniform mat4 u_face_matrix;
+out vec3 v_world;
+void main() {
+    vec4 world = u_model * vec4(pos, 1.0);
+    v_world = world.xyz;
+    gl_Position = u_face_matrix * world;
+}
+"#;
+
+/// Stores linear distance to the light rather than projected depth, so one comparison
+/// works for every face.
+const POINT_DEPTH_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec3 v_world;
+uniform vec3 u_light_position;
+uniform float u_far;
+void main() {
+    gl_FragDepth = length(v_world - u_light_position) / u_far;
+}
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowConfig {
+    /// Constant depth bias applied when comparing.
+    pub bias: f32,
+    /// Additional bias scaled by how much the surface faces away from the light.
+    pub normal_bias: f32,
+    /// Percentage-closer filtering radius in texels; 0 means a single hardware-filtered tap.
+    pub pcf_radius: i32,
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        ShadowConfig {
+            bias: 0.0005,
+            normal_bias: 0.002,
+            pcf_radius: 1,
+        }
+    }
+}
+
+pub struct ShadowMap {
+    framebuffer: Framebuffer,
+    depth: Texture,
+    depth_program: Program,
+    size: i32,
+    light_space: Mat4,
+    pub config: ShadowConfig,
+}
+
+impl ShadowMap {
+    pub fn new(size: i32) -> Result<ShadowMap> {
+        let depth = Texture::new(gl::TEXTURE_2D)?;
+        depth.bind();
+        depth.image_2d(
+            0,
+            gl::DEPTH_COMPONENT24,
+            size,
+            size,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            None,
+        );
+        depth.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        depth.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        depth.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+        depth.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+        depth.parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+        depth.parameter(gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+        unsafe {
+            let border = [1.0f32; 4];
+            gl::TexParameterfv(gl::TEXTURE_2D, gl::TEXTURE_BORDER_COLOR, border.as_ptr());
+        }
+        depth.unbind();
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_texture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &depth, 0);
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+        let status = framebuffer.check_status(gl::FRAMEBUFFER);
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        Ok(ShadowMap {
+            framebuffer,
+            depth,
+            depth_program: Program::from_sources(DEPTH_VERTEX_SHADER, DEPTH_FRAGMENT_SHADER)?,
+            size,
+            light_space: Mat4::IDENTITY,
+            config: ShadowConfig::default(),
+        })
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn depth_texture(&self) -> &Texture {
+        &self.depth
+    }
+
+    pub fn light_space(&self) -> Mat4 {
+        self.light_space
+    }
+
+    /// Fits an orthographic light projection around a sphere at `center` with `radius`,
+    /// looking along `direction`.
+    pub fn set_directional(&mut self, direction: Vec3, center: Vec3, radius: f32) {
+        let direction = direction.normalize();
+        let up = if direction.abs().dot(Vec3::Y) > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let view = Mat4::look_at_rh(center - direction * radius * 2.0, center, up);
+        let projection =
+            Mat4::orthographic_rh_gl(-radius, radius, -radius, radius, radius, radius * 3.0);
+        self.light_space = projection * view;
+    }
+
+    /// Fits a perspective light projection to the cone of a spot light at `position`
+    /// pointing along `direction`, with half-angle `outer_angle` in radians, out to
+    /// `range`.
+    pub fn set_spot(&mut self, position: Vec3, direction: Vec3, outer_angle: f32, range: f32) {
+        let direction = direction.normalize();
+        let up = if direction.abs().dot(Vec3::Y) > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let view = Mat4::look_at_rh(position, position + direction, up);
+        let fov = (outer_angle * 2.0).min(170f32.to_radians());
+        let projection = Mat4::perspective_rh_gl(fov, 1.0, range * 0.01, range);
+        self.light_space = projection * view;
+    }
+
+    /// Fits the light projection to a directional or spot `light`. `center` and
+    /// `radius` bound the shadowed part of the scene for directional lights. Point lights
+    /// need a [`PointShadowMap`].
+    pub fn set_light(&mut self, light: &Light, center: Vec3, radius: f32) -> Result<()> {
+        match *light {
+            Light::Directional { direction, .. } => {
+                self.set_directional(direction, center, radius);
+            }
+            Light::Spot {
+                position,
+                direction,
+                range,
+                outer_angle,
+                ..
+            } => self.set_spot(position, direction, outer_angle, range),
+            Light::Point { .. } => {
+                return Err(anyhow!("Point light shadows need a PointShadowMap"));
+            }
+        }
+        Ok(())
+    }
+
+    /// The depth-only program used during the shadow pass. It expects `u_model` to be set
+    /// for each draw; `u_light_space` is set by [`ShadowMap::begin`].
+    pub fn depth_program(&self) -> &Program {
+        &self.depth_program
+    }
+
+    /// Binds the shadow framebuffer and clears it. Draw shadow casters afterwards.
+    pub fn begin(&self) {
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        depth::begin_standard();
+        unsafe {
+            gl::Viewport(0, 0, self.size, self.size);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(1.1, 4.0);
+        }
+        self.depth_program.use_program();
+        self.depth_program
+            .set_mat4("u_light_space", &self.light_space.to_cols_array());
+    }
+
+    pub fn end(&self) {
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+        }
+        depth::end_standard();
+        self.framebuffer.unbind(gl::FRAMEBUFFER);
+    }
+
+    /// Binds the shadow map to texture `unit` and sets the `u_shadow_*` uniforms and
+    /// `u_light_space` on `program`, which must be in use.
+    pub fn apply(&self, program: &Program, unit: u32) {
+        self.depth.bind_unit(unit);
+        program.set_int("u_shadow_map", unit as i32);
+        program.set_float("u_shadow_bias", self.config.bias);
+        program.set_float("u_shadow_normal_bias", self.config.normal_bias);
+        program.set_int("u_shadow_pcf_radius", self.config.pcf_radius);
+        program.set_mat4("u_light_space", &self.light_space.to_cols_array());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CascadeConfig {
+    /// Blend between uniform (0.0) and logarithmic (1.0) split distances. Logarithmic
+    /// splits match perspective foreshortening but leave the last cascades very large.
+    pub split_lambda: f32,
+    /// Distance from the camera beyond which nothing is shadowed; clamped to the
+    /// camera's far plane.
+    pub max_distance: f32,
+    /// How far towards the light beyond each cascade's bounds casters are still
+    /// captured, for tall objects outside the view casting shadows into it.
+    pub caster_margin: f32,
+    /// Tints each cascade a different color through `cascade_debug_color`.
+    pub debug: bool,
+}
+
+impl Default for CascadeConfig {
+    fn default() -> Self {
+        CascadeConfig {
+            split_lambda: 0.75,
+            max_distance: 100.0,
+            caster_margin: 20.0,
+            debug: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CascadesStd140 {
+    light_space: [[f32; 16]; MAX_CASCADES],
+    splits: [f32; 4],
+    count: i32,
+    _padding: [i32; 3],
+}
+
+/// A directional-light shadow map split into cascades along the view direction.
+///
+/// Each frame, call [`CascadedShadowMap::update`] with the camera, then for every cascade
+/// draw the shadow casters between [`CascadedShadowMap::begin`] and
+/// [`CascadedShadowMap::end`] using [`CascadedShadowMap::depth_program`].
+pub struct CascadedShadowMap {
+    framebuffer: Framebuffer,
+    depth: Texture,
+    depth_program: Program,
+    buffer: Buffer,
+    size: i32,
+    count: usize,
+    light_spaces: [Mat4; MAX_CASCADES],
+    splits: [f32; MAX_CASCADES],
+    pub config: ShadowConfig,
+    pub cascades: CascadeConfig,
+}
+
+impl CascadedShadowMap {
+    /// Creates `count` cascades of `size × size` texels each.
+    pub fn new(size: i32, count: usize) -> Result<CascadedShadowMap> {
+        if count == 0 || count > MAX_CASCADES {
+            return Err(anyhow!(
+                "cascade count {} is outside 1..={}",
+                count,
+                MAX_CASCADES
+            ));
+        }
+        let depth = Texture::new(gl::TEXTURE_2D_ARRAY)?;
+        depth.bind();
+        depth.image_3d(
+            0,
+            gl::DEPTH_COMPONENT24,
+            size,
+            size,
+            count as i32,
+            gl::DEPTH_COMPONENT,
+            gl::FLOAT,
+            None,
+        );
+        depth.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        depth.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        depth.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+        depth.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+        depth.parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+        depth.parameter(gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+        unsafe {
+            let border = [1.0f32; 4];
+            gl::TexParameterfv(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_BORDER_COLOR,
+                border.as_ptr(),
+            );
+        }
+        depth.unbind();
+        depth.label("shadow cascades");
+
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        framebuffer.attach_texture_layer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &depth, 0, 0);
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+        let status = framebuffer.check_status(gl::FRAMEBUFFER);
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        let buffer = Buffer::new()?;
+        buffer.bind(gl::UNIFORM_BUFFER);
+        buffer.allocate(
+            gl::UNIFORM_BUFFER,
+            std::mem::size_of::<CascadesStd140>(),
+            gl::DYNAMIC_DRAW,
+        );
+        buffer.unbind(gl::UNIFORM_BUFFER);
+
+        Ok(CascadedShadowMap {
+            framebuffer,
+            depth,
+            depth_program: Program::from_sources(DEPTH_VERTEX_SHADER, DEPTH_FRAGMENT_SHADER)?,
+            buffer,
+            size,
+            count,
+            light_spaces: [Mat4::IDENTITY; MAX_CASCADES],
+            splits: [0.0; MAX_CASCADES],
+            config: ShadowConfig::default(),
+            cascades: CascadeConfig::default(),
+        })
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn cascade_count(&self) -> usize {
+        self.count
+    }
+
+    /// The depth texture array, one layer per cascade.
+    pub fn depth_texture(&self) -> &Texture {
+        &self.depth
+    }
+
+    pub fn light_space(&self, cascade: usize) -> Mat4 {
+        self.light_spaces[cascade]
+    }
+
+    /// The far view depth of each cascade, nearest first.
+    pub fn splits(&self) -> &[f32] {
+        &self.splits[..self.count]
+    }
+
+    /// Splits the view frustum of `camera` and fits a light projection looking along
+    /// `direction` around each slice, then uploads the matrices.
+    ///
+    /// Each projection bounds its slice with a sphere and snaps to whole texels, so
+    /// shadow edges don't shimmer as the camera moves or turns.
+    pub fn update(&mut self, camera: &Camera, aspect: f32, direction: Vec3) {
+        let near = camera.near;
+        let far = camera.far.min(self.cascades.max_distance).max(near);
+        let lambda = self.cascades.split_lambda;
+        let count = self.count;
+        for (i, split) in self.splits[..count].iter_mut().enumerate() {
+            let p = (i + 1) as f32 / count as f32;
+            let logarithmic = near * (far / near).powf(p);
+            let uniform = near + (far - near) * p;
+            *split = lambda * logarithmic + (1.0 - lambda) * uniform;
+        }
+
+        let forward = (camera.target - camera.eye).normalize();
+        let right = forward.cross(camera.up).normalize();
+        let up = right.cross(forward);
+        let tan_y = (camera.fov_y * 0.5).tan();
+        let tan_x = tan_y * aspect;
+
+        let direction = direction.normalize();
+        let light_up = if direction.abs().dot(Vec3::Y) > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let light_view = Mat4::look_at_rh(Vec3::ZERO, direction, light_up);
+
+        let mut slice_near = near;
+        for (i, &slice_far) in self.splits[..count].iter().enumerate() {
+            let corners = [slice_near, slice_far].into_iter().flat_map(|depth| {
+                let center = camera.eye + forward * depth;
+                let (x, y) = (right * tan_x * depth, up * tan_y * depth);
+                [
+                    center - x - y,
+                    center + x - y,
+                    center - x + y,
+                    center + x + y,
+                ]
+            });
+            let corners: Vec<Vec3> = corners.collect();
+            let center = corners.iter().copied().sum::<Vec3>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| corner.distance(center))
+                .fold(0.0f32, f32::max);
+            // Rounding keeps the texel size constant while the camera turns.
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            let texel = 2.0 * radius / self.size as f32;
+            let light_center = light_view.transform_point3(center);
+            let x = (light_center.x / texel).floor() * texel;
+            let y = (light_center.y / texel).floor() * texel;
+            let projection = Mat4::orthographic_rh_gl(
+                x - radius,
+                x + radius,
+                y - radius,
+                y + radius,
+                -light_center.z - radius - self.cascades.caster_margin,
+                -light_center.z + radius,
+            );
+            self.light_spaces[i] = projection * light_view;
+            slice_near = slice_far;
+        }
+
+        let mut block = CascadesStd140 {
+            light_space: [[0.0; 16]; MAX_CASCADES],
+            splits: [0.0; 4],
+            count: count as i32,
+            _padding: [0; 3],
+        };
+        for (i, light_space) in self.light_spaces[..count].iter().enumerate() {
+            block.light_space[i] = light_space.to_cols_array();
+        }
+        block.splits[..count].copy_from_slice(&self.splits[..count]);
+        self.buffer.bind(gl::UNIFORM_BUFFER);
+        self.buffer
+            .sub_data(gl::UNIFORM_BUFFER, 0, bytemuck::bytes_of(&block));
+        self.buffer.unbind(gl::UNIFORM_BUFFER);
+    }
+
+    /// The depth-only program used during the shadow pass. It expects `u_model` to be set
+    /// for each draw; `u_light_space` is set by [`CascadedShadowMap::begin`].
+    pub fn depth_program(&self) -> &Program {
+        &self.depth_program
+    }
+
+    /// Binds the layer of `cascade` and clears it. Draw shadow casters afterwards.
+    pub fn begin(&self, cascade: usize) {
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        self.framebuffer.attach_texture_layer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            &self.depth,
+            0,
+            cascade as i32,
+        );
+        depth::begin_standard();
+        unsafe {
+            gl::Viewport(0, 0, self.size, self.size);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(1.1, 4.0);
+        }
+        self.depth_program.use_program();
+        self.depth_program
+            .set_mat4("u_light_space", &self.light_spaces[cascade].to_cols_array());
+    }
+
+    pub fn end(&self) {
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+        }
+        depth::end_standard();
+        self.framebuffer.unbind(gl::FRAMEBUFFER);
+    }
+
+    /// Binds the depth array to texture `unit` and the cascades block at
+    /// [`CASCADES_BINDING`], and sets the `u_cascade_*` uniforms on `program`, which must
+    /// be in use.
+    pub fn apply(&self, program: &Program, unit: u32) {
+        self.depth.bind_unit(unit);
+        self.buffer.bind_base(gl::UNIFORM_BUFFER, CASCADES_BINDING);
+        program.bind_uniform_block("ShadowCascades", CASCADES_BINDING);
+        program.set_int("u_cascade_map", unit as i32);
+        program.set_float("u_cascade_bias", self.config.bias);
+        program.set_float("u_cascade_normal_bias", self.config.normal_bias);
+        program.set_int("u_cascade_pcf_radius", self.config.pcf_radius);
+        program.set_int("u_cascade_debug", self.cascades.debug as i32);
+    }
+}
+
+/// Whether [`PointShadowMap`] can render all faces in one pass. Geometry shaders are core
+/// on desktop GL 3.2 but need ES 3.2, beyond the ES 3.0 the shaders are translated to.
+fn layered_rendering_supported() -> bool {
+    !context::info().es
+}
+
+/// The view directions and up vectors of the cube faces, in GL's face order.
+pub(crate) const CUBE_FACES: [(Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::NEG_Y),
+    (Vec3::NEG_X, Vec3::NEG_Y),
+    (Vec3::Y, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::NEG_Z),
+    (Vec3::Z, Vec3::NEG_Y),
+    (Vec3::NEG_Z, Vec3::NEG_Y),
+];
+
+/// An omnidirectional shadow map for a point light: a depth cube map holding the
+/// distance from the light to the nearest caster in every direction.
+///
+/// Where geometry shaders are available, casters are drawn once and replicated to all
+/// six faces; otherwise they are drawn once per face. Either way, draw them for each of
+/// the [`PointShadowMap::passes`] between [`PointShadowMap::begin`] and
+/// [`PointShadowMap::end`] using [`PointShadowMap::depth_program`].
+pub struct PointShadowMap {
+    framebuffer: Framebuffer,
+    depth: Texture,
+    depth_program: Program,
+    layered: bool,
+    size: i32,
+    position: Vec3,
+    far: f32,
+    face_matrices: [Mat4; 6],
+    pub config: ShadowConfig,
+}
+
+impl PointShadowMap {
+    /// Creates a cube map with `size × size` texel faces.
+    pub fn new(size: i32) -> Result<PointShadowMap> {
+        let depth = Texture::new(gl::TEXTURE_CUBE_MAP)?;
+        depth.bind();
+        for face in 0..6 {
+            depth.image_cube_face(
+                face,
+                0,
+                gl::DEPTH_COMPONENT24,
+                size,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                None,
+            );
+        }
+        depth.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        depth.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+            depth.parameter(wrap, gl::CLAMP_TO_EDGE as i32);
+        }
+        depth.parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+        depth.parameter(gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+        depth.unbind();
+        depth.label("point shadow");
+
+        let layered = layered_rendering_supported();
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::FRAMEBUFFER);
+        if layered {
+            framebuffer.attach_layered(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &depth, 0);
+        } else {
+            framebuffer.attach_cube_face(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, &depth, 0, 0);
+        }
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+        let status = framebuffer.check_status(gl::FRAMEBUFFER);
+        framebuffer.unbind(gl::FRAMEBUFFER);
+        status?;
+
+        let depth_program = if layered {
+            Program::from_sources_with_geometry(
+                POINT_WORLD_VERTEX_SHADER,
+                POINT_GEOMETRY_SHADER,
+                POINT_DEPTH_FRAGMENT_SHADER,
+            )?
+        } else {
+            Program::from_sources(POINT_FACE_VERTEX_SHADER, POINT_DEPTH_FRAGMENT_SHADER)?
+        };
+
+        let mut shadow = PointShadowMap {
+            framebuffer,
+            depth,
+            depth_program,
+            layered,
+            size,
+            position: Vec3::ZERO,
+            far: 1.0,
+            face_matrices: [Mat4::IDENTITY; 6],
+            config: ShadowConfig::default(),
+        };
+        shadow.set_point(Vec3::ZERO, 10.0);
+        Ok(shadow)
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn depth_texture(&self) -> &Texture {
+        &self.depth
+    }
+
+    /// Whether all faces are rendered in one pass with a geometry shader.
+    pub fn is_layered(&self) -> bool {
+        self.layered
+    }
+
+    /// Number of passes casters must be drawn in: 1 when layered, otherwise 6.
+    pub fn passes(&self) -> usize {
+        if self.layered {
+            1
+        } else {
+            6
+        }
+    }
+
+    /// Places the light at `position`; casters and receivers beyond `range` are ignored.
+    pub fn set_point(&mut self, position: Vec3, range: f32) {
+        let projection = Mat4::perspective_rh_gl(90f32.to_radians(), 1.0, range * 0.01, range);
+        for (matrix, (direction, up)) in self.face_matrices.iter_mut().zip(CUBE_FACES) {
+            *matrix = projection * Mat4::look_at_rh(position, position + direction, up);
+        }
+        self.position = position;
+        self.far = range;
+    }
+
+    /// Places the shadow at a point `light`. Other light types need a [`ShadowMap`].
+    pub fn set_light(&mut self, light: &Light) -> Result<()> {
+        match *light {
+            Light::Point {
+                position, range, ..
+            } => {
+                self.set_point(position, range);
+                Ok(())
+            }
+            _ => Err(anyhow!("Only point lights have a PointShadowMap")),
+        }
+    }
+
+    /// The depth program used during the shadow pass. It expects `u_model` to be set for
+    /// each draw; the face matrices and light are set by [`PointShadowMap::begin`].
+    pub fn depth_program(&self) -> &Program {
+        &self.depth_program
+    }
+
+    /// Binds the shadow framebuffer and clears the faces drawn in `pass`, which must be
+    /// below [`PointShadowMap::passes`]. Draw shadow casters afterwards.
+    pub fn begin(&self, pass: usize) {
+        self.framebuffer.bind(gl::FRAMEBUFFER);
+        self.depth_program.use_program();
+        if self.layered {
+            for (face, matrix) in self.face_matrices.iter().enumerate() {
+                let name = format!("u_face_matrices[{}]", face);
+                self.depth_program.set_mat4(&name, &matrix.to_cols_array());
+            }
+        } else {
+            self.framebuffer.attach_cube_face(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                &self.depth,
+                pass as u32,
+                0,
+            );
+            self.depth_program
+                .set_mat4("u_face_matrix", &self.face_matrices[pass].to_cols_array());
+        }
+        self.depth_program
+            .set_vec3("u_light_position", self.position.to_array());
+        self.depth_program.set_float("u_far", self.far);
+        depth::begin_standard();
+        unsafe {
+            gl::Viewport(0, 0, self.size, self.size);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Clear(gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    pub fn end(&self) {
+        depth::end_standard();
+        self.framebuffer.unbind(gl::FRAMEBUFFER);
+    }
+
+    /// Binds the cube map to texture `unit` and sets the `u_point_shadow_*` uniforms on
+    /// `program`, which must be in use.
+    pub fn apply(&self, program: &Program, unit: u32) {
+        self.depth.bind_unit(unit);
+        program.set_int("u_point_shadow_map", unit as i32);
+        program.set_vec3("u_point_shadow_position", self.position.to_array());
+        program.set_float("u_point_shadow_far", self.far);
+        program.set_float("u_point_shadow_size", self.size as f32);
+        program.set_float("u_point_shadow_bias", self.config.bias);
+        program.set_float("u_point_shadow_normal_bias", self.config.normal_bias);
+        program.set_int("u_point_shadow_pcf_radius", self.config.pcf_radius);
+    }
+}
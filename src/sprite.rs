@@ -0,0 +1,551 @@
+//! Batched 2D sprite rendering.
+//!
+//! [`SpriteBatch`] collects textured quads into a CPU-side vertex array and streams it to
+//! the GPU in as few draw calls as possible: a flush happens only when the texture
+//! changes or the batch is full. Draw sprites sharing a [`TextureAtlas`] to keep them in
+//! one call; [`crate::atlas::AtlasBuilder`] packs separate images into one.
+//!
+//! Besides plain quads, [`SpriteBatch::draw_nine_patch`] stretches a [`NinePatch`] to any
+//! size without distorting its border, for UI panels, and [`SpriteBatch::draw_tiled`]
+//! repeats an image across a quad, for backgrounds. [`SpriteBatch::draw_layer`] samples
+//! one layer of a `GL_TEXTURE_2D_ARRAY`, which is how [`SpriteBatch::draw_animated`] shows
+//! the current frame of a [`crate::animated_texture::AnimatedTexture`].
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+
+use crate::animated_texture::{AnimatedTexture, Animator};
+use crate::buffer::{Buffer, VertexArray};
+use crate::gl;
+use crate::math::{Mat4, Vec2, Vec4};
+use crate::shader::Program;
+use crate::stats;
+use crate::texture::{Sampler, Texture};
+use crate::transient::{self, TransientBuffer};
+use crate::validate;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_uv;
+layout (location = 2) in vec4 a_color;
+layout (location = 3) in float a_layer;
+uniform mat4 u_projection;
+out vec2 v_uv;
+out vec4 v_color;
+out float v_layer;
+void main() {
+    v_uv = a_uv;
+    v_color = a_color;
+    v_layer = a_layer;
+    gl_Position = u_projection * vec4(a_position, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 frag_color;
+uniform sampler2D u_texture;
+void main() {
+    frag_color = texture(u_texture, v_uv) * v_color;
+}
+"#;
+
+const ARRAY_FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 v_uv;
+in vec4 v_color;
+in float v_layer;
+out vec4 frag_color;
+uniform sampler2DArray u_texture;
+void main() {
+    frag_color = texture(u_texture, vec3(v_uv, v_layer)) * v_color;
+}
+"#;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct SpriteVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+    /// Texture array layer; ignored for 2D textures.
+    pub layer: f32,
+}
+
+/// An axis-aligned rectangle; used for texture coordinates and atlas regions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    /// The whole texture, `0..1` on both axes.
+    pub const UNIT: Rect = Rect {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// One quad. `origin` is the pivot for placement and rotation, relative to the size
+/// (`(0.5, 0.5)` is the center).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprite {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub origin: Vec2,
+    /// Counter-clockwise, in radians.
+    pub rotation: f32,
+    pub uv: Rect,
+    pub color: Vec4,
+}
+
+impl Sprite {
+    pub fn new(position: Vec2, size: Vec2) -> Sprite {
+        Sprite {
+            position,
+            size,
+            origin: Vec2::ZERO,
+            rotation: 0.0,
+            uv: Rect::UNIT,
+            color: Vec4::ONE,
+        }
+    }
+}
+
+/// An image drawn in nine slices: the corners keep their size, the edges stretch along
+/// one axis and the center along both.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NinePatch {
+    /// The whole image, in texture coordinates.
+    pub uv: Rect,
+    /// Left, top, right and bottom borders as fractions of `uv`.
+    pub margins: [f32; 4],
+    /// Left, top, right and bottom borders as drawn, in the units of [`Sprite::size`].
+    pub borders: [f32; 4],
+}
+
+impl NinePatch {
+    /// Scales the drawn borders, e.g. by the UI scale factor.
+    pub fn scaled(self, scale: f32) -> NinePatch {
+        NinePatch {
+            borders: self.borders.map(|border| border * scale),
+            ..self
+        }
+    }
+}
+
+/// Named regions of a texture, stored in texture coordinates.
+pub struct TextureAtlas {
+    texture: Rc<Texture>,
+    width: u32,
+    height: u32,
+    regions: HashMap<String, Rect>,
+}
+
+impl TextureAtlas {
+    /// Wraps a `width` x `height` texture with no regions yet.
+    pub fn new(texture: Rc<Texture>, width: u32, height: u32) -> TextureAtlas {
+        TextureAtlas {
+            texture,
+            width,
+            height,
+            regions: HashMap::new(),
+        }
+    }
+
+    /// Splits the texture into `columns` x `rows` equal cells named `"0"`, `"1"`, ... in
+    /// row-major order.
+    pub fn grid(
+        texture: Rc<Texture>,
+        width: u32,
+        height: u32,
+        columns: u32,
+        rows: u32,
+    ) -> TextureAtlas {
+        let mut atlas = TextureAtlas::new(texture, width, height);
+        let (cell_width, cell_height) = (width / columns, height / rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                atlas.add_region(
+                    &(row * columns + column).to_string(),
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                );
+            }
+        }
+        atlas
+    }
+
+    /// Adds a region given in pixels.
+    pub fn add_region(&mut self, name: &str, x: u32, y: u32, width: u32, height: u32) {
+        let (w, h) = (self.width as f32, self.height as f32);
+        self.regions.insert(
+            name.to_owned(),
+            Rect::new(
+                x as f32 / w,
+                y as f32 / h,
+                width as f32 / w,
+                height as f32 / h,
+            ),
+        );
+    }
+
+    pub fn region(&self, name: &str) -> Option<Rect> {
+        self.regions.get(name).copied()
+    }
+
+    /// The region `name` as a [`NinePatch`] with `borders` (left, top, right, bottom) in
+    /// pixels, drawn one unit per pixel.
+    pub fn nine_patch(&self, name: &str, borders: [u32; 4]) -> Option<NinePatch> {
+        let uv = self.region(name)?;
+        let width = uv.width * self.width as f32;
+        let height = uv.height * self.height as f32;
+        let [left, top, right, bottom] = borders.map(|border| border as f32);
+        Some(NinePatch {
+            uv,
+            margins: [left / width, top / height, right / width, bottom / height],
+            borders: [left, top, right, bottom],
+        })
+    }
+
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+}
+
+pub struct SpriteBatch {
+    program: Program,
+    /// Variant of `program` for `GL_TEXTURE_2D_ARRAY` textures.
+    array_program: Program,
+    vertex_array: VertexArray,
+    vertex_buffer: TransientBuffer,
+    _index_buffer: Buffer,
+    vertices: Vec<SpriteVertex>,
+    capacity: usize,
+    texture: Option<Rc<Texture>>,
+    /// Sampler overriding the texture's wrapping for [`SpriteBatch::draw_tiled`].
+    repeat_sampler: Sampler,
+    /// Whether the pending sprites are sampled with `repeat_sampler`.
+    repeat: bool,
+    draw_calls: usize,
+}
+
+impl SpriteBatch {
+    /// Creates a batch flushing automatically every `capacity` sprites.
+    pub fn new(capacity: usize) -> Result<SpriteBatch> {
+        let program = Program::from_sources(VERTEX_SHADER, FRAGMENT_SHADER)?;
+        let array_program = Program::from_sources(VERTEX_SHADER, ARRAY_FRAGMENT_SHADER)?;
+
+        let vertex_buffer = TransientBuffer::new(
+            transient::DEFAULT_CAPACITY.max(capacity * 4 * std::mem::size_of::<SpriteVertex>()),
+        )?;
+        let vertex_array = VertexArray::new()?;
+        Self::set_attributes(&vertex_array, vertex_buffer.buffer(), 0);
+        vertex_array.bind();
+
+        let indices: Vec<u32> = (0..capacity as u32)
+            .flat_map(|i| [0, 1, 2, 2, 3, 0].map(|j| i * 4 + j))
+            .collect();
+        let index_buffer = Buffer::new()?;
+        index_buffer.bind(gl::ELEMENT_ARRAY_BUFFER);
+        index_buffer.data(
+            gl::ELEMENT_ARRAY_BUFFER,
+            bytemuck::cast_slice(&indices),
+            gl::STATIC_DRAW,
+        );
+        vertex_array.unbind();
+
+        let repeat_sampler = Sampler::new()?;
+        repeat_sampler.label("sprite repeat");
+        for filter in [gl::TEXTURE_MIN_FILTER, gl::TEXTURE_MAG_FILTER] {
+            repeat_sampler.parameter(filter, gl::LINEAR as i32);
+        }
+
+        Ok(SpriteBatch {
+            program,
+            array_program,
+            vertex_array,
+            vertex_buffer,
+            _index_buffer: index_buffer,
+            vertices: Vec::with_capacity(capacity * 4),
+            capacity,
+            texture: None,
+            repeat_sampler,
+            repeat: false,
+            draw_calls: 0,
+        })
+    }
+
+    /// Points the vertex attributes at vertices starting `offset` bytes into `buffer`.
+    fn set_attributes(vertex_array: &VertexArray, buffer: &Buffer, offset: usize) {
+        let stride = std::mem::size_of::<SpriteVertex>() as i32;
+        vertex_array.attribute(0, buffer, 2, gl::FLOAT, false, stride, offset);
+        vertex_array.attribute(1, buffer, 2, gl::FLOAT, false, stride, offset + 8);
+        vertex_array.attribute(2, buffer, 4, gl::FLOAT, false, stride, offset + 16);
+        vertex_array.attribute(3, buffer, 1, gl::FLOAT, false, stride, offset + 32);
+    }
+
+    /// Starts a batch drawn with `projection`, for example
+    /// `Mat4::orthographic_rh_gl(0.0, width, height, 0.0, -1.0, 1.0)` for pixel
+    /// coordinates with y pointing down.
+    pub fn begin(&mut self, projection: Mat4) {
+        self.draw_calls = 0;
+        for program in [&self.array_program, &self.program] {
+            program.use_program();
+            program.set_mat4("u_projection", &projection.to_cols_array());
+            program.set_int("u_texture", 0);
+        }
+    }
+
+    /// Sets the minification and magnification filter of tiled draws, which replaces the
+    /// texture's own while the repeat sampler is bound. `GL_LINEAR` by default; tiled
+    /// pixel art wants `GL_NEAREST`.
+    pub fn set_repeat_filter(&self, filter: gl::types::GLenum) {
+        for name in [gl::TEXTURE_MIN_FILTER, gl::TEXTURE_MAG_FILTER] {
+            self.repeat_sampler.parameter(name, filter as i32);
+        }
+    }
+
+    pub fn draw(&mut self, texture: &Rc<Texture>, sprite: &Sprite) {
+        self.draw_layer(texture, 0, sprite);
+    }
+
+    /// Draws `sprite` from layer `layer` of a `GL_TEXTURE_2D_ARRAY` `texture`.
+    pub fn draw_layer(&mut self, texture: &Rc<Texture>, layer: usize, sprite: &Sprite) {
+        self.prepare(texture, false);
+        let whole = Rect::new(0.0, 0.0, sprite.size.x, sprite.size.y);
+        self.push_quad(sprite, whole, sprite.uv, layer as f32);
+    }
+
+    /// Draws the frame of `texture` that `animator` is at.
+    pub fn draw_animated(
+        &mut self,
+        texture: &AnimatedTexture,
+        animator: &Animator,
+        sprite: &Sprite,
+    ) {
+        self.draw_layer(texture.texture(), animator.frame(), sprite);
+    }
+
+    /// Draws `patch` stretched to `sprite`'s size, placed and tinted like `sprite`, whose
+    /// `uv` is ignored. Borders wider than the sprite shrink to fit.
+    pub fn draw_nine_patch(&mut self, texture: &Rc<Texture>, patch: &NinePatch, sprite: &Sprite) {
+        self.prepare(texture, false);
+        let [left, top, right, bottom] = patch.borders;
+        let stops = |low: f32, high: f32, size: f32| {
+            let fit = if low + high > size {
+                size / (low + high)
+            } else {
+                1.0
+            };
+            [0.0, low * fit, size - high * fit, size]
+        };
+        let xs = stops(left, right, sprite.size.x);
+        let ys = stops(top, bottom, sprite.size.y);
+        let uv = patch.uv;
+        let [left, top, right, bottom] = patch.margins;
+        let us = [0.0, left, 1.0 - right, 1.0].map(|u| uv.x + u * uv.width);
+        let vs = [0.0, top, 1.0 - bottom, 1.0].map(|v| uv.y + v * uv.height);
+        for (y, v) in ys.windows(2).zip(vs.windows(2)) {
+            for (x, u) in xs.windows(2).zip(us.windows(2)) {
+                let local = Rect::new(x[0], y[0], x[1] - x[0], y[1] - y[0]);
+                if local.width > 0.0 && local.height > 0.0 {
+                    let uv = Rect::new(u[0], v[0], u[1] - u[0], v[1] - v[0]);
+                    self.push_quad(sprite, local, uv, 0.0);
+                }
+            }
+        }
+    }
+
+    /// Draws the atlas region `name` as a nine-patch with `borders` (left, top, right,
+    /// bottom) in pixels. Unknown regions draw nothing.
+    pub fn draw_region_nine_patch(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        borders: [u32; 4],
+        sprite: &Sprite,
+    ) {
+        if let Some(patch) = atlas.nine_patch(name, borders) {
+            self.draw_nine_patch(&atlas.texture, &patch, sprite);
+        }
+    }
+
+    /// Fills `sprite`'s quad with copies of its `uv` image, each `tile` units large,
+    /// starting in its top-left corner.
+    ///
+    /// An image covering the whole texture ([`Rect::UNIT`]) is drawn as one quad sampled
+    /// with repeat wrapping, whatever the texture's own wrap mode. A smaller region, like
+    /// an atlas entry, can't wrap in hardware and takes one quad per tile instead, the
+    /// last row and column cut off.
+    pub fn draw_tiled(&mut self, texture: &Rc<Texture>, sprite: &Sprite, tile: Vec2) {
+        if tile.x <= 0.0 || tile.y <= 0.0 {
+            return;
+        }
+        let size = sprite.size;
+        if sprite.uv == Rect::UNIT {
+            self.prepare(texture, true);
+            let repeats = size / tile;
+            let whole = Rect::new(0.0, 0.0, size.x, size.y);
+            let uv = Rect::new(0.0, 0.0, repeats.x, repeats.y);
+            self.push_quad(sprite, whole, uv, 0.0);
+            return;
+        }
+        self.prepare(texture, false);
+        let uv = sprite.uv;
+        let mut y = 0.0;
+        while y < size.y {
+            let height = tile.y.min(size.y - y);
+            let mut x = 0.0;
+            while x < size.x {
+                let width = tile.x.min(size.x - x);
+                let part = Rect::new(
+                    uv.x,
+                    uv.y,
+                    uv.width * width / tile.x,
+                    uv.height * height / tile.y,
+                );
+                self.push_quad(sprite, Rect::new(x, y, width, height), part, 0.0);
+                x += tile.x;
+            }
+            y += tile.y;
+        }
+    }
+
+    /// Tiles the atlas region `name` like [`SpriteBatch::draw_tiled`], one quad per tile.
+    /// Unknown regions draw nothing.
+    pub fn draw_region_tiled(
+        &mut self,
+        atlas: &TextureAtlas,
+        name: &str,
+        sprite: &Sprite,
+        tile: Vec2,
+    ) {
+        if let Some(uv) = atlas.region(name) {
+            let sprite = Sprite { uv, ..*sprite };
+            self.draw_tiled(&atlas.texture, &sprite, tile);
+        }
+    }
+
+    /// Makes `texture`, sampled repeating or not, the batch's, flushing the sprites so
+    /// far if that changes anything.
+    fn prepare(&mut self, texture: &Rc<Texture>, repeat: bool) {
+        let same_texture = self
+            .texture
+            .as_ref()
+            .is_some_and(|current| Rc::ptr_eq(current, texture));
+        if !same_texture || self.repeat != repeat {
+            self.flush();
+            self.texture = Some(texture.clone());
+            self.repeat = repeat;
+        }
+    }
+
+    /// Adds the part `local` of `sprite`'s quad, given in its unrotated space from the
+    /// top-left corner to `size`, textured with `uv` of array layer `layer`.
+    fn push_quad(&mut self, sprite: &Sprite, local: Rect, uv: Rect, layer: f32) {
+        if self.vertices.len() == self.capacity * 4 {
+            self.flush();
+        }
+        let (sin, cos) = sprite.rotation.sin_cos();
+        let pivot = sprite.origin * sprite.size;
+        let color = sprite.color.to_array();
+        let (left, top) = (local.x, local.y);
+        let (right, bottom) = (local.x + local.width, local.y + local.height);
+        let (u, v) = (uv.x + uv.width, uv.y + uv.height);
+        let corners = [
+            (Vec2::new(left, top), [uv.x, uv.y]),
+            (Vec2::new(right, top), [u, uv.y]),
+            (Vec2::new(right, bottom), [u, v]),
+            (Vec2::new(left, bottom), [uv.x, v]),
+        ];
+        for (corner, uv) in corners {
+            let local = corner - pivot;
+            let rotated = Vec2::new(local.x * cos - local.y * sin, local.x * sin + local.y * cos);
+            self.vertices.push(SpriteVertex {
+                position: (sprite.position + rotated).to_array(),
+                uv,
+                color,
+                layer,
+            });
+        }
+    }
+
+    /// Draws the atlas region `name` with `sprite`'s placement, ignoring its `uv`.
+    /// Unknown regions draw nothing.
+    pub fn draw_region(&mut self, atlas: &TextureAtlas, name: &str, sprite: &Sprite) {
+        if let Some(uv) = atlas.region(name) {
+            let sprite = Sprite { uv, ..*sprite };
+            self.draw(&atlas.texture, &sprite);
+        }
+    }
+
+    /// Draws the sprites recorded so far.
+    pub fn flush(&mut self) {
+        let Some(texture) = &self.texture else {
+            return;
+        };
+        if self.vertices.is_empty() {
+            return;
+        }
+        let offset = self.vertex_buffer.push(
+            bytemuck::cast_slice(&self.vertices),
+            std::mem::size_of::<SpriteVertex>(),
+        );
+        Self::set_attributes(&self.vertex_array, self.vertex_buffer.buffer(), offset);
+
+        if texture.target() == gl::TEXTURE_2D_ARRAY {
+            self.array_program.use_program();
+        } else {
+            self.program.use_program();
+        }
+        texture.bind_unit(0);
+        if self.repeat {
+            self.repeat_sampler.bind_unit(0);
+        }
+        self.vertex_array.bind();
+        validate::draw("a sprite batch");
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                (self.vertices.len() / 4 * 6) as i32,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+        self.vertex_array.unbind();
+        if self.repeat {
+            Sampler::unbind_unit(0);
+        }
+        stats::record_draw(self.vertices.len() / 2);
+        self.vertices.clear();
+        self.draw_calls += 1;
+    }
+
+    /// Flushes the remaining sprites and returns the number of draw calls since
+    /// [`SpriteBatch::begin`].
+    pub fn end(&mut self) -> usize {
+        self.flush();
+        self.texture = None;
+        self.draw_calls
+    }
+}
@@ -0,0 +1,387 @@
+//! Screen-space ambient occlusion.
+//!
+//! [`Ssao::compute`] estimates, for every pixel, how much of the hemisphere above the
+//! surface is blocked by nearby geometry in the depth buffer, by testing a kernel of
+//! sample points randomly rotated per pixel, then blurs away the rotation pattern. The
+//! result darkens ambient light: [`crate::deferred::Deferred::set_ssao`] applies it in
+//! the resolve pass, and [`crate::postprocess::PostProcess::set_ssao`] multiplies it
+//! into the scene before the post-processing passes run.
+//!
+//! Normals come from the G-buffer when there is one and are otherwise reconstructed
+//! from depth derivatives, which shows faint seams at depth discontinuities.
+
+use anyhow::{anyhow, Result};
+
+use crate::depth::{self, DEPTH_GLSL};
+use crate::framebuffer::RenderTarget;
+use crate::gl;
+use crate::math::{Mat4, Vec3};
+use crate::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use crate::shader::Program;
+use crate::texture::Texture;
+
+/// Largest [`SsaoConfig::kernel_size`].
+pub const MAX_KERNEL_SIZE: usize = 64;
+
+/// Edge length of the tiled rotation texture; the blur averages over the same size.
+const NOISE_SIZE: usize = 4;
+
+/// Follows the depth convention through [`DEPTH_GLSL`], pasted in front.
+const SSAO_SHADER: &str = r#"
+#define MAX_KERNEL_SIZE 64
+in vec2 uv;
+out float frag_occlusion;
+uniform sampler2D u_depth;
+uniform sampler2D u_normal;
+uniform sampler2D u_noise;
+uniform bool u_has_normals;
+uniform mat4 u_view;
+uniform mat4 u_projection;
+uniform mat4 u_inverse_projection;
+uniform vec3 u_kernel[MAX_KERNEL_SIZE];
+uniform int u_kernel_size;
+uniform float u_radius;
+uniform float u_bias;
+uniform float u_intensity;
+uniform vec2 u_noise_scale;
+
+vec3 view_position(vec2 st) {
+    float depth = texture(u_depth, st).r;
+    vec4 view = u_inverse_projection * vec4(st * 2.0 - 1.0, depth_to_ndc(depth), 1.0);
+    return view.xyz / view.w;
+}
+
+void main() {
+    if (is_far_depth(texture(u_depth, uv).r)) {
+        frag_occlusion = 1.0;
+        return;
+    }
+    vec3 position = view_position(uv);
+    vec3 normal = u_has_normals
+        ? mat3(u_view) * texture(u_normal, uv).xyz
+        : cross(dFdx(position), dFdy(position));
+    normal = normalize(normal);
+
+    // Rotate the kernel around the normal by the tiled noise vector.
+    vec3 random = vec3(texture(u_noise, uv * u_noise_scale).xy * 2.0 - 1.0, 0.0);
+    vec3 tangent = normalize(random - normal * dot(random, normal));
+    mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < u_kernel_size; ++i) {
+        vec3 sample_position = position + tbn * u_kernel[i] * u_radius;
+        vec4 clip = u_projection * vec4(sample_position, 1.0);
+        vec2 st = clip.xy / clip.w * 0.5 + 0.5;
+        float scene_z = view_position(st).z;
+        // Ignore occluders far outside the radius, such as a distant background.
+        float range = smoothstep(0.0, 1.0, u_radius / abs(position.z - scene_z));
+        occlusion += (scene_z >= sample_position.z + u_bias ? 1.0 : 0.0) * range;
+    }
+    frag_occlusion = pow(1.0 - occlusion / float(u_kernel_size), u_intensity);
+}
+"#;
+
+const BLUR_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out float frag_occlusion;
+uniform sampler2D u_occlusion;
+uniform int u_radius;
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(u_occlusion, 0));
+    float sum = 0.0;
+    for (int x = -u_radius; x < u_radius; ++x) {
+        for (int y = -u_radius; y < u_radius; ++y) {
+            sum += texture(u_occlusion, uv + (vec2(x, y) + 0.5) * texel).r;
+        }
+    }
+    frag_occlusion = sum / float(4 * u_radius * u_radius);
+}
+"#;
+
+const APPLY_SHADER: &str = r#"#version 330 core
+in vec2 uv;
+out vec4 frag_color;
+uniform sampler2D u_input;
+uniform sampler2D u_occlusion;
+
+void main() {
+    vec4 color = texture(u_input, uv);
+    frag_color = vec4(color.rgb * texture(u_occlusion, uv).r, color.a);
+}
+"#;
+
+/// Presets trading occlusion quality for speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SsaoQuality {
+    Low,
+    Medium,
+    High,
+    Ultra,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SsaoConfig {
+    /// Sample points per pixel, up to [`MAX_KERNEL_SIZE`].
+    pub kernel_size: usize,
+    /// World-space radius of the sampled hemisphere.
+    pub radius: f32,
+    /// Depth difference below which a sample doesn't count, against self-occlusion acne.
+    pub bias: f32,
+    /// Exponent applied to the result; above one darkens.
+    pub intensity: f32,
+    /// Half the blur's edge length in texels; `2` covers the 4×4 noise tile.
+    pub blur_radius: i32,
+    /// Computes occlusion at half the width and height, then upsamples.
+    pub half_resolution: bool,
+}
+
+impl SsaoConfig {
+    pub fn preset(quality: SsaoQuality) -> SsaoConfig {
+        let (kernel_size, half_resolution) = match quality {
+            SsaoQuality::Low => (8, true),
+            SsaoQuality::Medium => (16, true),
+            SsaoQuality::High => (32, false),
+            SsaoQuality::Ultra => (64, false),
+        };
+        SsaoConfig {
+            kernel_size,
+            radius: 0.5,
+            bias: 0.025,
+            intensity: 1.0,
+            blur_radius: 2,
+            half_resolution,
+        }
+    }
+}
+
+impl Default for SsaoConfig {
+    fn default() -> Self {
+        SsaoConfig::preset(SsaoQuality::Medium)
+    }
+}
+
+pub struct Ssao {
+    config: SsaoConfig,
+    width: i32,
+    height: i32,
+    occlusion: RenderTarget,
+    blurred: RenderTarget,
+    noise: Texture,
+    ssao: Program,
+    blur: Program,
+    apply: Program,
+    triangle: FullscreenTriangle,
+    view: Mat4,
+    projection: Mat4,
+}
+
+impl Ssao {
+    /// `width` and `height` are the size of the depth buffers passed to
+    /// [`Ssao::compute`].
+    pub fn new(width: i32, height: i32, config: SsaoConfig) -> Result<Ssao> {
+        let (occlusion, blurred) = create_targets(width, height, &config)?;
+        let mut ssao = Ssao {
+            config,
+            width,
+            height,
+            occlusion,
+            blurred,
+            noise: noise_texture()?,
+            ssao: Program::from_sources(
+                FULLSCREEN_VERTEX_SHADER,
+                &format!("#version 330 core\n{}\n{}", DEPTH_GLSL, SSAO_SHADER),
+            )?,
+            blur: Program::from_sources(FULLSCREEN_VERTEX_SHADER, BLUR_SHADER)?,
+            apply: Program::from_sources(FULLSCREEN_VERTEX_SHADER, APPLY_SHADER)?,
+            triangle: FullscreenTriangle::new()?,
+            view: Mat4::IDENTITY,
+            projection: Mat4::IDENTITY,
+        };
+        ssao.set_config(config)?;
+        Ok(ssao)
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) -> Result<()> {
+        (self.occlusion, self.blurred) = create_targets(width, height, &self.config)?;
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    pub fn config(&self) -> &SsaoConfig {
+        &self.config
+    }
+
+    /// Applies `config`, regenerating the sample kernel and, for a change of resolution,
+    /// the targets.
+    pub fn set_config(&mut self, config: SsaoConfig) -> Result<()> {
+        if config.kernel_size == 0 || config.kernel_size > MAX_KERNEL_SIZE {
+            return Err(anyhow!(
+                "SSAO kernel size {} is outside 1..={}",
+                config.kernel_size,
+                MAX_KERNEL_SIZE
+            ));
+        }
+        if config.half_resolution != self.config.half_resolution {
+            (self.occlusion, self.blurred) = create_targets(self.width, self.height, &config)?;
+        }
+        self.config = config;
+        self.ssao.use_program();
+        self.ssao
+            .set_vec3_array("u_kernel", &kernel(config.kernel_size));
+        Ok(())
+    }
+
+    /// Sets the camera the depth buffer was rendered with.
+    pub fn set_camera(&mut self, view: Mat4, projection: Mat4) {
+        self.view = view;
+        self.projection = projection;
+    }
+
+    /// Computes blurred occlusion from `depth` and, if given, world-space G-buffer
+    /// `normal`s, and returns it: one means unoccluded. Leaves an internal framebuffer
+    /// bound with its viewport, so rebind the target to draw into afterwards.
+    pub fn compute(&self, depth: &Texture, normal: Option<&Texture>) -> &Texture {
+        let _span = tracing::debug_span!("pass", name = "ssao").entered();
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+        }
+
+        self.occlusion.bind();
+        self.ssao.use_program();
+        depth.bind_unit(0);
+        // Some texture must be bound to the normal sampler even when it isn't read.
+        normal.unwrap_or(depth).bind_unit(1);
+        self.noise.bind_unit(2);
+        self.ssao.set_int("u_depth", 0);
+        self.ssao.set_int("u_normal", 1);
+        self.ssao.set_int("u_noise", 2);
+        self.ssao.set_int("u_has_normals", normal.is_some() as i32);
+        depth::set_uniforms(&self.ssao);
+        self.ssao.set_mat4("u_view", &self.view.to_cols_array());
+        self.ssao
+            .set_mat4("u_projection", &self.projection.to_cols_array());
+        self.ssao.set_mat4(
+            "u_inverse_projection",
+            &self.projection.inverse().to_cols_array(),
+        );
+        self.ssao
+            .set_int("u_kernel_size", self.config.kernel_size as i32);
+        self.ssao.set_float("u_radius", self.config.radius);
+        self.ssao.set_float("u_bias", self.config.bias);
+        self.ssao.set_float("u_intensity", self.config.intensity);
+        self.ssao.set_vec2(
+            "u_noise_scale",
+            [
+                self.occlusion.width() as f32 / NOISE_SIZE as f32,
+                self.occlusion.height() as f32 / NOISE_SIZE as f32,
+            ],
+        );
+        self.triangle.draw();
+
+        self.blurred.bind();
+        self.blur.use_program();
+        self.occlusion.color.bind_unit(0);
+        self.blur.set_int("u_occlusion", 0);
+        self.blur
+            .set_int("u_radius", self.config.blur_radius.max(1));
+        self.triangle.draw();
+        &self.blurred.color
+    }
+
+    /// The result of the last [`Ssao::compute`].
+    pub fn occlusion(&self) -> &Texture {
+        &self.blurred.color
+    }
+
+    /// Draws `color` darkened by the last occlusion into the bound framebuffer.
+    pub fn apply(&self, color: &Texture) {
+        self.apply.use_program();
+        color.bind_unit(0);
+        self.blurred.color.bind_unit(1);
+        self.apply.set_int("u_input", 0);
+        self.apply.set_int("u_occlusion", 1);
+        self.triangle.draw();
+    }
+}
+
+fn create_targets(
+    width: i32,
+    height: i32,
+    config: &SsaoConfig,
+) -> Result<(RenderTarget, RenderTarget)> {
+    let (width, height) = if config.half_resolution {
+        ((width / 2).max(1), (height / 2).max(1))
+    } else {
+        (width, height)
+    };
+    let occlusion = RenderTarget::new(width, height, gl::R8, false)?;
+    occlusion.framebuffer.label("SSAO");
+    let blurred = RenderTarget::new(width, height, gl::R8, false)?;
+    blurred.framebuffer.label("SSAO blur");
+    Ok((occlusion, blurred))
+}
+
+/// A deterministic xorshift sequence in `0..1`, so the kernel and noise don't flicker
+/// between runs.
+struct Random(u32);
+
+impl Random {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Points in the +Z unit hemisphere, denser towards the center.
+fn kernel(size: usize) -> Vec<[f32; 3]> {
+    let mut random = Random(0x9e37_79b9);
+    (0..size)
+        .map(|i| {
+            let direction = Vec3::new(
+                random.next_f32() * 2.0 - 1.0,
+                random.next_f32() * 2.0 - 1.0,
+                random.next_f32(),
+            )
+            .normalize_or_zero();
+            let t = i as f32 / size as f32;
+            let scale = 0.1 + 0.9 * t * t;
+            (direction * random.next_f32() * scale).to_array()
+        })
+        .collect()
+}
+
+/// Random rotation vectors in the XY plane, tiled over the screen.
+fn noise_texture() -> Result<Texture> {
+    let mut random = Random(0x85eb_ca6b);
+    let pixels: Vec<u8> = (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|_| {
+            let angle = random.next_f32() * std::f32::consts::TAU;
+            let encode = |x: f32| ((x * 0.5 + 0.5) * 255.0).round() as u8;
+            [encode(angle.cos()), encode(angle.sin()), 0, 255]
+        })
+        .collect();
+    let texture = Texture::new(gl::TEXTURE_2D)?;
+    texture.bind();
+    texture.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+    texture.parameter(gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+    texture.parameter(gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+    let size = NOISE_SIZE as i32;
+    texture.image_2d(
+        0,
+        gl::RGBA8,
+        size,
+        size,
+        gl::RGBA,
+        gl::UNSIGNED_BYTE,
+        Some(&pixels),
+    );
+    texture.unbind();
+    texture.label("SSAO noise");
+    Ok(texture)
+}
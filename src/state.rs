@@ -0,0 +1,346 @@
+//! Redundant-bind elimination.
+//!
+//! [`StateCache`] remembers what it last bound and skips GL calls that would not change
+//! anything. It only knows about binds made through it: call [`StateCache::invalidate`]
+//! after code that binds programs, vertex arrays, textures, buffers or framebuffers
+//! directly. The [`crate::scoped`] guards bind through it too and restore what it had
+//! bound.
+//!
+//! It also sets the rasterizer state: [`PolygonMode`], line width and
+//! [`PolygonOffset`]. [`set_wireframe`] forces every filled draw to lines for debugging;
+//! the example runners toggle it with F3.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::buffer::VertexArray;
+use crate::context;
+use crate::dsa;
+use crate::gl;
+use crate::shader::Program;
+use crate::texture::Texture;
+
+/// Number of texture units tracked. Binds to higher units are always issued.
+pub const MAX_CACHED_TEXTURE_UNITS: usize = 16;
+
+/// Counts of binds issued and skipped since the last [`StateCache::reset_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StateStats {
+    pub program_binds: usize,
+    pub vertex_array_binds: usize,
+    pub texture_binds: usize,
+    /// Buffer binds by [`crate::scoped`] guards.
+    pub buffer_binds: usize,
+    /// Framebuffer and renderbuffer binds by [`crate::scoped`] guards.
+    pub framebuffer_binds: usize,
+    /// Polygon mode, line width and polygon offset changes.
+    pub raster_changes: usize,
+    pub skipped: usize,
+}
+
+/// How polygons are rasterized. Only [`PolygonMode::Fill`] exists on OpenGL ES; the
+/// others fall back to it there.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    pub fn to_gl(self) -> gl::types::GLenum {
+        match self {
+            PolygonMode::Fill => gl::FILL,
+            PolygonMode::Line => gl::LINE,
+            PolygonMode::Point => gl::POINT,
+        }
+    }
+}
+
+/// Depth offset for filled polygons, `factor * slope + units * r` where `r` is the
+/// smallest resolvable depth difference. Negative values pull geometry towards the
+/// camera, e.g. for decals or a filled pass under a wireframe overlay.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PolygonOffset {
+    pub factor: f32,
+    pub units: f32,
+}
+
+static WIREFRAME: AtomicBool = AtomicBool::new(false);
+
+/// Whether [`set_wireframe`] is forcing line rasterization.
+pub fn wireframe() -> bool {
+    WIREFRAME.load(Ordering::Relaxed)
+}
+
+/// Rasterizes everything as lines in the current context, including draws that ask
+/// [`StateCache::set_polygon_mode`] for [`PolygonMode::Fill`]. Does nothing on
+/// OpenGL ES.
+pub fn set_wireframe(enabled: bool) {
+    WIREFRAME.store(enabled, Ordering::Relaxed);
+    if polygon_mode_supported() {
+        let mode = if enabled { gl::LINE } else { gl::FILL };
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, mode);
+        }
+    }
+}
+
+fn polygon_mode_supported() -> bool {
+    !context::info().es && gl::PolygonMode::is_loaded()
+}
+
+#[derive(Debug, Default)]
+pub struct StateCache {
+    program: Option<gl::types::GLuint>,
+    vertex_array: Option<gl::types::GLuint>,
+    textures: [Option<(gl::types::GLenum, gl::types::GLuint)>; MAX_CACHED_TEXTURE_UNITS],
+    /// By target. `GL_ELEMENT_ARRAY_BUFFER` is vertex array state and forgotten whenever
+    /// the vertex array changes.
+    buffers: HashMap<gl::types::GLenum, gl::types::GLuint>,
+    draw_framebuffer: Option<gl::types::GLuint>,
+    read_framebuffer: Option<gl::types::GLuint>,
+    renderbuffer: Option<gl::types::GLuint>,
+    /// The requested mode and whether wireframe was forced when it was set.
+    polygon_mode: Option<(PolygonMode, bool)>,
+    line_width: Option<f32>,
+    polygon_offset: Option<Option<PolygonOffset>>,
+    stats: StateStats,
+}
+
+impl StateCache {
+    pub fn new() -> StateCache {
+        StateCache::default()
+    }
+
+    /// Forgets all cached bindings, so the next bind of each kind is always issued.
+    pub fn invalidate(&mut self) {
+        self.program = None;
+        self.vertex_array = None;
+        self.textures = Default::default();
+        self.buffers.clear();
+        self.draw_framebuffer = None;
+        self.read_framebuffer = None;
+        self.renderbuffer = None;
+        self.polygon_mode = None;
+        self.line_width = None;
+        self.polygon_offset = None;
+    }
+
+    pub fn use_program(&mut self, program: &Program) {
+        if self.program == Some(program.id()) {
+            self.stats.skipped += 1;
+            return;
+        }
+        program.use_program();
+        self.program = Some(program.id());
+        self.stats.program_binds += 1;
+    }
+
+    /// Switches to program `id` without uploading anything it defers to binding.
+    pub(crate) fn use_program_id(&mut self, id: gl::types::GLuint) {
+        if self.program == Some(id) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::UseProgram(id);
+        }
+        self.program = Some(id);
+        self.stats.program_binds += 1;
+    }
+
+    pub fn bind_vertex_array(&mut self, vertex_array: &VertexArray) {
+        self.bind_vertex_array_id(vertex_array.id());
+    }
+
+    pub(crate) fn bind_vertex_array_id(&mut self, id: gl::types::GLuint) {
+        if self.vertex_array == Some(id) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::BindVertexArray(id);
+        }
+        self.vertex_array = Some(id);
+        self.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+        self.stats.vertex_array_binds += 1;
+    }
+
+    /// Unbinds the vertex array, leaving the default state for code outside the cache.
+    pub fn unbind_vertex_array(&mut self) {
+        if self.vertex_array.take().is_some() {
+            unsafe {
+                gl::BindVertexArray(0);
+            }
+            self.buffers.remove(&gl::ELEMENT_ARRAY_BUFFER);
+        }
+    }
+
+    pub fn bind_texture(&mut self, unit: u32, texture: &Texture) {
+        self.bind_texture_id(unit, texture.target(), texture.id());
+    }
+
+    /// Binds texture `id` of `target` to `unit`. With direct state access, binding 0
+    /// unbinds every target of the unit.
+    pub(crate) fn bind_texture_id(
+        &mut self,
+        unit: u32,
+        target: gl::types::GLenum,
+        id: gl::types::GLuint,
+    ) {
+        let binding = Some((target, id));
+        let cached = self.textures.get_mut(unit as usize);
+        if cached.as_deref() == Some(&binding) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            if dsa::is_available() {
+                gl::BindTextureUnit(unit, id);
+            } else {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+                gl::BindTexture(target, id);
+            }
+        }
+        if let Some(cached) = cached {
+            *cached = binding;
+        }
+        self.stats.texture_binds += 1;
+    }
+
+    pub(crate) fn bind_buffer_id(&mut self, target: gl::types::GLenum, id: gl::types::GLuint) {
+        if self.buffers.get(&target) == Some(&id) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::BindBuffer(target, id);
+        }
+        self.buffers.insert(target, id);
+        self.stats.buffer_binds += 1;
+    }
+
+    /// `GL_FRAMEBUFFER` binds both the draw and the read framebuffer.
+    pub(crate) fn bind_framebuffer_id(&mut self, target: gl::types::GLenum, id: gl::types::GLuint) {
+        let draw = matches!(target, gl::FRAMEBUFFER | gl::DRAW_FRAMEBUFFER);
+        let read = matches!(target, gl::FRAMEBUFFER | gl::READ_FRAMEBUFFER);
+        if (!draw || self.draw_framebuffer == Some(id))
+            && (!read || self.read_framebuffer == Some(id))
+        {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::BindFramebuffer(target, id);
+        }
+        if draw {
+            self.draw_framebuffer = Some(id);
+        }
+        if read {
+            self.read_framebuffer = Some(id);
+        }
+        self.stats.framebuffer_binds += 1;
+    }
+
+    pub(crate) fn bind_renderbuffer_id(&mut self, id: gl::types::GLuint) {
+        if self.renderbuffer == Some(id) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+        }
+        self.renderbuffer = Some(id);
+        self.stats.framebuffer_binds += 1;
+    }
+
+    /// What the cache last bound, `None` where it doesn't know.
+    pub(crate) fn program(&self) -> Option<gl::types::GLuint> {
+        self.program
+    }
+
+    pub(crate) fn vertex_array(&self) -> Option<gl::types::GLuint> {
+        self.vertex_array
+    }
+
+    pub(crate) fn texture(&self, unit: u32) -> Option<(gl::types::GLenum, gl::types::GLuint)> {
+        self.textures.get(unit as usize).copied().flatten()
+    }
+
+    pub(crate) fn buffer(&self, target: gl::types::GLenum) -> Option<gl::types::GLuint> {
+        self.buffers.get(&target).copied()
+    }
+
+    pub(crate) fn framebuffers(&self) -> (Option<gl::types::GLuint>, Option<gl::types::GLuint>) {
+        (self.draw_framebuffer, self.read_framebuffer)
+    }
+
+    pub(crate) fn renderbuffer(&self) -> Option<gl::types::GLuint> {
+        self.renderbuffer
+    }
+
+    /// Sets the polygon mode for both faces. While [`wireframe`] is on, `Fill` draws as
+    /// lines too.
+    pub fn set_polygon_mode(&mut self, mode: PolygonMode) {
+        let state = Some((mode, wireframe()));
+        if self.polygon_mode == state {
+            self.stats.skipped += 1;
+            return;
+        }
+        self.polygon_mode = state;
+        self.stats.raster_changes += 1;
+        if !polygon_mode_supported() {
+            return;
+        }
+        let mode = match mode {
+            PolygonMode::Fill if wireframe() => PolygonMode::Line,
+            mode => mode,
+        };
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, mode.to_gl());
+        }
+    }
+
+    /// Sets the width of lines in pixels. Core profiles only guarantee `1.0`; wider lines
+    /// are an error in forward-compatible contexts.
+    pub fn set_line_width(&mut self, width: f32) {
+        if self.line_width == Some(width) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            gl::LineWidth(width);
+        }
+        self.line_width = Some(width);
+        self.stats.raster_changes += 1;
+    }
+
+    /// Enables `GL_POLYGON_OFFSET_FILL` with `offset`, or disables it with `None`.
+    pub fn set_polygon_offset(&mut self, offset: Option<PolygonOffset>) {
+        if self.polygon_offset == Some(offset) {
+            self.stats.skipped += 1;
+            return;
+        }
+        unsafe {
+            match offset {
+                Some(offset) => {
+                    gl::Enable(gl::POLYGON_OFFSET_FILL);
+                    gl::PolygonOffset(offset.factor, offset.units);
+                }
+                None => gl::Disable(gl::POLYGON_OFFSET_FILL),
+            }
+        }
+        self.polygon_offset = Some(offset);
+        self.stats.raster_changes += 1;
+    }
+
+    pub fn stats(&self) -> StateStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = StateStats::default();
+    }
+}
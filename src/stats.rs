@@ -0,0 +1,279 @@
+//! Per-frame rendering statistics and an on-screen overlay.
+//!
+//! The crate's draw paths report into thread-local [`Counters`]; [`take_counters`] reads
+//! and resets them. [`StatsOverlay`] shows them together with FPS, frame-time percentiles
+//! and graph, [`crate::memory`] usage and any per-pass
+//! [`crate::profiler::PipelineStatistics`] it is given.
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::event::{KeyCode, WindowEvent};
+use crate::gl;
+use crate::math::{Mat4, Vec2, Vec4};
+use crate::memory::{self, GpuMemory, Usage};
+use crate::profiler::{PassStatistics, PipelineCounter};
+use crate::sprite::{Sprite, SpriteBatch};
+use crate::text::Font;
+use crate::texture::Texture;
+
+/// Draw calls and triangles submitted since the last [`take_counters`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub draw_calls: usize,
+    pub triangles: usize,
+}
+
+thread_local! {
+    static COUNTERS: Cell<Counters> = const {
+        Cell::new(Counters {
+            draw_calls: 0,
+            triangles: 0,
+        })
+    };
+}
+
+/// Records one draw call of `triangles` triangles.
+pub fn record_draw(triangles: usize) {
+    COUNTERS.with(|counters| {
+        let mut c = counters.get();
+        c.draw_calls += 1;
+        c.triangles += triangles;
+        counters.set(c);
+    });
+}
+
+/// Returns the counters and resets them to zero.
+pub fn take_counters() -> Counters {
+    COUNTERS.with(|counters| counters.replace(Counters::default()))
+}
+
+/// Number of frames shown in the frame-time graph.
+const HISTORY: usize = 120;
+
+/// FPS, 95th-percentile and worst frame times, a frame-time graph, draw-call and
+/// triangle counts, memory held by buffers and textures (with free video memory where
+/// the driver reports it), any [`crate::pacing`] limit and the vertex, primitive and
+/// fragment counts of the passes given to [`StatsOverlay::set_pass_statistics`] in the
+/// top-left corner. F3
+/// toggles it. Sized for a scale factor of 1 until told otherwise with
+/// [`StatsOverlay::set_scale_factor`] or a `ScaleFactorChanged` event.
+pub struct StatsOverlay {
+    batch: SpriteBatch,
+    white: Rc<Texture>,
+    frame_times: VecDeque<f32>,
+    counters: Counters,
+    memory: Usage,
+    gpu_memory: Option<GpuMemory>,
+    passes: Vec<PassStatistics>,
+    scale: f32,
+    pub visible: bool,
+}
+
+impl StatsOverlay {
+    pub fn new() -> Result<StatsOverlay> {
+        let white = Texture::new(gl::TEXTURE_2D)?;
+        white.bind();
+        white.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        white.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        white.image_2d(
+            0,
+            gl::RGBA8,
+            1,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&[255; 4]),
+        );
+        white.unbind();
+
+        Ok(StatsOverlay {
+            batch: SpriteBatch::new(HISTORY + 256)?,
+            white: Rc::new(white),
+            frame_times: VecDeque::with_capacity(HISTORY),
+            counters: Counters::default(),
+            memory: Usage::default(),
+            gpu_memory: None,
+            passes: Vec::new(),
+            scale: 1.0,
+            visible: true,
+        })
+    }
+
+    /// Scales the overlay for the window's `Window::scale_factor`.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale = scale_factor;
+    }
+
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            event if event.is_key_pressed(KeyCode::F3) => self.visible = !self.visible,
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.scale = *scale_factor as f32;
+            }
+            _ => (),
+        }
+    }
+
+    /// Records a frame of `dt` seconds and collects the counters of everything drawn
+    /// since the previous call.
+    pub fn end_frame(&mut self, dt: f32) {
+        if self.frame_times.len() == HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(dt);
+        self.counters = take_counters();
+        self.memory = memory::usage();
+        if self.visible {
+            self.gpu_memory = memory::gpu_memory();
+        }
+    }
+
+    /// Shows the passes of the newest frame in `passes`, typically
+    /// [`crate::profiler::PipelineStatistics::poll`]. Keeps the previous ones when
+    /// `passes` is empty, as it is on frames whose results are still in flight.
+    pub fn set_pass_statistics(&mut self, mut passes: Vec<PassStatistics>) {
+        let Some(newest) = passes.iter().map(|pass| pass.frame).max() else {
+            return;
+        };
+        passes.retain(|pass| pass.frame == newest);
+        self.passes = passes;
+    }
+
+    /// The counters collected by the last [`StatsOverlay::end_frame`].
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// Draws the overlay into the current framebuffer of `width` x `height` physical
+    /// pixels. The overlay's own draws are not counted.
+    pub fn draw(&mut self, font: &mut Font, width: u32, height: u32) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let mut sorted: Vec<f32> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let p95 = sorted
+            .get((sorted.len() * 95).div_ceil(100).saturating_sub(1))
+            .copied()
+            .unwrap_or(0.0);
+        let worst = sorted.last().copied().unwrap_or(0.0);
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.batch.begin(Mat4::orthographic_rh_gl(
+            0.0,
+            width as f32,
+            height as f32,
+            0.0,
+            -1.0,
+            1.0,
+        ));
+
+        // Laid out in logical pixels.
+        let s = self.scale;
+        let origin = Vec2::splat(8.0 * s);
+        let bar_width = 2.0 * s;
+        let graph = Vec2::new(HISTORY as f32 * bar_width, 48.0 * s);
+        let pass_lines = self.passes.len() as f32 * 17.0 * s;
+        let background = Sprite {
+            color: Vec4::new(0.0, 0.0, 0.0, 0.6),
+            ..Sprite::new(
+                origin - 4.0 * s,
+                Vec2::new(graph.x + 8.0 * s, graph.y + 118.0 * s + pass_lines),
+            )
+        };
+        self.batch.draw(&self.white, &background);
+
+        // Bars scale so that 33 ms (30 FPS) fills the graph.
+        for (i, &dt) in self.frame_times.iter().enumerate() {
+            let bar = (dt / 0.033).min(1.0) * graph.y;
+            let color = if dt > 1.0 / 30.0 {
+                Vec4::new(1.0, 0.3, 0.3, 1.0)
+            } else if dt > 1.0 / 60.0 {
+                Vec4::new(1.0, 0.8, 0.3, 1.0)
+            } else {
+                Vec4::new(0.3, 1.0, 0.4, 1.0)
+            };
+            let sprite = Sprite {
+                color,
+                ..Sprite::new(
+                    Vec2::new(origin.x + i as f32 * bar_width, origin.y + graph.y - bar),
+                    Vec2::new(bar_width, bar),
+                )
+            };
+            self.batch.draw(&self.white, &sprite);
+        }
+
+        let mb = |bytes: usize| bytes as f32 / (1024.0 * 1024.0);
+        let mut text = format!(
+            "{:.0} fps  {:.2} ms (p95 {:.2}, max {:.2})\n{} draws  {} triangles\n\
+             {:.1} MB in {} textures, {:.1} MB in {} buffers",
+            1.0 / average.max(1e-6),
+            average * 1000.0,
+            p95 * 1000.0,
+            worst * 1000.0,
+            self.counters.draw_calls,
+            self.counters.triangles,
+            mb(self.memory.texture_bytes + self.memory.renderbuffer_bytes),
+            self.memory.textures + self.memory.renderbuffers,
+            mb(self.memory.buffer_bytes),
+            self.memory.buffers
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(fps) = crate::pacing::target_fps() {
+            let missed = crate::pacing::stats().missed;
+            text += &format!("\nlimited to {:.0} fps, {} missed", fps, missed);
+        }
+        match self.gpu_memory {
+            Some(GpuMemory {
+                total_kb: Some(total),
+                available_kb,
+                ..
+            }) => text += &format!("\nVRAM {} / {} MB free", available_kb / 1024, total / 1024),
+            Some(gpu) => text += &format!("\nVRAM {} MB free", gpu.available_kb / 1024),
+            None => (),
+        }
+        for pass in &self.passes {
+            text += &format!("\n{}:", pass.name);
+            for (counter, unit) in [
+                (PipelineCounter::VerticesSubmitted, "verts"),
+                (PipelineCounter::PrimitivesGenerated, "prims"),
+                (PipelineCounter::FragmentShaderInvocations, "frags"),
+            ] {
+                if let Some(count) = pass.get(counter) {
+                    text += &format!("  {} {}", abbreviate(count), unit);
+                }
+            }
+        }
+        font.draw(
+            &mut self.batch,
+            &text,
+            Vec2::new(origin.x, origin.y + graph.y + 6.0 * s),
+            14.0 * s,
+            Vec4::ONE,
+        )?;
+        self.batch.end();
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+        take_counters();
+        Ok(())
+    }
+}
+
+/// `count` with a k or M suffix past a thousand.
+fn abbreviate(count: u64) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=999_999 => format!("{:.1}k", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
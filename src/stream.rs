@@ -0,0 +1,239 @@
+//! Background texture streaming.
+//!
+//! Images are decoded on worker threads, then copied into pixel-unpack buffers and
+//! uploaded with `glTexSubImage2D` from the GL thread when [`TextureStreamer::poll`]
+//! is called. The upload itself runs asynchronously on the GPU; a fence tells us when
+//! the staging buffer can be released and the texture handed to the caller.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::Buffer;
+use crate::gl;
+use crate::image::Image;
+use crate::sync::Fence;
+use crate::texture::Texture;
+
+type Callback = Box<dyn FnOnce(Result<Texture>)>;
+
+enum Source {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+}
+
+struct Job {
+    id: u64,
+    source: Source,
+}
+
+struct Decoded {
+    id: u64,
+    image: Result<Image>,
+}
+
+struct InFlight {
+    id: u64,
+    texture: Texture,
+    _staging: Buffer,
+    fence: Fence,
+}
+
+/// A pending upload started by [`TextureStreamer::load`].
+pub struct Upload(Rc<RefCell<Option<Result<Texture>>>>);
+
+impl Upload {
+    /// Takes the finished texture, or returns `None` while the upload is still running.
+    pub fn take(&self) -> Option<Result<Texture>> {
+        self.0.borrow_mut().take()
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.borrow().is_some()
+    }
+}
+
+pub struct TextureStreamer {
+    jobs: Option<Sender<Job>>,
+    decoded: Receiver<Decoded>,
+    workers: Vec<JoinHandle<()>>,
+    callbacks: Vec<(u64, Callback)>,
+    in_flight: Vec<InFlight>,
+    next_id: u64,
+}
+
+impl TextureStreamer {
+    pub fn new(threads: usize) -> TextureStreamer {
+        let (jobs, job_rx) = mpsc::channel::<Job>();
+        let (decoded_tx, decoded) = mpsc::channel();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let job_rx = job_rx.clone();
+                let decoded_tx = decoded_tx.clone();
+                std::thread::spawn(move || loop {
+                    let job = match job_rx.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let image = match job.source {
+                        Source::Path(path) => Image::load(path),
+                        Source::Bytes(bytes) => Image::from_png(bytes.as_slice()),
+                    };
+                    if decoded_tx.send(Decoded { id: job.id, image }).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        TextureStreamer {
+            jobs: Some(jobs),
+            decoded,
+            workers,
+            callbacks: Vec::new(),
+            in_flight: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Starts loading the PNG at `path`; `callback` runs on the GL thread once the
+    /// texture is ready to sample.
+    pub fn load_with<F>(&mut self, path: impl Into<PathBuf>, callback: F)
+    where
+        F: FnOnce(Result<Texture>) + 'static,
+    {
+        self.submit(Source::Path(path.into()), Box::new(callback));
+    }
+
+    /// Starts decoding PNG data that is already in memory.
+    pub fn load_bytes_with<F>(&mut self, bytes: Vec<u8>, callback: F)
+    where
+        F: FnOnce(Result<Texture>) + 'static,
+    {
+        self.submit(Source::Bytes(bytes), Box::new(callback));
+    }
+
+    /// Starts loading the PNG at `path`, returning a handle that can be polled each frame.
+    pub fn load(&mut self, path: impl Into<PathBuf>) -> Upload {
+        let slot = Rc::new(RefCell::new(None));
+        let result = slot.clone();
+        self.load_with(path, move |texture| *result.borrow_mut() = Some(texture));
+        Upload(slot)
+    }
+
+    /// Number of loads that have not completed yet.
+    pub fn pending(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    fn submit(&mut self, source: Source, callback: Callback) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.callbacks.push((id, callback));
+        let job = Job { id, source };
+        if let Err(mpsc::SendError(job)) = self.jobs.as_ref().unwrap().send(job) {
+            self.finish(
+                job.id,
+                Err(anyhow!("Texture streaming workers have stopped")),
+            );
+        }
+    }
+
+    /// Uploads newly decoded images and completes uploads whose fences have signaled.
+    ///
+    /// Must be called from the thread owning the GL context, typically once per frame.
+    pub fn poll(&mut self) {
+        while let Ok(decoded) = self.decoded.try_recv() {
+            match decoded.image.and_then(|image| Self::upload(&image)) {
+                Ok((texture, staging)) => self.in_flight.push(InFlight {
+                    id: decoded.id,
+                    texture,
+                    _staging: staging,
+                    fence: Fence::new(),
+                }),
+                Err(e) => self.finish(decoded.id, Err(e)),
+            }
+        }
+
+        let mut i = 0;
+        while i < self.in_flight.len() {
+            if self.in_flight[i].fence.is_signaled() {
+                let done = self.in_flight.swap_remove(i);
+                self.finish(done.id, Ok(done.texture));
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn upload(image: &Image) -> Result<(Texture, Buffer)> {
+        let size = image.pixels.len();
+        let staging = Buffer::new()?;
+        staging.bind(gl::PIXEL_UNPACK_BUFFER);
+        staging.allocate(gl::PIXEL_UNPACK_BUFFER, size, gl::STREAM_DRAW);
+        let ptr = staging.map_range(
+            gl::PIXEL_UNPACK_BUFFER,
+            0,
+            size,
+            gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT,
+        );
+        let ptr = match ptr {
+            Ok(ptr) => ptr,
+            Err(e) => {
+                staging.unbind(gl::PIXEL_UNPACK_BUFFER);
+                return Err(e);
+            }
+        };
+        unsafe {
+            std::ptr::copy_nonoverlapping(image.pixels.as_ptr(), ptr, size);
+        }
+        staging.unmap(gl::PIXEL_UNPACK_BUFFER);
+
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        let (width, height) = (image.width as i32, image.height as i32);
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.image_2d(
+            0,
+            gl::RGBA8,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            None,
+        );
+        texture.sub_image_2d_from_buffer(0, 0, 0, width, height, gl::RGBA, gl::UNSIGNED_BYTE, 0);
+        texture.unbind();
+        staging.unbind(gl::PIXEL_UNPACK_BUFFER);
+
+        Ok((texture, staging))
+    }
+
+    fn finish(&mut self, id: u64, result: Result<Texture>) {
+        if let Some(i) = self
+            .callbacks
+            .iter()
+            .position(|(pending, _)| *pending == id)
+        {
+            let (_, callback) = self.callbacks.swap_remove(i);
+            callback(result);
+        }
+    }
+}
+
+impl Drop for TextureStreamer {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+use crate::debug;
+use crate::gl;
+
+/// A GPU fence inserted into the command stream with `glFenceSync`.
+pub struct Fence(gl::types::GLsync);
+
+impl Fence {
+    pub fn new() -> Fence {
+        Fence(unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) })
+    }
+
+    /// Names the fence in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::sync_label(self.0, label);
+    }
+
+    /// Returns whether all commands issued before the fence have completed, without blocking.
+    pub fn is_signaled(&self) -> bool {
+        self.wait(Duration::ZERO)
+    }
+
+    /// Blocks for at most `timeout` waiting for the fence. Returns `true` once it has signaled.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let status = unsafe {
+            gl::ClientWaitSync(
+                self.0,
+                gl::SYNC_FLUSH_COMMANDS_BIT,
+                timeout.as_nanos() as gl::types::GLuint64,
+            )
+        };
+        status == gl::ALREADY_SIGNALED || status == gl::CONDITION_SATISFIED
+    }
+}
+
+impl Default for Fence {
+    fn default() -> Self {
+        Fence::new()
+    }
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSync(self.0);
+        }
+    }
+}
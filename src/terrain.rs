@@ -0,0 +1,456 @@
+//! Heightmap terrain with distance-based level of detail and a texture-splatting
+//! material.
+//!
+//! [`Terrain::new`] lays a [`Heightmap`] over a square of [`TerrainConfig::size`] world
+//! units centered on the origin and cuts it into chunks of
+//! [`TerrainConfig::chunk_cells`] cells. Every chunk gets one mesh per LOD level, each
+//! taking every second vertex of the one before; [`Terrain::draw`] picks a level per
+//! chunk from its distance to the camera, the first level change happening at
+//! [`TerrainConfig::lod_distance`] and every further one at twice the distance of the
+//! last. Chunks hang a skirt below their edges so the cracks between neighbours at
+//! different levels don't show.
+//!
+//! The [`TerrainShader`] blends four tiled layer textures by the channels of a splat
+//! map stretched over the whole terrain, and is lit by the [`crate::material`] `Lights`
+//! block.
+
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::culling::{Aabb, CullStats, Frustum};
+use crate::image::Image;
+use crate::material::{self, LIGHTS_BINDING, LIGHTS_GLSL};
+use crate::math::{Mat4, Vec2, Vec3};
+use crate::mesh::{Mesh, Vertex};
+use crate::noise::NoiseConfig;
+use crate::pixels;
+use crate::shader::Program;
+use crate::texture::Texture;
+
+/// Heights in `0..=1` on a grid of samples.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Heightmap {
+    width: u32,
+    depth: u32,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// `heights` holds `depth` rows of `width` samples each.
+    pub fn new(width: u32, depth: u32, heights: Vec<f32>) -> Result<Heightmap> {
+        if width < 2 || depth < 2 {
+            return Err(anyhow!(
+                "A heightmap needs at least 2×2 samples, not {}×{}",
+                width,
+                depth
+            ));
+        }
+        if heights.len() != (width * depth) as usize {
+            return Err(anyhow!(
+                "{} heights for a {}×{} heightmap",
+                heights.len(),
+                width,
+                depth
+            ));
+        }
+        Ok(Heightmap {
+            width,
+            depth,
+            heights,
+        })
+    }
+
+    /// Reads heights from the red channel of a grayscale image, rows top to bottom
+    /// running from -Z to +Z.
+    pub fn from_image(image: &Image) -> Result<Heightmap> {
+        let heights = image
+            .pixels
+            .chunks(4)
+            .map(|pixel| pixels::unorm8_to_f32(pixel[0]))
+            .collect();
+        Heightmap::new(image.width, image.height, heights)
+    }
+
+    /// Samples `height(x, z)` on a `width` × `depth` grid.
+    pub fn from_fn(
+        width: u32,
+        depth: u32,
+        mut height: impl FnMut(u32, u32) -> f32,
+    ) -> Result<Heightmap> {
+        let heights = (0..depth)
+            .flat_map(|z| (0..width).map(move |x| (x, z)))
+            .map(|(x, z)| height(x, z))
+            .collect();
+        Heightmap::new(width, depth, heights)
+    }
+
+    /// Samples `config` on a `width` × `depth` grid, spanning one period of the noise so
+    /// opposite edges match and copies of the terrain tile.
+    pub fn from_noise(width: u32, depth: u32, config: &NoiseConfig) -> Result<Heightmap> {
+        let last = |n: u32| n.saturating_sub(1).max(1) as f32;
+        Heightmap::from_fn(width, depth, |x, z| {
+            config.sample_2d(Vec2::new(x as f32 / last(width), z as f32 / last(depth)))
+        })
+    }
+
+    /// Samples along X and Z.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.depth)
+    }
+
+    /// The sample at `(x, z)`, clamped to the edges.
+    pub fn height(&self, x: i64, z: i64) -> f32 {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let z = z.clamp(0, self.depth as i64 - 1) as usize;
+        self.heights[z * self.width as usize + x]
+    }
+
+    /// Bilinearly interpolated height at fractional sample coordinates.
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (fx, fz) = (x - x0, z - z0);
+        let (x0, z0) = (x0 as i64, z0 as i64);
+        let top = lerp(self.height(x0, z0), self.height(x0 + 1, z0), fx);
+        let bottom = lerp(self.height(x0, z0 + 1), self.height(x0 + 1, z0 + 1), fx);
+        lerp(top, bottom, fz)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TerrainConfig {
+    /// Extent along X and Z in world units.
+    pub size: f32,
+    /// World height of a heightmap value of 1.
+    pub height: f32,
+    /// Cells along each side of a chunk at full detail; a power of two.
+    pub chunk_cells: u32,
+    /// Number of detail levels, including full detail.
+    pub lod_levels: u32,
+    /// Distance from the camera beyond which chunks drop to the second level.
+    pub lod_distance: f32,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        TerrainConfig {
+            size: 100.0,
+            height: 20.0,
+            chunk_cells: 32,
+            lod_levels: 4,
+            lod_distance: 30.0,
+        }
+    }
+}
+
+struct Chunk {
+    lods: Vec<Mesh>,
+    bounds: Aabb,
+}
+
+pub struct Terrain {
+    heightmap: Heightmap,
+    config: TerrainConfig,
+    chunks: Vec<Chunk>,
+}
+
+impl Terrain {
+    pub fn new(heightmap: Heightmap, config: TerrainConfig) -> Result<Terrain> {
+        if !config.chunk_cells.is_power_of_two() {
+            return Err(anyhow!(
+                "Terrain chunks must be a power of two cells wide, not {}",
+                config.chunk_cells
+            ));
+        }
+        let lod_levels = config
+            .lod_levels
+            .clamp(1, config.chunk_cells.trailing_zeros() + 1);
+        let (width, depth) = heightmap.size();
+        let mut terrain = Terrain {
+            heightmap,
+            config: TerrainConfig {
+                lod_levels,
+                ..config
+            },
+            chunks: Vec::new(),
+        };
+
+        let skirt = config.height * 0.05 + config.size / (width.max(depth) - 1) as f32;
+        for z0 in (0..depth - 1).step_by(config.chunk_cells as usize) {
+            for x0 in (0..width - 1).step_by(config.chunk_cells as usize) {
+                let x1 = (x0 + config.chunk_cells).min(width - 1);
+                let z1 = (z0 + config.chunk_cells).min(depth - 1);
+                let mut lods = Vec::with_capacity(lod_levels as usize);
+                let mut bounds = Aabb::EMPTY;
+                for lod in 0..lod_levels {
+                    let (vertices, indices) = terrain.chunk_mesh(x0, x1, z0, z1, 1 << lod, skirt);
+                    if lod == 0 {
+                        bounds = Aabb::from_points(
+                            vertices.iter().map(|v| Vec3::from_array(v.position)),
+                        );
+                    }
+                    let mesh = Mesh::new(&vertices, &indices)?;
+                    mesh.label(&format!("terrain chunk ({}, {}) LOD {}", x0, z0, lod));
+                    lods.push(mesh);
+                }
+                terrain.chunks.push(Chunk { lods, bounds });
+            }
+        }
+        Ok(terrain)
+    }
+
+    pub fn config(&self) -> &TerrainConfig {
+        &self.config
+    }
+
+    pub fn heightmap(&self) -> &Heightmap {
+        &self.heightmap
+    }
+
+    /// World-space position of heightmap sample `(x, z)`.
+    fn position(&self, x: u32, z: u32) -> Vec3 {
+        let (width, depth) = self.heightmap.size();
+        Vec3::new(
+            (x as f32 / (width - 1) as f32 - 0.5) * self.config.size,
+            self.heightmap.height(x as i64, z as i64) * self.config.height,
+            (z as f32 / (depth - 1) as f32 - 0.5) * self.config.size,
+        )
+    }
+
+    /// The surface normal at sample `(x, z)`, from central differences.
+    fn normal(&self, x: u32, z: u32) -> Vec3 {
+        let (width, depth) = self.heightmap.size();
+        let (x, z) = (x as i64, z as i64);
+        let h = |x, z| self.heightmap.height(x, z) * self.config.height;
+        let dx = self.config.size / (width - 1) as f32;
+        let dz = self.config.size / (depth - 1) as f32;
+        Vec3::new(
+            (h(x - 1, z) - h(x + 1, z)) / (2.0 * dx),
+            1.0,
+            (h(x, z - 1) - h(x, z + 1)) / (2.0 * dz),
+        )
+        .normalize()
+    }
+
+    /// The terrain height under world position `(x, z)`, or at the nearest edge.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let (width, depth) = self.heightmap.size();
+        let u = (x / self.config.size + 0.5) * (width - 1) as f32;
+        let v = (z / self.config.size + 0.5) * (depth - 1) as f32;
+        self.heightmap.sample(u, v) * self.config.height
+    }
+
+    /// The grid of the chunk spanning samples `x0..=x1` × `z0..=z1`, taking every
+    /// `step`th sample, with a skirt `skirt` units deep around it.
+    fn chunk_mesh(
+        &self,
+        x0: u32,
+        x1: u32,
+        z0: u32,
+        z1: u32,
+        step: u32,
+        skirt: f32,
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        let (width, depth) = self.heightmap.size();
+        let xs = samples(x0, x1, step);
+        let zs = samples(z0, z1, step);
+        let columns = xs.len() as u32;
+        let vertex = |x: u32, z: u32, drop: f32| {
+            let position = self.position(x, z) - Vec3::Y * drop;
+            Vertex {
+                position: position.to_array(),
+                normal: self.normal(x, z).to_array(),
+                uv: [x as f32 / (width - 1) as f32, z as f32 / (depth - 1) as f32],
+            }
+        };
+
+        let mut vertices = Vec::new();
+        for &z in &zs {
+            for &x in &xs {
+                vertices.push(vertex(x, z, 0.0));
+            }
+        }
+        let mut indices = Vec::new();
+        let mut quad = |a: u32, b: u32, c: u32, d: u32| {
+            indices.extend_from_slice(&[a, c, b, b, c, d]);
+        };
+        for row in 0..zs.len() as u32 - 1 {
+            for column in 0..columns - 1 {
+                let a = row * columns + column;
+                quad(a, a + 1, a + columns, a + columns + 1);
+            }
+        }
+
+        // Each edge, walked so the skirt faces outwards, as grid indices.
+        let last_row = (zs.len() as u32 - 1) * columns;
+        let edges: [Vec<u32>; 4] = [
+            (0..columns).rev().collect(),
+            (0..columns).map(|c| last_row + c).collect(),
+            (0..zs.len() as u32).map(|r| r * columns).collect(),
+            (0..zs.len() as u32)
+                .rev()
+                .map(|r| r * columns + columns - 1)
+                .collect(),
+        ];
+        for edge in edges {
+            let base = vertices.len() as u32;
+            for &index in &edge {
+                let top = vertices[index as usize];
+                vertices.push(Vertex {
+                    position: [top.position[0], top.position[1] - skirt, top.position[2]],
+                    ..top
+                });
+            }
+            for (i, pair) in edge.windows(2).enumerate() {
+                let i = i as u32;
+                quad(pair[0], pair[1], base + i, base + i + 1);
+            }
+        }
+        (vertices, indices)
+    }
+
+    /// The detail level for a chunk `distance` units from the camera.
+    fn lod(&self, distance: f32) -> usize {
+        if distance < self.config.lod_distance {
+            return 0;
+        }
+        let level = (distance / self.config.lod_distance).log2().floor() as usize + 1;
+        level.min(self.config.lod_levels as usize - 1)
+    }
+
+    /// Draws the chunks with `program`, which must be in use, choosing each chunk's
+    /// level by its distance to `camera_position` and skipping chunks outside
+    /// `frustum` when given.
+    pub fn draw(
+        &self,
+        program: &Program,
+        camera_position: Vec3,
+        frustum: Option<&Frustum>,
+    ) -> CullStats {
+        program.set_mat4("u_model", &Mat4::IDENTITY.to_cols_array());
+        let mut stats = CullStats::default();
+        for chunk in &self.chunks {
+            if frustum.is_some_and(|frustum| !frustum.intersects_aabb(&chunk.bounds)) {
+                stats.culled += 1;
+                continue;
+            }
+            let closest = camera_position.clamp(chunk.bounds.min, chunk.bounds.max);
+            let lod = self.lod(closest.distance(camera_position));
+            chunk.lods[lod].draw();
+            stats.visible += 1;
+        }
+        stats
+    }
+}
+
+/// `start..=end` in steps of `step`, always ending at `end`.
+fn samples(start: u32, end: u32, step: u32) -> Vec<u32> {
+    let mut samples: Vec<u32> = (start..end).step_by(step as usize).collect();
+    samples.push(end);
+    samples
+}
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv;
+uniform mat4 u_model;
+uniform mat4 u_view_projection;
+out vec3 v_world;
+out vec3 v_normal;
+out vec2 v_uv;
+void main() {
+    vec4 world = u_model * vec4(a_position, 1.0);
+    v_world = world.xyz;
+    v_normal = mat3(u_model) * a_normal;
+    v_uv = a_uv;
+    gl_Position = u_view_projection * world;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+in vec3 v_world;
+in vec3 v_normal;
+in vec2 v_uv;
+out vec4 frag_color;
+uniform sampler2D u_splat_map;
+uniform sampler2D u_layers[4];
+uniform float u_tiling;
+
+void main() {
+    vec4 weights = texture(u_splat_map, v_uv);
+    weights /= max(dot(weights, vec4(1.0)), 1e-4);
+    vec2 uv = v_uv * u_tiling;
+    vec3 albedo = texture(u_layers[0], uv).rgb * weights.r
+        + texture(u_layers[1], uv).rgb * weights.g
+        + texture(u_layers[2], uv).rgb * weights.b
+        + texture(u_layers[3], uv).rgb * weights.a;
+    vec3 n = normalize(v_normal);
+    vec3 color = ambient.rgb * albedo;
+    for (int i = 0; i < light_count.x; ++i) {
+        vec3 l;
+        vec3 radiance = light_radiance(lights[i], v_world, l);
+        color += albedo * radiance * max(dot(n, l), 0.0);
+    }
+    frag_color = vec4(color, 1.0);
+}
+"#;
+
+/// The textures of a [`TerrainShader`]: a splat map whose red, green, blue and alpha
+/// channels weigh the four layers, and the layers, repeated `tiling` times across the
+/// terrain.
+#[derive(Clone)]
+pub struct SplatMaterial {
+    pub splat_map: Rc<Texture>,
+    pub layers: [Rc<Texture>; 4],
+    pub tiling: f32,
+}
+
+/// The texture-splatting terrain program. The splat map is bound to unit 0 and the
+/// layers to units 1 to 4.
+pub struct TerrainShader {
+    program: Program,
+}
+
+impl TerrainShader {
+    pub fn new() -> Result<TerrainShader> {
+        let fragment = format!("#version 330 core\n{}\n{}", LIGHTS_GLSL, FRAGMENT_SHADER);
+        let program = Program::from_sources(VERTEX_SHADER, &fragment)?;
+        material::check_lights_block(&program)?;
+        program.label("terrain");
+        program.bind_uniform_block("Lights", LIGHTS_BINDING);
+        program.use_program();
+        program.set_int("u_splat_map", 0);
+        for layer in 0..4 {
+            program.set_int(&format!("u_layers[{}]", layer), layer + 1);
+        }
+        Ok(TerrainShader { program })
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// Uses the program and sets the camera.
+    pub fn set_camera(&self, view_projection: Mat4) {
+        self.program.use_program();
+        self.program
+            .set_mat4("u_view_projection", &view_projection.to_cols_array());
+    }
+
+    /// Uses the program and binds `material`'s textures. Returns the program for
+    /// [`Terrain::draw`].
+    pub fn bind(&self, material: &SplatMaterial) -> &Program {
+        self.program.use_program();
+        material.splat_map.bind_unit(0);
+        for (unit, layer) in material.layers.iter().enumerate() {
+            layer.bind_unit(unit as u32 + 1);
+        }
+        self.program.set_float("u_tiling", material.tiling);
+        &self.program
+    }
+}
@@ -0,0 +1,214 @@
+//! Text rendering from a glyph atlas.
+//!
+//! [`Font`] rasterizes glyphs with `fontdue` on first use and packs them into an atlas
+//! texture, then draws strings as sprites through a [`SpriteBatch`]. Coordinates are in
+//! pixels with y pointing down, matching an orthographic projection with the origin at
+//! the top-left.
+//!
+//! Sizes are in framebuffer (physical) pixels. Multiply them by the window's
+//! `scale_factor` so text keeps its size on HiDPI displays; glyphs are rasterized at
+//! the resulting size, so they stay sharp rather than being magnified.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use crate::atlas::ShelfPacker;
+use crate::gl;
+use crate::math::{Vec2, Vec4};
+use crate::sprite::{Rect, Sprite, SpriteBatch};
+use crate::texture::Texture;
+
+/// Padding between packed glyphs, so linear filtering doesn't bleed neighbours in.
+const GLYPH_PADDING: u32 = 1;
+
+/// Vertical metrics of a font at one size, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineMetrics {
+    /// Distance from the baseline to the top of the tallest glyphs.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest glyphs; negative.
+    pub descent: f32,
+    /// Distance between consecutive baselines.
+    pub line_height: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Glyph {
+    uv: Rect,
+    size: Vec2,
+    /// Offset of the bitmap's top-left corner from the pen position on the baseline.
+    offset: Vec2,
+    advance: f32,
+}
+
+pub struct Font {
+    font: fontdue::Font,
+    texture: Rc<Texture>,
+    packer: ShelfPacker,
+    /// Keyed by character and pixel size bits.
+    glyphs: HashMap<(char, u32), Glyph>,
+}
+
+impl Font {
+    pub fn load<P: AsRef<Path>>(path: P, atlas_size: u32) -> Result<Font> {
+        let bytes = std::fs::read(path.as_ref())
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.as_ref().display(), e))?;
+        let font = Font::from_bytes(&bytes, atlas_size)?;
+        font.texture
+            .label(&format!("{} atlas", path.as_ref().display()));
+        Ok(font)
+    }
+
+    /// Parses a TrueType or OpenType font and creates an empty `atlas_size` square atlas.
+    pub fn from_bytes(bytes: &[u8], atlas_size: u32) -> Result<Font> {
+        let font = fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+            .map_err(|e| anyhow!("Failed to parse font: {}", e))?;
+
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        let clear = vec![0u8; (atlas_size * atlas_size * 4) as usize];
+        texture.image_2d(
+            0,
+            gl::RGBA8,
+            atlas_size as i32,
+            atlas_size as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&clear),
+        );
+        texture.unbind();
+        texture.label("font atlas");
+
+        Ok(Font {
+            font,
+            texture: Rc::new(texture),
+            packer: ShelfPacker::new(atlas_size, atlas_size, GLYPH_PADDING),
+            glyphs: HashMap::new(),
+        })
+    }
+
+    /// The atlas texture: white, with glyph coverage in alpha.
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        match self.font.horizontal_line_metrics(size) {
+            Some(metrics) => LineMetrics {
+                ascent: metrics.ascent,
+                descent: metrics.descent,
+                line_height: metrics.new_line_size,
+            },
+            None => LineMetrics {
+                ascent: size,
+                descent: 0.0,
+                line_height: size,
+            },
+        }
+    }
+
+    fn glyph(&mut self, c: char, size: f32) -> Result<Glyph> {
+        if let Some(glyph) = self.glyphs.get(&(c, size.to_bits())) {
+            return Ok(*glyph);
+        }
+        let (metrics, coverage) = self.font.rasterize(c, size);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+        let (x, y) = if width == 0 || height == 0 {
+            (0, 0)
+        } else {
+            let (x, y) = self
+                .packer
+                .pack(width, height)
+                .ok_or_else(|| anyhow!("Glyph atlas is full"))?;
+            let pixels: Vec<u8> = coverage.iter().flat_map(|&a| [255, 255, 255, a]).collect();
+            self.texture.bind();
+            self.texture.sub_image_2d(
+                0,
+                x as i32,
+                y as i32,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                &pixels,
+            );
+            self.texture.unbind();
+            (x, y)
+        };
+        let atlas = self.packer.width() as f32;
+        let glyph = Glyph {
+            uv: Rect::new(
+                x as f32 / atlas,
+                y as f32 / atlas,
+                width as f32 / atlas,
+                height as f32 / atlas,
+            ),
+            size: Vec2::new(width as f32, height as f32),
+            offset: Vec2::new(metrics.xmin as f32, -(metrics.ymin as f32 + height as f32)),
+            advance: metrics.advance_width,
+        };
+        self.glyphs.insert((c, size.to_bits()), glyph);
+        Ok(glyph)
+    }
+
+    /// Lays out `text`, calling `f` with every visible glyph and the position of its
+    /// top-left corner relative to the first line's top-left. Returns the extent.
+    fn layout<F: FnMut(&Glyph, Vec2)>(&mut self, text: &str, size: f32, mut f: F) -> Result<Vec2> {
+        let metrics = self.line_metrics(size);
+        let mut pen = Vec2::new(0.0, metrics.ascent);
+        let mut width: f32 = 0.0;
+        let mut previous = None;
+        for c in text.chars() {
+            if c == '\n' {
+                width = width.max(pen.x);
+                pen = Vec2::new(0.0, pen.y + metrics.line_height);
+                previous = None;
+                continue;
+            }
+            if let Some(previous) = previous {
+                pen.x += self.font.horizontal_kern(previous, c, size).unwrap_or(0.0);
+            }
+            let glyph = self.glyph(c, size)?;
+            if glyph.size.x > 0.0 {
+                f(&glyph, pen + glyph.offset);
+            }
+            pen.x += glyph.advance;
+            previous = Some(c);
+        }
+        width = width.max(pen.x);
+        Ok(Vec2::new(width, pen.y - metrics.descent))
+    }
+
+    /// Width and height of `text` at `size` pixels, including all lines.
+    pub fn measure(&mut self, text: &str, size: f32) -> Result<Vec2> {
+        self.layout(text, size, |_, _| {})
+    }
+
+    /// Draws `text` with its top-left corner at `position` and returns its extent.
+    /// The batch must be between `begin` and `end`.
+    pub fn draw(
+        &mut self,
+        batch: &mut SpriteBatch,
+        text: &str,
+        position: Vec2,
+        size: f32,
+        color: Vec4,
+    ) -> Result<Vec2> {
+        let texture = self.texture.clone();
+        self.layout(text, size, |glyph, offset| {
+            let sprite = Sprite {
+                uv: glyph.uv,
+                color,
+                ..Sprite::new((position + offset).round(), glyph.size)
+            };
+            batch.draw(&texture, &sprite);
+        })
+    }
+}
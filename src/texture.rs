@@ -0,0 +1,804 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{anyhow, Result};
+
+use crate::context::{self, Deletion, GlContext};
+use crate::debug;
+use crate::dsa;
+use crate::framebuffer::Framebuffer;
+use crate::gl;
+use crate::image::{Image, TexelImage};
+use crate::memory::{self, Resource};
+use crate::pixels;
+use crate::scoped::{self, Binding};
+use crate::state::StateCache;
+
+/// Number of levels in a full mipmap chain for a `width`×`height` image.
+pub fn mip_levels(width: i32, height: i32) -> i32 {
+    32 - (width.max(height).max(1) as u32).leading_zeros() as i32
+}
+
+/// Whether `target` has sampler state; multisample and buffer textures do not.
+fn filterable(target: gl::types::GLenum) -> bool {
+    !matches!(
+        target,
+        gl::TEXTURE_2D_MULTISAMPLE | gl::TEXTURE_2D_MULTISAMPLE_ARRAY | gl::TEXTURE_BUFFER
+    )
+}
+
+/// [`set_default_anisotropy`], as the bits of an `f32`; `1.0` to start with.
+static DEFAULT_ANISOTROPY: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+/// The highest anisotropy the current context supports: `GL_MAX_TEXTURE_MAX_ANISOTROPY`
+/// with GL 4.6 or `GL_{ARB,EXT}_texture_filter_anisotropic`, `1.0` (none) without.
+pub fn max_anisotropy() -> f32 {
+    let info = context::info();
+    let supported = (!info.es && info.version >= (4, 6))
+        || info.has_extension("GL_ARB_texture_filter_anisotropic")
+        || info.has_extension("GL_EXT_texture_filter_anisotropic");
+    if !supported {
+        return 1.0;
+    }
+    let mut max = 1.0;
+    unsafe {
+        gl::GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+    }
+    max.max(1.0)
+}
+
+/// `anisotropy` clamped to what the context supports, or `None` if it supports none and
+/// the parameter must not be set.
+fn clamp_anisotropy(anisotropy: f32) -> Option<f32> {
+    let max = max_anisotropy();
+    (max > 1.0).then(|| anisotropy.clamp(1.0, max))
+}
+
+/// `bias` clamped to `±GL_MAX_TEXTURE_LOD_BIAS`, or `None` on OpenGL ES, which has no
+/// LOD bias parameter.
+fn clamp_lod_bias(bias: f32) -> Option<f32> {
+    if context::info().es {
+        return None;
+    }
+    let mut max = 0.0;
+    unsafe {
+        gl::GetFloatv(gl::MAX_TEXTURE_LOD_BIAS, &mut max);
+    }
+    Some(bias.clamp(-max, max))
+}
+
+/// Sets the anisotropy [`Texture::from_image`] gives the textures it creates, for every
+/// thread. `1.0`, the default, turns anisotropic filtering off; values above
+/// [`max_anisotropy`] are clamped when applied. The runners set the `anisotropy`
+/// setting.
+pub fn set_default_anisotropy(anisotropy: f32) {
+    DEFAULT_ANISOTROPY.store(anisotropy.max(1.0).to_bits(), Ordering::Relaxed);
+}
+
+pub fn default_anisotropy() -> f32 {
+    f32::from_bits(DEFAULT_ANISOTROPY.load(Ordering::Relaxed))
+}
+
+/// How the texels of a class of internal formats are read back; see [`TexelImage`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TexelClass {
+    Unorm8,
+    Float,
+    Depth,
+    Unsigned,
+    Signed,
+}
+
+impl TexelClass {
+    pub(crate) fn of(internal_format: gl::types::GLenum) -> TexelClass {
+        match internal_format {
+            gl::R8 | gl::RG8 | gl::RGB8 | gl::RGBA8 | gl::SRGB8 | gl::SRGB8_ALPHA8 => {
+                TexelClass::Unorm8
+            }
+            gl::DEPTH_COMPONENT16
+            | gl::DEPTH_COMPONENT24
+            | gl::DEPTH_COMPONENT32
+            | gl::DEPTH_COMPONENT32F
+            | gl::DEPTH24_STENCIL8
+            | gl::DEPTH32F_STENCIL8 => TexelClass::Depth,
+            gl::R8UI
+            | gl::RG8UI
+            | gl::RGB8UI
+            | gl::RGBA8UI
+            | gl::R16UI
+            | gl::RG16UI
+            | gl::RGB16UI
+            | gl::RGBA16UI
+            | gl::R32UI
+            | gl::RG32UI
+            | gl::RGB32UI
+            | gl::RGBA32UI
+            | gl::RGB10_A2UI => TexelClass::Unsigned,
+            gl::R8I
+            | gl::RG8I
+            | gl::RGB8I
+            | gl::RGBA8I
+            | gl::R16I
+            | gl::RG16I
+            | gl::RGB16I
+            | gl::RGBA16I
+            | gl::R32I
+            | gl::RG32I
+            | gl::RGB32I
+            | gl::RGBA32I => TexelClass::Signed,
+            _ => TexelClass::Float,
+        }
+    }
+
+    /// The pixel transfer format and type to read with.
+    fn transfer(self) -> (gl::types::GLenum, gl::types::GLenum) {
+        match self {
+            TexelClass::Unorm8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+            TexelClass::Float => (gl::RGBA, gl::FLOAT),
+            TexelClass::Depth => (gl::DEPTH_COMPONENT, gl::FLOAT),
+            TexelClass::Unsigned => (gl::RGBA_INTEGER, gl::UNSIGNED_INT),
+            TexelClass::Signed => (gl::RGBA_INTEGER, gl::INT),
+        }
+    }
+}
+
+/// Reads `width`×`height` texels of `class` with `read`, which is given the transfer
+/// format, type and destination, with tight row packing, and flips the rows top-down.
+pub(crate) fn read_texels(
+    class: TexelClass,
+    width: i32,
+    height: i32,
+    read: impl FnOnce(gl::types::GLenum, gl::types::GLenum, *mut gl::types::GLvoid),
+) -> TexelImage {
+    let (format, ty) = class.transfer();
+    let (width, height) = (width.max(0) as u32, height.max(0) as u32);
+    let count = width as usize * height as usize;
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+    }
+    let image = match class {
+        TexelClass::Unorm8 => {
+            let mut pixels = vec![0u8; count * 4];
+            read(format, ty, pixels.as_mut_ptr().cast());
+            let mut image = Image {
+                width,
+                height,
+                pixels,
+            };
+            image.flip_vertically();
+            TexelImage::Rgba8(image)
+        }
+        TexelClass::Float => {
+            let mut pixels = vec![[0.0f32; 4]; count];
+            read(format, ty, pixels.as_mut_ptr().cast());
+            pixels::flip_rows(&mut pixels, width as usize);
+            TexelImage::Float {
+                width,
+                height,
+                pixels,
+            }
+        }
+        TexelClass::Depth => {
+            let mut depth = vec![0.0f32; count];
+            read(format, ty, depth.as_mut_ptr().cast());
+            pixels::flip_rows(&mut depth, width as usize);
+            TexelImage::Float {
+                width,
+                height,
+                pixels: depth.into_iter().map(|d| [d, d, d, 1.0]).collect(),
+            }
+        }
+        TexelClass::Unsigned | TexelClass::Signed => {
+            let mut pixels = vec![[0u32; 4]; count];
+            read(format, ty, pixels.as_mut_ptr().cast());
+            pixels::flip_rows(&mut pixels, width as usize);
+            TexelImage::Integer {
+                width,
+                height,
+                pixels,
+            }
+        }
+    };
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+    }
+    image
+}
+
+/// Width, height and internal format of mip `level` of texture `id`. Without DSA the
+/// texture is bound to `target` and queried through `level_target`, which for a cube map
+/// face is the face's target, and `target` is left unbound.
+pub(crate) fn level_info(
+    id: gl::types::GLuint,
+    target: gl::types::GLenum,
+    level_target: gl::types::GLenum,
+    level: i32,
+) -> (i32, i32, gl::types::GLenum) {
+    let (mut width, mut height, mut internal_format) = (0, 0, 0);
+    unsafe {
+        if dsa::is_available() {
+            gl::GetTextureLevelParameteriv(id, level, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTextureLevelParameteriv(id, level, gl::TEXTURE_HEIGHT, &mut height);
+            gl::GetTextureLevelParameteriv(
+                id,
+                level,
+                gl::TEXTURE_INTERNAL_FORMAT,
+                &mut internal_format,
+            );
+        } else {
+            gl::BindTexture(target, id);
+            gl::GetTexLevelParameteriv(level_target, level, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTexLevelParameteriv(level_target, level, gl::TEXTURE_HEIGHT, &mut height);
+            gl::GetTexLevelParameteriv(
+                level_target,
+                level,
+                gl::TEXTURE_INTERNAL_FORMAT,
+                &mut internal_format,
+            );
+            gl::BindTexture(target, 0);
+        }
+    }
+    (width, height, internal_format as gl::types::GLenum)
+}
+
+pub struct Texture {
+    id: gl::types::GLuint,
+    target: gl::types::GLenum,
+    /// Estimated bytes of each mip level's storage, for [`crate::memory::usage`].
+    levels: RefCell<Vec<usize>>,
+    context: GlContext,
+}
+
+impl Texture {
+    /// Creates a texture object. Filterable targets start with a `GL_LINEAR` min filter
+    /// rather than GL's mipmapped default, which would leave a texture with only level 0
+    /// incomplete and sampling black; see [`Texture::generate_mipmaps`].
+    pub fn new(target: gl::types::GLenum) -> Result<Texture> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateTextures(target, 1, &mut id);
+            } else {
+                gl::GenTextures(1, &mut id);
+            }
+        }
+        if id == 0 {
+            return Err(anyhow!("Failed to create texture"));
+        }
+        memory::track_object(Resource::Texture, 1);
+        let texture = Texture {
+            id,
+            target,
+            levels: RefCell::new(Vec::new()),
+            context: gl_context,
+        };
+        if filterable(target) {
+            // Without DSA the name only becomes a texture object once bound.
+            if !dsa::is_available() {
+                texture.bind();
+            }
+            texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            if !dsa::is_available() {
+                texture.unbind();
+            }
+        }
+        Ok(texture)
+    }
+
+    /// Creates an `RGBA8` 2D texture from a decoded image with a full mipmap chain,
+    /// trilinear filtering with the [`default_anisotropy`] and repeat wrapping.
+    pub fn from_image(image: &Image) -> Result<Texture> {
+        context::limits().check_texture_size(image.width as i32, image.height as i32)?;
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
+        texture.image_level(0, image);
+        texture.generate_mipmaps();
+        let anisotropy = default_anisotropy();
+        if anisotropy > 1.0 {
+            texture.set_anisotropy(anisotropy);
+        }
+        texture.unbind();
+        Ok(texture)
+    }
+
+    /// Creates a `size`×`size` cube map with `levels` mip levels of uninitialized
+    /// `internal_format` storage, linear filtering (trilinear when mipmapped) and
+    /// clamped edges. Filtering across faces is seamless with
+    /// [`crate::context::ContextOptions::seamless_cube_maps`], on by default.
+    pub fn cube_map(internal_format: gl::types::GLenum, size: i32, levels: i32) -> Result<Texture> {
+        context::limits().check_cube_map_size(size)?;
+        let texture = Texture::new(gl::TEXTURE_CUBE_MAP)?;
+        texture.bind();
+        let min_filter = if levels > 1 {
+            gl::LINEAR_MIPMAP_LINEAR
+        } else {
+            gl::LINEAR
+        };
+        texture.parameter(gl::TEXTURE_MIN_FILTER, min_filter as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+            texture.parameter(wrap, gl::CLAMP_TO_EDGE as i32);
+        }
+        for level in 0..levels {
+            let size = (size >> level).max(1);
+            for face in 0..6 {
+                texture.image_cube_face(
+                    face,
+                    level,
+                    internal_format,
+                    size,
+                    gl::RGBA,
+                    gl::FLOAT,
+                    None,
+                );
+            }
+        }
+        texture.level_range(0, levels - 1);
+        texture.unbind();
+        Ok(texture)
+    }
+
+    /// A 1x1 `RGBA8` 2D texture of one color, e.g. to bind for an unused sampler.
+    pub fn solid(rgba: [u8; 4]) -> Result<Texture> {
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        texture.image_2d(0, gl::RGBA8, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, Some(&rgba));
+        texture.unbind();
+        Ok(texture)
+    }
+
+    /// Specifies mip level `level` of the bound 2D texture from a decoded image as
+    /// `RGBA8`. Levels uploaded by hand should halve in size from level 0; limit
+    /// sampling to the levels present with [`Texture::level_range`].
+    pub fn image_level(&self, level: i32, image: &Image) {
+        self.image_2d(
+            level,
+            gl::RGBA8,
+            image.width as i32,
+            image.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&image.pixels),
+        );
+    }
+
+    /// Fills every level above the base level by downsampling it. Without DSA the
+    /// texture must be bound. Use a `*_MIPMAP_*` min filter to sample the result.
+    pub fn generate_mipmaps(&self) {
+        unsafe {
+            if dsa::is_available() {
+                gl::GenerateTextureMipmap(self.id);
+            } else {
+                gl::GenerateMipmap(self.target);
+            }
+        }
+        // Each level is a quarter of the one before.
+        let (base, previous) = {
+            let levels = self.levels.borrow();
+            (levels.first().copied().unwrap_or(0), levels.len() as i32)
+        };
+        let count = (usize::BITS - base.leading_zeros()).div_ceil(2) as i32;
+        for level in 1..count.max(previous) {
+            let bytes = if level < count {
+                base >> (2 * level)
+            } else {
+                0
+            };
+            self.set_level_size(level, bytes);
+        }
+    }
+
+    /// Estimated bytes of storage specified through this wrapper, over all levels.
+    pub fn size(&self) -> usize {
+        self.levels.borrow().iter().sum()
+    }
+
+    fn set_level_size(&self, level: i32, bytes: usize) {
+        let mut levels = self.levels.borrow_mut();
+        let level = level as usize;
+        if levels.len() <= level {
+            levels.resize(level + 1, 0);
+        }
+        memory::track_bytes(Resource::Texture, levels[level], bytes);
+        levels[level] = bytes;
+    }
+
+    /// Restricts the texture to levels `base..=max`, for textures whose chain is only
+    /// partially uploaded or that should never be sampled past a given level.
+    pub fn level_range(&self, base: i32, max: i32) {
+        self.parameter(gl::TEXTURE_BASE_LEVEL, base);
+        self.parameter(gl::TEXTURE_MAX_LEVEL, max);
+    }
+
+    /// Clamps the level of detail chosen when sampling to `min..=max`, e.g. to hold a
+    /// streamed texture at a coarse level until its finer levels arrive.
+    pub fn lod_range(&self, min: f32, max: f32) {
+        self.parameter_f(gl::TEXTURE_MIN_LOD, min);
+        self.parameter_f(gl::TEXTURE_MAX_LOD, max);
+    }
+
+    /// Samples up to `anisotropy` texels along the direction a mipmapped texture is
+    /// stretched in, sharpening surfaces seen at grazing angles. Clamped to
+    /// [`max_anisotropy`]; ignored by contexts without anisotropic filtering. Without DSA
+    /// the texture must be bound.
+    pub fn set_anisotropy(&self, anisotropy: f32) {
+        if let Some(anisotropy) = clamp_anisotropy(anisotropy) {
+            self.parameter_f(gl::TEXTURE_MAX_ANISOTROPY, anisotropy);
+        }
+    }
+
+    /// Offsets the mip level chosen when sampling, negative for sharper and positive for
+    /// blurrier. Clamped to `GL_MAX_TEXTURE_LOD_BIAS`; ignored on OpenGL ES. Without DSA
+    /// the texture must be bound.
+    pub fn set_lod_bias(&self, bias: f32) {
+        if let Some(bias) = clamp_lod_bias(bias) {
+            self.parameter_f(gl::TEXTURE_LOD_BIAS, bias);
+        }
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    /// Names the texture in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::TEXTURE, self.id, label);
+    }
+
+    pub fn target(&self) -> gl::types::GLenum {
+        self.target
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindTexture(self.target, self.id);
+        }
+    }
+
+    pub fn unbind(&self) {
+        unsafe {
+            gl::BindTexture(self.target, 0);
+        }
+    }
+
+    /// Binds the texture to texture unit `unit` through `cache` until the guard is
+    /// dropped. See [`crate::scoped`].
+    pub fn bind_scoped<'a>(&'a self, cache: &'a mut StateCache, unit: u32) -> Binding<'a> {
+        scoped::bind_texture(cache, unit, self.target, self.id)
+    }
+
+    /// Binds the texture to texture unit `unit` (`0` for `GL_TEXTURE0`).
+    pub fn bind_unit(&self, unit: u32) {
+        unsafe {
+            if dsa::is_available() {
+                gl::BindTextureUnit(unit, self.id);
+            } else {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+                gl::BindTexture(self.target, self.id);
+            }
+        }
+    }
+
+    pub fn parameter(&self, name: gl::types::GLenum, value: gl::types::GLint) {
+        unsafe {
+            if dsa::is_available() {
+                gl::TextureParameteri(self.id, name, value);
+            } else {
+                gl::TexParameteri(self.target, name, value);
+            }
+        }
+    }
+
+    pub fn parameter_f(&self, name: gl::types::GLenum, value: gl::types::GLfloat) {
+        unsafe {
+            if dsa::is_available() {
+                gl::TextureParameterf(self.id, name, value);
+            } else {
+                gl::TexParameterf(self.target, name, value);
+            }
+        }
+    }
+
+    /// Specifies a 2D image for the bound texture. Passing `None` allocates storage only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn image_2d(
+        &self,
+        level: i32,
+        internal_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+        format: gl::types::GLenum,
+        ty: gl::types::GLenum,
+        data: Option<&[u8]>,
+    ) {
+        unsafe {
+            gl::TexImage2D(
+                self.target,
+                level,
+                internal_format as gl::types::GLint,
+                width,
+                height,
+                0,
+                format,
+                ty,
+                data.map_or(std::ptr::null(), |data| data.as_ptr().cast()),
+            );
+        }
+        let pixels = width as usize * height as usize;
+        self.set_level_size(level, pixels * memory::bytes_per_pixel(internal_format));
+    }
+
+    /// Specifies face `face` of the bound cube map, in GL's order: +X, -X, +Y, -Y, +Z,
+    /// -Z. Passing `None` allocates storage only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn image_cube_face(
+        &self,
+        face: u32,
+        level: i32,
+        internal_format: gl::types::GLenum,
+        size: i32,
+        format: gl::types::GLenum,
+        ty: gl::types::GLenum,
+        data: Option<&[u8]>,
+    ) {
+        unsafe {
+            gl::TexImage2D(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face,
+                level,
+                internal_format as gl::types::GLint,
+                size,
+                size,
+                0,
+                format,
+                ty,
+                data.map_or(std::ptr::null(), |data| data.as_ptr().cast()),
+            );
+        }
+        let pixels = 6 * size as usize * size as usize;
+        self.set_level_size(level, pixels * memory::bytes_per_pixel(internal_format));
+    }
+
+    /// Specifies a 3D image or, for `GL_TEXTURE_2D_ARRAY`, `depth` layers of the bound
+    /// texture. Passing `None` allocates storage only.
+    #[allow(clippy::too_many_arguments)]
+    pub fn image_3d(
+        &self,
+        level: i32,
+        internal_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+        depth: i32,
+        format: gl::types::GLenum,
+        ty: gl::types::GLenum,
+        data: Option<&[u8]>,
+    ) {
+        unsafe {
+            gl::TexImage3D(
+                self.target,
+                level,
+                internal_format as gl::types::GLint,
+                width,
+                height,
+                depth,
+                0,
+                format,
+                ty,
+                data.map_or(std::ptr::null(), |data| data.as_ptr().cast()),
+            );
+        }
+        let pixels = width as usize * height as usize * depth as usize;
+        self.set_level_size(level, pixels * memory::bytes_per_pixel(internal_format));
+    }
+
+    /// Allocates storage for the bound `GL_TEXTURE_2D_MULTISAMPLE` texture with fixed
+    /// sample locations.
+    pub fn image_2d_multisample(
+        &self,
+        samples: i32,
+        internal_format: gl::types::GLenum,
+        width: i32,
+        height: i32,
+    ) {
+        unsafe {
+            gl::TexImage2DMultisample(
+                self.target,
+                samples,
+                internal_format,
+                width,
+                height,
+                gl::TRUE,
+            );
+        }
+        let pixels = width as usize * height as usize * samples as usize;
+        self.set_level_size(0, pixels * memory::bytes_per_pixel(internal_format));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn sub_image_2d(
+        &self,
+        level: i32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: gl::types::GLenum,
+        ty: gl::types::GLenum,
+        data: &[u8],
+    ) {
+        let pixels = data.as_ptr().cast();
+        unsafe {
+            if dsa::is_available() && self.target == gl::TEXTURE_2D {
+                gl::TextureSubImage2D(self.id, level, x, y, width, height, format, ty, pixels);
+            } else {
+                gl::TexSubImage2D(self.target, level, x, y, width, height, format, ty, pixels);
+            }
+        }
+    }
+
+    /// Like [`Texture::sub_image_2d`], but sources the pixels from the buffer currently
+    /// bound to `GL_PIXEL_UNPACK_BUFFER`, starting at `offset` bytes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sub_image_2d_from_buffer(
+        &self,
+        level: i32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: gl::types::GLenum,
+        ty: gl::types::GLenum,
+        offset: usize,
+    ) {
+        let pixels = offset as *const gl::types::GLvoid;
+        unsafe {
+            if dsa::is_available() && self.target == gl::TEXTURE_2D {
+                gl::TextureSubImage2D(self.id, level, x, y, width, height, format, ty, pixels);
+            } else {
+                gl::TexSubImage2D(self.target, level, x, y, width, height, format, ty, pixels);
+            }
+        }
+    }
+
+    /// Reads back mip `level` of a `GL_TEXTURE_2D`, converted as [`TexelImage`]
+    /// describes, e.g. to inspect an intermediate render target. Stalls until rendering
+    /// into the texture has finished, and leaves the texture bound to the active unit. ES
+    /// can't read textures directly, so there color formats go through a temporary
+    /// framebuffer, leaving the default one bound for reading, and depth formats are an
+    /// error.
+    pub fn read_pixels(&self, level: i32) -> Result<TexelImage> {
+        if self.target != gl::TEXTURE_2D {
+            return Err(anyhow!(
+                "Can't read back texture target 0x{:x}",
+                self.target
+            ));
+        }
+        let (width, height, internal_format) = level_info(self.id, self.target, self.target, level);
+        if width == 0 || height == 0 {
+            return Err(anyhow!("Texture level {} has no image", level));
+        }
+        let class = TexelClass::of(internal_format);
+        if !context::info().es {
+            self.bind();
+            return Ok(read_texels(
+                class,
+                width,
+                height,
+                |format, ty, data| unsafe {
+                    gl::GetTexImage(gl::TEXTURE_2D, level, format, ty, data);
+                },
+            ));
+        }
+        if class == TexelClass::Depth {
+            return Err(anyhow!("OpenGL ES can't read back depth textures"));
+        }
+        let framebuffer = Framebuffer::new()?;
+        framebuffer.bind(gl::READ_FRAMEBUFFER);
+        framebuffer.attach_texture(gl::READ_FRAMEBUFFER, gl::COLOR_ATTACHMENT0, self, level);
+        let image = read_texels(class, width, height, |format, ty, data| unsafe {
+            gl::ReadPixels(0, 0, width, height, format, ty, data);
+        });
+        Framebuffer::bind_default(gl::READ_FRAMEBUFFER);
+        Ok(image)
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        self.context.delete(Deletion::Texture(self.id));
+        memory::track_bytes(Resource::Texture, self.size(), 0);
+        memory::track_object(Resource::Texture, -1);
+    }
+}
+
+/// Filtering and wrapping state apart from any texture. Bound to a unit, it overrides
+/// the texture's own parameters there, so one texture can be sampled both clamped and
+/// repeating.
+pub struct Sampler {
+    id: gl::types::GLuint,
+    context: GlContext,
+}
+
+impl Sampler {
+    /// Creates a sampler with GL's defaults: repeat wrapping, linear magnification and
+    /// mipmapped minification.
+    pub fn new() -> Result<Sampler> {
+        let gl_context = GlContext::current()?;
+        let mut id = 0;
+        unsafe {
+            if dsa::is_available() {
+                gl::CreateSamplers(1, &mut id);
+            } else {
+                gl::GenSamplers(1, &mut id);
+            }
+        }
+        if id == 0 {
+            return Err(anyhow!("Failed to create sampler"));
+        }
+        Ok(Sampler {
+            id,
+            context: gl_context,
+        })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    /// Names the sampler in graphics debuggers. See [`crate::debug`].
+    pub fn label(&self, label: &str) {
+        debug::object_label(gl::SAMPLER, self.id, label);
+    }
+
+    pub fn parameter(&self, name: gl::types::GLenum, value: gl::types::GLint) {
+        unsafe {
+            gl::SamplerParameteri(self.id, name, value);
+        }
+    }
+
+    pub fn parameter_f(&self, name: gl::types::GLenum, value: gl::types::GLfloat) {
+        unsafe {
+            gl::SamplerParameterf(self.id, name, value);
+        }
+    }
+
+    /// See [`Texture::set_anisotropy`].
+    pub fn set_anisotropy(&self, anisotropy: f32) {
+        if let Some(anisotropy) = clamp_anisotropy(anisotropy) {
+            self.parameter_f(gl::TEXTURE_MAX_ANISOTROPY, anisotropy);
+        }
+    }
+
+    /// See [`Texture::set_lod_bias`].
+    pub fn set_lod_bias(&self, bias: f32) {
+        if let Some(bias) = clamp_lod_bias(bias) {
+            self.parameter_f(gl::TEXTURE_LOD_BIAS, bias);
+        }
+    }
+
+    /// Binds the sampler to texture unit `unit` until [`Sampler::unbind_unit`].
+    pub fn bind_unit(&self, unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, self.id);
+        }
+    }
+
+    /// Goes back to the parameters of the texture bound to `unit`.
+    pub fn unbind_unit(unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, 0);
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        self.context.delete(Deletion::Sampler(self.id));
+    }
+}
@@ -0,0 +1,102 @@
+//! Texture units assigned by sampler name.
+//!
+//! [`TextureUnits`] gives every sampler uniform name its own texture unit the first time
+//! the name is used, up to `GL_MAX_TEXTURE_IMAGE_UNITS`, so shaders sharing names
+//! (`u_albedo`, `u_shadow_map`) share units and code drawing with several programs
+//! doesn't have to number units by hand. [`TextureUnits::bind`] binds a texture to the
+//! unit of a sampler and points the program's uniform at it. The unit's texture is
+//! remembered, and binding it again is skipped. Like [`crate::state::StateCache`], it
+//! only knows about binds made through it: call [`TextureUnits::invalidate`] after code
+//! that binds textures directly.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+
+use crate::gl;
+use crate::shader::Program;
+use crate::texture::Texture;
+
+pub struct TextureUnits {
+    units: HashMap<String, u32>,
+    bound: Vec<Option<(gl::types::GLenum, gl::types::GLuint)>>,
+    binds: usize,
+    skipped: usize,
+}
+
+impl TextureUnits {
+    /// Creates an allocator for the units the current context has for fragment shaders.
+    pub fn new() -> TextureUnits {
+        let mut max = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_IMAGE_UNITS, &mut max);
+        }
+        TextureUnits {
+            units: HashMap::new(),
+            bound: vec![None; max.max(0) as usize],
+            binds: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Number of units available.
+    pub fn max_units(&self) -> usize {
+        self.bound.len()
+    }
+
+    /// The unit of sampler `name`, assigning the next free one if the name is new.
+    pub fn unit(&mut self, name: &str) -> Result<u32> {
+        if let Some(&unit) = self.units.get(name) {
+            return Ok(unit);
+        }
+        let unit = self.units.len() as u32;
+        if unit as usize >= self.bound.len() {
+            return Err(anyhow!(
+                "No texture unit left for {}: all {} are assigned",
+                name,
+                self.bound.len()
+            ));
+        }
+        self.units.insert(name.to_owned(), unit);
+        Ok(unit)
+    }
+
+    /// Binds `texture` to the unit of sampler `name` and sets the sampler uniform of
+    /// `program`, which must be in use, to that unit. Returns the unit.
+    pub fn bind(&mut self, program: &Program, name: &str, texture: &Texture) -> Result<u32> {
+        let unit = self.unit(name)?;
+        let binding = Some((texture.target(), texture.id()));
+        let bound = &mut self.bound[unit as usize];
+        if *bound == binding {
+            self.skipped += 1;
+        } else {
+            texture.bind_unit(unit);
+            *bound = binding;
+            self.binds += 1;
+        }
+        program.set_int(name, unit as i32);
+        Ok(unit)
+    }
+
+    /// Forgets what is bound to every unit, so the next bind of each is always issued.
+    /// Assigned units are kept.
+    pub fn invalidate(&mut self) {
+        self.bound.fill(None);
+    }
+
+    /// Texture binds issued and skipped since the last [`TextureUnits::reset_stats`].
+    pub fn stats(&self) -> (usize, usize) {
+        (self.binds, self.skipped)
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.binds = 0;
+        self.skipped = 0;
+    }
+}
+
+impl Default for TextureUnits {
+    fn default() -> Self {
+        TextureUnits::new()
+    }
+}
@@ -0,0 +1,74 @@
+//! A fixed set of worker threads for CPU-heavy jobs such as decoding assets.
+//!
+//! Jobs run in submission order on whichever worker is free and must not touch GL,
+//! which is only current on the render thread; they send their results back over a
+//! channel instead. Dropping the pool lets queued jobs finish and joins the workers.
+
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+pub struct ThreadPool {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// Starts `threads` workers named `name`.
+    pub fn new(name: &str, threads: usize) -> Result<ThreadPool> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1))
+            .map(|i| {
+                let receiver = receiver.clone();
+                std::thread::Builder::new()
+                    .name(format!("{} {}", name, i))
+                    .spawn(move || loop {
+                        // The lock is only held while waiting, not while running the job.
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    })
+                    .map_err(|e| anyhow!("Failed to start {} thread: {}", name, e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ThreadPool {
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    /// One worker per core beyond the render thread's, at most four.
+    pub fn default_threads() -> usize {
+        std::thread::available_parallelism()
+            .map_or(1, |cores| cores.get().saturating_sub(1))
+            .clamp(1, 4)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Queues `job` for the next free worker.
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'static) {
+        if let Some(sender) = &self.sender {
+            // Only fails when every worker has panicked; the job is dropped then.
+            let _ = sender.send(Box::new(job));
+        }
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! `hello-gl toy shader.frag`: a local ShaderToy.
+//!
+//! The file defines `void mainImage(out vec4 fragColor, in vec2 fragCoord)` and may use
+//! ShaderToy's inputs: `iResolution`, `iTime`, `iTimeDelta`, `iFrame`, `iMouse`,
+//! `iChannel0..3` and `iChannelResolution`. Channels are images given with `--channel`.
+//! The shader and the images are reloaded when they change on disk; a shader that fails
+//! to compile reports its errors and the previous one keeps running.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, Result};
+use hello_gl::app::App;
+use hello_gl::assets::{Assets, Handle};
+use hello_gl::event::{ElementState, MouseButton, WindowEvent};
+use hello_gl::gl;
+use hello_gl::postprocess::{FullscreenTriangle, FULLSCREEN_VERTEX_SHADER};
+use hello_gl::shader::Program;
+use hello_gl::texture::Texture;
+
+const CHANNELS: usize = 4;
+
+const PRELUDE: &str = "#version 330 core
+uniform vec3 iResolution;
+uniform float iTime;
+uniform float iTimeDelta;
+uniform int iFrame;
+uniform vec4 iMouse;
+uniform sampler2D iChannel0;
+uniform sampler2D iChannel1;
+uniform sampler2D iChannel2;
+uniform sampler2D iChannel3;
+uniform vec3 iChannelResolution[4];
+out vec4 hello_gl_frag_color;
+#line 1
+";
+
+const EPILOGUE: &str = "
+void main() {
+    hello_gl_frag_color = vec4(0.0, 0.0, 0.0, 1.0);
+    mainImage(hello_gl_frag_color, gl_FragCoord.xy);
+}
+";
+
+/// How often the shader file is checked for changes.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct Toy {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    next_check: Instant,
+    program: Program,
+    triangle: FullscreenTriangle,
+    assets: Assets,
+    channels: Vec<Handle<Texture>>,
+    resolution: [f32; 2],
+    time: f32,
+    dt: f32,
+    frame: i32,
+    cursor: [f32; 2],
+    /// ShaderToy's encoding: `xy` is the cursor while a button is held, `zw` where it was
+    /// pressed; `z` turns negative on release and `w` after the first frame.
+    mouse: [f32; 4],
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn compile(path: &Path) -> Result<Program> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let fragment = format!("{}{}{}", PRELUDE, source, EPILOGUE);
+    let program = Program::from_sources(FULLSCREEN_VERTEX_SHADER, &fragment)
+        .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+    program.label(&path.display().to_string());
+    Ok(program)
+}
+
+impl Toy {
+    pub fn new(path: PathBuf, channels: &[PathBuf]) -> Result<Toy> {
+        if channels.len() > CHANNELS {
+            return Err(anyhow!("At most {} channels are supported", CHANNELS));
+        }
+        let mut assets = Assets::new(".");
+        let channels = channels
+            .iter()
+            .map(|channel| assets.load_texture(channel))
+            .collect::<Result<_>>()?;
+        assets.on_reload(|event| match &event.result {
+            Ok(()) => tracing::info!("Reloaded {}", event.path.display()),
+            Err(e) => tracing::error!("{:#}", e),
+        });
+        Ok(Toy {
+            modified: modified(&path),
+            next_check: Instant::now() + CHECK_INTERVAL,
+            program: compile(&path)?,
+            path,
+            triangle: FullscreenTriangle::new()?,
+            assets,
+            channels,
+            resolution: [1.0, 1.0],
+            time: 0.0,
+            dt: 0.0,
+            frame: 0,
+            cursor: [0.0, 0.0],
+            mouse: [0.0; 4],
+        })
+    }
+
+    fn reload_if_changed(&mut self) {
+        let now = Instant::now();
+        if now < self.next_check {
+            return;
+        }
+        self.next_check = now + CHECK_INTERVAL;
+        let stamp = modified(&self.path);
+        if stamp == self.modified {
+            return;
+        }
+        self.modified = stamp;
+        match compile(&self.path) {
+            Ok(program) => {
+                tracing::info!("Reloaded {}", self.path.display());
+                self.program = program;
+            }
+            Err(e) => tracing::error!("{:#}", e),
+        }
+    }
+}
+
+impl App for Toy {
+    fn resize(&mut self, width: u32, height: u32) {
+        self.resolution = [width as f32, height as f32];
+    }
+
+    fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, self.resolution[1] - position.y as f32];
+                if self.mouse[2] > 0.0 {
+                    self.mouse[0] = self.cursor[0];
+                    self.mouse[1] = self.cursor[1];
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    let [x, y] = self.cursor;
+                    self.mouse = [x, y, x, y];
+                }
+                ElementState::Released => self.mouse[2] = -self.mouse[2].abs(),
+            },
+            _ => (),
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.reload_if_changed();
+        self.assets.reload_changed();
+        self.time += dt;
+        self.dt = dt;
+    }
+
+    fn render(&mut self) {
+        let [width, height] = self.resolution;
+        self.program.use_program();
+        self.program.set_vec3("iResolution", [width, height, 1.0]);
+        self.program.set_float("iTime", self.time);
+        self.program.set_float("iTimeDelta", self.dt);
+        self.program.set_int("iFrame", self.frame);
+        self.program.set_vec4("iMouse", self.mouse);
+        for (unit, handle) in self.channels.iter().enumerate() {
+            let Some(texture) = self.assets.get(*handle) else {
+                continue;
+            };
+            texture.bind_unit(unit as u32);
+            let (mut w, mut h) = (0, 0);
+            unsafe {
+                // `bind_unit` may not change the active unit, which the queries read.
+                gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+                gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_WIDTH, &mut w);
+                gl::GetTexLevelParameteriv(gl::TEXTURE_2D, 0, gl::TEXTURE_HEIGHT, &mut h);
+            }
+            self.program
+                .set_int(&format!("iChannel{}", unit), unit as i32);
+            self.program.set_vec3(
+                &format!("iChannelResolution[{}]", unit),
+                [w as f32, h as f32, 1.0],
+            );
+        }
+        self.triangle.draw();
+
+        self.frame += 1;
+        self.mouse[3] = -self.mouse[3].abs();
+    }
+
+    fn context_restored(&mut self) -> Result<()> {
+        self.program = compile(&self.path)?;
+        self.triangle = FullscreenTriangle::new()?;
+        for event in self.assets.recreate_all() {
+            event.result?;
+        }
+        Ok(())
+    }
+}
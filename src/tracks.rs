@@ -0,0 +1,269 @@
+//! Keyframed camera and light motion.
+//!
+//! A [`CameraTrack`] moves a [`Camera`]'s eye and target, and optionally its field of
+//! view, along [`Track`]s; a [`LightTrack`] does the same for the position, direction,
+//! color and intensity of a [`Light`]. Both are pure functions of time, so a demo driven
+//! by them renders the same frames on every run, as the benchmark mode relies on.
+//! Built with [`Interpolation::CatmullRom`], fly-throughs pass smoothly through every
+//! keyframe.
+
+use anyhow::Result;
+
+use crate::animation::{Interpolation, Keyframe, Track};
+use crate::material::Light;
+use crate::math::Vec3;
+use crate::viewport::Camera;
+
+/// Samples `track` at `time`, repeating it if `looping`.
+fn sample<T: Keyframe>(track: &Track<T>, time: f32, looping: bool) -> T {
+    if looping {
+        track.sample_looped(time)
+    } else {
+        track.sample(time)
+    }
+}
+
+/// A camera fly-through.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraTrack {
+    pub eye: Track<Vec3>,
+    pub target: Track<Vec3>,
+    /// Vertical field of view in radians; the camera's own when `None`.
+    pub fov_y: Option<Track<f32>>,
+    /// Repeats the tracks from their first keyframe instead of holding the last one.
+    pub looping: bool,
+}
+
+impl CameraTrack {
+    /// A looping fly-through passing smoothly through `(time, eye, target)` keyframes,
+    /// which must be in ascending time order.
+    pub fn through(keys: &[(f32, Vec3, Vec3)]) -> Result<CameraTrack> {
+        let eye: Vec<_> = keys.iter().map(|&(time, eye, _)| (time, eye)).collect();
+        let target: Vec<_> = keys
+            .iter()
+            .map(|&(time, _, target)| (time, target))
+            .collect();
+        Ok(CameraTrack {
+            eye: Track::from_keys(&eye, Interpolation::CatmullRom)?,
+            target: Track::from_keys(&target, Interpolation::CatmullRom)?,
+            fov_y: None,
+            looping: true,
+        })
+    }
+
+    /// The time of the last keyframe of any track.
+    pub fn duration(&self) -> f32 {
+        let fov_y = self.fov_y.as_ref().map_or(0.0, Track::duration);
+        self.eye.duration().max(self.target.duration()).max(fov_y)
+    }
+
+    /// Moves `camera` to where the tracks are at `time`.
+    pub fn apply(&self, time: f32, camera: &mut Camera) {
+        camera.eye = sample(&self.eye, time, self.looping);
+        camera.target = sample(&self.target, time, self.looping);
+        if let Some(fov_y) = &self.fov_y {
+            camera.fov_y = sample(fov_y, time, self.looping);
+        }
+    }
+
+    /// `camera` moved to where the tracks are at `time`.
+    pub fn camera(&self, time: f32, camera: &Camera) -> Camera {
+        let mut camera = *camera;
+        self.apply(time, &mut camera);
+        camera
+    }
+}
+
+/// Animated properties of a light. Tracks that are `None`, and those the light's kind
+/// doesn't have, such as a position for a directional light, leave it unchanged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LightTrack {
+    pub position: Option<Track<Vec3>>,
+    /// Normalized after sampling.
+    pub direction: Option<Track<Vec3>>,
+    pub color: Option<Track<Vec3>>,
+    pub intensity: Option<Track<f32>>,
+    /// Repeats the tracks from their first keyframe instead of holding the last one.
+    pub looping: bool,
+}
+
+impl LightTrack {
+    /// A light circling `center` counterclockwise seen from above, once every `period`
+    /// seconds, at `radius` in the XZ plane.
+    pub fn orbit(center: Vec3, radius: f32, period: f32) -> LightTrack {
+        LightTrack {
+            position: Some(circle(center, radius, period)),
+            looping: true,
+            ..LightTrack::default()
+        }
+    }
+
+    /// Sets the animated properties of `light` to their values at `time`.
+    pub fn apply(&self, time: f32, light: &mut Light) {
+        let looping = self.looping;
+        let (position, direction, color, intensity) = match light {
+            Light::Directional {
+                direction,
+                color,
+                intensity,
+            } => (None, Some(direction), color, intensity),
+            Light::Point {
+                position,
+                color,
+                intensity,
+                ..
+            } => (Some(position), None, color, intensity),
+            Light::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                ..
+            } => (Some(position), Some(direction), color, intensity),
+        };
+        if let (Some(track), Some(position)) = (&self.position, position) {
+            *position = sample(track, time, looping);
+        }
+        if let (Some(track), Some(direction)) = (&self.direction, direction) {
+            *direction = sample(track, time, looping).normalize_or_zero();
+        }
+        if let Some(track) = &self.color {
+            *color = sample(track, time, looping);
+        }
+        if let Some(track) = &self.intensity {
+            *intensity = sample(track, time, looping);
+        }
+    }
+}
+
+/// A closed circle of `radius` around `center` in the XZ plane, traversed once every
+/// `period` seconds.
+pub fn circle(center: Vec3, radius: f32, period: f32) -> Track<Vec3> {
+    const KEYS: usize = 16;
+    let times = (0..=KEYS)
+        .map(|i| i as f32 / KEYS as f32 * period)
+        .collect();
+    let values = (0..=KEYS)
+        .map(|i| {
+            let angle = i as f32 / KEYS as f32 * std::f32::consts::TAU;
+            center + Vec3::new(angle.cos(), 0.0, -angle.sin()) * radius
+        })
+        .collect();
+    Track {
+        times,
+        values,
+        interpolation: Interpolation::CatmullRom,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        a.distance(b) < 1e-4
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_keys() {
+        let keys = [
+            (0.0, Vec3::ZERO),
+            (1.0, Vec3::new(1.0, 2.0, 0.0)),
+            (3.0, Vec3::new(4.0, 0.0, -1.0)),
+            (4.0, Vec3::ONE),
+        ];
+        let track = Track::from_keys(&keys, Interpolation::CatmullRom).unwrap();
+        for (time, value) in keys {
+            assert!(close(track.sample(time), value));
+        }
+        // Smooth: approaching a key from either side gives the same slope.
+        let slope = |from: f32, to: f32| (track.sample(to) - track.sample(from)) / (to - from);
+        assert!(slope(0.999, 1.0).distance(slope(1.0, 1.001)) < 0.05);
+    }
+
+    #[test]
+    fn catmull_rom_holds_the_ends() {
+        let keys = [(1.0, 2.0), (2.0, 4.0)];
+        let track = Track::from_keys(&keys, Interpolation::CatmullRom).unwrap();
+        assert_eq!(track.sample(0.0), 2.0);
+        assert_eq!(track.sample(5.0), 4.0);
+        // Two keys give a straight line.
+        assert!((track.sample(1.5) - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn cubic_spline_keys_need_tangents() {
+        assert!(Track::from_keys(&[(0.0, 1.0)], Interpolation::CubicSpline).is_err());
+    }
+
+    #[test]
+    fn looped_tracks_repeat() {
+        let keys = [(1.0, 0.0), (3.0, 2.0)];
+        let track = Track::from_keys(&keys, Interpolation::Linear).unwrap();
+        assert_eq!(track.sample_looped(2.0), 1.0);
+        assert_eq!(track.sample_looped(4.0), 1.0);
+        assert_eq!(track.sample_looped(0.0), 1.0);
+        assert_eq!(Track::constant(7.0).sample_looped(12.0), 7.0);
+    }
+
+    #[test]
+    fn circle_is_closed() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let track = circle(center, 2.0, 4.0);
+        assert!(close(track.sample(0.0), center + Vec3::X * 2.0));
+        assert!(close(track.sample(4.0), track.sample(0.0)));
+        // A quarter turn counterclockwise seen from above is towards -Z.
+        assert!(close(track.sample(1.0), center - Vec3::Z * 2.0));
+        // The ends use one-sided tangents, so the last and first segments bulge most.
+        for i in 0..40 {
+            let time = i as f32 * 0.1;
+            let tolerance = if (0.25..3.75).contains(&time) {
+                0.002
+            } else {
+                0.025
+            };
+            let radius = (track.sample(time) - center).length();
+            assert!((radius - 2.0).abs() < tolerance);
+        }
+    }
+
+    #[test]
+    fn camera_track_moves_eye_and_target() {
+        let mut track =
+            CameraTrack::through(&[(0.0, Vec3::Z, Vec3::ZERO), (2.0, Vec3::X, Vec3::Y)]).unwrap();
+        track.fov_y = Some(Track::constant(1.0));
+        assert_eq!(track.duration(), 2.0);
+        let start = Camera::look_at(Vec3::ONE, Vec3::ZERO);
+        // Looping wraps back to the first keyframe.
+        assert!(close(track.camera(2.0, &start).eye, Vec3::Z));
+        track.looping = false;
+        let camera = track.camera(3.0, &start);
+        assert!(close(camera.eye, Vec3::X));
+        assert!(close(camera.target, Vec3::Y));
+        assert_eq!(camera.fov_y, 1.0);
+    }
+
+    #[test]
+    fn light_track_skips_missing_properties() {
+        let track = LightTrack {
+            position: Some(Track::constant(Vec3::Y)),
+            direction: Some(Track::constant(Vec3::new(0.0, -2.0, 0.0))),
+            intensity: Some(Track::constant(3.0)),
+            ..LightTrack::default()
+        };
+        let mut light = Light::Directional {
+            direction: Vec3::X,
+            color: Vec3::ONE,
+            intensity: 1.0,
+        };
+        track.apply(0.0, &mut light);
+        assert_eq!(
+            light,
+            Light::Directional {
+                direction: Vec3::NEG_Y,
+                color: Vec3::ONE,
+                intensity: 3.0,
+            }
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Streaming of per-frame vertex and index data.
+//!
+//! A [`TransientBuffer`] is a bump allocator over one large buffer: each
+//! [`TransientBuffer::push`] copies its data in after the previous one and returns the
+//! offset to draw from. Nothing written is ever overwritten, so the driver never has to
+//! wait for draws still reading earlier data. When the buffer is full it is orphaned —
+//! given fresh storage with `glBufferData` while the driver keeps the old storage alive
+//! for the draws using it — and filling starts again from the front.
+//!
+//! [`crate::sprite::SpriteBatch`], [`crate::debug_draw::DebugDraw`] and the egui painter
+//! stream through one each, so a frame of many small batches costs a handful of
+//! orphanings rather than one per batch.
+
+use std::cell::Cell;
+
+use anyhow::Result;
+
+use crate::buffer::Buffer;
+use crate::gl;
+
+/// Storage of a [`TransientBuffer`] unless given otherwise: enough for a few frames of
+/// typical UI and debug geometry between orphanings.
+pub const DEFAULT_CAPACITY: usize = 1 << 20;
+
+pub struct TransientBuffer {
+    buffer: Buffer,
+    /// Where the next push may start.
+    offset: Cell<usize>,
+    orphanings: Cell<u64>,
+}
+
+impl TransientBuffer {
+    /// Creates a buffer with `capacity` bytes of storage, which grows when a single push
+    /// doesn't fit.
+    pub fn new(capacity: usize) -> Result<TransientBuffer> {
+        let buffer = Buffer::new()?;
+        buffer.bind(gl::COPY_WRITE_BUFFER);
+        buffer.allocate(gl::COPY_WRITE_BUFFER, capacity.max(1), gl::STREAM_DRAW);
+        buffer.unbind(gl::COPY_WRITE_BUFFER);
+        Ok(TransientBuffer {
+            buffer,
+            offset: Cell::new(0),
+            orphanings: Cell::new(0),
+        })
+    }
+
+    /// The buffer to source vertices or indices from, at the offsets
+    /// [`TransientBuffer::push`] returns. Its id stays the same across orphanings.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.size()
+    }
+
+    /// How often the storage has been replaced. Growing quickly means the capacity is
+    /// too small for what is streamed per frame.
+    pub fn orphanings(&self) -> u64 {
+        self.orphanings.get()
+    }
+
+    /// Copies `data` into unused storage and returns its byte offset, a multiple of
+    /// `align` (the vertex size for attributes, so offsets can be turned into a first
+    /// vertex). Orphans the storage first when `data` doesn't fit behind earlier pushes.
+    ///
+    /// Uses the `GL_COPY_WRITE_BUFFER` binding, so vertex array state is untouched.
+    pub fn push(&self, data: &[u8], align: usize) -> usize {
+        let align = align.max(1);
+        let mut offset = self.offset.get().div_ceil(align) * align;
+        self.buffer.bind(gl::COPY_WRITE_BUFFER);
+        if offset + data.len() > self.buffer.size() {
+            let capacity = self.buffer.size().max(data.len().next_power_of_two());
+            self.buffer
+                .allocate(gl::COPY_WRITE_BUFFER, capacity, gl::STREAM_DRAW);
+            self.orphanings.set(self.orphanings.get() + 1);
+            offset = 0;
+        }
+        if !data.is_empty() {
+            self.buffer.sub_data(gl::COPY_WRITE_BUFFER, offset, data);
+        }
+        self.buffer.unbind(gl::COPY_WRITE_BUFFER);
+        self.offset.set(offset + data.len());
+        offset
+    }
+}
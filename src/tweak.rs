@@ -0,0 +1,255 @@
+//! egui panels for tweaking a program's uniforms, saved between runs.
+//!
+//! [`Tweaks::ui`] lists the uniforms a program reads, from [`Program::active_uniforms`],
+//! and shows a control for each it knows how to edit: a slider for `float`, a color
+//! picker for `vec3` and `vec4` named `*_color`, and a checkbox for `bool`. Other
+//! uniforms are left to the application. Edited values are set on the program right
+//! away, and the program's own values are shown until a uniform is edited.
+//!
+//! Values are kept per panel name and written as TOML to the file passed to
+//! [`Tweaks::load`] by [`Tweaks::save`] and on drop. The next run's [`Tweaks::ui`] sets
+//! the saved values on the program the first time it shows it, so they survive
+//! restarts as long as the application doesn't overwrite them every frame.
+//!
+//! ```toml
+//! [tonemap]
+//! u_exposure = 1.5
+//! u_tint_color = [1.0, 0.9, 0.8]
+//! u_dither = true
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::gl;
+use crate::shader::{ActiveUniform, Program};
+
+/// The file [`Tweaks::default_path`] names, in the working directory.
+pub const FILE_NAME: &str = "hello-gl-tweaks.toml";
+
+/// A saved uniform value.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    Float(f32),
+    Color(Vec<f32>),
+}
+
+/// How a uniform is edited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Control {
+    Slider,
+    Color(usize),
+    Checkbox,
+}
+
+impl Control {
+    fn of(uniform: &ActiveUniform) -> Option<Control> {
+        if uniform.size != 1 {
+            return None;
+        }
+        match uniform.ty {
+            gl::FLOAT => Some(Control::Slider),
+            gl::FLOAT_VEC3 if uniform.name.ends_with("_color") => Some(Control::Color(3)),
+            gl::FLOAT_VEC4 if uniform.name.ends_with("_color") => Some(Control::Color(4)),
+            gl::BOOL => Some(Control::Checkbox),
+            _ => None,
+        }
+    }
+}
+
+/// The editable uniforms of the program a panel last showed.
+struct Panel {
+    program: gl::types::GLuint,
+    uniforms: Vec<(ActiveUniform, Control)>,
+}
+
+pub struct Tweaks {
+    path: PathBuf,
+    values: BTreeMap<String, BTreeMap<String, Value>>,
+    panels: HashMap<String, Panel>,
+    modified: bool,
+}
+
+impl Tweaks {
+    /// [`FILE_NAME`] in the working directory.
+    pub fn default_path() -> PathBuf {
+        PathBuf::from(FILE_NAME)
+    }
+
+    /// Reads the values saved in `path`. A missing file gives empty tweaks that will be
+    /// saved there.
+    pub fn load(path: impl AsRef<Path>) -> Result<Tweaks> {
+        let path = path.as_ref();
+        let values = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+        };
+        Ok(Tweaks {
+            path: path.to_owned(),
+            values,
+            panels: HashMap::new(),
+            modified: false,
+        })
+    }
+
+    /// Writes the values to the file they were loaded from, if any changed.
+    pub fn save(&mut self) -> Result<()> {
+        if !self.modified {
+            return Ok(());
+        }
+        let text = toml::to_string(&self.values)?;
+        std::fs::write(&self.path, text)
+            .map_err(|e| anyhow!("Failed to write {}: {}", self.path.display(), e))?;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// The saved value of uniform `uniform` in panel `name`.
+    pub fn value(&self, name: &str, uniform: &str) -> Option<&Value> {
+        self.values.get(name)?.get(uniform)
+    }
+
+    /// Shows the controls for the uniforms of `program` under the name `name`, which
+    /// keys the saved values; the first time, or after the program was replaced, also
+    /// sets the saved values on it. `program` needn't be in use.
+    pub fn ui(&mut self, ui: &mut ::egui::Ui, name: &str, program: &Program) {
+        let fresh = self
+            .panels
+            .get(name)
+            .is_none_or(|panel| panel.program != program.id());
+        if fresh {
+            let uniforms = program
+                .active_uniforms()
+                .into_iter()
+                .filter_map(|uniform| Control::of(&uniform).map(|control| (uniform, control)))
+                .collect();
+            self.panels.insert(
+                name.to_owned(),
+                Panel {
+                    program: program.id(),
+                    uniforms,
+                },
+            );
+        }
+        let panel = &self.panels[name];
+        let saved = self.values.entry(name.to_owned()).or_default();
+        if fresh {
+            for (uniform, _) in &panel.uniforms {
+                if let Some(value) = saved.get(&uniform.name) {
+                    set(program, uniform, value);
+                }
+            }
+        }
+        if panel.uniforms.is_empty() {
+            ui.label("No editable uniforms");
+            return;
+        }
+
+        for (uniform, control) in &panel.uniforms {
+            let mut value = match saved.get(&uniform.name) {
+                Some(value) => value.clone(),
+                None => get(program, uniform, *control),
+            };
+            let label = uniform.name.trim_start_matches("u_");
+            let changed = match (control, &mut value) {
+                (Control::Slider, Value::Float(v)) => {
+                    let range = v.min(0.0)..=v.max(1.0);
+                    ui.add(::egui::Slider::new(v, range).text(label)).changed()
+                }
+                (Control::Color(_), Value::Color(v)) if v.len() == 3 => {
+                    ui.horizontal(|ui| {
+                        let mut rgb = [v[0], v[1], v[2]];
+                        let changed = ui.color_edit_button_rgb(&mut rgb).changed();
+                        ui.label(label);
+                        *v = rgb.to_vec();
+                        changed
+                    })
+                    .inner
+                }
+                (Control::Color(_), Value::Color(v)) if v.len() == 4 => {
+                    ui.horizontal(|ui| {
+                        let mut rgba = [v[0], v[1], v[2], v[3]];
+                        let changed = ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed();
+                        ui.label(label);
+                        *v = rgba.to_vec();
+                        changed
+                    })
+                    .inner
+                }
+                (Control::Checkbox, Value::Bool(v)) => ui.checkbox(v, label).changed(),
+                // A saved value of another type, e.g. after the shader changed.
+                _ => {
+                    saved.remove(&uniform.name);
+                    self.modified = true;
+                    continue;
+                }
+            };
+            if changed {
+                set(program, uniform, &value);
+                saved.insert(uniform.name.clone(), value);
+                self.modified = true;
+            }
+        }
+    }
+}
+
+impl Drop for Tweaks {
+    fn drop(&mut self) {
+        if let Err(e) = self.save() {
+            tracing::warn!("{:#}", e);
+        }
+    }
+}
+
+/// The current value of `uniform` in `program`.
+fn get(program: &Program, uniform: &ActiveUniform, control: Control) -> Value {
+    match control {
+        Control::Slider | Control::Color(_) => {
+            let mut value = [0.0f32; 4];
+            unsafe {
+                gl::GetUniformfv(program.id(), uniform.location, value.as_mut_ptr());
+            }
+            match control {
+                Control::Color(components) => Value::Color(value[..components].to_vec()),
+                _ => Value::Float(value[0]),
+            }
+        }
+        Control::Checkbox => {
+            let mut value = 0;
+            unsafe {
+                gl::GetUniformiv(program.id(), uniform.location, &mut value);
+            }
+            Value::Bool(value != 0)
+        }
+    }
+}
+
+/// Sets `uniform` of `program` to `value`, using the program only for the call.
+fn set(program: &Program, uniform: &ActiveUniform, value: &Value) {
+    let location = uniform.location;
+    let mut current = 0;
+    unsafe {
+        gl::GetIntegerv(gl::CURRENT_PROGRAM, &mut current);
+        gl::UseProgram(program.id());
+        match (uniform.ty, value) {
+            (gl::FLOAT, Value::Float(v)) => gl::Uniform1f(location, *v),
+            (gl::FLOAT_VEC3, Value::Color(v)) if v.len() == 3 => {
+                gl::Uniform3f(location, v[0], v[1], v[2])
+            }
+            (gl::FLOAT_VEC4, Value::Color(v)) if v.len() == 4 => {
+                gl::Uniform4f(location, v[0], v[1], v[2], v[3])
+            }
+            (gl::BOOL, Value::Bool(v)) => gl::Uniform1i(location, *v as i32),
+            _ => {}
+        }
+        gl::UseProgram(current as u32);
+    }
+}
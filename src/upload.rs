@@ -0,0 +1,295 @@
+//! Resource creation on a background thread with a shared GL context.
+//!
+//! [`UploadContext`] owns a worker thread whose context shares objects with the render
+//! context, so textures, buffers and programs can be created and filled there without
+//! stalling frames. Each finished job is followed by a fence; [`UploadContext::poll`]
+//! hands the result to its callback on the render thread once the fence has signaled,
+//! so the GPU side of the upload is complete by the time the object is used.
+//!
+//! Vertex arrays and framebuffers are not shared between contexts and must still be
+//! created on the render thread. The worker renders into a 1×1 pbuffer, which WGL does
+//! not provide; there [`UploadContext::new`] fails and [`crate::stream`] remains the way
+//! to load textures without blocking.
+//!
+//! The worker's context shares objects with the render context of the moment, so after
+//! [`crate::app::App::context_lost`] drop the [`UploadContext`] with the other GL
+//! objects and create a new one once the context is restored. Objects it hands over
+//! then follow the new context; ones from the lost context skip deleting their names.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+use glutin::config::Config;
+use glutin::context::{
+    AsRawContext, ContextApi, ContextAttributesBuilder, GlProfile, PossiblyCurrentContext,
+    RawContext, Version,
+};
+use glutin::display::Display;
+use glutin::prelude::*;
+use glutin::surface::{PbufferSurface, SurfaceAttributesBuilder};
+
+use crate::context::{self, GlContext};
+use crate::gl;
+use crate::image::Image;
+use crate::shader::Program;
+use crate::sync::Fence;
+use crate::texture::Texture;
+
+type Output = Box<dyn Any + Send>;
+type Job = Box<dyn FnOnce() -> Result<Output> + Send>;
+type Callback = Box<dyn FnOnce(Result<Output>)>;
+
+/// What a new upload context needs to share objects with the render context.
+struct Share {
+    display: Display,
+    config: Config,
+    context: RawContext,
+}
+
+impl AsRawContext for Share {
+    fn raw_context(&self) -> RawContext {
+        self.context
+    }
+}
+
+thread_local! {
+    static SHARE: RefCell<Option<Share>> = const { RefCell::new(None) };
+}
+
+/// Records the current render context so [`UploadContext::new`] can share with it.
+/// Called by the runners once the context is current.
+pub(crate) fn register(display: &Display, config: &Config, context: &PossiblyCurrentContext) {
+    SHARE.with(|share| {
+        *share.borrow_mut() = Some(Share {
+            display: display.clone(),
+            config: config.clone(),
+            context: context.raw_context(),
+        })
+    });
+}
+
+/// The display and render context registered on this thread, for interop with the
+/// window system, e.g. [`crate::dmabuf`].
+pub(crate) fn registered() -> Option<(Display, RawContext)> {
+    SHARE.with(|share| {
+        let share = share.borrow();
+        share
+            .as_ref()
+            .map(|share| (share.display.clone(), share.context))
+    })
+}
+
+/// A GL object created on the upload thread. Wrappers hold the [`GlContext`] of the
+/// thread that created them and are not `Send`, but textures, buffers and programs
+/// belong to the share group, and the render thread only gets them after the job's
+/// fence has signaled. The fence itself is a sync object of the share group too, waited
+/// on by the render thread and deleted there.
+struct Shared<T>(T);
+
+unsafe impl<T> Send for Shared<T> {}
+
+struct Finished {
+    id: u64,
+    result: Result<Output>,
+    fence: Option<Shared<Fence>>,
+}
+
+/// A background thread with a context shared with the render context.
+pub struct UploadContext {
+    jobs: Option<Sender<(u64, Job)>>,
+    finished: Receiver<Finished>,
+    worker: Option<JoinHandle<()>>,
+    callbacks: Vec<(u64, Callback)>,
+    in_flight: Vec<Finished>,
+    next_id: u64,
+}
+
+impl UploadContext {
+    /// Creates a context sharing objects with the current render context and starts
+    /// its thread. Must be called on the render thread of [`crate::app::run`] or an
+    /// [`crate::app::EmbeddedContext`].
+    pub fn new() -> Result<UploadContext> {
+        let (display, config, context) = SHARE.with(|share| {
+            let share = share.borrow();
+            let share = share
+                .as_ref()
+                .ok_or_else(|| anyhow!("No render context to share with"))?;
+            let info = context::info();
+            let version = Some(Version::new(info.version.0 as u8, info.version.1 as u8));
+            let mut attributes = ContextAttributesBuilder::new().with_sharing(share);
+            attributes = if info.es {
+                attributes.with_context_api(ContextApi::Gles(version))
+            } else if info.core_profile {
+                attributes
+                    .with_context_api(ContextApi::OpenGl(version))
+                    .with_profile(GlProfile::Core)
+            } else {
+                attributes.with_context_api(ContextApi::OpenGl(version))
+            };
+            let context = unsafe {
+                share
+                    .display
+                    .create_context(&share.config, &attributes.build(None))
+            }?;
+            Ok::<_, anyhow::Error>((share.display.clone(), share.config.clone(), context))
+        })?;
+
+        // Objects the worker creates are dropped on this thread; its tokens have to be
+        // of this thread's generation, which is past 0 after recovering from a reset.
+        let generation = context::generation();
+        let (jobs, job_rx) = mpsc::channel::<(u64, Job)>();
+        let (finished_tx, finished) = mpsc::channel();
+        let (ready_tx, ready) = mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            let one = NonZeroU32::new(1).unwrap();
+            let attributes = SurfaceAttributesBuilder::<PbufferSurface>::new().build(one, one);
+            let current = unsafe { display.create_pbuffer_surface(&config, &attributes) }
+                .map_err(anyhow::Error::from)
+                .and_then(|surface| Ok((context.make_current(&surface)?, surface)));
+            let (_context, _surface) = match current {
+                Ok(current) => {
+                    // The function pointers loaded for the render context work for
+                    // contexts of the same display and config.
+                    context::share_generation(generation);
+                    unsafe { GlContext::assume_current() };
+                    let _ = ready_tx.send(Ok(()));
+                    current
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            while let Ok((id, job)) = job_rx.recv() {
+                let result = job();
+                let fence = result.is_ok().then(|| {
+                    let fence = Fence::new();
+                    // Submit the commands so the render thread's wait can complete.
+                    unsafe {
+                        gl::Flush();
+                    }
+                    Shared(fence)
+                });
+                if finished_tx.send(Finished { id, result, fence }).is_err() {
+                    break;
+                }
+            }
+        });
+        ready
+            .recv()
+            .map_err(|_| anyhow!("Upload thread exited during setup"))??;
+
+        Ok(UploadContext {
+            jobs: Some(jobs),
+            finished,
+            worker: Some(worker),
+            callbacks: Vec::new(),
+            in_flight: Vec::new(),
+            next_id: 0,
+        })
+    }
+
+    /// Runs `job` on the upload thread; `callback` receives its result on the render
+    /// thread, from [`UploadContext::poll`], once the GPU has finished the job's commands.
+    /// GL wrappers are not `Send`; [`UploadContext::load_texture`] and
+    /// [`UploadContext::compile_program`] hand them over instead.
+    pub fn submit<T, J, C>(&mut self, job: J, callback: C)
+    where
+        T: Send + 'static,
+        J: FnOnce() -> Result<T> + Send + 'static,
+        C: FnOnce(Result<T>) + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.callbacks.push((
+            id,
+            Box::new(move |result: Result<Output>| {
+                callback(result.map(|output| *output.downcast::<T>().unwrap()))
+            }),
+        ));
+        let job: Job = Box::new(move || job().map(|output| Box::new(output) as Output));
+        if self.jobs.as_ref().unwrap().send((id, job)).is_err() {
+            self.finish(id, Err(anyhow!("Upload thread has stopped")));
+        }
+    }
+
+    /// Decodes the PNG at `path` and creates a texture from it on the upload thread.
+    pub fn load_texture<F>(&mut self, path: impl Into<PathBuf>, callback: F)
+    where
+        F: FnOnce(Result<Texture>) + 'static,
+    {
+        let path = path.into();
+        self.submit(
+            move || {
+                let texture = Texture::from_image(&Image::load(&path)?)?;
+                texture.label(&path.to_string_lossy());
+                Ok(Shared(texture))
+            },
+            move |result| callback(result.map(|shared| shared.0)),
+        );
+    }
+
+    /// Compiles and links a program on the upload thread.
+    pub fn compile_program<F>(&mut self, vertex: String, fragment: String, callback: F)
+    where
+        F: FnOnce(Result<Program>) + 'static,
+    {
+        self.submit(
+            move || Program::from_sources(&vertex, &fragment).map(Shared),
+            move |result| callback(result.map(|shared| shared.0)),
+        );
+    }
+
+    /// Number of jobs whose callbacks have not run yet.
+    pub fn pending(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    /// Runs the callbacks of jobs whose fences have signaled. Call once per frame on
+    /// the render thread.
+    pub fn poll(&mut self) {
+        while let Ok(finished) = self.finished.try_recv() {
+            self.in_flight.push(finished);
+        }
+
+        let mut i = 0;
+        while i < self.in_flight.len() {
+            let signaled = self.in_flight[i]
+                .fence
+                .as_ref()
+                .is_none_or(|fence| fence.0.is_signaled());
+            if signaled {
+                let done = self.in_flight.swap_remove(i);
+                self.finish(done.id, done.result);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn finish(&mut self, id: u64, result: Result<Output>) {
+        if let Some(i) = self
+            .callbacks
+            .iter()
+            .position(|(pending, _)| *pending == id)
+        {
+            let (_, callback) = self.callbacks.swap_remove(i);
+            callback(result);
+        }
+    }
+}
+
+impl Drop for UploadContext {
+    fn drop(&mut self) {
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
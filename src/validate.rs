@@ -0,0 +1,256 @@
+//! Optional checks before every draw.
+//!
+//! With validation on ([`set_enabled`], or the `validate` setting of the runners), the
+//! crate's draw calls first look at what is bound and check the things GL otherwise
+//! fails on without a word, leaving a black frame: a program in use and linked, a
+//! vertex array with every attribute the program reads enabled, a texture of the right
+//! type on the unit each sampler reads, and a complete draw framebuffer. Each distinct
+//! problem is logged once as an error naming the draw; [`check`] returns it instead.
+//! Where a vertex array meets a program, as in [`crate::draw::DrawList::submit`],
+//! [`pairing`] also compares the attribute formats with the program's inputs.
+//! Independently of the mode, debug builds run [`Program::validate`] before the first
+//! draw with each program.
+//!
+//! The bindings are queried from GL rather than tracked by the wrappers, so binds made
+//! with raw `gl` calls are seen too. The queries are synchronous; leave validation off
+//! when measuring performance.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+
+use crate::buffer::VertexArray;
+use crate::context;
+use crate::gl;
+use crate::shader::{self, Program};
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static REPORTED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    static PAIRED: RefCell<HashSet<(gl::types::GLuint, gl::types::GLuint)>> =
+        RefCell::new(HashSet::new());
+    static VALIDATED: RefCell<HashSet<gl::types::GLuint>> = RefCell::new(HashSet::new());
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.with(|cell| cell.set(enabled));
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.with(Cell::get)
+}
+
+/// Checks the bound state for a draw described as `what` when validation is on,
+/// logging a problem the first time it is found. Called by the crate's draw calls.
+///
+/// Debug builds also run [`Program::validate`] on the program in use before its first
+/// draw, whether validation is on or not.
+pub fn draw(what: &str) {
+    if cfg!(debug_assertions) {
+        validate_program_once(what);
+    }
+    if !is_enabled() {
+        return;
+    }
+    if let Err(e) = check() {
+        let message = format!("{}: {:#}", what, e);
+        if REPORTED.with(|reported| reported.borrow_mut().insert(message.clone())) {
+            tracing::error!("Invalid draw of {}", message);
+        }
+    }
+}
+
+fn validate_program_once(what: &str) {
+    let program = integer(gl::CURRENT_PROGRAM) as gl::types::GLuint;
+    if program == 0 || !VALIDATED.with(|validated| validated.borrow_mut().insert(program)) {
+        return;
+    }
+    if let Err(e) = shader::validate_program(program) {
+        tracing::error!("Before the first draw of {}: {:#}", what, e);
+    }
+}
+
+/// Forgets what was checked about program `id`, which is being deleted and whose name
+/// may be reused.
+pub(crate) fn forget_program(id: gl::types::GLuint) {
+    VALIDATED.with(|validated| validated.borrow_mut().remove(&id));
+    PAIRED.with(|paired| paired.borrow_mut().retain(|&(_, program)| program != id));
+}
+
+/// Checks `vertex_array` against `program` with [`VertexArray::check_program`] the
+/// first time they are used together when validation is on, logging mismatches.
+pub fn pairing(vertex_array: &VertexArray, program: &Program) {
+    if !is_enabled() {
+        return;
+    }
+    let pair = (vertex_array.id(), program.id());
+    if !PAIRED.with(|paired| paired.borrow_mut().insert(pair)) {
+        return;
+    }
+    if let Err(e) = vertex_array.check_program(program) {
+        tracing::error!(
+            "Vertex array {} doesn't match program {}: {:#}",
+            pair.0,
+            pair.1,
+            e
+        );
+    }
+}
+
+/// Checks that a draw with the current bindings would render something.
+pub fn check() -> Result<()> {
+    let program = integer(gl::CURRENT_PROGRAM) as gl::types::GLuint;
+    if program == 0 {
+        return Err(anyhow!("no program is in use"));
+    }
+    if program_integer(program, gl::LINK_STATUS) == 0 {
+        return Err(anyhow!(
+            "program {} is not linked: {}",
+            program,
+            shader::program_info_log(program)
+        ));
+    }
+
+    let vertex_array = integer(gl::VERTEX_ARRAY_BINDING);
+    if vertex_array == 0 && context::info().core_profile {
+        return Err(anyhow!("no vertex array is bound"));
+    }
+    check_attributes(program, vertex_array)?;
+    check_samplers(program)?;
+
+    let framebuffer = integer(gl::DRAW_FRAMEBUFFER_BINDING);
+    let status = unsafe { gl::CheckFramebufferStatus(gl::DRAW_FRAMEBUFFER) };
+    if status != gl::FRAMEBUFFER_COMPLETE {
+        return Err(anyhow!(
+            "draw framebuffer {} is incomplete ({})",
+            framebuffer,
+            framebuffer_status(status)
+        ));
+    }
+    Ok(())
+}
+
+/// Every location of every active attribute must be enabled in the bound vertex array.
+fn check_attributes(program: gl::types::GLuint, vertex_array: i32) -> Result<()> {
+    for input in shader::active_attributes(program) {
+        if input.location < 0 {
+            continue;
+        }
+        let (_, _, columns) = input.shape();
+        for location in input.location..input.location + columns * input.size {
+            let mut enabled = 0;
+            unsafe {
+                gl::GetVertexAttribiv(
+                    location as u32,
+                    gl::VERTEX_ATTRIB_ARRAY_ENABLED,
+                    &mut enabled,
+                );
+            }
+            if enabled == 0 {
+                return Err(anyhow!(
+                    "attribute `{}` at location {} is not enabled in vertex array {}",
+                    input.name,
+                    location,
+                    vertex_array
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every active sampler must read a unit with a texture of its type bound.
+fn check_samplers(program: gl::types::GLuint) -> Result<()> {
+    let units = integer(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS);
+    let active_unit = integer(gl::ACTIVE_TEXTURE) as gl::types::GLenum;
+    let mut result = Ok(());
+    'uniforms: for uniform in shader::active_uniforms(program) {
+        let Some((binding, label)) = sampler_binding(uniform.ty) else {
+            continue;
+        };
+        for element in 0..uniform.size {
+            let mut unit = 0;
+            unsafe {
+                gl::GetUniformiv(program, uniform.location + element, &mut unit);
+            }
+            if unit < 0 || unit >= units {
+                result = Err(anyhow!(
+                    "sampler `{}` reads texture unit {}, beyond the {} available",
+                    uniform.name,
+                    unit,
+                    units
+                ));
+                break 'uniforms;
+            }
+            let texture = unsafe {
+                gl::ActiveTexture(gl::TEXTURE0 + unit as u32);
+                integer(binding)
+            };
+            if texture == 0 {
+                result = Err(anyhow!(
+                    "sampler `{}` reads texture unit {}, which has no {} texture bound",
+                    uniform.name,
+                    unit,
+                    label
+                ));
+                break 'uniforms;
+            }
+        }
+    }
+    unsafe {
+        gl::ActiveTexture(active_unit);
+    }
+    result
+}
+
+/// The binding query and a description of the texture target a sampler type reads.
+fn sampler_binding(kind: gl::types::GLenum) -> Option<(gl::types::GLenum, &'static str)> {
+    Some(match kind {
+        gl::SAMPLER_2D
+        | gl::SAMPLER_2D_SHADOW
+        | gl::INT_SAMPLER_2D
+        | gl::UNSIGNED_INT_SAMPLER_2D => (gl::TEXTURE_BINDING_2D, "2D"),
+        gl::SAMPLER_3D | gl::INT_SAMPLER_3D | gl::UNSIGNED_INT_SAMPLER_3D => {
+            (gl::TEXTURE_BINDING_3D, "3D")
+        }
+        gl::SAMPLER_CUBE | gl::SAMPLER_CUBE_SHADOW => (gl::TEXTURE_BINDING_CUBE_MAP, "cube map"),
+        gl::SAMPLER_2D_ARRAY
+        | gl::SAMPLER_2D_ARRAY_SHADOW
+        | gl::INT_SAMPLER_2D_ARRAY
+        | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY => (gl::TEXTURE_BINDING_2D_ARRAY, "2D array"),
+        gl::SAMPLER_2D_MULTISAMPLE => (gl::TEXTURE_BINDING_2D_MULTISAMPLE, "multisample"),
+        gl::SAMPLER_BUFFER => (gl::TEXTURE_BINDING_BUFFER, "buffer"),
+        _ => return None,
+    })
+}
+
+fn framebuffer_status(status: gl::types::GLenum) -> String {
+    match status {
+        gl::FRAMEBUFFER_UNDEFINED => "undefined".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "an attachment is incomplete".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "nothing is attached".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "a draw buffer has no attachment".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "the read buffer has no attachment".into(),
+        gl::FRAMEBUFFER_UNSUPPORTED => "unsupported attachment formats".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "attachments differ in samples".into(),
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "attachments differ in layering".into(),
+        status => format!("status 0x{:x}", status),
+    }
+}
+
+fn program_integer(program: gl::types::GLuint, name: gl::types::GLenum) -> i32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetProgramiv(program, name, &mut value);
+    }
+    value
+}
+
+fn integer(name: gl::types::GLenum) -> i32 {
+    let mut value = 0;
+    unsafe {
+        gl::GetIntegerv(name, &mut value);
+    }
+    value
+}
@@ -0,0 +1,184 @@
+//! Standard vertex formats.
+//!
+//! Each attribute has one location across the crate: positions at [`POSITION`], normals
+//! at [`NORMAL`], and so on. The formats here interleave a subset of them, and each comes
+//! with its vertex shader `in` declarations in [`VertexFormat::GLSL`] and the calls that
+//! source them from a buffer in [`VertexFormat::attach`]. A loader or generator that
+//! emits one of these, and a shader that starts from its `GLSL`, work together with no
+//! glue; [`crate::buffer::VertexArray::check_program`] catches the mismatches when not.
+//!
+//! [`PosNormUv`] and [`Skinned`] are [`crate::mesh::Vertex`] and
+//! [`crate::mesh::SkinnedVertex`], which [`crate::mesh::Mesh`] uploads alongside a
+//! separate tangent buffer: shaders for meshes can start from [`PosNormTanUv::GLSL`].
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::buffer::{Buffer, VertexArray};
+use crate::gl;
+use crate::mesh::{self, SkinnedVertex, Vertex};
+
+pub const POSITION: u32 = 0;
+pub const NORMAL: u32 = 1;
+pub const UV: u32 = 2;
+/// Four joint indices, read as a `uvec4`.
+pub const JOINTS: u32 = 3;
+pub const WEIGHTS: u32 = 4;
+/// The tangent in `xyz` and the bitangent's handedness in `w`.
+pub const TANGENT: u32 = mesh::TANGENT_LOCATION;
+/// Linear RGBA.
+pub const COLOR: u32 = 6;
+
+/// One attribute of a [`VertexFormat`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Attribute {
+    /// The shader input's name.
+    pub name: &'static str,
+    pub location: u32,
+    /// Components, 1 to 4.
+    pub size: i32,
+    pub ty: gl::types::GLenum,
+    /// Read as `ivec`/`uvec` instead of converted to floats.
+    pub integer: bool,
+    /// Bytes from the start of the vertex.
+    pub offset: usize,
+}
+
+/// An interleaved vertex layout with fixed attribute locations.
+pub trait VertexFormat: Pod {
+    const ATTRIBUTES: &'static [Attribute];
+    /// Vertex shader `in` declarations of [`VertexFormat::ATTRIBUTES`]. Insert it after
+    /// the `#version` line.
+    const GLSL: &'static str;
+
+    /// Sources every attribute of the format from `buffer`, tightly packed vertices of
+    /// this type from its start.
+    fn attach(vertex_array: &VertexArray, buffer: &Buffer) {
+        let stride = std::mem::size_of::<Self>() as i32;
+        for a in Self::ATTRIBUTES {
+            if a.integer {
+                vertex_array.integer_attribute(a.location, buffer, a.size, a.ty, stride, a.offset);
+            } else {
+                vertex_array.attribute(a.location, buffer, a.size, a.ty, false, stride, a.offset);
+            }
+        }
+    }
+}
+
+const fn float(name: &'static str, location: u32, size: i32, offset: usize) -> Attribute {
+    Attribute {
+        name,
+        location,
+        size,
+        ty: gl::FLOAT,
+        integer: false,
+        offset,
+    }
+}
+
+/// Untextured, unlit geometry such as debug lines and gizmos.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosColor {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl VertexFormat for PosColor {
+    const ATTRIBUTES: &'static [Attribute] = &[
+        float("a_position", POSITION, 3, 0),
+        float("a_color", COLOR, 4, 12),
+    ];
+    const GLSL: &'static str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 6) in vec4 a_color;
+"#;
+}
+
+/// Unlit textured geometry such as billboards and screen-space quads.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosUv {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+impl VertexFormat for PosUv {
+    const ATTRIBUTES: &'static [Attribute] = &[
+        float("a_position", POSITION, 3, 0),
+        float("a_uv", UV, 2, 12),
+    ];
+    const GLSL: &'static str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 2) in vec2 a_uv;
+"#;
+}
+
+/// Lit textured geometry without normal maps.
+pub type PosNormUv = Vertex;
+
+impl VertexFormat for Vertex {
+    const ATTRIBUTES: &'static [Attribute] = &[
+        float("a_position", POSITION, 3, 0),
+        float("a_normal", NORMAL, 3, 12),
+        float("a_uv", UV, 2, 24),
+    ];
+    const GLSL: &'static str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv;
+"#;
+}
+
+/// Lit textured geometry with an interleaved tangent frame for normal maps.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PosNormTanUv {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+impl VertexFormat for PosNormTanUv {
+    const ATTRIBUTES: &'static [Attribute] = &[
+        float("a_position", POSITION, 3, 0),
+        float("a_normal", NORMAL, 3, 12),
+        float("a_tangent", TANGENT, 4, 24),
+        float("a_uv", UV, 2, 40),
+    ];
+    const GLSL: &'static str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv;
+layout (location = 5) in vec4 a_tangent;
+"#;
+}
+
+/// [`PosNormUv`] with four joint influences. Its `GLSL` declares the joint inputs that
+/// [`crate::animation::SKINNING_GLSL`] also declares, so use it with a shader's own
+/// skinning code.
+pub type Skinned = SkinnedVertex;
+
+impl VertexFormat for SkinnedVertex {
+    const ATTRIBUTES: &'static [Attribute] = &[
+        float("a_position", POSITION, 3, 0),
+        float("a_normal", NORMAL, 3, 12),
+        float("a_uv", UV, 2, 24),
+        Attribute {
+            name: "a_joints",
+            location: JOINTS,
+            size: 4,
+            ty: gl::UNSIGNED_INT,
+            integer: true,
+            offset: 32,
+        },
+        float("a_weights", WEIGHTS, 4, 48),
+    ];
+    const GLSL: &'static str = r#"
+layout (location = 0) in vec3 a_position;
+layout (location = 1) in vec3 a_normal;
+layout (location = 2) in vec2 a_uv;
+layout (location = 3) in uvec4 a_joints;
+layout (location = 4) in vec4 a_weights;
+"#;
+}
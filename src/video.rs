@@ -0,0 +1,360 @@
+//! Video playback into a texture (feature `video`).
+//!
+//! [`VideoTexture::open`] starts a worker thread that demuxes and decodes the file with
+//! FFmpeg, converts each frame to RGBA and queues it. [`VideoTexture::update`] advances
+//! the playback clock and uploads the newest due frame through a pair of pixel-unpack
+//! buffers: while the GPU copies one frame out of one buffer, the next is written into
+//! the other, so uploads never wait for the previous one. The texture is an ordinary
+//! `RGBA8` [`Texture`] behind an [`Rc`], so it can be shared with any [`Material`]
+//! slot.
+//!
+//! Only the video stream is decoded; audio is ignored. The decoder runs at most
+//! [`QUEUED_FRAMES`] ahead of playback.
+//!
+//! [`Material`]: crate::material::Material
+
+use std::cell::Cell;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+use ffmpeg_next as ffmpeg;
+
+use crate::buffer::Buffer;
+use crate::context;
+use crate::gl;
+use crate::texture::Texture;
+
+/// Decoded frames waiting for upload. Bounds the memory the worker can use and how far
+/// it decodes ahead.
+pub const QUEUED_FRAMES: usize = 4;
+
+struct Frame {
+    pixels: Vec<u8>,
+    /// Presentation time in seconds since playback started, counting earlier loops.
+    time: f64,
+}
+
+enum Message {
+    Frame(Frame),
+    /// The file ended and isn't looping.
+    End,
+    Error(anyhow::Error),
+}
+
+/// Properties of an opened video.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VideoInfo {
+    pub width: u32,
+    pub height: u32,
+    /// Average frames per second, 0 if the container doesn't say.
+    pub frame_rate: f64,
+    /// Length in seconds, 0 if unknown.
+    pub duration: f64,
+}
+
+/// A texture showing a video file, one frame at a time.
+pub struct VideoTexture {
+    info: VideoInfo,
+    texture: Rc<Texture>,
+    staging: [Buffer; 2],
+    next_staging: usize,
+    frames: Receiver<Message>,
+    /// The next frame, received but not yet due.
+    pending: Option<Frame>,
+    worker: Option<JoinHandle<()>>,
+    clock: f64,
+    paused: bool,
+    finished: bool,
+}
+
+impl VideoTexture {
+    /// Opens `path` and starts decoding. With `looping`, playback restarts at the end
+    /// instead of holding the last frame.
+    pub fn open(path: impl AsRef<Path>, looping: bool) -> Result<VideoTexture> {
+        let path = path.as_ref().to_path_buf();
+        let (sender, frames) = mpsc::sync_channel(QUEUED_FRAMES);
+        let (info_sender, info) = mpsc::channel();
+        let worker = std::thread::Builder::new()
+            .name("video decoder".into())
+            .spawn(move || {
+                let result = decode(&path, looping, &sender, |info| {
+                    let _ = info_sender.send(Ok(info));
+                });
+                if let Err(error) = result {
+                    let error = error.context(format!("Failed to decode {}", path.display()));
+                    // Before the info, the error goes to `open`; afterwards to `update`.
+                    if let Err(mpsc::SendError(Err(error))) = info_sender.send(Err(error)) {
+                        let _ = sender.send(Message::Error(error));
+                    }
+                }
+            })?;
+        let info = info
+            .recv()
+            .map_err(|_| anyhow!("Video decoder stopped"))??;
+        context::limits().check_texture_size(info.width as i32, info.height as i32)?;
+
+        let texture = Texture::new(gl::TEXTURE_2D)?;
+        texture.bind();
+        texture.parameter(gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        texture.parameter(gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        texture.parameter(gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        let black = vec![0; info.width as usize * info.height as usize * 4];
+        texture.image_2d(
+            0,
+            gl::RGBA8,
+            info.width as i32,
+            info.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&black),
+        );
+        texture.unbind();
+        texture.label("video");
+
+        let staging = [Buffer::new()?, Buffer::new()?];
+        for buffer in &staging {
+            buffer.bind(gl::PIXEL_UNPACK_BUFFER);
+            buffer.allocate(gl::PIXEL_UNPACK_BUFFER, black.len(), gl::STREAM_DRAW);
+            buffer.unbind(gl::PIXEL_UNPACK_BUFFER);
+        }
+
+        Ok(VideoTexture {
+            info,
+            texture: Rc::new(texture),
+            staging,
+            next_staging: 0,
+            frames,
+            pending: None,
+            worker: Some(worker),
+            clock: 0.0,
+            paused: false,
+            finished: false,
+        })
+    }
+
+    pub fn info(&self) -> VideoInfo {
+        self.info
+    }
+
+    /// The texture, for [`crate::material::Material`] slots or [`Texture::bind_unit`].
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.texture
+    }
+
+    /// Seconds of playback, counting every loop and excluding pauses.
+    pub fn time(&self) -> f64 {
+        self.clock
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Whether a non-looping video has shown its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Advances playback by `dt` seconds and uploads the newest frame that is due, if
+    /// any. Fails once if the decoder hits an error, after which the last frame stays.
+    ///
+    /// Must be called from the thread owning the GL context, typically once per frame.
+    pub fn update(&mut self, dt: f32) -> Result<()> {
+        if self.paused || self.finished {
+            return Ok(());
+        }
+        self.clock += dt as f64;
+        let mut due = None;
+        loop {
+            let frame = match self.pending.take() {
+                Some(frame) => frame,
+                None => match self.frames.try_recv() {
+                    Ok(Message::Frame(frame)) => frame,
+                    Ok(Message::End) | Err(TryRecvError::Disconnected) => {
+                        self.finished = true;
+                        break;
+                    }
+                    Ok(Message::Error(error)) => {
+                        self.finished = true;
+                        return Err(error);
+                    }
+                    Err(TryRecvError::Empty) => break,
+                },
+            };
+            if frame.time > self.clock {
+                self.pending = Some(frame);
+                break;
+            }
+            // Frames the clock has already passed are dropped in favour of later ones.
+            due = Some(frame);
+        }
+        if let Some(frame) = due {
+            self.upload(&frame.pixels)?;
+        }
+        Ok(())
+    }
+
+    fn upload(&mut self, pixels: &[u8]) -> Result<()> {
+        let _span = tracing::debug_span!("pass", name = "video upload").entered();
+        let staging = &self.staging[self.next_staging];
+        self.next_staging = 1 - self.next_staging;
+        staging.bind(gl::PIXEL_UNPACK_BUFFER);
+        let ptr = staging.map_range(
+            gl::PIXEL_UNPACK_BUFFER,
+            0,
+            pixels.len(),
+            gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_BUFFER_BIT,
+        );
+        let ptr = match ptr {
+            Ok(ptr) => ptr,
+            Err(e) => {
+                staging.unbind(gl::PIXEL_UNPACK_BUFFER);
+                return Err(e);
+            }
+        };
+        unsafe {
+            std::ptr::copy_nonoverlapping(pixels.as_ptr(), ptr, pixels.len());
+        }
+        staging.unmap(gl::PIXEL_UNPACK_BUFFER);
+        self.texture.bind();
+        self.texture.sub_image_2d_from_buffer(
+            0,
+            0,
+            0,
+            self.info.width as i32,
+            self.info.height as i32,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            0,
+        );
+        self.texture.unbind();
+        staging.unbind(gl::PIXEL_UNPACK_BUFFER);
+        Ok(())
+    }
+}
+
+impl Drop for VideoTexture {
+    fn drop(&mut self) {
+        // Dropping the receiver fails the worker's next or blocked send, which stops it.
+        let (_, closed) = mpsc::sync_channel(0);
+        drop(std::mem::replace(&mut self.frames, closed));
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Decodes `path` into `frames` until the receiver goes away, calling `opened` once the
+/// stream's properties are known.
+fn decode(
+    path: &Path,
+    looping: bool,
+    frames: &SyncSender<Message>,
+    opened: impl FnOnce(VideoInfo),
+) -> Result<()> {
+    ffmpeg::init()?;
+    let mut input = ffmpeg::format::input(path)?;
+    let stream = input
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .ok_or_else(|| anyhow!("No video stream"))?;
+    let stream_index = stream.index();
+    let time_base = f64::from(stream.time_base());
+    let frame_rate = match f64::from(stream.avg_frame_rate()) {
+        rate if rate.is_finite() && rate > 0.0 => rate,
+        _ => 0.0,
+    };
+    let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+    let mut decoder = context.decoder().video()?;
+    let (width, height) = (decoder.width(), decoder.height());
+    let duration = match input.duration() {
+        duration if duration > 0 => duration as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE),
+        _ => 0.0,
+    };
+    let mut scaler = ffmpeg::software::scaling::Context::get(
+        decoder.format(),
+        width,
+        height,
+        ffmpeg::format::Pixel::RGBA,
+        width,
+        height,
+        ffmpeg::software::scaling::Flags::BILINEAR,
+    )?;
+    opened(VideoInfo {
+        width,
+        height,
+        frame_rate,
+        duration,
+    });
+
+    let mut decoded = ffmpeg::frame::Video::empty();
+    let mut rgba = ffmpeg::frame::Video::empty();
+    let period = if frame_rate > 0.0 {
+        frame_rate.recip()
+    } else {
+        0.0
+    };
+    let mut index = 0u64;
+    let mut start = None;
+    // Each loop continues the timeline where the last one ended, so playback never
+    // has to rewind its clock.
+    let loop_start = Cell::new(0.0);
+    let last_time = Cell::new(0.0);
+    // Sends every frame the decoder has ready; false once the receiver is gone.
+    let mut drain = |decoder: &mut ffmpeg::decoder::Video| -> Result<bool> {
+        while decoder.receive_frame(&mut decoded).is_ok() {
+            scaler.run(&decoded, &mut rgba)?;
+            let time = match decoded.timestamp() {
+                Some(timestamp) => timestamp as f64 * time_base,
+                None if frame_rate > 0.0 => index as f64 / frame_rate,
+                None => 0.0,
+            };
+            index += 1;
+            // Streams needn't start at zero; every loop starts where the first did.
+            let time = loop_start.get() + (time - *start.get_or_insert(time)).max(0.0);
+            last_time.set(time);
+            // Rows of the converted frame may be padded; the texture wants them packed.
+            let row = width as usize * 4;
+            let stride = rgba.stride(0);
+            let mut pixels = Vec::with_capacity(row * height as usize);
+            for y in 0..height as usize {
+                pixels.extend_from_slice(&rgba.data(0)[y * stride..y * stride + row]);
+            }
+            if frames.send(Message::Frame(Frame { pixels, time })).is_err() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    };
+
+    loop {
+        for (stream, packet) in input.packets() {
+            if stream.index() != stream_index {
+                continue;
+            }
+            decoder.send_packet(&packet)?;
+            if !drain(&mut decoder)? {
+                return Ok(());
+            }
+        }
+        decoder.send_eof()?;
+        if !drain(&mut decoder)? {
+            return Ok(());
+        }
+        if !looping {
+            let _ = frames.send(Message::End);
+            return Ok(());
+        }
+        loop_start.set(last_time.get() + period);
+        input.seek(0, ..)?;
+        decoder.flush();
+    }
+}
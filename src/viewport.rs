@@ -0,0 +1,388 @@
+//! Split-screen and multi-view rendering into regions of the default framebuffer.
+//!
+//! A [`Viewport`] is a pixel rectangle in GL window coordinates (origin at the bottom
+//! left). [`Viewport::apply`] sets both `glViewport` and a matching scissor box, so
+//! clears stay inside the region; call [`reset`] before drawing across the whole
+//! framebuffer again.
+//!
+//! [`Viewport::project`] and [`Viewport::unproject`] convert between world positions and
+//! window coordinates with `y` down, as winit reports cursor positions, and
+//! [`Viewport::ray`] casts the ray under the cursor. The [`Camera`] methods of the same
+//! names do the same through the camera's own matrices.
+//!
+//! For head-mounted displays, [`Camera::eye_view`] and [`Camera::eye_projection`] turn
+//! an [`EyeView`] from the XR runtime into matrices, with the camera placing the
+//! tracking space in the world.
+
+use serde::{Deserialize, Serialize};
+
+use crate::culling::Aabb;
+use crate::depth;
+use crate::event::MouseButton;
+use crate::gl;
+use crate::input::Input;
+use crate::math::{Mat4, Quat, Vec2, Vec3, Vec4};
+use crate::ray::Ray;
+
+/// A perspective camera looking from `eye` at `target`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Camera {
+    pub eye: Vec3,
+    pub target: Vec3,
+    pub up: Vec3,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    /// A camera with a 45° field of view and a 0.1–100 depth range.
+    pub fn look_at(eye: Vec3, target: Vec3) -> Camera {
+        Camera {
+            eye,
+            target,
+            up: Vec3::Y,
+            fov_y: 45f32.to_radians(),
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_rh_gl(self.fov_y, aspect, self.near, self.far)
+    }
+
+    /// Like [`Camera::projection`] for [`crate::context::ContextOptions::reversed_z`]:
+    /// maps `near` to depth 1 and `far` to 0.
+    pub fn projection_reversed_z(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_rh(self.fov_y, aspect, self.far, self.near)
+    }
+
+    /// Reversed-Z with the far plane at infinity, ignoring `far`. The float depth
+    /// buffer keeps enough precision that nothing is ever clipped by distance.
+    pub fn projection_infinite_reversed_z(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_infinite_reverse_rh(self.fov_y, aspect, self.near)
+    }
+
+    /// [`Camera::projection`] or [`Camera::projection_reversed_z`], whichever matches
+    /// the current context.
+    pub fn projection_for_context(&self, aspect: f32) -> Mat4 {
+        if depth::reversed_z() {
+            self.projection_reversed_z(aspect)
+        } else {
+            self.projection(aspect)
+        }
+    }
+
+    /// [`Viewport::project`] through this camera, with
+    /// [`Camera::projection_for_context`] at the viewport's aspect ratio.
+    pub fn project(
+        &self,
+        world: Vec3,
+        viewport: &Viewport,
+        framebuffer_height: u32,
+    ) -> Option<Vec3> {
+        viewport.project(
+            self.view_projection_for(viewport),
+            world,
+            framebuffer_height,
+        )
+    }
+
+    /// [`Viewport::unproject`] through this camera, like [`Camera::project`].
+    pub fn unproject(&self, screen: Vec3, viewport: &Viewport, framebuffer_height: u32) -> Vec3 {
+        viewport.unproject(
+            self.view_projection_for(viewport),
+            screen,
+            framebuffer_height,
+        )
+    }
+
+    /// [`Viewport::ray`] through this camera, like [`Camera::project`].
+    pub fn ray(&self, screen: Vec2, viewport: &Viewport, framebuffer_height: u32) -> Ray {
+        viewport.ray(
+            self.view_projection_for(viewport),
+            screen,
+            framebuffer_height,
+        )
+    }
+
+    fn view_projection_for(&self, viewport: &Viewport) -> Mat4 {
+        self.projection_for_context(viewport.aspect()) * self.view()
+    }
+
+    /// The view matrix of `eye`, whose pose is relative to a tracking space placed at
+    /// `eye` looking at `target`. Keep `target` level with `eye`, or the horizon tilts.
+    pub fn eye_view(&self, eye: &EyeView) -> Mat4 {
+        eye.view() * self.view()
+    }
+
+    /// The off-center projection of `eye`'s field of view with the camera's `near` and
+    /// `far`, reversed-Z if the current context is.
+    pub fn eye_projection(&self, eye: &EyeView) -> Mat4 {
+        let fov = eye.fov;
+        let (left, right) = (fov.left.tan(), fov.right.tan());
+        let (up, down) = (fov.up.tan(), fov.down.tan());
+        let (width, height) = (right - left, up - down);
+        let (near, far) = (self.near, self.far);
+        let (z, w) = if depth::reversed_z() {
+            (near / (far - near), far * near / (far - near))
+        } else {
+            (
+                -(far + near) / (far - near),
+                -2.0 * far * near / (far - near),
+            )
+        };
+        Mat4::from_cols(
+            Vec4::new(2.0 / width, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / height, 0.0, 0.0),
+            Vec4::new((right + left) / width, (up + down) / height, z, -1.0),
+            Vec4::new(0.0, 0.0, w, 0.0),
+        )
+    }
+}
+
+/// The field of view of one eye of a head-mounted display: the angles in radians of its
+/// edges from straight ahead, negative for `left` and `down`. Usually asymmetric.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fov {
+    pub left: f32,
+    pub right: f32,
+    pub up: f32,
+    pub down: f32,
+}
+
+/// One eye of a stereo frame, with the pose the XR runtime predicts for the moment the
+/// frame is displayed, in its tracking space: Y up, -Z forward, in meters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EyeView {
+    /// `0` for the left eye, `1` for the right.
+    pub index: usize,
+    pub position: Vec3,
+    pub orientation: Quat,
+    pub fov: Fov,
+}
+
+impl EyeView {
+    /// From tracking space to eye space; see [`Camera::eye_view`] to place it in the
+    /// world.
+    pub fn view(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.orientation, self.position).inverse()
+    }
+}
+
+/// Radians per pixel dragged.
+const ORBIT_SPEED: f32 = 0.01;
+
+/// A camera circling `target`, driven by [`Input`]: dragging with the left button orbits
+/// and the scroll wheel zooms.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: f32,
+    /// Around the Y axis, in radians; `0` looks down -Z.
+    pub yaw: f32,
+    /// Above the horizon, in radians.
+    pub pitch: f32,
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vec3, distance: f32) -> OrbitCamera {
+        OrbitCamera {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.3,
+            fov_y: 45f32.to_radians(),
+        }
+    }
+
+    /// Centers on `bounds` and backs off until its bounding sphere fits the field of view.
+    pub fn frame(&mut self, bounds: &Aabb) {
+        if bounds.is_empty() {
+            return;
+        }
+        let sphere = bounds.bounding_sphere();
+        self.target = sphere.center;
+        self.distance = (sphere.radius / (self.fov_y * 0.5).sin()).max(0.01);
+    }
+
+    pub fn update(&mut self, input: &Input) {
+        if input.is_button_pressed(MouseButton::Left) {
+            let drag = input.mouse_motion() * ORBIT_SPEED;
+            self.yaw -= drag.x;
+            self.pitch = (self.pitch + drag.y).clamp(-1.5, 1.5);
+        }
+        self.distance *= 0.9f32.powf(input.scroll());
+    }
+
+    /// The camera at the current orbit, with depth range scaled to the distance.
+    pub fn camera(&self) -> Camera {
+        let direction = Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        );
+        Camera {
+            near: self.distance * 0.01,
+            far: self.distance * 100.0,
+            fov_y: self.fov_y,
+            ..Camera::look_at(self.target + direction * self.distance, self.target)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Viewport {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Viewport {
+        Viewport {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole framebuffer.
+    pub fn full(width: u32, height: u32) -> Viewport {
+        Viewport::new(0, 0, width as i32, height as i32)
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    /// Whether the window-coordinate point `(x, y)` lies inside, with `y` measured from
+    /// the top as winit reports cursor positions.
+    pub fn contains(&self, x: f64, y: f64, framebuffer_height: u32) -> bool {
+        let y = framebuffer_height as f64 - y;
+        x >= self.x as f64
+            && x < (self.x + self.width) as f64
+            && y >= self.y as f64
+            && y < (self.y + self.height) as f64
+    }
+
+    /// `camera`'s projection at this viewport's aspect ratio times its view.
+    pub fn view_projection(&self, camera: &Camera) -> Mat4 {
+        camera.projection(self.aspect()) * camera.view()
+    }
+
+    /// Normalized device coordinates of window position `screen`, `y` down.
+    pub fn to_ndc(&self, screen: Vec2, framebuffer_height: u32) -> Vec2 {
+        let y = framebuffer_height as f32 - screen.y;
+        Vec2::new(
+            (screen.x - self.x as f32) / self.width.max(1) as f32 * 2.0 - 1.0,
+            (y - self.y as f32) / self.height.max(1) as f32 * 2.0 - 1.0,
+        )
+    }
+
+    /// Where `world` lands in a scene drawn into this viewport with `view_projection`:
+    /// window `x` and `y` in pixels, `y` down, and the depth buffer value in `z`. `None`
+    /// behind the camera. `view_projection` must follow the depth convention of the
+    /// context, as [`Camera::projection_for_context`] does.
+    pub fn project(
+        &self,
+        view_projection: Mat4,
+        world: Vec3,
+        framebuffer_height: u32,
+    ) -> Option<Vec3> {
+        let clip = view_projection * world.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        let x = self.x as f32 + (ndc.x * 0.5 + 0.5) * self.width as f32;
+        let y = self.y as f32 + (ndc.y * 0.5 + 0.5) * self.height as f32;
+        Some(Vec3::new(
+            x,
+            framebuffer_height as f32 - y,
+            depth::from_ndc(ndc.z),
+        ))
+    }
+
+    /// The inverse of [`Viewport::project`]: the world position at window position
+    /// `screen.xy`, `y` down, and depth buffer value `screen.z`, e.g. one read back from
+    /// the depth buffer.
+    pub fn unproject(&self, view_projection: Mat4, screen: Vec3, framebuffer_height: u32) -> Vec3 {
+        let ndc = self.to_ndc(screen.truncate(), framebuffer_height);
+        view_projection
+            .inverse()
+            .project_point3(ndc.extend(depth::to_ndc(screen.z)))
+    }
+
+    /// The ray from the near plane through window position `screen`, `y` down, like
+    /// [`Viewport::unproject`].
+    pub fn ray(&self, view_projection: Mat4, screen: Vec2, framebuffer_height: u32) -> Ray {
+        let ndc = self.to_ndc(screen, framebuffer_height);
+        let inverse = view_projection.inverse();
+        let near = inverse.project_point3(ndc.extend(depth::to_ndc(depth::near())));
+        // Halfway through the depth range, which stays finite with an infinite far plane.
+        let beyond = inverse.project_point3(ndc.extend(depth::to_ndc(0.5)));
+        Ray::new(near, beyond - near)
+    }
+
+    /// Restricts drawing and clearing to this viewport.
+    pub fn apply(&self) {
+        unsafe {
+            gl::Viewport(self.x, self.y, self.width, self.height);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Scissor(self.x, self.y, self.width, self.height);
+        }
+    }
+}
+
+/// Disables the scissor test and sets the viewport to the whole framebuffer.
+pub fn reset(width: u32, height: u32) {
+    unsafe {
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::Viewport(0, 0, width as i32, height as i32);
+    }
+}
+
+/// Divides the framebuffer into `columns × rows` cells, row by row from the top left.
+pub fn grid(width: u32, height: u32, columns: u32, rows: u32) -> Vec<Viewport> {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut viewports = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        // Integer edges so neighbouring cells neither overlap nor leave gaps.
+        let top = height - height * row / rows;
+        let bottom = height - height * (row + 1) / rows;
+        for column in 0..columns {
+            let left = width * column / columns;
+            let right = width * (column + 1) / columns;
+            viewports.push(Viewport::new(
+                left as i32,
+                bottom as i32,
+                (right - left) as i32,
+                (top - bottom) as i32,
+            ));
+        }
+    }
+    viewports
+}
+
+/// The usual split-screen layouts: one player fills the framebuffer, two are side by
+/// side, and three or four share a 2×2 grid, player one at the top left.
+pub fn split_screen(width: u32, height: u32, players: usize) -> Vec<Viewport> {
+    let mut viewports = match players {
+        0 | 1 => vec![Viewport::full(width, height)],
+        2 => grid(width, height, 2, 1),
+        _ => grid(width, height, 2, 2),
+    };
+    viewports.truncate(players.max(1));
+    viewports
+}
@@ -0,0 +1,164 @@
+//! WebGL2 backend for `wasm32`, built on `glow`.
+//!
+//! [`run`] creates a `glow::Context` on a canvas and drives an [`App`] from winit's web
+//! event loop. On `wasm32` the crate's `gl` is [`gl`], which forwards the calls the
+//! wrappers make to that context, so [`crate::buffer::Buffer`], [`crate::shader::Program`]
+//! and the rest work as on desktop GL, translating shaders to GLSL ES. [`context`] gives
+//! direct access to glow for what the wrappers don't cover.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::Result;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlCanvasElement, WebGl2RenderingContext};
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::platform::web::WindowAttributesExtWebSys;
+use winit::window::{Window, WindowId};
+
+use crate::app::{debug_keys, App};
+use crate::builtins;
+use crate::context::{ContextOptions, GlContext};
+use crate::event;
+use crate::per_frame;
+
+pub mod gl;
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Rc<glow::Context>>> = const { RefCell::new(None) };
+}
+
+/// The WebGL2 context created by [`run`].
+///
+/// Panics if called before [`run`].
+pub fn context() -> Rc<glow::Context> {
+    CONTEXT.with(|context| {
+        context
+            .borrow()
+            .clone()
+            .expect("WebGL2 context not created; call web::run first")
+    })
+}
+
+/// Creates a WebGL2 context on the `<canvas>` with id `canvas_id`, builds the app with
+/// `init` and runs it in the browser's animation loop.
+pub fn run<A, F>(canvas_id: &str, init: F) -> !
+where
+    A: App + 'static,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    let browser = web_sys::window().expect("no browser window");
+    let canvas = browser
+        .document()
+        .and_then(|document| document.get_element_by_id(canvas_id))
+        .and_then(|element| element.dyn_into::<HtmlCanvasElement>().ok())
+        .unwrap_or_else(|| panic!("no <canvas id=\"{}\">", canvas_id));
+    let webgl2 = canvas
+        .get_context("webgl2")
+        .ok()
+        .flatten()
+        .and_then(|context| context.dyn_into::<WebGl2RenderingContext>().ok())
+        .expect("WebGL2 is not supported");
+    CONTEXT.with(|context| {
+        *context.borrow_mut() = Some(Rc::new(glow::Context::from_webgl2_context(webgl2)));
+    });
+    unsafe { GlContext::assume_current() };
+    ContextOptions::default().apply();
+
+    // std::time::Instant is unavailable on wasm32-unknown-unknown.
+    let performance = browser.performance().expect("no performance timer");
+    let start = performance.now();
+    let event_loop = EventLoop::new().unwrap();
+    event_loop.set_control_flow(ControlFlow::Poll);
+    let mut runner = WebRunner {
+        canvas: Some(canvas),
+        init: Some(init),
+        window: None,
+        app: None,
+        performance,
+        start,
+        last_frame: start,
+    };
+    // On the web this hands control to the browser and never returns.
+    event_loop.run_app(&mut runner).unwrap();
+    unreachable!("the web event loop returned");
+}
+
+/// The canvas window and app of a [`run`], as a winit [`ApplicationHandler`].
+struct WebRunner<A, F> {
+    /// Taken by the first `Resumed`, which creates the window on it.
+    canvas: Option<HtmlCanvasElement>,
+    init: Option<F>,
+    window: Option<Window>,
+    app: Option<A>,
+    performance: web_sys::Performance,
+    start: f64,
+    last_frame: f64,
+}
+
+impl<A, F> ApplicationHandler for WebRunner<A, F>
+where
+    A: App,
+    F: FnOnce(&Window) -> Result<A>,
+{
+    /// Creates the window on the canvas and builds the app, the first time.
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(canvas), Some(init)) = (self.canvas.take(), self.init.take()) else {
+            return;
+        };
+        let attributes = Window::default_attributes().with_canvas(Some(canvas));
+        let window = event_loop.create_window(attributes).unwrap();
+        let mut app = init(&window).unwrap();
+        let size = window.inner_size();
+        builtins::resize(size.width, size.height);
+        app.resize(size.width, size.height);
+        self.window = Some(window);
+        self.app = Some(app);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let (Some(window), Some(app)) = (&mut self.window, &mut self.app) else {
+            return;
+        };
+        match event {
+            WindowEvent::RedrawRequested => {
+                let now = self.performance.now();
+                builtins::set_time(((now - self.start) / 1000.0) as f32);
+                app.update(((now - self.last_frame) / 1000.0) as f32);
+                app.update_window(window);
+                self.last_frame = now;
+
+                // The browser presents the canvas once the callback returns.
+                app.render();
+                per_frame::advance_frame();
+                return;
+            }
+            WindowEvent::Resized(physical_size)
+                if physical_size.width > 0 && physical_size.height > 0 =>
+            {
+                builtins::resize(physical_size.width, physical_size.height);
+                app.resize(physical_size.width, physical_size.height);
+            }
+            WindowEvent::CloseRequested => event_loop.exit(),
+            _ => (),
+        }
+        for event in event::WindowEvent::from_winit(&event) {
+            debug_keys(&event);
+            builtins::window_event(&event);
+            app.window_event(&event);
+        }
+    }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+}
@@ -0,0 +1,1336 @@
+//! The `gl` bindings on WebGL2: the generated desktop bindings, with the entry points
+//! the wrappers call replaced by functions forwarding to the `glow` context of
+//! [`super::run`]. GL names are mapped to glow's objects in tables on the thread, so
+//! [`crate::buffer::Buffer`], [`crate::texture::Texture`], [`crate::shader::Program`],
+//! [`crate::framebuffer::Framebuffer`] and the other wrappers run unchanged on their
+//! non-DSA paths. The context reports itself as OpenGL ES 3.0, with WebGL's extensions
+//! prefixed with `GL_`, so shaders are translated to GLSL ES.
+//!
+//! Entry points WebGL2 lacks, such as `glGetTexImage`, the DSA functions and compute,
+//! stay unloaded: `is_loaded` reports `false`, which the wrappers check for the optional
+//! ones, and calling them panics like any unloaded function. `glMapBufferRange` returns
+//! null, so [`crate::buffer::Buffer::map_range`] fails. Queries glow can't answer leave
+//! their output untouched, as GL does for an unknown parameter. `gl-trace` doesn't log
+//! the forwarded calls.
+
+#![allow(non_snake_case, clippy::missing_safety_doc, clippy::too_many_arguments)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_void;
+
+use glow::{HasContext, PixelPackData, PixelUnpackData};
+
+use self::types::{
+    GLbitfield, GLboolean, GLchar, GLenum, GLfloat, GLint, GLintptr, GLsizei, GLsizeiptr, GLubyte,
+    GLuint,
+};
+
+// With `gl-trace`, the tracing wrappers of the replaced functions go unused.
+#[allow(clippy::all, dead_code)]
+mod bindings {
+    include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+pub use self::bindings::*;
+
+/// Objects of one kind by GL name. Names start at 1 and aren't reused.
+struct Names<T>(Vec<Option<T>>);
+
+impl<T: Copy> Names<T> {
+    fn insert(&mut self, object: T) -> GLuint {
+        self.0.push(Some(object));
+        self.0.len() as GLuint
+    }
+
+    fn get(&self, name: GLuint) -> Option<T> {
+        let index = name.checked_sub(1)?;
+        self.0.get(index as usize).copied().flatten()
+    }
+
+    fn remove(&mut self, name: GLuint) -> Option<T> {
+        let index = name.checked_sub(1)?;
+        self.0.get_mut(index as usize)?.take()
+    }
+}
+
+impl<T> Default for Names<T> {
+    fn default() -> Self {
+        Names(Vec::new())
+    }
+}
+
+#[derive(Default)]
+struct State {
+    buffers: Names<glow::Buffer>,
+    framebuffers: Names<glow::Framebuffer>,
+    programs: Names<glow::Program>,
+    renderbuffers: Names<glow::Renderbuffer>,
+    samplers: Names<glow::Sampler>,
+    shaders: Names<glow::Shader>,
+    textures: Names<glow::Texture>,
+    vertex_arrays: Names<glow::VertexArray>,
+    /// Uniform locations handed out; a GL location is an index.
+    locations: Vec<glow::UniformLocation>,
+    /// Locations by program and uniform name, -1 if inactive. A program's are dropped
+    /// when it is linked again.
+    location_names: HashMap<(GLuint, String), GLint>,
+    /// Names bound for the `*_BINDING` queries, which WebGL answers with objects.
+    bindings: HashMap<GLenum, GLuint>,
+    /// `GL_PACK_ALIGNMENT` and `GL_UNPACK_ALIGNMENT`, to size pixel transfers.
+    alignments: HashMap<GLenum, GLint>,
+    /// Strings returned by `glGetString` and `glGetStringi`, kept for their pointers.
+    strings: HashMap<(GLenum, GLuint), CString>,
+}
+
+impl State {
+    fn binding(&self, pname: GLenum) -> GLuint {
+        self.bindings.get(&pname).copied().unwrap_or(0)
+    }
+
+    fn alignment(&self, pname: GLenum) -> GLint {
+        self.alignments.get(&pname).copied().unwrap_or(4)
+    }
+
+    fn string(&mut self, key: (GLenum, GLuint), value: String) -> *const GLubyte {
+        let value = CString::new(value).unwrap_or_default();
+        self.strings.entry(key).or_insert(value).as_ptr().cast()
+    }
+}
+
+thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+fn with<R>(f: impl FnOnce(&glow::Context, &mut State) -> R) -> R {
+    let gl = super::context();
+    STATE.with(|state| f(&gl, &mut state.borrow_mut()))
+}
+
+unsafe fn slice<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    }
+}
+
+unsafe fn slice_mut<'a, T>(data: *mut T, len: usize) -> &'a mut [T] {
+    if len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(data, len)
+    }
+}
+
+unsafe fn c_str(name: *const GLchar) -> String {
+    CStr::from_ptr(name).to_string_lossy().into_owned()
+}
+
+/// Copies `text` into the C string `dest` of `buf_size` bytes, truncating it, and its
+/// length without the nul into `length` if that isn't null.
+unsafe fn write_string(text: &str, buf_size: GLsizei, length: *mut GLsizei, dest: *mut GLchar) {
+    let len = text.len().min((buf_size.max(1) - 1) as usize);
+    if buf_size > 0 {
+        let dest = slice_mut(dest.cast::<u8>(), len + 1);
+        dest[..len].copy_from_slice(&text.as_bytes()[..len]);
+        dest[len] = 0;
+    }
+    if !length.is_null() {
+        *length = len as GLsizei;
+    }
+}
+
+/// Writes `n` names from `create` to `names`, 0 where it failed.
+unsafe fn create_names(
+    n: GLsizei,
+    names: *mut GLuint,
+    create: impl Fn(&glow::Context, &mut State) -> Option<GLuint>,
+) {
+    let names = slice_mut(names, n as usize);
+    with(|gl, state| {
+        for name in names {
+            *name = create(gl, state).unwrap_or(0);
+        }
+    });
+}
+
+unsafe fn delete_names(
+    n: GLsizei,
+    names: *const GLuint,
+    delete: impl Fn(&glow::Context, &mut State, GLuint),
+) {
+    let names = slice(names, n as usize);
+    with(|gl, state| {
+        for &name in names {
+            delete(gl, state, name);
+        }
+    });
+}
+
+/// Calls `set` with the uniform `location`, unless it is -1.
+fn uniform(location: GLint, set: impl FnOnce(&glow::Context, &glow::UniformLocation)) {
+    with(|gl, state| {
+        let index = usize::try_from(location).ok();
+        if let Some(location) = index.and_then(|index| state.locations.get(index)) {
+            set(gl, location);
+        }
+    });
+}
+
+/// Values returned by `glGet*v` for `pname`.
+fn parameter_count(pname: GLenum) -> usize {
+    match pname {
+        VIEWPORT | SCISSOR_BOX | COLOR_CLEAR_VALUE | BLEND_COLOR | COLOR_WRITEMASK => 4,
+        MAX_VIEWPORT_DIMS | DEPTH_RANGE | ALIASED_LINE_WIDTH_RANGE => 2,
+        _ => 1,
+    }
+}
+
+/// Bytes GL reads or writes for a `width`×`height`×`depth` image of `format` and `ty`
+/// with rows aligned to `alignment`.
+fn image_size(
+    width: GLsizei,
+    height: GLsizei,
+    depth: GLsizei,
+    format: GLenum,
+    ty: GLenum,
+    alignment: GLint,
+) -> usize {
+    let components = match format {
+        RED | RED_INTEGER | DEPTH_COMPONENT | STENCIL_INDEX => 1,
+        RG | RG_INTEGER | DEPTH_STENCIL => 2,
+        RGB | RGB_INTEGER => 3,
+        _ => 4,
+    };
+    let pixel = match ty {
+        UNSIGNED_BYTE | BYTE => components,
+        UNSIGNED_SHORT | SHORT | HALF_FLOAT => 2 * components,
+        UNSIGNED_SHORT_5_6_5 | UNSIGNED_SHORT_4_4_4_4 | UNSIGNED_SHORT_5_5_5_1 => 2,
+        UNSIGNED_INT_2_10_10_10_REV
+        | UNSIGNED_INT_10F_11F_11F_REV
+        | UNSIGNED_INT_5_9_9_9_REV
+        | UNSIGNED_INT_24_8 => 4,
+        FLOAT_32_UNSIGNED_INT_24_8_REV => 8,
+        _ => 4 * components,
+    };
+    let (width, rows) = (
+        width.max(0) as usize,
+        (height.max(0) * depth.max(0)) as usize,
+    );
+    if width == 0 || rows == 0 {
+        return 0;
+    }
+    let row = width * pixel;
+    let alignment = alignment.max(1) as usize;
+    let stride = row.div_ceil(alignment) * alignment;
+    stride * (rows - 1) + row
+}
+
+// Buffers and vertex arrays.
+
+pub unsafe fn GenBuffers(n: GLsizei, buffers: *mut GLuint) {
+    create_names(n, buffers, |gl, state| {
+        Some(state.buffers.insert(gl.create_buffer().ok()?))
+    });
+}
+
+pub unsafe fn DeleteBuffers(n: GLsizei, buffers: *const GLuint) {
+    delete_names(n, buffers, |gl, state, name| {
+        if let Some(buffer) = state.buffers.remove(name) {
+            gl.delete_buffer(buffer);
+        }
+    });
+}
+
+pub unsafe fn BindBuffer(target: GLenum, buffer: GLuint) {
+    with(|gl, state| {
+        match target {
+            PIXEL_PACK_BUFFER => state.bindings.insert(PIXEL_PACK_BUFFER_BINDING, buffer),
+            PIXEL_UNPACK_BUFFER => state.bindings.insert(PIXEL_UNPACK_BUFFER_BINDING, buffer),
+            _ => None,
+        };
+        gl.bind_buffer(target, state.buffers.get(buffer));
+    });
+}
+
+pub unsafe fn BindBufferBase(target: GLenum, index: GLuint, buffer: GLuint) {
+    with(|gl, state| gl.bind_buffer_base(target, index, state.buffers.get(buffer)));
+}
+
+pub unsafe fn BufferData(target: GLenum, size: GLsizeiptr, data: *const c_void, usage: GLenum) {
+    with(|gl, _| {
+        if data.is_null() {
+            gl.buffer_data_size(target, size as i32, usage);
+        } else {
+            gl.buffer_data_u8_slice(target, slice(data.cast(), size as usize), usage);
+        }
+    });
+}
+
+pub unsafe fn BufferSubData(
+    target: GLenum,
+    offset: GLintptr,
+    size: GLsizeiptr,
+    data: *const c_void,
+) {
+    let data = slice(data.cast(), size as usize);
+    with(|gl, _| gl.buffer_sub_data_u8_slice(target, offset as i32, data));
+}
+
+pub unsafe fn GetBufferSubData(
+    target: GLenum,
+    offset: GLintptr,
+    size: GLsizeiptr,
+    data: *mut c_void,
+) {
+    let data = slice_mut(data.cast(), size as usize);
+    with(|gl, _| gl.get_buffer_sub_data(target, offset as i32, data));
+}
+
+/// Zeroes the buffer bound to `target`, the only clear the wrappers ask for.
+pub unsafe fn ClearBufferData(
+    target: GLenum,
+    _internalformat: GLenum,
+    _format: GLenum,
+    _type: GLenum,
+    _data: *const c_void,
+) {
+    with(|gl, _| {
+        let size = gl.get_buffer_parameter_i32(target, BUFFER_SIZE);
+        gl.buffer_sub_data_u8_slice(target, 0, &vec![0; size.max(0) as usize]);
+    });
+}
+
+pub unsafe fn CopyBufferSubData(
+    readTarget: GLenum,
+    writeTarget: GLenum,
+    readOffset: GLintptr,
+    writeOffset: GLintptr,
+    size: GLsizeiptr,
+) {
+    with(|gl, _| {
+        gl.copy_buffer_sub_data(
+            readTarget,
+            writeTarget,
+            readOffset as i32,
+            writeOffset as i32,
+            size as i32,
+        )
+    });
+}
+
+/// WebGL can't map buffers.
+pub unsafe fn MapBufferRange(
+    _target: GLenum,
+    _offset: GLintptr,
+    _length: GLsizeiptr,
+    _access: GLbitfield,
+) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+pub unsafe fn UnmapBuffer(_target: GLenum) -> GLboolean {
+    FALSE
+}
+
+pub unsafe fn GenVertexArrays(n: GLsizei, arrays: *mut GLuint) {
+    create_names(n, arrays, |gl, state| {
+        Some(state.vertex_arrays.insert(gl.create_vertex_array().ok()?))
+    });
+}
+
+pub unsafe fn DeleteVertexArrays(n: GLsizei, arrays: *const GLuint) {
+    delete_names(n, arrays, |gl, state, name| {
+        if let Some(vertex_array) = state.vertex_arrays.remove(name) {
+            gl.delete_vertex_array(vertex_array);
+        }
+    });
+}
+
+pub unsafe fn BindVertexArray(array: GLuint) {
+    with(|gl, state| {
+        state.bindings.insert(VERTEX_ARRAY_BINDING, array);
+        gl.bind_vertex_array(state.vertex_arrays.get(array));
+    });
+}
+
+pub unsafe fn EnableVertexAttribArray(index: GLuint) {
+    with(|gl, _| gl.enable_vertex_attrib_array(index));
+}
+
+pub unsafe fn DisableVertexAttribArray(index: GLuint) {
+    with(|gl, _| gl.disable_vertex_attrib_array(index));
+}
+
+pub unsafe fn VertexAttribPointer(
+    index: GLuint,
+    size: GLint,
+    type_: GLenum,
+    normalized: GLboolean,
+    stride: GLsizei,
+    pointer: *const c_void,
+) {
+    let offset = pointer as usize as i32;
+    with(|gl, _| {
+        gl.vertex_attrib_pointer_f32(index, size, type_, normalized == TRUE, stride, offset)
+    });
+}
+
+pub unsafe fn VertexAttribIPointer(
+    index: GLuint,
+    size: GLint,
+    type_: GLenum,
+    stride: GLsizei,
+    pointer: *const c_void,
+) {
+    let offset = pointer as usize as i32;
+    with(|gl, _| gl.vertex_attrib_pointer_i32(index, size, type_, stride, offset));
+}
+
+pub unsafe fn VertexAttribDivisor(index: GLuint, divisor: GLuint) {
+    with(|gl, _| gl.vertex_attrib_divisor(index, divisor));
+}
+
+// Shaders and programs.
+
+pub unsafe fn CreateShader(type_: GLenum) -> GLuint {
+    with(|gl, state| {
+        gl.create_shader(type_)
+            .map_or(0, |shader| state.shaders.insert(shader))
+    })
+}
+
+pub unsafe fn DeleteShader(shader: GLuint) {
+    with(|gl, state| {
+        if let Some(shader) = state.shaders.remove(shader) {
+            gl.delete_shader(shader);
+        }
+    });
+}
+
+pub unsafe fn ShaderSource(
+    shader: GLuint,
+    count: GLsizei,
+    string: *const *const GLchar,
+    length: *const GLint,
+) {
+    let strings = slice(string, count as usize);
+    let lengths = (!length.is_null()).then(|| slice(length, count as usize));
+    let mut source = String::new();
+    for (i, &string) in strings.iter().enumerate() {
+        match lengths
+            .map(|lengths| lengths[i])
+            .filter(|&length| length >= 0)
+        {
+            Some(length) => source.push_str(&String::from_utf8_lossy(slice(
+                string.cast::<u8>(),
+                length as usize,
+            ))),
+            None => source.push_str(&c_str(string)),
+        }
+    }
+    with(|gl, state| {
+        if let Some(shader) = state.shaders.get(shader) {
+            gl.shader_source(shader, &source);
+        }
+    });
+}
+
+pub unsafe fn CompileShader(shader: GLuint) {
+    with(|gl, state| {
+        if let Some(shader) = state.shaders.get(shader) {
+            gl.compile_shader(shader);
+        }
+    });
+}
+
+pub unsafe fn GetShaderiv(shader: GLuint, pname: GLenum, params: *mut GLint) {
+    with(|gl, state| {
+        let Some(shader) = state.shaders.get(shader) else {
+            return;
+        };
+        match pname {
+            COMPILE_STATUS => *params = gl.get_shader_compile_status(shader) as GLint,
+            INFO_LOG_LENGTH => {
+                let log = gl.get_shader_info_log(shader);
+                *params = if log.is_empty() {
+                    0
+                } else {
+                    log.len() as GLint + 1
+                };
+            }
+            _ => (),
+        }
+    });
+}
+
+pub unsafe fn GetShaderInfoLog(
+    shader: GLuint,
+    bufSize: GLsizei,
+    length: *mut GLsizei,
+    infoLog: *mut GLchar,
+) {
+    let log = with(|gl, state| {
+        state
+            .shaders
+            .get(shader)
+            .map(|shader| gl.get_shader_info_log(shader))
+    });
+    write_string(&log.unwrap_or_default(), bufSize, length, infoLog);
+}
+
+pub unsafe fn CreateProgram() -> GLuint {
+    with(|gl, state| {
+        gl.create_program()
+            .map_or(0, |program| state.programs.insert(program))
+    })
+}
+
+pub unsafe fn DeleteProgram(program: GLuint) {
+    with(|gl, state| {
+        state
+            .location_names
+            .retain(|&(owner, _), _| owner != program);
+        if let Some(program) = state.programs.remove(program) {
+            gl.delete_program(program);
+        }
+    });
+}
+
+pub unsafe fn AttachShader(program: GLuint, shader: GLuint) {
+    with(|gl, state| {
+        if let (Some(program), Some(shader)) =
+            (state.programs.get(program), state.shaders.get(shader))
+        {
+            gl.attach_shader(program, shader);
+        }
+    });
+}
+
+pub unsafe fn DetachShader(program: GLuint, shader: GLuint) {
+    with(|gl, state| {
+        if let (Some(program), Some(shader)) =
+            (state.programs.get(program), state.shaders.get(shader))
+        {
+            gl.detach_shader(program, shader);
+        }
+    });
+}
+
+pub unsafe fn LinkProgram(program: GLuint) {
+    with(|gl, state| {
+        state
+            .location_names
+            .retain(|&(owner, _), _| owner != program);
+        if let Some(program) = state.programs.get(program) {
+            gl.link_program(program);
+        }
+    });
+}
+
+/// WebGL validates every draw itself; `GL_VALIDATE_STATUS` always reports success.
+pub unsafe fn ValidateProgram(_program: GLuint) {}
+
+pub unsafe fn GetProgramiv(program: GLuint, pname: GLenum, params: *mut GLint) {
+    with(|gl, state| {
+        let Some(program) = state.programs.get(program) else {
+            return;
+        };
+        match pname {
+            LINK_STATUS => *params = gl.get_program_link_status(program) as GLint,
+            VALIDATE_STATUS => *params = TRUE as GLint,
+            INFO_LOG_LENGTH => {
+                let log = gl.get_program_info_log(program);
+                *params = if log.is_empty() {
+                    0
+                } else {
+                    log.len() as GLint + 1
+                };
+            }
+            ACTIVE_ATTRIBUTES => *params = gl.get_active_attributes(program) as GLint,
+            ACTIVE_UNIFORMS => *params = gl.get_active_uniforms(program) as GLint,
+            _ => (),
+        }
+    });
+}
+
+pub unsafe fn GetProgramInfoLog(
+    program: GLuint,
+    bufSize: GLsizei,
+    length: *mut GLsizei,
+    infoLog: *mut GLchar,
+) {
+    let log = with(|gl, state| {
+        state
+            .programs
+            .get(program)
+            .map(|program| gl.get_program_info_log(program))
+    });
+    write_string(&log.unwrap_or_default(), bufSize, length, infoLog);
+}
+
+pub unsafe fn UseProgram(program: GLuint) {
+    with(|gl, state| {
+        state.bindings.insert(CURRENT_PROGRAM, program);
+        gl.use_program(state.programs.get(program));
+    });
+}
+
+pub unsafe fn GetActiveAttrib(
+    program: GLuint,
+    index: GLuint,
+    bufSize: GLsizei,
+    length: *mut GLsizei,
+    size: *mut GLint,
+    type_: *mut GLenum,
+    name: *mut GLchar,
+) {
+    let attribute = with(|gl, state| {
+        let program = state.programs.get(program)?;
+        gl.get_active_attribute(program, index)
+    });
+    if let Some(attribute) = attribute {
+        *size = attribute.size;
+        *type_ = attribute.atype;
+        write_string(&attribute.name, bufSize, length, name);
+    }
+}
+
+pub unsafe fn GetActiveUniform(
+    program: GLuint,
+    index: GLuint,
+    bufSize: GLsizei,
+    length: *mut GLsizei,
+    size: *mut GLint,
+    type_: *mut GLenum,
+    name: *mut GLchar,
+) {
+    let active = with(|gl, state| {
+        let program = state.programs.get(program)?;
+        gl.get_active_uniform(program, index)
+    });
+    if let Some(active) = active {
+        *size = active.size;
+        *type_ = active.utype;
+        write_string(&active.name, bufSize, length, name);
+    }
+}
+
+pub unsafe fn GetAttribLocation(program: GLuint, name: *const GLchar) -> GLint {
+    let name = c_str(name);
+    with(|gl, state| {
+        let program = state.programs.get(program)?;
+        gl.get_attrib_location(program, &name)
+    })
+    .map_or(-1, |location| location as GLint)
+}
+
+pub unsafe fn GetUniformLocation(program: GLuint, name: *const GLchar) -> GLint {
+    let key = (program, c_str(name));
+    with(|gl, state| {
+        if let Some(&location) = state.location_names.get(&key) {
+            return location;
+        }
+        let found = state
+            .programs
+            .get(program)
+            .and_then(|program| gl.get_uniform_location(program, &key.1));
+        let location = match found {
+            Some(found) => {
+                state.locations.push(found);
+                state.locations.len() as GLint - 1
+            }
+            None => -1,
+        };
+        state.location_names.insert(key, location);
+        location
+    })
+}
+
+pub unsafe fn GetUniformBlockIndex(program: GLuint, uniformBlockName: *const GLchar) -> GLuint {
+    let name = c_str(uniformBlockName);
+    with(|gl, state| {
+        let program = state.programs.get(program)?;
+        gl.get_uniform_block_index(program, &name)
+    })
+    .unwrap_or(INVALID_INDEX)
+}
+
+pub unsafe fn UniformBlockBinding(
+    program: GLuint,
+    uniformBlockIndex: GLuint,
+    uniformBlockBinding: GLuint,
+) {
+    with(|gl, state| {
+        if let Some(program) = state.programs.get(program) {
+            gl.uniform_block_binding(program, uniformBlockIndex, uniformBlockBinding);
+        }
+    });
+}
+
+pub unsafe fn GetActiveUniformBlockiv(
+    program: GLuint,
+    uniformBlockIndex: GLuint,
+    pname: GLenum,
+    params: *mut GLint,
+) {
+    with(|gl, state| {
+        if let Some(program) = state.programs.get(program) {
+            *params = gl.get_active_uniform_block_parameter_i32(program, uniformBlockIndex, pname);
+        }
+    });
+}
+
+pub unsafe fn GetActiveUniformBlockName(
+    program: GLuint,
+    uniformBlockIndex: GLuint,
+    bufSize: GLsizei,
+    length: *mut GLsizei,
+    uniformBlockName: *mut GLchar,
+) {
+    let name = with(|gl, state| {
+        state
+            .programs
+            .get(program)
+            .map(|program| gl.get_active_uniform_block_name(program, uniformBlockIndex))
+    });
+    write_string(&name.unwrap_or_default(), bufSize, length, uniformBlockName);
+}
+
+pub unsafe fn TransformFeedbackVaryings(
+    program: GLuint,
+    count: GLsizei,
+    varyings: *const *const GLchar,
+    bufferMode: GLenum,
+) {
+    let names: Vec<String> = slice(varyings, count as usize)
+        .iter()
+        .map(|&name| c_str(name))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    with(|gl, state| {
+        if let Some(program) = state.programs.get(program) {
+            gl.transform_feedback_varyings(program, &names, bufferMode);
+        }
+    });
+}
+
+pub unsafe fn Uniform1i(location: GLint, v0: GLint) {
+    uniform(location, |gl, location| {
+        gl.uniform_1_i32(Some(location), v0)
+    });
+}
+
+pub unsafe fn Uniform1ui(location: GLint, v0: GLuint) {
+    uniform(location, |gl, location| {
+        gl.uniform_1_u32(Some(location), v0)
+    });
+}
+
+pub unsafe fn Uniform1f(location: GLint, v0: GLfloat) {
+    uniform(location, |gl, location| {
+        gl.uniform_1_f32(Some(location), v0)
+    });
+}
+
+pub unsafe fn Uniform2f(location: GLint, v0: GLfloat, v1: GLfloat) {
+    uniform(location, |gl, location| {
+        gl.uniform_2_f32(Some(location), v0, v1)
+    });
+}
+
+pub unsafe fn Uniform3f(location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat) {
+    uniform(location, |gl, location| {
+        gl.uniform_3_f32(Some(location), v0, v1, v2)
+    });
+}
+
+pub unsafe fn Uniform4f(location: GLint, v0: GLfloat, v1: GLfloat, v2: GLfloat, v3: GLfloat) {
+    uniform(location, |gl, location| {
+        gl.uniform_4_f32(Some(location), v0, v1, v2, v3)
+    });
+}
+
+pub unsafe fn Uniform1fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    let value = slice(value, count as usize);
+    uniform(location, |gl, location| {
+        gl.uniform_1_f32_slice(Some(location), value)
+    });
+}
+
+pub unsafe fn Uniform3fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    let value = slice(value, 3 * count as usize);
+    uniform(location, |gl, location| {
+        gl.uniform_3_f32_slice(Some(location), value)
+    });
+}
+
+pub unsafe fn Uniform4fv(location: GLint, count: GLsizei, value: *const GLfloat) {
+    let value = slice(value, 4 * count as usize);
+    uniform(location, |gl, location| {
+        gl.uniform_4_f32_slice(Some(location), value)
+    });
+}
+
+pub unsafe fn UniformMatrix4fv(
+    location: GLint,
+    count: GLsizei,
+    transpose: GLboolean,
+    value: *const GLfloat,
+) {
+    let value = slice(value, 16 * count as usize);
+    uniform(location, |gl, location| {
+        gl.uniform_matrix_4_f32_slice(Some(location), transpose == TRUE, value)
+    });
+}
+
+// Textures and samplers.
+
+pub unsafe fn GenTextures(n: GLsizei, textures: *mut GLuint) {
+    create_names(n, textures, |gl, state| {
+        Some(state.textures.insert(gl.create_texture().ok()?))
+    });
+}
+
+pub unsafe fn DeleteTextures(n: GLsizei, textures: *const GLuint) {
+    delete_names(n, textures, |gl, state, name| {
+        if let Some(texture) = state.textures.remove(name) {
+            gl.delete_texture(texture);
+        }
+    });
+}
+
+pub unsafe fn ActiveTexture(texture: GLenum) {
+    with(|gl, _| gl.active_texture(texture));
+}
+
+pub unsafe fn BindTexture(target: GLenum, texture: GLuint) {
+    with(|gl, state| gl.bind_texture(target, state.textures.get(texture)));
+}
+
+pub unsafe fn TexParameteri(target: GLenum, pname: GLenum, param: GLint) {
+    with(|gl, _| gl.tex_parameter_i32(target, pname, param));
+}
+
+pub unsafe fn TexParameterf(target: GLenum, pname: GLenum, param: GLfloat) {
+    with(|gl, _| gl.tex_parameter_f32(target, pname, param));
+}
+
+pub unsafe fn GenerateMipmap(target: GLenum) {
+    with(|gl, _| gl.generate_mipmap(target));
+}
+
+pub unsafe fn PixelStorei(pname: GLenum, param: GLint) {
+    with(|gl, state| {
+        if matches!(pname, PACK_ALIGNMENT | UNPACK_ALIGNMENT) {
+            state.alignments.insert(pname, param);
+        }
+        gl.pixel_store_i32(pname, param);
+    });
+}
+
+pub unsafe fn TexImage2D(
+    target: GLenum,
+    level: GLint,
+    internalformat: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    border: GLint,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *const c_void,
+) {
+    with(|gl, state| {
+        let size = image_size(
+            width,
+            height,
+            1,
+            format,
+            type_,
+            state.alignment(UNPACK_ALIGNMENT),
+        );
+        let pixels = (!pixels.is_null()).then(|| slice(pixels.cast(), size));
+        gl.tex_image_2d(
+            target,
+            level,
+            internalformat,
+            width,
+            height,
+            border,
+            format,
+            type_,
+            pixels,
+        );
+    });
+}
+
+pub unsafe fn TexImage3D(
+    target: GLenum,
+    level: GLint,
+    internalformat: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    depth: GLsizei,
+    border: GLint,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *const c_void,
+) {
+    with(|gl, state| {
+        let alignment = state.alignment(UNPACK_ALIGNMENT);
+        let size = image_size(width, height, depth, format, type_, alignment);
+        let pixels = (!pixels.is_null()).then(|| slice(pixels.cast(), size));
+        gl.tex_image_3d(
+            target,
+            level,
+            internalformat,
+            width,
+            height,
+            depth,
+            border,
+            format,
+            type_,
+            pixels,
+        );
+    });
+}
+
+/// With a `GL_PIXEL_UNPACK_BUFFER` bound, `pixels` is an offset into it.
+pub unsafe fn TexSubImage2D(
+    target: GLenum,
+    level: GLint,
+    xoffset: GLint,
+    yoffset: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *const c_void,
+) {
+    with(|gl, state| {
+        let data = if state.binding(PIXEL_UNPACK_BUFFER_BINDING) != 0 {
+            PixelUnpackData::BufferOffset(pixels as usize as u32)
+        } else {
+            let alignment = state.alignment(UNPACK_ALIGNMENT);
+            let size = image_size(width, height, 1, format, type_, alignment);
+            PixelUnpackData::Slice(slice(pixels.cast(), size))
+        };
+        gl.tex_sub_image_2d(
+            target, level, xoffset, yoffset, width, height, format, type_, data,
+        );
+    });
+}
+
+pub unsafe fn GenSamplers(count: GLsizei, samplers: *mut GLuint) {
+    create_names(count, samplers, |gl, state| {
+        Some(state.samplers.insert(gl.create_sampler().ok()?))
+    });
+}
+
+pub unsafe fn DeleteSamplers(count: GLsizei, samplers: *const GLuint) {
+    delete_names(count, samplers, |gl, state, name| {
+        if let Some(sampler) = state.samplers.remove(name) {
+            gl.delete_sampler(sampler);
+        }
+    });
+}
+
+pub unsafe fn BindSampler(unit: GLuint, sampler: GLuint) {
+    with(|gl, state| gl.bind_sampler(unit, state.samplers.get(sampler)));
+}
+
+pub unsafe fn SamplerParameteri(sampler: GLuint, pname: GLenum, param: GLint) {
+    with(|gl, state| {
+        if let Some(sampler) = state.samplers.get(sampler) {
+            gl.sampler_parameter_i32(sampler, pname, param);
+        }
+    });
+}
+
+pub unsafe fn SamplerParameterf(sampler: GLuint, pname: GLenum, param: GLfloat) {
+    with(|gl, state| {
+        if let Some(sampler) = state.samplers.get(sampler) {
+            gl.sampler_parameter_f32(sampler, pname, param);
+        }
+    });
+}
+
+// Framebuffers and renderbuffers.
+
+pub unsafe fn GenFramebuffers(n: GLsizei, framebuffers: *mut GLuint) {
+    create_names(n, framebuffers, |gl, state| {
+        Some(state.framebuffers.insert(gl.create_framebuffer().ok()?))
+    });
+}
+
+pub unsafe fn DeleteFramebuffers(n: GLsizei, framebuffers: *const GLuint) {
+    delete_names(n, framebuffers, |gl, state, name| {
+        if let Some(framebuffer) = state.framebuffers.remove(name) {
+            gl.delete_framebuffer(framebuffer);
+            // Deleting a bound framebuffer binds the default one.
+            for binding in [DRAW_FRAMEBUFFER_BINDING, READ_FRAMEBUFFER_BINDING] {
+                if state.binding(binding) == name {
+                    state.bindings.insert(binding, 0);
+                }
+            }
+        }
+    });
+}
+
+pub unsafe fn BindFramebuffer(target: GLenum, framebuffer: GLuint) {
+    with(|gl, state| {
+        if matches!(target, FRAMEBUFFER | DRAW_FRAMEBUFFER) {
+            state.bindings.insert(DRAW_FRAMEBUFFER_BINDING, framebuffer);
+        }
+        if matches!(target, FRAMEBUFFER | READ_FRAMEBUFFER) {
+            state.bindings.insert(READ_FRAMEBUFFER_BINDING, framebuffer);
+        }
+        gl.bind_framebuffer(target, state.framebuffers.get(framebuffer));
+    });
+}
+
+pub unsafe fn FramebufferTexture2D(
+    target: GLenum,
+    attachment: GLenum,
+    textarget: GLenum,
+    texture: GLuint,
+    level: GLint,
+) {
+    with(|gl, state| {
+        let texture = state.textures.get(texture);
+        gl.framebuffer_texture_2d(target, attachment, textarget, texture, level);
+    });
+}
+
+pub unsafe fn FramebufferTextureLayer(
+    target: GLenum,
+    attachment: GLenum,
+    texture: GLuint,
+    level: GLint,
+    layer: GLint,
+) {
+    with(|gl, state| {
+        let texture = state.textures.get(texture);
+        gl.framebuffer_texture_layer(target, attachment, texture, level, layer);
+    });
+}
+
+pub unsafe fn FramebufferRenderbuffer(
+    target: GLenum,
+    attachment: GLenum,
+    renderbuffertarget: GLenum,
+    renderbuffer: GLuint,
+) {
+    with(|gl, state| {
+        let renderbuffer = state.renderbuffers.get(renderbuffer);
+        gl.framebuffer_renderbuffer(target, attachment, renderbuffertarget, renderbuffer);
+    });
+}
+
+pub unsafe fn CheckFramebufferStatus(target: GLenum) -> GLenum {
+    with(|gl, _| gl.check_framebuffer_status(target))
+}
+
+pub unsafe fn DrawBuffers(n: GLsizei, bufs: *const GLenum) {
+    let buffers = slice(bufs, n as usize);
+    with(|gl, _| gl.draw_buffers(buffers));
+}
+
+pub unsafe fn ReadBuffer(src: GLenum) {
+    with(|gl, _| gl.read_buffer(src));
+}
+
+pub unsafe fn BlitFramebuffer(
+    srcX0: GLint,
+    srcY0: GLint,
+    srcX1: GLint,
+    srcY1: GLint,
+    dstX0: GLint,
+    dstY0: GLint,
+    dstX1: GLint,
+    dstY1: GLint,
+    mask: GLbitfield,
+    filter: GLenum,
+) {
+    with(|gl, _| {
+        gl.blit_framebuffer(
+            srcX0, srcY0, srcX1, srcY1, dstX0, dstY0, dstX1, dstY1, mask, filter,
+        )
+    });
+}
+
+/// With a `GL_PIXEL_PACK_BUFFER` bound, `pixels` is an offset into it.
+pub unsafe fn ReadPixels(
+    x: GLint,
+    y: GLint,
+    width: GLsizei,
+    height: GLsizei,
+    format: GLenum,
+    type_: GLenum,
+    pixels: *mut c_void,
+) {
+    with(|gl, state| {
+        let data = if state.binding(PIXEL_PACK_BUFFER_BINDING) != 0 {
+            PixelPackData::BufferOffset(pixels as usize as u32)
+        } else {
+            let size = image_size(
+                width,
+                height,
+                1,
+                format,
+                type_,
+                state.alignment(PACK_ALIGNMENT),
+            );
+            PixelPackData::Slice(slice_mut(pixels.cast(), size))
+        };
+        gl.read_pixels(x, y, width, height, format, type_, data);
+    });
+}
+
+pub unsafe fn GenRenderbuffers(n: GLsizei, renderbuffers: *mut GLuint) {
+    create_names(n, renderbuffers, |gl, state| {
+        Some(state.renderbuffers.insert(gl.create_renderbuffer().ok()?))
+    });
+}
+
+pub unsafe fn DeleteRenderbuffers(n: GLsizei, renderbuffers: *const GLuint) {
+    delete_names(n, renderbuffers, |gl, state, name| {
+        if let Some(renderbuffer) = state.renderbuffers.remove(name) {
+            gl.delete_renderbuffer(renderbuffer);
+        }
+    });
+}
+
+pub unsafe fn BindRenderbuffer(target: GLenum, renderbuffer: GLuint) {
+    with(|gl, state| gl.bind_renderbuffer(target, state.renderbuffers.get(renderbuffer)));
+}
+
+pub unsafe fn RenderbufferStorage(
+    target: GLenum,
+    internalformat: GLenum,
+    width: GLsizei,
+    height: GLsizei,
+) {
+    with(|gl, _| gl.renderbuffer_storage(target, internalformat, width, height));
+}
+
+pub unsafe fn RenderbufferStorageMultisample(
+    target: GLenum,
+    samples: GLsizei,
+    internalformat: GLenum,
+    width: GLsizei,
+    height: GLsizei,
+) {
+    with(|gl, _| {
+        gl.renderbuffer_storage_multisample(target, samples, internalformat, width, height)
+    });
+}
+
+// Drawing and fixed-function state.
+
+pub unsafe fn DrawArrays(mode: GLenum, first: GLint, count: GLsizei) {
+    with(|gl, _| gl.draw_arrays(mode, first, count));
+}
+
+pub unsafe fn DrawArraysInstanced(
+    mode: GLenum,
+    first: GLint,
+    count: GLsizei,
+    instancecount: GLsizei,
+) {
+    with(|gl, _| gl.draw_arrays_instanced(mode, first, count, instancecount));
+}
+
+pub unsafe fn DrawElements(mode: GLenum, count: GLsizei, type_: GLenum, indices: *const c_void) {
+    let offset = indices as usize as i32;
+    with(|gl, _| gl.draw_elements(mode, count, type_, offset));
+}
+
+pub unsafe fn DrawElementsInstanced(
+    mode: GLenum,
+    count: GLsizei,
+    type_: GLenum,
+    indices: *const c_void,
+    instancecount: GLsizei,
+) {
+    let offset = indices as usize as i32;
+    with(|gl, _| gl.draw_elements_instanced(mode, count, type_, offset, instancecount));
+}
+
+pub unsafe fn Enable(cap: GLenum) {
+    with(|gl, _| gl.enable(cap));
+}
+
+pub unsafe fn Disable(cap: GLenum) {
+    with(|gl, _| gl.disable(cap));
+}
+
+pub unsafe fn BlendFunc(sfactor: GLenum, dfactor: GLenum) {
+    with(|gl, _| gl.blend_func(sfactor, dfactor));
+}
+
+pub unsafe fn BlendFuncSeparate(
+    sfactorRGB: GLenum,
+    dfactorRGB: GLenum,
+    sfactorAlpha: GLenum,
+    dfactorAlpha: GLenum,
+) {
+    with(|gl, _| gl.blend_func_separate(sfactorRGB, dfactorRGB, sfactorAlpha, dfactorAlpha));
+}
+
+pub unsafe fn DepthFunc(func: GLenum) {
+    with(|gl, _| gl.depth_func(func));
+}
+
+pub unsafe fn DepthMask(flag: GLboolean) {
+    with(|gl, _| gl.depth_mask(flag == TRUE));
+}
+
+pub unsafe fn ColorMask(red: GLboolean, green: GLboolean, blue: GLboolean, alpha: GLboolean) {
+    with(|gl, _| gl.color_mask(red == TRUE, green == TRUE, blue == TRUE, alpha == TRUE));
+}
+
+pub unsafe fn CullFace(mode: GLenum) {
+    with(|gl, _| gl.cull_face(mode));
+}
+
+pub unsafe fn FrontFace(mode: GLenum) {
+    with(|gl, _| gl.front_face(mode));
+}
+
+pub unsafe fn StencilFunc(func: GLenum, ref_: GLint, mask: GLuint) {
+    with(|gl, _| gl.stencil_func(func, ref_, mask));
+}
+
+pub unsafe fn StencilOp(fail: GLenum, zfail: GLenum, zpass: GLenum) {
+    with(|gl, _| gl.stencil_op(fail, zfail, zpass));
+}
+
+pub unsafe fn StencilMask(mask: GLuint) {
+    with(|gl, _| gl.stencil_mask(mask));
+}
+
+pub unsafe fn LineWidth(width: GLfloat) {
+    with(|gl, _| gl.line_width(width));
+}
+
+pub unsafe fn PolygonOffset(factor: GLfloat, units: GLfloat) {
+    with(|gl, _| gl.polygon_offset(factor, units));
+}
+
+pub unsafe fn Viewport(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    with(|gl, _| gl.viewport(x, y, width, height));
+}
+
+pub unsafe fn Scissor(x: GLint, y: GLint, width: GLsizei, height: GLsizei) {
+    with(|gl, _| gl.scissor(x, y, width, height));
+}
+
+pub unsafe fn ClearColor(red: GLfloat, green: GLfloat, blue: GLfloat, alpha: GLfloat) {
+    with(|gl, _| gl.clear_color(red, green, blue, alpha));
+}
+
+pub unsafe fn ClearDepthf(d: GLfloat) {
+    with(|gl, _| gl.clear_depth_f32(d));
+}
+
+pub unsafe fn Clear(mask: GLbitfield) {
+    with(|gl, _| gl.clear(mask));
+}
+
+/// Values a `glClearBuffer*v` call reads for `buffer`.
+fn clear_count(buffer: GLenum) -> usize {
+    if buffer == COLOR {
+        4
+    } else {
+        1
+    }
+}
+
+pub unsafe fn ClearBufferfv(buffer: GLenum, drawbuffer: GLint, value: *const GLfloat) {
+    let value = slice(value, clear_count(buffer));
+    with(|gl, _| gl.clear_buffer_f32_slice(buffer, drawbuffer as u32, value));
+}
+
+pub unsafe fn ClearBufferiv(buffer: GLenum, drawbuffer: GLint, value: *const GLint) {
+    let value = slice(value, clear_count(buffer));
+    with(|gl, _| gl.clear_buffer_i32_slice(buffer, drawbuffer as u32, value));
+}
+
+pub unsafe fn ClearBufferuiv(buffer: GLenum, drawbuffer: GLint, value: *const GLuint) {
+    let value = slice(value, clear_count(buffer));
+    with(|gl, _| gl.clear_buffer_u32_slice(buffer, drawbuffer as u32, value));
+}
+
+pub unsafe fn ClearBufferfi(buffer: GLenum, drawbuffer: GLint, depth: GLfloat, stencil: GLint) {
+    with(|gl, _| gl.clear_buffer_depth_stencil(buffer, drawbuffer as u32, depth, stencil));
+}
+
+pub unsafe fn Flush() {
+    with(|gl, _| gl.flush());
+}
+
+pub unsafe fn Finish() {
+    with(|gl, _| gl.finish());
+}
+
+// Queries.
+
+pub unsafe fn GetError() -> GLenum {
+    with(|gl, _| gl.get_error())
+}
+
+pub unsafe fn GetIntegerv(pname: GLenum, data: *mut GLint) {
+    with(|gl, state| {
+        *data = match pname {
+            MAJOR_VERSION => 3,
+            MINOR_VERSION => 0,
+            NUM_EXTENSIONS => gl.supported_extensions().len() as GLint,
+            CURRENT_PROGRAM
+            | VERTEX_ARRAY_BINDING
+            | DRAW_FRAMEBUFFER_BINDING
+            | READ_FRAMEBUFFER_BINDING
+            | PIXEL_PACK_BUFFER_BINDING
+            | PIXEL_UNPACK_BUFFER_BINDING => state.binding(pname) as GLint,
+            _ => {
+                gl.get_parameter_i32_slice(pname, slice_mut(data, parameter_count(pname)));
+                return;
+            }
+        };
+    });
+}
+
+pub unsafe fn GetFloatv(pname: GLenum, data: *mut GLfloat) {
+    let data = slice_mut(data, parameter_count(pname));
+    with(|gl, _| gl.get_parameter_f32_slice(pname, data));
+}
+
+/// `GL_VERSION` is prefixed with "OpenGL ES 3.0" for [`crate::context::ContextInfo`].
+pub unsafe fn GetString(name: GLenum) -> *const GLubyte {
+    with(|gl, state| {
+        let value = gl.get_parameter_string(name);
+        let value = if name == VERSION {
+            format!("OpenGL ES 3.0 {}", value)
+        } else {
+            value
+        };
+        state.string((name, 0), value)
+    })
+}
+
+/// Extensions are WebGL's, sorted and prefixed with `GL_`.
+pub unsafe fn GetStringi(name: GLenum, index: GLuint) -> *const GLubyte {
+    with(|gl, state| {
+        if name != EXTENSIONS {
+            return std::ptr::null();
+        }
+        let mut extensions: Vec<&String> = gl.supported_extensions().iter().collect();
+        extensions.sort();
+        match extensions.get(index as usize) {
+            Some(extension) => state.string((name, index), format!("GL_{}", extension)),
+            None => std::ptr::null(),
+        }
+    })
+}
@@ -0,0 +1,196 @@
+//! Golden-image tests: renders every demo scene headless and compares it with the
+//! reference PNGs in `tests/golden/`.
+//!
+//! Each scene is rendered by `hello-gl --headless` in its own process, at a fixed size
+//! and time step, without a settings file or `HELLO_GL_*` overrides other than
+//! `HELLO_GL_SOFTWARE`. A pixel differs when its perceptual color distance exceeds
+//! [`THRESHOLD`]; a scene fails when more than [`MAX_DIFFERING`] of its pixels differ,
+//! which tolerates drivers rasterizing edges and filtering slightly differently. On
+//! failure the rendered image and a diff (differing pixels in red over a faded
+//! reference) are left in the target directory.
+//!
+//! `HELLO_GL_BLESS=1 cargo test --test golden` writes the current renders as the new
+//! references. The tests need GL 3.3. Without a display server the headless context
+//! falls back to an EGL device, and `HELLO_GL_SOFTWARE=true` selects Mesa's llvmpipe on
+//! machines without a GPU. The references were rendered by llvmpipe, as on CI; bless
+//! new ones with the same renderer.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use anyhow::{anyhow, Result};
+use hello_gl::image::Image;
+
+const SCENES: [&str; 5] = ["triangle", "quad", "cube", "model", "particles"];
+
+const SIZE: u32 = 256;
+
+/// Frames rendered before the screenshot, so scenes that animate have moved.
+const FRAMES: u32 = 3;
+
+/// Largest color distance still considered equal, as a fraction of the largest
+/// possible distance (black to white).
+const THRESHOLD: f32 = 0.1;
+
+/// Fraction of pixels allowed to differ.
+const MAX_DIFFERING: f32 = 0.005;
+
+/// Set to rewrite the references instead of comparing against them.
+const BLESS_ENV: &str = "HELLO_GL_BLESS";
+
+fn references() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn output() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+/// Renders `scene` into `path`.
+fn render(scene: &str, path: &Path) -> Result<Image> {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_hello-gl"));
+    command
+        .args(["--headless", "--scene", scene])
+        .args(["--width", &SIZE.to_string(), "--height", &SIZE.to_string()])
+        .args(["--frames", &FRAMES.to_string()])
+        .arg("--screenshot")
+        .arg(path)
+        // Keeps a `hello-gl.toml` in the working directory out of it.
+        .current_dir(output());
+    for (key, _) in std::env::vars() {
+        if key.starts_with("HELLO_GL_") && key != "HELLO_GL_SOFTWARE" {
+            command.env_remove(key);
+        }
+    }
+    let result = command.output()?;
+    if !result.status.success() {
+        return Err(anyhow!(
+            "hello-gl failed ({}):\n{}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr)
+        ));
+    }
+    Image::load(path)
+}
+
+/// RGB blended over white, converted to YIQ.
+fn yiq(pixel: &[u8]) -> [f32; 3] {
+    let alpha = pixel[3] as f32 / 255.0;
+    let [r, g, b] = [0, 1, 2].map(|i| 255.0 + (pixel[i] as f32 - 255.0) * alpha);
+    [
+        0.298_895_3 * r + 0.586_622_5 * g + 0.114_482_23 * b,
+        0.595_977_99 * r - 0.274_176_48 * g - 0.321_801_5 * b,
+        0.211_470_19 * r - 0.522_617_61 * g + 0.311_147_42 * b,
+    ]
+}
+
+/// The YIQ distance of Kotsarenko and Ramos, "Measuring perceived color difference
+/// using YIQ NTSC transmission color space", squared; 35215 from black to white.
+fn distance(a: &[u8], b: &[u8]) -> f32 {
+    let ([ya, ia, qa], [yb, ib, qb]) = (yiq(a), yiq(b));
+    0.5053 * (ya - yb).powi(2) + 0.299 * (ia - ib).powi(2) + 0.1957 * (qa - qb).powi(2)
+}
+
+/// Compares `actual` with `expected`, returning the number of differing pixels and an
+/// image marking them.
+fn compare(actual: &Image, expected: &Image) -> (usize, Image) {
+    let limit = 35215.0 * THRESHOLD * THRESHOLD;
+    let mut differing = 0;
+    let mut pixels = Vec::with_capacity(expected.pixels.len());
+    for (a, e) in actual.pixels.chunks(4).zip(expected.pixels.chunks(4)) {
+        if distance(a, e) > limit {
+            differing += 1;
+            pixels.extend([255, 0, 0, 255]);
+        } else {
+            let gray = (yiq(e)[0] * 0.25 + 191.0) as u8;
+            pixels.extend([gray, gray, gray, 255]);
+        }
+    }
+    let diff = Image {
+        width: expected.width,
+        height: expected.height,
+        pixels,
+    };
+    (differing, diff)
+}
+
+fn check(scene: &str, bless: bool) -> Result<()> {
+    let actual_path = output().join(format!("{}.png", scene));
+    let actual = render(scene, &actual_path)?;
+    let reference = references().join(format!("{}.png", scene));
+    if bless {
+        return actual.save(&reference);
+    }
+    let expected = Image::load(&reference).map_err(|e| {
+        anyhow!(
+            "{:#}\n  (run with {}=1 to create the reference)",
+            e,
+            BLESS_ENV
+        )
+    })?;
+    if (actual.width, actual.height) != (expected.width, expected.height) {
+        return Err(anyhow!(
+            "rendered {}x{}, the reference is {}x{}",
+            actual.width,
+            actual.height,
+            expected.width,
+            expected.height
+        ));
+    }
+
+    let (differing, diff) = compare(&actual, &expected);
+    let allowed = (MAX_DIFFERING * (SIZE * SIZE) as f32) as usize;
+    if differing > allowed {
+        let diff_path = output().join(format!("{}-diff.png", scene));
+        diff.save(&diff_path)?;
+        return Err(anyhow!(
+            "{} pixels differ (at most {} allowed)\n  rendered: {}\n  diff:     {}",
+            differing,
+            allowed,
+            actual_path.display(),
+            diff_path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let bless = std::env::var_os(BLESS_ENV).is_some();
+    if let Err(e) = std::fs::create_dir_all(output()) {
+        eprintln!("Failed to create {}: {}", output().display(), e);
+        return ExitCode::FAILURE;
+    }
+    if bless {
+        if let Err(e) = std::fs::create_dir_all(references()) {
+            eprintln!("Failed to create {}: {}", references().display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    println!("\nrunning {} golden-image tests", SCENES.len());
+    let mut failures = Vec::new();
+    for scene in SCENES {
+        match check(scene, bless) {
+            Ok(()) if bless => println!("golden {} ... blessed", scene),
+            Ok(()) => println!("golden {} ... ok", scene),
+            Err(e) => {
+                println!("golden {} ... FAILED", scene);
+                failures.push((scene, e));
+            }
+        }
+    }
+    for (scene, e) in &failures {
+        println!("\n---- golden {} ----\n{:#}", scene, e);
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        SCENES.len() - failures.len(),
+        failures.len()
+    );
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}